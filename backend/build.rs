@@ -0,0 +1,12 @@
+fn main() {
+    // libxml2 validates generated SAF-T files against the schema
+    if std::env::var_os("CARGO_FEATURE_XSD_VALIDATION").is_none() {
+        return;
+    }
+    if let Err(err) = pkg_config::probe_library("libxml-2.0") {
+        panic!(
+            "libxml2 development files are required by the xsd-validation feature; \
+             install them or build with --no-default-features: {err}"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use sea_orm::entity::prelude::*;
+use sea_orm::prelude::StringLen;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(12))")]
+pub enum AccountingPeriodStatus {
+    /// Anyone with access to the company may book
+    #[sea_orm(string_value = "OPEN")]
+    Open,
+    /// Only company admins may book
+    #[sea_orm(string_value = "SOFT_CLOSED")]
+    SoftClosed,
+    /// Filed; nobody may book and the period cannot be reopened
+    #[sea_orm(string_value = "HARD_CLOSED")]
+    HardClosed,
+}
+
+/// Lock state of one calendar month of a company's books. Months without a
+/// row are open.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "accounting_periods")]
+#[graphql(concrete(name = "AccountingPeriod", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub year: i32,
+    pub month: i32,
+    pub status: AccountingPeriodStatus,
+    pub closed_by: Option<i32>,
+    pub closed_at: Option<DateTimeUtc>,
+    pub notes: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct SetAccountingPeriodStatusInput {
+    pub company_id: i32,
+    pub year: i32,
+    pub month: i32,
+    pub status: AccountingPeriodStatus,
+    pub notes: Option<String>,
+}
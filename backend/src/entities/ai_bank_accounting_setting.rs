@@ -23,6 +23,10 @@ pub struct Model {
     pub account_id: Option<i32>,
     pub counterpart_account_id: Option<i32>,
     pub vat_account_id: Option<i32>,
+    /// VAT rate of the amount split off to the VAT account
+    pub vat_rate_id: Option<i32>,
+    /// VAT operation the split-off VAT is reported under ("пок10", "про11", ...)
+    pub vat_operation: Option<String>,
 
     // Transaction direction
     pub direction: String, // "debit" or "credit"
@@ -93,6 +97,8 @@ pub struct CreateAiBankAccountingSettingInput {
     pub account_id: Option<i32>,
     pub counterpart_account_id: Option<i32>,
     pub vat_account_id: Option<i32>,
+    pub vat_rate_id: Option<i32>,
+    pub vat_operation: Option<String>,
     pub direction: Option<String>, // defaults to "debit"
     pub description_template: Option<String>,
     pub priority: Option<i32>, // defaults to 0
@@ -107,6 +113,8 @@ pub struct UpdateAiBankAccountingSettingInput {
     pub account_id: Option<i32>,
     pub counterpart_account_id: Option<i32>,
     pub vat_account_id: Option<i32>,
+    pub vat_rate_id: Option<i32>,
+    pub vat_operation: Option<String>,
     pub direction: Option<String>,
     pub description_template: Option<String>,
     pub priority: Option<i32>,
@@ -123,6 +131,8 @@ impl From<CreateAiBankAccountingSettingInput> for ActiveModel {
             account_id: Set(input.account_id),
             counterpart_account_id: Set(input.counterpart_account_id),
             vat_account_id: Set(input.vat_account_id),
+            vat_rate_id: Set(input.vat_rate_id),
+            vat_operation: Set(input.vat_operation),
             direction: Set(input.direction.unwrap_or_else(|| "debit".to_string())),
             description_template: Set(input.description_template),
             priority: Set(input.priority.unwrap_or(0)),
@@ -158,6 +168,17 @@ impl Model {
         }
     }
 
+    /// Whether the rule books transactions of this direction: "debit" rules
+    /// debit their account for outgoing payments, "credit" rules credit it
+    /// for incoming ones
+    pub fn applies_to(&self, is_credit: bool) -> bool {
+        match self.direction.to_lowercase().as_str() {
+            "debit" => !is_credit,
+            "credit" => is_credit,
+            _ => true,
+        }
+    }
+
     /// Get formatted description using template
     pub fn format_description(&self, counterpart: Option<&str>, description: &str) -> String {
         if let Some(ref template) = self.description_template {
@@ -0,0 +1,72 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use chrono::NaiveDate;
+use sea_orm::entity::prelude::*;
+use sea_orm::prelude::StringLen;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(10))")]
+pub enum AuditAction {
+    #[sea_orm(string_value = "CREATE")]
+    Create,
+    #[sea_orm(string_value = "UPDATE")]
+    Update,
+    #[sea_orm(string_value = "POST")]
+    Post,
+    #[sea_orm(string_value = "UNPOST")]
+    Unpost,
+    #[sea_orm(string_value = "DELETE")]
+    Delete,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "CREATE",
+            AuditAction::Update => "UPDATE",
+            AuditAction::Post => "POST",
+            AuditAction::Unpost => "UNPOST",
+            AuditAction::Delete => "DELETE",
+        }
+    }
+}
+
+/// One change to a company's books. Rows are append-only; the database
+/// rejects updates and deletes.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "audit_logs")]
+#[graphql(concrete(name = "AuditLog", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub company_id: Option<i32>,
+    pub user_id: Option<i32>,
+    /// Username at the time of the change
+    pub username: Option<String>,
+    pub action: AuditAction,
+    /// Table name of the changed record, e.g. `journal_entries`
+    pub entity_type: String,
+    pub entity_id: Option<i32>,
+    pub before_data: Option<Value>,
+    pub after_data: Option<Value>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize, Clone)]
+pub struct AuditLogFilter {
+    pub company_id: i32,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub action: Option<AuditAction>,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
@@ -25,6 +25,13 @@ pub struct Model {
     pub created_by: Option<i32>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
+    /// First and last booking date in the statement
+    pub statement_from: Option<Date>,
+    pub statement_to: Option<Date>,
+    /// Transactions left out because they were booked by an earlier import
+    pub skipped_duplicates: i32,
+    /// Transactions posted by a bank rule instead of the buffer account
+    pub auto_posted: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -14,6 +14,8 @@ pub struct Model {
     pub company_id: i32,
     pub name: String,
     pub iban: Option<String>,
+    /// BIC of the bank, used as debtor agent in payment files
+    pub bic: Option<String>,
     pub account_id: i32,
     pub buffer_account_id: i32,
     pub currency_code: String,
@@ -79,10 +81,34 @@ pub enum BankImportFormat {
     PayseraCamt053,
     #[graphql(name = "POSTBANK_XML")]
     PostbankXml,
+    /// UBB (ОББ) XML statement
     #[graphql(name = "OBB_XML")]
     ObbXml,
     #[graphql(name = "CCB_CSV")]
     CcbCsv,
+    #[graphql(name = "DSK_CSV")]
+    DskCsv,
+    #[graphql(name = "FIBANK_XML")]
+    FibankXml,
+    /// UBB (ОББ) Business Online CSV statement
+    #[graphql(name = "OBB_CSV")]
+    ObbCsv,
+    #[graphql(name = "PROCREDIT_MT940")]
+    ProcreditMt940,
+    #[graphql(name = "ALLIANZ_MT940")]
+    AllianzMt940,
+    /// Intraday SWIFT report of any bank
+    #[graphql(name = "MT942")]
+    Mt942,
+    /// ISO 20022 intraday account report of any bank
+    #[graphql(name = "CAMT052")]
+    Camt052,
+    /// ISO 20022 debit/credit notification of any bank
+    #[graphql(name = "CAMT054")]
+    Camt054,
+    /// Any CSV, read with the column mapping in the profile settings
+    #[graphql(name = "CUSTOM_CSV")]
+    CustomCsv,
 }
 
 impl BankImportFormat {
@@ -95,6 +121,15 @@ impl BankImportFormat {
             BankImportFormat::PostbankXml => "POSTBANK_XML",
             BankImportFormat::ObbXml => "OBB_XML",
             BankImportFormat::CcbCsv => "CCB_CSV",
+            BankImportFormat::DskCsv => "DSK_CSV",
+            BankImportFormat::FibankXml => "FIBANK_XML",
+            BankImportFormat::ObbCsv => "OBB_CSV",
+            BankImportFormat::ProcreditMt940 => "PROCREDIT_MT940",
+            BankImportFormat::AllianzMt940 => "ALLIANZ_MT940",
+            BankImportFormat::Mt942 => "MT942",
+            BankImportFormat::Camt052 => "CAMT052",
+            BankImportFormat::Camt054 => "CAMT054",
+            BankImportFormat::CustomCsv => "CUSTOM_CSV",
         }
     }
 
@@ -107,6 +142,15 @@ impl BankImportFormat {
             "POSTBANK_XML" => Some(Self::PostbankXml),
             "OBB_XML" => Some(Self::ObbXml),
             "CCB_CSV" => Some(Self::CcbCsv),
+            "DSK_CSV" => Some(Self::DskCsv),
+            "FIBANK_XML" => Some(Self::FibankXml),
+            "OBB_CSV" => Some(Self::ObbCsv),
+            "PROCREDIT_MT940" => Some(Self::ProcreditMt940),
+            "ALLIANZ_MT940" => Some(Self::AllianzMt940),
+            "MT942" => Some(Self::Mt942),
+            "CAMT052" => Some(Self::Camt052),
+            "CAMT054" => Some(Self::Camt054),
+            "CUSTOM_CSV" => Some(Self::CustomCsv),
             _ => None,
         }
     }
@@ -125,6 +169,7 @@ pub struct CreateBankProfileInput {
     pub company_id: i32,
     pub name: String,
     pub iban: Option<String>,
+    pub bic: Option<String>,
     pub account_id: i32,
     pub buffer_account_id: i32,
     pub currency_code: String,
@@ -137,6 +182,7 @@ pub struct CreateBankProfileInput {
 pub struct UpdateBankProfileInput {
     pub name: Option<String>,
     pub iban: Option<Option<String>>,
+    pub bic: Option<Option<String>>,
     pub account_id: Option<i32>,
     pub buffer_account_id: Option<i32>,
     pub currency_code: Option<String>,
@@ -151,6 +197,7 @@ impl From<CreateBankProfileInput> for ActiveModel {
             company_id: Set(input.company_id),
             name: Set(input.name.trim().to_string()),
             iban: Set(input.iban),
+            bic: Set(input.bic.map(|bic| bic.trim().to_uppercase())),
             account_id: Set(input.account_id),
             buffer_account_id: Set(input.buffer_account_id),
             currency_code: Set(input.currency_code.to_uppercase()),
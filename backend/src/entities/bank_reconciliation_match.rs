@@ -0,0 +1,88 @@
+use async_graphql::{InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Part of an imported bank transaction re-booked from the buffer account to
+/// a receivable or payable of a counterpart
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "bank_reconciliation_matches")]
+#[graphql(concrete(name = "BankReconciliationMatch", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    /// Journal entry booked by the bank import
+    pub bank_entry_id: i32,
+    /// Line of the bank entry that replaced the buffer account
+    pub entry_line_id: i32,
+    /// Invoice the payment settles; None for an advance
+    pub invoice_entry_id: Option<i32>,
+    pub counterpart_id: i32,
+    /// Receivable (411) or payable (401) account
+    pub account_id: i32,
+    pub amount: Decimal,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(
+        belongs_to = "super::journal_entry::Entity",
+        from = "Column::BankEntryId",
+        to = "super::journal_entry::Column::Id"
+    )]
+    BankEntry,
+    #[sea_orm(
+        belongs_to = "super::entry_line::Entity",
+        from = "Column::EntryLineId",
+        to = "super::entry_line::Column::Id"
+    )]
+    EntryLine,
+    #[sea_orm(
+        belongs_to = "super::journal_entry::Entity",
+        from = "Column::InvoiceEntryId",
+        to = "super::journal_entry::Column::Id"
+    )]
+    InvoiceEntry,
+    #[sea_orm(
+        belongs_to = "super::counterpart::Entity",
+        from = "Column::CounterpartId",
+        to = "super::counterpart::Column::Id"
+    )]
+    Counterpart,
+}
+
+impl Related<super::counterpart::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Counterpart.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// One part of a bank transaction to re-book
+#[derive(InputObject, Deserialize, Clone, Debug)]
+pub struct BankReconciliationAllocationInput {
+    /// Invoice to settle; its counterpart and account are used
+    pub invoice_entry_id: Option<i32>,
+    /// Required for an advance without an invoice
+    pub counterpart_id: Option<i32>,
+    /// Amount in the base currency
+    pub amount: Decimal,
+}
+
+#[derive(InputObject, Deserialize)]
+pub struct ConfirmBankReconciliationInput {
+    pub company_id: i32,
+    /// Journal entry booked by the bank import
+    pub bank_entry_id: i32,
+    pub allocations: Vec<BankReconciliationAllocationInput>,
+}
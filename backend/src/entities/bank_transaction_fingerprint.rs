@@ -0,0 +1,57 @@
+use async_graphql::SimpleObject;
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Bank transaction already booked from a statement of a bank profile
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "bank_transaction_fingerprints")]
+#[graphql(concrete(name = "BankTransactionFingerprint", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub bank_profile_id: i32,
+    pub bank_import_id: i32,
+    pub journal_entry_id: i32,
+    /// Hash of booking date, signed amount, reference, counterparty IBAN and
+    /// description
+    pub fingerprint: String,
+    pub booking_date: Date,
+    /// Positive for incoming, negative for outgoing payments
+    pub amount: Decimal,
+    pub reference: Option<String>,
+    pub counterparty_iban: Option<String>,
+    pub created_at: DateTimeUtc,
+    /// Bank rule that posted the transaction instead of the buffer account
+    pub ai_bank_accounting_setting_id: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::bank_profile::Entity",
+        from = "Column::BankProfileId",
+        to = "super::bank_profile::Column::Id"
+    )]
+    BankProfile,
+    #[sea_orm(
+        belongs_to = "super::bank_import::Entity",
+        from = "Column::BankImportId",
+        to = "super::bank_import::Column::Id"
+    )]
+    BankImport,
+    #[sea_orm(
+        belongs_to = "super::journal_entry::Entity",
+        from = "Column::JournalEntryId",
+        to = "super::journal_entry::Column::Id"
+    )]
+    JournalEntry,
+}
+
+impl Related<super::bank_import::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BankImport.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
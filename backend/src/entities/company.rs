@@ -59,6 +59,12 @@ impl Related<super::journal_entry::Entity> for Entity {
     }
 }
 
+impl Related<super::currency::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BaseCurrency.def()
+    }
+}
+
 // Input types for GraphQL mutations
 #[derive(InputObject, Deserialize, Serialize)]
 pub struct CreateCompanyInput {
@@ -110,7 +110,7 @@ impl From<CreateEntryLineInput> for ActiveModel {
             debit_amount: Set(input.debit_amount.unwrap_or(Decimal::ZERO)),
             credit_amount: Set(input.credit_amount.unwrap_or(Decimal::ZERO)),
             counterpart_id: Set(input.counterpart_id),
-            currency_code: Set(input.currency_code.or_else(|| Some("BGN".to_string()))),
+            currency_code: Set(input.currency_code),
             currency_amount: Set(input.currency_amount),
             exchange_rate: Set(input.exchange_rate.or_else(|| Some(Decimal::ONE))),
             vat_amount: Set(input.vat_amount.unwrap_or(Decimal::ZERO)),
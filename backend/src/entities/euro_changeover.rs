@@ -0,0 +1,62 @@
+use async_graphql::{InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Conversion of a company's books to the euro. Periods up to and including
+/// `cutoff_date` stay in `previous_currency_code`.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "euro_changeovers")]
+#[graphql(concrete(name = "EuroChangeover", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub cutoff_date: Date,
+    pub previous_currency_code: String,
+    pub exchange_rate: Decimal,
+    pub rounding_account_id: i32,
+    pub rounding_difference: Decimal,
+    pub journal_entry_id: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(
+        belongs_to = "super::account::Entity",
+        from = "Column::RoundingAccountId",
+        to = "super::account::Column::Id"
+    )]
+    RoundingAccount,
+    #[sea_orm(
+        belongs_to = "super::journal_entry::Entity",
+        from = "Column::JournalEntryId",
+        to = "super::journal_entry::Column::Id"
+    )]
+    JournalEntry,
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct EuroChangeoverInput {
+    pub company_id: i32,
+    /// Last day booked in BGN; the conversion entry is dated the next day
+    pub cutoff_date: Date,
+    /// Account receiving the rounding differences
+    pub rounding_account_id: i32,
+}
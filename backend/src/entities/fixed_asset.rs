@@ -3,9 +3,21 @@
 //! Represents individual fixed assets with separate accounting and tax depreciation
 
 use async_graphql::SimpleObject;
+use chrono::Datelike;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+/// Supported accounting depreciation methods
+pub const DEPRECIATION_METHODS: [&str; 4] = [
+    "straight_line",
+    "declining_balance",
+    "sum_of_years_digits",
+    "units_of_production",
+];
+
+/// Supported starts of depreciation
+pub const START_CONVENTIONS: [&str; 3] = ["next_month", "full_month", "daily"];
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
 #[sea_orm(table_name = "fixed_assets")]
 #[graphql(name = "FixedAsset")]
@@ -45,9 +57,17 @@ pub struct Model {
     /// Annual accounting depreciation rate as percentage
     pub accounting_depreciation_rate: Decimal,
 
-    /// Depreciation method (straight_line, declining_balance, etc.)
+    /// Depreciation method (straight_line, declining_balance, sum_of_years_digits,
+    /// units_of_production)
     pub accounting_depreciation_method: String,
 
+    /// When depreciation starts: next_month (month after put into service),
+    /// full_month (the month of putting into service) or daily (pro-rata from that day)
+    pub depreciation_start_convention: String,
+
+    /// Total units the asset is expected to produce, for units_of_production
+    pub production_capacity: Option<Decimal>,
+
     /// Salvage value for accounting depreciation
     pub accounting_salvage_value: Decimal,
 
@@ -64,6 +84,10 @@ pub struct Model {
     /// Accumulated tax depreciation to date
     pub tax_accumulated_depreciation: Decimal,
 
+    /// Tax depreciable value (ЗКПО) - acquisition cost plus improvements, less
+    /// the written-off part; revaluations and impairments do not change it
+    pub tax_depreciable_value: Decimal,
+
     /// Whether this is a new first-time investment (allows higher rates)
     pub is_new_first_time_investment: bool,
 
@@ -74,6 +98,20 @@ pub struct Model {
     /// Current tax book value (cost - accumulated tax depreciation)
     pub tax_book_value: Decimal,
 
+    /// Fixed monthly straight-line charge set by the last revaluation, improvement
+    /// or impairment; spreads the new book value over the remaining useful life
+    pub accounting_monthly_depreciation: Option<Decimal>,
+
+    /// Revaluation surplus of the asset carried in reserve account 112
+    pub revaluation_reserve: Decimal,
+
+    /// Revaluation decreases and impairments recognised as expense and not yet reversed
+    pub revaluation_loss: Decimal,
+
+    /// Month-end up to which the opening accumulated depreciation was taken over
+    /// from a previous system; depreciation is calculated from the next month
+    pub opening_balance_date: Option<Date>,
+
     // Status
     /// Asset status: active, disposed, sold
     pub status: String,
@@ -125,6 +163,12 @@ pub enum Relation {
 
     #[sea_orm(has_many = "super::depreciation_journal::Entity")]
     DepreciationJournal,
+
+    #[sea_orm(has_many = "super::fixed_asset_event::Entity")]
+    Events,
+
+    #[sea_orm(has_many = "super::fixed_asset_usage::Entity")]
+    Usages,
 }
 
 impl Related<super::fixed_asset_category::Entity> for Entity {
@@ -145,11 +189,26 @@ impl Related<super::depreciation_journal::Entity> for Entity {
     }
 }
 
+impl Related<super::fixed_asset_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Events.def()
+    }
+}
+
+impl Related<super::fixed_asset_usage::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Usages.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Model {
     /// Calculate monthly accounting depreciation amount
     pub fn calculate_monthly_accounting_depreciation(&self) -> Decimal {
+        if let Some(monthly) = self.accounting_monthly_depreciation {
+            return monthly;
+        }
         let depreciable_amount = self.acquisition_cost - self.accounting_salvage_value;
         let annual_amount =
             depreciable_amount * (self.accounting_depreciation_rate / Decimal::from(100));
@@ -159,7 +218,7 @@ impl Model {
     /// Calculate monthly tax depreciation amount
     pub fn calculate_monthly_tax_depreciation(&self) -> Decimal {
         let annual_amount =
-            self.acquisition_cost * (self.tax_depreciation_rate / Decimal::from(100));
+            self.tax_depreciable_value * (self.tax_depreciation_rate / Decimal::from(100));
         annual_amount / Decimal::from(12)
     }
 
@@ -215,6 +274,48 @@ impl Model {
         self.tax_book_value - self.accounting_book_value
     }
 
+    /// Date the asset was put into service, or acquired if it is not set
+    pub fn service_start_date(&self) -> Date {
+        self.put_into_service_date.unwrap_or(self.acquisition_date)
+    }
+
+    /// First month (as its first day) with a depreciation charge
+    pub fn first_depreciation_period(&self) -> Date {
+        let start = self.service_start_date();
+        let month_start = start.with_day(1).unwrap_or(start);
+        if self.depreciation_start_convention == "next_month" {
+            month_start + chrono::Months::new(1)
+        } else {
+            month_start
+        }
+    }
+
+    /// First month whose depreciation is calculated in this system; later than
+    /// the first depreciation period for assets imported with opening balances
+    pub fn first_calculated_period(&self) -> Date {
+        let first = self.first_depreciation_period();
+        match self.opening_balance_date {
+            Some(opening) => {
+                let after_opening = opening.with_day(1).unwrap_or(opening) + chrono::Months::new(1);
+                first.max(after_opening)
+            }
+            None => first,
+        }
+    }
+
+    /// Part of the first month that is charged: the days in service under the
+    /// daily convention, the whole month otherwise
+    pub fn first_month_fraction(&self) -> Decimal {
+        if self.depreciation_start_convention != "daily" {
+            return Decimal::ONE;
+        }
+        let start = self.service_start_date();
+        let month_start = start.with_day(1).unwrap_or(start);
+        let days_in_month = ((month_start + chrono::Months::new(1)) - month_start).num_days();
+        let days_in_service = days_in_month - i64::from(start.day()) + 1;
+        Decimal::from(days_in_service) / Decimal::from(days_in_month)
+    }
+
     /// Check if asset is active
     pub fn is_active(&self) -> bool {
         self.status == "active"
@@ -226,7 +327,58 @@ impl Model {
             "active" => "Активен",
             "disposed" => "Ликвидиран",
             "sold" => "Продаден",
+            "written_off" => "Отписан",
             _ => "Неизвестен статус",
         }
     }
 }
+
+#[cfg(test)]
+impl Model {
+    /// Test asset put into service on `put_into_service`: a machine depreciated
+    /// straight-line over 60 months from the next month, 30% for tax, with
+    /// nothing depreciated yet. Tests override the fields they care about.
+    pub fn fixture(id: i32, cost: Decimal, put_into_service: Date) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id,
+            inventory_number: format!("DMA-{:03}", id),
+            name: "Машина".to_string(),
+            description: None,
+            category_id: 2,
+            company_id: 1,
+            acquisition_cost: cost,
+            acquisition_date: put_into_service,
+            put_into_service_date: Some(put_into_service),
+            accounting_useful_life: 60,
+            accounting_depreciation_rate: Decimal::from(20),
+            accounting_depreciation_method: "straight_line".to_string(),
+            depreciation_start_convention: "next_month".to_string(),
+            production_capacity: None,
+            accounting_salvage_value: Decimal::ZERO,
+            accounting_accumulated_depreciation: Decimal::ZERO,
+            tax_useful_life: None,
+            tax_depreciation_rate: Decimal::from(30),
+            tax_accumulated_depreciation: Decimal::ZERO,
+            tax_depreciable_value: cost,
+            is_new_first_time_investment: false,
+            accounting_book_value: cost,
+            tax_book_value: cost,
+            accounting_monthly_depreciation: None,
+            revaluation_reserve: Decimal::ZERO,
+            revaluation_loss: Decimal::ZERO,
+            opening_balance_date: None,
+            status: "active".to_string(),
+            disposal_date: None,
+            disposal_amount: None,
+            location: None,
+            responsible_person: None,
+            serial_number: None,
+            manufacturer: None,
+            model: None,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -102,3 +102,27 @@ impl Model {
         }
     }
 }
+
+#[cfg(test)]
+impl Model {
+    /// Test category of tax category 2 (machinery, 30%) booked to 206/241/603
+    pub fn machinery() -> Self {
+        Self {
+            id: 2,
+            code: "MACHINERY".to_string(),
+            name: "Машини и оборудване".to_string(),
+            description: None,
+            tax_category: 2,
+            max_tax_depreciation_rate: Decimal::from(30),
+            default_accounting_depreciation_rate: None,
+            min_useful_life: None,
+            max_useful_life: None,
+            asset_account_code: "206".to_string(),
+            depreciation_account_code: "241".to_string(),
+            expense_account_code: "603".to_string(),
+            is_active: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::StringLen;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(15))")]
+pub enum FixedAssetEventType {
+    /// Sale of the whole asset or a part of it
+    #[sea_orm(string_value = "SALE")]
+    Sale,
+    /// Liquidation of the whole asset or a part of it
+    #[sea_orm(string_value = "DISPOSAL")]
+    Disposal,
+    /// Write-off of a missing or destroyed asset
+    #[sea_orm(string_value = "WRITE_OFF")]
+    WriteOff,
+    /// Remeasurement to fair value under the revaluation model of СС 16 / IAS 16
+    #[sea_orm(string_value = "REVALUATION")]
+    Revaluation,
+    /// Capitalised subsequent costs that may extend the useful life
+    #[sea_orm(string_value = "IMPROVEMENT")]
+    Improvement,
+    /// Write-down to the recoverable amount (СС 36 / IAS 36)
+    #[sea_orm(string_value = "IMPAIRMENT")]
+    Impairment,
+}
+
+impl FixedAssetEventType {
+    /// Whether the event derecognises the asset or a part of it
+    pub fn is_disposal(&self) -> bool {
+        matches!(
+            self,
+            FixedAssetEventType::Sale
+                | FixedAssetEventType::Disposal
+                | FixedAssetEventType::WriteOff
+        )
+    }
+
+    /// Asset status after the whole asset is derecognised
+    pub fn disposed_status(&self) -> Option<&'static str> {
+        match self {
+            FixedAssetEventType::Sale => Some("sold"),
+            FixedAssetEventType::Disposal => Some("disposed"),
+            FixedAssetEventType::WriteOff => Some("written_off"),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            FixedAssetEventType::Sale => "Продажба",
+            FixedAssetEventType::Disposal => "Ликвидация",
+            FixedAssetEventType::WriteOff => "Отписване",
+            FixedAssetEventType::Revaluation => "Преоценка",
+            FixedAssetEventType::Improvement => "Подобрение",
+            FixedAssetEventType::Impairment => "Обезценка",
+        }
+    }
+}
+
+/// Lifecycle event of a fixed asset together with the journal entry that books it
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "fixed_asset_events")]
+#[graphql(concrete(name = "FixedAssetEvent", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub fixed_asset_id: i32,
+    pub event_type: FixedAssetEventType,
+    pub event_date: Date,
+    /// Derecognised part of the asset; 1 for everything but partial disposals
+    pub portion: Decimal,
+    /// Sale proceeds, fair value, improvement cost or recoverable amount
+    pub amount: Decimal,
+    pub acquisition_cost_before: Decimal,
+    pub acquisition_cost_after: Decimal,
+    pub accounting_book_value_before: Decimal,
+    pub accounting_book_value_after: Decimal,
+    pub tax_book_value_before: Decimal,
+    pub tax_book_value_after: Decimal,
+    pub tax_depreciable_value_before: Decimal,
+    pub tax_depreciable_value_after: Decimal,
+    /// Effect on the carrying amount; positive is a gain or surplus
+    pub gain_loss: Decimal,
+    /// Months over which the new book value is depreciated
+    pub remaining_life_months: Option<i32>,
+    pub journal_entry_id: Option<i32>,
+    pub description: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::fixed_asset::Entity",
+        from = "Column::FixedAssetId",
+        to = "super::fixed_asset::Column::Id"
+    )]
+    FixedAsset,
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(
+        belongs_to = "super::journal_entry::Entity",
+        from = "Column::JournalEntryId",
+        to = "super::journal_entry::Column::Id"
+    )]
+    JournalEntry,
+}
+
+impl Related<super::fixed_asset::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FixedAsset.def()
+    }
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl Related<super::journal_entry::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalEntry.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct FixedAssetEventInput {
+    pub fixed_asset_id: i32,
+    pub event_type: FixedAssetEventType,
+    pub event_date: Date,
+    /// Sale proceeds without VAT, fair value, improvement cost or recoverable amount
+    pub amount: Option<Decimal>,
+    /// Part of the asset sold or disposed of, between 0 and 1; defaults to the whole asset
+    pub portion: Option<Decimal>,
+    /// New remaining useful life in months after a revaluation, improvement or impairment
+    pub remaining_life_months: Option<i32>,
+    /// Months an improvement adds to the remaining useful life
+    pub extends_life_months: Option<i32>,
+    /// Receivable account of a sale (411), source account of an improvement (613)
+    /// or expense account of a disposal (609) or write-off (699)
+    pub offset_account_id: Option<i32>,
+    /// Buyer of a sale or supplier of an improvement
+    pub counterpart_id: Option<i32>,
+    /// Sales operation a sale is reported under in the VAT journal (про11, про19, ...)
+    pub vat_operation: Option<String>,
+    /// VAT rate charged on the proceeds of a sale; without it no VAT is charged
+    pub vat_rate_id: Option<i32>,
+    /// Number of the sales invoice; defaults to the inventory number of the asset
+    pub document_number: Option<String>,
+    pub description: Option<String>,
+}
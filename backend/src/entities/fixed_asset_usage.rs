@@ -0,0 +1,58 @@
+use async_graphql::{InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Units produced by an asset in a month, the basis of units-of-production depreciation
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "fixed_asset_usages")]
+#[graphql(concrete(name = "FixedAssetUsage", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub fixed_asset_id: i32,
+    /// First day of the month
+    pub period: Date,
+    pub units: Decimal,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::fixed_asset::Entity",
+        from = "Column::FixedAssetId",
+        to = "super::fixed_asset::Column::Id"
+    )]
+    FixedAsset,
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+}
+
+impl Related<super::fixed_asset::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FixedAsset.def()
+    }
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct FixedAssetUsageInput {
+    pub fixed_asset_id: i32,
+    pub year: i32,
+    pub month: u32,
+    pub units: Decimal,
+}
@@ -0,0 +1,62 @@
+use async_graphql::{InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Month-end revaluation of foreign-currency balances at the closing rate,
+/// with the differences booked to the FX gain and loss accounts
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "fx_revaluations")]
+#[graphql(concrete(name = "FxRevaluation", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub year: i32,
+    pub month: i32,
+    /// Last day of the month; the rates of this date are used
+    pub revaluation_date: Date,
+    pub gain_account_id: i32,
+    pub loss_account_id: i32,
+    pub total_gain: Decimal,
+    pub total_loss: Decimal,
+    /// None when no balance needed revaluation
+    pub journal_entry_id: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(
+        belongs_to = "super::journal_entry::Entity",
+        from = "Column::JournalEntryId",
+        to = "super::journal_entry::Column::Id"
+    )]
+    JournalEntry,
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct FxRevaluationInput {
+    pub company_id: i32,
+    pub year: i32,
+    pub month: i32,
+    /// Defaults to account 724
+    pub gain_account_id: Option<i32>,
+    /// Defaults to account 624
+    pub loss_account_id: Option<i32>,
+}
@@ -33,6 +33,10 @@ pub struct Model {
     pub vat_sales_operation: Option<String>,
     pub vat_additional_operation: Option<String>,
     pub vat_additional_data: Option<String>,
+
+    // Numbering series the entry number was allocated from
+    pub series_id: Option<i32>,
+    pub sequence_number: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -57,6 +61,12 @@ pub enum Relation {
     PostedByUser,
     #[sea_orm(has_many = "super::entry_line::Entity")]
     EntryLines,
+    #[sea_orm(
+        belongs_to = "super::journal_entry_series::Entity",
+        from = "Column::SeriesId",
+        to = "super::journal_entry_series::Column::Id"
+    )]
+    Series,
 }
 
 impl Related<super::company::Entity> for Entity {
@@ -71,6 +81,12 @@ impl Related<super::entry_line::Entity> for Entity {
     }
 }
 
+impl Related<super::journal_entry_series::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Series.def()
+    }
+}
+
 // Input types for GraphQL mutations
 #[derive(Clone, InputObject, Deserialize, Serialize)]
 pub struct CreateJournalEntryInput {
@@ -137,13 +153,19 @@ pub struct JournalEntryFilter {
     pub document_number: Option<String>,
 }
 
-impl From<CreateJournalEntryInput> for ActiveModel {
-    fn from(input: CreateJournalEntryInput) -> Self {
-        ActiveModel {
-            entry_number: Set(input.entry_number.unwrap_or_else(|| {
-                // Generate entry number - will be improved with proper sequence
-                format!("JE-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"))
-            })),
+/// The entry number is either entered by hand or allocated from the numbering
+/// series before the conversion; an entry is never stored without one.
+impl TryFrom<CreateJournalEntryInput> for ActiveModel {
+    type Error = &'static str;
+
+    fn try_from(input: CreateJournalEntryInput) -> Result<Self, Self::Error> {
+        let entry_number = input
+            .entry_number
+            .filter(|number| !number.trim().is_empty())
+            .ok_or("No entry number was allocated for the journal entry")?;
+
+        Ok(ActiveModel {
+            entry_number: Set(entry_number),
             document_date: Set(input.document_date),
             vat_date: Set(input.vat_date),
             accounting_date: Set(input.accounting_date),
@@ -157,7 +179,7 @@ impl From<CreateJournalEntryInput> for ActiveModel {
             vat_additional_data: Set(input.vat_additional_data),
             // total_amount and total_vat_amount will be calculated from lines
             ..Default::default()
-        }
+        })
     }
 }
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,96 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use sea_orm::entity::prelude::*;
+use sea_orm::prelude::StringLen;
+use serde::{Deserialize, Serialize};
+
+/// Kind of document a numbering series is used for
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+pub enum JournalSeriesKind {
+    #[sea_orm(string_value = "MANUAL")]
+    Manual,
+    #[sea_orm(string_value = "BANK")]
+    Bank,
+    #[sea_orm(string_value = "SALES")]
+    Sales,
+    #[sea_orm(string_value = "PURCHASES")]
+    Purchases,
+    #[sea_orm(string_value = "DEPRECIATION")]
+    Depreciation,
+    /// Year-end closing, euro changeover and FX revaluation entries
+    #[sea_orm(string_value = "CLOSING")]
+    Closing,
+}
+
+impl JournalSeriesKind {
+    /// Prefix of a series until the company configures its own
+    pub fn default_prefix(&self) -> &'static str {
+        match self {
+            JournalSeriesKind::Manual => "JE",
+            JournalSeriesKind::Bank => "BANK",
+            JournalSeriesKind::Sales => "SAL",
+            JournalSeriesKind::Purchases => "PUR",
+            JournalSeriesKind::Depreciation => "DEP",
+            JournalSeriesKind::Closing => "CLS",
+        }
+    }
+}
+
+/// Gap-free numbering counter of one company, kind and year
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "journal_entry_series")]
+#[graphql(concrete(name = "JournalEntrySeries", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub code: JournalSeriesKind,
+    pub year: i32,
+    pub prefix: String,
+    /// Minimum number of digits of the sequence number
+    pub padding: i32,
+    /// First number of the series; renumbering starts from it
+    pub start_number: i32,
+    pub next_number: i32,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(has_many = "super::journal_entry::Entity")]
+    JournalEntries,
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl Related<super::journal_entry::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::JournalEntries.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct ConfigureJournalEntrySeriesInput {
+    pub company_id: i32,
+    pub code: JournalSeriesKind,
+    pub year: i32,
+    pub prefix: Option<String>,
+    pub padding: Option<i32>,
+    /// First number of the series; only allowed before any number is used
+    pub start_number: Option<i32>,
+}
@@ -1,9 +1,13 @@
 pub mod account;
+pub mod accounting_period;
 pub mod ai_accounting_setting;
 pub mod ai_bank_accounting_setting;
+pub mod audit_log;
 pub mod average_cost_correction;
 pub mod bank_import;
 pub mod bank_profile;
+pub mod bank_reconciliation_match;
+pub mod bank_transaction_fingerprint;
 pub mod company;
 pub mod contragent_setting;
 pub mod controlisy_imports;
@@ -11,9 +15,13 @@ pub mod counterpart;
 pub mod currency;
 pub mod depreciation_journal;
 pub mod entry_line;
+pub mod euro_changeover;
 pub mod exchange_rate;
 pub mod fixed_asset;
 pub mod fixed_asset_category;
+pub mod fixed_asset_event;
+pub mod fixed_asset_usage;
+pub mod fx_revaluation;
 pub mod global_contragent;
 pub mod inventory_balance;
 pub mod inventory_movement;
@@ -23,15 +31,23 @@ pub mod intrastat_declaration_item;
 pub mod intrastat_nomenclature;
 pub mod intrastat_settings;
 pub mod journal_entry;
+pub mod journal_entry_series;
+pub mod payment_batch;
+pub mod payment_order;
 pub mod saft;
 pub mod user;
 pub mod user_company;
 pub mod user_group;
 pub mod vat_rate;
 pub mod vat_return;
+pub mod year_end_closing;
 
 // Re-export for easier access
 pub use account::{ActiveModel as AccountActiveModel, Entity as Account, Model as AccountModel};
+pub use accounting_period::{
+    AccountingPeriodStatus, ActiveModel as AccountingPeriodActiveModel,
+    Entity as AccountingPeriod, Model as AccountingPeriodModel,
+};
 pub use ai_accounting_setting::{
     ActiveModel as AiAccountingSettingActiveModel, CreateAiAccountingSettingInput,
     Entity as AiAccountingSetting, Model as AiAccountingSettingModel,
@@ -42,6 +58,9 @@ pub use ai_bank_accounting_setting::{
     Entity as AiBankAccountingSetting, Model as AiBankAccountingSettingModel,
     UpdateAiBankAccountingSettingInput,
 };
+pub use audit_log::{
+    ActiveModel as AuditLogActiveModel, AuditAction, Entity as AuditLog, Model as AuditLogModel,
+};
 pub use average_cost_correction::{
     ActiveModel as AverageCostCorrectionActiveModel, Entity as AverageCostCorrection,
     Model as AverageCostCorrectionModel,
@@ -54,6 +73,14 @@ pub use bank_profile::{
     ActiveModel as BankProfileActiveModel, BankImportFormat, CreateBankProfileInput,
     Entity as BankProfile, Model as BankProfileModel, UpdateBankProfileInput,
 };
+pub use bank_reconciliation_match::{
+    ActiveModel as BankReconciliationMatchActiveModel, Entity as BankReconciliationMatch,
+    Model as BankReconciliationMatchModel,
+};
+pub use bank_transaction_fingerprint::{
+    ActiveModel as BankTransactionFingerprintActiveModel, Entity as BankTransactionFingerprint,
+    Model as BankTransactionFingerprintModel,
+};
 pub use company::{ActiveModel as CompanyActiveModel, Entity as Company, Model as CompanyModel};
 pub use contragent_setting::{
     ActiveModel as ContragentSettingActiveModel, Entity as ContragentSetting,
@@ -76,6 +103,10 @@ pub use depreciation_journal::{
 pub use entry_line::{
     ActiveModel as EntryLineActiveModel, Entity as EntryLine, Model as EntryLineModel,
 };
+pub use euro_changeover::{
+    ActiveModel as EuroChangeoverActiveModel, Entity as EuroChangeover,
+    Model as EuroChangeoverModel,
+};
 pub use exchange_rate::{
     ActiveModel as ExchangeRateActiveModel, Entity as ExchangeRate, Model as ExchangeRateModel,
 };
@@ -86,6 +117,17 @@ pub use fixed_asset_category::{
     ActiveModel as FixedAssetCategoryActiveModel, Entity as FixedAssetCategory,
     Model as FixedAssetCategoryModel,
 };
+pub use fixed_asset_event::{
+    ActiveModel as FixedAssetEventActiveModel, Entity as FixedAssetEvent, FixedAssetEventType,
+    Model as FixedAssetEventModel,
+};
+pub use fixed_asset_usage::{
+    ActiveModel as FixedAssetUsageActiveModel, Entity as FixedAssetUsage,
+    Model as FixedAssetUsageModel,
+};
+pub use fx_revaluation::{
+    ActiveModel as FxRevaluationActiveModel, Entity as FxRevaluation, Model as FxRevaluationModel,
+};
 pub use global_contragent::{
     ActiveModel as GlobalContragentActiveModel, Entity as GlobalContragent, GlobalContragentFilter,
     GlobalContragentSummary, Model as GlobalContragentModel,
@@ -121,6 +163,18 @@ pub use intrastat_settings::{
 pub use journal_entry::{
     ActiveModel as JournalEntryActiveModel, Entity as JournalEntry, Model as JournalEntryModel,
 };
+pub use journal_entry_series::{
+    ActiveModel as JournalEntrySeriesActiveModel, Entity as JournalEntrySeries, JournalSeriesKind,
+    Model as JournalEntrySeriesModel,
+};
+pub use payment_batch::{
+    ActiveModel as PaymentBatchActiveModel, CreatePaymentBatchInput, Entity as PaymentBatch,
+    Model as PaymentBatchModel, PaymentBatchKind, PaymentBatchStatus,
+};
+pub use payment_order::{
+    ActiveModel as PaymentOrderActiveModel, Entity as PaymentOrder, Model as PaymentOrderModel,
+    PaymentOrderInput,
+};
 pub use user::{ActiveModel as UserActiveModel, Entity as User, Model as UserModel};
 pub use user_company::{
     ActiveModel as UserCompanyActiveModel, Entity as UserCompany, Model as UserCompanyModel,
@@ -131,3 +185,7 @@ pub use vat_rate::{ActiveModel as VatRateActiveModel, Entity as VatRate, Model a
 pub use vat_return::{
     ActiveModel as VatReturnActiveModel, Entity as VatReturn, Model as VatReturnModel,
 };
+pub use year_end_closing::{
+    ActiveModel as YearEndClosingActiveModel, Entity as YearEndClosing,
+    Model as YearEndClosingModel,
+};
@@ -0,0 +1,112 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::StringLen;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(10))")]
+pub enum PaymentBatchKind {
+    /// Credit transfers to suppliers
+    #[sea_orm(string_value = "SUPPLIER")]
+    Supplier,
+    /// Budget payments of taxes to the National Revenue Agency
+    #[sea_orm(string_value = "NAP")]
+    Nap,
+    /// Budget payments to the National Social Security Institute
+    #[sea_orm(string_value = "NOI")]
+    Noi,
+}
+
+impl PaymentBatchKind {
+    pub fn is_budget(&self) -> bool {
+        matches!(self, PaymentBatchKind::Nap | PaymentBatchKind::Noi)
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Enum,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(10))")]
+pub enum PaymentBatchStatus {
+    #[sea_orm(string_value = "DRAFT")]
+    Draft,
+    /// The pain.001 file was generated for the bank
+    #[sea_orm(string_value = "EXPORTED")]
+    Exported,
+    /// Every order was found in an imported bank statement
+    #[sea_orm(string_value = "MATCHED")]
+    Matched,
+}
+
+/// Batch of credit transfers exported as one ISO 20022 pain.001 file
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "payment_batches")]
+#[graphql(concrete(name = "PaymentBatch", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    /// Bank profile whose account is debited
+    pub bank_profile_id: i32,
+    pub kind: PaymentBatchKind,
+    pub status: PaymentBatchStatus,
+    /// MsgId of the pain.001 group header
+    pub message_id: String,
+    pub execution_date: Date,
+    pub currency_code: String,
+    pub total_amount: Decimal,
+    pub exported_at: Option<DateTimeUtc>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(
+        belongs_to = "super::bank_profile::Entity",
+        from = "Column::BankProfileId",
+        to = "super::bank_profile::Column::Id"
+    )]
+    BankProfile,
+    #[sea_orm(has_many = "super::payment_order::Entity")]
+    PaymentOrders,
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl Related<super::bank_profile::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BankProfile.def()
+    }
+}
+
+impl Related<super::payment_order::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PaymentOrders.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct CreatePaymentBatchInput {
+    pub company_id: i32,
+    pub bank_profile_id: i32,
+    pub kind: PaymentBatchKind,
+    pub execution_date: Date,
+    pub orders: Vec<super::payment_order::PaymentOrderInput>,
+}
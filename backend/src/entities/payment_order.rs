@@ -0,0 +1,86 @@
+use async_graphql::{InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Credit transfer in a payment batch
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "payment_orders")]
+#[graphql(concrete(name = "PaymentOrder", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub batch_id: i32,
+    pub line_number: i32,
+    /// Supplier whose balance on account 401 the payment settles
+    pub counterpart_id: Option<i32>,
+    /// Purchase invoice the payment settles, if a single one
+    pub invoice_entry_id: Option<i32>,
+    pub creditor_name: String,
+    pub creditor_iban: String,
+    pub creditor_bic: Option<String>,
+    pub amount: Decimal,
+    pub remittance_info: String,
+    pub end_to_end_id: String,
+    /// Six-digit budget payment type code (вид плащане)
+    pub budget_payment_type: Option<String>,
+    /// Type of the document the budget obligation is based on
+    pub budget_document_type: Option<String>,
+    pub budget_document_number: Option<String>,
+    pub budget_document_date: Option<Date>,
+    pub budget_period_from: Option<Date>,
+    pub budget_period_to: Option<Date>,
+    /// Bank entry of the imported statement that executed the order
+    pub bank_entry_id: Option<i32>,
+    pub matched_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::payment_batch::Entity",
+        from = "Column::BatchId",
+        to = "super::payment_batch::Column::Id"
+    )]
+    PaymentBatch,
+    #[sea_orm(
+        belongs_to = "super::counterpart::Entity",
+        from = "Column::CounterpartId",
+        to = "super::counterpart::Column::Id"
+    )]
+    Counterpart,
+}
+
+impl Related<super::payment_batch::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PaymentBatch.def()
+    }
+}
+
+impl Related<super::counterpart::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Counterpart.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize, Clone)]
+pub struct PaymentOrderInput {
+    pub counterpart_id: Option<i32>,
+    pub invoice_entry_id: Option<i32>,
+    /// Defaults to the counterpart name
+    pub creditor_name: Option<String>,
+    /// Defaults to the IBAN of earlier payments to the counterpart
+    pub creditor_iban: Option<String>,
+    pub creditor_bic: Option<String>,
+    pub amount: Decimal,
+    /// Defaults to the invoice number
+    pub remittance_info: Option<String>,
+    pub budget_payment_type: Option<String>,
+    pub budget_document_type: Option<String>,
+    pub budget_document_number: Option<String>,
+    pub budget_document_date: Option<Date>,
+    pub budget_period_from: Option<Date>,
+    pub budget_period_to: Option<Date>,
+}
@@ -61,7 +61,7 @@ pub struct CompanyInfo {
 }
 
 /// Address structure v1.0.1
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Address {
     pub street_name: Option<String>,
     pub number: Option<String>,
@@ -75,7 +75,7 @@ pub struct Address {
 }
 
 /// Contact structure v1.0.1
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contact {
     pub contact_person: Option<ContactPerson>,
     pub telephone: Option<String>,
@@ -85,7 +85,7 @@ pub struct Contact {
 }
 
 /// Contact person structure
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContactPerson {
     pub title: Option<String>,
     pub first_name: Option<String>,
@@ -98,7 +98,7 @@ pub struct ContactPerson {
 }
 
 /// Tax Registration
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaxRegistration {
     pub tax_registration_number: String, // EIK
     pub tax_type: String,                // Tax type code
@@ -230,10 +230,10 @@ pub struct Owners {
     // Placeholder for owners data
 }
 
-/// Assets placeholder
+/// Assets container
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Assets {
-    // Placeholder for assets data
+    pub asset: Vec<SafTAsset>,
 }
 
 /// Taxonomies placeholder
@@ -242,10 +242,58 @@ pub struct Taxonomies {
     // Placeholder for taxonomies data
 }
 
-/// Physical Stock placeholder
+/// Physical Stock container
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PhysicalStock {
-    // Placeholder for physical stock data
+    pub physical_stock_entry: Vec<PhysicalStockEntry>,
+}
+
+/// Physical Stock entry - opening/closing stock per product for the period
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhysicalStockEntry {
+    pub warehouse_id: String,
+    pub product_code: String,
+    pub product_type: String,
+    pub stock_account_no: String,
+    pub uom_physical_stock: String,
+    pub uom_to_uom_base_conversion_factor: Decimal,
+    pub unit_price: Decimal,
+    pub opening_stock_quantity: Decimal,
+    pub opening_stock_value: Decimal,
+    pub closing_stock_quantity: Decimal,
+    pub closing_stock_value: Decimal,
+}
+
+/// SAF-T Asset (fixed asset register entry)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SafTAsset {
+    pub asset_id: String,   // Inventory number
+    pub account_id: String, // Asset account from the category
+    pub description: String,
+    pub supplier_id: Option<String>,
+    pub date_of_acquisition: NaiveDate,
+    pub start_up_date: Option<NaiveDate>,
+    pub valuations: Vec<AssetValuation>,
+}
+
+/// Asset Valuation for the reporting period
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssetValuation {
+    pub asset_valuation_type: String, // "Accounting" or "Tax"
+    pub valuation_class: Option<String>,
+    pub acquisition_and_production_costs_begin: Decimal,
+    pub acquisition_and_production_costs_end: Decimal,
+    pub asset_life_year: Option<Decimal>,
+    pub asset_addition: Decimal,
+    pub transfers: Decimal,
+    pub asset_disposal: Decimal,
+    pub book_value_begin: Decimal,
+    pub depreciation_method: String,
+    pub depreciation_percentage: Decimal,
+    pub depreciation_for_period: Decimal,
+    pub appreciation_for_period: Decimal,
+    pub accumulated_depreciation: Decimal,
+    pub book_value_end: Decimal,
 }
 
 /// SAF-T Account v1.0.1
@@ -499,10 +547,10 @@ pub struct SafTTaxInformation {
     pub tax_country_region: String, // "BG"
 }
 
-/// Source Documents Annual placeholder
+/// Source Documents Annual
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SourceDocumentsAnnual {
-    // Placeholder - specific implementation depends on requirements
+    pub asset_transactions: Option<AssetTransactions>,
 }
 
 /// Source Documents Monthly
@@ -540,6 +588,7 @@ pub struct SalesInvoice {
     pub invoice_date: NaiveDate,
     pub invoice_type: String,
     pub self_billing_indicator: String,
+    pub transaction_id: Option<String>, // Link to GeneralLedgerEntries transaction
     pub currency_code: Option<String>,
     pub exchange_rate: Option<Decimal>,
     pub lines: Vec<InvoiceLine>,
@@ -557,6 +606,7 @@ pub struct CustomerInfo {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InvoiceLine {
     pub line_number: String,
+    pub account_id: Option<i32>,
     pub product_code: Option<String>,
     pub product_description: Option<String>,
     pub quantity: Option<Decimal>,
@@ -605,6 +655,7 @@ pub struct PurchaseInvoice {
     pub invoice_date: NaiveDate,
     pub invoice_type: String,
     pub self_billing_indicator: String,
+    pub transaction_id: Option<String>, // Link to GeneralLedgerEntries transaction
     pub currency_code: Option<String>,
     pub exchange_rate: Option<Decimal>,
     pub lines: Vec<InvoiceLine>,
@@ -618,22 +669,105 @@ pub struct SupplierInfo {
     pub billing_address: Option<Address>,
 }
 
-/// Payments placeholder
+/// Payments
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Payments {
-    // Placeholder
+    pub number_of_entries: i32,
+    pub total_debit: Decimal,
+    pub total_credit: Decimal,
+    pub payment: Vec<Payment>,
+}
+
+/// Payment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Payment {
+    pub payment_ref_no: String,
+    pub period: i32,
+    pub period_year: i32,
+    pub transaction_id: String, // Link to GeneralLedgerEntries transaction
+    pub transaction_date: NaiveDate,
+    pub payment_method: String, // "01" cash, "02" bank transfer
+    pub description: String,
+    pub system_id: Option<String>,
+    pub source_id: Option<String>,
+    pub lines: Vec<PaymentLine>,
+    pub document_totals: DocumentTotals,
 }
 
-/// Movement of Goods placeholder
+/// Payment Line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentLine {
+    pub line_number: String,
+    pub source_document_id: Option<String>,
+    pub account_id: i32,
+    pub customer_id: Option<String>,
+    pub supplier_id: Option<String>,
+    pub description: String,
+    pub debit_credit_indicator: String, // "D" or "C"
+    pub payment_line_amount: AmountStructure,
+}
+
+/// Movement of Goods
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MovementOfGoods {
-    // Placeholder
+    pub number_of_movement_lines: i32,
+    pub total_quantity_received: Decimal,
+    pub total_quantity_issued: Decimal,
+    pub stock_movement: Vec<StockMovement>,
 }
 
-/// Asset Transactions placeholder
+/// Stock Movement - one per journal entry and movement type
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockMovement {
+    pub movement_reference: String,
+    pub movement_date: NaiveDate,
+    pub movement_type: String, // Code from MovementTypeTable
+    pub document_reference: Option<String>,
+    pub lines: Vec<StockMovementLine>,
+}
+
+/// Stock Movement Line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockMovementLine {
+    pub line_number: String,
+    pub account_id: String,
+    pub transaction_id: Option<String>, // Link to GeneralLedgerEntries transaction
+    pub product_code: String,
+    pub quantity: Decimal,
+    pub unit_of_measure: String,
+    pub uom_to_uom_base_conversion_factor: Decimal,
+    pub book_value: Decimal,
+    pub description: Option<String>,
+}
+
+/// Asset Transactions
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AssetTransactions {
-    // Placeholder
+    pub number_of_asset_transactions: i32,
+    pub asset_transaction: Vec<AssetTransaction>,
+}
+
+/// Asset Transaction
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssetTransaction {
+    pub asset_transaction_id: String,
+    pub asset_id: String,
+    pub asset_transaction_type: String, // "Acquisition", "Depreciation", "Disposal", "Sale", "Revaluation", "Improvement", "Impairment"
+    pub description: Option<String>,
+    pub asset_transaction_date: NaiveDate,
+    pub supplier_id: Option<String>,
+    pub customer_id: Option<String>,
+    pub transaction_id: Option<String>, // Link to GeneralLedgerEntries transaction
+    pub valuations: Vec<AssetTransactionValuation>,
+}
+
+/// Asset Transaction Valuation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssetTransactionValuation {
+    pub asset_valuation_type: String, // "Accounting" or "Tax"
+    pub acquisition_and_production_costs_on_transaction: Decimal,
+    pub book_value_on_transaction: Decimal,
+    pub asset_transaction_amount: Decimal,
 }
 
 /// Input structures for SAF-T generation
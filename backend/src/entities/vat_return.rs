@@ -15,6 +15,9 @@ pub enum VatReturnStatus {
     Submitted,
     #[sea_orm(string_value = "APPROVED")]
     Approved,
+    /// Filed, then replaced by a submitted corrective return
+    #[sea_orm(string_value = "SUPERSEDED")]
+    Superseded,
 }
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
@@ -122,6 +125,23 @@ pub struct Model {
     pub created_by: i32,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
+
+    // Corrective returns (поправителна декларация)
+    // Version 1 is the original return; corrections count up from there
+    pub version: i32,
+    // Return this one corrects
+    pub corrects_id: Option<i32>,
+    pub correction_reason: Option<String>,
+
+    // NAP files as submitted
+    #[graphql(skip)]
+    pub deklar_content: Option<String>,
+    #[graphql(skip)]
+    pub pokupki_content: Option<String>,
+    #[graphql(skip)]
+    pub prodagbi_content: Option<String>,
+    #[graphql(skip)]
+    pub vies_content: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -144,6 +164,8 @@ pub enum Relation {
         to = "super::user::Column::Id"
     )]
     SubmittedByUser,
+    #[sea_orm(belongs_to = "Entity", from = "Column::CorrectsId", to = "Column::Id")]
+    Corrects,
 }
 
 impl Related<super::company::Entity> for Entity {
@@ -161,6 +183,13 @@ pub struct CreateVatReturnInput {
     pub notes: Option<String>,
 }
 
+#[derive(InputObject, Deserialize, Serialize)]
+pub struct CreateCorrectiveVatReturnInput {
+    /// Submitted return to correct
+    pub vat_return_id: i32,
+    pub reason: String,
+}
+
 #[derive(InputObject, Deserialize, Serialize)]
 pub struct UpdateVatReturnInput {
     pub output_vat_amount: Option<Decimal>,
@@ -197,6 +226,7 @@ impl From<CreateVatReturnInput> for ActiveModel {
             period_to: Set(period_to),
             company_id: Set(input.company_id),
             notes: Set(input.notes),
+            version: Set(1),
             ..Default::default()
         }
     }
@@ -213,9 +243,17 @@ pub struct VatReturnFilter {
 }
 
 impl Model {
-    /// Check if VAT return is overdue
+    /// Check if VAT return is overdue; corrective returns are filed after the
+    /// due date by nature
     pub fn is_overdue(&self) -> bool {
-        self.status == VatReturnStatus::Draft && chrono::Utc::now().date_naive() > self.due_date
+        self.status == VatReturnStatus::Draft
+            && !self.is_corrective()
+            && chrono::Utc::now().date_naive() > self.due_date
+    }
+
+    /// Check if this return corrects an earlier one
+    pub fn is_corrective(&self) -> bool {
+        self.corrects_id.is_some()
     }
 
     /// Get period description in Bulgarian
@@ -263,6 +301,7 @@ impl Model {
             VatReturnStatus::Draft => "Чернова",
             VatReturnStatus::Submitted => "Подадена",
             VatReturnStatus::Approved => "Одобрена",
+            VatReturnStatus::Superseded => "Заменена",
         }
     }
 }
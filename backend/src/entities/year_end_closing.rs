@@ -0,0 +1,57 @@
+use async_graphql::{InputObject, SimpleObject};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Closing of a fiscal year: the revenue/expense result entry and the balance
+/// sheet closing entry dated 31 December, and the opening entry dated
+/// 1 January of the next year.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, SimpleObject)]
+#[sea_orm(table_name = "year_end_closings")]
+#[graphql(concrete(name = "YearEndClosing", params()))]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub company_id: i32,
+    pub fiscal_year: i32,
+    pub profit_loss_account_id: i32,
+    /// Profit positive, loss negative
+    pub net_result: Decimal,
+    pub result_entry_id: Option<i32>,
+    pub balance_closing_entry_id: Option<i32>,
+    pub opening_entry_id: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::company::Entity",
+        from = "Column::CompanyId",
+        to = "super::company::Column::Id"
+    )]
+    Company,
+    #[sea_orm(
+        belongs_to = "super::account::Entity",
+        from = "Column::ProfitLossAccountId",
+        to = "super::account::Column::Id"
+    )]
+    ProfitLossAccount,
+}
+
+impl Related<super::company::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Company.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(InputObject, Deserialize)]
+pub struct YearEndClosingInput {
+    pub company_id: i32,
+    pub fiscal_year: i32,
+    /// Defaults to account 123
+    pub profit_loss_account_id: Option<i32>,
+}
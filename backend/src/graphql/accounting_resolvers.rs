@@ -3,16 +3,31 @@ use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait,
-    QueryFilter, QueryOrder, QuerySelect, Set,
+    QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use std::sync::Arc;
 
 use crate::data::chart_of_accounts::load_chart_of_accounts;
 use crate::entities::account::{AccountWithBalance, CreateAccountInput, UpdateAccountInput};
+use crate::entities::accounting_period::{self, SetAccountingPeriodStatusInput};
+use crate::entities::audit_log::AuditAction;
+use crate::entities::fx_revaluation::{self, FxRevaluationInput};
 use crate::entities::journal_entry::{
     CreateJournalEntryInput, JournalEntryFilter, JournalEntryWithLines, UpdateJournalEntryInput,
 };
+use crate::entities::journal_entry_series::{
+    self, ConfigureJournalEntrySeriesInput, JournalSeriesKind,
+};
+use crate::entities::year_end_closing::{self, YearEndClosingInput};
 use crate::entities::{account, company, counterpart, entry_line, journal_entry};
+use crate::graphql::audit_resolvers::record_audit;
+use crate::graphql::context::{get_current_user, require_company_access, require_company_admin};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+use crate::services::euro_changeover::EuroChangeoverService;
+use crate::services::fx_revaluation::{FxRevaluationPlan, FxRevaluationService};
+use crate::services::journal_numbering::JournalNumberingService;
+use crate::services::year_end_closing::{YearEndClosingPlan, YearEndClosingService};
 
 #[derive(Default)]
 pub struct AccountingQuery;
@@ -327,6 +342,122 @@ impl AccountingQuery {
 
         Ok(count as i64)
     }
+
+    /// Closed periods of a company; months that are not listed are open
+    async fn accounting_periods(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: Option<i32>,
+    ) -> FieldResult<Vec<accounting_period::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let periods = AccountingPeriodService::list(db, company_id, year)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(periods)
+    }
+
+    /// Journal entry numbering series of a company
+    async fn journal_entry_series(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: Option<i32>,
+    ) -> FieldResult<Vec<journal_entry_series::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let series = JournalNumberingService::list(db, company_id, year)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(series)
+    }
+
+    /// Closing of a fiscal year, if it has been closed
+    async fn year_end_closing(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        fiscal_year: i32,
+    ) -> FieldResult<Option<year_end_closing::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let closing = YearEndClosingService::find(db, company_id, fiscal_year)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(closing)
+    }
+
+    /// Dry run of the year-end closing: the entries that would be booked
+    async fn year_end_closing_preview(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        fiscal_year: i32,
+        profit_loss_account_id: Option<i32>,
+    ) -> FieldResult<YearEndClosingPlan> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let plan =
+            YearEndClosingService::preview(db, company_id, fiscal_year, profit_loss_account_id)
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(plan)
+    }
+
+    /// Month-end revaluation of currency balances, if it has been booked
+    async fn fx_revaluation(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: i32,
+        month: i32,
+    ) -> FieldResult<Option<fx_revaluation::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let revaluation = FxRevaluationService::find(db, company_id, year, month)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(revaluation)
+    }
+
+    /// Dry run of the month-end revaluation: the differences that would be
+    /// booked
+    async fn fx_revaluation_preview(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: i32,
+        month: i32,
+        gain_account_id: Option<i32>,
+        loss_account_id: Option<i32>,
+    ) -> FieldResult<FxRevaluationPlan> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let plan = FxRevaluationService::preview(
+            db,
+            company_id,
+            year,
+            month,
+            gain_account_id,
+            loss_account_id,
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(plan)
+    }
 }
 
 #[derive(Default)]
@@ -343,10 +474,20 @@ impl AccountingMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
+        let txn = db.begin().await?;
         let company_model = company::ActiveModel::from(input);
         let company = company::Entity::insert(company_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "companies", company.id)
+                .company(company.id)
+                .after(&company),
+        )
+        .await?;
+        txn.commit().await?;
 
         // Load the default chart of accounts for the new company
         if let Err(e) = load_chart_of_accounts(db, company.id).await {
@@ -377,11 +518,11 @@ impl AccountingMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut company: company::ActiveModel = company::Entity::find_by_id(id)
+        let existing = company::Entity::find_by_id(id)
             .one(db)
             .await?
-            .ok_or_else(|| async_graphql::Error::new("Company not found"))?
-            .into();
+            .ok_or_else(|| async_graphql::Error::new("Company not found"))?;
+        let mut company: company::ActiveModel = existing.clone().into();
 
         if let Some(name) = input.name {
             company.name = Set(name);
@@ -428,7 +569,20 @@ impl AccountingMutation {
 
         company.updated_at = Set(chrono::Utc::now());
 
-        let updated_company = company.update(db).await?;
+        let txn = db.begin().await?;
+        let updated_company = company.update(&txn).await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "companies", id)
+                .company(id)
+                .before(&existing)
+                .after(&updated_company),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(updated_company)
     }
 
@@ -452,11 +606,22 @@ impl AccountingMutation {
             return Err("Account code already exists in this company".into());
         }
 
+        let txn = db.begin().await?;
         let account_model = account::ActiveModel::from(input);
         let account = account::Entity::insert(account_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
 
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "accounts", account.id)
+                .company(account.company_id)
+                .after(&account),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(account)
     }
 
@@ -490,13 +655,59 @@ impl AccountingMutation {
             .into());
         }
 
+        let user = get_current_user(ctx)?;
+        AccountingPeriodService::ensure_dates_open(
+            db,
+            input.company_id,
+            input.accounting_date,
+            input.vat_date,
+            Some(user.id),
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        // Lines without a currency are booked in the base currency in force on the document date
+        let base_currency =
+            EuroChangeoverService::base_currency_code(db, input.company_id, input.document_date)
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let txn = db.begin().await?;
+
         // Create journal entry
-        let mut entry_model = journal_entry::ActiveModel::from(input.clone());
-        entry_model.created_by = Set(1); // TODO: Get from auth context
+        let manual_number = input
+            .entry_number
+            .as_ref()
+            .is_some_and(|number| !number.trim().is_empty());
+        let number = if manual_number {
+            None
+        } else {
+            Some(
+                JournalNumberingService::allocate(
+                    &txn,
+                    input.company_id,
+                    JournalSeriesKind::Manual,
+                    input.accounting_date,
+                )
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?,
+            )
+        };
+        let mut entry_model = journal_entry::ActiveModel::try_from(CreateJournalEntryInput {
+            entry_number: number
+                .as_ref()
+                .map(|number| number.entry_number.clone())
+                .or_else(|| input.entry_number.clone()),
+            ..input.clone()
+        })?;
+        entry_model.created_by = Set(user.id);
         entry_model.total_amount = Set(total_debits);
+        if let Some(number) = &number {
+            number.apply(&mut entry_model);
+        }
 
         let entry = journal_entry::Entity::insert(entry_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
 
         // Create entry lines
@@ -510,7 +721,9 @@ impl AccountingMutation {
                 counterpart_id: line_input.counterpart_id,
                 vat_rate_id: None, // Not available in journal_entry::CreateEntryLineInput
                 vat_amount: line_input.vat_amount,
-                currency_code: line_input.currency_code,
+                currency_code: line_input
+                    .currency_code
+                    .or_else(|| Some(base_currency.clone())),
                 currency_amount: line_input.currency_amount,
                 exchange_rate: line_input.exchange_rate,
                 quantity: line_input.quantity,
@@ -522,15 +735,31 @@ impl AccountingMutation {
             line_model.line_order = Set((index + 1) as i32);
 
             let line = entry_line::Entity::insert(line_model)
-                .exec_with_returning(db)
+                .exec_with_returning(&txn)
                 .await?;
             lines.push(line);
         }
 
-        Ok(JournalEntryWithLines {
+        let created = JournalEntryWithLines {
             journal_entry: entry,
             lines,
-        })
+        };
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(
+                AuditAction::Create,
+                "journal_entries",
+                created.journal_entry.id,
+            )
+            .company(created.journal_entry.company_id)
+            .after(&created),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(created)
     }
 
     /// Post journal entry (make it permanent)
@@ -542,21 +771,37 @@ impl AccountingMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut entry: journal_entry::ActiveModel =
-            if let Some(entry) = journal_entry::Entity::find_by_id(id).one(db).await? {
-                if entry.is_posted {
-                    return Err("Journal entry is already posted".into());
-                }
-                entry.into()
-            } else {
-                return Err("Journal entry not found".into());
-            };
+        let user = get_current_user(ctx)?;
+        let existing = journal_entry::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("Journal entry not found")?;
+        if existing.is_posted {
+            return Err("Journal entry is already posted".into());
+        }
+        let txn = db.begin().await?;
+        AccountingPeriodService::ensure_entry_open(&txn, &existing, Some(user.id))
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
 
+        let mut entry: journal_entry::ActiveModel = existing.clone().into();
         entry.is_posted = Set(true);
-        entry.posted_by = Set(Some(1)); // TODO: Get from auth context
+        entry.posted_by = Set(Some(user.id));
         entry.posted_at = Set(Some(chrono::Utc::now()));
 
-        let updated_entry = journal_entry::Entity::update(entry).exec(db).await?;
+        let updated_entry = journal_entry::Entity::update(entry).exec(&txn).await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Post, "journal_entries", id)
+                .company(updated_entry.company_id)
+                .before(&existing)
+                .after(&updated_entry),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(updated_entry)
     }
 
@@ -580,6 +825,30 @@ impl AccountingMutation {
             return Err("Cannot update posted journal entry".into());
         }
 
+        // Both the current and the new dates must be in open periods
+        let user_id = get_current_user(ctx).ok().map(|user| user.id);
+        let txn = db.begin().await?;
+        AccountingPeriodService::ensure_entry_open(&txn, &existing_entry, user_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        AccountingPeriodService::ensure_dates_open(
+            &txn,
+            existing_entry.company_id,
+            input
+                .accounting_date
+                .unwrap_or(existing_entry.accounting_date),
+            input.vat_date.or(existing_entry.vat_date),
+            user_id,
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let company_id = existing_entry.company_id;
+        let document_date = input.document_date.unwrap_or(existing_entry.document_date);
+        let before = AuditLogService::journal_entry_snapshot(&txn, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
         // Update journal entry fields
         let mut entry_model: journal_entry::ActiveModel = existing_entry.into();
         if let Some(document_date) = input.document_date {
@@ -603,7 +872,7 @@ impl AccountingMutation {
             // Delete existing lines
             entry_line::Entity::delete_many()
                 .filter(entry_line::Column::JournalEntryId.eq(id))
-                .exec(db)
+                .exec(&txn)
                 .await?;
 
             // Validate that debits equal credits
@@ -627,6 +896,11 @@ impl AccountingMutation {
                 .into());
             }
 
+            let base_currency =
+                EuroChangeoverService::base_currency_code(&txn, company_id, document_date)
+                    .await
+                    .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
             // Create new lines
             for (index, line_input) in lines_input.into_iter().enumerate() {
                 let line_input_proper = entry_line::CreateEntryLineInput {
@@ -637,7 +911,9 @@ impl AccountingMutation {
                     counterpart_id: line_input.counterpart_id,
                     vat_rate_id: None,
                     vat_amount: line_input.vat_amount,
-                    currency_code: line_input.currency_code,
+                    currency_code: line_input
+                        .currency_code
+                        .or_else(|| Some(base_currency.clone())),
                     currency_amount: line_input.currency_amount,
                     exchange_rate: line_input.exchange_rate,
                     quantity: line_input.quantity,
@@ -646,14 +922,31 @@ impl AccountingMutation {
                 };
                 let mut line_model = entry_line::ActiveModel::from(line_input_proper);
                 line_model.journal_entry_id = Set(id);
-                entry_line::Entity::insert(line_model).exec(db).await?;
+                entry_line::Entity::insert(line_model).exec(&txn).await?;
             }
 
             entry_model.total_amount = Set(total_debits);
             entry_model.total_vat_amount = Set(Decimal::ZERO); // Calculate from lines if needed
         }
 
-        let updated_entry = journal_entry::Entity::update(entry_model).exec(db).await?;
+        let updated_entry = journal_entry::Entity::update(entry_model)
+            .exec(&txn)
+            .await?;
+
+        let after = AuditLogService::journal_entry_snapshot(&txn, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "journal_entries", id)
+                .company(company_id)
+                .before(&before)
+                .after(&after),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(updated_entry)
     }
 
@@ -666,20 +959,36 @@ impl AccountingMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut entry: journal_entry::ActiveModel =
-            if let Some(entry) = journal_entry::Entity::find_by_id(id).one(db).await? {
-                if !entry.is_posted {
-                    return Err("Journal entry is not posted".into());
-                }
-                entry.into()
-            } else {
-                return Err("Journal entry not found".into());
-            };
+        let existing = journal_entry::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("Journal entry not found")?;
+        if !existing.is_posted {
+            return Err("Journal entry is not posted".into());
+        }
+        let user_id = get_current_user(ctx).ok().map(|user| user.id);
+        let txn = db.begin().await?;
+        AccountingPeriodService::ensure_entry_open(&txn, &existing, user_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
 
+        let mut entry: journal_entry::ActiveModel = existing.clone().into();
         entry.is_posted = Set(false);
         entry.posted_at = Set(None);
 
-        let updated_entry = journal_entry::Entity::update(entry).exec(db).await?;
+        let updated_entry = journal_entry::Entity::update(entry).exec(&txn).await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Unpost, "journal_entries", id)
+                .company(updated_entry.company_id)
+                .before(&existing)
+                .after(&updated_entry),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(updated_entry)
     }
 
@@ -699,14 +1008,39 @@ impl AccountingMutation {
             return Err("Cannot delete posted journal entry. Unpost it first.".into());
         }
 
+        let user_id = get_current_user(ctx).ok().map(|user| user.id);
+        AccountingPeriodService::ensure_entry_open(db, &entry, user_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let before = AuditLogService::journal_entry_snapshot(db, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let txn = db.begin().await?;
+
         // Delete associated entry lines first (foreign key constraint)
         entry_line::Entity::delete_many()
             .filter(entry_line::Column::JournalEntryId.eq(id))
-            .exec(db)
+            .exec(&txn)
             .await?;
 
-        // Delete the journal entry
-        journal_entry::Entity::delete_by_id(id).exec(db).await?;
+        // Delete the journal entry and give its number back if it was the last one
+        journal_entry::Entity::delete_by_id(id).exec(&txn).await?;
+        JournalNumberingService::release(&txn, &entry)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Delete, "journal_entries", id)
+                .company(entry.company_id)
+                .before(&before),
+        )
+        .await?;
+
+        txn.commit().await?;
 
         Ok(true)
     }
@@ -722,16 +1056,40 @@ impl AccountingMutation {
             .all(db)
             .await?;
 
+        // Refuse the whole batch if any entry falls in a closed period
+        let user_id = get_current_user(ctx).ok().map(|user| user.id);
+        let txn = db.begin().await?;
+        for entry in entries.iter().filter(|entry| entry.is_posted) {
+            AccountingPeriodService::ensure_entry_open(&txn, entry, user_id)
+                .await
+                .map_err(|err| {
+                    async_graphql::Error::new(format!("{}: {}", entry.entry_number, err))
+                })?;
+        }
+
         let mut unposted_count = 0;
         for entry in entries {
             if entry.is_posted {
-                let mut entry_model: journal_entry::ActiveModel = entry.into();
+                let mut entry_model: journal_entry::ActiveModel = entry.clone().into();
                 entry_model.is_posted = Set(false);
                 entry_model.posted_at = Set(None);
-                journal_entry::Entity::update(entry_model).exec(db).await?;
+                let updated_entry = journal_entry::Entity::update(entry_model)
+                    .exec(&txn)
+                    .await?;
                 unposted_count += 1;
+
+                record_audit(
+                    ctx,
+                    &txn,
+                    AuditEvent::new(AuditAction::Unpost, "journal_entries", entry.id)
+                        .company(entry.company_id)
+                        .before(&entry)
+                        .after(&updated_entry),
+                )
+                .await?;
             }
         }
+        txn.commit().await?;
 
         Ok(unposted_count)
     }
@@ -758,17 +1116,58 @@ impl AccountingMutation {
             }
         }
 
+        let user_id = get_current_user(ctx).ok().map(|user| user.id);
+        for entry in &entries {
+            AccountingPeriodService::ensure_entry_open(db, entry, user_id)
+                .await
+                .map_err(|err| {
+                    async_graphql::Error::new(format!("{}: {}", entry.entry_number, err))
+                })?;
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in &entries {
+            let before = AuditLogService::journal_entry_snapshot(db, entry.id)
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+            snapshots.push((entry.id, entry.company_id, before));
+        }
+
+        let txn = db.begin().await?;
+
         // Delete associated entry lines first (foreign key constraint)
         entry_line::Entity::delete_many()
             .filter(entry_line::Column::JournalEntryId.is_in(ids.clone()))
-            .exec(db)
+            .exec(&txn)
             .await?;
 
         // Delete the journal entries
         let delete_result = journal_entry::Entity::delete_many()
             .filter(journal_entry::Column::Id.is_in(ids))
-            .exec(db)
+            .exec(&txn)
+            .await?;
+
+        // Release numbers from the highest down so trailing drafts free their whole range
+        let mut released = entries.clone();
+        released.sort_by_key(|entry| std::cmp::Reverse(entry.sequence_number));
+        for entry in &released {
+            JournalNumberingService::release(&txn, entry)
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        }
+
+        for (entry_id, company_id, before) in snapshots {
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Delete, "journal_entries", entry_id)
+                    .company(company_id)
+                    .before(&before),
+            )
             .await?;
+        }
+
+        txn.commit().await?;
 
         Ok(delete_result.rows_affected as i32)
     }
@@ -782,11 +1181,22 @@ impl AccountingMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
+        let txn = db.begin().await?;
         let counterpart_model = counterpart::ActiveModel::from(input);
         let counterpart = counterpart::Entity::insert(counterpart_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
 
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "counterparts", counterpart.id)
+                .company(counterpart.company_id)
+                .after(&counterpart),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(counterpart)
     }
 
@@ -800,11 +1210,11 @@ impl AccountingMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut counterpart_model: counterpart::ActiveModel = counterpart::Entity::find_by_id(id)
+        let existing = counterpart::Entity::find_by_id(id)
             .one(db)
             .await?
-            .ok_or_else(|| async_graphql::Error::new("Counterpart not found"))?
-            .into();
+            .ok_or_else(|| async_graphql::Error::new("Counterpart not found"))?;
+        let mut counterpart_model: counterpart::ActiveModel = existing.clone().into();
 
         let crate::entities::counterpart::UpdateCounterpartInput {
             name,
@@ -876,8 +1286,232 @@ impl AccountingMutation {
 
         counterpart_model.updated_at = Set(chrono::Utc::now());
 
-        let updated_counterpart = counterpart_model.update(db).await?;
+        let txn = db.begin().await?;
+        let updated_counterpart = counterpart_model.update(&txn).await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "counterparts", id)
+                .company(updated_counterpart.company_id)
+                .before(&existing)
+                .after(&updated_counterpart),
+        )
+        .await?;
+        txn.commit().await?;
 
         Ok(updated_counterpart)
     }
+
+    /// Open, soft-close or hard-close an accounting period
+    async fn set_accounting_period_status(
+        &self,
+        ctx: &Context<'_>,
+        input: SetAccountingPeriodStatusInput,
+    ) -> FieldResult<accounting_period::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        let user = require_company_admin(ctx, input.company_id).await?;
+
+        let txn = db.begin().await?;
+        let period = AccountingPeriodService::set_status(&txn, &input, Some(user.id))
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "accounting_periods", period.id)
+                .company(period.company_id)
+                .after(&period),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(period)
+    }
+
+    /// Set the prefix, padding or starting number of a numbering series
+    async fn configure_journal_entry_series(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfigureJournalEntrySeriesInput,
+    ) -> FieldResult<journal_entry_series::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_admin(ctx, input.company_id).await?;
+
+        let txn = db.begin().await?;
+        let series = JournalNumberingService::configure(&txn, &input)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "journal_entry_series", series.id)
+                .company(series.company_id)
+                .after(&series),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(series)
+    }
+
+    /// Renumber the draft entries of a series to close gaps left by deletions
+    async fn renumber_draft_entries(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        code: JournalSeriesKind,
+        year: i32,
+    ) -> FieldResult<i32> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_admin(ctx, company_id).await?;
+
+        let txn = db.begin().await?;
+        let renumbered = JournalNumberingService::renumber_drafts(&txn, company_id, code, year)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let series = JournalNumberingService::list(&txn, company_id, Some(year))
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?
+            .into_iter()
+            .find(|series| series.code == code);
+        if let Some(series) = series {
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Update, "journal_entry_series", series.id)
+                    .company(company_id)
+                    .after(&series),
+            )
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(renumbered)
+    }
+
+    /// Close a fiscal year and book the opening balances of the next one
+    async fn close_fiscal_year(
+        &self,
+        ctx: &Context<'_>,
+        input: YearEndClosingInput,
+    ) -> FieldResult<year_end_closing::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_admin(ctx, input.company_id).await?;
+
+        let txn = db.begin().await?;
+        let closing = YearEndClosingService::execute(&txn, &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "year_end_closings", closing.id)
+                .company(closing.company_id)
+                .after(&closing),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(closing)
+    }
+
+    /// Cancel the closing and opening entries of a fiscal year
+    async fn reverse_year_end_closing(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        fiscal_year: i32,
+    ) -> FieldResult<bool> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_admin(ctx, company_id).await?;
+
+        let txn = db.begin().await?;
+        let closing = YearEndClosingService::find(&txn, company_id, fiscal_year)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        YearEndClosingService::reverse(&txn, company_id, fiscal_year, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        if let Some(closing) = closing {
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Delete, "year_end_closings", closing.id)
+                    .company(company_id)
+                    .before(&closing),
+            )
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Revalue the open currency balances at the month-end rates
+    async fn revalue_foreign_currency_balances(
+        &self,
+        ctx: &Context<'_>,
+        input: FxRevaluationInput,
+    ) -> FieldResult<fx_revaluation::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_admin(ctx, input.company_id).await?;
+
+        let txn = db.begin().await?;
+        let revaluation = FxRevaluationService::execute(&txn, db.as_ref(), &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "fx_revaluations", revaluation.id)
+                .company(revaluation.company_id)
+                .after(&revaluation),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(revaluation)
+    }
+
+    /// Cancel the revaluation entry of a month with a storno entry
+    async fn reverse_fx_revaluation(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: i32,
+        month: i32,
+    ) -> FieldResult<bool> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_admin(ctx, company_id).await?;
+
+        let txn = db.begin().await?;
+        let revaluation = FxRevaluationService::find(&txn, company_id, year, month)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        FxRevaluationService::reverse(&txn, company_id, year, month, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        if let Some(revaluation) = revaluation {
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Delete, "fx_revaluations", revaluation.id)
+                    .company(company_id)
+                    .before(&revaluation),
+            )
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(true)
+    }
 }
@@ -7,6 +7,46 @@ use std::sync::Arc;
 use crate::entities::ai_bank_accounting_setting::{
     self, CreateAiBankAccountingSettingInput, UpdateAiBankAccountingSettingInput,
 };
+use crate::services::vat_return_calculation::operation_fields;
+
+/// A rule that splits off VAT needs the rate and a known VAT operation that
+/// fits its direction, otherwise the VAT is booked but never reported
+fn check_vat_setup(
+    direction: &str,
+    vat_account_id: Option<i32>,
+    vat_rate_id: Option<i32>,
+    vat_operation: Option<&str>,
+) -> FieldResult<()> {
+    if vat_account_id.is_none() {
+        return Ok(());
+    }
+    if vat_rate_id.is_none() {
+        return Err(async_graphql::Error::new(
+            "A rule with a VAT account needs a VAT rate",
+        ));
+    }
+    let operation = vat_operation.ok_or_else(|| {
+        async_graphql::Error::new("A rule with a VAT account needs a VAT operation")
+    })?;
+    if operation_fields(operation).is_none() {
+        return Err(async_graphql::Error::new(format!(
+            "Unknown VAT operation {}",
+            operation
+        )));
+    }
+    let fits_direction = match direction.to_lowercase().as_str() {
+        "debit" => operation.starts_with("пок"),
+        "credit" => operation.starts_with("про"),
+        _ => false,
+    };
+    if !fits_direction {
+        return Err(async_graphql::Error::new(format!(
+            "VAT operation {} does not fit a {} rule: debit rules report purchases (пок), credit rules sales (про)",
+            operation, direction
+        )));
+    }
+    Ok(())
+}
 
 #[derive(Default)]
 pub struct AiBankAccountingSettingsQuery;
@@ -81,6 +121,13 @@ impl AiBankAccountingSettingsMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
+        check_vat_setup(
+            input.direction.as_deref().unwrap_or("debit"),
+            input.vat_account_id,
+            input.vat_rate_id,
+            input.vat_operation.as_deref(),
+        )?;
+
         let settings_model = ai_bank_accounting_setting::ActiveModel::from(input);
         let settings = ai_bank_accounting_setting::Entity::insert(settings_model)
             .exec_with_returning(db)
@@ -100,14 +147,20 @@ impl AiBankAccountingSettingsMutation {
         let db = db.as_ref();
 
         // Find existing setting
-        let mut setting: ai_bank_accounting_setting::ActiveModel =
-            ai_bank_accounting_setting::Entity::find_by_id(id)
-                .one(db)
-                .await?
-                .ok_or_else(|| {
-                    async_graphql::Error::new("AI bank accounting setting not found")
-                })?
-                .into();
+        let existing = ai_bank_accounting_setting::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| async_graphql::Error::new("AI bank accounting setting not found"))?;
+        check_vat_setup(
+            input.direction.as_deref().unwrap_or(&existing.direction),
+            input.vat_account_id.or(existing.vat_account_id),
+            input.vat_rate_id.or(existing.vat_rate_id),
+            input
+                .vat_operation
+                .as_deref()
+                .or(existing.vat_operation.as_deref()),
+        )?;
+        let mut setting: ai_bank_accounting_setting::ActiveModel = existing.into();
 
         // Update fields
         if let Some(pattern_name) = input.pattern_name {
@@ -128,6 +181,12 @@ impl AiBankAccountingSettingsMutation {
         if let Some(vat_account_id) = input.vat_account_id {
             setting.vat_account_id = Set(Some(vat_account_id));
         }
+        if let Some(vat_rate_id) = input.vat_rate_id {
+            setting.vat_rate_id = Set(Some(vat_rate_id));
+        }
+        if let Some(vat_operation) = input.vat_operation {
+            setting.vat_operation = Set(Some(vat_operation));
+        }
         if let Some(direction) = input.direction {
             setting.direction = Set(direction);
         }
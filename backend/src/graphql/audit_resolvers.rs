@@ -0,0 +1,91 @@
+use async_graphql::{Context, FieldResult, Object};
+use base64::Engine;
+use sea_orm::{ConnectionTrait, DatabaseConnection};
+use std::sync::Arc;
+
+use crate::entities::audit_log::{self, AuditLogFilter};
+use crate::graphql::context::{get_current_user, require_company_admin};
+use crate::graphql::reports_resolvers::ReportExport;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+
+/// Append `event` to the audit trail on behalf of the current user. Pass the
+/// transaction of the change, so that a change is never committed without its
+/// audit entry.
+pub(crate) async fn record_audit<C: ConnectionTrait>(
+    ctx: &Context<'_>,
+    db: &C,
+    event: AuditEvent,
+) -> FieldResult<()> {
+    let user_id = get_current_user(ctx).ok().map(|user| user.id);
+
+    AuditLogService::record(db, user_id, event)
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct AuditQuery;
+
+#[Object]
+impl AuditQuery {
+    /// Audit trail of a company, oldest first
+    async fn audit_logs(
+        &self,
+        ctx: &Context<'_>,
+        filter: AuditLogFilter,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> FieldResult<Vec<audit_log::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_admin(ctx, filter.company_id).await?;
+
+        let logs = AuditLogService::list(db.as_ref(), &filter, limit, offset)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(logs)
+    }
+}
+
+#[derive(Default)]
+pub struct AuditMutation;
+
+#[Object]
+impl AuditMutation {
+    /// Export the audit trail for a tax inspection
+    async fn export_audit_log(
+        &self,
+        ctx: &Context<'_>,
+        filter: AuditLogFilter,
+        format: String, // "CSV", "XLSX"
+    ) -> FieldResult<ReportExport> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_admin(ctx, filter.company_id).await?;
+
+        let logs = AuditLogService::list(db.as_ref(), &filter, None, None)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let (content, mime_type) = match format.to_uppercase().as_str() {
+            "CSV" => (AuditLogService::export_csv(&logs), "text/csv"),
+            "XLSX" => (
+                AuditLogService::export_xlsx(&logs),
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            _ => return Err("Unsupported format. Use CSV or XLSX".into()),
+        };
+        let content = content.map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        Ok(ReportExport {
+            format: format.to_uppercase(),
+            content: base64::prelude::BASE64_STANDARD.encode(&content),
+            filename: format!(
+                "audit_log_{}_{}.{}",
+                filter.company_id,
+                chrono::Utc::now().format("%Y%m%d%H%M%S"),
+                format.to_lowercase()
+            ),
+            mime_type: mime_type.to_string(),
+        })
+    }
+}
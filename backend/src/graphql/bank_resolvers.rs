@@ -5,17 +5,29 @@ use chrono::{NaiveDate, TimeZone, Utc};
 use rust_decimal::Decimal;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    QueryOrder, QuerySelect, Set,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use std::{convert::TryFrom, sync::Arc};
 
+use crate::entities::audit_log::AuditAction;
+use crate::entities::bank_reconciliation_match::ConfirmBankReconciliationInput;
 use crate::entities::{
-    bank_import, bank_profile, counterpart, entry_line, BankImportModel, BankImportStatus,
-    BankProfileActiveModel, BankProfileModel, CreateBankProfileInput, UpdateBankProfileInput,
+    bank_import, bank_profile, counterpart, entry_line, payment_batch, BankImportModel,
+    BankImportStatus, BankProfileActiveModel, BankProfileModel, BankReconciliationMatchModel,
+    CreateBankProfileInput, CreatePaymentBatchInput, PaymentBatchModel, PaymentBatchStatus,
+    PaymentOrderModel, UpdateBankProfileInput,
+};
+use crate::graphql::audit_resolvers::record_audit;
+use crate::graphql::context::{get_current_user, require_company_access};
+use crate::services::audit_log::AuditEvent;
+use crate::services::bank_csv_mapping::{CsvMapping, CsvMappingPreview};
+use crate::services::bank_imports::{BankImportService, ImportSummary, StatementPreview};
+use crate::services::bank_reconciliation::{
+    BankReconciliationService, OpenInvoice, ReconciliationProposal,
 };
-use crate::services::bank_imports::{BankImportService, ImportSummary};
 use crate::services::bank_transaction_parser::{BankTransactionParser, ParsedTransactionData};
 use crate::services::contragent::ContragentService;
+use crate::services::payment_orders::{PaymentFile, PaymentOrderService, SupplierBalance};
 
 #[derive(Default)]
 pub struct BankQuery;
@@ -157,6 +169,131 @@ impl BankQuery {
 
         Ok(filtered_imports)
     }
+
+    /// Parse a statement without booking it and mark the transactions that
+    /// an earlier import of the profile already booked
+    async fn preview_bank_statement(
+        &self,
+        ctx: &Context<'_>,
+        input: PreviewBankStatementInput,
+    ) -> FieldResult<BankStatementPreviewPayload> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let profile = bank_profile::Entity::find_by_id(input.bank_profile_id)
+            .one(db)
+            .await?
+            .ok_or("Банковият профил не е намерен")?;
+
+        let file_bytes = decode_bank_document(&input.file_base64)?;
+        if file_bytes.is_empty() {
+            return Err("Файлът е празен".into());
+        }
+
+        let preview = BankImportService::preview_statement(db, &profile, &file_bytes)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        Ok(BankStatementPreviewPayload::from(preview))
+    }
+
+    /// Неразнесените редове от буферната сметка на профила с предложения
+    /// за фактурите, които плащат
+    async fn bank_reconciliation_proposals(
+        &self,
+        ctx: &Context<'_>,
+        bank_profile_id: i32,
+    ) -> FieldResult<Vec<ReconciliationProposal>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let profile = bank_profile::Entity::find_by_id(bank_profile_id)
+            .one(db)
+            .await?
+            .ok_or("Банковият профил не е намерен")?;
+        require_company_access(ctx, profile.company_id).await?;
+
+        let proposals = BankReconciliationService::proposals(db, profile.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(proposals)
+    }
+
+    /// Фактури по 411/401 с неплатен остатък
+    async fn open_invoices(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        counterpart_id: Option<i32>,
+    ) -> FieldResult<Vec<OpenInvoice>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let invoices = BankReconciliationService::open_invoices(db, company_id, counterpart_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(invoices)
+    }
+
+    /// Доставчици с кредитно салдо по 401 и неплатените им фактури
+    async fn supplier_payment_balances(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+    ) -> FieldResult<Vec<SupplierBalance>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let balances = PaymentOrderService::supplier_balances(db, company_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(balances)
+    }
+
+    async fn payment_batches(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        status: Option<PaymentBatchStatus>,
+    ) -> FieldResult<Vec<PaymentBatchModel>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let mut query = payment_batch::Entity::find()
+            .filter(payment_batch::Column::CompanyId.eq(company_id))
+            .order_by_desc(payment_batch::Column::CreatedAt);
+        if let Some(status) = status {
+            query = query.filter(payment_batch::Column::Status.eq(status));
+        }
+
+        let batches = query.all(db).await?;
+        Ok(batches)
+    }
+
+    async fn payment_batch(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        id: i32,
+    ) -> FieldResult<PaymentBatchPayload> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let (batch, orders) = PaymentOrderService::batch(db, company_id, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(PaymentBatchPayload { batch, orders })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct PaymentBatchPayload {
+    pub batch: PaymentBatchModel,
+    pub orders: Vec<PaymentOrderModel>,
 }
 
 #[derive(Default)]
@@ -217,6 +354,9 @@ impl BankMutation {
         if let Some(iban_opt) = input.iban {
             active.iban = Set(iban_opt);
         }
+        if let Some(bic_opt) = input.bic {
+            active.bic = Set(bic_opt.map(|bic| bic.trim().to_uppercase()));
+        }
         if let Some(account_id) = input.account_id {
             active.account_id = Set(account_id);
         }
@@ -275,12 +415,23 @@ impl BankMutation {
             return Err("Файлът е празен".into());
         }
 
+        let created_by = get_current_user(ctx)
+            .ok()
+            .map(|user| user.id)
+            .or(input.created_by);
+        let skip_indexes: Vec<usize> = input
+            .skip_transaction_indexes
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| usize::try_from(index).ok())
+            .collect();
         let summary = BankImportService::import_statement(
             db,
             &profile,
             file_name,
             &file_bytes,
-            input.created_by,
+            created_by,
+            &skip_indexes,
         )
         .await
         .map_err(|err| async_graphql::Error::new(err.to_string()))?;
@@ -328,6 +479,184 @@ impl BankMutation {
         Ok(record)
     }
 
+    /// Разнася банкова транзакция от буферната сметка към 411/401
+    async fn confirm_bank_reconciliation(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfirmBankReconciliationInput,
+    ) -> FieldResult<Vec<BankReconciliationMatchModel>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_access(ctx, input.company_id).await?;
+
+        let matches = BankReconciliationService::confirm(db.as_ref(), &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(matches)
+    }
+
+    /// Връща разнесените суми на банкова транзакция в буферната сметка
+    async fn undo_bank_reconciliation(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        bank_entry_id: i32,
+    ) -> FieldResult<bool> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_access(ctx, company_id).await?;
+
+        BankReconciliationService::undo(db.as_ref(), company_id, bank_entry_id, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(true)
+    }
+
+    /// Показва първите редове на CSV файл, прочетени с шаблона за колоните,
+    /// за да се поправи шаблонът преди импорта. Без `mapping` се ползва
+    /// шаблонът от настройките на профила.
+    async fn preview_csv_mapping(
+        &self,
+        ctx: &Context<'_>,
+        input: PreviewCsvMappingInput,
+    ) -> FieldResult<CsvMappingPreview> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let profile = bank_profile::Entity::find_by_id(input.bank_profile_id)
+            .one(db)
+            .await?
+            .ok_or("Банковият профил не е намерен")?;
+        require_company_access(ctx, profile.company_id).await?;
+
+        let file_bytes = decode_bank_document(&input.file_base64)?;
+        if file_bytes.is_empty() {
+            return Err("Файлът е празен".into());
+        }
+
+        let mapping = match input.mapping.as_ref() {
+            Some(template) => CsvMapping::from_value(template),
+            None => CsvMapping::from_settings(profile.settings.as_ref()),
+        }
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        let rows = input.rows.unwrap_or(10).clamp(1, 100) as usize;
+
+        let preview = mapping
+            .decode(&file_bytes)
+            .and_then(|content| mapping.preview(&content, &profile.currency_code, rows))
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(preview)
+    }
+
+    /// Създава пакет от платежни нареждания към доставчици или към бюджета
+    async fn create_payment_batch(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePaymentBatchInput,
+    ) -> FieldResult<PaymentBatchPayload> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_access(ctx, input.company_id).await?;
+
+        let txn = db.begin().await?;
+        let (batch, orders) = PaymentOrderService::create_batch(&txn, &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "payment_batches", batch.id)
+                .company(batch.company_id)
+                .after(&batch),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(PaymentBatchPayload { batch, orders })
+    }
+
+    async fn delete_payment_batch(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        id: i32,
+    ) -> FieldResult<bool> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_access(ctx, company_id).await?;
+
+        let txn = db.begin().await?;
+        let batch = PaymentOrderService::delete_batch(&txn, company_id, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Delete, "payment_batches", batch.id)
+                .company(company_id)
+                .before(&batch),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Генерира pain.001 файл за банката и маркира пакета като експортиран
+    async fn export_payment_batch(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        id: i32,
+    ) -> FieldResult<PaymentFile> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_access(ctx, company_id).await?;
+
+        let txn = db.begin().await?;
+        let (batch, file) = PaymentOrderService::export(&txn, company_id, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "payment_batches", batch.id)
+                .company(company_id)
+                .after(&batch),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(file)
+    }
+
+    /// Търси нарежданията от пакета в импортираните извлечения и разнася
+    /// платените суми по фактурите на доставчиците
+    async fn match_payment_batch(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        id: i32,
+    ) -> FieldResult<PaymentBatchPayload> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_access(ctx, company_id).await?;
+
+        let txn = db.begin().await?;
+        let (batch, orders) = PaymentOrderService::match_statement(&txn, company_id, id, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "payment_batches", batch.id)
+                .company(company_id)
+                .after(&batch),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(PaymentBatchPayload { batch, orders })
+    }
+
     /// Парсва описание на банкова транзакция и извлича контрагент с AI
     async fn parse_bank_transaction_description(
         &self,
@@ -401,6 +730,27 @@ pub struct ImportBankStatementInput {
     /// Base64 съдържание на файла. Поддържа се и data URI (`data:<mime>;base64,....`).
     pub file_base64: String,
     pub created_by: Option<i32>,
+    /// Индекси от прегледа на извлечението, които да не се осчетоводяват.
+    /// Вече импортирани транзакции се пропускат винаги.
+    pub skip_transaction_indexes: Option<Vec<i32>>,
+}
+
+#[derive(InputObject)]
+pub struct PreviewBankStatementInput {
+    pub bank_profile_id: i32,
+    /// Base64 съдържание на файла. Поддържа се и data URI (`data:<mime>;base64,....`).
+    pub file_base64: String,
+}
+
+#[derive(InputObject)]
+pub struct PreviewCsvMappingInput {
+    pub bank_profile_id: i32,
+    /// Base64 съдържание на файла. Поддържа се и data URI (`data:<mime>;base64,....`).
+    pub file_base64: String,
+    /// Шаблон за колоните (като `csvMapping` в настройките на профила)
+    pub mapping: Option<serde_json::Value>,
+    /// Брой редове за показване, по подразбиране 10
+    pub rows: Option<i32>,
 }
 
 #[derive(SimpleObject)]
@@ -410,6 +760,12 @@ pub struct BankImportSummaryPayload {
     pub total_debit: Decimal,
     pub total_credit: Decimal,
     pub journal_entry_ids: Vec<i32>,
+    /// Транзакции, пропуснати като вече импортирани
+    pub skipped_duplicates: i32,
+    /// Транзакции, осчетоводени по банково правило вместо в буферната сметка
+    pub auto_posted: i32,
+    /// Предишни импорти със застъпващ се период
+    pub overlapping_import_ids: Vec<i32>,
 }
 
 impl From<ImportSummary> for BankImportSummaryPayload {
@@ -419,6 +775,9 @@ impl From<ImportSummary> for BankImportSummaryPayload {
             journal_entry_ids,
             total_debit,
             total_credit,
+            skipped_duplicates,
+            auto_posted,
+            overlapping_import_ids,
             bank_import,
         } = summary;
 
@@ -430,6 +789,90 @@ impl From<ImportSummary> for BankImportSummaryPayload {
             total_debit,
             total_credit,
             journal_entry_ids,
+            skipped_duplicates: i32::try_from(skipped_duplicates).unwrap_or(i32::MAX),
+            auto_posted: i32::try_from(auto_posted).unwrap_or(i32::MAX),
+            overlapping_import_ids,
+        }
+    }
+}
+
+/// Транзакция от прегледа на банково извлечение
+#[derive(SimpleObject)]
+pub struct BankStatementPreviewLine {
+    pub index: i32,
+    pub booking_date: NaiveDate,
+    pub value_date: Option<NaiveDate>,
+    pub amount: Decimal,
+    pub currency: String,
+    pub is_credit: bool,
+    pub description: String,
+    pub reference: Option<String>,
+    pub counterparty_iban: Option<String>,
+    pub fingerprint: String,
+    pub is_duplicate: bool,
+    /// Статия, с която транзакцията вече е осчетоводена
+    pub duplicate_journal_entry_id: Option<i32>,
+    /// Банково правило, по което ще се осчетоводи транзакцията
+    pub rule_id: Option<i32>,
+    pub rule_name: Option<String>,
+    /// Сметка по правилото; без правило се използва буферната сметка
+    pub account_id: Option<i32>,
+    pub vat_account_id: Option<i32>,
+    pub vat_amount: Decimal,
+}
+
+#[derive(SimpleObject)]
+pub struct BankStatementPreviewPayload {
+    pub statement_from: Option<NaiveDate>,
+    pub statement_to: Option<NaiveDate>,
+    pub transactions: Vec<BankStatementPreviewLine>,
+    pub duplicate_count: i32,
+    /// Предишни импорти на профила, чийто период се застъпва с извлечението
+    pub overlapping_imports: Vec<BankImportModel>,
+}
+
+impl From<StatementPreview> for BankStatementPreviewPayload {
+    fn from(preview: StatementPreview) -> Self {
+        let duplicate_count = i32::try_from(preview.duplicate_count()).unwrap_or(i32::MAX);
+        let transactions = preview
+            .transactions
+            .into_iter()
+            .map(|previewed| {
+                let posting = previewed.rule.as_ref().map(|rule| {
+                    BankImportService::rule_posting(
+                        rule,
+                        previewed.vat_rate.as_ref(),
+                        previewed.transaction.amount.abs(),
+                    )
+                });
+                BankStatementPreviewLine {
+                    index: i32::try_from(previewed.index).unwrap_or(i32::MAX),
+                    booking_date: previewed.transaction.booking_date,
+                    value_date: previewed.transaction.value_date,
+                    amount: previewed.transaction.amount,
+                    currency: previewed.transaction.currency,
+                    is_credit: previewed.transaction.is_credit,
+                    description: previewed.transaction.description,
+                    reference: previewed.transaction.reference,
+                    counterparty_iban: previewed.fingerprint.counterparty_iban,
+                    fingerprint: previewed.fingerprint.fingerprint,
+                    is_duplicate: previewed.duplicate_of.is_some(),
+                    duplicate_journal_entry_id: previewed.duplicate_of,
+                    rule_id: previewed.rule.as_ref().map(|rule| rule.id),
+                    rule_name: previewed.rule.map(|rule| rule.pattern_name),
+                    account_id: posting.as_ref().map(|posting| posting.account_id),
+                    vat_account_id: posting.as_ref().and_then(|posting| posting.vat_account_id),
+                    vat_amount: posting.map_or(Decimal::ZERO, |posting| posting.vat_amount),
+                }
+            })
+            .collect();
+
+        Self {
+            statement_from: preview.statement_from,
+            statement_to: preview.statement_to,
+            transactions,
+            duplicate_count,
+            overlapping_imports: preview.overlapping_imports,
         }
     }
 }
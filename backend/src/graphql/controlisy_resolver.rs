@@ -108,6 +108,7 @@ impl ControlisyMutation {
             deklar_content: BASE64.encode(&vies_files.deklar),
             pokupki_content: BASE64.encode(&vies_files.pokupki),
             prodagbi_content: BASE64.encode(&vies_files.prodagbi),
+            vies_content: vies_files.vies.map(|vies| BASE64.encode(vies)),
         })
     }
 }
@@ -155,4 +156,6 @@ pub struct VatFilesResult {
     pub deklar_content: String,
     pub pokupki_content: String,
     pub prodagbi_content: String,
+    /// VIES.TXT; missing when the period has no intra-community supplies
+    pub vies_content: Option<String>,
 }
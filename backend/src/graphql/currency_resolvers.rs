@@ -1,4 +1,4 @@
-use async_graphql::{Context, FieldResult, Object};
+use async_graphql::{Context, FieldResult, Object, SimpleObject};
 use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use sea_orm::{
@@ -11,13 +11,57 @@ use std::sync::Arc;
 use crate::entities::currency::{
     CreateCurrencyInput, CurrencyFilter, CurrencyWithRate, UpdateCurrencyInput,
 };
+use crate::entities::euro_changeover::EuroChangeoverInput;
 use crate::entities::exchange_rate::{
     CreateExchangeRateInput, CurrencyConversion, ExchangeRateFilter, ExchangeRateWithCurrencies,
     RateSource, UpdateExchangeRateInput,
 };
-use crate::entities::{currency, exchange_rate};
+use crate::entities::{currency, euro_changeover, exchange_rate};
+use crate::graphql::context::{require_company_access, require_company_admin};
 use crate::services::bnb_service::BnbService;
 use crate::services::ecb_service::EcbService;
+use crate::services::euro_changeover::{
+    ChangeoverPlan, EuroChangeoverService, LEGACY_BASE_CURRENCY,
+};
+
+/// Balance restated by the euro changeover
+#[derive(SimpleObject)]
+pub struct EuroChangeoverLine {
+    pub account_id: i32,
+    pub account_code: String,
+    pub counterpart_id: Option<i32>,
+    pub balance_bgn: Decimal,
+    pub balance_eur: Decimal,
+    pub adjustment: Decimal,
+}
+
+#[derive(SimpleObject)]
+pub struct EuroChangeoverPreview {
+    pub exchange_rate: Decimal,
+    pub lines: Vec<EuroChangeoverLine>,
+    pub rounding_difference: Decimal,
+}
+
+impl From<ChangeoverPlan> for EuroChangeoverPreview {
+    fn from(plan: ChangeoverPlan) -> Self {
+        Self {
+            exchange_rate: crate::services::euro_changeover::fixed_rate(),
+            lines: plan
+                .balances
+                .into_iter()
+                .map(|balance| EuroChangeoverLine {
+                    adjustment: balance.adjustment(),
+                    account_id: balance.account_id,
+                    account_code: balance.account_code,
+                    counterpart_id: balance.counterpart_id,
+                    balance_bgn: balance.balance_bgn,
+                    balance_eur: balance.balance_eur,
+                })
+                .collect(),
+            rounding_difference: plan.rounding_difference,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct CurrencyQuery;
@@ -101,11 +145,17 @@ impl CurrencyQuery {
         Ok(currency)
     }
 
-    /// Get currencies with their latest exchange rates
-    async fn currencies_with_rates(&self, ctx: &Context<'_>) -> FieldResult<Vec<CurrencyWithRate>> {
+    /// Get currencies with their latest exchange rates against the base currency,
+    /// which is the company's base currency today when a company is given
+    async fn currencies_with_rates(
+        &self,
+        ctx: &Context<'_>,
+        company_id: Option<i32>,
+    ) -> FieldResult<Vec<CurrencyWithRate>> {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
         let bnb_service = BnbService::new();
+        let today = chrono::Utc::now().date_naive();
 
         let currencies = currency::Entity::find()
             .filter(currency::Column::IsActive.eq(true))
@@ -113,41 +163,61 @@ impl CurrencyQuery {
             .all(db)
             .await?;
 
-        let bgn_currency = currency::Entity::find()
-            .filter(currency::Column::Code.eq("BGN"))
-            .one(db)
-            .await?
-            .ok_or("BGN currency not found")?;
+        let base_code = match company_id {
+            Some(company_id) => {
+                require_company_access(ctx, company_id).await?;
+                EuroChangeoverService::base_currency_code(db, company_id, today)
+                    .await
+                    .map_err(|err| async_graphql::Error::new(err.to_string()))?
+            }
+            None => currencies
+                .iter()
+                .find(|curr| curr.is_base_currency)
+                .map(|curr| curr.code.clone())
+                .unwrap_or_else(|| LEGACY_BASE_CURRENCY.to_string()),
+        };
+        let base_currency = currencies
+            .iter()
+            .find(|curr| curr.code == base_code)
+            .cloned()
+            .ok_or_else(|| format!("{} currency not found", base_code))?;
 
         let mut results = Vec::new();
 
         for curr in currencies {
-            if curr.is_base_currency {
+            if curr.id == base_currency.id {
                 results.push(CurrencyWithRate {
                     currency: curr,
                     latest_rate: Some(Decimal::ONE),
-                    rate_date: Some(chrono::Utc::now().date_naive()),
+                    rate_date: Some(today),
                     rate_source: Some("BASE".to_string()),
                 });
+            } else if let Some(rate) =
+                EuroChangeoverService::fixed_conversion_rate(&curr.code, &base_currency.code)
+            {
+                results.push(CurrencyWithRate {
+                    currency: curr,
+                    latest_rate: Some(rate),
+                    rate_date: Some(today),
+                    rate_source: Some("FIXED".to_string()),
+                });
+            } else if let Ok(Some(rate)) = bnb_service
+                .get_latest_rate(db, curr.id, base_currency.id)
+                .await
+            {
+                results.push(CurrencyWithRate {
+                    currency: curr,
+                    latest_rate: Some(rate.rate),
+                    rate_date: Some(rate.valid_date),
+                    rate_source: Some(rate.get_rate_description()),
+                });
             } else {
-                if let Ok(Some(rate)) = bnb_service
-                    .get_latest_rate(db, curr.id, bgn_currency.id)
-                    .await
-                {
-                    results.push(CurrencyWithRate {
-                        currency: curr,
-                        latest_rate: Some(rate.rate),
-                        rate_date: Some(rate.valid_date),
-                        rate_source: Some(rate.get_rate_description()),
-                    });
-                } else {
-                    results.push(CurrencyWithRate {
-                        currency: curr,
-                        latest_rate: None,
-                        rate_date: None,
-                        rate_source: None,
-                    });
-                }
+                results.push(CurrencyWithRate {
+                    currency: curr,
+                    latest_rate: None,
+                    rate_date: None,
+                    rate_source: None,
+                });
             }
         }
 
@@ -332,6 +402,40 @@ impl CurrencyQuery {
 
         Ok(results)
     }
+
+    /// Euro changeover of a company, if it has been performed
+    async fn euro_changeover(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+    ) -> FieldResult<Option<euro_changeover::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let changeover = euro_changeover::Entity::find()
+            .filter(euro_changeover::Column::CompanyId.eq(company_id))
+            .one(db)
+            .await?;
+        Ok(changeover)
+    }
+
+    /// Balances that a euro changeover at the given cut-off date would restate
+    async fn euro_changeover_preview(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        cutoff_date: NaiveDate,
+    ) -> FieldResult<EuroChangeoverPreview> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        require_company_access(ctx, company_id).await?;
+
+        let plan = EuroChangeoverService::preview(db, company_id, cutoff_date)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(plan.into())
+    }
 }
 
 #[derive(Default)]
@@ -339,6 +443,22 @@ pub struct CurrencyMutation;
 
 #[Object]
 impl CurrencyMutation {
+    /// Convert a company's books from BGN to EUR as of the day after the cut-off date
+    async fn perform_euro_changeover(
+        &self,
+        ctx: &Context<'_>,
+        input: EuroChangeoverInput,
+    ) -> FieldResult<euro_changeover::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+        let user = require_company_admin(ctx, input.company_id).await?;
+
+        let changeover = EuroChangeoverService::execute(db, &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        Ok(changeover)
+    }
+
     /// Create a new currency
     async fn create_currency(
         &self,
@@ -3,19 +3,38 @@
 //! Handles CRUD operations for fixed assets, categories, and depreciation
 
 use async_graphql::{Context, FieldResult, InputObject, Object, SimpleObject};
-use chrono::{Datelike, NaiveDate};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{Datelike, NaiveDate, Utc};
 use rust_decimal::Decimal;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    QueryOrder, QuerySelect, Set,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use std::sync::Arc;
 
+use crate::entities::audit_log::AuditAction;
+use crate::entities::fixed_asset::{DEPRECIATION_METHODS, START_CONVENTIONS};
+use crate::entities::fixed_asset_event::{self, FixedAssetEventInput};
+use crate::entities::fixed_asset_usage::{self, FixedAssetUsageInput};
 use crate::entities::{
     depreciation_journal, fixed_asset, fixed_asset_category, DepreciationJournal, FixedAsset,
-    FixedAssetCategory,
+    FixedAssetCategory, FixedAssetUsage,
+};
+use crate::graphql::audit_resolvers::record_audit;
+use crate::graphql::context::require_company_access;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+use crate::services::depreciation_service::{
+    DepreciationService, MonthlyDepreciation, ProjectedDepreciation,
+};
+use crate::services::fixed_asset_import::{
+    FixedAssetImportMapping, FixedAssetImportPreview, FixedAssetImportResult,
+    FixedAssetImportService,
+};
+use crate::services::fixed_asset_lifecycle::FixedAssetLifecycleService;
+use crate::services::temporary_differences::{
+    TemporaryDifferencesReport, TemporaryDifferencesService,
 };
-use crate::services::depreciation_service::{DepreciationService, MonthlyDepreciation};
 
 // Input Types
 #[derive(InputObject)]
@@ -30,6 +49,10 @@ pub struct CreateFixedAssetInput {
     pub accounting_useful_life: i32,
     pub accounting_depreciation_rate: Decimal,
     pub accounting_depreciation_method: Option<String>,
+    /// next_month (default), full_month or daily
+    pub depreciation_start_convention: Option<String>,
+    /// Required for units_of_production
+    pub production_capacity: Option<Decimal>,
     pub accounting_salvage_value: Option<Decimal>,
     pub tax_useful_life: Option<i32>,
     pub tax_depreciation_rate: Decimal,
@@ -98,6 +121,26 @@ pub struct PostDepreciationInput {
     pub reference: Option<String>,
 }
 
+#[derive(InputObject)]
+pub struct ReverseDepreciationInput {
+    pub company_id: i32,
+    pub year: i32,
+    pub month: u32,
+}
+
+#[derive(InputObject)]
+pub struct ImportFixedAssetsInput {
+    pub company_id: i32,
+    /// CSV or XLSX file name
+    pub file_name: String,
+    /// Base64 content of the file; a data URI (`data:<mime>;base64,....`) is accepted too
+    pub file_base64: String,
+    /// Column mapping of the register (inventoryNumberColumn, nameColumn, ...)
+    pub mapping: serde_json::Value,
+    /// Month-end up to which the accumulated depreciation in the file is counted
+    pub opening_balance_date: Option<NaiveDate>,
+}
+
 // Output Types
 #[derive(SimpleObject)]
 pub struct FixedAssetWithCategory {
@@ -126,6 +169,19 @@ pub struct DepreciationPostingResult {
     pub message: String,
 }
 
+#[derive(SimpleObject)]
+pub struct DepreciationReversalResult {
+    pub success: bool,
+    /// Journal entries of the run, cancelled by a storno or deleted
+    pub journal_entry_ids: Vec<i32>,
+    /// Storno entries booked for the posted ones
+    pub storno_entry_ids: Vec<i32>,
+    pub accounting_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub assets_count: i32,
+    pub message: String,
+}
+
 #[derive(SimpleObject)]
 pub struct FixedAssetSummary {
     pub total_assets: i32,
@@ -305,7 +361,7 @@ impl FixedAssetsQuery {
 
             match asset.status.as_str() {
                 "active" => active_assets += 1,
-                "disposed" | "sold" => disposed_assets += 1,
+                "disposed" | "sold" | "written_off" => disposed_assets += 1,
                 _ => {}
             }
         }
@@ -368,6 +424,87 @@ impl FixedAssetsQuery {
         Ok(result)
     }
 
+    /// Get the lifecycle events of an asset
+    async fn fixed_asset_events(
+        &self,
+        ctx: &Context<'_>,
+        fixed_asset_id: i32,
+    ) -> FieldResult<Vec<fixed_asset_event::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+
+        let asset = FixedAsset::find_by_id(fixed_asset_id)
+            .one(db.as_ref())
+            .await?
+            .ok_or("Asset not found")?;
+        require_company_access(ctx, asset.company_id).await?;
+
+        FixedAssetLifecycleService::events(db.as_ref(), fixed_asset_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
+    /// Get the monthly usage entered for a units-of-production asset
+    async fn fixed_asset_usages(
+        &self,
+        ctx: &Context<'_>,
+        fixed_asset_id: i32,
+    ) -> FieldResult<Vec<fixed_asset_usage::Model>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+
+        let asset = FixedAsset::find_by_id(fixed_asset_id)
+            .one(db.as_ref())
+            .await?
+            .ok_or("Asset not found")?;
+        require_company_access(ctx, asset.company_id).await?;
+
+        let usages = FixedAssetUsage::find()
+            .filter(fixed_asset_usage::Column::FixedAssetId.eq(fixed_asset_id))
+            .order_by_asc(fixed_asset_usage::Column::Period)
+            .all(db.as_ref())
+            .await?;
+
+        Ok(usages)
+    }
+
+    /// Project the monthly depreciation of an asset in both books until it is fully depreciated
+    async fn projected_depreciation_schedule(
+        &self,
+        ctx: &Context<'_>,
+        fixed_asset_id: i32,
+    ) -> FieldResult<Vec<ProjectedDepreciation>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let service = ctx.data::<Arc<DepreciationService>>()?;
+
+        let asset = FixedAsset::find_by_id(fixed_asset_id)
+            .one(db.as_ref())
+            .await?
+            .ok_or("Asset not found")?;
+        require_company_access(ctx, asset.company_id).await?;
+
+        let schedule = service
+            .projected_schedule(db.as_ref(), fixed_asset_id)
+            .await?;
+
+        Ok(schedule)
+    }
+
+    /// Get the annual report of temporary differences and deferred tax for the ЗКПО return
+    async fn fixed_asset_temporary_differences(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: i32,
+        #[graphql(desc = "Tax rate in percent; defaults to the corporate tax rate")]
+        tax_rate: Option<Decimal>,
+    ) -> FieldResult<TemporaryDifferencesReport> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_access(ctx, company_id).await?;
+
+        TemporaryDifferencesService::report(db.as_ref(), company_id, year, tax_rate)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
     /// Get depreciation status for an asset
     async fn asset_depreciation_status(
         &self,
@@ -446,6 +583,28 @@ impl FixedAssetsMutation {
     ) -> FieldResult<fixed_asset::Model> {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
 
+        let method = input
+            .accounting_depreciation_method
+            .unwrap_or_else(|| "straight_line".to_string());
+        if !DEPRECIATION_METHODS.contains(&method.as_str()) {
+            return Err(format!("Unsupported depreciation method: {}", method).into());
+        }
+        let convention = input
+            .depreciation_start_convention
+            .unwrap_or_else(|| "next_month".to_string());
+        if !START_CONVENTIONS.contains(&convention.as_str()) {
+            return Err(
+                format!("Unsupported depreciation start convention: {}", convention).into(),
+            );
+        }
+        if method == "units_of_production"
+            && input
+                .production_capacity
+                .is_none_or(|capacity| capacity <= Decimal::ZERO)
+        {
+            return Err("Units-of-production depreciation requires a production capacity".into());
+        }
+
         // Calculate initial book values
         let accounting_book_value = input.acquisition_cost;
         let tax_book_value = input.acquisition_cost;
@@ -461,9 +620,9 @@ impl FixedAssetsMutation {
             put_into_service_date: Set(input.put_into_service_date),
             accounting_useful_life: Set(input.accounting_useful_life),
             accounting_depreciation_rate: Set(input.accounting_depreciation_rate),
-            accounting_depreciation_method: Set(input
-                .accounting_depreciation_method
-                .unwrap_or_else(|| "straight_line".to_string())),
+            accounting_depreciation_method: Set(method),
+            depreciation_start_convention: Set(convention),
+            production_capacity: Set(input.production_capacity),
             accounting_salvage_value: Set(input
                 .accounting_salvage_value
                 .unwrap_or_else(|| Decimal::from(0))),
@@ -471,6 +630,7 @@ impl FixedAssetsMutation {
             tax_useful_life: Set(input.tax_useful_life),
             tax_depreciation_rate: Set(input.tax_depreciation_rate),
             tax_accumulated_depreciation: Set(Decimal::from(0)),
+            tax_depreciable_value: Set(input.acquisition_cost),
             is_new_first_time_investment: Set(input.is_new_first_time_investment.unwrap_or(false)),
             accounting_book_value: Set(accounting_book_value),
             tax_book_value: Set(tax_book_value),
@@ -612,26 +772,38 @@ impl FixedAssetsMutation {
         input: CalculateDepreciationInput,
     ) -> FieldResult<DepreciationCalculationResult> {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_access(ctx, input.company_id).await?;
         let service = DepreciationService::new();
 
         let period =
             NaiveDate::from_ymd_opt(input.year, input.month, 1).ok_or("Invalid year/month")?;
 
+        let txn = db.begin().await?;
         let bulk_result = service
-            .calculate_bulk_depreciation(db.as_ref(), input.company_id, period)
+            .calculate_bulk_depreciation(&txn, input.company_id, period)
             .await?;
 
         // Save calculated depreciation
         let mut saved_count = 0;
         for depreciation in bulk_result.calculated {
-            match service
-                .save_depreciation(db.as_ref(), depreciation, input.company_id)
-                .await
-            {
-                Ok(_) => saved_count += 1,
-                Err(e) => eprintln!("Error saving depreciation: {}", e),
-            }
+            let id = service
+                .save_depreciation(&txn, depreciation, input.company_id)
+                .await?;
+            let row = DepreciationJournal::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or("Depreciation row not found")?;
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Create, "depreciation_journal", id)
+                    .company(input.company_id)
+                    .after(&row),
+            )
+            .await?;
+            saved_count += 1;
         }
+        txn.commit().await?;
 
         Ok(DepreciationCalculationResult {
             success: bulk_result.errors.is_empty(),
@@ -652,24 +824,39 @@ impl FixedAssetsMutation {
         &self,
         ctx: &Context<'_>,
         input: PostDepreciationInput,
-        user_id: i32,
     ) -> FieldResult<DepreciationPostingResult> {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_access(ctx, input.company_id).await?;
         let service = DepreciationService::new();
 
         let period =
             NaiveDate::from_ymd_opt(input.year, input.month, 1).ok_or("Invalid year/month")?;
 
+        let txn = db.begin().await?;
         let result = service
             .create_depreciation_journal_entry(
-                db.as_ref(),
+                &txn,
                 input.company_id,
                 period,
-                user_id,
+                user.id,
                 input.reference,
             )
             .await?;
 
+        let entry_id = result.journal_entry_id;
+        let after = AuditLogService::journal_entry_snapshot(&txn, entry_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "journal_entries", entry_id)
+                .company(input.company_id)
+                .after(&after),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(DepreciationPostingResult {
             success: true,
             journal_entry_id: result.journal_entry_id,
@@ -682,6 +869,230 @@ impl FixedAssetsMutation {
         })
     }
 
+    /// Reverse the depreciation of a period so it can be calculated again
+    async fn reverse_depreciation(
+        &self,
+        ctx: &Context<'_>,
+        input: ReverseDepreciationInput,
+    ) -> FieldResult<DepreciationReversalResult> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let user = require_company_access(ctx, input.company_id).await?;
+        let service = DepreciationService::new();
+
+        let period =
+            NaiveDate::from_ymd_opt(input.year, input.month, 1).ok_or("Invalid year/month")?;
+
+        let result = service
+            .reverse_depreciation(db.as_ref(), input.company_id, period, user.id)
+            .await?;
+
+        Ok(DepreciationReversalResult {
+            success: true,
+            journal_entry_ids: result.journal_entry_ids,
+            storno_entry_ids: result.storno_entry_ids,
+            accounting_amount: result.accounting_amount,
+            tax_amount: result.tax_amount,
+            assets_count: result.assets_count,
+            message: format!(
+                "Successfully reversed depreciation for {} assets",
+                result.assets_count
+            ),
+        })
+    }
+
+    /// Enter the units an asset produced in a month
+    async fn set_fixed_asset_usage(
+        &self,
+        ctx: &Context<'_>,
+        input: FixedAssetUsageInput,
+    ) -> FieldResult<fixed_asset_usage::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+
+        let asset = FixedAsset::find_by_id(input.fixed_asset_id)
+            .one(db.as_ref())
+            .await?
+            .ok_or("Asset not found")?;
+        require_company_access(ctx, asset.company_id).await?;
+
+        if input.units < Decimal::ZERO {
+            return Err("Usage cannot be negative".into());
+        }
+        let period =
+            NaiveDate::from_ymd_opt(input.year, input.month, 1).ok_or("Invalid year/month")?;
+
+        // The asset row is locked so a depreciation run cannot start between
+        // the check and the write
+        let txn = db.begin().await?;
+        FixedAsset::find_by_id(asset.id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+
+        let calculated = DepreciationJournal::find()
+            .filter(depreciation_journal::Column::FixedAssetId.eq(asset.id))
+            .filter(depreciation_journal::Column::Period.gte(period))
+            .count(&txn)
+            .await?;
+        if calculated > 0 {
+            return Err("Depreciation for this period is already calculated".into());
+        }
+
+        let existing = FixedAssetUsage::find()
+            .filter(fixed_asset_usage::Column::FixedAssetId.eq(asset.id))
+            .filter(fixed_asset_usage::Column::Period.eq(period))
+            .one(&txn)
+            .await?;
+
+        let usage = match existing.clone() {
+            Some(existing) => {
+                let mut usage: fixed_asset_usage::ActiveModel = existing.into();
+                usage.units = Set(input.units);
+                usage.updated_at = Set(Utc::now());
+                usage.update(&txn).await?
+            }
+            None => {
+                fixed_asset_usage::ActiveModel {
+                    company_id: Set(asset.company_id),
+                    fixed_asset_id: Set(asset.id),
+                    period: Set(period),
+                    units: Set(input.units),
+                    created_at: Set(Utc::now()),
+                    updated_at: Set(Utc::now()),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?
+            }
+        };
+
+        let event = match &existing {
+            Some(before) => {
+                AuditEvent::new(AuditAction::Update, "fixed_asset_usages", usage.id).before(before)
+            }
+            None => AuditEvent::new(AuditAction::Create, "fixed_asset_usages", usage.id),
+        };
+        record_audit(ctx, &txn, event.company(usage.company_id).after(&usage)).await?;
+        txn.commit().await?;
+
+        Ok(usage)
+    }
+
+    /// Sell, dispose of, write off, revalue, improve or impair a fixed asset
+    async fn record_fixed_asset_event(
+        &self,
+        ctx: &Context<'_>,
+        input: FixedAssetEventInput,
+    ) -> FieldResult<fixed_asset_event::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+
+        let before = FixedAsset::find_by_id(input.fixed_asset_id)
+            .one(db.as_ref())
+            .await?
+            .ok_or("Asset not found")?;
+        let user = require_company_access(ctx, before.company_id).await?;
+
+        let txn = db.begin().await?;
+        let event = FixedAssetLifecycleService::record(&txn, &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        let after = FixedAsset::find_by_id(event.fixed_asset_id)
+            .one(&txn)
+            .await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "fixed_asset_events", event.id)
+                .company(event.company_id)
+                .after(&event),
+        )
+        .await?;
+        if let Some(after) = after {
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Update, "fixed_assets", after.id)
+                    .company(after.company_id)
+                    .before(&before)
+                    .after(&after),
+            )
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(event)
+    }
+
+    /// Validate a fixed asset register file and show the rows with their errors
+    async fn preview_fixed_asset_import(
+        &self,
+        ctx: &Context<'_>,
+        input: ImportFixedAssetsInput,
+    ) -> FieldResult<FixedAssetImportPreview> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_access(ctx, input.company_id).await?;
+
+        let content = decode_import_file(&input.file_base64)?;
+        let mapping = FixedAssetImportMapping::from_value(&input.mapping)
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let result = FixedAssetImportService::import(
+            db.as_ref(),
+            input.company_id,
+            &input.file_name,
+            &content,
+            &mapping,
+            input.opening_balance_date,
+            false,
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        Ok(result.preview)
+    }
+
+    /// Import a fixed asset register with opening accumulated depreciation.
+    /// Nothing is imported when any row has errors.
+    async fn import_fixed_assets(
+        &self,
+        ctx: &Context<'_>,
+        input: ImportFixedAssetsInput,
+    ) -> FieldResult<FixedAssetImportResult> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        require_company_access(ctx, input.company_id).await?;
+
+        let content = decode_import_file(&input.file_base64)?;
+        let mapping = FixedAssetImportMapping::from_value(&input.mapping)
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let txn = db.begin().await?;
+        let result = FixedAssetImportService::import(
+            &txn,
+            input.company_id,
+            &input.file_name,
+            &content,
+            &mapping,
+            input.opening_balance_date,
+            true,
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        for asset in &result.imported {
+            record_audit(
+                ctx,
+                &txn,
+                AuditEvent::new(AuditAction::Create, "fixed_assets", asset.id)
+                    .company(asset.company_id)
+                    .after(asset),
+            )
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(result)
+    }
+
     /// Delete a fixed asset
     async fn delete_fixed_asset(&self, ctx: &Context<'_>, id: i32) -> FieldResult<bool> {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
@@ -704,3 +1115,20 @@ impl FixedAssetsMutation {
         Ok(result.rows_affected > 0)
     }
 }
+
+fn decode_import_file(encoded: &str) -> FieldResult<Vec<u8>> {
+    let trimmed = encoded.trim();
+    let payload = trimmed
+        .split_once(',')
+        .map(|(_, data)| data)
+        .unwrap_or(trimmed)
+        .trim();
+
+    if payload.is_empty() {
+        return Err("The file is empty".into());
+    }
+
+    BASE64
+        .decode(payload)
+        .map_err(|err| async_graphql::Error::new(format!("Invalid base64 content: {}", err)))
+}
@@ -5,13 +5,16 @@ use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
 use chrono::NaiveDate;
 use rust_decimal::Decimal;
-use sea_orm::DatabaseConnection;
+use sea_orm::{DatabaseConnection, TransactionTrait};
 
-use crate::entities::GlobalContragentModel;
+use crate::entities::{GlobalContragentModel, JournalSeriesKind};
+use crate::graphql::context::get_current_user;
+use crate::services::euro_changeover::EuroChangeoverService;
 use crate::services::invoice_processing::{
     InvoiceDocument, InvoiceProcessingService, ParsedCounterpart, ParsedInvoice, ParsedInvoiceItem,
     ProcessedInvoice,
 };
+use crate::services::journal_numbering::JournalNumberingService;
 
 use super::contragent_resolvers::ContragentSource;
 
@@ -219,6 +222,13 @@ impl InvoiceMutation {
             &document_number,
         );
 
+        let currency_code = match input.currency.clone() {
+            Some(currency) => currency,
+            None => EuroChangeoverService::base_currency_code(db, company_id, document_date)
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?,
+        };
+
         // Create journal entry lines based on VAT direction
         let lines = if vat_direction == "INPUT" {
             if is_unregistered_vat_purchase {
@@ -230,7 +240,7 @@ impl InvoiceMutation {
                         credit_amount: None,
                         counterpart_id: Some(counterpart_id),
                         description: Some(formatted_description.clone()),
-                        currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                        currency_code: Some(currency_code.clone()),
                         currency_amount: None,
                         exchange_rate: None,
                         vat_amount: None,
@@ -244,7 +254,7 @@ impl InvoiceMutation {
                         credit_amount: Some(total_amount),
                         counterpart_id: Some(counterpart_id),
                         description: Some(formatted_description.clone()),
-                        currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                        currency_code: Some(currency_code.clone()),
                         currency_amount: None,
                         exchange_rate: None,
                         vat_amount: None,
@@ -262,7 +272,7 @@ impl InvoiceMutation {
                     credit_amount: None,
                     counterpart_id: Some(counterpart_id),
                     description: Some(formatted_description.clone()),
-                    currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                    currency_code: Some(currency_code.clone()),
                     currency_amount: None,
                     exchange_rate: None,
                     vat_amount: None,
@@ -276,7 +286,7 @@ impl InvoiceMutation {
                     credit_amount: None,
                     counterpart_id: Some(counterpart_id),
                     description: Some("ДДС".to_string()),
-                    currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                    currency_code: Some(currency_code.clone()),
                     currency_amount: None,
                     exchange_rate: None,
                     vat_amount: Some(vat_amount),
@@ -290,7 +300,7 @@ impl InvoiceMutation {
                     credit_amount: Some(total_amount),
                     counterpart_id: Some(counterpart_id),
                     description: Some(formatted_description.clone()),
-                    currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                    currency_code: Some(currency_code.clone()),
                     currency_amount: None,
                     exchange_rate: None,
                     vat_amount: None,
@@ -309,7 +319,7 @@ impl InvoiceMutation {
                     credit_amount: None,
                     counterpart_id: Some(counterpart_id),
                     description: Some(formatted_description.clone()),
-                    currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                    currency_code: Some(currency_code.clone()),
                     currency_amount: None,
                     exchange_rate: None,
                     vat_amount: None,
@@ -323,7 +333,7 @@ impl InvoiceMutation {
                     credit_amount: Some(net_amount),
                     counterpart_id: Some(counterpart_id),
                     description: Some(formatted_description.clone()),
-                    currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                    currency_code: Some(currency_code.clone()),
                     currency_amount: None,
                     exchange_rate: None,
                     vat_amount: None,
@@ -337,7 +347,7 @@ impl InvoiceMutation {
                     credit_amount: Some(vat_amount),
                     counterpart_id: Some(counterpart_id),
                     description: Some("ДДС".to_string()),
-                    currency_code: Some(input.currency.clone().unwrap_or("BGN".to_string())),
+                    currency_code: Some(currency_code.clone()),
                     currency_amount: None,
                     exchange_rate: None,
                     vat_amount: Some(vat_amount),
@@ -350,7 +360,7 @@ impl InvoiceMutation {
 
         // Create the journal entry using existing infrastructure
         let journal_input = CreateJournalEntryInput {
-            entry_number: None, // Will be auto-generated
+            entry_number: None, // Allocated from the sales or purchases series
             document_date,
             vat_date: Some(input.vat_date.unwrap_or(document_date)),
             accounting_date: input.accounting_date.unwrap_or(document_date),
@@ -370,13 +380,34 @@ impl InvoiceMutation {
         // Use existing create_journal_entry logic
         use crate::entities::{journal_entry as je, entry_line};
 
-        let mut entry_model = je::ActiveModel::from(journal_input.clone());
-        entry_model.created_by = Set(1); // TODO: Get from auth context
+        let user = get_current_user(ctx)?;
+        let txn = db.begin().await?;
+
+        let series_kind = if vat_direction == "INPUT" {
+            JournalSeriesKind::Purchases
+        } else {
+            JournalSeriesKind::Sales
+        };
+        let number = JournalNumberingService::allocate(
+            &txn,
+            company_id,
+            series_kind,
+            journal_input.accounting_date,
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let mut entry_model = je::ActiveModel::try_from(CreateJournalEntryInput {
+            entry_number: Some(number.entry_number.clone()),
+            ..journal_input.clone()
+        })?;
+        entry_model.created_by = Set(user.id);
         entry_model.total_amount = Set(total_amount);
         entry_model.total_vat_amount = Set(vat_amount);
+        number.apply(&mut entry_model);
 
         let entry = je::Entity::insert(entry_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
 
         // Create entry lines
@@ -398,9 +429,11 @@ impl InvoiceMutation {
             };
             let mut line_model = entry_line::ActiveModel::from(line_input_proper);
             line_model.journal_entry_id = Set(entry.id);
-            entry_line::Entity::insert(line_model).exec(db).await?;
+            entry_line::Entity::insert(line_model).exec(&txn).await?;
         }
 
+        txn.commit().await?;
+
         Ok(VatJournalCreationPayload {
             success: true,
             journal_entry_id: entry.id,
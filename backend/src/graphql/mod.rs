@@ -2,6 +2,7 @@ pub mod accounting_resolvers;
 pub mod admin_resolvers;
 pub mod ai_accounting_settings_resolvers;
 pub mod ai_bank_accounting_settings_resolvers;
+pub mod audit_resolvers;
 pub mod bank_resolvers;
 pub mod contragent_resolvers;
 pub mod context;
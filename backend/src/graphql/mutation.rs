@@ -2,6 +2,7 @@ use super::accounting_resolvers::AccountingMutation;
 use super::admin_resolvers::AdminMutation;
 use super::ai_accounting_settings_resolvers::AiAccountingSettingsMutation;
 use super::ai_bank_accounting_settings_resolvers::AiBankAccountingSettingsMutation;
+use super::audit_resolvers::AuditMutation;
 use super::bank_resolvers::BankMutation;
 use super::contragent_resolvers::ContragentMutation;
 use super::controlisy_resolver::ControlisyMutation;
@@ -35,6 +36,7 @@ pub struct Mutation(
     ContragentMutation,
     MaintenanceMutation,
     InvoiceMutation,
+    AuditMutation,
 );
 
 #[derive(Default)]
@@ -2,6 +2,7 @@ use super::accounting_resolvers::AccountingQuery;
 use super::admin_resolvers::AdminQuery;
 use super::ai_accounting_settings_resolvers::AiAccountingSettingsQuery;
 use super::ai_bank_accounting_settings_resolvers::AiBankAccountingSettingsQuery;
+use super::audit_resolvers::AuditQuery;
 use super::bank_resolvers::BankQuery;
 use super::contragent_resolvers::ContragentQuery;
 use super::controlisy_resolver::ControlisyQuery;
@@ -33,6 +34,7 @@ pub struct Query(
     ControlisyQuery,
     ContragentQuery,
     MaintenanceQuery,
+    AuditQuery,
 );
 
 #[derive(Default)]
@@ -10,7 +10,9 @@ use sea_orm::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::entities::{account, company, counterpart, entry_line, journal_entry};
+use crate::entities::{account, company, counterpart, entry_line, euro_changeover, journal_entry};
+use crate::services::euro_changeover::EuroChangeoverService;
+use crate::services::year_end_closing::YearEndClosingService;
 
 // Input types for reports
 #[derive(InputObject, Deserialize)]
@@ -20,6 +22,7 @@ pub struct TurnoverReportInput {
     pub end_date: NaiveDate,
     pub account_id: Option<i32>, // Optional: specific account or all accounts
     pub show_zero_balances: Option<bool>, // Show accounts with zero balances
+    pub dual_currency: Option<bool>, // Also show amounts in BGN/EUR at the fixed rate
 }
 
 #[derive(InputObject, Deserialize)]
@@ -36,6 +39,7 @@ pub struct GeneralLedgerInput {
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
     pub account_id: Option<i32>,
+    pub dual_currency: Option<bool>, // Also show amounts in BGN/EUR at the fixed rate
 }
 
 #[derive(InputObject, Deserialize)]
@@ -44,6 +48,7 @@ pub struct ChronologicalReportInput {
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
     pub account_id: Option<i32>, // Optional: filter by specific account
+    pub dual_currency: Option<bool>, // Also show amounts in BGN/EUR at the fixed rate
 }
 
 // Report result types
@@ -55,6 +60,7 @@ pub struct ChronologicalEntry {
     pub credit_account_code: String,
     pub credit_account_name: String,
     pub amount: Decimal,
+    pub counterpart_amount: Option<Decimal>,
     pub debit_currency_amount: Option<Decimal>,
     pub debit_currency_code: Option<String>,
     pub credit_currency_amount: Option<Decimal>,
@@ -71,6 +77,9 @@ pub struct ChronologicalReport {
     pub period_end: NaiveDate,
     pub entries: Vec<ChronologicalEntry>,
     pub total_amount: Decimal,
+    pub counterpart_total_amount: Option<Decimal>,
+    pub currency_code: String,
+    pub counterpart_currency_code: Option<String>,
     pub generated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -88,6 +97,13 @@ pub struct TurnoverSheetEntry {
     // Closing balance
     pub closing_debit: Decimal,
     pub closing_credit: Decimal,
+    // Same columns in the dual display currency
+    pub counterpart_opening_debit: Option<Decimal>,
+    pub counterpart_opening_credit: Option<Decimal>,
+    pub counterpart_period_debit: Option<Decimal>,
+    pub counterpart_period_credit: Option<Decimal>,
+    pub counterpart_closing_debit: Option<Decimal>,
+    pub counterpart_closing_credit: Option<Decimal>,
 }
 
 #[derive(SimpleObject, Serialize)]
@@ -97,6 +113,8 @@ pub struct TurnoverSheet {
     pub period_end: NaiveDate,
     pub entries: Vec<TurnoverSheetEntry>,
     pub totals: TurnoverSheetEntry,
+    pub currency_code: String,
+    pub counterpart_currency_code: Option<String>,
     pub generated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -131,6 +149,9 @@ pub struct GeneralLedgerEntry {
     pub debit_amount: Decimal,
     pub credit_amount: Decimal,
     pub balance: Decimal,
+    pub counterpart_debit_amount: Option<Decimal>,
+    pub counterpart_credit_amount: Option<Decimal>,
+    pub counterpart_balance: Option<Decimal>,
     pub counterpart_name: Option<String>,
 }
 
@@ -143,6 +164,10 @@ pub struct GeneralLedgerAccount {
     pub closing_balance: Decimal,
     pub total_debits: Decimal,
     pub total_credits: Decimal,
+    pub counterpart_opening_balance: Option<Decimal>,
+    pub counterpart_closing_balance: Option<Decimal>,
+    pub counterpart_total_debits: Option<Decimal>,
+    pub counterpart_total_credits: Option<Decimal>,
     pub entries: Vec<GeneralLedgerEntry>,
 }
 
@@ -152,6 +177,8 @@ pub struct GeneralLedger {
     pub period_start: NaiveDate,
     pub period_end: NaiveDate,
     pub accounts: Vec<GeneralLedgerAccount>,
+    pub currency_code: String,
+    pub counterpart_currency_code: Option<String>,
     pub generated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -188,6 +215,161 @@ pub struct MonthlyStatsInput {
     pub to_month: i32,
 }
 
+/// Currency of a report and, during the dual display period, its fixed-rate
+/// counterpart. Every amount is converted and rounded on its own; totals and
+/// closing balances add up the converted amounts so that the counterpart
+/// columns foot.
+struct DualDisplay {
+    currency_code: String,
+    counterpart_currency_code: Option<String>,
+    restatement: Option<Restatement>,
+}
+
+/// Set when a report spans a euro changeover cut-off. Lines booked up to the
+/// cut-off are in the previous currency and are restated at the fixed rate;
+/// the conversion entry is left out, as it restates the same balances.
+struct Restatement {
+    cutoff_date: NaiveDate,
+    previous_currency_code: String,
+    conversion_entry_id: Option<i32>,
+}
+
+impl DualDisplay {
+    async fn load(
+        db: &DatabaseConnection,
+        company_id: i32,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        enabled: Option<bool>,
+    ) -> FieldResult<Self> {
+        let currency_code = EuroChangeoverService::base_currency_code(db, company_id, end_date)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        let counterpart_currency_code = if enabled.unwrap_or(false) {
+            EuroChangeoverService::dual_display_currency(&currency_code).map(str::to_string)
+        } else {
+            None
+        };
+
+        let restatement = euro_changeover::Entity::find()
+            .filter(euro_changeover::Column::CompanyId.eq(company_id))
+            .one(db)
+            .await?
+            .filter(|changeover| {
+                start_date <= changeover.cutoff_date && changeover.cutoff_date < end_date
+            })
+            .map(|changeover| Restatement {
+                cutoff_date: changeover.cutoff_date,
+                previous_currency_code: changeover.previous_currency_code,
+                conversion_entry_id: changeover.journal_entry_id,
+            });
+
+        Ok(Self {
+            currency_code,
+            counterpart_currency_code,
+            restatement,
+        })
+    }
+
+    fn convert(&self, amount: Decimal) -> Option<Decimal> {
+        self.counterpart_currency_code.as_ref()?;
+        EuroChangeoverService::dual_display_amount(amount, &self.currency_code)
+    }
+
+    /// Whether the journal entry is the changeover conversion entry of a restated report
+    fn skips(&self, journal_entry_id: i32) -> bool {
+        self.restatement
+            .as_ref()
+            .is_some_and(|r| r.conversion_entry_id == Some(journal_entry_id))
+    }
+
+    /// Whether amounts booked on `date` are in the previous currency
+    fn is_restated(&self, date: NaiveDate) -> bool {
+        self.restatement
+            .as_ref()
+            .is_some_and(|r| date <= r.cutoff_date)
+    }
+
+    /// Amount booked on `date` in the report currency
+    fn restate(&self, amount: Decimal, date: NaiveDate) -> Decimal {
+        match &self.restatement {
+            Some(r) if date <= r.cutoff_date => EuroChangeoverService::convert_fixed(
+                amount,
+                &r.previous_currency_code,
+                &self.currency_code,
+            )
+            .unwrap_or(amount),
+            _ => amount,
+        }
+    }
+
+    /// Debit and credit totals of lines in the report currency. Lines up to the
+    /// cut-off are added up first and converted once.
+    fn totals(
+        &self,
+        lines: &[(entry_line::Model, Option<journal_entry::Model>)],
+    ) -> (Decimal, Decimal) {
+        let mut current = (Decimal::ZERO, Decimal::ZERO);
+        let mut previous = (Decimal::ZERO, Decimal::ZERO);
+        let mut cutoff_date = None;
+
+        for (line, entry) in lines {
+            let Some(entry) = entry else { continue };
+            if self.skips(entry.id) {
+                continue;
+            }
+            let totals = if self.is_restated(entry.accounting_date) {
+                cutoff_date = Some(entry.accounting_date);
+                &mut previous
+            } else {
+                &mut current
+            };
+            totals.0 += line.debit_amount;
+            totals.1 += line.credit_amount;
+        }
+
+        match cutoff_date {
+            Some(date) => (
+                current.0 + self.restate(previous.0, date),
+                current.1 + self.restate(previous.1, date),
+            ),
+            None => current,
+        }
+    }
+
+    /// Counterpart closing debit and credit, derived from the converted opening
+    /// and period amounts rather than converted on their own
+    fn closing(
+        &self,
+        opening_debit: Option<Decimal>,
+        opening_credit: Option<Decimal>,
+        period_debit: Option<Decimal>,
+        period_credit: Option<Decimal>,
+    ) -> (Option<Decimal>, Option<Decimal>) {
+        match (opening_debit, opening_credit, period_debit, period_credit) {
+            (Some(od), Some(oc), Some(pd), Some(pc)) => {
+                let (debit, credit) = closing_sides(od + pd - oc - pc);
+                (Some(debit), Some(credit))
+            }
+            _ => (None, None),
+        }
+    }
+}
+
+/// Debit-positive balance split into closing debit and credit
+fn closing_sides(net: Decimal) -> (Decimal, Decimal) {
+    if net > Decimal::ZERO {
+        (net, Decimal::ZERO)
+    } else {
+        (Decimal::ZERO, net.abs())
+    }
+}
+
+/// Sum of converted amounts, or None when dual display is off
+fn sum_counterpart(amounts: impl IntoIterator<Item = Option<Decimal>>) -> Option<Decimal> {
+    amounts.into_iter().sum()
+}
+
 #[derive(Default)]
 pub struct ReportsQuery;
 
@@ -208,6 +390,19 @@ impl ReportsQuery {
             .await?
             .ok_or("Company not found")?;
 
+        let dual = DualDisplay::load(
+            db,
+            input.company_id,
+            input.start_date,
+            input.end_date,
+            input.dual_currency,
+        )
+        .await?;
+        // Year-end roll-over entries would show up as turnover of December and January
+        let rollover_entry_ids = YearEndClosingService::rollover_entry_ids(db, input.company_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
         // Get all accounts (or specific account if provided)
         let mut account_query = account::Entity::find()
             .filter(account::Column::CompanyId.eq(input.company_id))
@@ -232,52 +427,35 @@ impl ReportsQuery {
 
         for account in accounts {
             // Calculate opening balance (before start_date)
-            let opening_balance = entry_line::Entity::find()
-                .left_join(journal_entry::Entity)
+            let opening_lines = entry_line::Entity::find()
+                .find_also_related(journal_entry::Entity)
                 .filter(entry_line::Column::AccountId.eq(account.id))
                 .filter(journal_entry::Column::CompanyId.eq(input.company_id))
                 .filter(journal_entry::Column::AccountingDate.lt(input.start_date))
                 .filter(journal_entry::Column::IsPosted.eq(true))
+                .filter(journal_entry::Column::Id.is_not_in(rollover_entry_ids.clone()))
                 .all(db)
                 .await?;
 
-            let mut opening_debit = Decimal::ZERO;
-            let mut opening_credit = Decimal::ZERO;
-
-            for line in opening_balance {
-                opening_debit += line.debit_amount;
-                opening_credit += line.credit_amount;
-            }
+            let (opening_debit, opening_credit) = dual.totals(&opening_lines);
 
             // Calculate period turnovers
             let period_lines = entry_line::Entity::find()
-                .left_join(journal_entry::Entity)
+                .find_also_related(journal_entry::Entity)
                 .filter(entry_line::Column::AccountId.eq(account.id))
                 .filter(journal_entry::Column::CompanyId.eq(input.company_id))
                 .filter(journal_entry::Column::AccountingDate.gte(input.start_date))
                 .filter(journal_entry::Column::AccountingDate.lte(input.end_date))
                 .filter(journal_entry::Column::IsPosted.eq(true))
+                .filter(journal_entry::Column::Id.is_not_in(rollover_entry_ids.clone()))
                 .all(db)
                 .await?;
 
-            let mut period_debit = Decimal::ZERO;
-            let mut period_credit = Decimal::ZERO;
-
-            for line in period_lines {
-                period_debit += line.debit_amount;
-                period_credit += line.credit_amount;
-            }
+            let (period_debit, period_credit) = dual.totals(&period_lines);
 
             // Calculate closing balance
-            let closing_debit = opening_debit + period_debit;
-            let closing_credit = opening_credit + period_credit;
-            let net_closing = closing_debit - closing_credit;
-
-            let (final_closing_debit, final_closing_credit) = if net_closing > Decimal::ZERO {
-                (net_closing, Decimal::ZERO)
-            } else {
-                (Decimal::ZERO, net_closing.abs())
-            };
+            let (final_closing_debit, final_closing_credit) =
+                closing_sides(opening_debit + period_debit - opening_credit - period_credit);
 
             // Skip zero balance accounts if requested
             let show_zero_balances = input.show_zero_balances.unwrap_or(true);
@@ -290,6 +468,17 @@ impl ReportsQuery {
                 continue;
             }
 
+            let counterpart_opening_debit = dual.convert(opening_debit);
+            let counterpart_opening_credit = dual.convert(opening_credit);
+            let counterpart_period_debit = dual.convert(period_debit);
+            let counterpart_period_credit = dual.convert(period_credit);
+            let (counterpart_closing_debit, counterpart_closing_credit) = dual.closing(
+                counterpart_opening_debit,
+                counterpart_opening_credit,
+                counterpart_period_debit,
+                counterpart_period_credit,
+            );
+
             let entry = TurnoverSheetEntry {
                 account_id: account.id,
                 account_code: account.code.clone(),
@@ -300,6 +489,12 @@ impl ReportsQuery {
                 period_credit,
                 closing_debit: final_closing_debit,
                 closing_credit: final_closing_credit,
+                counterpart_opening_debit,
+                counterpart_opening_credit,
+                counterpart_period_debit,
+                counterpart_period_credit,
+                counterpart_closing_debit,
+                counterpart_closing_credit,
             };
 
             // Add to totals
@@ -323,6 +518,24 @@ impl ReportsQuery {
             period_credit: total_period_credit,
             closing_debit: total_closing_debit,
             closing_credit: total_closing_credit,
+            counterpart_opening_debit: sum_counterpart(
+                entries.iter().map(|e| e.counterpart_opening_debit),
+            ),
+            counterpart_opening_credit: sum_counterpart(
+                entries.iter().map(|e| e.counterpart_opening_credit),
+            ),
+            counterpart_period_debit: sum_counterpart(
+                entries.iter().map(|e| e.counterpart_period_debit),
+            ),
+            counterpart_period_credit: sum_counterpart(
+                entries.iter().map(|e| e.counterpart_period_credit),
+            ),
+            counterpart_closing_debit: sum_counterpart(
+                entries.iter().map(|e| e.counterpart_closing_debit),
+            ),
+            counterpart_closing_credit: sum_counterpart(
+                entries.iter().map(|e| e.counterpart_closing_credit),
+            ),
         };
 
         Ok(TurnoverSheet {
@@ -331,6 +544,8 @@ impl ReportsQuery {
             period_end: input.end_date,
             entries,
             totals,
+            currency_code: dual.currency_code,
+            counterpart_currency_code: dual.counterpart_currency_code,
             generated_at: Utc::now(),
         })
     }
@@ -437,6 +652,15 @@ impl ReportsQuery {
             .await?
             .ok_or("Company not found")?;
 
+        let dual = DualDisplay::load(
+            db,
+            input.company_id,
+            input.start_date,
+            input.end_date,
+            input.dual_currency,
+        )
+        .await?;
+
         // Get all journal entries for the period
         let entry_query = journal_entry::Entity::find()
             .filter(journal_entry::Column::CompanyId.eq(input.company_id))
@@ -454,6 +678,10 @@ impl ReportsQuery {
         let mut total_amount = Decimal::ZERO;
 
         for journal_entry in journal_entries {
+            if dual.skips(journal_entry.id) {
+                continue;
+            }
+
             // Get all entry lines for this journal entry
             let entry_lines = entry_line::Entity::find()
                 .filter(entry_line::Column::JournalEntryId.eq(journal_entry.id))
@@ -497,7 +725,10 @@ impl ReportsQuery {
                         .ok_or("Credit account not found")?;
 
                     // Calculate amount (use the smaller of debit and credit amounts)
-                    let amount = debit_line.debit_amount.min(credit_line.credit_amount);
+                    let amount = dual.restate(
+                        debit_line.debit_amount.min(credit_line.credit_amount),
+                        journal_entry.accounting_date,
+                    );
                     total_amount += amount;
 
                     chronological_entries.push(ChronologicalEntry {
@@ -507,6 +738,7 @@ impl ReportsQuery {
                         credit_account_code: credit_account.code.clone(),
                         credit_account_name: credit_account.name.clone(),
                         amount,
+                        counterpart_amount: dual.convert(amount),
                         debit_currency_amount: None, // TODO: Add currency support
                         debit_currency_code: None,
                         credit_currency_amount: None,
@@ -527,8 +759,13 @@ impl ReportsQuery {
             company_name: company.name,
             period_start: input.start_date,
             period_end: input.end_date,
+            counterpart_total_amount: sum_counterpart(
+                chronological_entries.iter().map(|e| e.counterpart_amount),
+            ),
             entries: chronological_entries,
             total_amount,
+            currency_code: dual.currency_code,
+            counterpart_currency_code: dual.counterpart_currency_code,
             generated_at: Utc::now(),
         })
     }
@@ -671,6 +908,19 @@ impl ReportsQuery {
             .await?
             .ok_or("Company not found")?;
 
+        let dual = DualDisplay::load(
+            db,
+            input.company_id,
+            input.start_date,
+            input.end_date,
+            input.dual_currency,
+        )
+        .await?;
+        // Year-end roll-over entries would show up as turnover of December and January
+        let rollover_entry_ids = YearEndClosingService::rollover_entry_ids(db, input.company_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
         // Get accounts to process - all accounts, not just analytical
         let mut account_query = account::Entity::find()
             .filter(account::Column::CompanyId.eq(input.company_id))
@@ -691,18 +941,18 @@ impl ReportsQuery {
         for account in accounts {
             // Calculate opening balance (before start_date)
             let opening_lines = entry_line::Entity::find()
-                .left_join(journal_entry::Entity)
+                .find_also_related(journal_entry::Entity)
                 .filter(entry_line::Column::AccountId.eq(account.id))
                 .filter(journal_entry::Column::CompanyId.eq(input.company_id))
                 .filter(journal_entry::Column::AccountingDate.lt(input.start_date))
                 .filter(journal_entry::Column::IsPosted.eq(true))
+                .filter(journal_entry::Column::Id.is_not_in(rollover_entry_ids.clone()))
                 .all(db)
                 .await?;
 
-            let mut opening_balance = Decimal::ZERO;
-            for line in opening_lines {
-                opening_balance += line.debit_amount - line.credit_amount;
-            }
+            let (opening_debit, opening_credit) = dual.totals(&opening_lines);
+            let opening_balance = opening_debit - opening_credit;
+            let counterpart_opening_balance = dual.convert(opening_balance);
 
             // Get period transactions
             let period_lines = entry_line::Entity::find()
@@ -712,6 +962,7 @@ impl ReportsQuery {
                 .filter(journal_entry::Column::AccountingDate.gte(input.start_date))
                 .filter(journal_entry::Column::AccountingDate.lte(input.end_date))
                 .filter(journal_entry::Column::IsPosted.eq(true))
+                .filter(journal_entry::Column::Id.is_not_in(rollover_entry_ids.clone()))
                 .order_by_asc(journal_entry::Column::AccountingDate)
                 .order_by_asc(entry_line::Column::LineOrder)
                 .all(db)
@@ -719,6 +970,7 @@ impl ReportsQuery {
 
             let mut entries = Vec::new();
             let mut running_balance = opening_balance;
+            let mut counterpart_running_balance = counterpart_opening_balance;
             let mut total_debits = Decimal::ZERO;
             let mut total_credits = Decimal::ZERO;
 
@@ -733,6 +985,9 @@ impl ReportsQuery {
                     .one(db)
                     .await?
                     .ok_or("Journal entry not found")?;
+                if dual.skips(je.id) {
+                    continue;
+                }
 
                 // Get counterpart name
                 let counterpart_name = if let Some(counterpart_id) = line.counterpart_id {
@@ -744,19 +999,31 @@ impl ReportsQuery {
                     None
                 };
 
+                let debit_amount = dual.restate(line.debit_amount, je.accounting_date);
+                let credit_amount = dual.restate(line.credit_amount, je.accounting_date);
+                let counterpart_debit_amount = dual.convert(debit_amount);
+                let counterpart_credit_amount = dual.convert(credit_amount);
+
                 // Update running balance
-                running_balance += line.debit_amount - line.credit_amount;
-                total_debits += line.debit_amount;
-                total_credits += line.credit_amount;
+                running_balance += debit_amount - credit_amount;
+                counterpart_running_balance = counterpart_running_balance
+                    .zip(counterpart_debit_amount)
+                    .zip(counterpart_credit_amount)
+                    .map(|((balance, debit), credit)| balance + debit - credit);
+                total_debits += debit_amount;
+                total_credits += credit_amount;
 
                 entries.push(GeneralLedgerEntry {
                     date: je.accounting_date,
                     entry_number: je.entry_number,
                     document_number: je.document_number,
                     description: line.description.unwrap_or(je.description.clone()),
-                    debit_amount: line.debit_amount,
-                    credit_amount: line.credit_amount,
+                    debit_amount,
+                    credit_amount,
                     balance: running_balance,
+                    counterpart_debit_amount,
+                    counterpart_credit_amount,
+                    counterpart_balance: counterpart_running_balance,
                     counterpart_name,
                 });
             }
@@ -774,6 +1041,14 @@ impl ReportsQuery {
                     closing_balance: running_balance,
                     total_debits,
                     total_credits,
+                    counterpart_opening_balance,
+                    counterpart_closing_balance: counterpart_running_balance,
+                    counterpart_total_debits: sum_counterpart(
+                        entries.iter().map(|e| e.counterpart_debit_amount),
+                    ),
+                    counterpart_total_credits: sum_counterpart(
+                        entries.iter().map(|e| e.counterpart_credit_amount),
+                    ),
                     entries,
                 });
             }
@@ -784,6 +1059,8 @@ impl ReportsQuery {
             period_start: input.start_date,
             period_end: input.end_date,
             accounts: ledger_accounts,
+            currency_code: dual.currency_code,
+            counterpart_currency_code: dual.counterpart_currency_code,
             generated_at: Utc::now(),
         })
     }
@@ -917,6 +1194,31 @@ impl ReportsMutation {
 }
 
 // Helper functions for export generation
+
+/// Currency line under the report title
+fn currency_caption(currency_code: &str, counterpart_currency_code: Option<&str>) -> String {
+    match counterpart_currency_code {
+        Some(counterpart) => format!(
+            "Валута: {} / {} (1 EUR = {} BGN)",
+            currency_code,
+            counterpart,
+            crate::services::euro_changeover::fixed_rate()
+        ),
+        None => format!("Валута: {}", currency_code),
+    }
+}
+
+/// Amount cell content, with the dual display amount underneath
+fn html_amount(amount: Decimal, counterpart: Option<Decimal>) -> String {
+    match counterpart {
+        Some(counterpart) => format!(
+            "{}<br><span class=\"counterpart\">{}</span>",
+            amount, counterpart
+        ),
+        None => amount.to_string(),
+    }
+}
+
 async fn generate_html_to_pdf_chronological(
     report: &ChronologicalReport,
 ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
@@ -1025,12 +1327,16 @@ fn generate_html_chronological(report: &ChronologicalReport) -> String {
         .description {{
             width: 15%;
         }}
+        .counterpart {{
+            color: #555;
+        }}
     </style>
 </head>
 <body>
     <div class="header">
         <div class="title">Хронологичен регистър - {}</div>
         <div class="period">от {} до {}</div>
+        <div class="period">{}</div>
     </div>
     
     <table>
@@ -1075,6 +1381,10 @@ fn generate_html_chronological(report: &ChronologicalReport) -> String {
         report.company_name,
         report.period_start.format("%d.%m.%Y"),
         report.period_end.format("%d.%m.%Y"),
+        currency_caption(
+            &report.currency_code,
+            report.counterpart_currency_code.as_deref()
+        ),
         report
             .entries
             .iter()
@@ -1099,7 +1409,7 @@ fn generate_html_chronological(report: &ChronologicalReport) -> String {
                 entry.debit_account_name,
                 entry.credit_account_code,
                 entry.credit_account_name,
-                entry.amount,
+                html_amount(entry.amount, entry.counterpart_amount),
                 entry
                     .debit_currency_amount
                     .map_or("".to_string(), |a| a.to_string()),
@@ -1116,7 +1426,7 @@ fn generate_html_chronological(report: &ChronologicalReport) -> String {
             ))
             .collect::<Vec<_>>()
             .join("\n"),
-        report.total_amount
+        html_amount(report.total_amount, report.counterpart_total_amount)
     )
 }
 
@@ -1218,12 +1528,16 @@ fn generate_html_turnover(sheet: &TurnoverSheet) -> String {
         .amount {{
             width: 10.33%;
         }}
+        .counterpart {{
+            color: #555;
+        }}
     </style>
 </head>
 <body>
     <div class="header">
         <div class="title">Оборотна ведомост - {}</div>
         <div class="period">Период: {} - {}</div>
+        <div class="period">{}</div>
     </div>
     
     <table>
@@ -1258,6 +1572,10 @@ fn generate_html_turnover(sheet: &TurnoverSheet) -> String {
         sheet.company_name,
         sheet.period_start,
         sheet.period_end,
+        currency_caption(
+            &sheet.currency_code,
+            sheet.counterpart_currency_code.as_deref()
+        ),
         sheet
             .entries
             .iter()
@@ -1274,23 +1592,41 @@ fn generate_html_turnover(sheet: &TurnoverSheet) -> String {
             </tr>",
                 entry.account_code,
                 entry.account_name,
-                entry.opening_debit,
-                entry.opening_credit,
-                entry.period_debit,
-                entry.period_credit,
-                entry.closing_debit,
-                entry.closing_credit
+                html_amount(entry.opening_debit, entry.counterpart_opening_debit),
+                html_amount(entry.opening_credit, entry.counterpart_opening_credit),
+                html_amount(entry.period_debit, entry.counterpart_period_debit),
+                html_amount(entry.period_credit, entry.counterpart_period_credit),
+                html_amount(entry.closing_debit, entry.counterpart_closing_debit),
+                html_amount(entry.closing_credit, entry.counterpart_closing_credit)
             ))
             .collect::<Vec<_>>()
             .join("\n"),
         sheet.totals.account_code,
         sheet.totals.account_name,
-        sheet.totals.opening_debit,
-        sheet.totals.opening_credit,
-        sheet.totals.period_debit,
-        sheet.totals.period_credit,
-        sheet.totals.closing_debit,
-        sheet.totals.closing_credit
+        html_amount(
+            sheet.totals.opening_debit,
+            sheet.totals.counterpart_opening_debit
+        ),
+        html_amount(
+            sheet.totals.opening_credit,
+            sheet.totals.counterpart_opening_credit
+        ),
+        html_amount(
+            sheet.totals.period_debit,
+            sheet.totals.counterpart_period_debit
+        ),
+        html_amount(
+            sheet.totals.period_credit,
+            sheet.totals.counterpart_period_credit
+        ),
+        html_amount(
+            sheet.totals.closing_debit,
+            sheet.totals.counterpart_closing_debit
+        ),
+        html_amount(
+            sheet.totals.closing_credit,
+            sheet.totals.counterpart_closing_credit
+        )
     )
 }
 
@@ -1363,7 +1699,15 @@ fn generate_xlsx_chronological(
         0,
         row,
         12,
-        &format!("от {} до {}", report.period_start, report.period_end),
+        &format!(
+            "от {} до {}, {}",
+            report.period_start,
+            report.period_end,
+            currency_caption(
+                &report.currency_code,
+                report.counterpart_currency_code.as_deref()
+            )
+        ),
         &title_format,
     )?;
     row += 2;
@@ -1382,6 +1726,15 @@ fn generate_xlsx_chronological(
     worksheet.write_string_with_format(row, 10, "Док. вид", &header_format)?;
     worksheet.write_string_with_format(row, 11, "Док. дата", &header_format)?;
     worksheet.write_string_with_format(row, 12, "Описание", &header_format)?;
+    if let Some(counterpart_code) = &report.counterpart_currency_code {
+        worksheet.set_column_width(13, 15.0)?;
+        worksheet.write_string_with_format(
+            row,
+            13,
+            format!("Сума ({})", counterpart_code),
+            &header_format,
+        )?;
+    }
     row += 1;
 
     // Data rows
@@ -1457,6 +1810,14 @@ fn generate_xlsx_chronological(
         }
 
         worksheet.write_string_with_format(row, 12, &entry.description, &text_format)?;
+        if let Some(amt) = entry.counterpart_amount {
+            worksheet.write_number_with_format(
+                row,
+                13,
+                amt.to_f64().unwrap_or(0.0),
+                &number_format,
+            )?;
+        }
         row += 1;
     }
 
@@ -1478,6 +1839,14 @@ fn generate_xlsx_chronological(
     for col in 6..13 {
         worksheet.write_string_with_format(row, col, "", &totals_format)?;
     }
+    if let Some(total) = report.counterpart_total_amount {
+        worksheet.write_number_with_format(
+            row,
+            13,
+            total.to_f64().unwrap_or(0.0),
+            &totals_format,
+        )?;
+    }
 
     let buffer = workbook.save_to_buffer()?;
     Ok(buffer)
@@ -1543,7 +1912,15 @@ fn generate_xlsx_turnover(
         0,
         row,
         7,
-        &format!("Период: {} - {}", sheet.period_start, sheet.period_end),
+        &format!(
+            "Период: {} - {}, {}",
+            sheet.period_start,
+            sheet.period_end,
+            currency_caption(
+                &sheet.currency_code,
+                sheet.counterpart_currency_code.as_deref()
+            )
+        ),
         &title_format,
     )?;
     row += 2;
@@ -1557,6 +1934,28 @@ fn generate_xlsx_turnover(
     worksheet.write_string_with_format(row, 5, "Обороти Кт", &header_format)?;
     worksheet.write_string_with_format(row, 6, "Крайно салдо Дт", &header_format)?;
     worksheet.write_string_with_format(row, 7, "Крайно салдо Кт", &header_format)?;
+
+    // Dual display columns repeat the six amounts in the counterpart currency
+    if let Some(counterpart_code) = &sheet.counterpart_currency_code {
+        let headers = [
+            "Начално салдо Дт",
+            "Начално салдо Кт",
+            "Обороти Дт",
+            "Обороти Кт",
+            "Крайно салдо Дт",
+            "Крайно салдо Кт",
+        ];
+        for (index, header) in headers.iter().enumerate() {
+            let col = 8 + index as u16;
+            worksheet.set_column_width(col, 15.0)?;
+            worksheet.write_string_with_format(
+                row,
+                col,
+                format!("{} ({})", header, counterpart_code),
+                &header_format,
+            )?;
+        }
+    }
     row += 1;
 
     // Data rows
@@ -1599,6 +1998,7 @@ fn generate_xlsx_turnover(
             entry.closing_credit.to_f64().unwrap_or(0.0),
             &number_format,
         )?;
+        write_turnover_counterpart(worksheet, row, entry, &number_format)?;
         row += 1;
     }
 
@@ -1644,11 +2044,40 @@ fn generate_xlsx_turnover(
         sheet.totals.closing_credit.to_f64().unwrap_or(0.0),
         &totals_format,
     )?;
+    write_turnover_counterpart(worksheet, row, &sheet.totals, &totals_format)?;
 
     let buffer = workbook.save_to_buffer()?;
     Ok(buffer)
 }
 
+/// Dual display amounts of a turnover sheet row, in columns 8-13
+fn write_turnover_counterpart(
+    worksheet: &mut rust_xlsxwriter::Worksheet,
+    row: u32,
+    entry: &TurnoverSheetEntry,
+    format: &rust_xlsxwriter::Format,
+) -> Result<(), rust_xlsxwriter::XlsxError> {
+    let amounts = [
+        entry.counterpart_opening_debit,
+        entry.counterpart_opening_credit,
+        entry.counterpart_period_debit,
+        entry.counterpart_period_credit,
+        entry.counterpart_closing_debit,
+        entry.counterpart_closing_credit,
+    ];
+    for (index, amount) in amounts.iter().enumerate() {
+        if let Some(amount) = amount {
+            worksheet.write_number_with_format(
+                row,
+                8 + index as u16,
+                amount.to_f64().unwrap_or(0.0),
+                format,
+            )?;
+        }
+    }
+    Ok(())
+}
+
 // Generate PDF for monthly transaction statistics
 async fn generate_monthly_stats_pdf(
     stats: &[MonthlyTransactionStats],
@@ -1849,3 +2278,54 @@ fn generate_html_monthly_stats(stats: &[MonthlyTransactionStats], company_name:
         chrono::Utc::now().format("%d.%m.%Y %H:%M")
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::euro_changeover::{EURO, LEGACY_BASE_CURRENCY};
+
+    fn spanning_report() -> DualDisplay {
+        DualDisplay {
+            currency_code: EURO.to_string(),
+            counterpart_currency_code: Some(LEGACY_BASE_CURRENCY.to_string()),
+            restatement: Some(Restatement {
+                cutoff_date: NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+                previous_currency_code: LEGACY_BASE_CURRENCY.to_string(),
+                conversion_entry_id: Some(42),
+            }),
+        }
+    }
+
+    #[test]
+    fn counterpart_closing_foots_with_converted_components() {
+        let dual = spanning_report();
+        let (closing_debit, closing_credit) = dual.closing(
+            dual.convert(Decimal::new(1, 2)),
+            dual.convert(Decimal::ZERO),
+            dual.convert(Decimal::new(11, 2)),
+            dual.convert(Decimal::ZERO),
+        );
+
+        // 0.02 + 0.22; converting the 0.12 closing on its own gives 0.23
+        assert_eq!(closing_debit, Some(Decimal::new(24, 2)));
+        assert_eq!(closing_credit, Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn spanning_report_restates_amounts_up_to_the_cutoff() {
+        let dual = spanning_report();
+        let before = NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+        let after = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+
+        assert_eq!(
+            dual.restate(Decimal::new(19558, 2), before),
+            Decimal::new(10000, 2)
+        );
+        assert_eq!(
+            dual.restate(Decimal::new(10000, 2), after),
+            Decimal::new(10000, 2)
+        );
+        assert!(dual.skips(42));
+        assert!(!dual.skips(43));
+    }
+}
@@ -4,6 +4,7 @@ use sea_orm::DatabaseConnection;
 
 use crate::entities::saft::{SafTExportRequest, SafTFileType};
 use crate::services::saft_service_v2::SafTServiceV2;
+use crate::services::saft_validator::SafTValidationIssue;
 
 #[derive(Default)]
 pub struct SafTMutation;
@@ -19,6 +20,27 @@ pub struct SafTExportInput {
     pub tax_accounting_basis: String, // "A", "P", "BANK", "INSURANCE"
 }
 
+impl SafTExportInput {
+    fn to_request(&self) -> Result<SafTExportRequest> {
+        let file_type = match self.file_type.as_str() {
+            "Annual" => SafTFileType::Annual,
+            "Monthly" => SafTFileType::Monthly,
+            "OnDemand" => SafTFileType::OnDemand,
+            _ => return Err("Invalid file type. Must be Annual, Monthly or OnDemand".into()),
+        };
+
+        Ok(SafTExportRequest {
+            company_id: self.company_id,
+            period_start: self.period_start,
+            period_start_year: self.period_start_year,
+            period_end: self.period_end,
+            period_end_year: self.period_end_year,
+            file_type,
+            tax_accounting_basis: self.tax_accounting_basis.clone(),
+        })
+    }
+}
+
 #[derive(SimpleObject)]
 pub struct SafTExportResult {
     pub success: bool,
@@ -37,23 +59,7 @@ impl SafTMutation {
     ) -> Result<SafTExportResult> {
         let db = ctx.data::<DatabaseConnection>()?;
         let saft_service = SafTServiceV2::new(db.clone());
-
-        let file_type = match input.file_type.as_str() {
-            "Annual" => SafTFileType::Annual,
-            "Monthly" => SafTFileType::Monthly,
-            "OnDemand" => SafTFileType::OnDemand,
-            _ => return Err("Invalid file type. Must be Annual, Monthly or OnDemand".into()),
-        };
-
-        let request = SafTExportRequest {
-            company_id: input.company_id,
-            period_start: input.period_start,
-            period_start_year: input.period_start_year,
-            period_end: input.period_end,
-            period_end_year: input.period_end_year,
-            file_type,
-            tax_accounting_basis: input.tax_accounting_basis.clone(),
-        };
+        let request = input.to_request()?;
 
         match saft_service.generate_saft(request).await {
             Ok(xml_content) => {
@@ -94,10 +100,29 @@ pub struct SafTQuery;
 pub struct SafTValidationResult {
     pub is_valid: bool,
     pub validation_errors: Vec<String>,
+    pub issues: Vec<SafTValidationIssueResult>,
     pub file_size_bytes: i32,
     pub number_of_transactions: i32,
 }
 
+/// Schema or reconciliation problem in the generated file
+#[derive(SimpleObject)]
+pub struct SafTValidationIssueResult {
+    pub category: String, // "SCHEMA" | "RECONCILIATION"
+    pub xpath: String,
+    pub message: String,
+}
+
+impl From<SafTValidationIssue> for SafTValidationIssueResult {
+    fn from(issue: SafTValidationIssue) -> Self {
+        Self {
+            category: issue.kind.as_str().to_string(),
+            xpath: issue.xpath,
+            message: issue.message,
+        }
+    }
+}
+
 #[Object]
 impl SafTQuery {
     /// Validate SAF-T export parameters before generating the file
@@ -138,6 +163,7 @@ impl SafTQuery {
 
         // Check if company exists
         use crate::entities::company::Entity as CompanyEntity;
+        use sea_orm::EntityTrait;
         let company_exists = CompanyEntity::find_by_id(input.company_id)
             .one(db)
             .await?
@@ -147,38 +173,36 @@ impl SafTQuery {
             validation_errors.push("Company not found".to_string());
         }
 
-        // Count transactions in the period (for estimation)
-        use crate::entities::journal_entry::Entity as JournalEntryEntity;
-        use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
-
-        // Calculate date range for transaction count
-        let start_date =
-            NaiveDate::from_ymd_opt(input.period_start_year, input.period_start as u32, 1).unwrap();
-        let end_date = if input.period_end == 12 {
-            NaiveDate::from_ymd_opt(input.period_end_year, 12, 31).unwrap()
-        } else {
-            NaiveDate::from_ymd_opt(input.period_end_year, (input.period_end + 1) as u32, 1)
-                .unwrap()
-                .pred_opt()
-                .unwrap()
-        };
-
-        let transaction_count = JournalEntryEntity::find()
-            .filter(crate::entities::journal_entry::Column::CompanyId.eq(input.company_id))
-            .filter(
-                crate::entities::journal_entry::Column::DocumentDate.between(start_date, end_date),
-            )
-            .count(db)
-            .await? as i32;
+        if !validation_errors.is_empty() {
+            return Ok(SafTValidationResult {
+                is_valid: false,
+                validation_errors,
+                issues: vec![],
+                file_size_bytes: 0,
+                number_of_transactions: 0,
+            });
+        }
 
-        // Estimate file size (rough calculation: ~1KB per transaction)
-        let estimated_file_size = transaction_count * 1000;
+        // Generate the file and check it against the XSD and the ledger balances
+        let saft_service = SafTServiceV2::new(db.clone());
+        let report = saft_service
+            .validate_saft(input.to_request()?)
+            .await
+            .map_err(|e| format!("SAF-T validation failed: {}", e))?;
+
+        validation_errors.extend(
+            report
+                .issues
+                .iter()
+                .map(|issue| format!("{}: {}", issue.xpath, issue.message)),
+        );
 
         Ok(SafTValidationResult {
             is_valid: validation_errors.is_empty(),
             validation_errors,
-            file_size_bytes: estimated_file_size,
-            number_of_transactions: transaction_count,
+            issues: report.issues.into_iter().map(Into::into).collect(),
+            file_size_bytes: report.file_size_bytes as i32,
+            number_of_transactions: report.number_of_transactions,
         })
     }
 
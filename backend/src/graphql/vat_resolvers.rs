@@ -1,17 +1,24 @@
+use crate::entities::audit_log::AuditAction;
 use crate::entities::vat_rate::{
     CreateVatRateInput, UpdateVatRateInput, VatCalculation, VatRateFilter,
 };
 use crate::entities::vat_return::{
-    CreateVatReturnInput, MonthlyVatSummary, UpdateVatReturnInput, VatReturnFilter,
-    VatReturnStatus, VatReturnSummary,
+    CreateCorrectiveVatReturnInput, CreateVatReturnInput, MonthlyVatSummary, UpdateVatReturnInput,
+    VatReturnFilter, VatReturnStatus, VatReturnSummary,
 };
 use crate::entities::{vat_rate, vat_return};
+use crate::graphql::audit_resolvers::record_audit;
+use crate::graphql::context::{get_current_user, require_company_access, require_company_admin};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::audit_log::AuditEvent;
+use crate::services::vat_return_calculation::{VatReturnCalculation, VatReturnCalculationService};
+use crate::services::vat_return_correction::{VatFileDiff, VatReturnCorrectionService};
 use async_graphql::{Context, FieldResult, Object, SimpleObject, InputObject};
 use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use sea_orm::{
     ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
-    Set, Statement, DbBackend, ConnectionTrait,
+    Set, Statement, DbBackend, ConnectionTrait, TransactionTrait,
 };
 use std::sync::Arc;
 use serde::{Serialize, Deserialize};
@@ -259,6 +266,62 @@ impl VatQuery {
         Ok(vat_return)
     }
 
+    /// DEKLAR fields computed from the VAT journals, with their source
+    /// entries and the differences to the stored values
+    async fn vat_return_calculation(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> FieldResult<VatReturnCalculation> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let calculation = VatReturnCalculationService::preview(db, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        require_company_access(ctx, calculation.vat_return.company_id).await?;
+
+        Ok(calculation)
+    }
+
+    /// All versions of a period's VAT return: the original and its corrections
+    async fn vat_return_versions(
+        &self,
+        ctx: &Context<'_>,
+        company_id: i32,
+        year: i32,
+        month: i32,
+    ) -> FieldResult<Vec<vat_return::Model>> {
+        require_company_access(ctx, company_id).await?;
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        VatReturnCorrectionService::versions(db, company_id, year, month)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
+    /// Differences between the NAP files of a corrective return and the files
+    /// filed for the version it corrects
+    async fn vat_return_file_diff(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> FieldResult<Vec<VatFileDiff>> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let vat_return = vat_return::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("VAT return not found")?;
+        require_company_access(ctx, vat_return.company_id).await?;
+
+        VatReturnCorrectionService::diff(db, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
     /// Get VAT return summary with calculations
     async fn vat_return_summary(
         &self,
@@ -301,6 +364,7 @@ impl VatQuery {
         let returns = vat_return::Entity::find()
             .filter(vat_return::Column::CompanyId.eq(company_id))
             .filter(vat_return::Column::Status.eq(VatReturnStatus::Draft))
+            .filter(vat_return::Column::CorrectsId.is_null())
             .filter(vat_return::Column::DueDate.lt(today))
             .order_by_asc(vat_return::Column::DueDate)
             .all(db)
@@ -630,11 +694,22 @@ impl VatMutation {
             return Err("VAT rate code already exists for this company".into());
         }
 
+        let txn = db.begin().await?;
         let rate_model = vat_rate::ActiveModel::from(input);
         let rate = vat_rate::Entity::insert(rate_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
 
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "vat_rates", rate.id)
+                .company(rate.company_id)
+                .after(&rate),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(rate)
     }
 
@@ -648,12 +723,11 @@ impl VatMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut rate: vat_rate::ActiveModel =
-            if let Some(rate) = vat_rate::Entity::find_by_id(id).one(db).await? {
-                rate.into()
-            } else {
-                return Err("VAT rate not found".into());
-            };
+        let existing = vat_rate::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("VAT rate not found")?;
+        let mut rate: vat_rate::ActiveModel = existing.clone().into();
 
         if let Some(code) = input.code {
             rate.code = Set(code);
@@ -683,7 +757,20 @@ impl VatMutation {
             rate.is_active = Set(is_active);
         }
 
-        let updated_rate = vat_rate::Entity::update(rate).exec(db).await?;
+        let txn = db.begin().await?;
+        let updated_rate = vat_rate::Entity::update(rate).exec(&txn).await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "vat_rates", id)
+                .company(updated_rate.company_id)
+                .before(&existing)
+                .after(&updated_rate),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(updated_rate)
     }
 
@@ -708,14 +795,38 @@ impl VatMutation {
             return Err("VAT return already exists for this period".into());
         }
 
+        let user = get_current_user(ctx)?;
+        let period_from = NaiveDate::from_ymd_opt(input.period_year, input.period_month as u32, 1)
+            .ok_or("Invalid VAT period")?;
+        let txn = db.begin().await?;
+        AccountingPeriodService::ensure_open(&txn, input.company_id, period_from, Some(user.id))
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
         let mut vat_return_model = vat_return::ActiveModel::from(input);
-        // Set created_by - should come from authentication context
-        vat_return_model.created_by = Set(1); // TODO: Get from auth context
+        vat_return_model.created_by = Set(user.id);
 
         let vat_return = vat_return::Entity::insert(vat_return_model)
-            .exec_with_returning(db)
+            .exec_with_returning(&txn)
             .await?;
 
+        // Start from the figures in the VAT journals
+        let vat_return =
+            VatReturnCalculationService::recalculate(&txn, vat_return.id, Some(user.id))
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?
+                .vat_return;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "vat_returns", vat_return.id)
+                .company(vat_return.company_id)
+                .after(&vat_return),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(vat_return)
     }
 
@@ -729,12 +840,21 @@ impl VatMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut vat_return: vat_return::ActiveModel =
-            if let Some(vat_return) = vat_return::Entity::find_by_id(id).one(db).await? {
-                vat_return.into()
-            } else {
-                return Err("VAT return not found".into());
-            };
+        let existing = vat_return::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("VAT return not found")?;
+        let user_id = get_current_user(ctx).ok().map(|user| user.id);
+        let txn = db.begin().await?;
+        AccountingPeriodService::ensure_open(
+            &txn,
+            existing.company_id,
+            existing.period_from,
+            user_id,
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        let mut vat_return: vat_return::ActiveModel = existing.clone().into();
 
         // Update amounts
         if let Some(output_vat) = input.output_vat_amount {
@@ -773,10 +893,57 @@ impl VatMutation {
             vat_return.notes = Set(Some(notes));
         }
 
-        let updated_return = vat_return::Entity::update(vat_return).exec(db).await?;
+        let updated_return = vat_return::Entity::update(vat_return).exec(&txn).await?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "vat_returns", id)
+                .company(updated_return.company_id)
+                .before(&existing)
+                .after(&updated_return),
+        )
+        .await?;
+        txn.commit().await?;
+
         Ok(updated_return)
     }
 
+    /// Refill a draft VAT return from the posted VAT journals of its period
+    async fn recalculate_vat_return(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> FieldResult<VatReturnCalculation> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let user = get_current_user(ctx)?;
+        let existing = vat_return::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("VAT return not found")?;
+        require_company_access(ctx, existing.company_id).await?;
+
+        let txn = db.begin().await?;
+        let calculation = VatReturnCalculationService::recalculate(&txn, id, Some(user.id))
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "vat_returns", id)
+                .company(existing.company_id)
+                .before(&existing)
+                .after(&calculation.vat_return),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(calculation)
+    }
+
     /// Submit VAT return
     async fn submit_vat_return(
         &self,
@@ -786,24 +953,80 @@ impl VatMutation {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
 
-        let mut vat_return: vat_return::ActiveModel =
-            if let Some(vat_return) = vat_return::Entity::find_by_id(id).one(db).await? {
-                if !vat_return.can_be_submitted() {
-                    return Err("VAT return cannot be submitted".into());
-                }
-                vat_return.into()
-            } else {
-                return Err("VAT return not found".into());
-            };
-
-        vat_return.status = Set(VatReturnStatus::Submitted);
-        vat_return.submitted_at = Set(Some(chrono::Utc::now()));
-        vat_return.submitted_by = Set(Some(1)); // TODO: Get from auth context
+        let user = get_current_user(ctx)?;
+        let existing = vat_return::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or("VAT return not found")?;
+        if !existing.can_be_submitted() {
+            return Err("VAT return cannot be submitted".into());
+        }
+        AccountingPeriodService::ensure_open(
+            db,
+            existing.company_id,
+            existing.period_from,
+            Some(user.id),
+        )
+        .await
+        .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        let company_id = existing.company_id;
+        // Keeps the files as filed, supersedes the corrected versions and
+        // hard-closes the period
+        let txn = db.begin().await?;
+        let updated_return =
+            VatReturnCorrectionService::record_submission(&txn, existing.clone(), user.id)
+                .await
+                .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Update, "vat_returns", id)
+                .company(company_id)
+                .before(&existing)
+                .after(&updated_return),
+        )
+        .await?;
+        txn.commit().await?;
 
-        let updated_return = vat_return::Entity::update(vat_return).exec(db).await?;
         Ok(updated_return)
     }
 
+    /// Open a corrective return (поправителна декларация) for a filed period.
+    /// Company admins can then book late documents into the closed period.
+    async fn create_corrective_vat_return(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateCorrectiveVatReturnInput,
+    ) -> FieldResult<vat_return::Model> {
+        let db = ctx.data::<Arc<DatabaseConnection>>()?;
+        let db = db.as_ref();
+
+        let original = vat_return::Entity::find_by_id(input.vat_return_id)
+            .one(db)
+            .await?
+            .ok_or("VAT return not found")?;
+        let user = require_company_admin(ctx, original.company_id).await?;
+
+        let txn = db.begin().await?;
+        let correction = VatReturnCorrectionService::create_correction(&txn, &input, user.id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        record_audit(
+            ctx,
+            &txn,
+            AuditEvent::new(AuditAction::Create, "vat_returns", correction.id)
+                .company(correction.company_id)
+                .after(&correction),
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(correction)
+    }
+
     /// Generate monthly VAT returns for a year
     async fn generate_monthly_returns(
         &self,
@@ -813,6 +1036,7 @@ impl VatMutation {
     ) -> FieldResult<Vec<vat_return::Model>> {
         let db = ctx.data::<Arc<DatabaseConnection>>()?;
         let db = db.as_ref();
+        let user = get_current_user(ctx)?;
 
         let mut generated_returns = Vec::new();
 
@@ -825,7 +1049,17 @@ impl VatMutation {
                 .one(db)
                 .await?;
 
-            if existing.is_none() {
+            // Closed months keep whatever they were filed with
+            let period_open = match NaiveDate::from_ymd_opt(year, month as u32, 1) {
+                Some(period_from) => {
+                    AccountingPeriodService::ensure_open(db, company_id, period_from, Some(user.id))
+                        .await
+                        .is_ok()
+                }
+                None => false,
+            };
+
+            if existing.is_none() && period_open {
                 let input = CreateVatReturnInput {
                     period_year: year,
                     period_month: month,
@@ -834,11 +1068,28 @@ impl VatMutation {
                 };
 
                 let mut vat_return_model = vat_return::ActiveModel::from(input);
-                vat_return_model.created_by = Set(1); // TODO: Get from auth context
+                vat_return_model.created_by = Set(user.id);
 
+                let txn = db.begin().await?;
                 let vat_return = vat_return::Entity::insert(vat_return_model)
-                    .exec_with_returning(db)
+                    .exec_with_returning(&txn)
                     .await?;
+                let vat_return =
+                    VatReturnCalculationService::recalculate(&txn, vat_return.id, Some(user.id))
+                        .await
+                        .map_err(|err| async_graphql::Error::new(err.to_string()))?
+                        .vat_return;
+
+                record_audit(
+                    ctx,
+                    &txn,
+                    AuditEvent::new(AuditAction::Create, "vat_returns", vat_return.id)
+                        .company(company_id)
+                        .after(&vat_return),
+                )
+                .await?;
+                txn.commit().await?;
+
                 generated_returns.push(vat_return);
             }
         }
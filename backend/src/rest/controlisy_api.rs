@@ -1,4 +1,4 @@
-use actix_web::{get, post, web, HttpResponse, Result as ActixResult};
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse, Result as ActixResult};
 use base64::{engine::general_purpose, Engine as _};
 use chrono;
 use sea_orm::{
@@ -7,6 +7,7 @@ use sea_orm::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::auth::AuthenticatedUser;
 use crate::entities::controlisy_imports;
 
 use crate::services::controlisy::ControlisyService;
@@ -210,12 +211,17 @@ pub async fn parse_file(req: web::Json<serde_json::Value>) -> ActixResult<HttpRe
 
 /// REST API endpoint for processing staged import
 pub async fn process_import(
+    http_req: HttpRequest,
     import_id: web::Path<i32>,
     db: web::Data<Arc<DatabaseConnection>>,
 ) -> ActixResult<HttpResponse> {
     let db = db.as_ref();
+    let user_id = http_req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.id);
 
-    match ControlisyService::process_import(db, *import_id).await {
+    match ControlisyService::process_import(db, *import_id, user_id).await {
         Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "message": "Import processed successfully"
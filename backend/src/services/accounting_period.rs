@@ -0,0 +1,277 @@
+//! Accounting Period Service
+//!
+//! Monthly locking of a company's books. Every path that creates, changes,
+//! posts, unposts or deletes journal entries checks the months the entry
+//! touches (its accounting date and its VAT date) before writing:
+//!
+//! - open: anyone with access to the company may book
+//! - soft-closed: only company admins may book
+//! - hard-closed: nobody may book, and the period cannot be reopened. Months
+//!   covered by a submitted VAT return are hard-closed automatically. While a
+//!   corrective VAT return for the month is in draft, company admins may book
+//!   late documents into it.
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use sea_orm::*;
+
+use crate::entities::accounting_period::{
+    self, AccountingPeriodStatus, SetAccountingPeriodStatusInput,
+};
+use crate::entities::vat_return::{self, VatReturnStatus};
+use crate::entities::{journal_entry, user_company};
+
+pub struct AccountingPeriodService;
+
+impl AccountingPeriodService {
+    /// Fail unless `user_id` may change the books on `date`. Inside a
+    /// transaction the period row stays locked until commit, so the month
+    /// cannot be closed while the caller is still writing into it.
+    pub async fn ensure_open<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        date: NaiveDate,
+        user_id: Option<i32>,
+    ) -> Result<()> {
+        let status = Self::find_period(company_id, date)
+            .lock_exclusive()
+            .one(db)
+            .await?
+            .map(|period| period.status)
+            .unwrap_or(AccountingPeriodStatus::Open);
+
+        match status {
+            AccountingPeriodStatus::Open => Ok(()),
+            AccountingPeriodStatus::SoftClosed => {
+                if let Some(user_id) = user_id {
+                    if Self::is_company_admin(db, user_id, company_id).await? {
+                        return Ok(());
+                    }
+                }
+                bail!(
+                    "Period {} is soft-closed; only company admins can change it",
+                    date.format("%Y-%m")
+                )
+            }
+            AccountingPeriodStatus::HardClosed => {
+                if let Some(user_id) = user_id {
+                    if Self::has_draft_correction(db, company_id, date).await?
+                        && Self::is_company_admin(db, user_id, company_id).await?
+                    {
+                        return Ok(());
+                    }
+                }
+                bail!(
+                    "Period {} is closed and cannot be changed",
+                    date.format("%Y-%m")
+                )
+            }
+        }
+    }
+
+    /// Check the months of an entry's accounting date and VAT date
+    pub async fn ensure_dates_open<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        accounting_date: NaiveDate,
+        vat_date: Option<NaiveDate>,
+        user_id: Option<i32>,
+    ) -> Result<()> {
+        Self::ensure_open(db, company_id, accounting_date, user_id).await?;
+        if let Some(vat_date) = vat_date {
+            Self::ensure_open(db, company_id, vat_date, user_id).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn ensure_entry_open<C: ConnectionTrait>(
+        db: &C,
+        entry: &journal_entry::Model,
+        user_id: Option<i32>,
+    ) -> Result<()> {
+        Self::ensure_dates_open(
+            db,
+            entry.company_id,
+            entry.accounting_date,
+            entry.vat_date,
+            user_id,
+        )
+        .await
+    }
+
+    /// Stored periods of a company; months that are not listed are open
+    pub async fn list<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        year: Option<i32>,
+    ) -> Result<Vec<accounting_period::Model>> {
+        let mut query = accounting_period::Entity::find()
+            .filter(accounting_period::Column::CompanyId.eq(company_id));
+        if let Some(year) = year {
+            query = query.filter(accounting_period::Column::Year.eq(year));
+        }
+
+        Ok(query
+            .order_by_asc(accounting_period::Column::Year)
+            .order_by_asc(accounting_period::Column::Month)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn set_status<C: ConnectionTrait>(
+        db: &C,
+        input: &SetAccountingPeriodStatusInput,
+        user_id: Option<i32>,
+    ) -> Result<accounting_period::Model> {
+        if !(1..=12).contains(&input.month) {
+            bail!("Invalid month {}", input.month);
+        }
+
+        let existing = accounting_period::Entity::find()
+            .filter(accounting_period::Column::CompanyId.eq(input.company_id))
+            .filter(accounting_period::Column::Year.eq(input.year))
+            .filter(accounting_period::Column::Month.eq(input.month))
+            .one(db)
+            .await?;
+
+        let current = existing
+            .as_ref()
+            .map(|period| period.status)
+            .unwrap_or(AccountingPeriodStatus::Open);
+        Self::check_transition(current, input.status)?;
+
+        let now = Utc::now();
+        let (closed_by, closed_at) = match input.status {
+            AccountingPeriodStatus::Open => (None, None),
+            _ => (user_id, Some(now)),
+        };
+
+        let period = match existing {
+            Some(existing) => {
+                let notes = input.notes.clone().or(existing.notes.clone());
+                let mut period: accounting_period::ActiveModel = existing.into();
+                period.status = Set(input.status);
+                period.closed_by = Set(closed_by);
+                period.closed_at = Set(closed_at);
+                period.notes = Set(notes);
+                period.updated_at = Set(now);
+                period.update(db).await?
+            }
+            None => {
+                accounting_period::ActiveModel {
+                    company_id: Set(input.company_id),
+                    year: Set(input.year),
+                    month: Set(input.month),
+                    status: Set(input.status),
+                    closed_by: Set(closed_by),
+                    closed_at: Set(closed_at),
+                    notes: Set(input.notes.clone()),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?
+            }
+        };
+
+        Ok(period)
+    }
+
+    /// Hard-close the month containing `date`, e.g. once its VAT return is filed
+    pub async fn hard_close<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        date: NaiveDate,
+        user_id: Option<i32>,
+        notes: Option<String>,
+    ) -> Result<accounting_period::Model> {
+        let existing = Self::find_period(company_id, date)
+            .lock_exclusive()
+            .one(db)
+            .await?;
+        if let Some(period) = existing {
+            if period.status == AccountingPeriodStatus::HardClosed {
+                return Ok(period);
+            }
+        }
+
+        let input = SetAccountingPeriodStatusInput {
+            company_id,
+            year: date.year(),
+            month: date.month() as i32,
+            status: AccountingPeriodStatus::HardClosed,
+            notes,
+        };
+        Self::set_status(db, &input, user_id).await
+    }
+
+    fn find_period(company_id: i32, date: NaiveDate) -> Select<accounting_period::Entity> {
+        accounting_period::Entity::find()
+            .filter(accounting_period::Column::CompanyId.eq(company_id))
+            .filter(accounting_period::Column::Year.eq(date.year()))
+            .filter(accounting_period::Column::Month.eq(date.month() as i32))
+    }
+
+    fn check_transition(from: AccountingPeriodStatus, to: AccountingPeriodStatus) -> Result<()> {
+        if from == AccountingPeriodStatus::HardClosed && to != AccountingPeriodStatus::HardClosed {
+            bail!("A hard-closed period cannot be reopened");
+        }
+        if from == to {
+            bail!("Period is already {:?}", to);
+        }
+        Ok(())
+    }
+
+    /// Whether a corrective VAT return for the month of `date` is in draft
+    async fn has_draft_correction<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        date: NaiveDate,
+    ) -> Result<bool> {
+        let drafts = vat_return::Entity::find()
+            .filter(vat_return::Column::CompanyId.eq(company_id))
+            .filter(vat_return::Column::PeriodYear.eq(date.year()))
+            .filter(vat_return::Column::PeriodMonth.eq(date.month() as i32))
+            .filter(vat_return::Column::Status.eq(VatReturnStatus::Draft))
+            .filter(vat_return::Column::CorrectsId.is_not_null())
+            .count(db)
+            .await?;
+
+        Ok(drafts > 0)
+    }
+
+    async fn is_company_admin<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+        company_id: i32,
+    ) -> Result<bool> {
+        let membership = user_company::Entity::find()
+            .filter(user_company::Column::UserId.eq(user_id))
+            .filter(user_company::Column::CompanyId.eq(company_id))
+            .filter(user_company::Column::IsActive.eq(true))
+            .one(db)
+            .await?;
+
+        Ok(membership.is_some_and(|m| m.role == user_company::UserCompanyRole::Admin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hard_closed_periods_cannot_be_reopened() {
+        use AccountingPeriodStatus::*;
+
+        assert!(AccountingPeriodService::check_transition(Open, SoftClosed).is_ok());
+        assert!(AccountingPeriodService::check_transition(SoftClosed, Open).is_ok());
+        assert!(AccountingPeriodService::check_transition(SoftClosed, HardClosed).is_ok());
+        assert!(AccountingPeriodService::check_transition(Open, HardClosed).is_ok());
+
+        assert!(AccountingPeriodService::check_transition(HardClosed, Open).is_err());
+        assert!(AccountingPeriodService::check_transition(HardClosed, SoftClosed).is_err());
+        assert!(AccountingPeriodService::check_transition(Open, Open).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Audit Log Service
+//!
+//! Append-only trail of changes to a company's books. Every create, update,
+//! post, unpost and delete records the acting user, the company, the changed
+//! record and JSON snapshots of it before and after the change. The trail can
+//! be filtered and exported for tax inspections.
+
+use anyhow::Result;
+use chrono::{Duration, NaiveTime, Utc};
+use rust_xlsxwriter::{Format, Workbook};
+use sea_orm::*;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::entities::audit_log::{self, AuditAction, AuditLogFilter};
+use crate::entities::journal_entry::JournalEntryWithLines;
+use crate::entities::{entry_line, journal_entry, user};
+
+/// A change about to be written to the audit trail
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub action: AuditAction,
+    pub entity_type: &'static str,
+    pub entity_id: Option<i32>,
+    pub company_id: Option<i32>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, entity_type: &'static str, entity_id: i32) -> Self {
+        Self {
+            action,
+            entity_type,
+            entity_id: Some(entity_id),
+            company_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn company(mut self, company_id: i32) -> Self {
+        self.company_id = Some(company_id);
+        self
+    }
+
+    pub fn before<T: Serialize>(mut self, value: &T) -> Self {
+        self.before = serde_json::to_value(value).ok();
+        self
+    }
+
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = serde_json::to_value(value).ok();
+        self
+    }
+}
+
+pub struct AuditLogService;
+
+const EXPORT_HEADERS: [&str; 10] = [
+    "ID",
+    "Дата и час (UTC)",
+    "Фирма",
+    "Потребител ID",
+    "Потребител",
+    "Действие",
+    "Обект",
+    "Обект ID",
+    "Преди",
+    "След",
+];
+
+impl AuditLogService {
+    /// Append an event to the trail on behalf of `user_id`
+    pub async fn record<C: ConnectionTrait>(
+        db: &C,
+        user_id: Option<i32>,
+        event: AuditEvent,
+    ) -> Result<audit_log::Model> {
+        let username = match user_id {
+            Some(user_id) => user::Entity::find_by_id(user_id)
+                .one(db)
+                .await?
+                .map(|user| user.username),
+            None => None,
+        };
+
+        let log = audit_log::ActiveModel {
+            company_id: Set(event.company_id),
+            user_id: Set(user_id),
+            username: Set(username),
+            action: Set(event.action),
+            entity_type: Set(event.entity_type.to_string()),
+            entity_id: Set(event.entity_id),
+            before_data: Set(event.before),
+            after_data: Set(event.after),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+
+        Ok(audit_log::Entity::insert(log)
+            .exec_with_returning(db)
+            .await?)
+    }
+
+    /// Journal entry together with its lines, as stored in the trail
+    pub async fn journal_entry_snapshot<C: ConnectionTrait>(
+        db: &C,
+        entry_id: i32,
+    ) -> Result<Option<JournalEntryWithLines>> {
+        let Some(entry) = journal_entry::Entity::find_by_id(entry_id).one(db).await? else {
+            return Ok(None);
+        };
+        let lines = entry_line::Entity::find()
+            .filter(entry_line::Column::JournalEntryId.eq(entry_id))
+            .order_by_asc(entry_line::Column::LineOrder)
+            .all(db)
+            .await?;
+
+        Ok(Some(JournalEntryWithLines {
+            journal_entry: entry,
+            lines,
+        }))
+    }
+
+    /// Entries matching the filter, oldest first
+    pub async fn list<C: ConnectionTrait>(
+        db: &C,
+        filter: &AuditLogFilter,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<audit_log::Model>> {
+        let mut query =
+            audit_log::Entity::find().filter(audit_log::Column::CompanyId.eq(filter.company_id));
+
+        if let Some(entity_type) = &filter.entity_type {
+            query = query.filter(audit_log::Column::EntityType.eq(entity_type.as_str()));
+        }
+        if let Some(entity_id) = filter.entity_id {
+            query = query.filter(audit_log::Column::EntityId.eq(entity_id));
+        }
+        if let Some(user_id) = filter.user_id {
+            query = query.filter(audit_log::Column::UserId.eq(user_id));
+        }
+        if let Some(action) = filter.action {
+            query = query.filter(audit_log::Column::Action.eq(action));
+        }
+        if let Some(from_date) = filter.from_date {
+            query = query.filter(
+                audit_log::Column::CreatedAt.gte(from_date.and_time(NaiveTime::MIN).and_utc()),
+            );
+        }
+        if let Some(to_date) = filter.to_date {
+            let end = (to_date + Duration::days(1))
+                .and_time(NaiveTime::MIN)
+                .and_utc();
+            query = query.filter(audit_log::Column::CreatedAt.lt(end));
+        }
+
+        let mut query = query
+            .order_by_asc(audit_log::Column::CreatedAt)
+            .order_by_asc(audit_log::Column::Id);
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        if let Some(offset) = offset {
+            query = query.offset(offset);
+        }
+
+        Ok(query.all(db).await?)
+    }
+
+    fn export_row(log: &audit_log::Model) -> [String; 10] {
+        let json = |value: &Option<Value>| {
+            value
+                .as_ref()
+                .map(|value| value.to_string())
+                .unwrap_or_default()
+        };
+
+        [
+            log.id.to_string(),
+            log.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            log.company_id.map(|id| id.to_string()).unwrap_or_default(),
+            log.user_id.map(|id| id.to_string()).unwrap_or_default(),
+            log.username.clone().unwrap_or_default(),
+            log.action.as_str().to_string(),
+            log.entity_type.clone(),
+            log.entity_id.map(|id| id.to_string()).unwrap_or_default(),
+            json(&log.before_data),
+            json(&log.after_data),
+        ]
+    }
+
+    pub fn export_csv(logs: &[audit_log::Model]) -> Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(EXPORT_HEADERS)?;
+        for log in logs {
+            writer.write_record(Self::export_row(log))?;
+        }
+        Ok(writer.into_inner()?)
+    }
+
+    pub fn export_xlsx(logs: &[audit_log::Model]) -> Result<Vec<u8>> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("Одит")?;
+
+        let header_format = Format::new().set_bold();
+        for (col, header) in EXPORT_HEADERS.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, *header, &header_format)?;
+        }
+        for (row, log) in logs.iter().enumerate() {
+            for (col, value) in Self::export_row(log).iter().enumerate() {
+                worksheet.write_string(row as u32 + 1, col as u16, value)?;
+            }
+        }
+
+        Ok(workbook.save_to_buffer()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn csv_export_keeps_before_and_after_snapshots() {
+        let event = AuditEvent::new(AuditAction::Update, "vat_rates", 5)
+            .company(2)
+            .before(&json!({ "rate": 9 }))
+            .after(&json!({ "rate": 20 }));
+
+        let log = audit_log::Model {
+            id: 1,
+            company_id: event.company_id,
+            user_id: Some(3),
+            username: Some("ivan".to_string()),
+            action: event.action,
+            entity_type: event.entity_type.to_string(),
+            entity_id: event.entity_id,
+            before_data: event.before,
+            after_data: event.after,
+            created_at: Utc.with_ymd_and_hms(2025, 3, 4, 10, 30, 0).unwrap(),
+        };
+
+        let csv = String::from_utf8(AuditLogService::export_csv(&[log]).unwrap()).unwrap();
+        let mut rows = csv.lines();
+        assert!(rows.next().unwrap().starts_with("ID,"));
+        assert_eq!(
+            rows.next().unwrap(),
+            r#"1,2025-03-04 10:30:00,2,3,ivan,UPDATE,vat_rates,5,"{""rate"":9}","{""rate"":20}""#
+        );
+    }
+}
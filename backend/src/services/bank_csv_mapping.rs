@@ -0,0 +1,482 @@
+//! CSV Statement Mapping
+//!
+//! Bank profiles with the `CUSTOM_CSV` import format keep a column-mapping
+//! template under the `csvMapping` key of their settings. The template says
+//! how the file is encoded and delimited, how many rows to skip, whether a
+//! header row names the columns, how dates and amounts are written and
+//! which columns hold the booking date, the amount (signed, in debit and
+//! credit columns, or with a direction column), the description and the
+//! reference.
+//!
+//! Columns are given by their 1-based number or by their header name.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+use crate::services::bank_imports::{BankImportService, BankTransaction};
+
+/// Key of the template in `bank_profiles.settings`
+pub const CSV_MAPPING_SETTINGS_KEY: &str = "csvMapping";
+
+/// Direction values of a credit when not configured
+const DEFAULT_CREDIT_INDICATORS: [&str; 6] = ["C", "CR", "CRDT", "К", "КТ", "КРЕДИТ"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    /// 1-based column number
+    Number(usize),
+    /// Header name, matched case-insensitively
+    Header(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvMapping {
+    /// `;` by default; `\t` or `tab` for tab-separated files
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+    /// Encoding label such as `UTF-8` or `windows-1251`; UTF-8 with a
+    /// Windows-1251 fallback when missing
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// chrono format (`%d.%m.%Y`) or `DD.MM.YYYY` style
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    /// Rows before the header row, or before the data without one
+    #[serde(default)]
+    pub skip_rows: usize,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    pub booking_date_column: CsvColumn,
+    #[serde(default)]
+    pub value_date_column: Option<CsvColumn>,
+    /// Signed amount, negative for outgoing payments, unless a direction
+    /// column is set
+    #[serde(default)]
+    pub amount_column: Option<CsvColumn>,
+    /// Outgoing payments need an amount column or debit and credit columns
+    #[serde(default)]
+    pub debit_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub credit_column: Option<CsvColumn>,
+    /// Debit/credit flag of an unsigned amount column
+    #[serde(default)]
+    pub direction_column: Option<CsvColumn>,
+    /// Values of the direction column meaning a credit
+    #[serde(default)]
+    pub credit_indicators: Vec<String>,
+    /// Files where outgoing payments are positive
+    #[serde(default)]
+    pub negate_amounts: bool,
+    /// Joined with spaces into the transaction description
+    #[serde(default)]
+    pub description_columns: Vec<CsvColumn>,
+    #[serde(default)]
+    pub reference_column: Option<CsvColumn>,
+    /// Defaults to the currency of the bank profile
+    #[serde(default)]
+    pub currency_column: Option<CsvColumn>,
+}
+
+/// First rows of a file parsed with a mapping
+#[derive(SimpleObject, Debug, Clone)]
+pub struct CsvMappingPreview {
+    pub headers: Vec<String>,
+    pub rows: Vec<CsvPreviewRow>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct CsvPreviewRow {
+    /// 1-based line of the file
+    pub line_number: i32,
+    pub fields: Vec<String>,
+    pub booking_date: Option<NaiveDate>,
+    pub value_date: Option<NaiveDate>,
+    pub amount: Option<Decimal>,
+    pub is_credit: Option<bool>,
+    pub currency: Option<String>,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    /// Why the row cannot be imported
+    pub error: Option<String>,
+}
+
+/// Column positions of a mapping resolved against the header row
+struct ResolvedColumns {
+    booking_date: usize,
+    value_date: Option<usize>,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    direction: Option<usize>,
+    description: Vec<usize>,
+    reference: Option<usize>,
+    currency: Option<usize>,
+}
+
+impl CsvMapping {
+    pub fn from_settings(settings: Option<&Value>) -> Result<Self> {
+        let template = settings
+            .and_then(|settings| settings.get(CSV_MAPPING_SETTINGS_KEY))
+            .ok_or_else(|| anyhow!("The bank profile has no CSV mapping in its settings"))?;
+        Self::from_value(template)
+    }
+
+    pub fn from_value(template: &Value) -> Result<Self> {
+        let mapping: CsvMapping = serde_json::from_value(template.clone())
+            .map_err(|err| anyhow!("Invalid CSV mapping: {}", err))?;
+        if mapping.amount_column.is_none()
+            && (mapping.debit_column.is_none() || mapping.credit_column.is_none())
+        {
+            bail!("The CSV mapping needs an amount column or debit and credit columns");
+        }
+        Ok(mapping)
+    }
+
+    pub fn decode(&self, content: &[u8]) -> Result<String> {
+        let text = match self.encoding.as_deref().map(str::trim) {
+            Some(label) if !label.is_empty() => {
+                let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| anyhow!("Unknown encoding {}", label))?;
+                encoding.decode(content).0.into_owned()
+            }
+            _ => match std::str::from_utf8(content) {
+                Ok(text) => text.to_string(),
+                Err(_) => encoding_rs::WINDOWS_1251.decode(content).0.into_owned(),
+            },
+        };
+        Ok(text.trim_start_matches('\u{feff}').replace('\r', ""))
+    }
+
+    /// Transactions of the file; a row that cannot be read fails the whole
+    /// import with its line number
+    pub fn parse(&self, content: &str, currency_code: &str) -> Result<Vec<BankTransaction>> {
+        let preview = self.preview(content, currency_code, usize::MAX)?;
+        let mut transactions = Vec::with_capacity(preview.rows.len());
+
+        for row in preview.rows {
+            if let Some(error) = row.error {
+                bail!("Line {}: {}", row.line_number, error);
+            }
+            let (Some(booking_date), Some(amount), Some(is_credit)) =
+                (row.booking_date, row.amount, row.is_credit)
+            else {
+                continue;
+            };
+            transactions.push(BankTransaction {
+                booking_date,
+                value_date: row.value_date,
+                amount,
+                currency: row.currency.unwrap_or_else(|| currency_code.to_string()),
+                is_credit,
+                description: row.description.unwrap_or_default(),
+                reference: row.reference,
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    /// Parse up to `limit` data rows, keeping the errors per row so the
+    /// mapping can be corrected
+    pub fn preview(
+        &self,
+        content: &str,
+        currency_code: &str,
+        limit: usize,
+    ) -> Result<CsvMappingPreview> {
+        let delimiter = self.delimiter_char();
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .skip(self.skip_rows)
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let headers = if self.has_header {
+            let (_, header) = lines
+                .next()
+                .ok_or_else(|| anyhow!("The file has no header row"))?;
+            BankImportService::split_csv_line(header, delimiter)
+                .into_iter()
+                .map(|header| header.trim().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let columns = self.resolve_columns(&headers)?;
+
+        let mut rows = Vec::new();
+        for (index, line) in lines.take(limit) {
+            let fields: Vec<String> = BankImportService::split_csv_line(line, delimiter)
+                .into_iter()
+                .map(|field| field.trim().to_string())
+                .collect();
+            let mut row = CsvPreviewRow {
+                line_number: index as i32 + 1,
+                fields,
+                booking_date: None,
+                value_date: None,
+                amount: None,
+                is_credit: None,
+                currency: None,
+                description: None,
+                reference: None,
+                error: None,
+            };
+            if let Err(err) = self.read_row(&mut row, &columns, currency_code) {
+                row.error = Some(err.to_string());
+            }
+            rows.push(row);
+        }
+
+        Ok(CsvMappingPreview { headers, rows })
+    }
+
+    fn read_row(
+        &self,
+        row: &mut CsvPreviewRow,
+        columns: &ResolvedColumns,
+        currency_code: &str,
+    ) -> Result<()> {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| row.fields.get(index))
+                .map(String::as_str)
+                .filter(|value| !value.is_empty())
+        };
+
+        // Totals and footer rows have no date
+        let Some(booking_date) = field(Some(columns.booking_date)) else {
+            return Ok(());
+        };
+        let booking_date = self.parse_date(booking_date)?;
+        let value_date = field(columns.value_date)
+            .map(|value| self.parse_date(value))
+            .transpose()?;
+
+        let (amount, is_credit) = if columns.debit.is_some() || columns.credit.is_some() {
+            let credit = field(columns.credit)
+                .map(|value| self.parse_amount(value))
+                .transpose()?
+                .filter(|amount| !amount.is_zero());
+            let debit = field(columns.debit)
+                .map(|value| self.parse_amount(value))
+                .transpose()?
+                .filter(|amount| !amount.is_zero());
+            match (credit, debit) {
+                (Some(credit), _) => (credit.abs(), true),
+                (None, Some(debit)) => (debit.abs(), false),
+                (None, None) => return Ok(()),
+            }
+        } else {
+            let amount = field(columns.amount)
+                .map(|value| self.parse_amount(value))
+                .transpose()?
+                .filter(|amount| !amount.is_zero());
+            let Some(amount) = amount else {
+                return Ok(());
+            };
+            let is_credit = match columns.direction {
+                Some(_) => {
+                    let direction = field(columns.direction)
+                        .ok_or_else(|| anyhow!("Missing debit/credit flag"))?
+                        .to_uppercase();
+                    if self.credit_indicators.is_empty() {
+                        DEFAULT_CREDIT_INDICATORS.contains(&direction.as_str())
+                    } else {
+                        self.credit_indicators
+                            .iter()
+                            .any(|indicator| indicator.trim().to_uppercase() == direction)
+                    }
+                }
+                None => (amount > Decimal::ZERO) != self.negate_amounts,
+            };
+            (amount.abs(), is_credit)
+        };
+
+        let description = columns
+            .description
+            .iter()
+            .filter_map(|index| field(Some(*index)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let reference = field(columns.reference).map(str::to_string);
+
+        row.booking_date = Some(booking_date);
+        row.value_date = value_date;
+        row.amount = Some(amount);
+        row.is_credit = Some(is_credit);
+        row.currency = Some(
+            field(columns.currency)
+                .map(str::to_uppercase)
+                .unwrap_or_else(|| currency_code.to_string()),
+        );
+        row.description = Some(if description.is_empty() {
+            reference.clone().unwrap_or_default()
+        } else {
+            description
+        });
+        row.reference = reference;
+        Ok(())
+    }
+
+    fn resolve_columns(&self, headers: &[String]) -> Result<ResolvedColumns> {
+        let resolve = |column: &CsvColumn| -> Result<usize> {
+            match column {
+                CsvColumn::Number(0) => bail!("Column numbers start at 1"),
+                CsvColumn::Number(number) => Ok(number - 1),
+                CsvColumn::Header(name) => {
+                    if headers.is_empty() {
+                        bail!("Column \"{}\" needs a header row", name);
+                    }
+                    let wanted = name.trim().to_lowercase();
+                    headers
+                        .iter()
+                        .position(|header| header.trim().to_lowercase() == wanted)
+                        .ok_or_else(|| anyhow!("No column named \"{}\" in the header", name))
+                }
+            }
+        };
+        let resolve_optional =
+            |column: &Option<CsvColumn>| column.as_ref().map(resolve).transpose();
+
+        Ok(ResolvedColumns {
+            booking_date: resolve(&self.booking_date_column)?,
+            value_date: resolve_optional(&self.value_date_column)?,
+            amount: resolve_optional(&self.amount_column)?,
+            debit: resolve_optional(&self.debit_column)?,
+            credit: resolve_optional(&self.credit_column)?,
+            direction: resolve_optional(&self.direction_column)?,
+            description: self
+                .description_columns
+                .iter()
+                .map(resolve)
+                .collect::<Result<_>>()?,
+            reference: resolve_optional(&self.reference_column)?,
+            currency: resolve_optional(&self.currency_column)?,
+        })
+    }
+
+    fn delimiter_char(&self) -> char {
+        match self.delimiter.as_str() {
+            "\\t" | "tab" | "TAB" => '\t',
+            delimiter => delimiter.chars().next().unwrap_or(';'),
+        }
+    }
+
+    fn parse_date(&self, value: &str) -> Result<NaiveDate> {
+        let format = if self.date_format.contains('%') {
+            self.date_format.clone()
+        } else {
+            self.date_format
+                .replace("YYYY", "%Y")
+                .replace("YY", "%y")
+                .replace("MM", "%m")
+                .replace("DD", "%d")
+        };
+        NaiveDate::parse_from_str(value, &format)
+            .map_err(|_| anyhow!("Date \"{}\" does not match {}", value, self.date_format))
+    }
+
+    fn parse_amount(&self, value: &str) -> Result<Decimal> {
+        let mut normalized: String = value
+            .chars()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+'))
+            .collect();
+        if self.decimal_separator == "," {
+            normalized = normalized.replace('.', "").replace(',', ".");
+        } else {
+            normalized = normalized.replace(',', "");
+        }
+        // Some banks write the sign after the amount
+        if let Some(unsigned) = normalized.strip_suffix('-') {
+            normalized = format!("-{}", unsigned);
+        }
+        Decimal::from_str(&normalized).map_err(|_| anyhow!("Invalid amount \"{}\"", value))
+    }
+}
+
+fn default_delimiter() -> String {
+    ";".to_string()
+}
+
+fn default_date_format() -> String {
+    "%d.%m.%Y".to_string()
+}
+
+fn default_decimal_separator() -> String {
+    ",".to_string()
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn csv_mapping_reads_signed_amounts_by_header_and_reports_bad_rows() {
+        let settings = json!({
+            "csvMapping": {
+                "delimiter": ",",
+                "dateFormat": "YYYY-MM-DD",
+                "decimalSeparator": ".",
+                "skipRows": 1,
+                "bookingDateColumn": "Date",
+                "amountColumn": "Amount",
+                "descriptionColumns": ["Payee", 4],
+                "referenceColumn": 5
+            }
+        });
+        let mapping = CsvMapping::from_settings(Some(&settings)).unwrap();
+        let content = "\
+Statement BG80BNBG96611020345678
+Date,Amount,Payee,Details,Ref
+2025-10-01,\"1,250.00\",Client Ltd,Invoice 7001,R1
+2025-10-02,-35.20,Bank,Monthly fee,R2
+01/10/2025,10.00,Someone,Wrong date,R3
+,1215.80,,Total,
+";
+
+        let preview = mapping.preview(content, "BGN", 10).unwrap();
+        assert_eq!(
+            preview.headers,
+            vec!["Date", "Amount", "Payee", "Details", "Ref"]
+        );
+        assert_eq!(preview.rows.len(), 4);
+        assert_eq!(preview.rows[0].line_number, 3);
+        assert_eq!(preview.rows[0].is_credit, Some(true));
+        assert_eq!(preview.rows[0].amount, Some(Decimal::from(1250)));
+        assert_eq!(
+            preview.rows[0].description.as_deref(),
+            Some("Client Ltd Invoice 7001")
+        );
+        assert_eq!(preview.rows[1].is_credit, Some(false));
+        assert!(preview.rows[2].error.is_some());
+        assert!(preview.rows[3].booking_date.is_none() && preview.rows[3].error.is_none());
+
+        let error = mapping.parse(content, "BGN").unwrap_err();
+        assert!(error.to_string().starts_with("Line 5:"));
+
+        let valid: String = content
+            .lines()
+            .filter(|line| !line.contains("R3"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let transactions = mapping.parse(&valid, "BGN").unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[1].amount, Decimal::from_str("35.20").unwrap());
+        assert_eq!(transactions[1].reference.as_deref(), Some("R2"));
+    }
+}
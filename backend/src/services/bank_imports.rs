@@ -1,18 +1,30 @@
 use anyhow::{anyhow, Result};
 use chrono::{NaiveDate, NaiveDateTime, Utc};
 use quick_xml::de::from_str as from_xml_str;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use sea_orm::prelude::*;
 use sea_orm::{
-    ActiveModelTrait, DatabaseConnection, DatabaseTransaction, EntityTrait, Set, TransactionTrait,
+    ActiveModelTrait, DatabaseConnection, DatabaseTransaction, EntityTrait, QueryOrder, Set,
+    TransactionTrait,
 };
 use serde::Deserialize;
 use serde_json::json;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
+use crate::entities::audit_log::AuditAction;
 use crate::entities::{
-    bank_import, entry_line, journal_entry, BankImportFormat, BankImportStatus, BankProfileModel,
+    ai_bank_accounting_setting, bank_import, bank_transaction_fingerprint, entry_line,
+    journal_entry, vat_rate, BankImportFormat, BankImportStatus, BankProfileModel,
+    JournalSeriesKind,
 };
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+use crate::services::bank_csv_mapping::CsvMapping;
+use crate::services::bank_transaction_parser::BankTransactionParser;
+use crate::services::bnb_service::BnbService;
+use crate::services::euro_changeover::EuroChangeoverService;
+use crate::services::journal_numbering::JournalNumberingService;
 
 pub struct BankImportService;
 
@@ -27,12 +39,84 @@ pub struct BankTransaction {
     pub reference: Option<String>,
 }
 
+/// Key a transaction is recognised by when the same statement, or one
+/// overlapping it, is imported again
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionFingerprint {
+    pub fingerprint: String,
+    /// Positive for incoming, negative for outgoing payments
+    pub signed_amount: Decimal,
+    pub reference: Option<String>,
+    pub counterparty_iban: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PreviewTransaction {
+    /// Position in the statement, used to leave transactions out of the import
+    pub index: usize,
+    pub transaction: BankTransaction,
+    pub fingerprint: TransactionFingerprint,
+    /// Journal entry that booked the same transaction in an earlier import
+    pub duplicate_of: Option<i32>,
+    /// Highest-priority bank rule matching the transaction; it is posted to
+    /// the rule's account instead of the buffer account
+    pub rule: Option<ai_bank_accounting_setting::Model>,
+    /// VAT rate of the matching rule
+    pub vat_rate: Option<vat_rate::Model>,
+}
+
+/// Counter lines of a transaction posted by a bank rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RulePosting {
+    pub account_id: i32,
+    pub net_amount: Decimal,
+    pub vat_account_id: Option<i32>,
+    pub vat_amount: Decimal,
+    pub vat_rate_id: Option<i32>,
+    /// Percentage the VAT was split off at, zero without a VAT line
+    pub vat_rate: Decimal,
+    /// VAT operation of the rule the entry is reported under in the VAT
+    /// journals, set whenever the rule carries a VAT rate
+    pub vat_operation: Option<String>,
+}
+
+impl RulePosting {
+    /// Whether the entry goes to the purchase journal rather than the sales one
+    pub fn is_purchase(&self) -> bool {
+        self.vat_operation
+            .as_deref()
+            .is_some_and(|operation| operation.starts_with("пок"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatementPreview {
+    pub transactions: Vec<PreviewTransaction>,
+    pub statement_from: Option<NaiveDate>,
+    pub statement_to: Option<NaiveDate>,
+    /// Earlier imports of the profile whose booking dates overlap this statement
+    pub overlapping_imports: Vec<bank_import::Model>,
+}
+
+impl StatementPreview {
+    pub fn duplicate_count(&self) -> usize {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.duplicate_of.is_some())
+            .count()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ImportSummary {
     pub transactions: usize,
     pub journal_entry_ids: Vec<i32>,
     pub total_debit: Decimal,
     pub total_credit: Decimal,
+    pub skipped_duplicates: usize,
+    /// Transactions posted by a bank rule
+    pub auto_posted: usize,
+    pub overlapping_import_ids: Vec<i32>,
     pub bank_import: bank_import::Model,
 }
 
@@ -46,16 +130,67 @@ impl BankImportService {
             BankImportFormat::PostbankXml,
             BankImportFormat::ObbXml,
             BankImportFormat::CcbCsv,
+            BankImportFormat::DskCsv,
+            BankImportFormat::FibankXml,
+            BankImportFormat::ObbCsv,
+            BankImportFormat::ProcreditMt940,
+            BankImportFormat::AllianzMt940,
+            BankImportFormat::Mt942,
+            BankImportFormat::Camt052,
+            BankImportFormat::Camt054,
+            BankImportFormat::CustomCsv,
         ]
     }
 
+    /// Book a statement. Transactions booked by an earlier import of the
+    /// profile are skipped, as are those listed in `skip_indexes`, so
+    /// importing the same file twice does not double the bank account.
     pub async fn import_statement(
         db: &DatabaseConnection,
         profile: &BankProfileModel,
         file_name: &str,
         file_content: &[u8],
         created_by: Option<i32>,
+        skip_indexes: &[usize],
     ) -> Result<ImportSummary> {
+        let transactions = Self::read_statement(profile, file_content)?;
+
+        let created_by = created_by.unwrap_or(1);
+
+        let preview = Self::check_duplicates(db, profile, transactions).await?;
+        let rates = Self::load_rates(db, profile.company_id, &preview).await?;
+
+        let txn = db.begin().await?;
+        let result = Self::persist_transactions(
+            &txn,
+            profile,
+            file_name,
+            &preview,
+            &rates,
+            skip_indexes,
+            created_by,
+        )
+        .await?;
+        txn.commit().await?;
+
+        Ok(result)
+    }
+
+    /// Parse a statement and mark the transactions that are already booked
+    /// and the bank rules that would post them, without changing anything
+    pub async fn preview_statement(
+        db: &DatabaseConnection,
+        profile: &BankProfileModel,
+        file_content: &[u8],
+    ) -> Result<StatementPreview> {
+        let transactions = Self::read_statement(profile, file_content)?;
+        Self::check_duplicates(db, profile, transactions).await
+    }
+
+    fn read_statement(
+        profile: &BankProfileModel,
+        file_content: &[u8],
+    ) -> Result<Vec<BankTransaction>> {
         if profile.import_format.is_empty() {
             return Err(anyhow!(
                 "Bank profile {} has no import format configured",
@@ -68,26 +203,243 @@ impl BankImportService {
             .parse()
             .map_err(|e| anyhow!("{}", e))?;
 
-        let content_str = Self::decode_to_string(file_content, format)?;
+        let content_str = match format {
+            BankImportFormat::CustomCsv => {
+                CsvMapping::from_settings(profile.settings.as_ref())?.decode(file_content)?
+            }
+            _ => Self::decode_to_string(file_content, format)?,
+        };
         let transactions = Self::parse_transactions(&content_str, format, profile)?;
 
         if transactions.is_empty() {
             return Err(anyhow!("No transactions found in supplied file"));
         }
 
-        let created_by = created_by.unwrap_or(1);
+        Ok(transactions)
+    }
 
-        let txn = db.begin().await?;
-        let result =
-            Self::persist_transactions(&txn, profile, file_name, &transactions, created_by).await?;
-        txn.commit().await?;
+    /// Rate to the base currency for every currency and booking date of the
+    /// transactions that will be booked
+    async fn load_rates(
+        db: &DatabaseConnection,
+        company_id: i32,
+        preview: &StatementPreview,
+    ) -> Result<HashMap<(String, NaiveDate), Decimal>> {
+        let bnb_service = BnbService::new();
+        let mut rates = HashMap::new();
+
+        for previewed in &preview.transactions {
+            if previewed.duplicate_of.is_some() {
+                continue;
+            }
+            let tx = &previewed.transaction;
+            let key = (tx.currency.clone(), tx.booking_date);
+            if rates.contains_key(&key) {
+                continue;
+            }
+            let base_currency =
+                EuroChangeoverService::base_currency_code(db, company_id, tx.booking_date).await?;
+            let rate = bnb_service
+                .get_rate_to_base(db, &tx.currency, &base_currency, tx.booking_date)
+                .await?;
+            rates.insert(key, rate);
+        }
 
-        Ok(result)
+        Ok(rates)
+    }
+
+    async fn check_duplicates<C: ConnectionTrait>(
+        db: &C,
+        profile: &BankProfileModel,
+        transactions: Vec<BankTransaction>,
+    ) -> Result<StatementPreview> {
+        let fingerprints = Self::fingerprints(&transactions);
+
+        let booked: HashMap<String, i32> = bank_transaction_fingerprint::Entity::find()
+            .filter(bank_transaction_fingerprint::Column::BankProfileId.eq(profile.id))
+            .filter(
+                bank_transaction_fingerprint::Column::Fingerprint
+                    .is_in(fingerprints.iter().map(|f| f.fingerprint.clone())),
+            )
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|booked| (booked.fingerprint, booked.journal_entry_id))
+            .collect();
+
+        let statement_from = transactions.iter().map(|tx| tx.booking_date).min();
+        let statement_to = transactions.iter().map(|tx| tx.booking_date).max();
+
+        let overlapping_imports = match (statement_from, statement_to) {
+            (Some(from), Some(to)) => {
+                bank_import::Entity::find()
+                    .filter(bank_import::Column::BankProfileId.eq(profile.id))
+                    .filter(bank_import::Column::StatementFrom.lte(to))
+                    .filter(bank_import::Column::StatementTo.gte(from))
+                    .order_by_asc(bank_import::Column::StatementFrom)
+                    .all(db)
+                    .await?
+            }
+            _ => Vec::new(),
+        };
+
+        let rules = BankTransactionParser::active_rules(db, profile.company_id).await?;
+        let vat_rate_ids: Vec<i32> = rules.iter().filter_map(|rule| rule.vat_rate_id).collect();
+        let vat_rates: HashMap<i32, vat_rate::Model> = if vat_rate_ids.is_empty() {
+            HashMap::new()
+        } else {
+            vat_rate::Entity::find()
+                .filter(vat_rate::Column::Id.is_in(vat_rate_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|rate| (rate.id, rate))
+                .collect()
+        };
+
+        let transactions = transactions
+            .into_iter()
+            .zip(fingerprints)
+            .enumerate()
+            .map(|(index, (transaction, fingerprint))| {
+                let rule = BankTransactionParser::select_rule(
+                    &rules,
+                    &transaction.description,
+                    transaction.is_credit,
+                )
+                .cloned();
+                let vat_rate = rule
+                    .as_ref()
+                    .and_then(|rule| rule.vat_rate_id)
+                    .and_then(|id| vat_rates.get(&id))
+                    .cloned();
+                PreviewTransaction {
+                    index,
+                    duplicate_of: booked.get(&fingerprint.fingerprint).copied(),
+                    rule,
+                    vat_rate,
+                    transaction,
+                    fingerprint,
+                }
+            })
+            .collect();
+
+        Ok(StatementPreview {
+            transactions,
+            statement_from,
+            statement_to,
+            overlapping_imports,
+        })
+    }
+
+    /// Split of a transaction amount posted by a rule. With a VAT account the
+    /// amount is taken to include VAT at the rule's VAT rate; a rule without
+    /// a rate, or with a zero rate, posts the whole amount to its account.
+    pub fn rule_posting(
+        rule: &ai_bank_accounting_setting::Model,
+        vat_rate: Option<&vat_rate::Model>,
+        amount: Decimal,
+    ) -> RulePosting {
+        let rate = vat_rate
+            .filter(|_| rule.vat_account_id.is_some())
+            .map(|vat_rate| vat_rate.rate)
+            .unwrap_or_default();
+        let vat_amount = if rate > Decimal::ZERO {
+            (amount * rate / (Decimal::ONE_HUNDRED + rate))
+                .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+        } else {
+            Decimal::ZERO
+        };
+        RulePosting {
+            account_id: rule.account_id.unwrap_or_default(),
+            net_amount: amount - vat_amount,
+            vat_account_id: rule.vat_account_id.filter(|_| !vat_amount.is_zero()),
+            vat_amount,
+            vat_rate_id: vat_rate.map(|vat_rate| vat_rate.id),
+            vat_rate: rate,
+            vat_operation: rule
+                .vat_operation
+                .clone()
+                .filter(|_| rule.vat_account_id.is_some() && vat_rate.is_some()),
+        }
+    }
+
+    /// Fingerprints of the transactions of one statement. Identical
+    /// transactions within the statement are told apart by their occurrence.
+    pub fn fingerprints(transactions: &[BankTransaction]) -> Vec<TransactionFingerprint> {
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+        transactions
+            .iter()
+            .map(|tx| {
+                let signed_amount = if tx.is_credit {
+                    tx.amount.abs()
+                } else {
+                    -tx.amount.abs()
+                }
+                .normalize();
+                let reference = tx
+                    .reference
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|reference| !reference.is_empty())
+                    .map(str::to_string);
+                let counterparty_iban = Self::extract_iban(&tx.description);
+                let description = tx
+                    .description
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase();
+
+                let key = format!(
+                    "{}|{}|{}|{}|{:016x}",
+                    tx.booking_date,
+                    signed_amount,
+                    reference.as_deref().unwrap_or_default(),
+                    counterparty_iban.as_deref().unwrap_or_default(),
+                    Self::fnv1a(&description)
+                );
+                let occurrence = occurrences.entry(key.clone()).or_default();
+                *occurrence += 1;
+
+                TransactionFingerprint {
+                    fingerprint: format!(
+                        "{:016x}",
+                        Self::fnv1a(&format!("{}|{}", key, occurrence))
+                    ),
+                    signed_amount,
+                    reference,
+                    counterparty_iban,
+                }
+            })
+            .collect()
+    }
+
+    /// First IBAN-shaped word of a payment description
+    fn extract_iban(text: &str) -> Option<String> {
+        text.split(|c: char| !c.is_ascii_alphanumeric())
+            .find(|word| {
+                (15..=34).contains(&word.len())
+                    && word[..2].chars().all(|c| c.is_ascii_alphabetic())
+                    && word[2..4].chars().all(|c| c.is_ascii_digit())
+            })
+            .map(str::to_ascii_uppercase)
+    }
+
+    /// FNV-1a; stable across builds, unlike the std hasher
+    fn fnv1a(text: &str) -> u64 {
+        text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
     }
 
     fn decode_to_string(content: &[u8], format: BankImportFormat) -> Result<String> {
         match format {
-            BankImportFormat::UnicreditMt940 => {
+            BankImportFormat::UnicreditMt940
+            | BankImportFormat::ProcreditMt940
+            | BankImportFormat::AllianzMt940
+            | BankImportFormat::Mt942 => {
                 // Files from banks may be encoded in Windows-1251; try UTF-8 first then fallback
                 if let Ok(text) = std::str::from_utf8(content) {
                     Ok(text.replace('\r', ""))
@@ -96,7 +448,7 @@ impl BankImportService {
                     Ok(cow.into_owned().replace('\r', ""))
                 }
             }
-            BankImportFormat::CcbCsv => {
+            BankImportFormat::CcbCsv | BankImportFormat::DskCsv | BankImportFormat::ObbCsv => {
                 if let Ok(text) = std::str::from_utf8(content) {
                     Ok(text.replace('\r', ""))
                 } else {
@@ -119,15 +471,32 @@ impl BankImportService {
         profile: &BankProfileModel,
     ) -> Result<Vec<BankTransaction>> {
         match format {
-            BankImportFormat::UnicreditMt940 => Self::parse_mt940(content, &profile.currency_code),
+            BankImportFormat::UnicreditMt940 | BankImportFormat::Mt942 => {
+                Self::parse_mt940(content, &profile.currency_code)
+            }
+            BankImportFormat::ProcreditMt940 => Self::parse_mt940_details(
+                content,
+                &profile.currency_code,
+                Mt940Details::QuestionMarkCodes,
+            ),
+            BankImportFormat::AllianzMt940 => {
+                Self::parse_mt940_details(content, &profile.currency_code, Mt940Details::SlashCodes)
+            }
             BankImportFormat::WiseCamt053
             | BankImportFormat::RevolutCamt053
-            | BankImportFormat::PayseraCamt053 => Self::parse_camt053(content),
+            | BankImportFormat::PayseraCamt053
+            | BankImportFormat::Camt052
+            | BankImportFormat::Camt054 => Self::parse_camt(content),
             BankImportFormat::PostbankXml => {
                 Self::parse_postbank_xml(content, &profile.currency_code)
             }
             BankImportFormat::ObbXml => Self::parse_obb_xml(content, &profile.currency_code),
             BankImportFormat::CcbCsv => Self::parse_ccb_csv(content, &profile.currency_code),
+            BankImportFormat::DskCsv => Self::parse_dsk_csv(content, &profile.currency_code),
+            BankImportFormat::FibankXml => Self::parse_fibank_xml(content, &profile.currency_code),
+            BankImportFormat::ObbCsv => Self::parse_obb_csv(content, &profile.currency_code),
+            BankImportFormat::CustomCsv => CsvMapping::from_settings(profile.settings.as_ref())?
+                .parse(content, &profile.currency_code),
         }
     }
 
@@ -135,42 +504,104 @@ impl BankImportService {
         txn: &DatabaseTransaction,
         profile: &BankProfileModel,
         file_name: &str,
-        transactions: &[BankTransaction],
+        preview: &StatementPreview,
+        rates: &HashMap<(String, NaiveDate), Decimal>,
+        skip_indexes: &[usize],
         created_by: i32,
     ) -> Result<ImportSummary> {
-        let mut journal_entry_ids = Vec::with_capacity(transactions.len());
+        let skip_indexes: HashSet<usize> = skip_indexes.iter().copied().collect();
+        let mut journal_entry_ids = Vec::with_capacity(preview.transactions.len());
+        let mut booked = Vec::with_capacity(preview.transactions.len());
         let mut total_debit = Decimal::ZERO;
         let mut total_credit = Decimal::ZERO;
+        let mut skipped_duplicates = 0;
+        let mut auto_posted = 0;
+
+        for previewed in &preview.transactions {
+            if previewed.duplicate_of.is_some() {
+                skipped_duplicates += 1;
+                continue;
+            }
+            if skip_indexes.contains(&previewed.index) {
+                continue;
+            }
+            let (idx, tx) = (previewed.index, &previewed.transaction);
 
-        for (idx, tx) in transactions.iter().enumerate() {
             let ledger_date = tx.booking_date;
             let value_date = tx.value_date.unwrap_or(tx.booking_date);
             let amount = tx.amount.abs();
-
-            let entry_number = format!(
-                "BANK-{}-{}-{:04}",
-                profile.id,
-                ledger_date.format("%Y%m%d"),
-                idx + 1
-            );
+            // Foreign-currency transactions are booked at the rate of the booking date
+            let exchange_rate = rates
+                .get(&(tx.currency.clone(), tx.booking_date))
+                .copied()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No BNB rate for {} on {} was loaded",
+                        tx.currency,
+                        tx.booking_date
+                    )
+                })?;
+            let base_amount = (amount * exchange_rate)
+                .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+
+            AccountingPeriodService::ensure_dates_open(
+                txn,
+                profile.company_id,
+                ledger_date,
+                Some(value_date),
+                Some(created_by),
+            )
+            .await?;
+
+            let number = JournalNumberingService::allocate(
+                txn,
+                profile.company_id,
+                JournalSeriesKind::Bank,
+                ledger_date,
+            )
+            .await?;
 
             let document_number = tx
                 .reference
                 .clone()
                 .or_else(|| Some(format!("{}-{}", file_name, idx + 1)));
 
+            let line_description = match &previewed.rule {
+                Some(rule) => rule.format_description(None, &tx.description),
+                None => tx.description.clone(),
+            };
             let entry_description =
-                format!("Банково извлечение {} — {}", profile.name, tx.description);
+                format!("Банково извлечение {} — {}", profile.name, line_description);
+
+            let posting = previewed
+                .rule
+                .as_ref()
+                .map(|rule| Self::rule_posting(rule, previewed.vat_rate.as_ref(), base_amount));
+            if let (Some(rule), Some(posting)) = (&previewed.rule, &posting) {
+                if !posting.vat_amount.is_zero() && posting.vat_operation.is_none() {
+                    return Err(anyhow!(
+                        "Bank rule '{}' splits off VAT but has no VAT operation to report it under",
+                        rule.pattern_name
+                    ));
+                }
+            }
+            let total_vat_amount = posting
+                .as_ref()
+                .map(|posting| posting.vat_amount)
+                .unwrap_or_default();
+            let vat_operation = posting
+                .as_ref()
+                .and_then(|posting| Some((posting.vat_operation.clone()?, posting.is_purchase())));
 
             let mut entry = journal_entry::ActiveModel {
-                entry_number: Set(entry_number),
+                entry_number: Set(number.entry_number),
                 document_date: Set(ledger_date),
                 vat_date: Set(Some(value_date)),
                 accounting_date: Set(ledger_date),
                 document_number: Set(document_number),
                 description: Set(entry_description),
-                total_amount: Set(amount),
-                total_vat_amount: Set(Decimal::ZERO),
+                total_amount: Set(base_amount),
+                total_vat_amount: Set(total_vat_amount),
                 is_posted: Set(false),
                 posted_by: Set(None),
                 posted_at: Set(None),
@@ -179,10 +610,17 @@ impl BankImportService {
                 created_at: Set(Utc::now()),
                 updated_at: Set(Utc::now()),
                 vat_document_type: Set(None),
-                vat_purchase_operation: Set(None),
-                vat_sales_operation: Set(None),
+                vat_purchase_operation: Set(vat_operation
+                    .clone()
+                    .filter(|(_, is_purchase)| *is_purchase)
+                    .map(|(operation, _)| operation)),
+                vat_sales_operation: Set(vat_operation
+                    .filter(|(_, is_purchase)| !*is_purchase)
+                    .map(|(operation, _)| operation)),
                 vat_additional_operation: Set(None),
                 vat_additional_data: Set(None),
+                series_id: Set(Some(number.series_id)),
+                sequence_number: Set(Some(number.sequence_number)),
                 ..Default::default()
             };
 
@@ -190,13 +628,18 @@ impl BankImportService {
                 .exec_with_returning(txn)
                 .await?;
 
-            let bank_line_debit = if tx.is_credit { amount } else { Decimal::ZERO };
-            let bank_line_credit = if tx.is_credit { Decimal::ZERO } else { amount };
-
-            let buffer_line_debit = if tx.is_credit { Decimal::ZERO } else { amount };
-            let buffer_line_credit = if tx.is_credit { amount } else { Decimal::ZERO };
+            let bank_line_debit = if tx.is_credit {
+                base_amount
+            } else {
+                Decimal::ZERO
+            };
+            let bank_line_credit = if tx.is_credit {
+                Decimal::ZERO
+            } else {
+                base_amount
+            };
 
-            let description = Some(tx.description.clone());
+            let description = Some(line_description);
 
             let bank_line = entry_line::ActiveModel {
                 journal_entry_id: Set(entry.id),
@@ -206,8 +649,8 @@ impl BankImportService {
                 counterpart_id: Set(None),
                 currency_code: Set(Some(tx.currency.clone())),
                 currency_amount: Set(Some(amount)),
-                exchange_rate: Set(Some(Decimal::ONE)),
-                base_amount: Set(amount),
+                exchange_rate: Set(Some(exchange_rate)),
+                base_amount: Set(base_amount),
                 vat_amount: Set(Decimal::ZERO),
                 vat_rate_id: Set(None),
                 quantity: Set(None),
@@ -218,37 +661,84 @@ impl BankImportService {
                 ..Default::default()
             };
 
-            let buffer_line = entry_line::ActiveModel {
-                journal_entry_id: Set(entry.id),
-                account_id: Set(profile.buffer_account_id),
-                debit_amount: Set(buffer_line_debit),
-                credit_amount: Set(buffer_line_credit),
-                counterpart_id: Set(None),
-                currency_code: Set(Some(tx.currency.clone())),
-                currency_amount: Set(Some(amount)),
-                exchange_rate: Set(Some(Decimal::ONE)),
-                base_amount: Set(amount),
-                vat_amount: Set(Decimal::ZERO),
-                vat_rate_id: Set(None),
-                quantity: Set(None),
-                unit_of_measure_code: Set(None),
-                description: Set(description),
-                line_order: Set(2),
-                created_at: Set(Utc::now()),
-                ..Default::default()
+            // The other side goes to the buffer account, or to the rule's
+            // account and VAT account. The net line carries the VAT of the
+            // entry and both lines the rule's VAT rate.
+            let counter_parts = match &posting {
+                Some(posting) => {
+                    let mut parts = vec![(
+                        posting.account_id,
+                        posting.net_amount,
+                        posting.vat_amount,
+                        posting.vat_rate_id,
+                    )];
+                    if let Some(vat_account_id) = posting.vat_account_id {
+                        parts.push((
+                            vat_account_id,
+                            posting.vat_amount,
+                            Decimal::ZERO,
+                            posting.vat_rate_id,
+                        ));
+                    }
+                    parts
+                }
+                None => vec![(profile.buffer_account_id, base_amount, Decimal::ZERO, None)],
             };
 
-            entry_line::Entity::insert_many([bank_line, buffer_line])
-                .exec(txn)
-                .await?;
+            let mut lines = vec![bank_line];
+            for (order, (account_id, part, vat_amount, vat_rate_id)) in
+                counter_parts.into_iter().enumerate()
+            {
+                let currency_amount = if part == base_amount || base_amount.is_zero() {
+                    amount
+                } else {
+                    (amount * part / base_amount)
+                        .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+                };
+                lines.push(entry_line::ActiveModel {
+                    journal_entry_id: Set(entry.id),
+                    account_id: Set(account_id),
+                    debit_amount: Set(if tx.is_credit { Decimal::ZERO } else { part }),
+                    credit_amount: Set(if tx.is_credit { part } else { Decimal::ZERO }),
+                    counterpart_id: Set(None),
+                    currency_code: Set(Some(tx.currency.clone())),
+                    currency_amount: Set(Some(currency_amount)),
+                    exchange_rate: Set(Some(exchange_rate)),
+                    base_amount: Set(part),
+                    vat_amount: Set(vat_amount),
+                    vat_rate_id: Set(vat_rate_id),
+                    quantity: Set(None),
+                    unit_of_measure_code: Set(None),
+                    description: Set(description.clone()),
+                    line_order: Set(order as i32 + 2),
+                    created_at: Set(Utc::now()),
+                    ..Default::default()
+                });
+            }
+
+            entry_line::Entity::insert_many(lines).exec(txn).await?;
+            if previewed.rule.is_some() {
+                auto_posted += 1;
+            }
+
+            let snapshot = AuditLogService::journal_entry_snapshot(txn, entry.id).await?;
+            AuditLogService::record(
+                txn,
+                Some(created_by),
+                AuditEvent::new(AuditAction::Create, "journal_entries", entry.id)
+                    .company(profile.company_id)
+                    .after(&snapshot),
+            )
+            .await?;
 
             if tx.is_credit {
-                total_debit += amount;
+                total_debit += base_amount;
             } else {
-                total_credit += amount;
+                total_credit += base_amount;
             }
 
             journal_entry_ids.push(entry.id);
+            booked.push((previewed, entry.id));
         }
 
         let bank_import_record = bank_import::ActiveModel {
@@ -257,7 +747,7 @@ impl BankImportService {
             file_name: Set(file_name.to_string()),
             import_format: Set(profile.import_format.clone()),
             imported_at: Set(Utc::now()),
-            transactions_count: Set(transactions.len() as i32),
+            transactions_count: Set(preview.transactions.len() as i32),
             total_credit: Set(total_credit),
             total_debit: Set(total_debit),
             created_journal_entries: Set(journal_entry_ids.len() as i32),
@@ -267,24 +757,87 @@ impl BankImportService {
             created_by: Set(Some(created_by)),
             created_at: Set(Utc::now()),
             updated_at: Set(Utc::now()),
+            statement_from: Set(preview.statement_from),
+            statement_to: Set(preview.statement_to),
+            skipped_duplicates: Set(skipped_duplicates as i32),
+            auto_posted: Set(auto_posted as i32),
             ..Default::default()
         }
         .insert(txn)
         .await?;
 
+        if !booked.is_empty() {
+            let fingerprints = booked.iter().map(|(previewed, journal_entry_id)| {
+                bank_transaction_fingerprint::ActiveModel {
+                    bank_profile_id: Set(profile.id),
+                    bank_import_id: Set(bank_import_record.id),
+                    journal_entry_id: Set(*journal_entry_id),
+                    fingerprint: Set(previewed.fingerprint.fingerprint.clone()),
+                    booking_date: Set(previewed.transaction.booking_date),
+                    amount: Set(previewed.fingerprint.signed_amount),
+                    reference: Set(previewed.fingerprint.reference.clone()),
+                    counterparty_iban: Set(previewed.fingerprint.counterparty_iban.clone()),
+                    created_at: Set(Utc::now()),
+                    ai_bank_accounting_setting_id: Set(previewed.rule.as_ref().map(|rule| rule.id)),
+                    ..Default::default()
+                }
+            });
+            bank_transaction_fingerprint::Entity::insert_many(fingerprints)
+                .exec(txn)
+                .await?;
+        }
+
+        AuditLogService::record(
+            txn,
+            Some(created_by),
+            AuditEvent::new(AuditAction::Create, "bank_imports", bank_import_record.id)
+                .company(profile.company_id)
+                .after(&bank_import_record),
+        )
+        .await?;
+
         Ok(ImportSummary {
-            transactions: transactions.len(),
+            transactions: preview.transactions.len(),
             journal_entry_ids,
             total_debit,
             total_credit,
+            skipped_duplicates,
+            auto_posted,
+            overlapping_import_ids: preview
+                .overlapping_imports
+                .iter()
+                .map(|import| import.id)
+                .collect(),
             bank_import: bank_import_record,
         })
     }
 
+    /// MT940 statements and MT942 intraday reports share the :61: and :86:
+    /// fields; the balance and summary fields are skipped
     fn parse_mt940(content: &str, currency_code: &str) -> Result<Vec<BankTransaction>> {
+        Self::parse_mt940_details(content, currency_code, Mt940Details::FreeText)
+    }
+
+    /// MT940/MT942 statement whose :86: field is laid out as `details`
+    fn parse_mt940_details(
+        content: &str,
+        currency_code: &str,
+        details: Mt940Details,
+    ) -> Result<Vec<BankTransaction>> {
         let mut transactions = Vec::new();
         let mut current: Option<BankTransaction> = None;
 
+        let flush = |tx: Option<BankTransaction>, transactions: &mut Vec<BankTransaction>| {
+            if let Some(mut tx) = tx {
+                tx.description = match details {
+                    Mt940Details::FreeText => tx.description.trim().to_string(),
+                    Mt940Details::QuestionMarkCodes => Self::question_mark_details(&tx.description),
+                    Mt940Details::SlashCodes => Self::slash_code_details(&tx.description),
+                };
+                transactions.push(tx);
+            }
+        };
+
         for raw_line in content.lines() {
             let line = raw_line.trim();
             if line.is_empty() || line == "-" {
@@ -292,10 +845,7 @@ impl BankImportService {
             }
 
             if line.starts_with(":61:") {
-                if let Some(mut tx) = current.take() {
-                    tx.description = tx.description.trim().to_string();
-                    transactions.push(tx);
-                }
+                flush(current.take(), &mut transactions);
 
                 let data = line.trim_start_matches(":61:");
                 let (booking_date, is_credit, amount, reference) =
@@ -313,28 +863,122 @@ impl BankImportService {
             } else if line.starts_with(":86:") {
                 if let Some(ref mut tx) = current {
                     let descr = line.trim_start_matches(":86:").trim();
-                    Self::append_description(&mut tx.description, descr);
+                    Self::append_details(&mut tx.description, descr, details);
                 }
             } else if line.starts_with(":62") {
                 // Closing balance, flush current transaction if any
-                if let Some(tx) = current.take() {
-                    transactions.push(tx);
-                }
+                flush(current.take(), &mut transactions);
             } else if !line.starts_with(':') {
                 if let Some(ref mut tx) = current {
-                    Self::append_description(&mut tx.description, line);
+                    Self::append_details(&mut tx.description, line, details);
                 }
             }
         }
 
-        if let Some(mut tx) = current.take() {
-            tx.description = tx.description.trim().to_string();
-            transactions.push(tx);
-        }
+        flush(current.take(), &mut transactions);
 
         Ok(transactions)
     }
 
+    /// Structured :86: fields are wrapped at a fixed width, so their lines
+    /// are joined as they are
+    fn append_details(existing: &mut String, fragment: &str, details: Mt940Details) {
+        match details {
+            Mt940Details::FreeText => Self::append_description(existing, fragment),
+            Mt940Details::QuestionMarkCodes | Mt940Details::SlashCodes => {
+                existing.push_str(fragment)
+            }
+        }
+    }
+
+    /// :86: field split into `?NN` subfields (ProCredit): ?00 booking text,
+    /// ?20-?29 remittance information, ?31 counterparty IBAN and ?32-?33
+    /// counterparty name
+    fn question_mark_details(raw: &str) -> String {
+        if !raw.contains('?') {
+            return raw.trim().to_string();
+        }
+
+        let mut booking_text = String::new();
+        let mut remittance = String::new();
+        let mut name = String::new();
+        let mut iban = String::new();
+        for subfield in raw.split('?').skip(1) {
+            let Some(code) = subfield.get(..2) else {
+                continue;
+            };
+            let value = &subfield[2..];
+            match code {
+                "00" => booking_text.push_str(value),
+                "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" => {
+                    remittance.push_str(value)
+                }
+                "31" => iban.push_str(value),
+                "32" | "33" => name.push_str(value),
+                _ => {}
+            }
+        }
+
+        let mut description = String::new();
+        let remittance = if remittance.trim().is_empty() {
+            booking_text
+        } else {
+            remittance
+        };
+        for value in [remittance, name, iban] {
+            if !value.trim().is_empty() {
+                Self::append_description(&mut description, &value);
+            }
+        }
+        description
+    }
+
+    /// :86: field split into `/CODE/` subfields (Allianz): /REMI/ remittance
+    /// information, /NAME/ and /IBAN/ of the counterparty and /TRTP/
+    /// transaction type
+    fn slash_code_details(raw: &str) -> String {
+        const CODES: [&str; 8] = [
+            "TRTP", "REMI", "NAME", "IBAN", "BIC", "EREF", "ORDP", "BENM",
+        ];
+        if !CODES.iter().any(|code| raw.contains(&format!("/{code}/"))) {
+            return raw.trim().to_string();
+        }
+
+        let mut values: HashMap<&str, String> = HashMap::new();
+        let mut current: Option<&str> = None;
+        let mut tokens = raw.split('/').skip(1).peekable();
+        while let Some(token) = tokens.next() {
+            if let Some(code) = CODES.iter().find(|code| **code == token) {
+                current = Some(code);
+                values.entry(code).or_default();
+                // The value is the next token, which may itself be empty
+                if let Some(value) = tokens.next_if(|next| !CODES.contains(next)) {
+                    values.entry(code).or_default().push_str(value);
+                }
+            } else if let Some(code) = current {
+                // A slash inside the value
+                let value = values.entry(code).or_default();
+                value.push('/');
+                value.push_str(token);
+            }
+        }
+
+        let remittance = values
+            .get("REMI")
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| values.get("TRTP"));
+        let mut description = String::new();
+        for value in [remittance, values.get("NAME"), values.get("IBAN")]
+            .into_iter()
+            .flatten()
+        {
+            if !value.trim().is_empty() {
+                Self::append_description(&mut description, value);
+            }
+        }
+        description
+    }
+
     fn parse_mt940_61_line(
         data: &str,
         currency_code: &str,
@@ -352,17 +996,19 @@ impl BankImportService {
 
         let rest = &data[6..];
         let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
-        let (sign_part, remainder) = rest
-            .chars()
-            .next()
-            .map(|c| (c, rest[1..].to_string()))
-            .ok_or_else(|| anyhow!("Invalid :61: line payload"))?;
-
-        let is_credit = match sign_part {
-            'C' | 'c' => true,
-            'D' | 'd' => false,
-            _ => return Err(anyhow!("Unknown transaction indicator in :61: line")),
+        // RC and RD reverse a credit and a debit
+        let (is_credit, remainder) = match rest.get(..2) {
+            Some("RC" | "rc") => (false, &rest[2..]),
+            Some("RD" | "rd") => (true, &rest[2..]),
+            _ => match rest.chars().next() {
+                Some('C' | 'c') => (true, &rest[1..]),
+                Some('D' | 'd') => (false, &rest[1..]),
+                Some(_) => return Err(anyhow!("Unknown transaction indicator in :61: line")),
+                None => return Err(anyhow!("Invalid :61: line payload")),
+            },
         };
+        // Optional funds code, the third letter of the currency code
+        let remainder = remainder.trim_start_matches(|c: char| c.is_ascii_alphabetic());
 
         // Amount is until first letter (N) or sign
         let mut amount_str = String::new();
@@ -417,70 +1063,78 @@ impl BankImportService {
         Ok(Some(amount))
     }
 
-    fn parse_camt053(content: &str) -> Result<Vec<BankTransaction>> {
+    /// CAMT.053 statements, CAMT.052 intraday reports and CAMT.054
+    /// notifications; entries not booked yet are skipped
+    fn parse_camt(content: &str) -> Result<Vec<BankTransaction>> {
         let document: CamtDocument = from_xml_str(content)?;
         let mut transactions = Vec::new();
 
-        let statements = match &document.bk_to_cstmr_stmt {
-            CamtStatementContainer::Single(stmt) => vec![stmt.clone()],
-            CamtStatementContainer::Multiple(list) => list.clone(),
-        };
+        let entries = [
+            document.bk_to_cstmr_stmt,
+            document.bk_to_cstmr_acct_rpt,
+            document.bk_to_cstmr_dbt_cdt_ntfctn,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(CamtMessage::entries);
 
-        for statement in statements {
-            for entry in statement.entries.unwrap_or_default() {
-                if let Some(amount) = entry.amount {
-                    let currency = amount.currency.unwrap_or_else(|| "EUR".to_string());
-                    let amount_val = Decimal::from_str(&amount.value.replace(',', "."))?;
-                    let is_credit = entry
-                        .credit_debit_indicator
-                        .as_deref()
-                        .map(|v| v.eq_ignore_ascii_case("CRDT"))
-                        .unwrap_or(false);
-
-                    let booking_date = entry
-                        .booking_date
-                        .as_ref()
-                        .and_then(|d| d.to_naive_date())
-                        .ok_or_else(|| anyhow!("Missing booking date in CAMT entry"))?;
-                    let value_date = entry.value_date.as_ref().and_then(|d| d.to_naive_date());
-
-                    let mut description = String::new();
-                    if let Some(dtls) = entry.details {
-                        for detail in dtls.transactions {
-                            if let Some(remit) = detail.remittance_info {
-                                for line in remit.unstructured {
-                                    Self::append_description(&mut description, &line);
-                                }
-                            }
-                            if let Some(extra) = detail.additional_info {
-                                Self::append_description(&mut description, &extra);
+        for entry in entries {
+            if !entry.is_booked() {
+                continue;
+            }
+            if let Some(amount) = entry.amount {
+                let currency = amount.currency.unwrap_or_else(|| "EUR".to_string());
+                let amount_val = Decimal::from_str(&amount.value.replace(',', "."))?;
+                let is_credit = entry
+                    .credit_debit_indicator
+                    .as_deref()
+                    .map(|v| v.eq_ignore_ascii_case("CRDT"))
+                    .unwrap_or(false);
+
+                let value_date = entry.value_date.as_ref().and_then(|d| d.to_naive_date());
+                let booking_date = entry
+                    .booking_date
+                    .as_ref()
+                    .and_then(|d| d.to_naive_date())
+                    .or(value_date)
+                    .ok_or_else(|| anyhow!("Missing booking date in CAMT entry"))?;
+
+                let mut description = String::new();
+                if let Some(dtls) = entry.details {
+                    for detail in dtls.transactions {
+                        if let Some(remit) = detail.remittance_info {
+                            for line in remit.unstructured {
+                                Self::append_description(&mut description, &line);
                             }
-                            if let Some(card_tx) = detail.card_transaction {
-                                if let Some(info) = card_tx.additional_info {
-                                    Self::append_description(&mut description, &info);
-                                }
+                        }
+                        if let Some(extra) = detail.additional_info {
+                            Self::append_description(&mut description, &extra);
+                        }
+                        if let Some(card_tx) = detail.card_transaction {
+                            if let Some(info) = card_tx.additional_info {
+                                Self::append_description(&mut description, &info);
                             }
                         }
                     }
+                }
 
-                    if description.is_empty() {
-                        if let Some(ref ref_text) = entry.reference {
-                            description = ref_text.clone();
-                        }
+                if description.is_empty() {
+                    if let Some(ref ref_text) = entry.reference {
+                        description = ref_text.clone();
                     }
-
-                    let description = description.trim().to_string();
-
-                    transactions.push(BankTransaction {
-                        booking_date,
-                        value_date,
-                        amount: amount_val,
-                        currency,
-                        is_credit,
-                        description,
-                        reference: entry.reference.clone(),
-                    });
                 }
+
+                let description = description.trim().to_string();
+
+                transactions.push(BankTransaction {
+                    booking_date,
+                    value_date,
+                    amount: amount_val,
+                    currency,
+                    is_credit,
+                    description,
+                    reference: entry.reference.clone(),
+                });
             }
         }
 
@@ -552,6 +1206,306 @@ impl BankImportService {
         Ok(transactions)
     }
 
+    /// DSK Direct CSV export: a header row naming the columns, then one row
+    /// per transaction. Columns are found by their Bulgarian headers, so
+    /// the optional ones may be missing or reordered.
+    fn parse_dsk_csv(content: &str, currency_code: &str) -> Result<Vec<BankTransaction>> {
+        let mut lines = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        let header = lines
+            .by_ref()
+            .find(|line| {
+                let lower = line.to_lowercase();
+                lower.contains("дебит") && lower.contains("кредит")
+            })
+            .ok_or_else(|| anyhow!("Missing header row in DSK CSV"))?;
+        let delimiter = if header.contains(';') { ';' } else { ',' };
+        let columns: Vec<String> = Self::split_csv_line(header, delimiter)
+            .iter()
+            .map(|column| column.to_lowercase())
+            .collect();
+        let column = |keywords: &[&str]| {
+            keywords
+                .iter()
+                .find_map(|keyword| columns.iter().position(|column| column.contains(keyword)))
+        };
+        let booking_date_col = column(&["осчетоводяване", "дата"])
+            .ok_or_else(|| anyhow!("Missing date column in DSK CSV"))?;
+        let value_date_col = column(&["вальор"]);
+        let debit_col = column(&["дебит"]);
+        let credit_col = column(&["кредит"]);
+        let reference_col = column(&["референция"]);
+        let counterparty_col = column(&["контрагент", "наредител", "получател"]);
+        let iban_col = column(&["iban", "сметка"]);
+        let description_col = column(&["основание", "описание"]);
+
+        let mut transactions = Vec::new();
+        for line in lines {
+            let fields = Self::split_csv_line(line, delimiter);
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+            };
+
+            let Some(booking_date) = field(Some(booking_date_col))
+                .and_then(|value| NaiveDate::parse_from_str(value, "%d.%m.%Y").ok())
+            else {
+                // Totals and footer rows
+                continue;
+            };
+            let value_date = field(value_date_col)
+                .and_then(|value| NaiveDate::parse_from_str(value, "%d.%m.%Y").ok());
+
+            let credit_amount = field(credit_col).map(Self::parse_obb_amount).transpose()?;
+            let debit_amount = field(debit_col).map(Self::parse_obb_amount).transpose()?;
+            let (amount, is_credit) = match (credit_amount, debit_amount) {
+                (Some(val), _) if !val.is_zero() => (val, true),
+                (_, Some(val)) if !val.is_zero() => (val, false),
+                _ => continue,
+            };
+
+            let mut description = String::new();
+            for value in [
+                field(description_col),
+                field(counterparty_col),
+                field(iban_col),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                Self::append_description(&mut description, value);
+            }
+            let reference = field(reference_col).map(str::to_string);
+            if description.is_empty() {
+                description = reference
+                    .clone()
+                    .unwrap_or_else(|| "ДСК транзакция".to_string());
+            }
+
+            transactions.push(BankTransaction {
+                booking_date,
+                value_date,
+                amount,
+                currency: currency_code.to_string(),
+                is_credit,
+                description,
+                reference,
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    /// UBB (ОББ) Business Online CSV export: a header row, then one row per
+    /// transaction with the amount in one column and its direction in a
+    /// Д/К column. The opening and closing balance rows carry no date and
+    /// are skipped.
+    fn parse_obb_csv(content: &str, currency_code: &str) -> Result<Vec<BankTransaction>> {
+        let mut lines = content
+            .lines()
+            .map(|line| line.trim().trim_start_matches('\u{feff}'))
+            .filter(|line| !line.is_empty());
+        let header = lines
+            .by_ref()
+            .find(|line| {
+                let lower = line.to_lowercase();
+                lower.contains("сума") && lower.contains("дата")
+            })
+            .ok_or_else(|| anyhow!("Missing header row in OBB CSV"))?;
+        let delimiter = if header.contains(';') { ';' } else { ',' };
+        let columns: Vec<String> = Self::split_csv_line(header, delimiter)
+            .iter()
+            .map(|column| column.trim().to_lowercase())
+            .collect();
+        let column = |keywords: &[&str]| {
+            keywords
+                .iter()
+                .find_map(|keyword| columns.iter().position(|column| column.contains(keyword)))
+        };
+        let value_date_col = column(&["вальор"]);
+        let booking_date_col = columns
+            .iter()
+            .position(|column| column.contains("дата") && !column.contains("вальор"))
+            .ok_or_else(|| anyhow!("Missing date column in OBB CSV"))?;
+        let amount_col = column(&["сума"]).ok_or_else(|| anyhow!("Missing amount column"))?;
+        let direction_col = column(&["д/к", "дт/кт", "тип"]);
+        let currency_col = column(&["валута"]);
+        let reference_col = column(&["референция"]);
+        let counterparty_col = column(&["наредител", "получател", "контрагент"]);
+        let iban_col = column(&["iban", "сметка"]);
+        let description_col = column(&["основание", "описание"]);
+
+        let mut transactions = Vec::new();
+        for line in lines {
+            let fields = Self::split_csv_line(line, delimiter);
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+            };
+
+            let Some(booking_date) = field(Some(booking_date_col))
+                .and_then(|value| NaiveDate::parse_from_str(value, "%d.%m.%Y").ok())
+            else {
+                continue;
+            };
+            let value_date = field(value_date_col)
+                .and_then(|value| NaiveDate::parse_from_str(value, "%d.%m.%Y").ok());
+
+            let Some(amount) = field(Some(amount_col))
+                .map(Self::parse_obb_amount)
+                .transpose()?
+            else {
+                continue;
+            };
+            if amount.is_zero() {
+                continue;
+            }
+            // Without a direction column the amount is signed
+            let is_credit = match field(direction_col).map(|value| value.to_uppercase()) {
+                Some(value) if value.starts_with('К') || value.starts_with('C') => true,
+                Some(value) if value.starts_with('Д') || value.starts_with('D') => false,
+                Some(other) => {
+                    return Err(anyhow!("Unknown debit/credit flag '{other}' in OBB CSV"))
+                }
+                None => amount > Decimal::ZERO,
+            };
+
+            let mut description = String::new();
+            for value in [
+                field(description_col),
+                field(counterparty_col),
+                field(iban_col),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                Self::append_description(&mut description, value);
+            }
+            let reference = field(reference_col).map(str::to_string);
+            if description.is_empty() {
+                description = reference
+                    .clone()
+                    .unwrap_or_else(|| "ОББ транзакция".to_string());
+            }
+
+            transactions.push(BankTransaction {
+                booking_date,
+                value_date,
+                amount: amount.abs(),
+                currency: field(currency_col)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| currency_code.to_string()),
+                is_credit,
+                description,
+                reference,
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    /// Split a CSV line, honouring double-quoted fields
+    pub(crate) fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    current.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                ch if ch == delimiter && !quoted => fields.push(std::mem::take(&mut current)),
+                ch => current.push(ch),
+            }
+        }
+        fields.push(current);
+
+        fields
+    }
+
+    /// Fibank (e-fibank) XML account statement
+    fn parse_fibank_xml(content: &str, currency_code: &str) -> Result<Vec<BankTransaction>> {
+        let document: FibankStatement = from_xml_str(content)?;
+        let currency = document
+            .currency
+            .filter(|currency| !currency.trim().is_empty())
+            .unwrap_or_else(|| currency_code.to_string());
+        let mut transactions = Vec::new();
+
+        for movement in document.movements.items {
+            let booking_date = movement
+                .posting_date
+                .as_deref()
+                .and_then(Self::parse_fibank_date)
+                .ok_or_else(|| anyhow!("Missing posting date in Fibank movement"))?;
+            let value_date = movement
+                .value_date
+                .as_deref()
+                .and_then(Self::parse_fibank_date);
+            let amount = Self::parse_obb_amount(&movement.amount)?;
+            let is_credit = match movement.debit_credit.trim() {
+                "C" | "c" | "К" | "к" => true,
+                "D" | "d" | "Д" | "д" => false,
+                other => {
+                    return Err(anyhow!(
+                        "Unknown debit/credit flag '{other}' in Fibank movement"
+                    ))
+                }
+            };
+
+            let mut description = String::new();
+            for value in [
+                movement.payment_reason,
+                movement.additional_info,
+                movement.contragent_name,
+                movement.contragent_iban,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                Self::append_description(&mut description, &value);
+            }
+            let reference = movement
+                .reference
+                .map(|reference| reference.trim().to_string())
+                .filter(|reference| !reference.is_empty());
+            if description.is_empty() {
+                description = reference
+                    .clone()
+                    .unwrap_or_else(|| "Fibank транзакция".to_string());
+            }
+
+            transactions.push(BankTransaction {
+                booking_date,
+                value_date,
+                amount,
+                currency: currency.clone(),
+                is_credit,
+                description: description.trim().to_string(),
+                reference,
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    fn parse_fibank_date(value: &str) -> Option<NaiveDate> {
+        let trimmed = value.trim();
+        NaiveDate::parse_from_str(trimmed, "%d.%m.%Y")
+            .ok()
+            .or_else(|| NaiveDate::parse_from_str(trimmed.get(..10)?, "%Y-%m-%d").ok())
+    }
+
     fn parse_postbank_xml(content: &str, currency_code: &str) -> Result<Vec<BankTransaction>> {
         let document: PostbankDocument = from_xml_str(content)?;
         let mut transactions = Vec::new();
@@ -757,17 +1711,56 @@ impl BankImportService {
     }
 }
 
+/// Layout of the :86: information to account owner of an MT940 statement
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mt940Details {
+    /// Free text, possibly over several lines
+    FreeText,
+    /// `?NN` subfields
+    QuestionMarkCodes,
+    /// `/CODE/` subfields
+    SlashCodes,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct CamtDocument {
-    #[serde(rename = "BkToCstmrStmt")]
-    pub bk_to_cstmr_stmt: CamtStatementContainer,
+    /// CAMT.053 statement
+    #[serde(rename = "BkToCstmrStmt", default)]
+    pub bk_to_cstmr_stmt: Option<CamtMessage>,
+    /// CAMT.052 intraday report
+    #[serde(rename = "BkToCstmrAcctRpt", default)]
+    pub bk_to_cstmr_acct_rpt: Option<CamtMessage>,
+    /// CAMT.054 debit/credit notification
+    #[serde(rename = "BkToCstmrDbtCdtNtfctn", default)]
+    pub bk_to_cstmr_dbt_cdt_ntfctn: Option<CamtMessage>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)]
-enum CamtStatementContainer {
-    Single(CamtStatement),
-    Multiple(Vec<CamtStatement>),
+struct CamtMessage {
+    #[serde(rename = "Stmt", default)]
+    pub statements: Vec<CamtStatement>,
+    #[serde(rename = "Rpt", default)]
+    pub reports: Vec<CamtStatement>,
+    #[serde(rename = "Ntfctn", default)]
+    pub notifications: Vec<CamtStatement>,
+    /// Some exports put the entries directly under the message
+    #[serde(rename = "Ntry", default)]
+    pub entries: Vec<CamtEntry>,
+}
+
+impl CamtMessage {
+    fn entries(self) -> Vec<CamtEntry> {
+        let mut entries = self.entries;
+        for statement in self
+            .statements
+            .into_iter()
+            .chain(self.reports)
+            .chain(self.notifications)
+        {
+            entries.extend(statement.entries.unwrap_or_default());
+        }
+        entries
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -788,10 +1781,32 @@ struct CamtEntry {
     pub value_date: Option<CamtDate>,
     #[serde(rename = "AcctSvcrRef", default)]
     pub reference: Option<String>,
+    #[serde(rename = "Sts", default)]
+    pub status: Option<CamtStatus>,
     #[serde(rename = "NtryDtls", default)]
     pub details: Option<CamtEntryDetails>,
 }
 
+impl CamtEntry {
+    /// Intraday reports also carry pending and informational entries
+    pub fn is_booked(&self) -> bool {
+        self.status
+            .as_ref()
+            .and_then(|status| status.code.as_deref().or(status.text.as_deref()))
+            .map(|code| code.trim().eq_ignore_ascii_case("BOOK"))
+            .unwrap_or(true)
+    }
+}
+
+/// Plain code up to camt.05x.001.07, `<Cd>` element after
+#[derive(Debug, Clone, Deserialize)]
+struct CamtStatus {
+    #[serde(rename = "Cd", default)]
+    pub code: Option<String>,
+    #[serde(rename = "$text", default)]
+    pub text: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct CamtAmount {
     #[serde(rename = "@Ccy", default)]
@@ -851,6 +1866,43 @@ struct CamtCardTransaction {
     pub additional_info: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct FibankStatement {
+    #[serde(rename = "Currency", default)]
+    pub currency: Option<String>,
+    #[serde(rename = "Movements")]
+    pub movements: FibankMovements,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FibankMovements {
+    #[serde(rename = "Movement", default)]
+    pub items: Vec<FibankMovement>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FibankMovement {
+    #[serde(rename = "PostingDate", default)]
+    pub posting_date: Option<String>,
+    #[serde(rename = "ValueDate", default)]
+    pub value_date: Option<String>,
+    #[serde(rename = "Reference", default)]
+    pub reference: Option<String>,
+    /// `D`/`C`, or `Д`/`К` in the Bulgarian export
+    #[serde(rename = "DebitCredit")]
+    pub debit_credit: String,
+    #[serde(rename = "Amount")]
+    pub amount: String,
+    #[serde(rename = "ContragentName", default)]
+    pub contragent_name: Option<String>,
+    #[serde(rename = "ContragentIBAN", default)]
+    pub contragent_iban: Option<String>,
+    #[serde(rename = "PaymentReason", default)]
+    pub payment_reason: Option<String>,
+    #[serde(rename = "AdditionalInfo", default)]
+    pub additional_info: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct PostbankDocument {
     #[serde(rename = "MultipleAccountTransactionItemAPIOutputModel", default)]
@@ -951,7 +2003,7 @@ impl PostbankDateTime {
 
 #[cfg(test)]
 mod tests {
-    use super::{BankImportFormat, BankImportService};
+    use super::{BankImportFormat, BankImportService, BankTransaction, Mt940Details};
     use chrono::NaiveDate;
     use rust_decimal::Decimal;
     use std::str::FromStr;
@@ -1023,4 +2075,381 @@ mod tests {
             "expected at least one inbound transaction"
         );
     }
+
+    #[test]
+    fn fingerprints_identify_reimported_transactions() {
+        let tx = |amount: &str, description: &str| BankTransaction {
+            booking_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            value_date: None,
+            amount: Decimal::from_str(amount).unwrap(),
+            currency: "BGN".to_string(),
+            is_credit: false,
+            description: description.to_string(),
+            reference: None,
+        };
+        let statement = vec![
+            tx("12.50", "Плащане към BG80BNBG96611020345678  Доставчик ООД"),
+            tx("5.00", "Такса"),
+            tx("5.00", "Такса"),
+        ];
+
+        let first = BankImportService::fingerprints(&statement);
+        assert_eq!(
+            first[0].counterparty_iban.as_deref(),
+            Some("BG80BNBG96611020345678")
+        );
+        assert_eq!(first[0].signed_amount, Decimal::from_str("-12.5").unwrap());
+        // Two identical fees in one statement are two transactions
+        assert_ne!(first[1].fingerprint, first[2].fingerprint);
+
+        // The same statement re-exported with other spacing and zeros
+        let again = BankImportService::fingerprints(&[
+            tx("12.5", "Плащане към BG80BNBG96611020345678 Доставчик ООД"),
+            tx("5", "Такса"),
+            tx("5", "Такса"),
+        ]);
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn bank_rules_post_fees_with_the_vat_split_off() {
+        use crate::entities::{ai_bank_accounting_setting, vat_rate};
+        use crate::services::bank_transaction_parser::BankTransactionParser;
+        use chrono::Utc;
+
+        let rule = |id,
+                    keywords: &str,
+                    direction: &str,
+                    vat_account_id,
+                    vat_rate_id,
+                    vat_operation: Option<&str>| {
+            ai_bank_accounting_setting::Model {
+                id,
+                company_id: 1,
+                pattern_name: keywords.to_string(),
+                description_keywords: Some(keywords.to_string()),
+                transaction_type: "fee".to_string(),
+                account_id: Some(629),
+                counterpart_account_id: None,
+                vat_account_id,
+                vat_rate_id,
+                vat_operation: vat_operation.map(str::to_string),
+                direction: direction.to_string(),
+                description_template: None,
+                priority: 0,
+                is_active: true,
+                created_at: chrono::NaiveDateTime::default(),
+                updated_at: chrono::NaiveDateTime::default(),
+            }
+        };
+        let vat_rate = |id, rate: &str| vat_rate::Model {
+            id,
+            code: format!("VAT{}", rate),
+            name: format!("ДДС {}%", rate),
+            rate: Decimal::from_str(rate).unwrap(),
+            vat_direction: crate::entities::account::VatDirection::Input,
+            is_active: true,
+            valid_from: NaiveDate::default(),
+            valid_to: None,
+            company_id: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let rules = vec![
+            rule(1, "такса", "debit", None, None, None),
+            rule(2, "гориво", "debit", Some(4531), Some(20), Some("пок10")),
+            rule(3, "нощувка", "credit", Some(4532), Some(9), Some("про17")),
+        ];
+
+        let fee = BankTransactionParser::select_rule(&rules, "Такса поддръжка", false).unwrap();
+        assert_eq!(fee.id, 1);
+        // Incoming money never matches a debit rule
+        assert!(BankTransactionParser::select_rule(&rules, "Такса поддръжка", true).is_none());
+
+        let fuel = BankTransactionParser::select_rule(&rules, "Гориво ПОС", false).unwrap();
+        let posting = BankImportService::rule_posting(
+            fuel,
+            Some(&vat_rate(20, "20")),
+            Decimal::from_str("120.00").unwrap(),
+        );
+        assert_eq!(posting.account_id, 629);
+        assert_eq!(posting.net_amount, Decimal::from_str("100.00").unwrap());
+        assert_eq!(posting.vat_account_id, Some(4531));
+        assert_eq!(posting.vat_amount, Decimal::from_str("20.00").unwrap());
+        assert_eq!(posting.vat_rate_id, Some(20));
+        assert_eq!(posting.vat_operation.as_deref(), Some("пок10"));
+        assert!(posting.is_purchase());
+
+        // The VAT is split off at the rule's own rate and reported under its operation
+        let hotel = BankTransactionParser::select_rule(&rules, "Нощувка хотел", true).unwrap();
+        let posting = BankImportService::rule_posting(
+            hotel,
+            Some(&vat_rate(9, "9")),
+            Decimal::from_str("109.00").unwrap(),
+        );
+        assert_eq!(posting.net_amount, Decimal::from_str("100.00").unwrap());
+        assert_eq!(posting.vat_amount, Decimal::from_str("9.00").unwrap());
+        assert_eq!(posting.vat_operation.as_deref(), Some("про17"));
+        assert!(!posting.is_purchase());
+
+        // Without a rate nothing is split off
+        let posting =
+            BankImportService::rule_posting(hotel, None, Decimal::from_str("109.00").unwrap());
+        assert_eq!(posting.net_amount, Decimal::from_str("109.00").unwrap());
+        assert_eq!(posting.vat_account_id, None);
+        assert_eq!(posting.vat_operation, None);
+    }
+
+    /// Anonymised statement export under `banki/`, decoded as on import
+    fn sample(path: &str, format: BankImportFormat) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../banki")
+            .join(path);
+        let bytes = std::fs::read(&path).expect("failed to read sample file");
+        BankImportService::decode_to_string(&bytes, format).expect("failed to decode sample")
+    }
+
+    #[test]
+    fn parse_dsk_csv_sample_extracts_transactions() {
+        let content = sample(
+            "dsk/dsk-statement_BG18STSA93000012345678.csv",
+            BankImportFormat::DskCsv,
+        );
+        let transactions =
+            BankImportService::parse_dsk_csv(&content, "BGN").expect("failed to parse DSK CSV");
+
+        // The zero correction and the totals and balance rows are skipped
+        assert_eq!(transactions.len(), 3);
+        let payment = &transactions[0];
+        assert_eq!(
+            payment.booking_date,
+            NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()
+        );
+        assert_eq!(payment.amount, Decimal::from_str("1234.56").unwrap());
+        assert!(!payment.is_credit);
+        assert_eq!(payment.reference.as_deref(), Some("FT2524400001"));
+        assert!(payment.description.contains("Доставчик; ЕООД"));
+        assert!(payment.description.contains("BG80BNBG96611020345678"));
+        assert!(transactions[1].is_credit);
+        assert_eq!(transactions[1].amount, Decimal::from_str("600.00").unwrap());
+        assert_eq!(transactions[2].description, "Такса обслужване на сметка");
+    }
+
+    #[test]
+    fn parse_fibank_xml_sample_extracts_transactions() {
+        let content = sample(
+            "fibank/fibank-statement_BG11FINV91501012345678.xml",
+            BankImportFormat::FibankXml,
+        );
+        let transactions = BankImportService::parse_fibank_xml(&content, "BGN")
+            .expect("failed to parse Fibank XML");
+
+        assert_eq!(transactions.len(), 2);
+        let incoming = &transactions[0];
+        assert!(incoming.is_credit);
+        assert_eq!(incoming.amount, Decimal::from_str("2500.00").unwrap());
+        assert_eq!(incoming.currency, "EUR");
+        assert_eq!(incoming.reference.as_deref(), Some("0915FIB001"));
+        assert!(incoming.description.starts_with("Фактура 3003"));
+        let fee = &transactions[1];
+        assert!(!fee.is_credit);
+        assert_eq!(
+            fee.booking_date,
+            NaiveDate::from_ymd_opt(2025, 9, 16).unwrap()
+        );
+        assert_eq!(fee.value_date, None);
+    }
+
+    #[test]
+    fn parse_obb_csv_sample_extracts_transactions() {
+        let content = sample(
+            "OBB/obb-statement_BG43UBBS80021012345678.csv",
+            BankImportFormat::ObbCsv,
+        );
+        let transactions =
+            BankImportService::parse_obb_csv(&content, "EUR").expect("failed to parse OBB CSV");
+
+        // The balance rows carry no date
+        assert_eq!(transactions.len(), 3);
+        let incoming = &transactions[0];
+        assert!(incoming.is_credit);
+        assert_eq!(incoming.amount, Decimal::from_str("3600.00").unwrap());
+        assert_eq!(incoming.currency, "BGN");
+        assert_eq!(incoming.reference.as_deref(), Some("UBB25274A1B2"));
+        assert_eq!(
+            incoming.description,
+            "ф-ра 0000000451 КЛИЕНТ ЕООД BG27STSA93000098765432"
+        );
+        let rent = &transactions[1];
+        assert!(!rent.is_credit);
+        assert_eq!(rent.amount, Decimal::from_str("480.00").unwrap());
+        assert!(rent.description.starts_with("ф-ра 2025-118, наем октомври"));
+        assert_eq!(transactions[2].description, "Такса за превод");
+    }
+
+    #[test]
+    fn parse_procredit_mt940_sample_reads_the_structured_details() {
+        let content = sample(
+            "procredit/procredit-mt940_BG62PRCB92301012345678.sta",
+            BankImportFormat::ProcreditMt940,
+        );
+        let transactions = BankImportService::parse_mt940_details(
+            &content,
+            "BGN",
+            Mt940Details::QuestionMarkCodes,
+        )
+        .expect("failed to parse ProCredit MT940");
+
+        assert_eq!(transactions.len(), 3);
+        let incoming = &transactions[0];
+        assert!(incoming.is_credit);
+        assert_eq!(incoming.amount, Decimal::from_str("2350.00").unwrap());
+        assert_eq!(incoming.reference.as_deref(), Some("2527600001"));
+        // Subfields wrapped over two lines are joined back
+        assert_eq!(
+            incoming.description,
+            "ФАКТУРА 0000001207 ОТ 25.09.2025 КЛИЕНТ ООД BG87UNCR70001512345678"
+        );
+        assert_eq!(
+            BankImportService::extract_iban(&incoming.description).as_deref(),
+            Some("BG87UNCR70001512345678")
+        );
+        assert_eq!(
+            transactions[1].description,
+            "ВОДА СЕПТЕМВРИ ВОДОСНАБДЯВАНЕ И КАНАЛИЗАЦИЯ ЕАД BG19STSA93000023456789"
+        );
+        // Without remittance information the booking text is used
+        assert!(!transactions[2].is_credit);
+        assert_eq!(transactions[2].description, "Такса превод");
+    }
+
+    #[test]
+    fn parse_allianz_mt940_sample_reads_reversals_and_details() {
+        let content = sample(
+            "allianz/allianz-mt940_BG19BUIN95611012345678.sta",
+            BankImportFormat::AllianzMt940,
+        );
+        let transactions =
+            BankImportService::parse_mt940_details(&content, "BGN", Mt940Details::SlashCodes)
+                .expect("failed to parse Allianz MT940");
+
+        assert_eq!(transactions.len(), 3);
+        assert!(transactions[0].is_credit);
+        assert_eq!(
+            transactions[0].description,
+            "ФАКТУРА 0000000088/2025 КЛИЕНТ ЕООД BG27STSA93000098765432"
+        );
+        assert!(!transactions[1].is_credit);
+        assert_eq!(
+            transactions[1].description,
+            "АБОНАМЕНТ СЕПТЕМВРИ ТЕЛЕКОМ АД BG80BNBG96611020345678"
+        );
+        // RD reverses the debit, so the money comes back in
+        let reversal = &transactions[2];
+        assert!(reversal.is_credit);
+        assert_eq!(reversal.amount, Decimal::from_str("95.00").unwrap());
+        assert_eq!(reversal.description, "СТОРНО ИЗХОДЯЩ ПРЕВОД ТЕЛЕКОМ АД");
+    }
+
+    #[test]
+    fn parse_camt052_sample_skips_pending_entries() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.052.001.02">
+  <BkToCstmrAcctRpt>
+    <GrpHdr><MsgId>RPT-1</MsgId></GrpHdr>
+    <Rpt>
+      <Id>RPT-1-1</Id>
+      <Ntry>
+        <Amt Ccy="BGN">150.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2025-10-01</Dt></BookgDt>
+        <ValDt><Dt>2025-10-01</Dt></ValDt>
+        <AcctSvcrRef>INTRA-1</AcctSvcrRef>
+        <NtryDtls><TxDtls><RmtInf><Ustrd>Фактура 4004</Ustrd></RmtInf></TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="BGN">80.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <ValDt><Dt>2025-10-02</Dt></ValDt>
+      </Ntry>
+    </Rpt>
+  </BkToCstmrAcctRpt>
+</Document>"#;
+        let transactions =
+            BankImportService::parse_camt(content).expect("failed to parse CAMT.052");
+
+        assert_eq!(transactions.len(), 1);
+        assert!(transactions[0].is_credit);
+        assert_eq!(transactions[0].amount, Decimal::from_str("150.00").unwrap());
+        assert_eq!(transactions[0].reference.as_deref(), Some("INTRA-1"));
+        assert_eq!(transactions[0].description, "Фактура 4004");
+    }
+
+    #[test]
+    fn parse_camt054_sample_extracts_notified_entries() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.054.001.08">
+  <BkToCstmrDbtCdtNtfctn>
+    <GrpHdr><MsgId>NTF-1</MsgId></GrpHdr>
+    <Ntfctn>
+      <Id>NTF-1-1</Id>
+      <Ntry>
+        <Amt Ccy="EUR">99.90</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <ValDt><Dt>2025-10-03</Dt></ValDt>
+        <AcctSvcrRef>NTF-REF-7</AcctSvcrRef>
+      </Ntry>
+    </Ntfctn>
+  </BkToCstmrDbtCdtNtfctn>
+</Document>"#;
+        let transactions =
+            BankImportService::parse_camt(content).expect("failed to parse CAMT.054");
+
+        assert_eq!(transactions.len(), 1);
+        let debit = &transactions[0];
+        assert!(!debit.is_credit);
+        assert_eq!(debit.currency, "EUR");
+        // Notifications without a booking date are booked on the value date
+        assert_eq!(
+            debit.booking_date,
+            NaiveDate::from_ymd_opt(2025, 10, 3).unwrap()
+        );
+        assert_eq!(debit.description, "NTF-REF-7");
+    }
+
+    #[test]
+    fn parse_mt942_sample_extracts_transactions() {
+        let content = "\
+:20:MT942REF
+:25:BG80BNBG96611020345678
+:28C:00001/001
+:34F:BGN0,
+:13D:2510061030+0300
+:61:2510061006C1250,00NTRFNONREF//AB12345
+:86:Плащане по фактура 5005 Клиент ЕООД
+:61:2510061006D35,20NMSCNONREF//FEE778
+:86:Такса
+:90D:1BGN35,20
+:90C:1BGN1250,00
+-";
+        let transactions =
+            BankImportService::parse_mt940(content, "BGN").expect("failed to parse MT942");
+
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions[0].is_credit);
+        assert_eq!(
+            transactions[0].amount,
+            Decimal::from_str("1250.00").unwrap()
+        );
+        assert_eq!(transactions[0].reference.as_deref(), Some("AB12345"));
+        assert_eq!(
+            transactions[0].description,
+            "Плащане по фактура 5005 Клиент ЕООД"
+        );
+        assert!(!transactions[1].is_credit);
+        assert_eq!(transactions[1].amount, Decimal::from_str("35.20").unwrap());
+    }
 }
@@ -0,0 +1,917 @@
+//! Bank Reconciliation Service
+//!
+//! Bank imports book every transaction against the buffer account of the
+//! bank profile. Reconciliation proposes which open invoices a buffer line
+//! pays, using the invoice number in the payment description, the EIK in it,
+//! the counterparty IBAN (learned from earlier reconciliations) and the
+//! amount.
+//!
+//! Confirming a match re-books the buffer line of the bank entry to the
+//! receivable (411) or payable (401) account of the invoice with its
+//! counterpart. A transaction may settle several invoices, an invoice may be
+//! settled by several transactions, and whatever is not allocated stays on
+//! the buffer account. Undoing a reconciliation moves the amounts back to
+//! the buffer account. Both only change draft bank entries; a posted entry
+//! has to be unposted first.
+//!
+//! What is open on an invoice follows the net balance of the counterpart on
+//! the receivable or payable account, so payments booked outside
+//! reconciliation (cash, offsets, credit notes) settle invoices as well.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use sea_orm::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::entities::audit_log::AuditAction;
+use crate::entities::bank_reconciliation_match::ConfirmBankReconciliationInput;
+use crate::entities::{
+    account, bank_profile, bank_reconciliation_match, bank_transaction_fingerprint, counterpart,
+    entry_line, journal_entry,
+};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+use crate::services::bank_transaction_parser::BankTransactionParser;
+
+/// Receivables from customers
+pub const RECEIVABLES_ACCOUNT_CODE: &str = "411";
+/// Payables to suppliers
+pub const PAYABLES_ACCOUNT_CODE: &str = "401";
+
+/// Suggestions below this score only match on the amount
+const IDENTIFIED_SCORE: i32 = 30;
+
+pub struct BankReconciliationService;
+
+/// Part of an imported bank transaction still on the buffer account
+#[derive(Debug, Clone, SimpleObject)]
+pub struct BufferLine {
+    pub bank_entry_id: i32,
+    pub entry_line_id: i32,
+    pub booking_date: NaiveDate,
+    pub description: String,
+    /// Amount in the base currency still on the buffer account
+    pub amount: Decimal,
+    /// Money received, which settles receivables; otherwise payables
+    pub is_incoming: bool,
+    pub counterparty_iban: Option<String>,
+    pub eik: Option<String>,
+}
+
+/// Invoice with an amount not yet settled through reconciliation
+#[derive(Debug, Clone, SimpleObject)]
+pub struct OpenInvoice {
+    pub invoice_entry_id: i32,
+    pub document_number: Option<String>,
+    pub document_date: NaiveDate,
+    pub counterpart_id: i32,
+    pub counterpart_name: String,
+    pub counterpart_eik: Option<String>,
+    /// Receivable or payable account the invoice is booked on
+    pub account_id: i32,
+    pub is_receivable: bool,
+    pub invoice_amount: Decimal,
+    pub open_amount: Decimal,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ReconciliationSuggestion {
+    pub invoice_entry_id: i32,
+    pub document_number: Option<String>,
+    pub counterpart_id: i32,
+    pub counterpart_name: String,
+    pub open_amount: Decimal,
+    /// Part of the bank transaction proposed for the invoice
+    pub amount: Decimal,
+    /// Higher is more certain
+    pub score: i32,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ReconciliationProposal {
+    pub line: BufferLine,
+    /// Allocations of the transaction when the invoice or counterpart was
+    /// identified; otherwise alternative invoices of the same amount
+    pub suggestions: Vec<ReconciliationSuggestion>,
+}
+
+impl BankReconciliationService {
+    /// Propose invoices for a buffer line. `known_ibans` maps counterparty
+    /// IBANs to the counterpart they were reconciled with before.
+    pub fn suggest(
+        line: &BufferLine,
+        invoices: &[OpenInvoice],
+        known_ibans: &HashMap<String, i32>,
+    ) -> Vec<ReconciliationSuggestion> {
+        let tokens: Vec<String> = line
+            .description
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .map(str::to_uppercase)
+            .collect();
+        let iban_counterpart = line
+            .counterparty_iban
+            .as_ref()
+            .and_then(|iban| known_ibans.get(iban))
+            .copied();
+
+        let mut scored: Vec<(i32, Vec<String>, &OpenInvoice)> = invoices
+            .iter()
+            .filter(|invoice| {
+                invoice.is_receivable == line.is_incoming && invoice.open_amount > Decimal::ZERO
+            })
+            .filter_map(|invoice| {
+                let mut score = 0;
+                let mut reasons = Vec::new();
+                if let Some(number) = &invoice.document_number {
+                    if Self::mentions_document(&tokens, number) {
+                        score += 50;
+                        reasons.push(format!("Номер на фактура {}", number));
+                    }
+                }
+                if line.eik.is_some() && line.eik == invoice.counterpart_eik {
+                    score += 30;
+                    reasons.push("ЕИК на контрагента".to_string());
+                }
+                if iban_counterpart == Some(invoice.counterpart_id) {
+                    score += 30;
+                    reasons.push("IBAN от предишно плащане".to_string());
+                }
+                if invoice.open_amount == line.amount {
+                    score += 20;
+                    reasons.push("Точна сума".to_string());
+                }
+                (score > 0).then_some((score, reasons, invoice))
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(a.2.document_date.cmp(&b.2.document_date))
+                .then(a.2.invoice_entry_id.cmp(&b.2.invoice_entry_id))
+        });
+
+        let suggestion = |score, reasons, invoice: &OpenInvoice, amount| ReconciliationSuggestion {
+            invoice_entry_id: invoice.invoice_entry_id,
+            document_number: invoice.document_number.clone(),
+            counterpart_id: invoice.counterpart_id,
+            counterpart_name: invoice.counterpart_name.clone(),
+            open_amount: invoice.open_amount,
+            amount,
+            score,
+            reasons,
+        };
+
+        if scored
+            .iter()
+            .any(|(score, _, _)| *score >= IDENTIFIED_SCORE)
+        {
+            // Settle the identified invoices, best match and oldest first
+            let mut remaining = line.amount;
+            let mut suggestions = Vec::new();
+            for (score, reasons, invoice) in scored {
+                if score < IDENTIFIED_SCORE || remaining <= Decimal::ZERO {
+                    continue;
+                }
+                let amount = invoice.open_amount.min(remaining);
+                remaining -= amount;
+                suggestions.push(suggestion(score, reasons, invoice, amount));
+            }
+            suggestions
+        } else {
+            scored
+                .into_iter()
+                .map(|(score, reasons, invoice)| suggestion(score, reasons, invoice, line.amount))
+                .collect()
+        }
+    }
+
+    /// Whether the payment description quotes an invoice number; payers
+    /// often leave out the leading zeros
+    fn mentions_document(tokens: &[String], document_number: &str) -> bool {
+        let number: String = document_number
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_uppercase();
+        let short = number.trim_start_matches('0');
+        if short.len() < 3 {
+            return false;
+        }
+        tokens
+            .iter()
+            .any(|token| *token == number || token.trim_start_matches('0') == short)
+    }
+
+    /// Reconciliation proposals for the buffer lines of a bank profile
+    pub async fn proposals(
+        db: &DatabaseConnection,
+        bank_profile_id: i32,
+    ) -> Result<Vec<ReconciliationProposal>> {
+        let profile = bank_profile::Entity::find_by_id(bank_profile_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Bank profile not found"))?;
+
+        let lines = Self::buffer_lines(db, &profile).await?;
+        let invoices = Self::open_invoices(db, profile.company_id, None).await?;
+        let known_ibans = Self::known_ibans(db, profile.company_id).await?;
+
+        Ok(lines
+            .into_iter()
+            .map(|line| {
+                let suggestions = Self::suggest(&line, &invoices, &known_ibans);
+                ReconciliationProposal { line, suggestions }
+            })
+            .collect())
+    }
+
+    /// Buffer-account lines of the draft bank entries of a profile
+    pub async fn buffer_lines<C: ConnectionTrait>(
+        db: &C,
+        profile: &bank_profile::Model,
+    ) -> Result<Vec<BufferLine>> {
+        let lines = entry_line::Entity::find()
+            .find_also_related(journal_entry::Entity)
+            .filter(journal_entry::Column::CompanyId.eq(profile.company_id))
+            .filter(journal_entry::Column::IsPosted.eq(false))
+            .filter(entry_line::Column::AccountId.eq(profile.buffer_account_id))
+            .order_by_asc(journal_entry::Column::AccountingDate)
+            .order_by_asc(entry_line::Column::Id)
+            .all(db)
+            .await?;
+
+        let entry_ids: Vec<i32> = lines
+            .iter()
+            .map(|(line, _)| line.journal_entry_id)
+            .collect();
+        let ibans: HashMap<i32, String> = bank_transaction_fingerprint::Entity::find()
+            .filter(bank_transaction_fingerprint::Column::JournalEntryId.is_in(entry_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|fingerprint| {
+                fingerprint
+                    .counterparty_iban
+                    .map(|iban| (fingerprint.journal_entry_id, iban))
+            })
+            .collect();
+
+        Ok(lines
+            .into_iter()
+            .filter_map(|(line, entry)| {
+                let entry = entry?;
+                let amount = line.debit_amount + line.credit_amount;
+                if amount.is_zero() {
+                    return None;
+                }
+                let description = line.description.clone().unwrap_or(entry.description);
+                Some(BufferLine {
+                    bank_entry_id: line.journal_entry_id,
+                    entry_line_id: line.id,
+                    booking_date: entry.accounting_date,
+                    eik: BankTransactionParser::extract_eik(&description),
+                    description,
+                    amount,
+                    is_incoming: line.credit_amount > Decimal::ZERO,
+                    counterparty_iban: ibans.get(&line.journal_entry_id).cloned(),
+                })
+            })
+            .collect())
+    }
+
+    /// Invoices on the receivable and payable accounts not yet covered by
+    /// the net balance of their counterpart. Payments reconciled to an
+    /// invoice settle that invoice; every other payment, credit note or
+    /// offset on the account settles the oldest invoices first.
+    pub async fn open_invoices<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        counterpart_id: Option<i32>,
+    ) -> Result<Vec<OpenInvoice>> {
+        let accounts: HashMap<i32, bool> = account::Entity::find()
+            .filter(account::Column::CompanyId.eq(company_id))
+            .filter(
+                Condition::any()
+                    .add(account::Column::Code.starts_with(RECEIVABLES_ACCOUNT_CODE))
+                    .add(account::Column::Code.starts_with(PAYABLES_ACCOUNT_CODE)),
+            )
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|account| {
+                (
+                    account.id,
+                    account.code.starts_with(RECEIVABLES_ACCOUNT_CODE),
+                )
+            })
+            .collect();
+
+        // Reconciled lines stay in draft bank entries until those are posted
+        let matches = bank_reconciliation_match::Entity::find()
+            .filter(bank_reconciliation_match::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?;
+        let reconciled_invoices: HashMap<i32, i32> = matches
+            .iter()
+            .filter_map(|m| Some((m.entry_line_id, m.invoice_entry_id?)))
+            .collect();
+        let reconciled_lines: Vec<i32> = matches.iter().map(|m| m.entry_line_id).collect();
+
+        let mut query = entry_line::Entity::find()
+            .find_also_related(journal_entry::Entity)
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .filter(
+                Condition::any()
+                    .add(journal_entry::Column::IsPosted.eq(true))
+                    .add(entry_line::Column::Id.is_in(reconciled_lines)),
+            )
+            .filter(entry_line::Column::AccountId.is_in(accounts.keys().copied()))
+            .filter(entry_line::Column::CounterpartId.is_not_null());
+        if let Some(counterpart_id) = counterpart_id {
+            query = query.filter(entry_line::Column::CounterpartId.eq(counterpart_id));
+        }
+        let lines = query.all(db).await?;
+
+        // Invoices debit receivables and credit payables; the other side is
+        // a payment, credit note or offset
+        let mut invoices: HashMap<(i32, i32, i32), OpenInvoice> = HashMap::new();
+        let mut settlements: Vec<(Option<i32>, i32, i32, Decimal)> = Vec::new();
+        for (line, entry) in lines {
+            let (Some(entry), Some(counterpart_id)) = (entry, line.counterpart_id) else {
+                continue;
+            };
+            let is_receivable = accounts[&line.account_id];
+            let amount = if is_receivable {
+                line.debit_amount - line.credit_amount
+            } else {
+                line.credit_amount - line.debit_amount
+            };
+            if amount < Decimal::ZERO {
+                let invoice_entry_id = reconciled_invoices.get(&line.id).copied();
+                settlements.push((invoice_entry_id, counterpart_id, line.account_id, -amount));
+                continue;
+            }
+            if amount.is_zero() || !entry.is_posted {
+                continue;
+            }
+            let invoice = invoices
+                .entry((entry.id, counterpart_id, line.account_id))
+                .or_insert_with(|| OpenInvoice {
+                    invoice_entry_id: entry.id,
+                    document_number: entry.document_number.clone(),
+                    document_date: entry.document_date,
+                    counterpart_id,
+                    counterpart_name: String::new(),
+                    counterpart_eik: None,
+                    account_id: line.account_id,
+                    is_receivable,
+                    invoice_amount: Decimal::ZERO,
+                    open_amount: Decimal::ZERO,
+                });
+            invoice.invoice_amount += amount;
+            invoice.open_amount += amount;
+        }
+
+        let mut invoices: Vec<OpenInvoice> = invoices.into_values().collect();
+        Self::settle(&mut invoices, settlements);
+
+        let counterpart_ids: HashSet<i32> = invoices
+            .iter()
+            .map(|invoice| invoice.counterpart_id)
+            .collect();
+        let counterparts: HashMap<i32, counterpart::Model> = counterpart::Entity::find()
+            .filter(counterpart::Column::Id.is_in(counterpart_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|counterpart| (counterpart.id, counterpart))
+            .collect();
+
+        let mut result: Vec<OpenInvoice> = invoices
+            .into_iter()
+            .filter(|invoice| invoice.open_amount > Decimal::ZERO)
+            .map(|mut invoice| {
+                if let Some(counterpart) = counterparts.get(&invoice.counterpart_id) {
+                    invoice.counterpart_name = counterpart.name.clone();
+                    invoice.counterpart_eik = counterpart.eik.clone();
+                }
+                invoice
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            a.document_date
+                .cmp(&b.document_date)
+                .then(a.invoice_entry_id.cmp(&b.invoice_entry_id))
+        });
+
+        Ok(result)
+    }
+
+    /// Reduce the open amounts by settlements given as (reconciled invoice,
+    /// counterpart, account, amount). A reconciled settlement goes to its
+    /// invoice; the rest of the counterpart's settlements on the account go
+    /// to its oldest invoices first.
+    pub fn settle(
+        invoices: &mut [OpenInvoice],
+        settlements: impl IntoIterator<Item = (Option<i32>, i32, i32, Decimal)>,
+    ) {
+        invoices.sort_by(|a, b| {
+            a.document_date
+                .cmp(&b.document_date)
+                .then(a.invoice_entry_id.cmp(&b.invoice_entry_id))
+        });
+
+        let mut unallocated: HashMap<(i32, i32), Decimal> = HashMap::new();
+        for (invoice_entry_id, counterpart_id, account_id, amount) in settlements {
+            let invoice = invoice_entry_id.and_then(|invoice_entry_id| {
+                invoices.iter_mut().find(|invoice| {
+                    invoice.invoice_entry_id == invoice_entry_id
+                        && invoice.counterpart_id == counterpart_id
+                        && invoice.account_id == account_id
+                })
+            });
+            let rest = match invoice {
+                Some(invoice) => {
+                    let applied = amount.min(invoice.open_amount);
+                    invoice.open_amount -= applied;
+                    amount - applied
+                }
+                None => amount,
+            };
+            *unallocated.entry((counterpart_id, account_id)).or_default() += rest;
+        }
+
+        for invoice in invoices.iter_mut() {
+            let Some(available) =
+                unallocated.get_mut(&(invoice.counterpart_id, invoice.account_id))
+            else {
+                continue;
+            };
+            let applied = invoice.open_amount.min(*available);
+            invoice.open_amount -= applied;
+            *available -= applied;
+        }
+    }
+
+    /// Counterparty IBANs of reconciled transactions and the counterpart
+    /// they were matched to, latest match winning
+    pub(crate) async fn known_ibans<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+    ) -> Result<HashMap<String, i32>> {
+        let matches = bank_reconciliation_match::Entity::find()
+            .filter(bank_reconciliation_match::Column::CompanyId.eq(company_id))
+            .order_by_asc(bank_reconciliation_match::Column::Id)
+            .all(db)
+            .await?;
+        let entry_ids: HashSet<i32> = matches.iter().map(|m| m.bank_entry_id).collect();
+        let ibans: HashMap<i32, String> = bank_transaction_fingerprint::Entity::find()
+            .filter(bank_transaction_fingerprint::Column::JournalEntryId.is_in(entry_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|fingerprint| {
+                fingerprint
+                    .counterparty_iban
+                    .map(|iban| (fingerprint.journal_entry_id, iban))
+            })
+            .collect();
+
+        Ok(matches
+            .into_iter()
+            .filter_map(|m| Some((ibans.get(&m.bank_entry_id)?.clone(), m.counterpart_id)))
+            .collect())
+    }
+
+    /// Re-book the buffer line of a bank entry to the receivables or
+    /// payables of the allocated invoices and counterparts
+    pub async fn confirm<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        input: &ConfirmBankReconciliationInput,
+        user_id: i32,
+    ) -> Result<Vec<bank_reconciliation_match::Model>> {
+        if input.allocations.is_empty() {
+            bail!("Nothing to reconcile");
+        }
+        if input
+            .allocations
+            .iter()
+            .any(|allocation| allocation.amount <= Decimal::ZERO)
+        {
+            bail!("Allocated amounts must be positive");
+        }
+
+        let txn = db.begin().await?;
+        let (entry, profile, lines) =
+            Self::bank_entry(&txn, input.company_id, input.bank_entry_id).await?;
+        AccountingPeriodService::ensure_open(
+            &txn,
+            input.company_id,
+            entry.accounting_date,
+            Some(user_id),
+        )
+        .await?;
+
+        let buffer_line = lines
+            .iter()
+            .find(|line| line.account_id == profile.buffer_account_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("The bank transaction is already fully reconciled"))?;
+        let buffer_amount = buffer_line.debit_amount + buffer_line.credit_amount;
+        let is_incoming = buffer_line.credit_amount > Decimal::ZERO;
+        let allocated: Decimal = input.allocations.iter().map(|a| a.amount).sum();
+        if allocated > buffer_amount {
+            bail!(
+                "Allocated {} exceeds the unreconciled {} of the transaction",
+                allocated,
+                buffer_amount
+            );
+        }
+
+        let before = AuditLogService::journal_entry_snapshot(&txn, entry.id).await?;
+        let invoices = Self::open_invoices(&txn, input.company_id, None).await?;
+        let mut settled: HashMap<(i32, i32), Decimal> = HashMap::new();
+        let mut line_order = lines.iter().map(|line| line.line_order).max().unwrap_or(0);
+        let mut matches = Vec::with_capacity(input.allocations.len());
+
+        for allocation in &input.allocations {
+            let (invoice_entry_id, counterpart_id, account_id) = match allocation.invoice_entry_id {
+                Some(invoice_entry_id) => {
+                    let invoice = invoices
+                        .iter()
+                        .find(|invoice| {
+                            invoice.invoice_entry_id == invoice_entry_id
+                                && invoice.is_receivable == is_incoming
+                                && allocation
+                                    .counterpart_id
+                                    .is_none_or(|id| id == invoice.counterpart_id)
+                        })
+                        .ok_or_else(|| {
+                            anyhow!("Invoice {} has nothing open to settle", invoice_entry_id)
+                        })?;
+                    let total = settled
+                        .entry((invoice.invoice_entry_id, invoice.counterpart_id))
+                        .or_default();
+                    *total += allocation.amount;
+                    if *total > invoice.open_amount {
+                        bail!(
+                            "Invoice {} has only {} open",
+                            invoice.document_number.as_deref().unwrap_or_default(),
+                            invoice.open_amount
+                        );
+                    }
+                    (
+                        Some(invoice.invoice_entry_id),
+                        invoice.counterpart_id,
+                        invoice.account_id,
+                    )
+                }
+                None => {
+                    let counterpart_id = allocation
+                        .counterpart_id
+                        .ok_or_else(|| anyhow!("An advance needs a counterpart"))?;
+                    let code = if is_incoming {
+                        RECEIVABLES_ACCOUNT_CODE
+                    } else {
+                        PAYABLES_ACCOUNT_CODE
+                    };
+                    let account = account::Entity::find()
+                        .filter(account::Column::CompanyId.eq(input.company_id))
+                        .filter(account::Column::Code.eq(code))
+                        .one(&txn)
+                        .await?
+                        .ok_or_else(|| anyhow!("Account {} not found in this company", code))?;
+                    (None, counterpart_id, account.id)
+                }
+            };
+
+            line_order += 1;
+            let line = Self::split_line(&buffer_line, allocation.amount, line_order);
+            let line = entry_line::ActiveModel {
+                account_id: Set(account_id),
+                counterpart_id: Set(Some(counterpart_id)),
+                ..line
+            };
+            let line = entry_line::Entity::insert(line)
+                .exec_with_returning(&txn)
+                .await?;
+
+            let matched = bank_reconciliation_match::ActiveModel {
+                company_id: Set(input.company_id),
+                bank_entry_id: Set(entry.id),
+                entry_line_id: Set(line.id),
+                invoice_entry_id: Set(invoice_entry_id),
+                counterpart_id: Set(counterpart_id),
+                account_id: Set(account_id),
+                amount: Set(allocation.amount),
+                created_by: Set(Some(user_id)),
+                created_at: Set(Utc::now()),
+                ..Default::default()
+            };
+            matches.push(
+                bank_reconciliation_match::Entity::insert(matched)
+                    .exec_with_returning(&txn)
+                    .await?,
+            );
+        }
+
+        Self::set_buffer_amount(&txn, buffer_line, buffer_amount - allocated).await?;
+
+        let after = AuditLogService::journal_entry_snapshot(&txn, entry.id).await?;
+        AuditLogService::record(
+            &txn,
+            Some(user_id),
+            AuditEvent::new(AuditAction::Update, "journal_entries", entry.id)
+                .company(input.company_id)
+                .before(&before)
+                .after(&after),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(matches)
+    }
+
+    /// Move the reconciled amounts of a bank entry back to the buffer account
+    pub async fn undo(
+        db: &DatabaseConnection,
+        company_id: i32,
+        bank_entry_id: i32,
+        user_id: i32,
+    ) -> Result<()> {
+        let txn = db.begin().await?;
+        let (entry, profile, lines) = Self::bank_entry(&txn, company_id, bank_entry_id).await?;
+        AccountingPeriodService::ensure_open(
+            &txn,
+            company_id,
+            entry.accounting_date,
+            Some(user_id),
+        )
+        .await?;
+
+        let matches = bank_reconciliation_match::Entity::find()
+            .filter(bank_reconciliation_match::Column::BankEntryId.eq(entry.id))
+            .all(&txn)
+            .await?;
+        if matches.is_empty() {
+            bail!("The bank transaction is not reconciled");
+        }
+
+        let before = AuditLogService::journal_entry_snapshot(&txn, entry.id).await?;
+        let line_ids: Vec<i32> = matches.iter().map(|m| m.entry_line_id).collect();
+        let reconciled: Vec<&entry_line::Model> = lines
+            .iter()
+            .filter(|line| line_ids.contains(&line.id))
+            .collect();
+        let template = *reconciled
+            .first()
+            .ok_or_else(|| anyhow!("The reconciled lines were not found"))?;
+        let restored: Decimal = reconciled
+            .iter()
+            .map(|line| line.debit_amount + line.credit_amount)
+            .sum();
+
+        bank_reconciliation_match::Entity::delete_many()
+            .filter(bank_reconciliation_match::Column::BankEntryId.eq(entry.id))
+            .exec(&txn)
+            .await?;
+
+        match lines
+            .iter()
+            .find(|line| line.account_id == profile.buffer_account_id)
+        {
+            Some(buffer_line) => {
+                let amount = buffer_line.debit_amount + buffer_line.credit_amount + restored;
+                Self::set_buffer_amount(&txn, buffer_line.clone(), amount).await?;
+            }
+            None => {
+                let line = Self::split_line(template, restored, template.line_order);
+                let line = entry_line::ActiveModel {
+                    account_id: Set(profile.buffer_account_id),
+                    counterpart_id: Set(None),
+                    ..line
+                };
+                entry_line::Entity::insert(line).exec(&txn).await?;
+            }
+        }
+        entry_line::Entity::delete_many()
+            .filter(entry_line::Column::Id.is_in(line_ids))
+            .exec(&txn)
+            .await?;
+
+        let after = AuditLogService::journal_entry_snapshot(&txn, entry.id).await?;
+        AuditLogService::record(
+            &txn,
+            Some(user_id),
+            AuditEvent::new(AuditAction::Update, "journal_entries", entry.id)
+                .company(company_id)
+                .before(&before)
+                .after(&after),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// A draft bank entry of the company with the bank profile that booked it
+    async fn bank_entry<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        bank_entry_id: i32,
+    ) -> Result<(
+        journal_entry::Model,
+        bank_profile::Model,
+        Vec<entry_line::Model>,
+    )> {
+        let entry = journal_entry::Entity::find_by_id(bank_entry_id)
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .lock_exclusive()
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Journal entry not found"))?;
+        if entry.is_posted {
+            bail!(
+                "Bank entry {} is posted; unpost it before changing its reconciliation",
+                entry.entry_number
+            );
+        }
+        let lines = entry_line::Entity::find()
+            .filter(entry_line::Column::JournalEntryId.eq(entry.id))
+            .order_by_asc(entry_line::Column::LineOrder)
+            .all(db)
+            .await?;
+
+        let account_ids: Vec<i32> = lines.iter().map(|line| line.account_id).collect();
+        let profile = bank_profile::Entity::find()
+            .filter(bank_profile::Column::CompanyId.eq(company_id))
+            .filter(bank_profile::Column::AccountId.is_in(account_ids))
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("The journal entry is not a bank transaction"))?;
+
+        Ok((entry, profile, lines))
+    }
+
+    /// New line on the same side and in the same currency as `line`
+    fn split_line(
+        line: &entry_line::Model,
+        amount: Decimal,
+        line_order: i32,
+    ) -> entry_line::ActiveModel {
+        let (debit, credit) = if line.debit_amount > Decimal::ZERO {
+            (amount, Decimal::ZERO)
+        } else {
+            (Decimal::ZERO, amount)
+        };
+        entry_line::ActiveModel {
+            journal_entry_id: Set(line.journal_entry_id),
+            account_id: Set(line.account_id),
+            debit_amount: Set(debit),
+            credit_amount: Set(credit),
+            counterpart_id: Set(line.counterpart_id),
+            currency_code: Set(line.currency_code.clone()),
+            currency_amount: Set(Self::currency_amount(line, amount)),
+            exchange_rate: Set(line.exchange_rate),
+            base_amount: Set(amount),
+            vat_amount: Set(Decimal::ZERO),
+            vat_rate_id: Set(None),
+            quantity: Set(None),
+            unit_of_measure_code: Set(None),
+            description: Set(line.description.clone()),
+            line_order: Set(line_order),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+    }
+
+    /// Leave `amount` on the buffer line, deleting it when nothing is left
+    async fn set_buffer_amount(
+        txn: &DatabaseTransaction,
+        line: entry_line::Model,
+        amount: Decimal,
+    ) -> Result<()> {
+        if amount.is_zero() {
+            entry_line::Entity::delete_by_id(line.id).exec(txn).await?;
+            return Ok(());
+        }
+
+        let currency_amount = Self::currency_amount(&line, amount);
+        let is_debit = line.debit_amount > Decimal::ZERO;
+        let mut model: entry_line::ActiveModel = line.into();
+        if is_debit {
+            model.debit_amount = Set(amount);
+        } else {
+            model.credit_amount = Set(amount);
+        }
+        model.base_amount = Set(amount);
+        model.currency_amount = Set(currency_amount);
+        model.update(txn).await?;
+        Ok(())
+    }
+
+    fn currency_amount(line: &entry_line::Model, amount: Decimal) -> Option<Decimal> {
+        match line.exchange_rate {
+            Some(rate) if !rate.is_zero() && rate != Decimal::ONE => Some(
+                (amount / rate).round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero),
+            ),
+            _ => Some(amount),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn invoice(id: i32, number: &str, counterpart_id: i32, eik: &str, open: &str) -> OpenInvoice {
+        OpenInvoice {
+            invoice_entry_id: id,
+            document_number: Some(number.to_string()),
+            document_date: NaiveDate::from_ymd_opt(2025, 3, id as u32).unwrap(),
+            counterpart_id,
+            counterpart_name: format!("Клиент {}", counterpart_id),
+            counterpart_eik: Some(eik.to_string()),
+            account_id: 411,
+            is_receivable: true,
+            invoice_amount: dec(open),
+            open_amount: dec(open),
+        }
+    }
+
+    #[test]
+    fn payment_of_several_invoices_is_split_between_them() {
+        let invoices = vec![
+            invoice(1, "0000000101", 7, "123456789", "100.00"),
+            invoice(2, "0000000102", 7, "123456789", "80.00"),
+            invoice(3, "0000000103", 8, "987654321", "150.00"),
+        ];
+        let line = BufferLine {
+            bank_entry_id: 50,
+            entry_line_id: 51,
+            booking_date: NaiveDate::from_ymd_opt(2025, 4, 2).unwrap(),
+            description: "Плащане по ф-ри 101 и 102, ЕИК 123456789".to_string(),
+            amount: dec("150.00"),
+            is_incoming: true,
+            counterparty_iban: None,
+            eik: BankTransactionParser::extract_eik("ЕИК 123456789"),
+        };
+
+        let suggestions = BankReconciliationService::suggest(&line, &invoices, &HashMap::new());
+
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].invoice_entry_id, 1);
+        assert_eq!(suggestions[0].amount, dec("100.00"));
+        assert_eq!(suggestions[1].invoice_entry_id, 2);
+        assert_eq!(suggestions[1].amount, dec("50.00"));
+
+        // Without number or EIK only the invoice of the same amount is offered
+        let anonymous = BufferLine {
+            description: "Превод".to_string(),
+            eik: None,
+            ..line
+        };
+        let suggestions =
+            BankReconciliationService::suggest(&anonymous, &invoices, &HashMap::new());
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].invoice_entry_id, 3);
+    }
+
+    #[test]
+    fn payments_outside_reconciliation_settle_the_oldest_invoices() {
+        let mut invoices = vec![
+            invoice(2, "0000000102", 7, "123456789", "80.00"),
+            invoice(1, "0000000101", 7, "123456789", "100.00"),
+            invoice(3, "0000000103", 8, "987654321", "150.00"),
+        ];
+
+        BankReconciliationService::settle(
+            &mut invoices,
+            vec![
+                // Cash payment and credit note of counterpart 7
+                (None, 7, 411, dec("90.00")),
+                (None, 7, 411, dec("30.00")),
+                // Bank payment reconciled to invoice 3
+                (Some(3), 8, 411, dec("150.00")),
+            ],
+        );
+
+        let open: Vec<(i32, Decimal)> = invoices
+            .iter()
+            .map(|invoice| (invoice.invoice_entry_id, invoice.open_amount))
+            .collect();
+        assert_eq!(
+            open,
+            vec![(1, Decimal::ZERO), (2, dec("60.00")), (3, Decimal::ZERO)]
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use sea_orm::{DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, QueryOrder};
+use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, QueryOrder};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -161,63 +161,43 @@ impl BankTransactionParser {
         }))
     }
 
-    /// Намира подходяща AI настройка за автоматично попълване на сметки въз основа на описание
-    pub async fn find_matching_ai_setting(
-        &self,
-        db: &DatabaseConnection,
+    /// Извлича ЕИК/Булстат от описание без AI: първата самостоятелна
+    /// поредица от 9 или 13 цифри (номерата на IBAN са по-дълги)
+    pub fn extract_eik(description: &str) -> Option<String> {
+        description
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|digits| digits.len() == 9 || digits.len() == 13)
+            .map(str::to_string)
+    }
+
+    /// Активните правила на компанията, с най-висок приоритет първо
+    pub async fn active_rules<C: ConnectionTrait>(
+        db: &C,
         company_id: i32,
-        description: &str,
-    ) -> Result<Option<ai_bank_accounting_setting::Model>> {
-        // Зареждане на всички активни AI настройки за компанията, сортирани по приоритет
-        let settings = AiBankAccountingSetting::find()
+    ) -> Result<Vec<ai_bank_accounting_setting::Model>> {
+        Ok(AiBankAccountingSetting::find()
             .filter(ai_bank_accounting_setting::Column::CompanyId.eq(company_id))
             .filter(ai_bank_accounting_setting::Column::IsActive.eq(true))
             .order_by_desc(ai_bank_accounting_setting::Column::Priority)
+            .order_by_asc(ai_bank_accounting_setting::Column::Id)
             .all(db)
-            .await?;
-
-        // Намиране на първата съвпадаща настройка (с най-висок приоритет)
-        for setting in settings {
-            if setting.matches_description(description) {
-                return Ok(Some(setting));
-            }
-        }
-
-        Ok(None)
+            .await?)
     }
 
-    /// Автоматично попълване на банкова транзакция с подходящи сметки
-    pub async fn auto_complete_transaction(
-        &self,
-        db: &DatabaseConnection,
-        company_id: i32,
+    /// Правилото с най-висок приоритет, което отговаря на описанието и
+    /// посоката на транзакцията и има сметка за осчетоводяване
+    pub fn select_rule<'a>(
+        rules: &'a [ai_bank_accounting_setting::Model],
         description: &str,
-    ) -> Result<Option<AutoCompletedTransaction>> {
-        // Намиране на съвпадаща AI настройка
-        let setting = match self.find_matching_ai_setting(db, company_id, description).await? {
-            Some(s) => s,
-            None => return Ok(None),
-        };
-
-        // Парсване на транзакцията за извличане на допълнителна информация (ако е необходимо)
-        let parsed_data = self.parse_transaction_description(db, description).await.ok().flatten();
-
-        // Извличане на името на контрагента, ако е налично
-        let counterpart_name = parsed_data.as_ref()
-            .and_then(|d| d.counterpart_name.as_ref())
-            .map(|s| s.as_str());
-
-        Ok(Some(AutoCompletedTransaction {
-            pattern_name: setting.pattern_name.clone(),
-            transaction_type: setting.transaction_type.clone(),
-            account_id: setting.account_id,
-            counterpart_account_id: setting.counterpart_account_id,
-            vat_account_id: setting.vat_account_id,
-            direction: setting.direction.clone(),
-            description: setting.format_description(counterpart_name, description),
-            parsed_data,
-        }))
+        is_credit: bool,
+    ) -> Option<&'a ai_bank_accounting_setting::Model> {
+        rules.iter().find(|rule| {
+            rule.account_id.is_some()
+                && rule.applies_to(is_credit)
+                && rule.matches_description(description)
+        })
     }
+
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -244,19 +224,6 @@ pub struct ParsedTransactionData {
     pub confidence: f64,
 }
 
-/// Автоматично попълнена транзакция с подходящи сметки
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AutoCompletedTransaction {
-    pub pattern_name: String,
-    pub transaction_type: String,
-    pub account_id: Option<i32>,
-    pub counterpart_account_id: Option<i32>,
-    pub vat_account_id: Option<i32>,
-    pub direction: String,
-    pub description: String,
-    pub parsed_data: Option<ParsedTransactionData>,
-}
-
 #[derive(Debug, Deserialize)]
 struct MistralResponse {
     choices: Vec<MistralChoice>,
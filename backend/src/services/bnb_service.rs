@@ -11,6 +11,7 @@ use std::str::FromStr;
 
 use crate::entities::exchange_rate::{BnbRate, BnbResponse, RateSource};
 use crate::entities::{currency, exchange_rate};
+use crate::services::euro_changeover::{EuroChangeoverService, LEGACY_BASE_CURRENCY};
 
 pub struct BnbService {
     client: Client,
@@ -119,12 +120,13 @@ impl BnbService {
         let bnb_rates = self.fetch_rates_for_date(date).await?;
         let mut updated_count = 0;
 
-        // Get BGN currency (base currency)
+        // BNB quotes its reference rates in leva whatever the companies' base
+        // currency; books kept in euro convert through the fixed rate
         let bgn_currency = currency::Entity::find()
-            .filter(currency::Column::Code.eq("BGN"))
+            .filter(currency::Column::Code.eq(LEGACY_BASE_CURRENCY))
             .one(db)
             .await?
-            .ok_or_else(|| anyhow!("BGN currency not found"))?;
+            .ok_or_else(|| anyhow!("{} currency not found", LEGACY_BASE_CURRENCY))?;
 
         for bnb_rate in bnb_rates {
             // Find currency by BNB code
@@ -280,6 +282,56 @@ impl BnbService {
         Ok(rate)
     }
 
+    /// Units of `base_code` per unit of `currency_code` on `date`, from the
+    /// stored BNB/ECB rates in either direction. BGN and EUR use the fixed
+    /// peg.
+    pub async fn get_rate_to_base(
+        &self,
+        db: &DatabaseConnection,
+        currency_code: &str,
+        base_code: &str,
+        date: NaiveDate,
+    ) -> Result<Decimal> {
+        if currency_code == base_code {
+            return Ok(Decimal::ONE);
+        }
+        if let Some(rate) = EuroChangeoverService::fixed_conversion_rate(currency_code, base_code) {
+            return Ok(rate);
+        }
+
+        let find_currency = |code: &str| {
+            currency::Entity::find()
+                .filter(currency::Column::Code.eq(code.to_string()))
+                .one(db)
+        };
+        let foreign = find_currency(currency_code)
+            .await?
+            .ok_or_else(|| anyhow!("Currency {} not found", currency_code))?;
+        let base = find_currency(base_code)
+            .await?
+            .ok_or_else(|| anyhow!("Currency {} not found", base_code))?;
+
+        if let Some(rate) = self
+            .get_rate_for_date(db, foreign.id, base.id, date)
+            .await?
+        {
+            return Ok(rate.rate);
+        }
+        if let Some(rate) = self
+            .get_rate_for_date(db, base.id, foreign.id, date)
+            .await?
+        {
+            return Ok(rate.reverse_rate);
+        }
+
+        Err(anyhow!(
+            "No {}/{} exchange rate on or before {}; update the exchange rates first",
+            currency_code,
+            base_code,
+            date
+        ))
+    }
+
     /// Check which currencies need rate updates
     pub async fn get_currencies_needing_updates(
         &self,
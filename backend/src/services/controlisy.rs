@@ -8,6 +8,13 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::entities::audit_log::AuditAction;
+use crate::entities::JournalSeriesKind;
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+use crate::services::euro_changeover::{EuroChangeoverService, LEGACY_BASE_CURRENCY};
+use crate::services::journal_numbering::JournalNumberingService;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ControlisyData {
     pub contractors: Vec<Contractor>,
@@ -716,7 +723,11 @@ impl ControlisyService {
         Ok(())
     }
 
-    pub async fn process_import(db: &DatabaseConnection, import_id: i32) -> Result<()> {
+    pub async fn process_import(
+        db: &DatabaseConnection,
+        import_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<()> {
         // Update status to processing (from staged or reviewed status)
         db.execute(Statement::from_sql_and_values(
             DbBackend::Postgres,
@@ -761,7 +772,7 @@ impl ControlisyService {
                 document.document_number
             );
 
-            match Self::process_document(db, company_id, import_id, document).await {
+            match Self::process_document(db, company_id, import_id, document, user_id).await {
                 Ok(_) => {
                     successful_documents += 1;
                     println!(
@@ -884,6 +895,7 @@ impl ControlisyService {
         company_id: i32,
         import_id: i32,
         document: &Document,
+        user_id: Option<i32>,
     ) -> Result<()> {
         println!(
             "🔄 Processing document: {} - {}",
@@ -942,6 +954,7 @@ impl ControlisyService {
             document,
             contractor_id,
             &document_type,
+            user_id,
         )
         .await?;
 
@@ -960,6 +973,7 @@ impl ControlisyService {
         document: &Document,
         contractor_id: Option<i32>,
         import_document_type: &str,
+        user_id: Option<i32>,
     ) -> Result<i32> {
         // Check if this is a payment document (empty or "0" VAT operation ID)
         let is_payment_document = document.ca_vat_operation_id.is_empty()
@@ -1042,49 +1056,69 @@ impl ControlisyService {
             }
         };
 
-        // Generate unique entry number with retry logic
-        let mut retry_count = 0;
-        let entry_number =
-            loop {
-                let candidate = format!(
-                    "CTRL-{}-{}-{}",
-                    document.document_number,
-                    chrono::Utc::now().format("%Y%m%d%H%M%S"),
-                    chrono::Utc::now().timestamp_micros() % 1000000
-                );
-
-                println!("🔢 Trying entry number: {}", candidate);
-
-                // Check if this number already exists
-                let existing = db.query_one(Statement::from_sql_and_values(
-                DbBackend::Postgres,
-                "SELECT id FROM journal_entries WHERE company_id = $1 AND entry_number = $2",
-                vec![company_id.into(), candidate.clone().into()],
-            )).await?;
+        let vat_date = if is_payment_document {
+            document.document_date // Use document_date for payment documents
+        } else {
+            // For VAT documents: different logic for purchases vs sales
+            match import_document_type {
+                "purchase" => document.vat_month, // Purchases: VAT date = period (YYYY-MM-01)
+                "sale" => document.document_date, // Sales: VAT date = document date
+                _ => document.vat_month,          // Default to period date
+            }
+        };
+        // Accounting date logic
+        let accounting_date = if is_payment_document {
+            document.document_date // Payment documents: all dates = document date
+        } else {
+            // For VAT documents: purchases use document date, sales use document date
+            match import_document_type {
+                "purchase" => document.document_date, // Purchases: accounting date = document date
+                "sale" => document.document_date,     // Sales: accounting date = document date
+                _ => document.accounting_month,       // Fallback to accounting_month
+            }
+        };
 
-                if existing.is_none() {
-                    println!("✅ Entry number is unique: {}", candidate);
-                    break candidate; // Number is unique
-                }
+        // Closed periods reject imported documents like manual entries
+        AccountingPeriodService::ensure_dates_open(
+            db,
+            company_id,
+            accounting_date,
+            Some(vat_date),
+            user_id,
+        )
+        .await?;
 
-                retry_count += 1;
-                if retry_count > 10 {
-                    return Err(anyhow::anyhow!(
-                        "Failed to generate unique journal entry number"
-                    ));
-                }
+        let txn = db.begin().await?;
 
-                println!(
-                    "⚠️  Entry number already exists, retrying... (attempt {})",
-                    retry_count
-                );
-                // Small delay before retry
-                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-            };
+        // Controlisy amounts are in leva; books kept in euro take them at the fixed rate
+        let currency_code =
+            EuroChangeoverService::base_currency_code(&txn, company_id, document.document_date)
+                .await?;
+        let to_base = |amount_bgn: Decimal| {
+            EuroChangeoverService::convert_fixed(amount_bgn, LEGACY_BASE_CURRENCY, &currency_code)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Controlisy amounts in BGN cannot be booked in {}",
+                        currency_code
+                    )
+                })
+        };
+
+        // Sales and purchases are numbered in their own series
+        let series_kind = match import_document_type {
+            "sale" => JournalSeriesKind::Sales,
+            _ => JournalSeriesKind::Purchases,
+        };
+        let number =
+            JournalNumberingService::allocate(&txn, company_id, series_kind, accounting_date)
+                .await?;
 
         // Create journal entry with proper VAT fields (or without for payment documents)
-        println!("📝 Creating journal entry with number: {}", entry_number);
-        let entry_result = db
+        println!(
+            "📝 Creating journal entry with number: {}",
+            number.entry_number
+        );
+        let entry_result = txn
             .query_one(Statement::from_sql_and_values(
                 DbBackend::Postgres,
                 r#"
@@ -1093,49 +1127,32 @@ impl ControlisyService {
                 document_date, vat_date, accounting_date,
                 description, total_amount, total_vat_amount,
                 vat_document_type, vat_purchase_operation, vat_sales_operation,
-                created_by, is_posted,
+                created_by, is_posted, series_id, sequence_number,
                 created_at, updated_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, NOW(), NOW())
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, NOW(), NOW())
             RETURNING id
             "#,
                 vec![
                     company_id.into(),
-                    entry_number.into(),
+                    number.entry_number.into(),
                     document.document_number.clone().into(),
                     document.document_date.into(), // Document date always stays the same
-                    if is_payment_document {
-                        document.document_date.into() // Use document_date for payment documents
-                    } else {
-                        // For VAT documents: different logic for purchases vs sales
-                        match import_document_type {
-                            "purchase" => document.vat_month.into(), // Purchases: VAT date = period (YYYY-MM-01)
-                            "sale" => document.document_date.into(), // Sales: VAT date = document date
-                            _ => document.vat_month.into(),          // Default to period date
-                        }
-                    },
-                    // Accounting date logic
-                    if is_payment_document {
-                        document.document_date.into() // Payment documents: all dates = document date
-                    } else {
-                        // For VAT documents: purchases use document date, sales use document date
-                        match import_document_type {
-                            "purchase" => document.document_date.into(), // Purchases: accounting date = document date
-                            "sale" => document.document_date.into(), // Sales: accounting date = document date
-                            _ => document.accounting_month.into(),   // Fallback to accounting_month
-                        }
-                    },
+                    vat_date.into(),
+                    accounting_date.into(),
                     document.reason.clone().into(),
-                    document.total_amount_bgn.into(),
+                    to_base(document.total_amount_bgn)?.into(),
                     if is_payment_document {
                         Decimal::ZERO.into() // No VAT for payment documents
                     } else {
-                        document.vat_amount_bgn.into()
+                        to_base(document.vat_amount_bgn)?.into()
                     },
                     vat_document_type.into(),
                     vat_purchase_op.into(),
                     vat_sales_op.into(),
-                    1.into(),     // Default user ID - should be passed as parameter
+                    user_id.unwrap_or(1).into(),
                     false.into(), // Not posted by default
+                    number.series_id.into(),
+                    number.sequence_number.into(),
                 ],
             ))
             .await?
@@ -1148,20 +1165,21 @@ impl ControlisyService {
         for accounting in &document.accountings {
             for detail in &accounting.accounting_details {
                 let account_id = Self::get_or_create_account(
-                    db,
+                    &txn,
                     company_id,
                     &detail.account_number,
                     &detail.account_name,
                 )
                 .await?;
 
+                let amount = to_base(accounting.amount_bgn)?;
                 let (debit_amount, credit_amount) = if detail.direction == "Debit" {
-                    (accounting.amount_bgn, Decimal::ZERO)
+                    (amount, Decimal::ZERO)
                 } else {
-                    (Decimal::ZERO, accounting.amount_bgn)
+                    (Decimal::ZERO, amount)
                 };
 
-                db.execute(Statement::from_sql_and_values(
+                txn.execute(Statement::from_sql_and_values(
                     DbBackend::Postgres,
                     r#"
                     INSERT INTO entry_lines (
@@ -1177,12 +1195,12 @@ impl ControlisyService {
                         contractor_id.into(),
                         debit_amount.into(),
                         credit_amount.into(),
-                        accounting.amount_bgn.into(), // base_amount
+                        amount.into(),        // base_amount
                         Decimal::ZERO.into(), // VAT amount per line - will be calculated later
                         document.reason.clone().into(),
                         line_order.into(),
-                        "BGN".into(),
-                        Decimal::ONE.into(), // exchange_rate for BGN
+                        currency_code.clone().into(),
+                        Decimal::ONE.into(), // base currency
                     ],
                 ))
                 .await?;
@@ -1191,11 +1209,23 @@ impl ControlisyService {
             }
         }
 
+        let snapshot = AuditLogService::journal_entry_snapshot(&txn, journal_entry_id).await?;
+        AuditLogService::record(
+            &txn,
+            user_id,
+            AuditEvent::new(AuditAction::Create, "journal_entries", journal_entry_id)
+                .company(company_id)
+                .after(&snapshot),
+        )
+        .await?;
+
+        txn.commit().await?;
+
         Ok(journal_entry_id)
     }
 
-    async fn get_or_create_account(
-        db: &DatabaseConnection,
+    async fn get_or_create_account<C: ConnectionTrait>(
+        db: &C,
         company_id: i32,
         account_number: &str,
         account_name: &str,
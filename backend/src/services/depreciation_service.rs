@@ -3,14 +3,22 @@
 //! Handles fixed asset depreciation calculations according to Bulgarian accounting
 //! and tax standards (ЗКПО - Corporate Income Tax Act)
 
+use async_graphql::SimpleObject;
 use chrono::{Datelike, NaiveDate};
 use rust_decimal::Decimal;
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
 use std::collections::HashMap;
 
+use crate::entities::audit_log::AuditAction;
 use crate::entities::{
-    account, depreciation_journal, entry_line, fixed_asset, fixed_asset_category, journal_entry,
+    account, depreciation_journal, entry_line, fixed_asset, fixed_asset_category,
+    fixed_asset_event, fixed_asset_usage, journal_entry, JournalSeriesKind,
 };
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::audit_log::{AuditEvent, AuditLogService};
+use crate::services::journal_numbering::JournalNumberingService;
+use crate::services::journal_storno::JournalStornoService;
 
 /// Service for managing fixed asset depreciation calculations
 pub struct DepreciationService;
@@ -45,6 +53,25 @@ pub struct DepreciationError {
     pub error_message: String,
 }
 
+/// Units produced by an asset in a month and before it
+#[derive(Debug, Clone, Copy)]
+pub struct UsageToDate {
+    pub units: Decimal,
+    pub used_before: Decimal,
+}
+
+/// One month of a projected depreciation schedule
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ProjectedDepreciation {
+    pub period: NaiveDate,
+    pub accounting_amount: Decimal,
+    pub accounting_book_value: Decimal,
+    pub tax_amount: Decimal,
+    pub tax_book_value: Decimal,
+    /// Accounting minus tax book value after the month
+    pub temporary_difference: Decimal,
+}
+
 /// Journal entry creation result
 #[derive(Debug)]
 pub struct DepreciationJournalEntry {
@@ -53,15 +80,27 @@ pub struct DepreciationJournalEntry {
     pub assets_count: i32,
 }
 
+/// Reversed depreciation run
+#[derive(Debug)]
+pub struct DepreciationReversal {
+    /// Journal entries of the run, cancelled by a storno or deleted
+    pub journal_entry_ids: Vec<i32>,
+    /// Storno entries booked for the posted ones
+    pub storno_entry_ids: Vec<i32>,
+    pub accounting_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub assets_count: i32,
+}
+
 impl DepreciationService {
     pub fn new() -> Self {
         Self
     }
 
     /// Calculate monthly depreciation for a single asset
-    pub async fn calculate_monthly_depreciation(
+    pub async fn calculate_monthly_depreciation<C: ConnectionTrait>(
         &self,
-        db: &DatabaseConnection,
+        db: &C,
         asset_id: i32,
         period: NaiveDate,
     ) -> Result<MonthlyDepreciation, Box<dyn std::error::Error + Send + Sync>> {
@@ -92,24 +131,29 @@ impl DepreciationService {
             .into());
         }
 
-        // Check if asset was put into service before or during this period
-        if let Some(service_date) = asset.put_into_service_date {
-            if service_date > period {
-                return Err(format!(
-                    "Asset {} not yet in service for period {}-{}",
-                    asset.name,
-                    period.year(),
-                    period.month()
-                )
-                .into());
-            }
+        // Check if the start convention or the opening balance already charges this period
+        let first_period = asset.first_calculated_period();
+        if first_period > period {
+            return Err(format!(
+                "Asset {} is depreciated from {}-{:02}",
+                asset.name,
+                first_period.year(),
+                first_period.month()
+            )
+            .into());
         }
 
         // Validate sequential period calculation - check if previous period is calculated
         self.validate_sequential_period(db, asset_id, &asset, period).await?;
 
+        let usage = if asset.accounting_depreciation_method == "units_of_production" {
+            Some(self.usage_to_date(db, &asset, period).await?)
+        } else {
+            None
+        };
+
         // Calculate accounting depreciation
-        let accounting_monthly = self.calculate_accounting_depreciation(&asset, period)?;
+        let accounting_monthly = self.calculate_accounting_depreciation(&asset, period, usage)?;
         let accounting_book_value_before = asset.accounting_book_value;
         let accounting_book_value_after =
             (accounting_book_value_before - accounting_monthly).max(asset.accounting_salvage_value);
@@ -132,64 +176,183 @@ impl DepreciationService {
     }
 
     /// Calculate accounting depreciation amount for a single month
-    fn calculate_accounting_depreciation(
+    ///
+    /// Amounts are rounded to cents and never take the book value below the
+    /// salvage value, so every method ends exactly at the salvage value.
+    pub fn calculate_accounting_depreciation(
         &self,
         asset: &fixed_asset::Model,
-        _period: NaiveDate,
+        period: NaiveDate,
+        usage: Option<UsageToDate>,
     ) -> Result<Decimal, Box<dyn std::error::Error + Send + Sync>> {
-        if asset.accounting_book_value <= asset.accounting_salvage_value {
-            return Ok(Decimal::from(0)); // Fully depreciated
+        let remaining = asset.accounting_book_value - asset.accounting_salvage_value;
+        if remaining <= Decimal::ZERO {
+            return Ok(Decimal::ZERO); // Fully depreciated
+        }
+
+        let first_period = asset.first_depreciation_period();
+        if period < first_period {
+            return Ok(Decimal::ZERO);
+        }
+
+        // Months of useful life consumed before this period and charged in it
+        let first_fraction = asset.first_month_fraction();
+        let elapsed = Self::months_between(first_period, period);
+        let (position, portion) = if elapsed == 0 {
+            (Decimal::ZERO, first_fraction)
+        } else {
+            (first_fraction + Decimal::from(elapsed - 1), Decimal::ONE)
+        };
+
+        // A revaluation, improvement or impairment spreads the rest evenly over
+        // the remaining life; usage keeps driving units-of-production
+        if let Some(monthly) = asset
+            .accounting_monthly_depreciation
+            .filter(|_| asset.accounting_depreciation_method != "units_of_production")
+        {
+            return Ok((monthly * portion).round_dp(2).min(remaining));
         }
 
-        match asset.accounting_depreciation_method.as_str() {
+        let depreciable_amount = asset.acquisition_cost - asset.accounting_salvage_value;
+        let amount = match asset.accounting_depreciation_method.as_str() {
             "straight_line" => {
-                let depreciable_amount = asset.acquisition_cost - asset.accounting_salvage_value;
-                let annual_amount =
-                    depreciable_amount * (asset.accounting_depreciation_rate / Decimal::from(100));
-                Ok(annual_amount / Decimal::from(12))
+                let monthly =
+                    depreciable_amount * asset.accounting_depreciation_rate / Decimal::from(1200);
+                // Rounding the running total spreads the cents over the months
+                (monthly * (position + portion)).round_dp(2) - (monthly * position).round_dp(2)
             }
             "declining_balance" => {
                 // Double-declining balance method
-                let book_value = asset.accounting_book_value;
                 let annual_rate = asset.accounting_depreciation_rate / Decimal::from(100);
-                let monthly_rate = annual_rate / Decimal::from(12);
-                let monthly_amount = book_value * monthly_rate;
-
-                // Ensure we don't depreciate below salvage value
-                let max_depreciation = asset.accounting_book_value - asset.accounting_salvage_value;
-                Ok(monthly_amount.min(max_depreciation))
+                (asset.accounting_book_value * annual_rate / Decimal::from(12) * portion)
+                    .round_dp(2)
             }
-            _ => Err(format!(
-                "Unsupported depreciation method: {}",
-                asset.accounting_depreciation_method
-            )
-            .into()),
+            "sum_of_years_digits" => {
+                let life = asset.accounting_useful_life;
+                let after = Self::sum_of_years_remaining(life, position + portion);
+                if after.is_zero() {
+                    remaining
+                } else {
+                    // Rounding the running total keeps the yearly totals on the digits
+                    (depreciable_amount * Self::sum_of_years_remaining(life, position)).round_dp(2)
+                        - (depreciable_amount * after).round_dp(2)
+                }
+            }
+            "units_of_production" => {
+                let usage = usage.ok_or_else(|| {
+                    format!(
+                        "Usage of asset {} for {}-{:02} is not entered",
+                        asset.name,
+                        period.year(),
+                        period.month()
+                    )
+                })?;
+                let capacity = asset
+                    .production_capacity
+                    .filter(|capacity| *capacity > Decimal::ZERO)
+                    .ok_or_else(|| format!("Asset {} has no production capacity", asset.name))?;
+                let remaining_capacity = capacity - usage.used_before;
+                let share = if usage.units >= remaining_capacity {
+                    Decimal::ONE
+                } else {
+                    usage.units / remaining_capacity
+                };
+                (remaining * share).round_dp(2)
+            }
+            _ => {
+                return Err(format!(
+                    "Unsupported depreciation method: {}",
+                    asset.accounting_depreciation_method
+                )
+                .into())
+            }
+        };
+
+        // Ensure we don't depreciate below salvage value
+        Ok(amount.max(Decimal::ZERO).min(remaining))
+    }
+
+    /// Part of the depreciable amount left after `position` months. Each year of
+    /// life takes its digits' share, spread evenly over its months; a final
+    /// partial year takes the share of its fraction.
+    fn sum_of_years_remaining(useful_life_months: i32, position: Decimal) -> Decimal {
+        let life = Decimal::from(useful_life_months.max(1));
+        if position >= life {
+            return Decimal::ZERO;
         }
+        let years = life / Decimal::from(12);
+        let remaining_at = |year: Decimal| {
+            (years - year) * (years - year + Decimal::ONE) / (years * (years + Decimal::ONE))
+        };
+
+        let elapsed_years = position / Decimal::from(12);
+        let year = elapsed_years.floor();
+        let year_end = (year + Decimal::ONE).min(years);
+        let start = remaining_at(year);
+        let end = remaining_at(year_end);
+        start + (end - start) * (elapsed_years - year) / (year_end - year)
+    }
+
+    fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+        (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32
     }
 
     /// Calculate tax depreciation amount for a single month
-    fn calculate_tax_depreciation(
+    pub fn calculate_tax_depreciation(
         &self,
         asset: &fixed_asset::Model,
-        _period: NaiveDate,
+        period: NaiveDate,
     ) -> Result<Decimal, Box<dyn std::error::Error + Send + Sync>> {
-        if asset.tax_book_value <= Decimal::from(0) {
+        if asset.tax_book_value <= Decimal::from(0) || period < asset.first_depreciation_period() {
             return Ok(Decimal::from(0)); // Fully depreciated for tax purposes
         }
 
-        // Tax depreciation is always straight-line in Bulgaria
+        // Tax depreciation is always straight-line for whole months in Bulgaria
         let annual_amount =
-            asset.acquisition_cost * (asset.tax_depreciation_rate / Decimal::from(100));
-        let monthly_amount = annual_amount / Decimal::from(12);
+            asset.tax_depreciable_value * (asset.tax_depreciation_rate / Decimal::from(100));
+        let monthly_amount = (annual_amount / Decimal::from(12)).round_dp(2);
 
         // Ensure we don't depreciate below zero
         Ok(monthly_amount.min(asset.tax_book_value))
     }
 
+    /// Units produced in a period and before it
+    async fn usage_to_date<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        asset: &fixed_asset::Model,
+        period: NaiveDate,
+    ) -> Result<UsageToDate, Box<dyn std::error::Error + Send + Sync>> {
+        let usages = fixed_asset_usage::Entity::find()
+            .filter(fixed_asset_usage::Column::FixedAssetId.eq(asset.id))
+            .filter(fixed_asset_usage::Column::Period.lte(period))
+            .all(db)
+            .await?;
+
+        let units = usages
+            .iter()
+            .find(|usage| usage.period == period)
+            .map(|usage| usage.units)
+            .ok_or_else(|| {
+                format!(
+                    "Usage of asset {} for {}-{:02} is not entered",
+                    asset.name,
+                    period.year(),
+                    period.month()
+                )
+            })?;
+        let used_before = usages
+            .iter()
+            .filter(|usage| usage.period < period)
+            .fold(Decimal::ZERO, |acc, usage| acc + usage.units);
+
+        Ok(UsageToDate { units, used_before })
+    }
+
     /// Calculate monthly depreciation for all active assets in a company
-    pub async fn calculate_bulk_depreciation(
+    pub async fn calculate_bulk_depreciation<C: ConnectionTrait>(
         &self,
-        db: &DatabaseConnection,
+        db: &C,
         company_id: i32,
         period: NaiveDate,
     ) -> Result<BulkDepreciationResult, Box<dyn std::error::Error + Send + Sync>> {
@@ -234,9 +397,9 @@ impl DepreciationService {
     }
 
     /// Save calculated depreciation to the database
-    pub async fn save_depreciation(
+    pub async fn save_depreciation<C: ConnectionTrait>(
         &self,
-        db: &DatabaseConnection,
+        db: &C,
         depreciation: MonthlyDepreciation,
         company_id: i32,
     ) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
@@ -258,33 +421,46 @@ impl DepreciationService {
             .exec(db)
             .await?;
 
-        // Update the fixed asset book values
-        let asset_update = fixed_asset::ActiveModel {
-            id: Set(depreciation.fixed_asset_id),
-            accounting_book_value: Set(depreciation.accounting_book_value_after),
-            tax_book_value: Set(depreciation.tax_book_value_after),
-            accounting_accumulated_depreciation: Set(depreciation.accounting_book_value_before
-                - depreciation.accounting_book_value_after),
-            tax_accumulated_depreciation: Set(
-                depreciation.tax_book_value_before - depreciation.tax_book_value_after
-            ),
-            ..Default::default()
-        };
-
-        fixed_asset::Entity::update(asset_update).exec(db).await?;
+        // Update the fixed asset book values and add the month to the accumulated depreciation
+        fixed_asset::Entity::update_many()
+            .col_expr(
+                fixed_asset::Column::AccountingBookValue,
+                Expr::value(depreciation.accounting_book_value_after),
+            )
+            .col_expr(
+                fixed_asset::Column::TaxBookValue,
+                Expr::value(depreciation.tax_book_value_after),
+            )
+            .col_expr(
+                fixed_asset::Column::AccountingAccumulatedDepreciation,
+                Expr::col(fixed_asset::Column::AccountingAccumulatedDepreciation).add(
+                    depreciation.accounting_book_value_before
+                        - depreciation.accounting_book_value_after,
+                ),
+            )
+            .col_expr(
+                fixed_asset::Column::TaxAccumulatedDepreciation,
+                Expr::col(fixed_asset::Column::TaxAccumulatedDepreciation)
+                    .add(depreciation.tax_book_value_before - depreciation.tax_book_value_after),
+            )
+            .filter(fixed_asset::Column::Id.eq(depreciation.fixed_asset_id))
+            .exec(db)
+            .await?;
 
         Ok(result.last_insert_id)
     }
 
     /// Create journal entry for depreciation postings
-    pub async fn create_depreciation_journal_entry(
+    pub async fn create_depreciation_journal_entry<C: ConnectionTrait + TransactionTrait>(
         &self,
-        db: &DatabaseConnection,
+        db: &C,
         company_id: i32,
         period: NaiveDate,
         user_id: i32,
         reference: Option<String>,
     ) -> Result<DepreciationJournalEntry, Box<dyn std::error::Error + Send + Sync>> {
+        AccountingPeriodService::ensure_open(db, company_id, period, Some(user_id)).await?;
+
         // Get all unposted depreciation for the period and company
         let depreciations = depreciation_journal::Entity::find()
             .filter(depreciation_journal::Column::CompanyId.eq(company_id))
@@ -328,17 +504,20 @@ impl DepreciationService {
             .map(|d| d.accounting_depreciation_amount)
             .fold(Decimal::from(0), |acc, amount| acc + amount);
 
-        // Generate entry number
-        let entry_number = format!(
-            "DEP-{}-{:02}-{}",
-            period.year(),
-            period.month(),
-            chrono::Utc::now().format("%H%M%S")
-        );
+        let txn = db.begin().await?;
+
+        // Take the next number of the depreciation series
+        let number = JournalNumberingService::allocate(
+            &txn,
+            company_id,
+            JournalSeriesKind::Depreciation,
+            period,
+        )
+        .await?;
 
         // Create the main journal entry
         let journal_entry = journal_entry::ActiveModel {
-            entry_number: Set(entry_number),
+            entry_number: Set(number.entry_number),
             company_id: Set(company_id),
             document_date: Set(period),
             accounting_date: Set(period),
@@ -359,18 +538,20 @@ impl DepreciationService {
             total_vat_amount: Set(Decimal::from(0)),
             is_posted: Set(true),
             created_by: Set(user_id),
+            series_id: Set(Some(number.series_id)),
+            sequence_number: Set(Some(number.sequence_number)),
             ..Default::default()
         };
 
         let journal_result = journal_entry::Entity::insert(journal_entry)
-            .exec(db)
+            .exec(&txn)
             .await?;
         let journal_entry_id = journal_result.last_insert_id;
 
         // Create entry lines for each category
         for (category_id, (amount, _)) in category_groups {
             let category = fixed_asset_category::Entity::find_by_id(category_id)
-                .one(db)
+                .one(&txn)
                 .await?
                 .ok_or("Asset category not found")?;
 
@@ -378,7 +559,7 @@ impl DepreciationService {
             let expense_account = account::Entity::find()
                 .filter(account::Column::Code.eq(&category.expense_account_code))
                 .filter(account::Column::CompanyId.eq(company_id))
-                .one(db)
+                .one(&txn)
                 .await?
                 .ok_or(format!(
                     "Expense account {} not found",
@@ -388,7 +569,7 @@ impl DepreciationService {
             let depreciation_account = account::Entity::find()
                 .filter(account::Column::Code.eq(&category.depreciation_account_code))
                 .filter(account::Column::CompanyId.eq(company_id))
-                .one(db)
+                .one(&txn)
                 .await?
                 .ok_or(format!(
                     "Depreciation account {} not found",
@@ -411,7 +592,7 @@ impl DepreciationService {
                 ..Default::default()
             };
 
-            entry_line::Entity::insert(expense_line).exec(db).await?;
+            entry_line::Entity::insert(expense_line).exec(&txn).await?;
 
             // Credit accumulated depreciation account (241)
             let depreciation_line = entry_line::ActiveModel {
@@ -430,7 +611,7 @@ impl DepreciationService {
             };
 
             entry_line::Entity::insert(depreciation_line)
-                .exec(db)
+                .exec(&txn)
                 .await?;
         }
 
@@ -448,10 +629,12 @@ impl DepreciationService {
             };
 
             depreciation_journal::Entity::update(update_model)
-                .exec(db)
+                .exec(&txn)
                 .await?;
         }
 
+        txn.commit().await?;
+
         Ok(DepreciationJournalEntry {
             journal_entry_id,
             total_amount,
@@ -459,6 +642,211 @@ impl DepreciationService {
         })
     }
 
+    /// Reverse the depreciation of a period so it can be calculated again:
+    /// delete its rows, restore the asset values and cancel its journal
+    /// entries. Posted entries are cancelled with a storno entry so that
+    /// the series keeps no gaps; entries unposted since are deleted. Only
+    /// the latest calculated period of the assets can be reversed.
+    pub async fn reverse_depreciation<C: ConnectionTrait + TransactionTrait>(
+        &self,
+        db: &C,
+        company_id: i32,
+        period: NaiveDate,
+        user_id: i32,
+    ) -> Result<DepreciationReversal, Box<dyn std::error::Error + Send + Sync>> {
+        let txn = db.begin().await?;
+
+        AccountingPeriodService::ensure_open(&txn, company_id, period, Some(user_id)).await?;
+
+        let rows = depreciation_journal::Entity::find()
+            .filter(depreciation_journal::Column::CompanyId.eq(company_id))
+            .filter(depreciation_journal::Column::Period.eq(period))
+            .lock_exclusive()
+            .all(&txn)
+            .await?;
+        let Some(run_started) = rows.iter().map(|row| row.created_at).min() else {
+            return Err(format!(
+                "No depreciation calculated for {}-{:02}",
+                period.year(),
+                period.month()
+            )
+            .into());
+        };
+        let asset_ids: Vec<i32> = rows.iter().map(|row| row.fixed_asset_id).collect();
+
+        let later = depreciation_journal::Entity::find()
+            .filter(depreciation_journal::Column::FixedAssetId.is_in(asset_ids.clone()))
+            .filter(depreciation_journal::Column::Period.gt(period))
+            .order_by_desc(depreciation_journal::Column::Period)
+            .one(&txn)
+            .await?;
+        if let Some(later) = later {
+            return Err(format!(
+                "Depreciation for {}-{:02} must be reversed first",
+                later.period.year(),
+                later.period.month()
+            )
+            .into());
+        }
+
+        let events = fixed_asset_event::Entity::find()
+            .filter(fixed_asset_event::Column::FixedAssetId.is_in(asset_ids.clone()))
+            .filter(fixed_asset_event::Column::CreatedAt.gte(run_started))
+            .all(&txn)
+            .await?;
+        if let Some(event) = Self::event_booked_on_run(&rows, &events) {
+            return Err(format!(
+                "{} of asset {} on {} is booked on this depreciation",
+                event.event_type.display_name(),
+                event.fixed_asset_id,
+                event.event_date
+            )
+            .into());
+        }
+
+        let assets: HashMap<i32, fixed_asset::Model> = fixed_asset::Entity::find()
+            .filter(fixed_asset::Column::Id.is_in(asset_ids))
+            .lock_exclusive()
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|asset| (asset.id, asset))
+            .collect();
+
+        let mut accounting_amount = Decimal::ZERO;
+        let mut tax_amount = Decimal::ZERO;
+        for row in &rows {
+            accounting_amount += row.accounting_depreciation_amount;
+            tax_amount += row.tax_depreciation_amount;
+
+            let asset = assets
+                .get(&row.fixed_asset_id)
+                .ok_or_else(|| format!("Fixed asset {} not found", row.fixed_asset_id))?;
+            let restored = Self::restore_before_depreciation(asset, row);
+            let restored = fixed_asset::Entity::update(fixed_asset::ActiveModel {
+                id: Unchanged(asset.id),
+                accounting_book_value: Set(restored.accounting_book_value),
+                tax_book_value: Set(restored.tax_book_value),
+                accounting_accumulated_depreciation: Set(
+                    restored.accounting_accumulated_depreciation
+                ),
+                tax_accumulated_depreciation: Set(restored.tax_accumulated_depreciation),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await?;
+            AuditLogService::record(
+                &txn,
+                Some(user_id),
+                AuditEvent::new(AuditAction::Update, "fixed_assets", asset.id)
+                    .company(company_id)
+                    .before(asset)
+                    .after(&restored),
+            )
+            .await?;
+
+            depreciation_journal::Entity::delete_by_id(row.id)
+                .exec(&txn)
+                .await?;
+            AuditLogService::record(
+                &txn,
+                Some(user_id),
+                AuditEvent::new(AuditAction::Delete, "depreciation_journal", row.id)
+                    .company(company_id)
+                    .before(row),
+            )
+            .await?;
+        }
+
+        let mut journal_entry_ids: Vec<i32> =
+            rows.iter().filter_map(|row| row.journal_entry_id).collect();
+        journal_entry_ids.sort_unstable();
+        journal_entry_ids.dedup();
+        let mut storno_entry_ids = Vec::new();
+        for entry_id in &journal_entry_ids {
+            let Some(entry) = journal_entry::Entity::find_by_id(*entry_id)
+                .one(&txn)
+                .await?
+            else {
+                continue;
+            };
+
+            if entry.is_posted {
+                let storno = JournalStornoService::storno(&txn, entry.id, user_id).await?;
+                let snapshot = AuditLogService::journal_entry_snapshot(&txn, storno.id).await?;
+                AuditLogService::record(
+                    &txn,
+                    Some(user_id),
+                    AuditEvent::new(AuditAction::Create, "journal_entries", storno.id)
+                        .company(company_id)
+                        .after(&snapshot),
+                )
+                .await?;
+                storno_entry_ids.push(storno.id);
+                continue;
+            }
+
+            let snapshot = AuditLogService::journal_entry_snapshot(&txn, entry.id).await?;
+            entry_line::Entity::delete_many()
+                .filter(entry_line::Column::JournalEntryId.eq(entry.id))
+                .exec(&txn)
+                .await?;
+            journal_entry::Entity::delete_by_id(entry.id)
+                .exec(&txn)
+                .await?;
+            JournalNumberingService::release(&txn, &entry).await?;
+            AuditLogService::record(
+                &txn,
+                Some(user_id),
+                AuditEvent::new(AuditAction::Delete, "journal_entries", entry.id)
+                    .company(company_id)
+                    .before(&snapshot),
+            )
+            .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(DepreciationReversal {
+            journal_entry_ids,
+            storno_entry_ids,
+            accounting_amount,
+            tax_amount,
+            assets_count: rows.len() as i32,
+        })
+    }
+
+    /// Lifecycle event recorded after the depreciation of its asset was
+    /// calculated, and so booked on the depreciated values. The event date
+    /// does not matter: an event dated in the period but recorded before
+    /// the run was already taken into account by it.
+    pub fn event_booked_on_run<'a>(
+        rows: &[depreciation_journal::Model],
+        events: &'a [fixed_asset_event::Model],
+    ) -> Option<&'a fixed_asset_event::Model> {
+        events.iter().find(|event| {
+            rows.iter().any(|row| {
+                row.fixed_asset_id == event.fixed_asset_id && event.created_at > row.created_at
+            })
+        })
+    }
+
+    /// Asset values as they were before a month's depreciation
+    pub fn restore_before_depreciation(
+        asset: &fixed_asset::Model,
+        row: &depreciation_journal::Model,
+    ) -> fixed_asset::Model {
+        fixed_asset::Model {
+            accounting_book_value: row.accounting_book_value_before,
+            tax_book_value: row.tax_book_value_before,
+            accounting_accumulated_depreciation: asset.accounting_accumulated_depreciation
+                - (row.accounting_book_value_before - row.accounting_book_value_after),
+            tax_accumulated_depreciation: asset.tax_accumulated_depreciation
+                - (row.tax_book_value_before - row.tax_book_value_after),
+            ..asset.clone()
+        }
+    }
+
     /// Get depreciation summary for a company and period
     pub async fn get_depreciation_summary(
         &self,
@@ -530,19 +918,16 @@ impl DepreciationService {
 
     /// Validate that depreciation is calculated sequentially
     /// Checks if all previous periods since put-into-service date are calculated
-    async fn validate_sequential_period(
+    async fn validate_sequential_period<C: ConnectionTrait>(
         &self,
-        db: &DatabaseConnection,
+        db: &C,
         asset_id: i32,
         asset: &fixed_asset::Model,
         period: NaiveDate,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Get the put-into-service date or acquisition date as starting point
-        let start_date = asset.put_into_service_date.unwrap_or(asset.acquisition_date);
-
-        // If this is the first month of service, no previous period to check
-        let start_period = NaiveDate::from_ymd_opt(start_date.year(), start_date.month(), 1)
-            .ok_or("Invalid start date")?;
+        // The first month charged under the start convention, or after the
+        // opening balance of an imported asset, is the starting point
+        let start_period = asset.first_calculated_period();
 
         if period <= start_period {
             // This is the first or same period as service start, so it's valid
@@ -584,6 +969,99 @@ impl DepreciationService {
         Ok(())
     }
 
+    /// Project the remaining schedule of an asset from its current book values
+    pub async fn projected_schedule(
+        &self,
+        db: &DatabaseConnection,
+        asset_id: i32,
+    ) -> Result<Vec<ProjectedDepreciation>, Box<dyn std::error::Error + Send + Sync>> {
+        let asset = fixed_asset::Entity::find_by_id(asset_id)
+            .one(db)
+            .await?
+            .ok_or("Fixed asset not found")?;
+
+        let last = depreciation_journal::Entity::find()
+            .filter(depreciation_journal::Column::FixedAssetId.eq(asset_id))
+            .order_by_desc(depreciation_journal::Column::Period)
+            .one(db)
+            .await?;
+        let from = match last {
+            Some(last) => last.period + chrono::Months::new(1),
+            None => asset.first_calculated_period(),
+        };
+
+        let usages = fixed_asset_usage::Entity::find()
+            .filter(fixed_asset_usage::Column::FixedAssetId.eq(asset_id))
+            .all(db)
+            .await?;
+
+        self.project_schedule(&asset, from, &usages)
+    }
+
+    /// Every month from `from` until both books are fully depreciated.
+    /// Units-of-production assets are projected only as far as usage is entered.
+    pub fn project_schedule(
+        &self,
+        asset: &fixed_asset::Model,
+        from: NaiveDate,
+        usages: &[fixed_asset_usage::Model],
+    ) -> Result<Vec<ProjectedDepreciation>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut lines = Vec::new();
+        if !asset.is_active() {
+            return Ok(lines);
+        }
+
+        let by_usage = asset.accounting_depreciation_method == "units_of_production";
+        let mut asset = asset.clone();
+        let mut period = from.max(asset.first_calculated_period());
+        let mut used_before = usages
+            .iter()
+            .filter(|usage| usage.period < period)
+            .fold(Decimal::ZERO, |acc, usage| acc + usage.units);
+
+        loop {
+            let accounting_open = asset.accounting_book_value > asset.accounting_salvage_value
+                && (!by_usage || usages.iter().any(|usage| usage.period >= period));
+            let tax_open = asset.tax_book_value > Decimal::ZERO;
+            if !accounting_open && !tax_open {
+                break;
+            }
+            if lines.len() >= 1200 {
+                return Err(format!("The schedule of asset {} does not end", asset.name).into());
+            }
+
+            let usage = usages
+                .iter()
+                .find(|usage| usage.period == period)
+                .map(|usage| UsageToDate {
+                    units: usage.units,
+                    used_before,
+                });
+            let accounting_amount = if by_usage && usage.is_none() {
+                Decimal::ZERO
+            } else {
+                self.calculate_accounting_depreciation(&asset, period, usage)?
+            };
+            let tax_amount = self.calculate_tax_depreciation(&asset, period)?;
+
+            asset.accounting_book_value -= accounting_amount;
+            asset.tax_book_value -= tax_amount;
+            used_before += usage.map_or(Decimal::ZERO, |usage| usage.units);
+
+            lines.push(ProjectedDepreciation {
+                period,
+                accounting_amount,
+                accounting_book_value: asset.accounting_book_value,
+                tax_amount,
+                tax_book_value: asset.tax_book_value,
+                temporary_difference: asset.accounting_book_value - asset.tax_book_value,
+            });
+            period = period + chrono::Months::new(1);
+        }
+
+        Ok(lines)
+    }
+
     /// Get calculated periods for a fixed asset
     pub async fn get_calculated_periods(
         &self,
@@ -627,3 +1105,252 @@ impl DepreciationService {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn asset(method: &str, convention: &str, put_into_service: NaiveDate) -> fixed_asset::Model {
+        fixed_asset::Model {
+            accounting_depreciation_method: method.to_string(),
+            depreciation_start_convention: convention.to_string(),
+            accounting_salvage_value: dec("500"),
+            ..fixed_asset::Model::fixture(1, dec("10000"), put_into_service)
+        }
+    }
+
+    /// Depreciate month by month until nothing is left; returns the charged amounts
+    fn schedule(mut asset: fixed_asset::Model, usage: &[&str]) -> Vec<Decimal> {
+        let service = DepreciationService::new();
+        let mut period = asset.first_depreciation_period();
+        let mut used_before = Decimal::ZERO;
+        let mut amounts = Vec::new();
+
+        while asset.accounting_book_value > asset.accounting_salvage_value {
+            assert!(amounts.len() < 600, "schedule does not end");
+            let usage = usage.get(amounts.len()).map(|units| UsageToDate {
+                units: dec(units),
+                used_before,
+            });
+            let amount = service
+                .calculate_accounting_depreciation(&asset, period, usage)
+                .unwrap();
+            asset.accounting_book_value -= amount;
+            used_before += usage.map_or(Decimal::ZERO, |usage| usage.units);
+            amounts.push(amount);
+            period = period + chrono::Months::new(1);
+        }
+
+        assert_eq!(asset.accounting_book_value, asset.accounting_salvage_value);
+        assert_eq!(
+            amounts.iter().copied().sum::<Decimal>(),
+            asset.acquisition_cost - asset.accounting_salvage_value
+        );
+        amounts
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn straight_line_schedules_follow_the_start_convention() {
+        // 9 500 over 60 months is 158.33(3) a month; the cents land on every third month
+        let next_month = asset("straight_line", "next_month", date(2025, 3, 16));
+        assert_eq!(next_month.first_depreciation_period(), date(2025, 4, 1));
+        let amounts = schedule(next_month, &[]);
+        assert_eq!(amounts.len(), 60);
+        assert_eq!(amounts[..3], [dec("158.33"), dec("158.34"), dec("158.33")]);
+
+        let full_month = asset("straight_line", "full_month", date(2025, 3, 16));
+        assert_eq!(full_month.first_depreciation_period(), date(2025, 3, 1));
+        assert_eq!(schedule(full_month, &[]).len(), 60);
+
+        // 16 of 31 days in March, the other 15/31 of a month at the end
+        let daily = schedule(asset("straight_line", "daily", date(2025, 3, 16)), &[]);
+        assert_eq!(daily.len(), 61);
+        assert_eq!(daily[0], dec("81.72"));
+        assert_eq!(daily[60], dec("76.61"));
+    }
+
+    #[test]
+    fn sum_of_years_digits_charges_each_year_its_digits() {
+        let amounts = schedule(
+            asset("sum_of_years_digits", "next_month", date(2025, 1, 10)),
+            &[],
+        );
+        assert_eq!(amounts.len(), 60);
+        // Year 1 takes 5/15 of 9 500, year 5 takes 1/15
+        assert_eq!(amounts[0], dec("263.89"));
+        assert_eq!(
+            amounts[..12].iter().copied().sum::<Decimal>(),
+            dec("3166.67")
+        );
+        assert_eq!(
+            amounts[48..].iter().copied().sum::<Decimal>(),
+            dec("633.33")
+        );
+
+        // A useful life of 30 months ends with half a year
+        let mut short = asset("sum_of_years_digits", "daily", date(2025, 1, 10));
+        short.accounting_useful_life = 30;
+        assert_eq!(schedule(short, &[]).len(), 31);
+    }
+
+    #[test]
+    fn units_of_production_stops_at_the_capacity() {
+        let mut press = asset("units_of_production", "full_month", date(2025, 1, 10));
+        press.production_capacity = Some(dec("1000"));
+        let amounts = schedule(press, &["120", "0", "300", "250", "400"]);
+        assert_eq!(
+            amounts,
+            [
+                dec("1140"),
+                Decimal::ZERO,
+                dec("2850"),
+                dec("2375"),
+                dec("3135")
+            ]
+        );
+    }
+
+    #[test]
+    fn projected_schedule_runs_both_books_to_the_end() {
+        let service = DepreciationService::new();
+        let machine = asset("straight_line", "next_month", date(2025, 3, 16));
+        let lines = service
+            .project_schedule(&machine, date(2025, 1, 1), &[])
+            .unwrap();
+
+        // Tax ends after 40 months, accounting after 60
+        assert_eq!(lines.len(), 60);
+        assert_eq!(lines[0].period, date(2025, 4, 1));
+        assert_eq!(lines[39].tax_book_value, Decimal::ZERO);
+        assert_eq!(lines[39].temporary_difference, dec("3666.67"));
+        let last = lines.last().unwrap();
+        assert_eq!(last.period, date(2030, 3, 1));
+        assert_eq!(last.accounting_book_value, dec("500"));
+        assert_eq!(last.temporary_difference, dec("500"));
+    }
+
+    #[test]
+    fn tax_depreciation_ends_at_zero() {
+        let service = DepreciationService::new();
+        let mut machine = asset("straight_line", "next_month", date(2025, 3, 16));
+        assert!(service
+            .calculate_tax_depreciation(&machine, date(2025, 3, 1))
+            .unwrap()
+            .is_zero());
+
+        let mut period = machine.first_depreciation_period();
+        let mut months = 0;
+        while machine.tax_book_value > Decimal::ZERO {
+            machine.tax_book_value -= service
+                .calculate_tax_depreciation(&machine, period)
+                .unwrap();
+            period = period + chrono::Months::new(1);
+            months += 1;
+        }
+        // 250 a month at 30%, the 40th month takes the last 250
+        assert_eq!(months, 40);
+        assert_eq!(machine.tax_book_value, Decimal::ZERO);
+    }
+
+    fn depreciation_row(
+        fixed_asset_id: i32,
+        created_at: chrono::DateTime<Utc>,
+    ) -> depreciation_journal::Model {
+        depreciation_journal::Model {
+            id: fixed_asset_id,
+            fixed_asset_id,
+            period: date(2025, 6, 1),
+            company_id: 1,
+            accounting_depreciation_amount: dec("158.33"),
+            accounting_book_value_before: dec("9050.00"),
+            accounting_book_value_after: dec("8891.67"),
+            tax_depreciation_amount: dec("250"),
+            tax_book_value_before: dec("8750"),
+            tax_book_value_after: dec("8500"),
+            journal_entry_id: Some(7),
+            is_posted: true,
+            posted_at: None,
+            posted_by: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    fn event(
+        fixed_asset_id: i32,
+        event_date: NaiveDate,
+        created_at: chrono::DateTime<Utc>,
+    ) -> fixed_asset_event::Model {
+        fixed_asset_event::Model {
+            id: 1,
+            company_id: 1,
+            fixed_asset_id,
+            event_type: fixed_asset_event::FixedAssetEventType::Sale,
+            event_date,
+            portion: Decimal::ONE,
+            amount: dec("9000"),
+            acquisition_cost_before: dec("10000"),
+            acquisition_cost_after: Decimal::ZERO,
+            accounting_book_value_before: dec("8891.67"),
+            accounting_book_value_after: Decimal::ZERO,
+            tax_book_value_before: dec("8500"),
+            tax_book_value_after: Decimal::ZERO,
+            tax_depreciable_value_before: dec("10000"),
+            tax_depreciable_value_after: Decimal::ZERO,
+            gain_loss: dec("108.33"),
+            remaining_life_months: None,
+            journal_entry_id: None,
+            description: None,
+            created_by: None,
+            created_at,
+        }
+    }
+
+    #[test]
+    fn only_events_recorded_after_the_run_block_its_reversal() {
+        let run = Utc::now();
+        let after_run = run + chrono::Duration::minutes(5);
+        let rows = [depreciation_row(1, run)];
+
+        // Dated in the period, but recorded before the run took its values
+        let earlier = [event(1, date(2025, 6, 20), run - chrono::Duration::days(1))];
+        assert!(DepreciationService::event_booked_on_run(&rows, &earlier).is_none());
+
+        // Dated before the period, but booked on the depreciated values
+        let later = [event(1, date(2025, 5, 31), after_run)];
+        assert_eq!(
+            DepreciationService::event_booked_on_run(&rows, &later).map(|event| event.id),
+            Some(1)
+        );
+
+        // Another asset's event has nothing to do with the run
+        let other = [event(2, date(2025, 6, 20), after_run)];
+        assert!(DepreciationService::event_booked_on_run(&rows, &other).is_none());
+    }
+
+    #[test]
+    fn reversal_restores_the_values_before_the_depreciation() {
+        let mut machine = asset("straight_line", "next_month", date(2025, 3, 16));
+        machine.accounting_book_value = dec("8891.67");
+        machine.accounting_accumulated_depreciation = dec("1108.33");
+        machine.tax_book_value = dec("8500");
+        machine.tax_accumulated_depreciation = dec("1500");
+
+        let row = depreciation_row(1, Utc::now());
+        let restored = DepreciationService::restore_before_depreciation(&machine, &row);
+        assert_eq!(restored.accounting_book_value, dec("9050.00"));
+        assert_eq!(restored.accounting_accumulated_depreciation, dec("950.00"));
+        assert_eq!(restored.tax_book_value, dec("8750"));
+        assert_eq!(restored.tax_accumulated_depreciation, dec("1250"));
+        assert_eq!(restored.acquisition_cost, machine.acquisition_cost);
+    }
+}
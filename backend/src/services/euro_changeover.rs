@@ -0,0 +1,519 @@
+//! Euro Changeover Service
+//!
+//! Converts a company's books from BGN to EUR at the irrevocably fixed rate of
+//! 1.95583 BGN per euro. Balances at the cut-off date are converted per account
+//! and counterpart and rounded to the cent (half away from zero). One posted
+//! conversion entry, dated the day after the cut-off, restates them in euro and
+//! books the rounding difference to a chosen account. Entries up to the cut-off
+//! are left untouched and keep being reported in BGN.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use sea_orm::*;
+use std::collections::BTreeMap;
+
+use crate::entities::euro_changeover::EuroChangeoverInput;
+use crate::entities::{
+    account, company, currency, entry_line, euro_changeover, journal_entry, JournalSeriesKind,
+};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::journal_numbering::JournalNumberingService;
+
+/// Base currency of companies that have not changed over
+pub const LEGACY_BASE_CURRENCY: &str = "BGN";
+pub const EURO: &str = "EUR";
+
+/// Fixed conversion rate, BGN per 1 EUR
+pub fn fixed_rate() -> Decimal {
+    Decimal::new(195583, 5)
+}
+
+pub struct EuroChangeoverService;
+
+/// Balance of one account/counterpart pair at the cut-off date
+#[derive(Debug, Clone)]
+pub struct ConvertedBalance {
+    pub account_id: i32,
+    pub account_code: String,
+    pub counterpart_id: Option<i32>,
+    /// Debit-positive balance in BGN
+    pub balance_bgn: Decimal,
+    /// Debit-positive balance in EUR
+    pub balance_eur: Decimal,
+}
+
+impl ConvertedBalance {
+    /// Amount the conversion entry books on the account, debit-positive
+    pub fn adjustment(&self) -> Decimal {
+        self.balance_eur - self.balance_bgn
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangeoverPlan {
+    pub balances: Vec<ConvertedBalance>,
+    /// Amount booked on the rounding account, debit-positive
+    pub rounding_difference: Decimal,
+}
+
+impl EuroChangeoverService {
+    /// Convert a BGN amount to EUR, rounded to the cent
+    pub fn convert_to_euro(amount_bgn: Decimal) -> Decimal {
+        (amount_bgn / fixed_rate())
+            .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+    }
+
+    /// Convert a EUR amount to BGN, rounded to the stotinka
+    pub fn convert_from_euro(amount_eur: Decimal) -> Decimal {
+        (amount_eur * fixed_rate())
+            .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+    }
+
+    /// Units of `to` per unit of `from` when the pair is pegged (BGN/EUR)
+    pub fn fixed_conversion_rate(from: &str, to: &str) -> Option<Decimal> {
+        match (from, to) {
+            (EURO, LEGACY_BASE_CURRENCY) => Some(fixed_rate()),
+            (LEGACY_BASE_CURRENCY, EURO) => Some(Decimal::ONE / fixed_rate()),
+            _ => None,
+        }
+    }
+
+    /// Convert an amount between BGN and EUR at the fixed rate, rounded to the cent
+    pub fn convert_fixed(amount: Decimal, from: &str, to: &str) -> Option<Decimal> {
+        match (from, to) {
+            _ if from == to => Some(amount),
+            (LEGACY_BASE_CURRENCY, EURO) => Some(Self::convert_to_euro(amount)),
+            (EURO, LEGACY_BASE_CURRENCY) => Some(Self::convert_from_euro(amount)),
+            _ => None,
+        }
+    }
+
+    /// Currency shown alongside the base currency during the dual display period
+    pub fn dual_display_currency(base_currency: &str) -> Option<&'static str> {
+        match base_currency {
+            LEGACY_BASE_CURRENCY => Some(EURO),
+            EURO => Some(LEGACY_BASE_CURRENCY),
+            _ => None,
+        }
+    }
+
+    /// Amount in the dual display currency, converted at the fixed rate
+    pub fn dual_display_amount(amount: Decimal, base_currency: &str) -> Option<Decimal> {
+        match base_currency {
+            LEGACY_BASE_CURRENCY => Some(Self::convert_to_euro(amount)),
+            EURO => Some(Self::convert_from_euro(amount)),
+            _ => None,
+        }
+    }
+
+    /// Convert cut-off balances given as (account id, account code, counterpart, BGN balance)
+    pub fn plan(
+        balances: impl IntoIterator<Item = (i32, String, Option<i32>, Decimal)>,
+    ) -> ChangeoverPlan {
+        let balances: Vec<ConvertedBalance> = balances
+            .into_iter()
+            .filter(|(_, _, _, balance)| !balance.is_zero())
+            .map(
+                |(account_id, account_code, counterpart_id, balance_bgn)| ConvertedBalance {
+                    account_id,
+                    account_code,
+                    counterpart_id,
+                    balance_bgn,
+                    balance_eur: Self::convert_to_euro(balance_bgn),
+                },
+            )
+            .collect();
+
+        // The BGN balances net to zero, so whatever the converted balances do
+        // not net to is the rounding difference
+        let total_adjustment: Decimal = balances.iter().map(|b| b.adjustment()).sum();
+
+        ChangeoverPlan {
+            balances,
+            rounding_difference: -total_adjustment,
+        }
+    }
+
+    /// Calculate the conversion without booking anything
+    pub async fn preview<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        cutoff_date: NaiveDate,
+    ) -> Result<ChangeoverPlan> {
+        let balances = Self::load_balances(db, company_id, cutoff_date).await?;
+        Ok(Self::plan(balances))
+    }
+
+    /// Book the conversion entry and switch the company's base currency to EUR
+    pub async fn execute(
+        db: &DatabaseConnection,
+        input: &EuroChangeoverInput,
+        created_by: i32,
+    ) -> Result<euro_changeover::Model> {
+        let txn = db.begin().await?;
+
+        let company = company::Entity::find_by_id(input.company_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow!("Company not found"))?;
+
+        let existing = euro_changeover::Entity::find()
+            .filter(euro_changeover::Column::CompanyId.eq(company.id))
+            .one(&txn)
+            .await?;
+        if let Some(existing) = existing {
+            bail!(
+                "Company was already converted to EUR with cut-off date {}",
+                existing.cutoff_date
+            );
+        }
+
+        let previous_currency =
+            Self::base_currency_code(&txn, company.id, input.cutoff_date).await?;
+        if previous_currency == EURO {
+            bail!("Company already keeps its books in EUR");
+        }
+        if previous_currency != LEGACY_BASE_CURRENCY {
+            bail!(
+                "Euro changeover converts from BGN, company base currency is {}",
+                previous_currency
+            );
+        }
+
+        let euro = currency::Entity::find()
+            .filter(currency::Column::Code.eq(EURO))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow!("EUR currency not found"))?;
+
+        let rounding_account = account::Entity::find_by_id(input.rounding_account_id)
+            .filter(account::Column::CompanyId.eq(company.id))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow!("Rounding account not found in this company"))?;
+
+        let drafts = journal_entry::Entity::find()
+            .filter(journal_entry::Column::CompanyId.eq(company.id))
+            .filter(journal_entry::Column::IsPosted.eq(false))
+            .filter(journal_entry::Column::DocumentDate.lte(input.cutoff_date))
+            .count(&txn)
+            .await?;
+        if drafts > 0 {
+            bail!(
+                "{} unposted journal entries are dated on or before the cut-off date; post or delete them first",
+                drafts
+            );
+        }
+
+        let later_entries = journal_entry::Entity::find()
+            .filter(journal_entry::Column::CompanyId.eq(company.id))
+            .filter(journal_entry::Column::DocumentDate.gt(input.cutoff_date))
+            .count(&txn)
+            .await?;
+        if later_entries > 0 {
+            bail!(
+                "{} journal entries are dated after the cut-off date; they must be booked in EUR after the changeover",
+                later_entries
+            );
+        }
+
+        let changeover_date = input
+            .cutoff_date
+            .succ_opt()
+            .ok_or_else(|| anyhow!("Invalid cut-off date"))?;
+        AccountingPeriodService::ensure_open(&txn, company.id, changeover_date, Some(created_by))
+            .await?;
+
+        let plan = Self::plan(Self::load_balances(&txn, company.id, input.cutoff_date).await?);
+
+        let mut lines: Vec<(i32, Option<i32>, Decimal, String)> = plan
+            .balances
+            .iter()
+            .filter(|balance| !balance.adjustment().is_zero())
+            .map(|balance| {
+                (
+                    balance.account_id,
+                    balance.counterpart_id,
+                    balance.adjustment(),
+                    format!(
+                        "Преизчисляване {} BGN -> {} EUR",
+                        balance.balance_bgn, balance.balance_eur
+                    ),
+                )
+            })
+            .collect();
+        if !plan.rounding_difference.is_zero() {
+            lines.push((
+                rounding_account.id,
+                None,
+                plan.rounding_difference,
+                "Разлики от закръгляване при преминаване към евро".to_string(),
+            ));
+        }
+
+        let journal_entry_id = if lines.is_empty() {
+            None
+        } else {
+            let total_amount: Decimal = lines
+                .iter()
+                .map(|(_, _, amount, _)| (*amount).max(Decimal::ZERO))
+                .sum();
+            let number = JournalNumberingService::allocate(
+                &txn,
+                company.id,
+                JournalSeriesKind::Closing,
+                changeover_date,
+            )
+            .await?;
+
+            let entry = journal_entry::ActiveModel {
+                entry_number: Set(number.entry_number),
+                document_date: Set(changeover_date),
+                vat_date: Set(None),
+                accounting_date: Set(changeover_date),
+                document_number: Set(None),
+                description: Set(format!(
+                    "Преминаване към евро по фиксиран курс {} BGN/EUR",
+                    fixed_rate()
+                )),
+                total_amount: Set(total_amount),
+                total_vat_amount: Set(Decimal::ZERO),
+                is_posted: Set(true),
+                posted_by: Set(Some(created_by)),
+                posted_at: Set(Some(Utc::now())),
+                created_by: Set(created_by),
+                company_id: Set(company.id),
+                created_at: Set(Utc::now()),
+                updated_at: Set(Utc::now()),
+                vat_document_type: Set(None),
+                vat_purchase_operation: Set(None),
+                vat_sales_operation: Set(None),
+                vat_additional_operation: Set(None),
+                vat_additional_data: Set(None),
+                series_id: Set(Some(number.series_id)),
+                sequence_number: Set(Some(number.sequence_number)),
+                ..Default::default()
+            };
+            let entry = journal_entry::Entity::insert(entry)
+                .exec_with_returning(&txn)
+                .await?;
+
+            let line_models = lines.into_iter().enumerate().map(
+                |(idx, (account_id, counterpart_id, amount, description))| {
+                    entry_line::ActiveModel {
+                        journal_entry_id: Set(entry.id),
+                        account_id: Set(account_id),
+                        debit_amount: Set(amount.max(Decimal::ZERO)),
+                        credit_amount: Set((-amount).max(Decimal::ZERO)),
+                        counterpart_id: Set(counterpart_id),
+                        currency_code: Set(Some(EURO.to_string())),
+                        currency_amount: Set(Some(amount.abs())),
+                        exchange_rate: Set(Some(Decimal::ONE)),
+                        base_amount: Set(amount.abs()),
+                        vat_amount: Set(Decimal::ZERO),
+                        vat_rate_id: Set(None),
+                        quantity: Set(None),
+                        unit_of_measure_code: Set(None),
+                        description: Set(Some(description)),
+                        line_order: Set(idx as i32 + 1),
+                        created_at: Set(Utc::now()),
+                        ..Default::default()
+                    }
+                },
+            );
+            entry_line::Entity::insert_many(line_models)
+                .exec(&txn)
+                .await?;
+
+            Some(entry.id)
+        };
+
+        let changeover = euro_changeover::ActiveModel {
+            company_id: Set(company.id),
+            cutoff_date: Set(input.cutoff_date),
+            previous_currency_code: Set(previous_currency),
+            exchange_rate: Set(fixed_rate()),
+            rounding_account_id: Set(rounding_account.id),
+            rounding_difference: Set(plan.rounding_difference),
+            journal_entry_id: Set(journal_entry_id),
+            created_by: Set(Some(created_by)),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let changeover = euro_changeover::Entity::insert(changeover)
+            .exec_with_returning(&txn)
+            .await?;
+
+        let mut company_model: company::ActiveModel = company.into();
+        company_model.base_currency_id = Set(Some(euro.id));
+        company_model.updated_at = Set(Utc::now());
+        company_model.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(changeover)
+    }
+
+    /// ISO code of the currency the company's books are kept in on `date`.
+    /// Dates up to a euro changeover cut-off stay in the previous currency.
+    pub async fn base_currency_code<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        date: NaiveDate,
+    ) -> Result<String> {
+        if let Some(changeover) = euro_changeover::Entity::find()
+            .filter(euro_changeover::Column::CompanyId.eq(company_id))
+            .one(db)
+            .await?
+        {
+            if date <= changeover.cutoff_date {
+                return Ok(changeover.previous_currency_code);
+            }
+        }
+
+        let base_currency = company::Entity::find_by_id(company_id)
+            .find_also_related(currency::Entity)
+            .one(db)
+            .await?
+            .and_then(|(_, currency)| currency);
+
+        Ok(base_currency
+            .map(|currency| currency.code)
+            .unwrap_or_else(|| LEGACY_BASE_CURRENCY.to_string()))
+    }
+
+    /// Posted balances per account and counterpart up to the cut-off date
+    async fn load_balances<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        cutoff_date: NaiveDate,
+    ) -> Result<Vec<(i32, String, Option<i32>, Decimal)>> {
+        let lines = entry_line::Entity::find()
+            .find_also_related(journal_entry::Entity)
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .filter(journal_entry::Column::IsPosted.eq(true))
+            .filter(journal_entry::Column::DocumentDate.lte(cutoff_date))
+            .all(db)
+            .await?;
+
+        let mut balances: BTreeMap<(i32, Option<i32>), Decimal> = BTreeMap::new();
+        for (line, _) in lines {
+            *balances
+                .entry((line.account_id, line.counterpart_id))
+                .or_default() += line.debit_amount - line.credit_amount;
+        }
+
+        let account_codes: BTreeMap<i32, String> = account::Entity::find()
+            .filter(account::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|account| (account.id, account.code))
+            .collect();
+
+        let mut result: Vec<_> = balances
+            .into_iter()
+            .map(|((account_id, counterpart_id), balance)| {
+                let code = account_codes.get(&account_id).cloned().unwrap_or_default();
+                (account_id, code, counterpart_id, balance)
+            })
+            .collect();
+        result.sort_by(|a, b| a.1.cmp(&b.1).then(a.2.cmp(&b.2)));
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn converts_at_fixed_rate_rounding_half_away_from_zero() {
+        assert_eq!(
+            EuroChangeoverService::convert_to_euro(dec("195.583")),
+            dec("100.00")
+        );
+        assert_eq!(
+            EuroChangeoverService::convert_to_euro(dec("1.00")),
+            dec("0.51")
+        );
+        // 0.01 BGN = 0.0051129... EUR
+        assert_eq!(
+            EuroChangeoverService::convert_to_euro(dec("0.01")),
+            dec("0.01")
+        );
+        assert_eq!(
+            EuroChangeoverService::convert_to_euro(dec("-0.01")),
+            dec("-0.01")
+        );
+    }
+
+    #[test]
+    fn dual_display_converts_both_ways() {
+        assert_eq!(
+            EuroChangeoverService::dual_display_amount(dec("100.00"), "EUR"),
+            Some(dec("195.58"))
+        );
+        assert_eq!(
+            EuroChangeoverService::dual_display_amount(dec("195.58"), "BGN"),
+            Some(dec("100.00"))
+        );
+        assert_eq!(
+            EuroChangeoverService::dual_display_amount(dec("100.00"), "USD"),
+            None
+        );
+    }
+
+    #[test]
+    fn fixed_conversion_covers_only_the_bgn_euro_peg() {
+        assert_eq!(
+            EuroChangeoverService::convert_fixed(dec("19.56"), "BGN", "EUR"),
+            Some(dec("10.00"))
+        );
+        assert_eq!(
+            EuroChangeoverService::convert_fixed(dec("12.34"), "EUR", "EUR"),
+            Some(dec("12.34"))
+        );
+        assert_eq!(
+            EuroChangeoverService::convert_fixed(dec("10.00"), "BGN", "USD"),
+            None
+        );
+        assert_eq!(
+            EuroChangeoverService::fixed_conversion_rate("EUR", "BGN"),
+            Some(fixed_rate())
+        );
+        assert_eq!(
+            EuroChangeoverService::fixed_conversion_rate("USD", "BGN"),
+            None
+        );
+    }
+
+    #[test]
+    fn rounding_difference_balances_the_conversion_entry() {
+        let plan = EuroChangeoverService::plan(vec![
+            (1, "501".to_string(), None, dec("0.01")),
+            (2, "503".to_string(), None, dec("0.01")),
+            (3, "411".to_string(), Some(7), dec("0.01")),
+            (4, "101".to_string(), None, dec("-0.03")),
+            (5, "602".to_string(), None, Decimal::ZERO),
+        ]);
+
+        assert_eq!(plan.balances.len(), 4);
+        // 3 x 0.01 EUR against 0.02 EUR leaves one cent to write off
+        assert_eq!(plan.rounding_difference, dec("-0.01"));
+
+        let entry_total: Decimal = plan
+            .balances
+            .iter()
+            .map(|b| b.adjustment())
+            .sum::<Decimal>()
+            + plan.rounding_difference;
+        assert!(entry_total.is_zero());
+    }
+}
@@ -0,0 +1,920 @@
+//! Fixed Asset Register Import
+//!
+//! Loads the fixed asset register of a new client from a CSV or XLSX file.
+//! A column mapping says which columns hold the asset fields; columns are
+//! given by their 1-based number or by their header name, as in the bank
+//! CSV templates.
+//!
+//! Assets taken over from a previous system carry their accumulated
+//! accounting and tax depreciation up to the opening balance date. Their
+//! depreciation is calculated here from the month after that date.
+//!
+//! Every row is validated before anything is written. A file with any
+//! invalid row imports nothing and returns the errors per row.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use chrono::NaiveDate;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::str::FromStr;
+
+use crate::entities::fixed_asset::{self, DEPRECIATION_METHODS, START_CONVENTIONS};
+use crate::entities::fixed_asset_category;
+use crate::services::bank_csv_mapping::CsvColumn;
+use crate::services::bank_imports::BankImportService;
+
+pub struct FixedAssetImportService;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixedAssetImportMapping {
+    /// CSV delimiter, `;` by default; `\t` or `tab` for tab-separated files
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+    /// CSV encoding label; UTF-8 with a Windows-1251 fallback when missing
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// XLSX sheet name; the first sheet when missing
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// chrono format (`%d.%m.%Y`) or `DD.MM.YYYY` style for dates written as text
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Decimal separator of numbers written as text
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    /// Rows before the header row, or before the data without one
+    #[serde(default)]
+    pub skip_rows: usize,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    pub inventory_number_column: CsvColumn,
+    pub name_column: CsvColumn,
+    /// Category code or name
+    pub category_column: CsvColumn,
+    pub acquisition_cost_column: CsvColumn,
+    pub acquisition_date_column: CsvColumn,
+    #[serde(default)]
+    pub put_into_service_date_column: Option<CsvColumn>,
+    /// Useful life in months; derived from the rate when missing
+    #[serde(default)]
+    pub accounting_useful_life_column: Option<CsvColumn>,
+    /// Annual rate in percent; derived from the useful life or taken from the
+    /// category when missing
+    #[serde(default)]
+    pub accounting_depreciation_rate_column: Option<CsvColumn>,
+    /// straight_line when missing
+    #[serde(default)]
+    pub depreciation_method_column: Option<CsvColumn>,
+    /// next_month when missing
+    #[serde(default)]
+    pub depreciation_start_convention_column: Option<CsvColumn>,
+    /// Remaining capacity at the opening balance date for units-of-production
+    /// assets taken over with depreciation
+    #[serde(default)]
+    pub production_capacity_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub salvage_value_column: Option<CsvColumn>,
+    /// Annual rate in percent; the category's ЗКПО maximum when missing
+    #[serde(default)]
+    pub tax_depreciation_rate_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub accounting_accumulated_depreciation_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub tax_accumulated_depreciation_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub description_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub location_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub responsible_person_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub serial_number_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub manufacturer_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub model_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub notes_column: Option<CsvColumn>,
+}
+
+fn default_delimiter() -> String {
+    ";".to_string()
+}
+
+fn default_date_format() -> String {
+    "%d.%m.%Y".to_string()
+}
+
+fn default_decimal_separator() -> String {
+    ",".to_string()
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+/// Cell of the file; XLSX keeps numbers and dates typed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportCell {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+}
+
+/// Rows of the file with their 1-based line or row numbers
+#[derive(Debug, Clone, Default)]
+pub struct ImportTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<(i32, Vec<ImportCell>)>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct FixedAssetImportRow {
+    /// 1-based line of the CSV file or row of the sheet
+    pub line_number: i32,
+    pub inventory_number: Option<String>,
+    pub name: Option<String>,
+    pub category_code: Option<String>,
+    pub acquisition_cost: Option<Decimal>,
+    pub acquisition_date: Option<NaiveDate>,
+    pub accounting_useful_life: Option<i32>,
+    pub accounting_depreciation_rate: Option<Decimal>,
+    pub tax_depreciation_rate: Option<Decimal>,
+    pub accounting_book_value: Option<Decimal>,
+    pub tax_book_value: Option<Decimal>,
+    /// Why the row cannot be imported
+    pub errors: Vec<String>,
+    #[graphql(skip)]
+    pub asset: Option<fixed_asset::ActiveModel>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct FixedAssetImportPreview {
+    pub headers: Vec<String>,
+    pub rows: Vec<FixedAssetImportRow>,
+    pub valid_count: i32,
+    pub error_count: i32,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct FixedAssetImportResult {
+    /// Created assets; empty when any row has errors
+    pub imported: Vec<fixed_asset::Model>,
+    pub preview: FixedAssetImportPreview,
+}
+
+/// Column positions of a mapping resolved against the header row
+struct ResolvedColumns {
+    inventory_number: usize,
+    name: usize,
+    category: usize,
+    acquisition_cost: usize,
+    acquisition_date: usize,
+    put_into_service_date: Option<usize>,
+    accounting_useful_life: Option<usize>,
+    accounting_depreciation_rate: Option<usize>,
+    depreciation_method: Option<usize>,
+    depreciation_start_convention: Option<usize>,
+    production_capacity: Option<usize>,
+    salvage_value: Option<usize>,
+    tax_depreciation_rate: Option<usize>,
+    accounting_accumulated_depreciation: Option<usize>,
+    tax_accumulated_depreciation: Option<usize>,
+    description: Option<usize>,
+    location: Option<usize>,
+    responsible_person: Option<usize>,
+    serial_number: Option<usize>,
+    manufacturer: Option<usize>,
+    model: Option<usize>,
+    notes: Option<usize>,
+}
+
+impl FixedAssetImportMapping {
+    pub fn from_value(template: &Value) -> Result<Self> {
+        serde_json::from_value(template.clone())
+            .map_err(|err| anyhow!("Invalid fixed asset import mapping: {}", err))
+    }
+
+    fn resolve_columns(&self, headers: &[String]) -> Result<ResolvedColumns> {
+        let resolve = |column: &CsvColumn| -> Result<usize> {
+            match column {
+                CsvColumn::Number(0) => bail!("Column numbers start at 1"),
+                CsvColumn::Number(number) => Ok(number - 1),
+                CsvColumn::Header(name) => {
+                    if headers.is_empty() {
+                        bail!("Column \"{}\" needs a header row", name);
+                    }
+                    let wanted = name.trim().to_lowercase();
+                    headers
+                        .iter()
+                        .position(|header| header.trim().to_lowercase() == wanted)
+                        .ok_or_else(|| anyhow!("No column named \"{}\" in the header", name))
+                }
+            }
+        };
+        let resolve_optional =
+            |column: &Option<CsvColumn>| column.as_ref().map(resolve).transpose();
+
+        Ok(ResolvedColumns {
+            inventory_number: resolve(&self.inventory_number_column)?,
+            name: resolve(&self.name_column)?,
+            category: resolve(&self.category_column)?,
+            acquisition_cost: resolve(&self.acquisition_cost_column)?,
+            acquisition_date: resolve(&self.acquisition_date_column)?,
+            put_into_service_date: resolve_optional(&self.put_into_service_date_column)?,
+            accounting_useful_life: resolve_optional(&self.accounting_useful_life_column)?,
+            accounting_depreciation_rate: resolve_optional(
+                &self.accounting_depreciation_rate_column,
+            )?,
+            depreciation_method: resolve_optional(&self.depreciation_method_column)?,
+            depreciation_start_convention: resolve_optional(
+                &self.depreciation_start_convention_column,
+            )?,
+            production_capacity: resolve_optional(&self.production_capacity_column)?,
+            salvage_value: resolve_optional(&self.salvage_value_column)?,
+            tax_depreciation_rate: resolve_optional(&self.tax_depreciation_rate_column)?,
+            accounting_accumulated_depreciation: resolve_optional(
+                &self.accounting_accumulated_depreciation_column,
+            )?,
+            tax_accumulated_depreciation: resolve_optional(
+                &self.tax_accumulated_depreciation_column,
+            )?,
+            description: resolve_optional(&self.description_column)?,
+            location: resolve_optional(&self.location_column)?,
+            responsible_person: resolve_optional(&self.responsible_person_column)?,
+            serial_number: resolve_optional(&self.serial_number_column)?,
+            manufacturer: resolve_optional(&self.manufacturer_column)?,
+            model: resolve_optional(&self.model_column)?,
+            notes: resolve_optional(&self.notes_column)?,
+        })
+    }
+
+    fn delimiter_char(&self) -> char {
+        match self.delimiter.as_str() {
+            "\\t" | "tab" | "TAB" => '\t',
+            delimiter => delimiter.chars().next().unwrap_or(';'),
+        }
+    }
+
+    fn parse_date(&self, cell: &ImportCell) -> Result<NaiveDate> {
+        let value = match cell {
+            ImportCell::Date(date) => return Ok(*date),
+            ImportCell::Number(number) => bail!("{} is not a date", number),
+            ImportCell::Text(value) => value,
+        };
+        let format = if self.date_format.contains('%') {
+            self.date_format.clone()
+        } else {
+            self.date_format
+                .replace("YYYY", "%Y")
+                .replace("YY", "%y")
+                .replace("MM", "%m")
+                .replace("DD", "%d")
+        };
+        NaiveDate::parse_from_str(value, &format)
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+            .map_err(|_| anyhow!("Date \"{}\" does not match {}", value, self.date_format))
+    }
+
+    fn parse_decimal(&self, cell: &ImportCell) -> Result<Decimal> {
+        let value = match cell {
+            ImportCell::Number(number) => {
+                return Decimal::from_f64(*number)
+                    .map(|value| value.round_dp(6).normalize())
+                    .ok_or_else(|| anyhow!("Invalid number {}", number));
+            }
+            ImportCell::Date(date) => bail!("{} is not a number", date),
+            ImportCell::Text(value) => value,
+        };
+        let mut normalized: String = value
+            .chars()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+'))
+            .collect();
+        if self.decimal_separator == "," {
+            normalized = normalized.replace('.', "").replace(',', ".");
+        } else {
+            normalized = normalized.replace(',', "");
+        }
+        Decimal::from_str(&normalized).map_err(|_| anyhow!("Invalid number \"{}\"", value))
+    }
+}
+
+impl ImportCell {
+    fn text(&self) -> String {
+        match self {
+            ImportCell::Text(value) => value.clone(),
+            ImportCell::Number(number) => number.to_string(),
+            ImportCell::Date(date) => date.format("%d.%m.%Y").to_string(),
+        }
+    }
+}
+
+impl FixedAssetImportService {
+    /// Validate the file and, when `commit` is set and every row is valid,
+    /// create the assets in one transaction
+    pub async fn import<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        file_name: &str,
+        content: &[u8],
+        mapping: &FixedAssetImportMapping,
+        opening_balance_date: Option<NaiveDate>,
+        commit: bool,
+    ) -> Result<FixedAssetImportResult> {
+        let table = Self::read_table(file_name, content, mapping)?;
+
+        let categories = fixed_asset_category::Entity::find()
+            .filter(fixed_asset_category::Column::IsActive.eq(true))
+            .all(db)
+            .await?;
+        let existing: HashSet<String> = fixed_asset::Entity::find()
+            .filter(fixed_asset::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|asset| asset.inventory_number.trim().to_lowercase())
+            .collect();
+
+        let preview = Self::preview(
+            mapping,
+            &table,
+            &categories,
+            &existing,
+            company_id,
+            opening_balance_date,
+        )?;
+        if !commit || preview.error_count > 0 || preview.rows.is_empty() {
+            return Ok(FixedAssetImportResult {
+                imported: Vec::new(),
+                preview,
+            });
+        }
+
+        let txn = db.begin().await?;
+        let mut imported = Vec::with_capacity(preview.rows.len());
+        for row in &preview.rows {
+            if let Some(asset) = row.asset.clone() {
+                imported.push(asset.insert(&txn).await?);
+            }
+        }
+        txn.commit().await?;
+
+        Ok(FixedAssetImportResult { imported, preview })
+    }
+
+    /// Rows of a CSV or XLSX file; spreadsheets are recognised by their
+    /// extension or content
+    pub fn read_table(
+        file_name: &str,
+        content: &[u8],
+        mapping: &FixedAssetImportMapping,
+    ) -> Result<ImportTable> {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase())
+            .unwrap_or_default();
+        let is_spreadsheet = matches!(extension.as_str(), "xlsx" | "xlsm" | "xls" | "ods")
+            || content.starts_with(b"PK\x03\x04")
+            || content.starts_with(&[0xD0, 0xCF, 0x11, 0xE0]);
+
+        let mut rows = if is_spreadsheet {
+            Self::read_spreadsheet(content, mapping)?
+        } else {
+            Self::read_csv(content, mapping)?
+        }
+        .into_iter()
+        .skip(mapping.skip_rows)
+        .filter(|(_, cells)| {
+            cells
+                .iter()
+                .any(|cell| !matches!(cell, ImportCell::Text(value) if value.is_empty()))
+        });
+
+        let headers = if mapping.has_header {
+            let (_, header) = rows
+                .next()
+                .ok_or_else(|| anyhow!("The file has no header row"))?;
+            header
+                .iter()
+                .map(|cell| cell.text().trim().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(ImportTable {
+            headers,
+            rows: rows.collect(),
+        })
+    }
+
+    fn read_csv(
+        content: &[u8],
+        mapping: &FixedAssetImportMapping,
+    ) -> Result<Vec<(i32, Vec<ImportCell>)>> {
+        let text = match mapping.encoding.as_deref().map(str::trim) {
+            Some(label) if !label.is_empty() => {
+                let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| anyhow!("Unknown encoding {}", label))?;
+                encoding.decode(content).0.into_owned()
+            }
+            _ => match std::str::from_utf8(content) {
+                Ok(text) => text.to_string(),
+                Err(_) => encoding_rs::WINDOWS_1251.decode(content).0.into_owned(),
+            },
+        };
+        let delimiter = mapping.delimiter_char();
+
+        Ok(text
+            .trim_start_matches('\u{feff}')
+            .replace('\r', "")
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let cells = BankImportService::split_csv_line(line, delimiter)
+                    .into_iter()
+                    .map(|field| ImportCell::Text(field.trim().to_string()))
+                    .collect();
+                (index as i32 + 1, cells)
+            })
+            .collect())
+    }
+
+    fn read_spreadsheet(
+        content: &[u8],
+        mapping: &FixedAssetImportMapping,
+    ) -> Result<Vec<(i32, Vec<ImportCell>)>> {
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(content.to_vec()))
+            .map_err(|err| anyhow!("Cannot read the spreadsheet: {}", err))?;
+        let sheet = match mapping.sheet.as_deref() {
+            Some(sheet) => sheet.to_string(),
+            None => workbook
+                .sheet_names()
+                .first()
+                .cloned()
+                .ok_or_else(|| anyhow!("The spreadsheet has no sheets"))?,
+        };
+        let range = workbook
+            .worksheet_range(&sheet)
+            .map_err(|err| anyhow!("Cannot read sheet \"{}\": {}", sheet, err))?;
+        let first_row = range.start().map_or(0, |(row, _)| row);
+
+        Ok(range
+            .rows()
+            .enumerate()
+            .map(|(index, cells)| {
+                let cells = cells
+                    .iter()
+                    .map(|cell| match cell {
+                        Data::Int(value) => ImportCell::Number(*value as f64),
+                        Data::Float(value) => ImportCell::Number(*value),
+                        Data::DateTime(value) => value
+                            .as_datetime()
+                            .map(|value| ImportCell::Date(value.date()))
+                            .unwrap_or_else(|| ImportCell::Number(value.as_f64())),
+                        Data::DateTimeIso(value) => value
+                            .get(..10)
+                            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+                            .map(ImportCell::Date)
+                            .unwrap_or_else(|| ImportCell::Text(value.clone())),
+                        Data::Empty => ImportCell::Text(String::new()),
+                        other => ImportCell::Text(other.to_string().trim().to_string()),
+                    })
+                    .collect();
+                ((first_row as usize + index) as i32 + 1, cells)
+            })
+            .collect())
+    }
+
+    /// Validate every row against the categories and the inventory numbers
+    /// already used by the company
+    pub fn preview(
+        mapping: &FixedAssetImportMapping,
+        table: &ImportTable,
+        categories: &[fixed_asset_category::Model],
+        existing_inventory_numbers: &HashSet<String>,
+        company_id: i32,
+        opening_balance_date: Option<NaiveDate>,
+    ) -> Result<FixedAssetImportPreview> {
+        let columns = mapping.resolve_columns(&table.headers)?;
+        let mut seen = existing_inventory_numbers.clone();
+
+        let rows: Vec<FixedAssetImportRow> = table
+            .rows
+            .iter()
+            .map(|(line_number, cells)| {
+                Self::read_row(
+                    mapping,
+                    &columns,
+                    *line_number,
+                    cells,
+                    categories,
+                    &mut seen,
+                    company_id,
+                    opening_balance_date,
+                )
+            })
+            .collect();
+        let error_count = rows.iter().filter(|row| !row.errors.is_empty()).count() as i32;
+
+        Ok(FixedAssetImportPreview {
+            headers: table.headers.clone(),
+            valid_count: rows.len() as i32 - error_count,
+            error_count,
+            rows,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn read_row(
+        mapping: &FixedAssetImportMapping,
+        columns: &ResolvedColumns,
+        line_number: i32,
+        cells: &[ImportCell],
+        categories: &[fixed_asset_category::Model],
+        seen: &mut HashSet<String>,
+        company_id: i32,
+        opening_balance_date: Option<NaiveDate>,
+    ) -> FixedAssetImportRow {
+        let mut errors = Vec::new();
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|index| cells.get(index))
+                .filter(|cell| !matches!(cell, ImportCell::Text(value) if value.is_empty()))
+        };
+        let text = |index: Option<usize>| cell(index).map(ImportCell::text);
+        let required_text = |index: usize, field: &str, errors: &mut Vec<String>| {
+            let value = text(Some(index));
+            if value.is_none() {
+                errors.push(format!("Missing {}", field));
+            }
+            value
+        };
+        let decimal = |index: Option<usize>, field: &str, errors: &mut Vec<String>| {
+            cell(index).and_then(|value| match mapping.parse_decimal(value) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    errors.push(format!("{}: {}", field, err));
+                    None
+                }
+            })
+        };
+        let date = |index: Option<usize>, field: &str, errors: &mut Vec<String>| {
+            cell(index).and_then(|value| match mapping.parse_date(value) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    errors.push(format!("{}: {}", field, err));
+                    None
+                }
+            })
+        };
+
+        let inventory_number =
+            required_text(columns.inventory_number, "inventory number", &mut errors);
+        if let Some(inventory_number) = &inventory_number {
+            if !seen.insert(inventory_number.trim().to_lowercase()) {
+                errors.push(format!(
+                    "Inventory number {} is already used",
+                    inventory_number
+                ));
+            }
+        }
+        let name = required_text(columns.name, "name", &mut errors);
+
+        let category_code = required_text(columns.category, "category", &mut errors);
+        let category = category_code.as_ref().and_then(|code| {
+            let wanted = code.trim().to_lowercase();
+            let category = categories.iter().find(|category| {
+                category.code.to_lowercase() == wanted || category.name.to_lowercase() == wanted
+            });
+            if category.is_none() {
+                errors.push(format!("Unknown category {}", code));
+            }
+            category
+        });
+
+        let acquisition_cost = decimal(
+            Some(columns.acquisition_cost),
+            "Acquisition cost",
+            &mut errors,
+        );
+        match acquisition_cost {
+            Some(cost) if cost <= Decimal::ZERO => {
+                errors.push("Acquisition cost must be positive".to_string())
+            }
+            None if cell(Some(columns.acquisition_cost)).is_none() => {
+                errors.push("Missing acquisition cost".to_string())
+            }
+            _ => {}
+        }
+        let acquisition_date = date(
+            Some(columns.acquisition_date),
+            "Acquisition date",
+            &mut errors,
+        );
+        if cell(Some(columns.acquisition_date)).is_none() {
+            errors.push("Missing acquisition date".to_string());
+        }
+        let put_into_service_date = date(
+            columns.put_into_service_date,
+            "Put into service date",
+            &mut errors,
+        );
+        if let (Some(acquired), Some(in_service)) = (acquisition_date, put_into_service_date) {
+            if in_service < acquired {
+                errors.push("Put into service before the acquisition date".to_string());
+            }
+        }
+
+        // Accounting rate and useful life complete each other
+        let mut useful_life = decimal(columns.accounting_useful_life, "Useful life", &mut errors);
+        if useful_life.is_some_and(|life| life <= Decimal::ZERO || !life.fract().is_zero()) {
+            errors.push("Useful life must be a whole number of months".to_string());
+            useful_life = None;
+        }
+        let mut rate = decimal(
+            columns.accounting_depreciation_rate,
+            "Accounting depreciation rate",
+            &mut errors,
+        );
+        if rate.is_some_and(|rate| rate <= Decimal::ZERO || rate > Decimal::from(100)) {
+            errors.push("Accounting depreciation rate must be between 0 and 100%".to_string());
+            rate = None;
+        }
+        let (useful_life, rate) = match (useful_life, rate) {
+            (Some(life), Some(rate)) => (Some(life), Some(rate)),
+            (Some(life), None) => (Some(life), Some((Decimal::from(1200) / life).round_dp(2))),
+            (None, rate) => {
+                let rate =
+                    rate.or_else(|| category.and_then(|c| c.default_accounting_depreciation_rate));
+                let life = rate
+                    .filter(|rate| *rate > Decimal::ZERO)
+                    .map(|rate| (Decimal::from(1200) / rate).round());
+                if life.is_none() {
+                    errors.push("Missing accounting useful life or depreciation rate".to_string());
+                }
+                (life, rate)
+            }
+        };
+        let useful_life = useful_life.and_then(|life| life.to_i32());
+
+        let method = text(columns.depreciation_method)
+            .map(|method| method.trim().to_lowercase())
+            .unwrap_or_else(|| "straight_line".to_string());
+        if !DEPRECIATION_METHODS.contains(&method.as_str()) {
+            errors.push(format!("Unsupported depreciation method: {}", method));
+        }
+        let convention = text(columns.depreciation_start_convention)
+            .map(|convention| convention.trim().to_lowercase())
+            .unwrap_or_else(|| "next_month".to_string());
+        if !START_CONVENTIONS.contains(&convention.as_str()) {
+            errors.push(format!(
+                "Unsupported depreciation start convention: {}",
+                convention
+            ));
+        }
+        let production_capacity = decimal(
+            columns.production_capacity,
+            "Production capacity",
+            &mut errors,
+        );
+        if method == "units_of_production"
+            && production_capacity.is_none_or(|capacity| capacity <= Decimal::ZERO)
+        {
+            errors.push(
+                "Units-of-production depreciation requires a production capacity".to_string(),
+            );
+        }
+
+        let salvage_value =
+            decimal(columns.salvage_value, "Salvage value", &mut errors).unwrap_or(Decimal::ZERO);
+        if let Some(cost) = acquisition_cost {
+            if salvage_value < Decimal::ZERO || salvage_value >= cost {
+                errors.push("Salvage value must be below the acquisition cost".to_string());
+            }
+        }
+
+        let tax_rate = decimal(
+            columns.tax_depreciation_rate,
+            "Tax depreciation rate",
+            &mut errors,
+        )
+        .or_else(|| category.map(|category| category.max_tax_depreciation_rate));
+        if let (Some(category), Some(tax_rate)) = (category, tax_rate) {
+            if tax_rate < Decimal::ZERO || !category.is_rate_within_limits(tax_rate) {
+                errors.push(format!(
+                    "Tax depreciation rate {}% exceeds the ЗКПО limit of {}% for {}",
+                    tax_rate,
+                    category.max_tax_depreciation_rate,
+                    category.get_tax_category_display()
+                ));
+            }
+        }
+
+        // Opening balances taken over from the previous system
+        let accounting_accumulated = decimal(
+            columns.accounting_accumulated_depreciation,
+            "Accumulated accounting depreciation",
+            &mut errors,
+        )
+        .unwrap_or(Decimal::ZERO);
+        let tax_accumulated = decimal(
+            columns.tax_accumulated_depreciation,
+            "Accumulated tax depreciation",
+            &mut errors,
+        )
+        .unwrap_or(Decimal::ZERO);
+        if let Some(cost) = acquisition_cost {
+            if accounting_accumulated < Decimal::ZERO
+                || accounting_accumulated > cost - salvage_value
+            {
+                errors.push(
+                    "Accumulated accounting depreciation must be between 0 and the depreciable amount"
+                        .to_string(),
+                );
+            }
+            if tax_accumulated < Decimal::ZERO || tax_accumulated > cost {
+                errors.push(
+                    "Accumulated tax depreciation must be between 0 and the acquisition cost"
+                        .to_string(),
+                );
+            }
+        }
+        let has_opening_balance = !accounting_accumulated.is_zero() || !tax_accumulated.is_zero();
+        match opening_balance_date {
+            None if has_opening_balance => {
+                errors.push("Accumulated depreciation needs an opening balance date".to_string())
+            }
+            Some(opening) if has_opening_balance => {
+                let in_service = put_into_service_date.or(acquisition_date);
+                if in_service.is_some_and(|date| date > opening) {
+                    errors.push(
+                        "An asset put into service after the opening balance date has no accumulated depreciation"
+                            .to_string(),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        let accounting_book_value = acquisition_cost.map(|cost| cost - accounting_accumulated);
+        let tax_book_value = acquisition_cost.map(|cost| cost - tax_accumulated);
+
+        let asset = match (
+            errors.is_empty(),
+            &inventory_number,
+            &name,
+            category,
+            acquisition_cost,
+            acquisition_date,
+            useful_life,
+            rate,
+            tax_rate,
+        ) {
+            (
+                true,
+                Some(inventory_number),
+                Some(name),
+                Some(category),
+                Some(cost),
+                Some(acquisition_date),
+                Some(useful_life),
+                Some(rate),
+                Some(tax_rate),
+            ) => Some(fixed_asset::ActiveModel {
+                inventory_number: Set(inventory_number.clone()),
+                name: Set(name.clone()),
+                description: Set(text(columns.description)),
+                category_id: Set(category.id),
+                company_id: Set(company_id),
+                acquisition_cost: Set(cost),
+                acquisition_date: Set(acquisition_date),
+                put_into_service_date: Set(put_into_service_date),
+                accounting_useful_life: Set(useful_life),
+                accounting_depreciation_rate: Set(rate),
+                accounting_depreciation_method: Set(method),
+                depreciation_start_convention: Set(convention),
+                production_capacity: Set(production_capacity),
+                accounting_salvage_value: Set(salvage_value),
+                accounting_accumulated_depreciation: Set(accounting_accumulated),
+                tax_useful_life: Set(None),
+                tax_depreciation_rate: Set(tax_rate),
+                tax_accumulated_depreciation: Set(tax_accumulated),
+                tax_depreciable_value: Set(cost),
+                is_new_first_time_investment: Set(false),
+                accounting_book_value: Set(cost - accounting_accumulated),
+                tax_book_value: Set(cost - tax_accumulated),
+                opening_balance_date: Set(opening_balance_date),
+                status: Set("active".to_string()),
+                location: Set(text(columns.location)),
+                responsible_person: Set(text(columns.responsible_person)),
+                serial_number: Set(text(columns.serial_number)),
+                manufacturer: Set(text(columns.manufacturer)),
+                model: Set(text(columns.model)),
+                notes: Set(text(columns.notes)),
+                ..Default::default()
+            }),
+            _ => None,
+        };
+
+        FixedAssetImportRow {
+            line_number,
+            inventory_number,
+            name,
+            category_code: category
+                .map(|category| category.code.clone())
+                .or(category_code),
+            acquisition_cost,
+            acquisition_date,
+            accounting_useful_life: useful_life,
+            accounting_depreciation_rate: rate,
+            tax_depreciation_rate: tax_rate,
+            accounting_book_value,
+            tax_book_value,
+            errors,
+            asset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn preview_reports_row_errors_and_keeps_opening_balances() {
+        let mapping = FixedAssetImportMapping::from_value(&json!({
+            "inventoryNumberColumn": "Инв. номер",
+            "nameColumn": "Наименование",
+            "categoryColumn": "Група",
+            "acquisitionCostColumn": "Стойност",
+            "acquisitionDateColumn": "Дата",
+            "accountingUsefulLifeColumn": "Срок",
+            "taxDepreciationRateColumn": "Данъчна норма",
+            "accountingAccumulatedDepreciationColumn": "Счет. амортизация",
+            "taxAccumulatedDepreciationColumn": "Дан. амортизация"
+        }))
+        .unwrap();
+        let content = "Инв. номер;Наименование;Група;Стойност;Дата;Срок;Данъчна норма;Счет. амортизация;Дан. амортизация\n\
+            DMA-001;Струг;machinery;12 000,00;15.03.2023;60;30;4 800,00;7 200,00\n\
+            DMA-002;Фреза;MACHINERY;9000;01.02.2024;48;40;;\n\
+            DMA-001;Преса;Транспорт;5000;01.02.2024;60;;;\n\
+            DMA-009;Кран;MACHINERY;8000;01.02.2024;60;;;\n";
+        let table =
+            FixedAssetImportService::read_table("register.csv", content.as_bytes(), &mapping)
+                .unwrap();
+        let existing = HashSet::from(["dma-009".to_string()]);
+
+        let preview = FixedAssetImportService::preview(
+            &mapping,
+            &table,
+            &[fixed_asset_category::Model::machinery()],
+            &existing,
+            1,
+            NaiveDate::from_ymd_opt(2024, 12, 31),
+        )
+        .unwrap();
+
+        assert_eq!(preview.valid_count, 1);
+        assert_eq!(preview.error_count, 3);
+
+        let lathe = &preview.rows[0];
+        assert!(lathe.errors.is_empty());
+        assert_eq!(
+            lathe.accounting_depreciation_rate,
+            Some("20".parse().unwrap())
+        );
+        assert_eq!(lathe.accounting_book_value, Some("7200".parse().unwrap()));
+        assert_eq!(lathe.tax_book_value, Some("4800".parse().unwrap()));
+        let asset = lathe.asset.as_ref().unwrap();
+        assert_eq!(asset.tax_depreciable_value, Set("12000".parse().unwrap()));
+        assert_eq!(
+            asset.opening_balance_date,
+            Set(NaiveDate::from_ymd_opt(2024, 12, 31))
+        );
+
+        assert_eq!(preview.rows[1].line_number, 3);
+        assert!(preview.rows[1].errors[0].contains("exceeds the ЗКПО limit of 30%"));
+        assert_eq!(
+            preview.rows[2].errors,
+            vec![
+                "Inventory number DMA-001 is already used".to_string(),
+                "Unknown category Транспорт".to_string(),
+            ]
+        );
+        assert_eq!(
+            preview.rows[3].errors,
+            vec!["Inventory number DMA-009 is already used".to_string()]
+        );
+        assert!(preview.rows[1..].iter().all(|row| row.asset.is_none()));
+    }
+}
@@ -0,0 +1,877 @@
+//! Fixed Asset Lifecycle Service
+//!
+//! Books the events that change a fixed asset after it is put into service:
+//! partial or full sale, disposal and write-off, revaluation to fair value
+//! (revaluation model of СС 16 / IAS 16), capitalised improvements and
+//! impairment. Each event posts one entry of the depreciation series and
+//! moves the accounting and tax values of the asset.
+//!
+//! Revaluation eliminates the accumulated depreciation against the asset
+//! account. Increases go to the revaluation reserve (112) unless they reverse
+//! an earlier decrease recognised as expense; decreases and impairments use up
+//! the reserve of the asset first and the rest is expensed (608). After a
+//! revaluation, improvement or impairment the new book value is spread evenly
+//! over the remaining useful life.
+//!
+//! ЗКПО does not recognise revaluations and impairments, so they leave the
+//! tax values untouched. Improvements raise the tax depreciable value and
+//! disposals remove the disposed part of it.
+//!
+//! A sale is a supply for VAT purposes: the proceeds are the tax base, the
+//! VAT charged on them is credited to 4532 and the entry is reported in the
+//! sales journal under the operation of the sale.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use sea_orm::*;
+
+use crate::entities::fixed_asset_event::{FixedAssetEventInput, FixedAssetEventType};
+use crate::entities::{
+    account, depreciation_journal, entry_line, fixed_asset, fixed_asset_category,
+    fixed_asset_event, journal_entry, vat_rate, JournalSeriesKind,
+};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::journal_numbering::JournalNumberingService;
+use crate::services::vat_return_calculation::operation_fields;
+
+/// Reserve from subsequent valuation of non-current assets
+pub const REVALUATION_RESERVE_ACCOUNT_CODE: &str = "112";
+/// Retained earnings, which take over the reserve of a derecognised asset
+pub const RETAINED_EARNINGS_ACCOUNT_CODE: &str = "122";
+/// Expense from subsequent valuation of assets
+pub const REVALUATION_EXPENSE_ACCOUNT_CODE: &str = "608";
+/// Other income, used for reversals of expensed decreases
+pub const REVALUATION_INCOME_ACCOUNT_CODE: &str = "709";
+/// Revenue from sales of non-current assets
+pub const SALE_REVENUE_ACCOUNT_CODE: &str = "705";
+/// Book value of sold and liquidated assets
+pub const DISPOSAL_EXPENSE_ACCOUNT_CODE: &str = "609";
+/// Book value of missing and destroyed assets
+pub const WRITE_OFF_EXPENSE_ACCOUNT_CODE: &str = "699";
+/// Default receivable of a sale
+pub const SALE_RECEIVABLE_ACCOUNT_CODE: &str = "411";
+/// VAT charged on sales
+pub const SALE_VAT_ACCOUNT_CODE: &str = "4532";
+/// Default source of an improvement
+pub const IMPROVEMENT_SOURCE_ACCOUNT_CODE: &str = "613";
+
+pub struct FixedAssetLifecycleService;
+
+/// Account of one side of a lifecycle posting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostingAccount {
+    /// Asset account of the category
+    Asset,
+    /// Accumulated depreciation account of the category
+    AccumulatedDepreciation,
+    /// Account chosen for the event, see `FixedAssetEventInput::offset_account_id`
+    Offset,
+    Fixed(&'static str),
+}
+
+/// Debit/credit pair of a lifecycle entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifecyclePosting {
+    pub debit: PostingAccount,
+    pub credit: PostingAccount,
+    pub amount: Decimal,
+    pub description: &'static str,
+}
+
+/// Values of the asset after an event and the postings that get it there
+#[derive(Debug, Clone)]
+pub struct LifecyclePlan {
+    pub acquisition_cost: Decimal,
+    pub accounting_accumulated_depreciation: Decimal,
+    pub accounting_book_value: Decimal,
+    pub accounting_salvage_value: Decimal,
+    pub accounting_useful_life: i32,
+    pub accounting_monthly_depreciation: Option<Decimal>,
+    pub tax_depreciable_value: Decimal,
+    pub tax_accumulated_depreciation: Decimal,
+    pub tax_book_value: Decimal,
+    pub revaluation_reserve: Decimal,
+    pub revaluation_loss: Decimal,
+    pub status: String,
+    pub portion: Decimal,
+    pub amount: Decimal,
+    pub gain_loss: Decimal,
+    pub remaining_life_months: Option<i32>,
+    /// VAT charged on the proceeds of a sale
+    pub vat_amount: Decimal,
+    pub vat_rate_id: Option<i32>,
+    pub postings: Vec<LifecyclePosting>,
+}
+
+impl FixedAssetLifecycleService {
+    /// Work out the postings and the new values of an asset for an event
+    pub fn plan(asset: &fixed_asset::Model, input: &FixedAssetEventInput) -> Result<LifecyclePlan> {
+        if !asset.is_active() {
+            bail!("Asset {} is not active", asset.name);
+        }
+        let amount = input.amount.unwrap_or(Decimal::ZERO);
+        if amount < Decimal::ZERO {
+            bail!("The event amount cannot be negative");
+        }
+
+        let mut plan = LifecyclePlan {
+            acquisition_cost: asset.acquisition_cost,
+            // Derived from the book value so impairments written off the asset account add up
+            accounting_accumulated_depreciation: asset.acquisition_cost
+                - asset.accounting_book_value,
+            accounting_book_value: asset.accounting_book_value,
+            accounting_salvage_value: asset.accounting_salvage_value,
+            accounting_useful_life: asset.accounting_useful_life,
+            accounting_monthly_depreciation: asset.accounting_monthly_depreciation,
+            tax_depreciable_value: asset.tax_depreciable_value,
+            tax_accumulated_depreciation: asset.tax_depreciable_value - asset.tax_book_value,
+            tax_book_value: asset.tax_book_value,
+            revaluation_reserve: asset.revaluation_reserve,
+            revaluation_loss: asset.revaluation_loss,
+            status: asset.status.clone(),
+            portion: Decimal::ONE,
+            amount,
+            gain_loss: Decimal::ZERO,
+            remaining_life_months: None,
+            vat_amount: Decimal::ZERO,
+            vat_rate_id: None,
+            postings: Vec::new(),
+        };
+
+        if input.event_type == FixedAssetEventType::Sale {
+            let operation = input
+                .vat_operation
+                .as_deref()
+                .ok_or_else(|| anyhow!("The VAT sales operation of the sale is required"))?;
+            if !operation.starts_with("про") || operation_fields(operation).is_none() {
+                bail!("{} is not a VAT sales operation", operation);
+            }
+        } else if input.vat_operation.is_some() || input.vat_rate_id.is_some() {
+            bail!("Only a sale is reported for VAT");
+        }
+
+        match input.event_type {
+            FixedAssetEventType::Sale
+            | FixedAssetEventType::Disposal
+            | FixedAssetEventType::WriteOff => Self::plan_disposal(&mut plan, input)?,
+            FixedAssetEventType::Revaluation => {
+                if input.amount.is_none() {
+                    bail!("The fair value of the asset is required");
+                }
+                Self::plan_revaluation(&mut plan, asset, input)?
+            }
+            FixedAssetEventType::Improvement => {
+                if amount.is_zero() {
+                    bail!("The improvement cost must be positive");
+                }
+                Self::plan_improvement(&mut plan, asset, input)?
+            }
+            FixedAssetEventType::Impairment => {
+                if input.amount.is_none() {
+                    bail!("The recoverable amount of the asset is required");
+                }
+                Self::plan_impairment(&mut plan, asset, input)?
+            }
+        }
+
+        plan.postings.retain(|posting| !posting.amount.is_zero());
+        Ok(plan)
+    }
+
+    fn plan_disposal(plan: &mut LifecyclePlan, input: &FixedAssetEventInput) -> Result<()> {
+        let portion = input.portion.unwrap_or(Decimal::ONE);
+        if portion <= Decimal::ZERO || portion > Decimal::ONE {
+            bail!("The disposed portion must be greater than 0 and at most 1");
+        }
+        if input.event_type == FixedAssetEventType::WriteOff && portion != Decimal::ONE {
+            bail!("A write-off removes the whole asset");
+        }
+        if input.event_type == FixedAssetEventType::Sale && plan.amount.is_zero() {
+            bail!("The sale proceeds must be positive");
+        }
+
+        let whole = portion == Decimal::ONE;
+        let part = |value: Decimal| {
+            if whole {
+                value
+            } else {
+                (value * portion).round_dp(2)
+            }
+        };
+
+        let cost_out = part(plan.acquisition_cost);
+        let accumulated_out = part(plan.accounting_accumulated_depreciation);
+        let book_out = cost_out - accumulated_out;
+        let reserve_out = part(plan.revaluation_reserve);
+        let tax_value_out = part(plan.tax_depreciable_value);
+        let tax_book_out = part(plan.tax_book_value);
+
+        let expense = match input.event_type {
+            FixedAssetEventType::Disposal | FixedAssetEventType::WriteOff => PostingAccount::Offset,
+            _ => PostingAccount::Fixed(DISPOSAL_EXPENSE_ACCOUNT_CODE),
+        };
+        plan.postings.push(LifecyclePosting {
+            debit: PostingAccount::AccumulatedDepreciation,
+            credit: PostingAccount::Asset,
+            amount: accumulated_out,
+            description: "Отписана амортизация",
+        });
+        plan.postings.push(LifecyclePosting {
+            debit: expense,
+            credit: PostingAccount::Asset,
+            amount: book_out,
+            description: "Балансова стойност на отписания актив",
+        });
+        if input.event_type == FixedAssetEventType::Sale {
+            plan.postings.push(LifecyclePosting {
+                debit: PostingAccount::Offset,
+                credit: PostingAccount::Fixed(SALE_REVENUE_ACCOUNT_CODE),
+                amount: plan.amount,
+                description: "Приход от продажба на актив",
+            });
+        }
+        plan.postings.push(LifecyclePosting {
+            debit: PostingAccount::Fixed(REVALUATION_RESERVE_ACCOUNT_CODE),
+            credit: PostingAccount::Fixed(RETAINED_EARNINGS_ACCOUNT_CODE),
+            amount: reserve_out,
+            description: "Реализиран преоценъчен резерв",
+        });
+
+        plan.acquisition_cost -= cost_out;
+        plan.accounting_accumulated_depreciation -= accumulated_out;
+        plan.accounting_book_value -= book_out;
+        plan.accounting_salvage_value -= part(plan.accounting_salvage_value);
+        plan.accounting_monthly_depreciation = plan
+            .accounting_monthly_depreciation
+            .map(|monthly| (monthly * (Decimal::ONE - portion)).round_dp(2));
+        plan.tax_depreciable_value -= tax_value_out;
+        plan.tax_book_value -= tax_book_out;
+        plan.tax_accumulated_depreciation = plan.tax_depreciable_value - plan.tax_book_value;
+        plan.revaluation_reserve -= reserve_out;
+        plan.revaluation_loss -= part(plan.revaluation_loss);
+        plan.portion = portion;
+        plan.gain_loss = plan.amount - book_out;
+        if whole {
+            if let Some(status) = input.event_type.disposed_status() {
+                plan.status = status.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Charge VAT at `rate` on the proceeds of a sale, owed by the buyer on
+    /// top of them
+    pub fn add_output_vat(plan: &mut LifecyclePlan, rate: &vat_rate::Model) {
+        plan.vat_amount = (plan.amount * rate.rate / Decimal::ONE_HUNDRED)
+            .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+        plan.vat_rate_id = Some(rate.id);
+        if !plan.vat_amount.is_zero() {
+            plan.postings.push(LifecyclePosting {
+                debit: PostingAccount::Offset,
+                credit: PostingAccount::Fixed(SALE_VAT_ACCOUNT_CODE),
+                amount: plan.vat_amount,
+                description: "ДДС при продажба на актив",
+            });
+        }
+    }
+
+    fn plan_revaluation(
+        plan: &mut LifecyclePlan,
+        asset: &fixed_asset::Model,
+        input: &FixedAssetEventInput,
+    ) -> Result<()> {
+        let fair_value = plan.amount;
+        let difference = fair_value - plan.accounting_book_value;
+
+        // Elimination approach: the revalued amount becomes the new gross value
+        plan.postings.push(LifecyclePosting {
+            debit: PostingAccount::AccumulatedDepreciation,
+            credit: PostingAccount::Asset,
+            amount: plan.accounting_accumulated_depreciation,
+            description: "Елиминиране на натрупаната амортизация",
+        });
+        if difference > Decimal::ZERO {
+            let reversal = difference.min(plan.revaluation_loss);
+            plan.postings.push(LifecyclePosting {
+                debit: PostingAccount::Asset,
+                credit: PostingAccount::Fixed(REVALUATION_INCOME_ACCOUNT_CODE),
+                amount: reversal,
+                description: "Възстановена загуба от преоценка",
+            });
+            plan.postings.push(LifecyclePosting {
+                debit: PostingAccount::Asset,
+                credit: PostingAccount::Fixed(REVALUATION_RESERVE_ACCOUNT_CODE),
+                amount: difference - reversal,
+                description: "Положителна преоценка",
+            });
+            plan.revaluation_loss -= reversal;
+            plan.revaluation_reserve += difference - reversal;
+        } else {
+            Self::write_down(plan, -difference, "Отрицателна преоценка");
+        }
+
+        let remaining = Self::remaining_life(asset, input.remaining_life_months, 0)?;
+        plan.acquisition_cost = fair_value;
+        plan.accounting_accumulated_depreciation = Decimal::ZERO;
+        plan.accounting_book_value = fair_value;
+        plan.gain_loss = difference;
+        Self::respread(plan, asset, remaining)
+    }
+
+    fn plan_improvement(
+        plan: &mut LifecyclePlan,
+        asset: &fixed_asset::Model,
+        input: &FixedAssetEventInput,
+    ) -> Result<()> {
+        let extends = input.extends_life_months.unwrap_or(0);
+        if extends < 0 {
+            bail!("An improvement cannot shorten the useful life");
+        }
+
+        plan.postings.push(LifecyclePosting {
+            debit: PostingAccount::Asset,
+            credit: PostingAccount::Offset,
+            amount: plan.amount,
+            description: "Подобрение на актив",
+        });
+
+        let remaining = Self::remaining_life(asset, input.remaining_life_months, extends)?;
+        plan.acquisition_cost += plan.amount;
+        plan.accounting_book_value += plan.amount;
+        plan.tax_depreciable_value += plan.amount;
+        plan.tax_book_value += plan.amount;
+        Self::respread(plan, asset, remaining)
+    }
+
+    fn plan_impairment(
+        plan: &mut LifecyclePlan,
+        asset: &fixed_asset::Model,
+        input: &FixedAssetEventInput,
+    ) -> Result<()> {
+        let loss = plan.accounting_book_value - plan.amount;
+        if loss <= Decimal::ZERO {
+            bail!("The recoverable amount is not below the book value of the asset");
+        }
+
+        Self::write_down(plan, loss, "Обезценка на актив");
+
+        let remaining = Self::remaining_life(asset, input.remaining_life_months, 0)?;
+        plan.acquisition_cost -= loss;
+        plan.accounting_book_value -= loss;
+        plan.gain_loss = -loss;
+        Self::respread(plan, asset, remaining)
+    }
+
+    /// Decrease of the carrying amount: the reserve of the asset first, the rest to expense
+    fn write_down(plan: &mut LifecyclePlan, amount: Decimal, description: &'static str) {
+        let from_reserve = amount.min(plan.revaluation_reserve);
+        plan.postings.push(LifecyclePosting {
+            debit: PostingAccount::Fixed(REVALUATION_RESERVE_ACCOUNT_CODE),
+            credit: PostingAccount::Asset,
+            amount: from_reserve,
+            description,
+        });
+        plan.postings.push(LifecyclePosting {
+            debit: PostingAccount::Fixed(REVALUATION_EXPENSE_ACCOUNT_CODE),
+            credit: PostingAccount::Asset,
+            amount: amount - from_reserve,
+            description,
+        });
+        plan.revaluation_reserve -= from_reserve;
+        plan.revaluation_loss += amount - from_reserve;
+    }
+
+    /// Remaining accounting life in months, either given or the current one plus `extends`
+    fn remaining_life(
+        asset: &fixed_asset::Model,
+        given: Option<i32>,
+        extends: i32,
+    ) -> Result<Option<i32>> {
+        if let Some(months) = given {
+            if months <= 0 {
+                bail!("The remaining useful life must be at least one month");
+            }
+            return Ok(Some(months));
+        }
+        match asset.remaining_accounting_life_months() {
+            i32::MAX => Ok(None),
+            months if months + extends > 0 => Ok(Some(months + extends)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Spread the new depreciable amount evenly over the remaining life
+    fn respread(
+        plan: &mut LifecyclePlan,
+        asset: &fixed_asset::Model,
+        remaining: Option<i32>,
+    ) -> Result<()> {
+        plan.accounting_salvage_value = plan
+            .accounting_salvage_value
+            .min(plan.accounting_book_value);
+        let depreciable = plan.accounting_book_value - plan.accounting_salvage_value;
+
+        if depreciable.is_zero() {
+            plan.accounting_monthly_depreciation = Some(Decimal::ZERO);
+            return Ok(());
+        }
+        let remaining = remaining.ok_or_else(|| {
+            anyhow!(
+                "The remaining useful life of {} must be given, it is already fully depreciated",
+                asset.name
+            )
+        })?;
+
+        let current_remaining = asset
+            .remaining_accounting_life_months()
+            .min(asset.accounting_useful_life);
+        let elapsed = (asset.accounting_useful_life - current_remaining).max(0);
+        plan.accounting_useful_life = elapsed + remaining;
+        plan.accounting_monthly_depreciation =
+            Some((depreciable / Decimal::from(remaining)).round_dp(2));
+        plan.remaining_life_months = Some(remaining);
+        Ok(())
+    }
+
+    /// Book an event of a fixed asset
+    pub async fn record<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        input: &FixedAssetEventInput,
+        created_by: i32,
+    ) -> Result<fixed_asset_event::Model> {
+        let txn = db.begin().await?;
+
+        let asset = fixed_asset::Entity::find_by_id(input.fixed_asset_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow!("Fixed asset {} not found", input.fixed_asset_id))?;
+        let company_id = asset.company_id;
+        let date = input.event_date;
+
+        AccountingPeriodService::ensure_open(&txn, company_id, date, Some(created_by)).await?;
+
+        let month_start = NaiveDate::from_ymd_opt(date.year(), date.month(), 1)
+            .ok_or_else(|| anyhow!("Invalid event date {}", date))?;
+        let later_depreciation = depreciation_journal::Entity::find()
+            .filter(depreciation_journal::Column::FixedAssetId.eq(asset.id))
+            .filter(depreciation_journal::Column::Period.gt(month_start))
+            .count(&txn)
+            .await?;
+        if later_depreciation > 0 {
+            bail!(
+                "Depreciation of {} is already calculated after {}",
+                asset.name,
+                date.format("%m.%Y")
+            );
+        }
+        let later_events = fixed_asset_event::Entity::find()
+            .filter(fixed_asset_event::Column::FixedAssetId.eq(asset.id))
+            .filter(fixed_asset_event::Column::EventDate.gt(date))
+            .count(&txn)
+            .await?;
+        if later_events > 0 {
+            bail!(
+                "{} has events after {}",
+                asset.name,
+                date.format("%d.%m.%Y")
+            );
+        }
+
+        let mut plan = Self::plan(&asset, input)?;
+        if let Some(vat_rate_id) = input.vat_rate_id {
+            let rate = vat_rate::Entity::find_by_id(vat_rate_id)
+                .filter(vat_rate::Column::CompanyId.eq(company_id))
+                .one(&txn)
+                .await?
+                .ok_or_else(|| anyhow!("VAT rate {} not found in this company", vat_rate_id))?;
+            Self::add_output_vat(&mut plan, &rate);
+        }
+        let journal_entry_id = Self::book_entry(&txn, &asset, input, &plan, created_by).await?;
+
+        let mut update: fixed_asset::ActiveModel = asset.clone().into();
+        update.acquisition_cost = Set(plan.acquisition_cost);
+        update.accounting_accumulated_depreciation = Set(plan.accounting_accumulated_depreciation);
+        update.accounting_book_value = Set(plan.accounting_book_value);
+        update.accounting_salvage_value = Set(plan.accounting_salvage_value);
+        update.accounting_useful_life = Set(plan.accounting_useful_life);
+        update.accounting_monthly_depreciation = Set(plan.accounting_monthly_depreciation);
+        update.tax_depreciable_value = Set(plan.tax_depreciable_value);
+        update.tax_accumulated_depreciation = Set(plan.tax_accumulated_depreciation);
+        update.tax_book_value = Set(plan.tax_book_value);
+        update.revaluation_reserve = Set(plan.revaluation_reserve);
+        update.revaluation_loss = Set(plan.revaluation_loss);
+        if plan.status != asset.status {
+            update.status = Set(plan.status.clone());
+            update.disposal_date = Set(Some(date));
+            update.disposal_amount = Set(Some(plan.amount));
+        }
+        update.updated_at = Set(Utc::now());
+        update.update(&txn).await?;
+
+        let event = fixed_asset_event::ActiveModel {
+            company_id: Set(company_id),
+            fixed_asset_id: Set(asset.id),
+            event_type: Set(input.event_type),
+            event_date: Set(date),
+            portion: Set(plan.portion),
+            amount: Set(plan.amount),
+            acquisition_cost_before: Set(asset.acquisition_cost),
+            acquisition_cost_after: Set(plan.acquisition_cost),
+            accounting_book_value_before: Set(asset.accounting_book_value),
+            accounting_book_value_after: Set(plan.accounting_book_value),
+            tax_book_value_before: Set(asset.tax_book_value),
+            tax_book_value_after: Set(plan.tax_book_value),
+            tax_depreciable_value_before: Set(asset.tax_depreciable_value),
+            tax_depreciable_value_after: Set(plan.tax_depreciable_value),
+            gain_loss: Set(plan.gain_loss),
+            remaining_life_months: Set(plan.remaining_life_months),
+            journal_entry_id: Set(journal_entry_id),
+            description: Set(input.description.clone()),
+            created_by: Set(Some(created_by)),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let event = fixed_asset_event::Entity::insert(event)
+            .exec_with_returning(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(event)
+    }
+
+    /// Events of an asset in chronological order
+    pub async fn events<C: ConnectionTrait>(
+        db: &C,
+        fixed_asset_id: i32,
+    ) -> Result<Vec<fixed_asset_event::Model>> {
+        Ok(fixed_asset_event::Entity::find()
+            .filter(fixed_asset_event::Column::FixedAssetId.eq(fixed_asset_id))
+            .order_by_asc(fixed_asset_event::Column::EventDate)
+            .order_by_asc(fixed_asset_event::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    async fn book_entry(
+        txn: &DatabaseTransaction,
+        asset: &fixed_asset::Model,
+        input: &FixedAssetEventInput,
+        plan: &LifecyclePlan,
+        created_by: i32,
+    ) -> Result<Option<i32>> {
+        if plan.postings.is_empty() {
+            return Ok(None);
+        }
+
+        let company_id = asset.company_id;
+        let date = input.event_date;
+        let category = fixed_asset_category::Entity::find_by_id(asset.category_id)
+            .one(txn)
+            .await?
+            .ok_or_else(|| anyhow!("Asset category {} not found", asset.category_id))?;
+
+        let asset_account = Self::account_by_code(txn, company_id, &category.asset_account_code)
+            .await?
+            .id;
+        let depreciation_account =
+            Self::account_by_code(txn, company_id, &category.depreciation_account_code)
+                .await?
+                .id;
+        let offset_account = Self::offset_account(txn, company_id, input).await?;
+
+        let description = input.description.clone().unwrap_or_else(|| {
+            format!(
+                "{} на {} {}",
+                input.event_type.display_name(),
+                asset.inventory_number,
+                asset.name
+            )
+        });
+        // A sale is reported at its invoice value, the other events at the
+        // sum of their postings
+        let total_amount = if input.vat_operation.is_some() {
+            plan.amount + plan.vat_amount
+        } else {
+            plan.postings
+                .iter()
+                .fold(Decimal::ZERO, |acc, posting| acc + posting.amount)
+        };
+        let number = JournalNumberingService::allocate(
+            txn,
+            company_id,
+            JournalSeriesKind::Depreciation,
+            date,
+        )
+        .await?;
+
+        let entry = journal_entry::ActiveModel {
+            entry_number: Set(number.entry_number),
+            company_id: Set(company_id),
+            document_date: Set(date),
+            accounting_date: Set(date),
+            document_number: Set(Some(
+                input
+                    .document_number
+                    .clone()
+                    .unwrap_or_else(|| asset.inventory_number.clone()),
+            )),
+            description: Set(description),
+            vat_document_type: Set(None),
+            total_amount: Set(total_amount),
+            total_vat_amount: Set(plan.vat_amount),
+            vat_sales_operation: Set(input.vat_operation.clone()),
+            is_posted: Set(true),
+            posted_by: Set(Some(created_by)),
+            posted_at: Set(Some(Utc::now())),
+            created_by: Set(created_by),
+            series_id: Set(Some(number.series_id)),
+            sequence_number: Set(Some(number.sequence_number)),
+            ..Default::default()
+        };
+        let entry = journal_entry::Entity::insert(entry)
+            .exec_with_returning(txn)
+            .await?;
+
+        let mut lines = Vec::new();
+        for posting in &plan.postings {
+            for (side, is_debit) in [(posting.debit, true), (posting.credit, false)] {
+                let account_id = match side {
+                    PostingAccount::Asset => asset_account,
+                    PostingAccount::AccumulatedDepreciation => depreciation_account,
+                    PostingAccount::Offset => offset_account,
+                    PostingAccount::Fixed(code) => {
+                        Self::account_by_code(txn, company_id, code).await?.id
+                    }
+                };
+                let counterpart_id = if side == PostingAccount::Offset {
+                    input.counterpart_id
+                } else {
+                    None
+                };
+                // The revenue line carries the VAT of the sale, both lines its rate
+                let (vat_amount, vat_rate_id) = match side {
+                    PostingAccount::Fixed(SALE_REVENUE_ACCOUNT_CODE) => {
+                        (plan.vat_amount, plan.vat_rate_id)
+                    }
+                    PostingAccount::Fixed(SALE_VAT_ACCOUNT_CODE) => {
+                        (Decimal::ZERO, plan.vat_rate_id)
+                    }
+                    _ => (Decimal::ZERO, None),
+                };
+                lines.push(entry_line::ActiveModel {
+                    journal_entry_id: Set(entry.id),
+                    account_id: Set(account_id),
+                    debit_amount: Set(if is_debit {
+                        posting.amount
+                    } else {
+                        Decimal::ZERO
+                    }),
+                    credit_amount: Set(if is_debit {
+                        Decimal::ZERO
+                    } else {
+                        posting.amount
+                    }),
+                    counterpart_id: Set(counterpart_id),
+                    description: Set(Some(posting.description.to_string())),
+                    line_order: Set(lines.len() as i32 + 1),
+                    base_amount: Set(posting.amount),
+                    vat_amount: Set(vat_amount),
+                    vat_rate_id: Set(vat_rate_id),
+                    ..Default::default()
+                });
+            }
+        }
+        entry_line::Entity::insert_many(lines).exec(txn).await?;
+
+        Ok(Some(entry.id))
+    }
+
+    async fn offset_account<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        input: &FixedAssetEventInput,
+    ) -> Result<i32> {
+        if let Some(account_id) = input.offset_account_id {
+            let account = account::Entity::find_by_id(account_id)
+                .filter(account::Column::CompanyId.eq(company_id))
+                .one(db)
+                .await?
+                .ok_or_else(|| anyhow!("Account {} not found in this company", account_id))?;
+            return Ok(account.id);
+        }
+
+        let code = match input.event_type {
+            FixedAssetEventType::Sale => SALE_RECEIVABLE_ACCOUNT_CODE,
+            FixedAssetEventType::Improvement => IMPROVEMENT_SOURCE_ACCOUNT_CODE,
+            FixedAssetEventType::WriteOff => WRITE_OFF_EXPENSE_ACCOUNT_CODE,
+            _ => DISPOSAL_EXPENSE_ACCOUNT_CODE,
+        };
+        Ok(Self::account_by_code(db, company_id, code).await?.id)
+    }
+
+    async fn account_by_code<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        code: &str,
+    ) -> Result<account::Model> {
+        account::Entity::find()
+            .filter(account::Column::CompanyId.eq(company_id))
+            .filter(account::Column::Code.eq(code))
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Account {} not found in this company", code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn asset(cost: &str, book: &str) -> fixed_asset::Model {
+        fixed_asset::Model {
+            accounting_accumulated_depreciation: dec(cost) - dec(book),
+            accounting_book_value: dec(book),
+            tax_accumulated_depreciation: dec("3000"),
+            tax_book_value: dec(cost) - dec("3000"),
+            ..fixed_asset::Model::fixture(
+                1,
+                dec(cost),
+                NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+            )
+        }
+    }
+
+    fn input(event_type: FixedAssetEventType, amount: Option<&str>) -> FixedAssetEventInput {
+        FixedAssetEventInput {
+            fixed_asset_id: 1,
+            event_type,
+            event_date: NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(),
+            amount: amount.map(dec),
+            portion: None,
+            remaining_life_months: None,
+            extends_life_months: None,
+            offset_account_id: None,
+            counterpart_id: None,
+            vat_operation: None,
+            vat_rate_id: None,
+            document_number: None,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn lifecycle_events_move_book_values_and_post_gain_or_loss() {
+        // 12 000 cost, 20% a year: 200 a month, 36 months left at 7 200
+        let machine = asset("12000", "7200");
+
+        let mut half = input(FixedAssetEventType::Sale, Some("4000"));
+        half.portion = Some(dec("0.5"));
+        half.vat_operation = Some("про11".to_string());
+        let sale = FixedAssetLifecycleService::plan(&machine, &half).unwrap();
+        assert_eq!(sale.acquisition_cost, dec("6000"));
+        assert_eq!(sale.accounting_book_value, dec("3600"));
+        assert_eq!(sale.tax_book_value, dec("4500"));
+        assert_eq!(sale.gain_loss, dec("400"));
+        assert_eq!(sale.status, "active");
+        assert_eq!(sale.postings.len(), 3);
+
+        let revaluation = FixedAssetLifecycleService::plan(
+            &machine,
+            &input(FixedAssetEventType::Revaluation, Some("9000")),
+        )
+        .unwrap();
+        assert_eq!(revaluation.acquisition_cost, dec("9000"));
+        assert_eq!(revaluation.revaluation_reserve, dec("1800"));
+        assert_eq!(
+            revaluation.accounting_monthly_depreciation,
+            Some(dec("250"))
+        );
+        assert_eq!(revaluation.tax_book_value, machine.tax_book_value);
+
+        // The impairment uses up the reserve before it is expensed
+        let mut revalued = machine.clone();
+        revalued.acquisition_cost = dec("9000");
+        revalued.accounting_book_value = dec("9000");
+        revalued.revaluation_reserve = dec("1800");
+        revalued.accounting_monthly_depreciation = Some(dec("250"));
+        let impairment = FixedAssetLifecycleService::plan(
+            &revalued,
+            &input(FixedAssetEventType::Impairment, Some("6000")),
+        )
+        .unwrap();
+        assert_eq!(impairment.revaluation_reserve, Decimal::ZERO);
+        assert_eq!(impairment.revaluation_loss, dec("1200"));
+        assert_eq!(impairment.accounting_book_value, dec("6000"));
+        assert_eq!(impairment.gain_loss, dec("-3000"));
+
+        let mut improvement = input(FixedAssetEventType::Improvement, Some("1800"));
+        improvement.extends_life_months = Some(12);
+        let improved = FixedAssetLifecycleService::plan(&machine, &improvement).unwrap();
+        assert_eq!(improved.tax_depreciable_value, dec("13800"));
+        assert_eq!(improved.accounting_useful_life, 72);
+        assert_eq!(improved.accounting_monthly_depreciation, Some(dec("187.5")));
+
+        let write_off =
+            FixedAssetLifecycleService::plan(&machine, &input(FixedAssetEventType::WriteOff, None))
+                .unwrap();
+        assert_eq!(write_off.status, "written_off");
+        assert_eq!(write_off.accounting_book_value, Decimal::ZERO);
+        assert_eq!(write_off.gain_loss, dec("-7200"));
+    }
+
+    #[test]
+    fn sales_charge_output_vat_on_the_net_proceeds() {
+        let machine = asset("12000", "7200");
+
+        let mut sale = input(FixedAssetEventType::Sale, Some("8000"));
+        assert!(FixedAssetLifecycleService::plan(&machine, &sale).is_err());
+        sale.vat_operation = Some("пок10".to_string());
+        assert!(FixedAssetLifecycleService::plan(&machine, &sale).is_err());
+        sale.vat_operation = Some("про11".to_string());
+
+        let mut plan = FixedAssetLifecycleService::plan(&machine, &sale).unwrap();
+        let now = Utc::now();
+        let rate = vat_rate::Model {
+            id: 20,
+            code: "VAT20".to_string(),
+            name: "ДДС 20%".to_string(),
+            rate: dec("20"),
+            vat_direction: crate::entities::account::VatDirection::Output,
+            is_active: true,
+            valid_from: NaiveDate::default(),
+            valid_to: None,
+            company_id: 1,
+            created_at: now,
+            updated_at: now,
+        };
+        FixedAssetLifecycleService::add_output_vat(&mut plan, &rate);
+
+        // The gain is measured on the proceeds without VAT
+        assert_eq!(plan.gain_loss, dec("800"));
+        assert_eq!(plan.vat_amount, dec("1600"));
+        assert_eq!(plan.vat_rate_id, Some(20));
+        let revenue = plan
+            .postings
+            .iter()
+            .find(|posting| posting.credit == PostingAccount::Fixed(SALE_REVENUE_ACCOUNT_CODE))
+            .unwrap();
+        assert_eq!(revenue.debit, PostingAccount::Offset);
+        assert_eq!(revenue.amount, dec("8000"));
+        let vat = plan.postings.last().unwrap();
+        assert_eq!(vat.debit, PostingAccount::Offset);
+        assert_eq!(vat.credit, PostingAccount::Fixed(SALE_VAT_ACCOUNT_CODE));
+        assert_eq!(vat.amount, dec("1600"));
+
+        // Only sales are reported for VAT
+        let mut write_off = input(FixedAssetEventType::WriteOff, None);
+        write_off.vat_operation = Some("про11".to_string());
+        assert!(FixedAssetLifecycleService::plan(&machine, &write_off).is_err());
+    }
+}
@@ -0,0 +1,638 @@
+//! FX Revaluation Service
+//!
+//! Month-end revaluation of open foreign-currency balances. Every monetary
+//! account/counterpart pair that carries a currency amount (cash and bank
+//! accounts of group 50, receivables and payables of class 4) is valued at
+//! the BNB/ECB rate of the last day of the month; the difference to its book
+//! value in the base currency is posted against the FX gain (724) and FX loss
+//! (624) accounts in one entry of the closing series. Non-monetary balances
+//! such as fixed assets, inventories and equity stay at their historical rate.
+//!
+//! The adjusting lines carry a zero currency amount, so the currency balance
+//! stays untouched while the book value moves to the closing rate. A run can
+//! be reversed while it is the latest one of the company and its period is
+//! open; its entry is then cancelled with a storno entry.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use sea_orm::*;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::entities::fx_revaluation::FxRevaluationInput;
+use crate::entities::{account, entry_line, fx_revaluation, journal_entry, JournalSeriesKind};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::bnb_service::BnbService;
+use crate::services::euro_changeover::{EuroChangeoverService, EURO, LEGACY_BASE_CURRENCY};
+use crate::services::journal_numbering::JournalNumberingService;
+use crate::services::journal_storno::JournalStornoService;
+
+/// Financial revenue from exchange rate differences
+pub const FX_GAIN_ACCOUNT_CODE: &str = "724";
+/// Financial expense from exchange rate differences
+pub const FX_LOSS_ACCOUNT_CODE: &str = "624";
+
+pub struct FxRevaluationService;
+
+/// Account code, account, counterpart and currency of a revalued balance
+type BalanceKey = (String, i32, Option<i32>, String);
+
+/// Open currency balance of one account/counterpart pair at month end
+#[derive(Debug, Clone)]
+pub struct FxBalance {
+    pub account_id: i32,
+    pub account_code: String,
+    pub counterpart_id: Option<i32>,
+    pub currency_code: String,
+    /// Debit-positive balance in the foreign currency
+    pub currency_balance: Decimal,
+    /// Debit-positive balance in the base currency
+    pub book_balance: Decimal,
+    /// Base currency units per unit of the foreign currency at month end
+    pub rate: Decimal,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct FxRevaluationLine {
+    pub account_id: i32,
+    pub account_code: String,
+    pub counterpart_id: Option<i32>,
+    pub currency_code: String,
+    pub currency_balance: Decimal,
+    pub rate: Decimal,
+    pub book_balance: Decimal,
+    pub revalued_balance: Decimal,
+    /// Debit-positive adjustment; positive is a gain
+    pub difference: Decimal,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct FxRevaluationPlan {
+    pub year: i32,
+    pub month: i32,
+    pub revaluation_date: NaiveDate,
+    pub gain_account_id: i32,
+    pub loss_account_id: i32,
+    pub total_gain: Decimal,
+    pub total_loss: Decimal,
+    pub lines: Vec<FxRevaluationLine>,
+}
+
+impl FxRevaluationService {
+    /// Value the balances at their month-end rates
+    pub fn plan(
+        year: i32,
+        month: i32,
+        revaluation_date: NaiveDate,
+        gain_account_id: i32,
+        loss_account_id: i32,
+        balances: impl IntoIterator<Item = FxBalance>,
+    ) -> FxRevaluationPlan {
+        let mut lines = Vec::new();
+        let mut total_gain = Decimal::ZERO;
+        let mut total_loss = Decimal::ZERO;
+
+        for balance in balances {
+            if !Self::is_monetary_account(&balance.account_code) {
+                continue;
+            }
+            let revalued_balance = (balance.currency_balance * balance.rate)
+                .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+            let difference = revalued_balance - balance.book_balance;
+            if difference.is_zero() {
+                continue;
+            }
+            if difference > Decimal::ZERO {
+                total_gain += difference;
+            } else {
+                total_loss -= difference;
+            }
+            lines.push(FxRevaluationLine {
+                account_id: balance.account_id,
+                account_code: balance.account_code,
+                counterpart_id: balance.counterpart_id,
+                currency_code: balance.currency_code,
+                currency_balance: balance.currency_balance,
+                rate: balance.rate,
+                book_balance: balance.book_balance,
+                revalued_balance,
+                difference,
+            });
+        }
+
+        FxRevaluationPlan {
+            year,
+            month,
+            revaluation_date,
+            gain_account_id,
+            loss_account_id,
+            total_gain,
+            total_loss,
+            lines,
+        }
+    }
+
+    /// Calculate the revaluation without booking anything
+    pub async fn preview(
+        db: &DatabaseConnection,
+        company_id: i32,
+        year: i32,
+        month: i32,
+        gain_account_id: Option<i32>,
+        loss_account_id: Option<i32>,
+    ) -> Result<FxRevaluationPlan> {
+        let revaluation_date = Self::month_end(year, month)?;
+        let gain_account =
+            Self::fx_account(db, company_id, gain_account_id, FX_GAIN_ACCOUNT_CODE).await?;
+        let loss_account =
+            Self::fx_account(db, company_id, loss_account_id, FX_LOSS_ACCOUNT_CODE).await?;
+        let balances = Self::load_balances(db, db, company_id, revaluation_date).await?;
+
+        Ok(Self::plan(
+            year,
+            month,
+            revaluation_date,
+            gain_account.id,
+            loss_account.id,
+            balances,
+        ))
+    }
+
+    pub async fn find<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        year: i32,
+        month: i32,
+    ) -> Result<Option<fx_revaluation::Model>> {
+        Ok(fx_revaluation::Entity::find()
+            .filter(fx_revaluation::Column::CompanyId.eq(company_id))
+            .filter(fx_revaluation::Column::Year.eq(year))
+            .filter(fx_revaluation::Column::Month.eq(month))
+            .one(db)
+            .await?)
+    }
+
+    /// Book the revaluation entry for a month. Missing rates are fetched
+    /// through `rates_db`, outside the booking transaction.
+    pub async fn execute<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        rates_db: &DatabaseConnection,
+        input: &FxRevaluationInput,
+        created_by: i32,
+    ) -> Result<fx_revaluation::Model> {
+        let txn = db.begin().await?;
+        let company_id = input.company_id;
+        let revaluation_date = Self::month_end(input.year, input.month)?;
+
+        if Self::find(&txn, company_id, input.year, input.month)
+            .await?
+            .is_some()
+        {
+            bail!(
+                "Currency balances for {:02}/{} are already revalued",
+                input.month,
+                input.year
+            );
+        }
+
+        AccountingPeriodService::ensure_open(&txn, company_id, revaluation_date, Some(created_by))
+            .await?;
+
+        let gain_account = Self::fx_account(
+            &txn,
+            company_id,
+            input.gain_account_id,
+            FX_GAIN_ACCOUNT_CODE,
+        )
+        .await?;
+        let loss_account = Self::fx_account(
+            &txn,
+            company_id,
+            input.loss_account_id,
+            FX_LOSS_ACCOUNT_CODE,
+        )
+        .await?;
+        let plan = Self::plan(
+            input.year,
+            input.month,
+            revaluation_date,
+            gain_account.id,
+            loss_account.id,
+            Self::load_balances(&txn, rates_db, company_id, revaluation_date).await?,
+        );
+
+        let journal_entry_id = Self::book_entry(&txn, company_id, created_by, &plan).await?;
+
+        let revaluation = fx_revaluation::ActiveModel {
+            company_id: Set(company_id),
+            year: Set(input.year),
+            month: Set(input.month),
+            revaluation_date: Set(revaluation_date),
+            gain_account_id: Set(gain_account.id),
+            loss_account_id: Set(loss_account.id),
+            total_gain: Set(plan.total_gain),
+            total_loss: Set(plan.total_loss),
+            journal_entry_id: Set(journal_entry_id),
+            created_by: Set(Some(created_by)),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let revaluation = fx_revaluation::Entity::insert(revaluation)
+            .exec_with_returning(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(revaluation)
+    }
+
+    /// Cancel the revaluation entry of a month with a storno entry and drop
+    /// the run, so the month can be revalued again
+    pub async fn reverse<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        year: i32,
+        month: i32,
+        user_id: i32,
+    ) -> Result<()> {
+        let txn = db.begin().await?;
+
+        let revaluation = Self::find(&txn, company_id, year, month)
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "Currency balances for {:02}/{} are not revalued",
+                    month,
+                    year
+                )
+            })?;
+
+        let later = fx_revaluation::Entity::find()
+            .filter(fx_revaluation::Column::CompanyId.eq(company_id))
+            .filter(fx_revaluation::Column::RevaluationDate.gt(revaluation.revaluation_date))
+            .count(&txn)
+            .await?;
+        if later > 0 {
+            bail!("Only the latest currency revaluation can be reversed");
+        }
+
+        AccountingPeriodService::ensure_open(
+            &txn,
+            company_id,
+            revaluation.revaluation_date,
+            Some(user_id),
+        )
+        .await?;
+
+        if let Some(entry_id) = revaluation.journal_entry_id {
+            JournalStornoService::storno(&txn, entry_id, user_id).await?;
+        }
+
+        fx_revaluation::Entity::delete_by_id(revaluation.id)
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    fn month_end(year: i32, month: i32) -> Result<NaiveDate> {
+        let (next_year, next_month) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+        NaiveDate::from_ymd_opt(next_year, next_month as u32, 1)
+            .and_then(|date| date.pred_opt())
+            .filter(|date| date.month() as i32 == month)
+            .ok_or_else(|| anyhow!("Invalid period {:02}/{}", month, year))
+    }
+
+    async fn fx_account<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        account_id: Option<i32>,
+        default_code: &str,
+    ) -> Result<account::Model> {
+        let query = account::Entity::find().filter(account::Column::CompanyId.eq(company_id));
+        match account_id {
+            Some(account_id) => query.filter(account::Column::Id.eq(account_id)),
+            None => query.filter(account::Column::Code.eq(default_code)),
+        }
+        .one(db)
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "FX difference account {} not found in this company",
+                account_id.map_or_else(|| default_code.to_string(), |id| id.to_string())
+            )
+        })
+    }
+
+    async fn book_entry(
+        txn: &DatabaseTransaction,
+        company_id: i32,
+        created_by: i32,
+        plan: &FxRevaluationPlan,
+    ) -> Result<Option<i32>> {
+        if plan.lines.is_empty() {
+            return Ok(None);
+        }
+
+        let date = plan.revaluation_date;
+        let base_currency =
+            EuroChangeoverService::base_currency_code(txn, company_id, date).await?;
+        let description = format!("Преоценка на валутни салда към {}", date.format("%d.%m.%Y"));
+        let debit_total = plan.total_gain + plan.total_loss;
+        let number =
+            JournalNumberingService::allocate(txn, company_id, JournalSeriesKind::Closing, date)
+                .await?;
+
+        let entry = journal_entry::ActiveModel {
+            entry_number: Set(number.entry_number),
+            document_date: Set(date),
+            vat_date: Set(None),
+            accounting_date: Set(date),
+            document_number: Set(None),
+            description: Set(description.clone()),
+            total_amount: Set(debit_total),
+            total_vat_amount: Set(Decimal::ZERO),
+            is_posted: Set(true),
+            posted_by: Set(Some(created_by)),
+            posted_at: Set(Some(Utc::now())),
+            created_by: Set(created_by),
+            company_id: Set(company_id),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            vat_document_type: Set(None),
+            vat_purchase_operation: Set(None),
+            vat_sales_operation: Set(None),
+            vat_additional_operation: Set(None),
+            vat_additional_data: Set(None),
+            series_id: Set(Some(number.series_id)),
+            sequence_number: Set(Some(number.sequence_number)),
+            ..Default::default()
+        };
+        let entry = journal_entry::Entity::insert(entry)
+            .exec_with_returning(txn)
+            .await?;
+
+        // (account, counterpart, debit, credit, currency, rate)
+        let mut postings: Vec<(i32, Option<i32>, Decimal, Decimal, String, Decimal)> = plan
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.account_id,
+                    line.counterpart_id,
+                    line.difference.max(Decimal::ZERO),
+                    (-line.difference).max(Decimal::ZERO),
+                    line.currency_code.clone(),
+                    line.rate,
+                )
+            })
+            .collect();
+        if !plan.total_gain.is_zero() {
+            postings.push((
+                plan.gain_account_id,
+                None,
+                Decimal::ZERO,
+                plan.total_gain,
+                base_currency.clone(),
+                Decimal::ONE,
+            ));
+        }
+        if !plan.total_loss.is_zero() {
+            postings.push((
+                plan.loss_account_id,
+                None,
+                plan.total_loss,
+                Decimal::ZERO,
+                base_currency.clone(),
+                Decimal::ONE,
+            ));
+        }
+
+        let line_models = postings.into_iter().enumerate().map(
+            |(idx, (account_id, counterpart_id, debit, credit, currency_code, rate))| {
+                let amount = debit + credit;
+                // Only the book value moves; the currency balance stays as it is
+                let currency_amount = if currency_code == base_currency {
+                    amount
+                } else {
+                    Decimal::ZERO
+                };
+                entry_line::ActiveModel {
+                    journal_entry_id: Set(entry.id),
+                    account_id: Set(account_id),
+                    debit_amount: Set(debit),
+                    credit_amount: Set(credit),
+                    counterpart_id: Set(counterpart_id),
+                    currency_code: Set(Some(currency_code)),
+                    currency_amount: Set(Some(currency_amount)),
+                    exchange_rate: Set(Some(rate)),
+                    base_amount: Set(amount),
+                    vat_amount: Set(Decimal::ZERO),
+                    vat_rate_id: Set(None),
+                    quantity: Set(None),
+                    unit_of_measure_code: Set(None),
+                    description: Set(Some(description.clone())),
+                    line_order: Set(idx as i32 + 1),
+                    created_at: Set(Utc::now()),
+                    ..Default::default()
+                }
+            },
+        );
+        entry_line::Entity::insert_many(line_models)
+            .exec(txn)
+            .await?;
+
+        Ok(Some(entry.id))
+    }
+
+    /// Posted foreign-currency balances of monetary accounts up to the
+    /// revaluation date, with their closing rates. Rates are read through
+    /// `rates_db`, which may sit outside the booking transaction.
+    /// Whether an account holds a monetary item revalued at the closing
+    /// rate: cash and bank (50x) or a receivable or payable (class 4)
+    pub fn is_monetary_account(code: &str) -> bool {
+        code.starts_with("50") || code.starts_with('4')
+    }
+
+    async fn load_balances<C: ConnectionTrait>(
+        db: &C,
+        rates_db: &DatabaseConnection,
+        company_id: i32,
+        revaluation_date: NaiveDate,
+    ) -> Result<Vec<FxBalance>> {
+        let base_currency =
+            EuroChangeoverService::base_currency_code(db, company_id, revaluation_date).await?;
+        // The lev is pegged to the euro, so neither needs revaluing against the other
+        let pegged = match base_currency.as_str() {
+            EURO => Some(LEGACY_BASE_CURRENCY),
+            LEGACY_BASE_CURRENCY => Some(EURO),
+            _ => None,
+        };
+
+        let accounts: HashMap<i32, account::Model> = account::Entity::find()
+            .filter(account::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|account| Self::is_monetary_account(&account.code))
+            .map(|account| (account.id, account))
+            .collect();
+
+        let lines = entry_line::Entity::find()
+            .find_also_related(journal_entry::Entity)
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .filter(journal_entry::Column::IsPosted.eq(true))
+            .filter(journal_entry::Column::AccountingDate.lte(revaluation_date))
+            .filter(entry_line::Column::CurrencyCode.is_not_null())
+            .all(db)
+            .await?;
+
+        // Currency and book balance per key
+        let mut balances: BTreeMap<BalanceKey, (Decimal, Decimal)> = BTreeMap::new();
+        for (line, _) in lines {
+            let Some(account) = accounts.get(&line.account_id) else {
+                continue;
+            };
+            let Some(currency_code) = line.currency_code else {
+                continue;
+            };
+            if currency_code == base_currency || Some(currency_code.as_str()) == pegged {
+                continue;
+            }
+
+            let book = line.debit_amount - line.credit_amount;
+            let currency_amount = line.currency_amount.unwrap_or_default().abs();
+            let signed_currency = if book < Decimal::ZERO {
+                -currency_amount
+            } else {
+                currency_amount
+            };
+
+            let balance = balances
+                .entry((
+                    account.code.clone(),
+                    line.account_id,
+                    line.counterpart_id,
+                    currency_code,
+                ))
+                .or_default();
+            balance.0 += signed_currency;
+            balance.1 += book;
+        }
+
+        let bnb_service = BnbService::new();
+        let mut rates: HashMap<String, Decimal> = HashMap::new();
+        let mut result = Vec::new();
+        for ((account_code, account_id, counterpart_id, currency_code), (currency, book)) in
+            balances
+        {
+            if currency.is_zero() && book.is_zero() {
+                continue;
+            }
+            let rate = match rates.get(&currency_code) {
+                Some(rate) => *rate,
+                None => {
+                    let rate = bnb_service
+                        .get_rate_to_base(
+                            rates_db,
+                            &currency_code,
+                            &base_currency,
+                            revaluation_date,
+                        )
+                        .await?;
+                    rates.insert(currency_code.clone(), rate);
+                    rate
+                }
+            };
+            result.push(FxBalance {
+                account_id,
+                account_code,
+                counterpart_id,
+                currency_code,
+                currency_balance: currency,
+                book_balance: book,
+                rate,
+            });
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn balance(account_id: i32, code: &str, currency: &str, book: &str, rate: &str) -> FxBalance {
+        FxBalance {
+            account_id,
+            account_code: code.to_string(),
+            counterpart_id: None,
+            currency_code: "USD".to_string(),
+            currency_balance: dec(currency),
+            book_balance: dec(book),
+            rate: dec(rate),
+        }
+    }
+
+    #[test]
+    fn plan_books_differences_to_gain_and_loss() {
+        let plan = FxRevaluationService::plan(
+            2025,
+            3,
+            NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+            20,
+            21,
+            vec![
+                // Bank account: 1000 USD booked at 1.80, now 1.85
+                balance(1, "504", "1000.00", "1800.00", "1.85"),
+                // Supplier: 500 USD owed, booked at 1.80
+                balance(2, "401", "-500.00", "-900.00", "1.85"),
+                // Already at the closing rate
+                balance(3, "411", "200.00", "370.00", "1.85"),
+            ],
+        );
+
+        assert_eq!(plan.lines.len(), 2);
+        assert_eq!(plan.total_gain, dec("50.00"));
+        assert_eq!(plan.total_loss, dec("25.00"));
+        assert_eq!(plan.lines[0].revalued_balance, dec("1850.00"));
+        assert_eq!(plan.lines[1].difference, dec("-25.00"));
+    }
+
+    #[test]
+    fn non_monetary_balances_keep_their_historical_rate() {
+        let plan = FxRevaluationService::plan(
+            2025,
+            3,
+            NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+            20,
+            21,
+            vec![
+                // Machine bought for 1000 USD at 1.80
+                balance(1, "204", "1000.00", "1800.00", "1.85"),
+                // Goods bought for 400 USD at 1.80
+                balance(2, "304", "400.00", "720.00", "1.85"),
+                // Customer owes 400 USD booked at 1.80
+                balance(3, "411", "400.00", "720.00", "1.85"),
+            ],
+        );
+
+        assert_eq!(plan.lines.len(), 1);
+        assert_eq!(plan.lines[0].account_code, "411");
+        assert_eq!(plan.total_gain, dec("20.00"));
+        assert!(!FxRevaluationService::is_monetary_account("101"));
+        assert!(FxRevaluationService::is_monetary_account("503"));
+    }
+}
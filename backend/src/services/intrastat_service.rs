@@ -7,6 +7,7 @@ use crate::entities::{
     company, entry_line, intrastat_account_mapping, intrastat_declaration,
     intrastat_declaration_item, intrastat_nomenclature, intrastat_settings, journal_entry,
 };
+use crate::services::euro_changeover::EuroChangeoverService;
 
 #[allow(dead_code)]
 pub struct IntrastatService {
@@ -204,6 +205,10 @@ impl IntrastatService {
             NaiveDate::from_ymd_opt(year, (month + 1) as u32, 1).unwrap()
         };
 
+        let currency_code =
+            EuroChangeoverService::base_currency_code(&self.db, declaration.company_id, start_date)
+                .await?;
+
         let journal_entries = journal_entry::Entity::find()
             .filter(journal_entry::Column::CompanyId.eq(declaration.company_id))
             .filter(journal_entry::Column::AccountingDate.gte(start_date))
@@ -265,7 +270,7 @@ impl IntrastatService {
                                 net_mass_kg: Set("0.000".parse().unwrap()),
                                 invoice_value: Set(line.debit_amount),
                                 statistical_value: Set(line.debit_amount),
-                                currency_code: Set("BGN".to_string()),
+                                currency_code: Set(currency_code.clone()),
                                 description: Set(nom.description_bg),
                                 journal_entry_id: Set(Some(entry.id)),
                                 entry_line_id: Set(Some(line.id)),
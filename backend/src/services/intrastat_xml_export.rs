@@ -1,11 +1,12 @@
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::Writer;
 use sea_orm::{QueryFilter, *};
 use std::io::Cursor;
 
 use crate::entities::{company, intrastat_declaration, intrastat_declaration_item};
+use crate::services::euro_changeover::EuroChangeoverService;
 
 #[allow(dead_code)]
 pub struct IntrastatXmlExporter {
@@ -34,6 +35,12 @@ impl IntrastatXmlExporter {
             .all(&self.db)
             .await?;
 
+        let period_start =
+            NaiveDate::from_ymd_opt(declaration.year, declaration.month as u32, 1)
+                .ok_or_else(|| anyhow::anyhow!("Invalid declaration period"))?;
+        let currency_code =
+            EuroChangeoverService::base_currency_code(&self.db, company.id, period_start).await?;
+
         let mut writer = Writer::new(Cursor::new(Vec::new()));
 
         writer.write_event(Event::Decl(quick_xml::events::BytesDecl::new(
@@ -50,7 +57,7 @@ impl IntrastatXmlExporter {
         ));
         writer.write_event(Event::Start(instat_elem))?;
 
-        self.write_envelope(&mut writer, &declaration, &company)?;
+        self.write_envelope(&mut writer, &declaration, &company, &currency_code)?;
 
         let declaration_type = match declaration.declaration_type {
             intrastat_declaration::DeclarationType::Arrival => "INSTAT_A",
@@ -76,6 +83,7 @@ impl IntrastatXmlExporter {
         writer: &mut Writer<Cursor<Vec<u8>>>,
         declaration: &intrastat_declaration::Model,
         company: &company::Model,
+        currency_code: &str,
     ) -> Result<()> {
         writer.write_event(Event::Start(BytesStart::new("Envelope")))?;
 
@@ -137,7 +145,7 @@ impl IntrastatXmlExporter {
             },
         )?;
         self.write_element(writer, "flowCode", function_code)?;
-        self.write_element(writer, "currencyCode", "BGN")?;
+        self.write_element(writer, "currencyCode", currency_code)?;
         self.write_element(
             writer,
             "totalInvoicedAmount",
@@ -0,0 +1,340 @@
+//! Journal Numbering Service
+//!
+//! Allocates journal entry numbers from gap-free series kept per company,
+//! kind (manual, bank, sales, ...) and year. A number is taken by locking the
+//! series row inside the caller's transaction, so two concurrent entries never
+//! get the same number and a rolled-back entry gives its number back.
+//!
+//! Deleting the most recent draft of a series releases its number. Gaps left
+//! by other deleted drafts are closed by renumbering the drafts of the series
+//! from its configured start number; posted entries never change their number.
+//! A unique index on (series, number) keeps two entries off the same number.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+
+use crate::entities::journal_entry;
+use crate::entities::journal_entry_series::{
+    self, ConfigureJournalEntrySeriesInput, JournalSeriesKind,
+};
+
+pub struct JournalNumberingService;
+
+/// Number taken from a series for a new entry
+#[derive(Debug, Clone)]
+pub struct AllocatedNumber {
+    pub series_id: i32,
+    pub sequence_number: i32,
+    pub entry_number: String,
+}
+
+impl AllocatedNumber {
+    /// Set the entry number and series reference on a new entry
+    pub fn apply(&self, entry: &mut journal_entry::ActiveModel) {
+        entry.entry_number = Set(self.entry_number.clone());
+        entry.series_id = Set(Some(self.series_id));
+        entry.sequence_number = Set(Some(self.sequence_number));
+    }
+}
+
+impl JournalNumberingService {
+    /// Entry number as shown in journals, e.g. `JE-2025-000042`
+    pub fn format_number(prefix: &str, year: i32, number: i32, padding: i32) -> String {
+        format!(
+            "{}-{}-{:0width$}",
+            prefix,
+            year,
+            number,
+            width = padding.max(1) as usize
+        )
+    }
+
+    /// Take the next number of the company's series for the year of `date`
+    pub async fn allocate(
+        txn: &DatabaseTransaction,
+        company_id: i32,
+        kind: JournalSeriesKind,
+        date: NaiveDate,
+    ) -> Result<AllocatedNumber> {
+        let series = Self::lock_series(txn, company_id, kind, date.year()).await?;
+
+        let sequence_number = series.next_number;
+        let entry_number =
+            Self::format_number(&series.prefix, series.year, sequence_number, series.padding);
+
+        let series_id = series.id;
+        let mut series: journal_entry_series::ActiveModel = series.into();
+        series.next_number = Set(sequence_number + 1);
+        series.updated_at = Set(Utc::now());
+        series.update(txn).await?;
+
+        Ok(AllocatedNumber {
+            series_id,
+            sequence_number,
+            entry_number,
+        })
+    }
+
+    /// Give back the number of a deleted entry if it was the last one taken
+    pub async fn release(txn: &DatabaseTransaction, entry: &journal_entry::Model) -> Result<()> {
+        let (Some(series_id), Some(sequence_number)) = (entry.series_id, entry.sequence_number)
+        else {
+            return Ok(());
+        };
+
+        let Some(series) = journal_entry_series::Entity::find_by_id(series_id)
+            .lock_exclusive()
+            .one(txn)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        if series.next_number == sequence_number + 1 {
+            let mut series: journal_entry_series::ActiveModel = series.into();
+            series.next_number = Set(sequence_number);
+            series.updated_at = Set(Utc::now());
+            series.update(txn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Series of a company, optionally for one year
+    pub async fn list<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        year: Option<i32>,
+    ) -> Result<Vec<journal_entry_series::Model>> {
+        let mut query = journal_entry_series::Entity::find()
+            .filter(journal_entry_series::Column::CompanyId.eq(company_id));
+        if let Some(year) = year {
+            query = query.filter(journal_entry_series::Column::Year.eq(year));
+        }
+
+        Ok(query
+            .order_by_desc(journal_entry_series::Column::Year)
+            .order_by_asc(journal_entry_series::Column::Code)
+            .all(db)
+            .await?)
+    }
+
+    /// Change the prefix, padding or starting number of a series
+    pub async fn configure<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        input: &ConfigureJournalEntrySeriesInput,
+    ) -> Result<journal_entry_series::Model> {
+        if let Some(padding) = input.padding {
+            if !(1..=10).contains(&padding) {
+                bail!("Padding must be between 1 and 10 digits");
+            }
+        }
+        if let Some(prefix) = &input.prefix {
+            if prefix.trim().is_empty() || prefix.len() > 20 {
+                bail!("Prefix must be between 1 and 20 characters");
+            }
+        }
+
+        let txn = db.begin().await?;
+        let series = Self::lock_series(&txn, input.company_id, input.code, input.year).await?;
+
+        if let Some(start_number) = input.start_number {
+            if start_number < 1 {
+                bail!("Start number must be positive");
+            }
+            let used = journal_entry::Entity::find()
+                .filter(journal_entry::Column::SeriesId.eq(series.id))
+                .count(&txn)
+                .await?;
+            if used > 0 {
+                bail!("The starting number cannot change once the series is in use");
+            }
+        }
+
+        let mut model: journal_entry_series::ActiveModel = series.into();
+        if let Some(prefix) = &input.prefix {
+            model.prefix = Set(prefix.trim().to_string());
+        }
+        if let Some(padding) = input.padding {
+            model.padding = Set(padding);
+        }
+        if let Some(start_number) = input.start_number {
+            model.start_number = Set(start_number);
+            model.next_number = Set(start_number);
+        }
+        model.updated_at = Set(Utc::now());
+        let series = model.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(series)
+    }
+
+    /// Close the gaps of a series by renumbering its drafts. Fails if a
+    /// posted entry would have to change its number.
+    pub async fn renumber_drafts<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        kind: JournalSeriesKind,
+        year: i32,
+    ) -> Result<i32> {
+        let txn = db.begin().await?;
+
+        let series = journal_entry_series::Entity::find()
+            .filter(journal_entry_series::Column::CompanyId.eq(company_id))
+            .filter(journal_entry_series::Column::Code.eq(kind))
+            .filter(journal_entry_series::Column::Year.eq(year))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow!("Numbering series not found"))?;
+
+        let entries = journal_entry::Entity::find()
+            .filter(journal_entry::Column::SeriesId.eq(series.id))
+            .order_by_asc(journal_entry::Column::SequenceNumber)
+            .order_by_asc(journal_entry::Column::Id)
+            .all(&txn)
+            .await?;
+
+        let first_number = series.start_number;
+        let plan = Self::renumber_plan(
+            first_number,
+            entries
+                .iter()
+                .map(|entry| (entry.sequence_number.unwrap_or_default(), entry.is_posted)),
+        )?;
+
+        let mut changed = 0;
+        for (entry, new_number) in entries.iter().zip(&plan) {
+            if entry.sequence_number == Some(*new_number) {
+                continue;
+            }
+            let mut model: journal_entry::ActiveModel = entry.clone().into();
+            model.sequence_number = Set(Some(*new_number));
+            model.entry_number = Set(Self::format_number(
+                &series.prefix,
+                series.year,
+                *new_number,
+                series.padding,
+            ));
+            model.updated_at = Set(Utc::now());
+            model.update(&txn).await?;
+            changed += 1;
+        }
+
+        let next_number = plan.last().map(|number| number + 1).unwrap_or(first_number);
+        let mut series: journal_entry_series::ActiveModel = series.into();
+        series.next_number = Set(next_number);
+        series.updated_at = Set(Utc::now());
+        series.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(changed)
+    }
+
+    /// New consecutive numbers for entries given in sequence order as
+    /// (current number, is posted)
+    fn renumber_plan(
+        first_number: i32,
+        entries: impl IntoIterator<Item = (i32, bool)>,
+    ) -> Result<Vec<i32>> {
+        let mut plan = Vec::new();
+        for (expected, (current, is_posted)) in (first_number..).zip(entries) {
+            if is_posted && current != expected {
+                bail!(
+                    "Posted entry number {} would become {}; unpost the entries after the gap first",
+                    current,
+                    expected
+                );
+            }
+            plan.push(expected);
+        }
+        Ok(plan)
+    }
+
+    /// Series row locked for update, created with defaults on first use
+    async fn lock_series(
+        txn: &DatabaseTransaction,
+        company_id: i32,
+        kind: JournalSeriesKind,
+        year: i32,
+    ) -> Result<journal_entry_series::Model> {
+        let series = journal_entry_series::ActiveModel {
+            company_id: Set(company_id),
+            code: Set(kind),
+            year: Set(year),
+            prefix: Set(kind.default_prefix().to_string()),
+            padding: Set(6),
+            start_number: Set(1),
+            next_number: Set(1),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        journal_entry_series::Entity::insert(series)
+            .on_conflict(
+                OnConflict::columns([
+                    journal_entry_series::Column::CompanyId,
+                    journal_entry_series::Column::Code,
+                    journal_entry_series::Column::Year,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
+            .exec(txn)
+            .await?;
+
+        journal_entry_series::Entity::find()
+            .filter(journal_entry_series::Column::CompanyId.eq(company_id))
+            .filter(journal_entry_series::Column::Code.eq(kind))
+            .filter(journal_entry_series::Column::Year.eq(year))
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .ok_or_else(|| anyhow!("Numbering series not found"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_numbers_with_prefix_year_and_padding() {
+        assert_eq!(
+            JournalNumberingService::format_number("JE", 2025, 42, 6),
+            "JE-2025-000042"
+        );
+        assert_eq!(
+            JournalNumberingService::format_number("BANK", 2026, 1234567, 6),
+            "BANK-2026-1234567"
+        );
+    }
+
+    #[test]
+    fn renumbering_closes_gaps_between_drafts_only() {
+        // 1 posted, 2 deleted, 3 and 5 drafts
+        let plan =
+            JournalNumberingService::renumber_plan(1, vec![(1, true), (3, false), (5, false)])
+                .unwrap();
+        assert_eq!(plan, vec![1, 2, 3]);
+
+        // A posted entry after the gap would have to move
+        assert!(
+            JournalNumberingService::renumber_plan(1, vec![(1, true), (3, true), (4, false)])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn renumbering_keeps_the_configured_start_number() {
+        // Series configured to start at 100; 100 posted, 101 deleted, 102 draft
+        let plan =
+            JournalNumberingService::renumber_plan(100, vec![(100, true), (102, false)]).unwrap();
+        assert_eq!(plan, vec![100, 101]);
+    }
+}
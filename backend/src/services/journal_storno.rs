@@ -0,0 +1,222 @@
+//! Journal Storno Service
+//!
+//! Posted entries are never deleted. An entry that has to be withdrawn is
+//! cancelled by a storno entry (черно сторно): the same lines with debit and
+//! credit swapped, dated on the accounting date of the original so that both
+//! fall into the same period, and numbered in the series of the original.
+//!
+//! VAT documents, entries reported under a VAT sales or purchase operation,
+//! are corrected with credit and debit notes instead, so they cannot be
+//! cancelled here. Other document types, such as depreciation, can.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use sea_orm::*;
+
+use crate::entities::{entry_line, journal_entry, journal_entry_series, JournalSeriesKind};
+use crate::services::journal_numbering::JournalNumberingService;
+
+pub struct JournalStornoService;
+
+impl JournalStornoService {
+    /// Book the storno of a journal entry and return the new entry
+    pub async fn storno(
+        txn: &DatabaseTransaction,
+        entry_id: i32,
+        user_id: i32,
+    ) -> Result<journal_entry::Model> {
+        let original = journal_entry::Entity::find_by_id(entry_id)
+            .one(txn)
+            .await?
+            .ok_or_else(|| anyhow!("Journal entry {} not found", entry_id))?;
+        if Self::is_vat_document(&original) {
+            bail!(
+                "Journal entry {} is a VAT document; issue a credit or debit note instead",
+                original.entry_number
+            );
+        }
+
+        let lines = entry_line::Entity::find()
+            .filter(entry_line::Column::JournalEntryId.eq(entry_id))
+            .order_by_asc(entry_line::Column::LineOrder)
+            .all(txn)
+            .await?;
+
+        let kind = match original.series_id {
+            Some(series_id) => journal_entry_series::Entity::find_by_id(series_id)
+                .one(txn)
+                .await?
+                .map(|series| series.code)
+                .unwrap_or(JournalSeriesKind::Manual),
+            None => JournalSeriesKind::Manual,
+        };
+        let number = JournalNumberingService::allocate(
+            txn,
+            original.company_id,
+            kind,
+            original.accounting_date,
+        )
+        .await?;
+
+        let now = Utc::now();
+        let description = format!(
+            "Сторно на {}: {}",
+            original.entry_number, original.description
+        );
+        let mut storno = journal_entry::ActiveModel {
+            document_date: Set(original.accounting_date),
+            vat_date: Set(None),
+            accounting_date: Set(original.accounting_date),
+            document_number: Set(original.document_number.clone()),
+            description: Set(description),
+            total_amount: Set(original.total_amount),
+            total_vat_amount: Set(original.total_vat_amount),
+            is_posted: Set(true),
+            posted_by: Set(Some(user_id)),
+            posted_at: Set(Some(now)),
+            created_by: Set(user_id),
+            company_id: Set(original.company_id),
+            created_at: Set(now),
+            updated_at: Set(now),
+            vat_document_type: Set(None),
+            vat_purchase_operation: Set(None),
+            vat_sales_operation: Set(None),
+            vat_additional_operation: Set(None),
+            vat_additional_data: Set(None),
+            ..Default::default()
+        };
+        number.apply(&mut storno);
+        let storno = journal_entry::Entity::insert(storno)
+            .exec_with_returning(txn)
+            .await?;
+
+        if !lines.is_empty() {
+            let line_models = lines
+                .into_iter()
+                .map(|line| Self::storno_line(line, storno.id));
+            entry_line::Entity::insert_many(line_models)
+                .exec(txn)
+                .await?;
+        }
+
+        Ok(storno)
+    }
+
+    /// Whether an entry is reported in the VAT journals
+    pub fn is_vat_document(entry: &journal_entry::Model) -> bool {
+        entry.vat_sales_operation.is_some() || entry.vat_purchase_operation.is_some()
+    }
+
+    /// Line of the storno entry: the original line with debit and credit
+    /// swapped
+    pub fn storno_line(line: entry_line::Model, storno_id: i32) -> entry_line::ActiveModel {
+        entry_line::ActiveModel {
+            journal_entry_id: Set(storno_id),
+            account_id: Set(line.account_id),
+            debit_amount: Set(line.credit_amount),
+            credit_amount: Set(line.debit_amount),
+            counterpart_id: Set(line.counterpart_id),
+            currency_code: Set(line.currency_code),
+            currency_amount: Set(line.currency_amount),
+            exchange_rate: Set(line.exchange_rate),
+            base_amount: Set(line.base_amount),
+            vat_amount: Set(line.vat_amount),
+            vat_rate_id: Set(line.vat_rate_id),
+            quantity: Set(line.quantity),
+            unit_of_measure_code: Set(line.unit_of_measure_code),
+            description: Set(line.description),
+            line_order: Set(line.line_order),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    fn depreciation_entry() -> journal_entry::Model {
+        let now = Utc::now();
+        journal_entry::Model {
+            id: 7,
+            entry_number: "АМ-2025-000006".to_string(),
+            document_date: NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(),
+            vat_date: None,
+            accounting_date: NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(),
+            document_number: Some("Амортизация за 2025-06".to_string()),
+            description: "Месечна амортизация за 2025-06".to_string(),
+            total_amount: Decimal::new(15833, 2),
+            total_vat_amount: Decimal::ZERO,
+            is_posted: true,
+            posted_by: Some(1),
+            posted_at: Some(now),
+            created_by: 1,
+            company_id: 1,
+            created_at: now,
+            updated_at: now,
+            vat_document_type: Some("DEPRECIATION".to_string()),
+            vat_purchase_operation: None,
+            vat_sales_operation: None,
+            vat_additional_operation: None,
+            vat_additional_data: None,
+            series_id: Some(3),
+            sequence_number: Some(6),
+        }
+    }
+
+    fn line(order: i32, account_id: i32, debit: Decimal, credit: Decimal) -> entry_line::Model {
+        entry_line::Model {
+            id: order,
+            journal_entry_id: 7,
+            account_id,
+            debit_amount: debit,
+            credit_amount: credit,
+            counterpart_id: None,
+            currency_code: None,
+            currency_amount: None,
+            exchange_rate: None,
+            base_amount: debit.max(credit),
+            vat_amount: Decimal::ZERO,
+            vat_rate_id: None,
+            quantity: None,
+            unit_of_measure_code: None,
+            description: Some("Амортизация ДМА".to_string()),
+            line_order: order,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn posted_depreciation_runs_are_cancelled_with_swapped_lines() {
+        // The depreciation document type is not a VAT document
+        let run = depreciation_entry();
+        assert!(!JournalStornoService::is_vat_document(&run));
+
+        let amount = run.total_amount;
+        let expense = line(1, 603, amount, Decimal::ZERO);
+        let accumulated = line(2, 241, Decimal::ZERO, amount);
+        let storno: Vec<_> = [expense, accumulated]
+            .into_iter()
+            .map(|line| JournalStornoService::storno_line(line, 8))
+            .collect();
+
+        assert_eq!(storno[0].journal_entry_id, Set(8));
+        assert_eq!(storno[0].account_id, Set(603));
+        assert_eq!(storno[0].debit_amount, Set(Decimal::ZERO));
+        assert_eq!(storno[0].credit_amount, Set(amount));
+        assert_eq!(storno[1].account_id, Set(241));
+        assert_eq!(storno[1].debit_amount, Set(amount));
+        assert_eq!(storno[1].credit_amount, Set(Decimal::ZERO));
+        assert_eq!(storno[1].line_order, Set(2));
+
+        let invoice = journal_entry::Model {
+            vat_document_type: Some("01".to_string()),
+            vat_sales_operation: Some("про11".to_string()),
+            ..run
+        };
+        assert!(JournalStornoService::is_vat_document(&invoice));
+    }
+}
@@ -1,15 +1,31 @@
+pub mod accounting_period;
+pub mod audit_log;
+pub mod bank_csv_mapping;
 pub mod bank_imports;
+pub mod bank_reconciliation;
 pub mod bank_transaction_parser;
 pub mod bnb_service;
 pub mod ecb_service;
 pub mod contragent;
 pub mod controlisy;
 pub mod depreciation_service;
+pub mod euro_changeover;
+pub mod fixed_asset_import;
+pub mod fixed_asset_lifecycle;
+pub mod fx_revaluation;
 pub mod intrastat_service;
 pub mod intrastat_xml_export;
 pub mod invoice_processing;
+pub mod journal_numbering;
+pub mod journal_storno;
 pub mod maintenance;
 pub mod nap_export;
+pub mod payment_orders;
 pub mod saft_service;
 pub mod saft_service_v2;
+pub mod saft_validator;
+pub mod temporary_differences;
+pub mod vat_return_calculation;
+pub mod vat_return_correction;
+pub mod year_end_closing;
 pub mod inventory_service;
@@ -3,18 +3,108 @@ use chrono::{Datelike, NaiveDate};
 use encoding_rs::WINDOWS_1251;
 use rust_decimal::Decimal;
 use sea_orm::*;
+use std::collections::BTreeMap;
 use std::fmt::Write;
 
+use crate::services::vat_return_calculation::{VatJournalDocument, VatReturnCalculationService};
+
 pub struct NapExportService;
 
+/// Sales journal operation codes reported in the VIES declaration
+const VIES_GOODS_OPERATION: &str = "про20";
+const VIES_SERVICES_OPERATION: &str = "про22";
+const VIES_TRIANGULAR_OPERATION: &str = "про25";
+
+/// Intra-community supplies to one customer for the VIES declaration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViesRow {
+    pub vat_number: String,
+    /// Intra-community supplies of goods (ВОД)
+    pub goods: Decimal,
+    /// Supplies as intermediary in a triangular operation
+    pub triangular: Decimal,
+    /// Services under art. 21(2) VAT Act
+    pub services: Decimal,
+}
+
+/// Registered person (VTR) and the person filing for it (VDR) on VIES.TXT
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViesDeclarant {
+    pub vat_number: String,
+    pub company_name: String,
+    pub company_address: String,
+    pub person_egn: String,
+    pub person_name: String,
+    pub city: String,
+    pub post_code: String,
+    /// 'A' for the legal representative, 'B' for an authorized person
+    pub person_capacity: char,
+}
+
+impl ViesDeclarant {
+    /// Declarant from the company data; the authorized person files when
+    /// one is set, otherwise the manager
+    pub fn from_company(
+        vat_number: &str,
+        company_name: &str,
+        address: Option<String>,
+        city: Option<String>,
+        manager: (Option<String>, Option<String>),
+        authorized_person: (Option<String>, Option<String>),
+    ) -> Result<Self> {
+        let present = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let (person_name, person_egn, person_capacity) = match (
+            present(&authorized_person.0),
+            present(&authorized_person.1),
+        ) {
+            (Some(name), Some(egn)) => (name, egn, 'B'),
+            _ => match (present(&manager.0), present(&manager.1)) {
+                (Some(name), Some(egn)) => (name, egn, 'A'),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "The VIES declaration needs the name and EGN of the company manager or authorized person"
+                    ))
+                }
+            },
+        };
+
+        // Bulgarian post codes are four digits, usually written with the city
+        let city = present(&city).unwrap_or_default();
+        let post_code = city
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|part| part.len() == 4)
+            .unwrap_or_default()
+            .to_string();
+        let city_name = city
+            .split_whitespace()
+            .filter(|part| *part != post_code)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(ViesDeclarant {
+            vat_number: vat_number.to_string(),
+            company_name: company_name.to_string(),
+            company_address: present(&address).unwrap_or_default(),
+            person_egn,
+            person_name,
+            city: city_name,
+            post_code,
+            person_capacity,
+        })
+    }
+}
+
 impl NapExportService {
-    /// Format text field with fixed width, padded with spaces
+    /// Format text field with fixed width in characters, padded with spaces
     fn format_text_field(text: &str, width: usize) -> String {
-        let mut result = text.chars().take(width).collect::<String>();
-        while result.len() < width {
-            result.push(' ');
-        }
-        result
+        let result = text.chars().take(width).collect::<String>();
+        format!("{:<width$}", result, width = width)
     }
 
     /// Format numeric field with fixed width, right-aligned, padded with spaces
@@ -34,17 +124,32 @@ impl NapExportService {
         cow.into_owned()
     }
     /// Generate VIES format files for NAP (Bulgarian Tax Administration)
-    pub async fn generate_vies_files(
-        db: &DatabaseConnection,
+    pub async fn generate_vies_files<C: ConnectionTrait>(
+        db: &C,
         company_id: i32,
         year: i32,
         month: i32,
     ) -> Result<ViesFiles> {
+        let texts = Self::generate_texts(db, company_id, year, month).await?;
+        Ok(texts.encode())
+    }
+
+    /// Generate the NAP files of a period as text, before encoding
+    pub async fn generate_texts<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        year: i32,
+        month: i32,
+    ) -> Result<NapFileTexts> {
         // Get company info
         let company = db
             .query_one(Statement::from_sql_and_values(
                 DbBackend::Postgres,
-                "SELECT name, eik, vat_number, address FROM companies WHERE id = $1",
+                r#"
+            SELECT name, eik, vat_number, address, city,
+                manager_name, manager_egn, authorized_person, authorized_person_egn
+            FROM companies WHERE id = $1
+            "#,
                 vec![company_id.into()],
             ))
             .await?
@@ -65,143 +170,198 @@ impl NapExportService {
                 - chrono::Duration::days(1)
         };
 
+        // Every file is built from the same documents as the VAT return
+        let documents =
+            VatReturnCalculationService::load_documents(db, company_id, start_date, end_date)
+                .await?;
+
         // Generate DEKLAR.TXT
-        let deklar = Self::generate_deklar(
-            db,
-            company_id,
-            &company_name,
-            &company_eik,
-            &company_vat,
-            start_date,
-            end_date,
-        )
-        .await?;
+        let deklar = Self::generate_deklar(&company_name, &company_vat, start_date, &documents);
 
         // Generate POKUPKI.TXT (Purchases)
-        let pokupki =
-            Self::generate_pokupki(db, company_id, &company_vat, start_date, end_date).await?;
+        let pokupki = Self::generate_pokupki(&company_vat, start_date, &documents);
 
         // Generate PRODAGBI.TXT (Sales)
-        let prodagbi =
-            Self::generate_prodagbi(db, company_id, &company_vat, start_date, end_date).await?;
+        let prodagbi = Self::generate_prodagbi(&company_vat, start_date, &documents);
+
+        // Generate VIES.TXT only when there are intra-community supplies
+        let vies_rows = Self::vies_rows(&documents)?;
+        let vies = if vies_rows.is_empty() {
+            None
+        } else {
+            let declarant = ViesDeclarant::from_company(
+                &company_vat,
+                &company_name,
+                company.try_get("", "address")?,
+                company.try_get("", "city")?,
+                (
+                    company.try_get("", "manager_name")?,
+                    company.try_get("", "manager_egn")?,
+                ),
+                (
+                    company.try_get("", "authorized_person")?,
+                    company.try_get("", "authorized_person_egn")?,
+                ),
+            )?;
+            Some(Self::generate_vies(&declarant, start_date, &vies_rows))
+        };
 
-        Ok(ViesFiles {
-            deklar: Self::to_windows_1251(&deklar),
-            pokupki: Self::to_windows_1251(&pokupki),
-            prodagbi: Self::to_windows_1251(&prodagbi),
+        Ok(NapFileTexts {
+            deklar,
+            pokupki,
+            prodagbi,
+            vies,
         })
     }
 
-    async fn generate_deklar(
-        db: &DatabaseConnection,
-        company_id: i32,
+    /// Sales with ВОД, triangular or art. 21(2) service codes, summed per
+    /// customer VAT number
+    fn vies_rows(documents: &[VatJournalDocument]) -> Result<Vec<ViesRow>> {
+        let mut supplies = Vec::new();
+        for document in documents {
+            let Some(operation) = document.sales_operation.as_deref().filter(|operation| {
+                [
+                    VIES_GOODS_OPERATION,
+                    VIES_TRIANGULAR_OPERATION,
+                    VIES_SERVICES_OPERATION,
+                ]
+                .contains(operation)
+            }) else {
+                continue;
+            };
+            let contractor_vat = document.counterpart_vat_number.clone().unwrap_or_default();
+            if contractor_vat.trim().is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Document {} is an intra-community supply without a customer VAT number",
+                    Self::document_number(document)
+                ));
+            }
+            supplies.push((contractor_vat, operation.to_string(), document.base_amount));
+        }
+
+        Ok(Self::aggregate_vies_rows(supplies))
+    }
+
+    /// Number of the document, or of the journal entry when it has none
+    fn document_number(document: &VatJournalDocument) -> &str {
+        document
+            .document_number
+            .as_deref()
+            .unwrap_or(&document.entry_number)
+    }
+
+    /// Sum supplies given as (customer VAT number, operation code, base amount)
+    /// into one row per customer, ordered by VAT number
+    pub fn aggregate_vies_rows(
+        supplies: impl IntoIterator<Item = (String, String, Decimal)>,
+    ) -> Vec<ViesRow> {
+        let mut rows: BTreeMap<String, ViesRow> = BTreeMap::new();
+        for (vat_number, operation, amount) in supplies {
+            let vat_number: String = vat_number
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_uppercase();
+            let row = rows.entry(vat_number.clone()).or_insert_with(|| ViesRow {
+                vat_number,
+                ..Default::default()
+            });
+            match operation.as_str() {
+                VIES_GOODS_OPERATION => row.goods += amount,
+                VIES_TRIANGULAR_OPERATION => row.triangular += amount,
+                VIES_SERVICES_OPERATION => row.services += amount,
+                _ => {}
+            }
+        }
+        rows.into_values().collect()
+    }
+
+    /// VIES.TXT in the NAP record layout: header (VHR), the person filing
+    /// the declaration (VDR), the registered person (VTR), totals (TTR) and
+    /// one VIR record per customer. Amounts are rounded to whole units.
+    pub fn generate_vies(declarant: &ViesDeclarant, period: NaiveDate, rows: &[ViesRow]) -> String {
+        let whole = |amount: Decimal| Self::format_numeric_field(amount.round_dp(0), 15, 0);
+
+        let mut vies = String::new();
+
+        // Заглавен запис: период ММГГГГ и брой VIR записи
+        vies.push_str("VHR");
+        vies.push_str(&format!("{:02}{}", period.month(), period.year()));
+        vies.push_str(&format!("{:>5}", rows.len()));
+        vies.push_str("\r\n");
+
+        // Лицето, подаващо декларацията
+        vies.push_str("VDR");
+        vies.push_str(&Self::format_text_field(&declarant.person_egn, 10));
+        vies.push_str(&Self::format_text_field(&declarant.person_name, 150));
+        vies.push_str(&Self::format_text_field(&declarant.city, 50));
+        vies.push_str(&Self::format_text_field(&declarant.post_code, 4));
+        vies.push_str(&Self::format_text_field(&declarant.company_address, 150));
+        vies.push(declarant.person_capacity);
+        vies.push_str("\r\n");
+
+        // Регистрираното лице
+        vies.push_str("VTR");
+        vies.push_str(&Self::format_text_field(&declarant.vat_number, 15));
+        vies.push_str(&Self::format_text_field(&declarant.company_name, 150));
+        vies.push_str(&Self::format_text_field(&declarant.company_address, 200));
+        vies.push_str("\r\n");
+
+        // Общи суми: всичко, ВОД, тристранни операции, услуги
+        let goods: Decimal = rows.iter().map(|row| row.goods.round_dp(0)).sum();
+        let triangular: Decimal = rows.iter().map(|row| row.triangular.round_dp(0)).sum();
+        let services: Decimal = rows.iter().map(|row| row.services.round_dp(0)).sum();
+        let total = goods + triangular + services;
+        vies.push_str("TTR");
+        vies.push_str(&whole(total));
+        vies.push_str(&whole(goods));
+        vies.push_str(&whole(triangular));
+        vies.push_str(&whole(services));
+        vies.push_str("\r\n");
+
+        // Редове по контрагенти; последното поле е период на корекция (празно)
+        for (index, row) in rows.iter().enumerate() {
+            vies.push_str("VIR");
+            vies.push_str(&format!("{:>5}", index + 1));
+            vies.push_str(&Self::format_text_field(&row.vat_number, 15));
+            vies.push_str(&whole(row.goods));
+            vies.push_str(&whole(row.triangular));
+            vies.push_str(&whole(row.services));
+            vies.push_str(&Self::format_text_field("", 6));
+            vies.push_str("\r\n");
+        }
+
+        vies
+    }
+
+    fn generate_deklar(
         company_name: &str,
-        _company_eik: &str,
         company_vat: &str,
-        start_date: NaiveDate,
-        end_date: NaiveDate,
-    ) -> Result<String> {
+        period: NaiveDate,
+        documents: &[VatJournalDocument],
+    ) -> String {
         let mut deklar = String::new();
 
-        // Debug: Get count of entries first
-        let debug_count = db
-            .query_one(Statement::from_sql_and_values(
-                DbBackend::Postgres,
-                r#"
-            SELECT 
-                COUNT(*) as total_entries,
-                COUNT(CASE WHEN je.vat_document_type = '01' THEN 1 END) as sales_count,
-                COUNT(CASE WHEN je.vat_document_type = '03' THEN 1 END) as purchases_count,
-                COUNT(CASE WHEN je.is_posted = true THEN 1 END) as posted_count,
-                COUNT(CASE WHEN je.vat_document_type IS NOT NULL THEN 1 END) as vat_entries_count
-            FROM journal_entries je
-            WHERE je.company_id = $1
-            AND je.document_date >= $2
-            AND je.document_date <= $3
-            "#,
-                vec![company_id.into(), start_date.into(), end_date.into()],
-            ))
-            .await?
-            .ok_or(anyhow::anyhow!("No debug results"))?;
-
-        println!(
-            "DEBUG NAP Export for company {}, period {} to {}",
-            company_id, start_date, end_date
-        );
-        println!(
-            "Total entries: {:?}",
-            debug_count.try_get::<i64>("", "total_entries")
-        );
-        println!(
-            "Sales entries: {:?}",
-            debug_count.try_get::<i64>("", "sales_count")
-        );
-        println!(
-            "Purchase entries: {:?}",
-            debug_count.try_get::<i64>("", "purchases_count")
-        );
-        println!(
-            "Posted entries: {:?}",
-            debug_count.try_get::<i64>("", "posted_count")
-        );
-        println!(
-            "VAT entries: {:?}",
-            debug_count.try_get::<i64>("", "vat_entries_count")
-        );
-
-        // Get summary data from journal entries
-        let summary = db
-            .query_one(Statement::from_sql_and_values(
-                DbBackend::Postgres,
-                r#"
-            SELECT 
-                -- Purchases (vat_document_type = '03')
-                COALESCE(SUM(CASE WHEN je.vat_document_type = '03' 
-                    THEN je.total_amount - je.total_vat_amount 
-                    ELSE 0 END), 0) as purchases_base,
-                COALESCE(SUM(CASE WHEN je.vat_document_type = '03' 
-                    THEN je.total_vat_amount 
-                    ELSE 0 END), 0) as purchases_vat,
-                -- Sales (vat_document_type = '01')
-                COALESCE(SUM(CASE WHEN je.vat_document_type = '01' 
-                    THEN je.total_amount - je.total_vat_amount 
-                    ELSE 0 END), 0) as sales_base,
-                COALESCE(SUM(CASE WHEN je.vat_document_type = '01' 
-                    THEN je.total_vat_amount 
-                    ELSE 0 END), 0) as sales_vat
-            FROM journal_entries je
-            WHERE je.company_id = $1
-            AND je.document_date >= $2
-            AND je.document_date <= $3
-            AND je.is_posted = true
-            AND je.vat_document_type IS NOT NULL
-            "#,
-                vec![company_id.into(), start_date.into(), end_date.into()],
-            ))
-            .await?;
-
-        let purchases_base: Decimal = summary
-            .as_ref()
-            .and_then(|s| s.try_get("", "purchases_base").ok())
-            .unwrap_or(Decimal::ZERO);
-        let purchases_vat: Decimal = summary
-            .as_ref()
-            .and_then(|s| s.try_get("", "purchases_vat").ok())
-            .unwrap_or(Decimal::ZERO);
-        let sales_base: Decimal = summary
-            .as_ref()
-            .and_then(|s| s.try_get("", "sales_base").ok())
-            .unwrap_or(Decimal::ZERO);
-        let sales_vat: Decimal = summary
-            .as_ref()
-            .and_then(|s| s.try_get("", "sales_vat").ok())
-            .unwrap_or(Decimal::ZERO);
+        let mut sales_base = Decimal::ZERO;
+        let mut sales_vat = Decimal::ZERO;
+        let mut purchases_base = Decimal::ZERO;
+        let mut purchases_vat = Decimal::ZERO;
+        for document in documents {
+            if document.sales_operation.is_some() {
+                sales_base += document.base_amount;
+                sales_vat += document.vat_amount;
+            }
+            if document.purchase_operation.is_some() {
+                purchases_base += document.base_amount;
+                purchases_vat += document.vat_amount;
+            }
+        }
 
         // Според НАП спецификацията DEKLAR.TXT има един запис с фиксирана дължина на полетата
         // Format: VAT number (15), Company name (80), Period (6), data fields...
         deklar.push_str(&Self::format_text_field(company_vat, 15));
         deklar.push_str(&Self::format_text_field(company_name, 80));
-        deklar.push_str(&format!("{}{:02}", start_date.year(), start_date.month()));
+        deklar.push_str(&format!("{}{:02}", period.year(), period.month()));
 
         // Номерация на дневниците (14 знака за всяко)
         deklar.push_str(&Self::format_integer_field(14, 14)); // Брой записи продажби
@@ -229,95 +389,80 @@ impl NapExportService {
 
         deklar.push_str("\r\n");
 
-        Ok(deklar)
+        deklar
     }
 
-    async fn generate_pokupki(
-        db: &DatabaseConnection,
-        company_id: i32,
+    /// Company VAT number, period, branch, line and document columns shared
+    /// by the POKUPKI.TXT and PRODAGBI.TXT records
+    fn journal_record_header(
         company_vat: &str,
-        start_date: NaiveDate,
-        end_date: NaiveDate,
-    ) -> Result<String> {
+        period: NaiveDate,
+        line_no: usize,
+        document: &VatJournalDocument,
+    ) -> String {
+        let mut record = String::new();
+
+        // Първата колона трябва да бъде ДДС номера на моята компания, не на контрагента
+        record.push_str(&Self::format_text_field(company_vat, 15));
+        record.push_str(&format!("{}{:02}", period.year(), period.month()));
+        record.push_str(&format!("   {:<15}", "0")); // клон/обособено звено - 3 spaces + "0" + padding
+
+        // Номер на ред + тип документ + номер на документ (заедно в поле с дължина 18)
+        // Според спецификацията: line_no + doc_type(2) + doc_number
+        // Типът документ трябва да е двуцифрен с водеща нула (01, 02, 03)
+        let doc_type_formatted =
+            format!("{:0>2}", document.document_type.as_deref().unwrap_or("01"));
+        let line_doc_combined = format!(
+            "{}{}{}",
+            line_no,
+            &doc_type_formatted,
+            Self::document_number(document)
+        );
+        record.push_str(&Self::format_text_field(&line_doc_combined, 18));
+
+        // Дата на документ (10 символа)
+        let formatted_date = document.document_date.format("%Y/%m/%d").to_string();
+        record.push_str(&Self::format_text_field(&formatted_date, 10));
+        record.push_str(&Self::format_text_field(
+            document
+                .counterpart_vat_number
+                .as_deref()
+                .unwrap_or_default(),
+            15,
+        ));
+        let contractor_name = document
+            .counterpart_name
+            .as_deref()
+            .unwrap_or(&document.description);
+        record.push_str(&Self::format_text_field(contractor_name, 50));
+        record.push_str(&Self::format_text_field(&document.description, 50)); // Описание на операцията
+
+        record
+    }
+
+    /// POKUPKI.TXT: one record per document with a purchase operation
+    fn generate_pokupki(
+        company_vat: &str,
+        period: NaiveDate,
+        documents: &[VatJournalDocument],
+    ) -> String {
         let mut pokupki = String::new();
 
-        // Get purchase documents from journal entries with VAT
-        let purchases = db
-            .query_all(Statement::from_sql_and_values(
-                DbBackend::Postgres,
-                r#"
-            SELECT 
-                je.document_number as doc_number,
-                TO_CHAR(je.document_date, 'YYYY-MM-DD') as doc_date,
-                COALESCE(cp.name, je.description, '') as contractor_name,
-                COALESCE(cp.vat_number, '') as contractor_vat,
-                je.total_amount as total_amount,
-                je.total_vat_amount as vat_amount,
-                (je.total_amount - je.total_vat_amount) as net_amount,
-                COALESCE(je.vat_document_type, '01') as document_type,
-                COALESCE(je.description, '') as operation_description
-            FROM journal_entries je
-            LEFT JOIN (
-                SELECT DISTINCT el.journal_entry_id, cp.name, cp.vat_number
-                FROM entry_lines el
-                JOIN counterparts cp ON el.counterpart_id = cp.id
-            ) cp ON je.id = cp.journal_entry_id
-            WHERE je.company_id = $1
-            AND je.document_date BETWEEN $2 AND $3
-            AND je.is_posted = true
-            AND je.vat_document_type = '03'  -- Purchase invoices
-            ORDER BY je.document_date, je.document_number
-            "#,
-                vec![company_id.into(), start_date.into(), end_date.into()],
-            ))
-            .await?;
-
-        let mut line_no = 1;
-        for purchase in purchases {
-            let doc_number: String = purchase.try_get("", "doc_number")?;
-            let doc_date: String = purchase.try_get("", "doc_date")?;
-            let contractor_name: String =
-                purchase.try_get("", "contractor_name").unwrap_or_default();
-            let contractor_vat: String = purchase.try_get("", "contractor_vat").unwrap_or_default();
-            let net_amount: Decimal = purchase.try_get("", "net_amount")?;
-            let vat_amount: Decimal = purchase.try_get("", "vat_amount")?;
-            let _total_amount: Decimal = purchase.try_get("", "total_amount")?;
-            let document_type: String = purchase
-                .try_get("", "document_type")
-                .unwrap_or("01".to_string());
-            let operation_description: String = purchase
-                .try_get("", "operation_description")
-                .unwrap_or_default();
-
-            // Формат според НАП спецификацията за POKUPKI.TXT с фиксирана дължина
-            // VAT number (15), Period (6), Branch code field (18 chars with branch code inside),
-            // След това: Line no + Doc type + Doc no
-            // Doc date (10), Contractor VAT (15), Contractor name (50), Operation description (50)
-            // Various amounts (11 fields x 13 chars each)
-
-            // Първата колона трябва да бъде ДДС номера на моята компания, не на контрагента
-            pokupki.push_str(&Self::format_text_field(company_vat, 15));
-            pokupki.push_str(&format!("{}{:02}", start_date.year(), start_date.month()));
-            pokupki.push_str(&format!("   {:<15}", "0")); // клон/обособено звено - 3 spaces + "0" + padding
-
-            // Номер на ред + тип документ + номер на документ (заедно в поле с дължина 18)
-            // Според спецификацията: line_no + doc_type(2) + doc_number
-            // Типът документ трябва да е двуцифрен с водеща нула (01, 02, 03)
-            let doc_type_formatted = format!("{:0>2}", document_type);
-            let line_doc_combined = format!("{}{}{}", line_no, &doc_type_formatted, &doc_number);
-            pokupki.push_str(&Self::format_text_field(&line_doc_combined, 18));
-
-            // Дата на документ (10 символа)
-            let formatted_date = doc_date.replace("-", "/");
-            pokupki.push_str(&Self::format_text_field(&formatted_date, 10));
-            pokupki.push_str(&Self::format_text_field(&contractor_vat, 15));
-            pokupki.push_str(&Self::format_text_field(&contractor_name, 50));
-            pokupki.push_str(&Self::format_text_field(&operation_description, 50)); // Описание на операцията
+        let purchases = documents
+            .iter()
+            .filter(|document| document.purchase_operation.is_some());
+        for (index, purchase) in purchases.enumerate() {
+            pokupki.push_str(&Self::journal_record_header(
+                company_vat,
+                period,
+                index + 1,
+                purchase,
+            ));
 
             // Числови полета - най-важните са net amount и vat amount
             pokupki.push_str(&Self::format_numeric_field(Decimal::ZERO, 13, 2)); // Поле 1
-            pokupki.push_str(&Self::format_numeric_field(net_amount, 13, 2)); // Поле 2 - основа
-            pokupki.push_str(&Self::format_numeric_field(vat_amount, 13, 2)); // Поле 3 - ДДС
+            pokupki.push_str(&Self::format_numeric_field(purchase.base_amount, 13, 2)); // Поле 2 - основа
+            pokupki.push_str(&Self::format_numeric_field(purchase.vat_amount, 13, 2)); // Поле 3 - ДДС
 
             // Останалите 8 полета като нули
             for _ in 0..8 {
@@ -325,98 +470,35 @@ impl NapExportService {
             }
 
             pokupki.push_str("\r\n");
-            line_no += 1;
         }
 
-        Ok(pokupki)
+        pokupki
     }
 
-    async fn generate_prodagbi(
-        db: &DatabaseConnection,
-        company_id: i32,
+    /// PRODAGBI.TXT: one record per document with a sales operation
+    fn generate_prodagbi(
         company_vat: &str,
-        start_date: NaiveDate,
-        end_date: NaiveDate,
-    ) -> Result<String> {
+        period: NaiveDate,
+        documents: &[VatJournalDocument],
+    ) -> String {
         let mut prodagbi = String::new();
 
-        // Get sales documents from journal entries with VAT
-        let sales = db
-            .query_all(Statement::from_sql_and_values(
-                DbBackend::Postgres,
-                r#"
-            SELECT 
-                je.document_number as doc_number,
-                TO_CHAR(je.document_date, 'YYYY-MM-DD') as doc_date,
-                COALESCE(cp.name, je.description, '') as contractor_name,
-                COALESCE(cp.vat_number, '') as contractor_vat,
-                je.total_amount as total_amount,
-                je.total_vat_amount as vat_amount,
-                (je.total_amount - je.total_vat_amount) as net_amount,
-                COALESCE(je.vat_document_type, '01') as document_type,
-                COALESCE(je.description, '') as operation_description
-            FROM journal_entries je
-            LEFT JOIN (
-                SELECT DISTINCT el.journal_entry_id, cp.name, cp.vat_number
-                FROM entry_lines el
-                JOIN counterparts cp ON el.counterpart_id = cp.id
-            ) cp ON je.id = cp.journal_entry_id
-            WHERE je.company_id = $1
-            AND je.document_date BETWEEN $2 AND $3
-            AND je.is_posted = true
-            AND je.vat_document_type = '01'  -- Sales invoices
-            ORDER BY je.document_date, je.document_number
-            "#,
-                vec![company_id.into(), start_date.into(), end_date.into()],
-            ))
-            .await?;
-
-        let mut line_no = 1;
-        for sale in sales {
-            let doc_number: String = sale.try_get("", "doc_number")?;
-            let doc_date: String = sale.try_get("", "doc_date")?;
-            let contractor_name: String = sale.try_get("", "contractor_name").unwrap_or_default();
-            let contractor_vat: String = sale.try_get("", "contractor_vat").unwrap_or_default();
-            let net_amount: Decimal = sale.try_get("", "net_amount")?;
-            let vat_amount: Decimal = sale.try_get("", "vat_amount")?;
-            let _total_amount: Decimal = sale.try_get("", "total_amount")?;
-            let document_type: String = sale
-                .try_get("", "document_type")
-                .unwrap_or("01".to_string());
-            let operation_description: String = sale
-                .try_get("", "operation_description")
-                .unwrap_or_default();
-
-            // Формат според НАП спецификацията за PRODAGBI.TXT с фиксирана дължина
-            // Същата структура като POKUPKI.TXT
-            // VAT number (15), Period (6), Branch code field (18 chars with branch code inside),
-            // След това: Line no + Doc type + Doc no
-            // Doc date (10), Contractor VAT (15), Contractor name (50), Operation description (50)
-
-            // Първата колона трябва да бъде ДДС номера на моята компания, не на контрагента
-            prodagbi.push_str(&Self::format_text_field(company_vat, 15));
-            prodagbi.push_str(&format!("{}{:02}", start_date.year(), start_date.month()));
-            prodagbi.push_str(&format!("   {:<15}", "0")); // клон/обособено звено - 3 spaces + "0" + padding
-
-            // Номер на ред + тип документ + номер на документ (заедно в поле с дължина 18)
-            // Според спецификацията: line_no + doc_type(2) + doc_number
-            // Типът документ трябва да е двуцифрен с водеща нула (01, 02, 03)
-            let doc_type_formatted = format!("{:0>2}", document_type);
-            let line_doc_combined = format!("{}{}{}", line_no, &doc_type_formatted, &doc_number);
-            prodagbi.push_str(&Self::format_text_field(&line_doc_combined, 18));
-
-            // Дата на документ (10 символа)
-            let formatted_date = doc_date.replace("-", "/");
-            prodagbi.push_str(&Self::format_text_field(&formatted_date, 10));
-            prodagbi.push_str(&Self::format_text_field(&contractor_vat, 15));
-            prodagbi.push_str(&Self::format_text_field(&contractor_name, 50));
-            prodagbi.push_str(&Self::format_text_field(&operation_description, 50)); // Описание на операцията
+        let sales = documents
+            .iter()
+            .filter(|document| document.sales_operation.is_some());
+        for (index, sale) in sales.enumerate() {
+            prodagbi.push_str(&Self::journal_record_header(
+                company_vat,
+                period,
+                index + 1,
+                sale,
+            ));
 
             // Числови полета - за продажби обикновено са net amount, vat amount
-            prodagbi.push_str(&Self::format_numeric_field(net_amount, 13, 2)); // Поле 1 - основа
-            prodagbi.push_str(&Self::format_numeric_field(vat_amount, 13, 2)); // Поле 2 - ДДС
-            prodagbi.push_str(&Self::format_numeric_field(net_amount, 13, 2)); // Поле 3 - основа (дублиране)
-            prodagbi.push_str(&Self::format_numeric_field(vat_amount, 13, 2)); // Поле 4 - ДДС (дублиране)
+            prodagbi.push_str(&Self::format_numeric_field(sale.base_amount, 13, 2)); // Поле 1 - основа
+            prodagbi.push_str(&Self::format_numeric_field(sale.vat_amount, 13, 2)); // Поле 2 - ДДС
+            prodagbi.push_str(&Self::format_numeric_field(sale.base_amount, 13, 2)); // Поле 3 - основа (дублиране)
+            prodagbi.push_str(&Self::format_numeric_field(sale.vat_amount, 13, 2)); // Поле 4 - ДДС (дублиране)
 
             // Останалите 13 полета като нули (17 общо полета)
             for _ in 0..13 {
@@ -424,10 +506,30 @@ impl NapExportService {
             }
 
             prodagbi.push_str("\r\n");
-            line_no += 1;
         }
 
-        Ok(prodagbi)
+        prodagbi
+    }
+}
+
+/// NAP files of a period as text
+#[derive(Debug, Clone)]
+pub struct NapFileTexts {
+    pub deklar: String,
+    pub pokupki: String,
+    pub prodagbi: String,
+    pub vies: Option<String>,
+}
+
+impl NapFileTexts {
+    /// Encode the files in Windows-1251 as NAP expects them
+    pub fn encode(&self) -> ViesFiles {
+        ViesFiles {
+            deklar: NapExportService::to_windows_1251(&self.deklar),
+            pokupki: NapExportService::to_windows_1251(&self.pokupki),
+            prodagbi: NapExportService::to_windows_1251(&self.prodagbi),
+            vies: self.vies.as_deref().map(NapExportService::to_windows_1251),
+        }
     }
 }
 
@@ -436,4 +538,175 @@ pub struct ViesFiles {
     pub deklar: Vec<u8>,
     pub pokupki: Vec<u8>,
     pub prodagbi: Vec<u8>,
+    /// VIES.TXT; only produced when the period has intra-community supplies
+    pub vies: Option<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn vies_rows_are_summed_per_customer_and_row_type() {
+        let rows = NapExportService::aggregate_vies_rows(vec![
+            (
+                "DE123456789".to_string(),
+                "про20".to_string(),
+                dec!(1000.40),
+            ),
+            ("de 123456789".to_string(), "про22".to_string(), dec!(250)),
+            ("AT U12345678".to_string(), "про25".to_string(), dec!(99.50)),
+            ("DE123456789".to_string(), "про20".to_string(), dec!(500)),
+        ]);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].vat_number, "ATU12345678");
+        assert_eq!(rows[0].triangular, dec!(99.50));
+        assert_eq!(rows[1].goods, dec!(1500.40));
+        assert_eq!(rows[1].services, dec!(250));
+
+        let declarant = ViesDeclarant::from_company(
+            "BG123456789",
+            "Фирма ООД",
+            Some("ул. Витоша 1".to_string()),
+            Some("1000 София".to_string()),
+            (
+                Some("Иван Петров".to_string()),
+                Some("8001011234".to_string()),
+            ),
+            (None, None),
+        )
+        .unwrap();
+        assert_eq!(declarant.post_code, "1000");
+        assert_eq!(declarant.city, "София");
+        assert_eq!(declarant.person_capacity, 'A');
+
+        let vies = NapExportService::generate_vies(
+            &declarant,
+            NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            &rows,
+        );
+        let lines: Vec<&str> = vies.split("\r\n").collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "VHR032025    2");
+        assert!(lines[1].starts_with("VDR8001011234Иван Петров"));
+        assert!(lines[1].ends_with('A'));
+        assert_eq!(lines[1].chars().count(), 368);
+        assert!(lines[2].starts_with("VTRBG123456789    Фирма ООД"));
+        assert_eq!(lines[2].chars().count(), 368);
+        assert_eq!(
+            lines[3],
+            format!("TTR{:>15}{:>15}{:>15}{:>15}", 1850, 1500, 100, 250)
+        );
+    }
+
+    #[test]
+    fn journals_and_vies_are_built_from_the_same_documents() {
+        let document = |id: i32, sales: Option<&str>, purchase: Option<&str>, vat: Option<&str>| {
+            VatJournalDocument {
+                journal_entry_id: id,
+                entry_number: format!("JE-2025-{:06}", id),
+                document_number: Some(format!("{:010}", id)),
+                document_date: NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(),
+                document_type: Some("01".to_string()),
+                description: "Доставка".to_string(),
+                counterpart_name: Some("Клиент".to_string()),
+                counterpart_vat_number: vat.map(str::to_string),
+                sales_operation: sales.map(str::to_string),
+                purchase_operation: purchase.map(str::to_string),
+                base_amount: dec!(100),
+                vat_amount: if sales == Some("про20") {
+                    Decimal::ZERO
+                } else {
+                    dec!(20)
+                },
+            }
+        };
+        let documents = vec![
+            document(1, Some("про11"), None, Some("BG111111111")),
+            document(2, Some("про20"), None, Some("DE123456789")),
+            document(3, None, Some("пок10"), Some("BG222222222")),
+            // ВОП: in both journals
+            document(4, Some("про12"), Some("пок10"), Some("AT U12345678")),
+        ];
+        let period = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+
+        let prodagbi = NapExportService::generate_prodagbi("BG123456789", period, &documents);
+        let pokupki = NapExportService::generate_pokupki("BG123456789", period, &documents);
+        assert_eq!(prodagbi.matches("\r\n").count(), 3);
+        assert_eq!(pokupki.matches("\r\n").count(), 2);
+        assert!(pokupki.contains("2025/03/10"));
+
+        let deklar =
+            NapExportService::generate_deklar("Фирма ООД", "BG123456789", period, &documents);
+        assert!(deklar.contains(&format!("{:>13}{:>13}", "300.00", "40.00")));
+        assert!(deklar.contains(&format!("{:>13}{:>13}", "200.00", "40.00")));
+
+        let vies = NapExportService::vies_rows(&documents).unwrap();
+        assert_eq!(vies.len(), 1);
+        assert_eq!(vies[0].goods, dec!(100));
+    }
+
+    #[test]
+    fn vies_customer_record_matches_the_nap_layout() {
+        // VIR record of the NAP VIES.TXT layout: row number (5), customer VAT
+        // number (15), ВОД (15), triangular (15), services (15), corrected
+        // period (6)
+        let rows = vec![ViesRow {
+            vat_number: "DE123456789".to_string(),
+            goods: dec!(1500.40),
+            triangular: Decimal::ZERO,
+            services: dec!(249.50),
+        }];
+        let declarant = ViesDeclarant {
+            vat_number: "BG123456789".to_string(),
+            person_capacity: 'B',
+            ..Default::default()
+        };
+
+        let vies = NapExportService::generate_vies(
+            &declarant,
+            NaiveDate::from_ymd_opt(2025, 11, 1).unwrap(),
+            &rows,
+        );
+        let lines: Vec<&str> = vies.split("\r\n").collect();
+        assert_eq!(lines[0], "VHR112025    1");
+        assert_eq!(
+            lines[4],
+            "VIR    1DE123456789               1500              0            250      "
+        );
+    }
+
+    #[test]
+    fn vies_declarant_requires_a_filing_person() {
+        let missing_egn = ViesDeclarant::from_company(
+            "BG123456789",
+            "Фирма ООД",
+            None,
+            None,
+            (Some("Иван Петров".to_string()), None),
+            (None, None),
+        );
+        assert!(missing_egn.is_err());
+
+        let authorized = ViesDeclarant::from_company(
+            "BG123456789",
+            "Фирма ООД",
+            None,
+            Some("Пловдив".to_string()),
+            (
+                Some("Иван Петров".to_string()),
+                Some("8001011234".to_string()),
+            ),
+            (
+                Some("Мария Георгиева".to_string()),
+                Some("8502023456".to_string()),
+            ),
+        )
+        .unwrap();
+        assert_eq!(authorized.person_name, "Мария Георгиева");
+        assert_eq!(authorized.person_capacity, 'B');
+        assert_eq!(authorized.post_code, "");
+    }
 }
@@ -0,0 +1,955 @@
+//! Payment Orders Service
+//!
+//! Builds batches of credit transfers from the open supplier balances on
+//! account 401 and exports them as ISO 20022 pain.001.001.03 files for the
+//! bank of a bank profile. Budget batches (NAP, NOI) carry the Bulgarian
+//! budget payment details: the payment type code, the document the
+//! obligation is based on, the period and the liable person.
+//!
+//! After the bank statement is imported, the batch is matched against the
+//! outgoing transactions of the profile by amount and creditor IBAN or
+//! EndToEndId, and matched supplier payments are reconciled with the
+//! invoices they pay.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::Writer;
+use rust_decimal::{Decimal, RoundingStrategy};
+use sea_orm::*;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+use crate::entities::bank_reconciliation_match::{
+    BankReconciliationAllocationInput, ConfirmBankReconciliationInput,
+};
+use crate::entities::payment_batch::{
+    CreatePaymentBatchInput, PaymentBatchKind, PaymentBatchStatus,
+};
+use crate::entities::{
+    account, bank_profile, bank_transaction_fingerprint, company, counterpart, entry_line,
+    journal_entry, payment_batch, payment_order,
+};
+use crate::services::bank_reconciliation::{
+    BankReconciliationService, OpenInvoice, PAYABLES_ACCOUNT_CODE,
+};
+use crate::services::euro_changeover::EuroChangeoverService;
+
+const PAIN_001_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:pain.001.001.03";
+/// Max length of names in pain.001
+const MAX_NAME_LENGTH: usize = 70;
+/// Max length of unstructured remittance information in pain.001
+const MAX_REMITTANCE_LENGTH: usize = 140;
+
+pub struct PaymentOrderService;
+
+/// Open balance of a supplier on the payables accounts
+#[derive(SimpleObject, Clone, Debug)]
+pub struct SupplierBalance {
+    pub counterpart_id: i32,
+    pub counterpart_name: String,
+    pub counterpart_eik: Option<String>,
+    /// Credit balance on the 401 accounts
+    pub balance: Decimal,
+    /// Already in draft or exported batches that are not matched yet
+    pub in_batches: Decimal,
+    pub payable: Decimal,
+    /// IBAN of earlier payments to the supplier
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+    pub invoices: Vec<OpenInvoice>,
+}
+
+/// Generated pain.001 file
+#[derive(SimpleObject, Clone, Debug)]
+pub struct PaymentFile {
+    pub file_name: String,
+    pub content: String,
+}
+
+impl PaymentOrderService {
+    /// Suppliers with a credit balance on account 401, largest first
+    pub async fn supplier_balances<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+    ) -> Result<Vec<SupplierBalance>> {
+        let account_ids: Vec<i32> = account::Entity::find()
+            .filter(account::Column::CompanyId.eq(company_id))
+            .filter(account::Column::Code.starts_with(PAYABLES_ACCOUNT_CODE))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|account| account.id)
+            .collect();
+
+        let lines = entry_line::Entity::find()
+            .inner_join(journal_entry::Entity)
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .filter(entry_line::Column::AccountId.is_in(account_ids))
+            .filter(entry_line::Column::CounterpartId.is_not_null())
+            .all(db)
+            .await?;
+        let mut balances: HashMap<i32, Decimal> = HashMap::new();
+        for line in lines {
+            if let Some(counterpart_id) = line.counterpart_id {
+                *balances.entry(counterpart_id).or_default() +=
+                    line.credit_amount - line.debit_amount;
+            }
+        }
+        balances.retain(|_, balance| *balance > Decimal::ZERO);
+
+        let pending = Self::pending_amounts(db, company_id).await?;
+        let accounts = Self::creditor_accounts(db, company_id).await?;
+        let mut invoices: HashMap<i32, Vec<OpenInvoice>> = HashMap::new();
+        for invoice in BankReconciliationService::open_invoices(db, company_id, None).await? {
+            if !invoice.is_receivable {
+                invoices
+                    .entry(invoice.counterpart_id)
+                    .or_default()
+                    .push(invoice);
+            }
+        }
+        let counterparts: HashMap<i32, counterpart::Model> = counterpart::Entity::find()
+            .filter(counterpart::Column::Id.is_in(balances.keys().copied()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|counterpart| (counterpart.id, counterpart))
+            .collect();
+
+        let mut result: Vec<SupplierBalance> = balances
+            .into_iter()
+            .filter_map(|(counterpart_id, balance)| {
+                let counterpart = counterparts.get(&counterpart_id)?;
+                let in_batches = pending.get(&counterpart_id).copied().unwrap_or_default();
+                let (iban, bic) = accounts
+                    .get(&counterpart_id)
+                    .cloned()
+                    .map_or((None, None), |(iban, bic)| (Some(iban), bic));
+                Some(SupplierBalance {
+                    counterpart_id,
+                    counterpart_name: counterpart.name.clone(),
+                    counterpart_eik: counterpart.eik.clone(),
+                    balance,
+                    in_batches,
+                    payable: (balance - in_batches).max(Decimal::ZERO),
+                    iban,
+                    bic,
+                    invoices: invoices.remove(&counterpart_id).unwrap_or_default(),
+                })
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            b.payable
+                .cmp(&a.payable)
+                .then(a.counterpart_name.cmp(&b.counterpart_name))
+        });
+
+        Ok(result)
+    }
+
+    pub async fn create_batch<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        input: &CreatePaymentBatchInput,
+        user_id: i32,
+    ) -> Result<(payment_batch::Model, Vec<payment_order::Model>)> {
+        if input.orders.is_empty() {
+            bail!("The payment batch has no orders");
+        }
+        if input
+            .orders
+            .iter()
+            .any(|order| order.amount <= Decimal::ZERO)
+        {
+            bail!("Payment amounts must be positive");
+        }
+
+        let profile = bank_profile::Entity::find_by_id(input.bank_profile_id)
+            .filter(bank_profile::Column::CompanyId.eq(input.company_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Bank profile not found"))?;
+        match profile.iban.as_deref() {
+            Some(iban) if is_valid_iban(iban) => {}
+            _ => bail!("The bank profile {} has no valid IBAN", profile.name),
+        }
+
+        let suppliers: HashMap<i32, SupplierBalance> =
+            Self::supplier_balances(db, input.company_id)
+                .await?
+                .into_iter()
+                .map(|supplier| (supplier.counterpart_id, supplier))
+                .collect();
+        let accounts = Self::creditor_accounts(db, input.company_id).await?;
+        let counterpart_ids: HashSet<i32> = input
+            .orders
+            .iter()
+            .filter_map(|order| order.counterpart_id)
+            .collect();
+        let counterparts: HashMap<i32, counterpart::Model> = counterpart::Entity::find()
+            .filter(counterpart::Column::CompanyId.eq(input.company_id))
+            .filter(counterpart::Column::Id.is_in(counterpart_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|counterpart| (counterpart.id, counterpart))
+            .collect();
+
+        let mut requested: HashMap<i32, Decimal> = HashMap::new();
+        let now = Utc::now();
+        let message_id = format!("PAY{}{}", input.company_id, now.format("%Y%m%d%H%M%S%3f"));
+        let mut orders = Vec::with_capacity(input.orders.len());
+
+        for (index, order) in input.orders.iter().enumerate() {
+            let line_number = index as i32 + 1;
+            let counterpart = match order.counterpart_id {
+                Some(counterpart_id) => Some(
+                    counterparts
+                        .get(&counterpart_id)
+                        .ok_or_else(|| anyhow!("Counterpart {} not found", counterpart_id))?,
+                ),
+                None => None,
+            };
+
+            if input.kind == PaymentBatchKind::Supplier {
+                let counterpart = counterpart
+                    .ok_or_else(|| anyhow!("Order {} has no supplier to pay", line_number))?;
+                let total = requested.entry(counterpart.id).or_default();
+                *total += order.amount;
+                let payable = suppliers
+                    .get(&counterpart.id)
+                    .map(|supplier| supplier.payable)
+                    .unwrap_or_default();
+                if *total > payable {
+                    bail!(
+                        "Payments of {} to {} exceed the open balance of {}",
+                        total,
+                        counterpart.name,
+                        payable
+                    );
+                }
+            } else {
+                let payment_type = order.budget_payment_type.as_deref().unwrap_or_default();
+                if payment_type.len() != 6 || !payment_type.chars().all(|c| c.is_ascii_digit()) {
+                    bail!(
+                        "Order {} needs a six-digit budget payment type code",
+                        line_number
+                    );
+                }
+            }
+
+            let known_account = order.counterpart_id.and_then(|id| accounts.get(&id));
+            let creditor_iban = order
+                .creditor_iban
+                .as_deref()
+                .map(normalize_iban)
+                .or_else(|| known_account.map(|(iban, _)| iban.clone()))
+                .ok_or_else(|| anyhow!("Order {} has no creditor IBAN", line_number))?;
+            if !is_valid_iban(&creditor_iban) {
+                bail!("Invalid creditor IBAN {}", creditor_iban);
+            }
+            let creditor_bic = order
+                .creditor_bic
+                .as_deref()
+                .map(|bic| bic.trim().to_uppercase())
+                .filter(|bic| !bic.is_empty())
+                .or_else(|| known_account.and_then(|(_, bic)| bic.clone()));
+            if let Some(bic) = &creditor_bic {
+                if bic.len() != 8 && bic.len() != 11 {
+                    bail!("Invalid creditor BIC {}", bic);
+                }
+            }
+            let creditor_name = order
+                .creditor_name
+                .as_deref()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .or_else(|| counterpart.map(|counterpart| counterpart.name.clone()))
+                .ok_or_else(|| anyhow!("Order {} has no creditor name", line_number))?;
+
+            let invoice_number = order.invoice_entry_id.and_then(|invoice_entry_id| {
+                suppliers
+                    .values()
+                    .flat_map(|supplier| supplier.invoices.iter())
+                    .find(|invoice| invoice.invoice_entry_id == invoice_entry_id)
+                    .and_then(|invoice| invoice.document_number.clone())
+            });
+            let remittance_info = order
+                .remittance_info
+                .as_deref()
+                .map(str::trim)
+                .filter(|info| !info.is_empty())
+                .map(str::to_string)
+                .or_else(|| invoice_number.map(|number| format!("Плащане по фактура {}", number)))
+                .unwrap_or_else(|| "Плащане".to_string());
+
+            orders.push(payment_order::ActiveModel {
+                line_number: Set(line_number),
+                counterpart_id: Set(order.counterpart_id),
+                invoice_entry_id: Set(order.invoice_entry_id),
+                creditor_name: Set(truncate(&creditor_name, MAX_NAME_LENGTH)),
+                creditor_iban: Set(creditor_iban),
+                creditor_bic: Set(creditor_bic),
+                amount: Set(order
+                    .amount
+                    .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)),
+                remittance_info: Set(truncate(&remittance_info, MAX_REMITTANCE_LENGTH)),
+                end_to_end_id: Set(format!("{}-{}", message_id, line_number)),
+                budget_payment_type: Set(order.budget_payment_type.clone()),
+                budget_document_type: Set(order.budget_document_type.clone()),
+                budget_document_number: Set(order.budget_document_number.clone()),
+                budget_document_date: Set(order.budget_document_date),
+                budget_period_from: Set(order.budget_period_from),
+                budget_period_to: Set(order.budget_period_to),
+                ..Default::default()
+            });
+        }
+
+        let total_amount: Decimal = orders
+            .iter()
+            .map(|order| order.amount.clone().unwrap())
+            .sum();
+
+        let txn = db.begin().await?;
+        let batch = payment_batch::ActiveModel {
+            company_id: Set(input.company_id),
+            bank_profile_id: Set(profile.id),
+            kind: Set(input.kind),
+            status: Set(PaymentBatchStatus::Draft),
+            message_id: Set(message_id),
+            execution_date: Set(input.execution_date),
+            currency_code: Set(profile.currency_code.clone()),
+            total_amount: Set(total_amount),
+            exported_at: Set(None),
+            created_by: Set(Some(user_id)),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let mut inserted = Vec::with_capacity(orders.len());
+        for mut order in orders {
+            order.batch_id = Set(batch.id);
+            inserted.push(order.insert(&txn).await?);
+        }
+        txn.commit().await?;
+
+        Ok((batch, inserted))
+    }
+
+    pub async fn batch<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        batch_id: i32,
+    ) -> Result<(payment_batch::Model, Vec<payment_order::Model>)> {
+        let batch = payment_batch::Entity::find_by_id(batch_id)
+            .filter(payment_batch::Column::CompanyId.eq(company_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Payment batch not found"))?;
+        let orders = payment_order::Entity::find()
+            .filter(payment_order::Column::BatchId.eq(batch.id))
+            .order_by_asc(payment_order::Column::LineNumber)
+            .all(db)
+            .await?;
+        Ok((batch, orders))
+    }
+
+    /// Only draft batches can be deleted; exported ones may already be at
+    /// the bank
+    pub async fn delete_batch<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        batch_id: i32,
+    ) -> Result<payment_batch::Model> {
+        let (batch, _) = Self::batch(db, company_id, batch_id).await?;
+        if batch.status != PaymentBatchStatus::Draft {
+            bail!("Only draft payment batches can be deleted");
+        }
+        payment_batch::Entity::delete_by_id(batch.id)
+            .exec(db)
+            .await?;
+        Ok(batch)
+    }
+
+    /// Generate the pain.001 file and mark the batch exported. Exporting
+    /// again returns the same file with the same MsgId, so the bank rejects
+    /// it as a duplicate.
+    pub async fn export<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        batch_id: i32,
+    ) -> Result<(payment_batch::Model, PaymentFile)> {
+        let (batch, orders) = Self::batch(db, company_id, batch_id).await?;
+        let profile = bank_profile::Entity::find_by_id(batch.bank_profile_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Bank profile not found"))?;
+        let company = company::Entity::find_by_id(company_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Company not found"))?;
+
+        let batch = if batch.status == PaymentBatchStatus::Draft {
+            let now = Utc::now();
+            let mut active: payment_batch::ActiveModel = batch.into();
+            active.status = Set(PaymentBatchStatus::Exported);
+            active.exported_at = Set(Some(now));
+            active.updated_at = Set(now);
+            active.update(db).await?
+        } else {
+            batch
+        };
+        let created_at = batch.exported_at.unwrap_or(batch.created_at);
+        let content = Self::generate_pain001(&company, &profile, &batch, &orders, created_at)?;
+
+        Ok((
+            batch.clone(),
+            PaymentFile {
+                file_name: format!("{}.xml", batch.message_id),
+                content,
+            },
+        ))
+    }
+
+    /// Match the orders of an exported batch with the outgoing transactions
+    /// of the imported statements and reconcile the supplier payments
+    pub async fn match_statement<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        batch_id: i32,
+        user_id: i32,
+    ) -> Result<(payment_batch::Model, Vec<payment_order::Model>)> {
+        let (batch, orders) = Self::batch(db, company_id, batch_id).await?;
+        if batch.status == PaymentBatchStatus::Draft {
+            bail!("The payment batch has not been exported yet");
+        }
+
+        let mut used: HashSet<i32> = payment_order::Entity::find()
+            .inner_join(payment_batch::Entity)
+            .filter(payment_batch::Column::BankProfileId.eq(batch.bank_profile_id))
+            .filter(payment_order::Column::BankEntryId.is_not_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|order| order.bank_entry_id)
+            .collect();
+        let fingerprints = bank_transaction_fingerprint::Entity::find()
+            .filter(bank_transaction_fingerprint::Column::BankProfileId.eq(batch.bank_profile_id))
+            .filter(bank_transaction_fingerprint::Column::Amount.lt(Decimal::ZERO))
+            .filter(
+                bank_transaction_fingerprint::Column::BookingDate
+                    .gte(batch.created_at.date_naive()),
+            )
+            .order_by_asc(bank_transaction_fingerprint::Column::BookingDate)
+            .all(db)
+            .await?;
+        let descriptions: HashMap<i32, String> = journal_entry::Entity::find()
+            .filter(
+                journal_entry::Column::Id.is_in(
+                    fingerprints
+                        .iter()
+                        .map(|fingerprint| fingerprint.journal_entry_id),
+                ),
+            )
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.id, entry.description))
+            .collect();
+
+        // Allocations are in the base currency; payments in another
+        // currency are only linked and left for manual reconciliation
+        let base_currency =
+            EuroChangeoverService::base_currency_code(db, company_id, batch.execution_date).await?;
+        let reconcile =
+            batch.kind == PaymentBatchKind::Supplier && batch.currency_code == base_currency;
+
+        let mut result = Vec::with_capacity(orders.len());
+        for order in orders {
+            if order.bank_entry_id.is_some() {
+                result.push(order);
+                continue;
+            }
+            let found = fingerprints.iter().find(|fingerprint| {
+                if used.contains(&fingerprint.journal_entry_id)
+                    || -fingerprint.amount != order.amount
+                {
+                    return false;
+                }
+                let same_iban = fingerprint
+                    .counterparty_iban
+                    .as_deref()
+                    .is_some_and(|iban| normalize_iban(iban) == order.creditor_iban);
+                let mentions_id = fingerprint
+                    .reference
+                    .iter()
+                    .chain(descriptions.get(&fingerprint.journal_entry_id))
+                    .any(|text| text.contains(&order.end_to_end_id));
+                same_iban || mentions_id
+            });
+            let Some(fingerprint) = found else {
+                result.push(order);
+                continue;
+            };
+            used.insert(fingerprint.journal_entry_id);
+
+            if reconcile {
+                if let Some(counterpart_id) = order.counterpart_id {
+                    let input = ConfirmBankReconciliationInput {
+                        company_id,
+                        bank_entry_id: fingerprint.journal_entry_id,
+                        allocations: vec![BankReconciliationAllocationInput {
+                            invoice_entry_id: order.invoice_entry_id,
+                            counterpart_id: Some(counterpart_id),
+                            amount: order.amount,
+                        }],
+                    };
+                    // A bank rule or a manual reconciliation may already
+                    // have booked the transaction
+                    if let Err(err) = BankReconciliationService::confirm(db, &input, user_id).await
+                    {
+                        tracing::warn!(
+                            "Payment order {} matched bank entry {} without reconciliation: {}",
+                            order.end_to_end_id,
+                            fingerprint.journal_entry_id,
+                            err
+                        );
+                    }
+                }
+            }
+
+            let mut active: payment_order::ActiveModel = order.into();
+            active.bank_entry_id = Set(Some(fingerprint.journal_entry_id));
+            active.matched_at = Set(Some(Utc::now()));
+            result.push(active.update(db).await?);
+        }
+
+        let batch = if result.iter().all(|order| order.bank_entry_id.is_some()) {
+            let mut active: payment_batch::ActiveModel = batch.into();
+            active.status = Set(PaymentBatchStatus::Matched);
+            active.updated_at = Set(Utc::now());
+            active.update(db).await?
+        } else {
+            batch
+        };
+
+        Ok((batch, result))
+    }
+
+    /// pain.001.001.03 customer credit transfer initiation with one payment
+    /// information block for the batch
+    pub fn generate_pain001(
+        company: &company::Model,
+        profile: &bank_profile::Model,
+        batch: &payment_batch::Model,
+        orders: &[payment_order::Model],
+        created_at: DateTime<Utc>,
+    ) -> Result<String> {
+        let debtor_iban = profile
+            .iban
+            .as_deref()
+            .map(normalize_iban)
+            .ok_or_else(|| anyhow!("The bank profile {} has no IBAN", profile.name))?;
+        let is_sepa = batch.kind == PaymentBatchKind::Supplier && batch.currency_code == "EUR";
+        let control_sum = format_amount(orders.iter().map(|order| order.amount).sum());
+        let transactions = orders.len().to_string();
+
+        let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        let mut document = BytesStart::new("Document");
+        document.push_attribute(("xmlns", PAIN_001_NAMESPACE));
+        document.push_attribute(("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"));
+        writer.write_event(Event::Start(document))?;
+        start(&mut writer, "CstmrCdtTrfInitn")?;
+
+        start(&mut writer, "GrpHdr")?;
+        write_element(&mut writer, "MsgId", &batch.message_id)?;
+        write_element(
+            &mut writer,
+            "CreDtTm",
+            &created_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        )?;
+        write_element(&mut writer, "NbOfTxs", &transactions)?;
+        write_element(&mut writer, "CtrlSum", &control_sum)?;
+        write_party(&mut writer, "InitgPty", &company.name, Some(&company.eik))?;
+        end(&mut writer, "GrpHdr")?;
+
+        start(&mut writer, "PmtInf")?;
+        write_element(&mut writer, "PmtInfId", &batch.message_id)?;
+        write_element(&mut writer, "PmtMtd", "TRF")?;
+        // Every order is booked separately, so the statement can be matched
+        write_element(&mut writer, "BtchBookg", "false")?;
+        write_element(&mut writer, "NbOfTxs", &transactions)?;
+        write_element(&mut writer, "CtrlSum", &control_sum)?;
+        let category_purpose = match batch.kind {
+            PaymentBatchKind::Supplier => None,
+            PaymentBatchKind::Nap => Some("TAXS"),
+            PaymentBatchKind::Noi => Some("GOVT"),
+        };
+        if is_sepa || category_purpose.is_some() {
+            start(&mut writer, "PmtTpInf")?;
+            if is_sepa {
+                start(&mut writer, "SvcLvl")?;
+                write_element(&mut writer, "Cd", "SEPA")?;
+                end(&mut writer, "SvcLvl")?;
+            }
+            if let Some(code) = category_purpose {
+                start(&mut writer, "CtgyPurp")?;
+                write_element(&mut writer, "Cd", code)?;
+                end(&mut writer, "CtgyPurp")?;
+            }
+            end(&mut writer, "PmtTpInf")?;
+        }
+        write_element(
+            &mut writer,
+            "ReqdExctnDt",
+            &batch.execution_date.format("%Y-%m-%d").to_string(),
+        )?;
+        write_party(&mut writer, "Dbtr", &company.name, Some(&company.eik))?;
+        start(&mut writer, "DbtrAcct")?;
+        write_iban(&mut writer, &debtor_iban)?;
+        write_element(&mut writer, "Ccy", &batch.currency_code)?;
+        end(&mut writer, "DbtrAcct")?;
+        start(&mut writer, "DbtrAgt")?;
+        start(&mut writer, "FinInstnId")?;
+        match profile.bic.as_deref().filter(|bic| !bic.is_empty()) {
+            Some(bic) => write_element(&mut writer, "BIC", bic)?,
+            None => {
+                start(&mut writer, "Othr")?;
+                write_element(&mut writer, "Id", "NOTPROVIDED")?;
+                end(&mut writer, "Othr")?;
+            }
+        }
+        end(&mut writer, "FinInstnId")?;
+        end(&mut writer, "DbtrAgt")?;
+        write_element(&mut writer, "ChrgBr", if is_sepa { "SLEV" } else { "SHAR" })?;
+
+        for order in orders {
+            start(&mut writer, "CdtTrfTxInf")?;
+            start(&mut writer, "PmtId")?;
+            write_element(&mut writer, "EndToEndId", &order.end_to_end_id)?;
+            end(&mut writer, "PmtId")?;
+            start(&mut writer, "Amt")?;
+            let mut amount = BytesStart::new("InstdAmt");
+            amount.push_attribute(("Ccy", batch.currency_code.as_str()));
+            writer.write_event(Event::Start(amount))?;
+            writer.write_event(Event::Text(BytesText::new(&format_amount(order.amount))))?;
+            end(&mut writer, "InstdAmt")?;
+            end(&mut writer, "Amt")?;
+            if batch.kind.is_budget() {
+                // The liable person of the budget payment
+                write_party(&mut writer, "UltmtDbtr", &company.name, Some(&company.eik))?;
+            }
+            if let Some(bic) = order.creditor_bic.as_deref() {
+                start(&mut writer, "CdtrAgt")?;
+                start(&mut writer, "FinInstnId")?;
+                write_element(&mut writer, "BIC", bic)?;
+                end(&mut writer, "FinInstnId")?;
+                end(&mut writer, "CdtrAgt")?;
+            }
+            write_party(&mut writer, "Cdtr", &order.creditor_name, None)?;
+            start(&mut writer, "CdtrAcct")?;
+            write_iban(&mut writer, &order.creditor_iban)?;
+            end(&mut writer, "CdtrAcct")?;
+            start(&mut writer, "RmtInf")?;
+            write_element(&mut writer, "Ustrd", &order.remittance_info)?;
+            if batch.kind.is_budget() {
+                write_budget_details(&mut writer, order)?;
+            }
+            end(&mut writer, "RmtInf")?;
+            end(&mut writer, "CdtTrfTxInf")?;
+        }
+
+        end(&mut writer, "PmtInf")?;
+        end(&mut writer, "CstmrCdtTrfInitn")?;
+        end(&mut writer, "Document")?;
+
+        let result = writer.into_inner().into_inner();
+        Ok(String::from_utf8(result)?)
+    }
+
+    /// Amounts per counterpart in draft or exported orders not matched yet
+    async fn pending_amounts<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+    ) -> Result<HashMap<i32, Decimal>> {
+        let orders = payment_order::Entity::find()
+            .inner_join(payment_batch::Entity)
+            .filter(payment_batch::Column::CompanyId.eq(company_id))
+            .filter(payment_batch::Column::Kind.eq(PaymentBatchKind::Supplier))
+            .filter(payment_batch::Column::Status.ne(PaymentBatchStatus::Matched))
+            .filter(payment_order::Column::BankEntryId.is_null())
+            .all(db)
+            .await?;
+        let mut pending: HashMap<i32, Decimal> = HashMap::new();
+        for order in orders {
+            if let Some(counterpart_id) = order.counterpart_id {
+                *pending.entry(counterpart_id).or_default() += order.amount;
+            }
+        }
+        Ok(pending)
+    }
+
+    /// IBAN and BIC per counterpart from the latest payment order, falling
+    /// back to the counterparty IBANs learned by bank reconciliation
+    async fn creditor_accounts<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+    ) -> Result<HashMap<i32, (String, Option<String>)>> {
+        let mut accounts: HashMap<i32, (String, Option<String>)> = HashMap::new();
+        for (iban, counterpart_id) in BankReconciliationService::known_ibans(db, company_id).await?
+        {
+            accounts.insert(counterpart_id, (normalize_iban(&iban), None));
+        }
+
+        let orders = payment_order::Entity::find()
+            .inner_join(payment_batch::Entity)
+            .filter(payment_batch::Column::CompanyId.eq(company_id))
+            .filter(payment_order::Column::CounterpartId.is_not_null())
+            .order_by_asc(payment_order::Column::Id)
+            .all(db)
+            .await?;
+        for order in orders {
+            if let Some(counterpart_id) = order.counterpart_id {
+                accounts.insert(counterpart_id, (order.creditor_iban, order.creditor_bic));
+            }
+        }
+        Ok(accounts)
+    }
+}
+
+pub fn normalize_iban(iban: &str) -> String {
+    iban.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// ISO 13616 check: country code, check digits and mod 97 of the
+/// rearranged IBAN equal to 1
+pub fn is_valid_iban(iban: &str) -> bool {
+    let iban = normalize_iban(iban);
+    if !(15..=34).contains(&iban.len()) || !iban.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let (head, tail) = iban.split_at(4);
+    if !head[..2].chars().all(|c| c.is_ascii_alphabetic())
+        || !head[2..].chars().all(|c| c.is_ascii_digit())
+    {
+        return false;
+    }
+
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let value = c.to_digit(36).unwrap_or_default();
+        let base = if value >= 10 { 100 } else { 10 };
+        remainder = (remainder * base + value) % 97;
+    }
+    remainder == 1
+}
+
+fn format_amount(amount: Decimal) -> String {
+    format!(
+        "{:.2}",
+        amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+    )
+}
+
+fn truncate(value: &str, max: usize) -> String {
+    value.chars().take(max).collect()
+}
+
+fn start(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(name)))?;
+    Ok(())
+}
+
+fn end(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str) -> Result<()> {
+    writer.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+fn write_element(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, value: &str) -> Result<()> {
+    start(writer, name)?;
+    writer.write_event(Event::Text(BytesText::new(value)))?;
+    end(writer, name)
+}
+
+fn write_party(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    tag: &str,
+    name: &str,
+    eik: Option<&str>,
+) -> Result<()> {
+    start(writer, tag)?;
+    write_element(writer, "Nm", &truncate(name, MAX_NAME_LENGTH))?;
+    if let Some(eik) = eik.filter(|eik| !eik.is_empty()) {
+        start(writer, "Id")?;
+        start(writer, "OrgId")?;
+        start(writer, "Othr")?;
+        write_element(writer, "Id", eik)?;
+        end(writer, "Othr")?;
+        end(writer, "OrgId")?;
+        end(writer, "Id")?;
+    }
+    end(writer, tag)
+}
+
+fn write_iban(writer: &mut Writer<Cursor<Vec<u8>>>, iban: &str) -> Result<()> {
+    start(writer, "Id")?;
+    write_element(writer, "IBAN", iban)?;
+    end(writer, "Id")
+}
+
+/// Budget payment details of the Bulgarian payment order to the budget:
+/// the payment type code as creditor reference type, the document as
+/// `type/number/DDMMYYYY` and the period as additional information
+fn write_budget_details(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    order: &payment_order::Model,
+) -> Result<()> {
+    start(writer, "Strd")?;
+    start(writer, "CdtrRefInf")?;
+    start(writer, "Tp")?;
+    start(writer, "CdOrPrtry")?;
+    write_element(
+        writer,
+        "Prtry",
+        order.budget_payment_type.as_deref().unwrap_or_default(),
+    )?;
+    end(writer, "CdOrPrtry")?;
+    end(writer, "Tp")?;
+    let document = [
+        order.budget_document_type.clone().unwrap_or_default(),
+        order.budget_document_number.clone().unwrap_or_default(),
+        order
+            .budget_document_date
+            .map(|date| date.format("%d%m%Y").to_string())
+            .unwrap_or_default(),
+    ];
+    if document.iter().any(|part| !part.is_empty()) {
+        write_element(writer, "Ref", &document.join("/"))?;
+    }
+    end(writer, "CdtrRefInf")?;
+    if let (Some(from), Some(to)) = (order.budget_period_from, order.budget_period_to) {
+        write_element(
+            writer,
+            "AddtlRmtInf",
+            &format!("{}-{}", from.format("%d%m%Y"), to.format("%d%m%Y")),
+        )?;
+    }
+    end(writer, "Strd")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn pain001_budget_batch_carries_payment_type_and_liable_person() {
+        assert!(is_valid_iban("BG80 BNBG 9661 1020 3456 78"));
+        assert!(!is_valid_iban("BG81BNBG96611020345678"));
+
+        let created_at = Utc.with_ymd_and_hms(2025, 11, 14, 9, 30, 0).unwrap();
+        let company = company::Model {
+            id: 1,
+            name: "Тест ООД".to_string(),
+            eik: "123456789".to_string(),
+            vat_number: None,
+            address: None,
+            city: None,
+            country: None,
+            phone: None,
+            email: None,
+            contact_person: None,
+            manager_name: None,
+            authorized_person: None,
+            manager_egn: None,
+            authorized_person_egn: None,
+            is_active: true,
+            contragent_api_url: None,
+            contragent_api_key: None,
+            enable_vies_validation: false,
+            enable_ai_mapping: false,
+            auto_validate_on_import: false,
+            base_currency_id: None,
+            created_at,
+            updated_at: created_at,
+        };
+        let profile = bank_profile::Model {
+            id: 2,
+            company_id: 1,
+            name: "БНБ".to_string(),
+            iban: Some("BG80BNBG96611020345678".to_string()),
+            bic: Some("BNBGBGSD".to_string()),
+            account_id: 10,
+            buffer_account_id: 11,
+            currency_code: "BGN".to_string(),
+            import_format: "CCB_CSV".to_string(),
+            is_active: true,
+            settings: None,
+            created_by: None,
+            created_at,
+            updated_at: created_at,
+        };
+        let batch = payment_batch::Model {
+            id: 3,
+            company_id: 1,
+            bank_profile_id: 2,
+            kind: PaymentBatchKind::Nap,
+            status: PaymentBatchStatus::Draft,
+            message_id: "PAY120251114093000000".to_string(),
+            execution_date: NaiveDate::from_ymd_opt(2025, 11, 14).unwrap(),
+            currency_code: "BGN".to_string(),
+            total_amount: dec!(1250.40),
+            exported_at: None,
+            created_by: None,
+            created_at,
+            updated_at: created_at,
+        };
+        let order = payment_order::Model {
+            id: 4,
+            batch_id: 3,
+            line_number: 1,
+            counterpart_id: None,
+            invoice_entry_id: None,
+            creditor_name: "ТД на НАП София".to_string(),
+            creditor_iban: "BG80BNBG96611020345678".to_string(),
+            creditor_bic: Some("BNBGBGSD".to_string()),
+            amount: dec!(1250.4),
+            remittance_info: "ДДС за октомври 2025".to_string(),
+            end_to_end_id: "PAY120251114093000000-1".to_string(),
+            budget_payment_type: Some("110000".to_string()),
+            budget_document_type: Some("9".to_string()),
+            budget_document_number: Some("1".to_string()),
+            budget_document_date: NaiveDate::from_ymd_opt(2025, 11, 14),
+            budget_period_from: NaiveDate::from_ymd_opt(2025, 10, 1),
+            budget_period_to: NaiveDate::from_ymd_opt(2025, 10, 31),
+            bank_entry_id: None,
+            matched_at: None,
+        };
+
+        let xml =
+            PaymentOrderService::generate_pain001(&company, &profile, &batch, &[order], created_at)
+                .unwrap();
+
+        assert!(xml.contains(PAIN_001_NAMESPACE));
+        assert!(xml.contains("<CreDtTm>2025-11-14T09:30:00</CreDtTm>"));
+        assert!(xml.contains("<CtrlSum>1250.40</CtrlSum>"));
+        assert!(xml.contains("<Cd>TAXS</Cd>"));
+        assert!(!xml.contains("<Cd>SEPA</Cd>"));
+        assert!(xml.contains("<InstdAmt Ccy=\"BGN\">1250.40</InstdAmt>"));
+        assert!(xml.contains("<UltmtDbtr>"));
+        assert!(xml.contains("<Prtry>110000</Prtry>"));
+        assert!(xml.contains("<Ref>9/1/14112025</Ref>"));
+        assert!(xml.contains("<AddtlRmtInf>01102025-31102025</AddtlRmtInf>"));
+        assert!(xml.contains("<ChrgBr>SHAR</ChrgBr>"));
+    }
+}
@@ -11,11 +11,37 @@ use crate::entities::{
     counterpart::Entity as CounterpartEntity, entry_line::Entity as EntryLineEntity,
     journal_entry::Entity as JournalEntryEntity, saft::*,
 };
+use crate::services::euro_changeover::EuroChangeoverService;
+use crate::services::saft_validator::{self, SafTValidationIssue, SafTValidationReport};
 
 pub struct SafTServiceV2 {
     db: DatabaseConnection,
 }
 
+enum SourceDocumentKind {
+    SalesInvoice,
+    PurchaseInvoice,
+    Payment,
+}
+
+/// Posted turnover of an account or counterpart; balances are debit-positive
+#[derive(Default, Clone, Copy)]
+struct PeriodBalance {
+    opening: Decimal,
+    debit: Decimal,
+    credit: Decimal,
+}
+
+impl PeriodBalance {
+    fn closing(&self) -> Decimal {
+        self.opening + self.debit - self.credit
+    }
+
+    fn is_empty(&self) -> bool {
+        self.opening.is_zero() && self.debit.is_zero() && self.credit.is_zero()
+    }
+}
+
 impl SafTServiceV2 {
     pub fn new(db: DatabaseConnection) -> Self {
         Self { db }
@@ -26,6 +52,37 @@ impl SafTServiceV2 {
         &self,
         request: SafTExportRequest,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let saft = self.build_saft(&request).await?;
+        self.generate_xml(&saft)
+    }
+
+    /// Generate the SAF-T file without exporting it and check it against the
+    /// SAF-T schema and the ledger balances
+    pub async fn validate_saft(
+        &self,
+        request: SafTExportRequest,
+    ) -> Result<SafTValidationReport, Box<dyn std::error::Error + Send + Sync>> {
+        let saft = self.build_saft(&request).await?;
+        let xml = self.generate_xml(&saft)?;
+
+        let mut issues = saft_validator::validate_xml(&xml)?;
+        issues.extend(Self::reconcile(&saft));
+
+        Ok(SafTValidationReport {
+            issues,
+            file_size_bytes: xml.len(),
+            number_of_transactions: saft
+                .general_ledger_entries
+                .as_ref()
+                .map(|entries| entries.number_of_entries)
+                .unwrap_or(0),
+        })
+    }
+
+    async fn build_saft(
+        &self,
+        request: &SafTExportRequest,
+    ) -> Result<BulgarianSafT, Box<dyn std::error::Error + Send + Sync>> {
         // Get company information
         let company = CompanyEntity::find_by_id(request.company_id)
             .one(&self.db)
@@ -33,7 +90,7 @@ impl SafTServiceV2 {
             .ok_or("Company not found")?;
 
         // Build header
-        let header = self.build_header(&company, &request).await?;
+        let header = self.build_header(&company, request).await?;
 
         // Build SAF-T structure based on file type
         let mut saft = BulgarianSafT {
@@ -54,29 +111,28 @@ impl SafTServiceV2 {
 
         match request.file_type {
             SafTFileType::Annual => {
-                saft.master_files_annual = Some(self.build_master_files_annual(&request).await?);
+                saft.master_files_annual = Some(self.build_master_files_annual(request).await?);
                 saft.source_documents_annual =
-                    Some(self.build_source_documents_annual(&request).await?);
+                    Some(self.build_source_documents_annual(request).await?);
             }
             SafTFileType::Monthly => {
-                saft.master_files_monthly = Some(self.build_master_files_monthly(&request).await?);
+                saft.master_files_monthly = Some(self.build_master_files_monthly(request).await?);
                 saft.corresponding_accounts_report =
-                    self.build_corresponding_accounts_report(&request).await?;
+                    self.build_corresponding_accounts_report(request).await?;
                 saft.general_ledger_entries =
-                    Some(self.build_general_ledger_entries(&request).await?);
+                    Some(self.build_general_ledger_entries(request).await?);
                 saft.source_documents_monthly =
-                    Some(self.build_source_documents_monthly(&request).await?);
+                    Some(self.build_source_documents_monthly(request).await?);
             }
             SafTFileType::OnDemand => {
                 saft.master_files_on_demand =
-                    Some(self.build_master_files_on_demand(&request).await?);
+                    Some(self.build_master_files_on_demand(request).await?);
                 saft.source_documents_on_demand =
-                    Some(self.build_source_documents_on_demand(&request).await?);
+                    Some(self.build_source_documents_on_demand(request).await?);
             }
         }
 
-        // Generate XML
-        self.generate_xml(&saft)
+        Ok(saft)
     }
 
     async fn build_header(
@@ -91,6 +147,11 @@ impl SafTServiceV2 {
         }
         .to_string();
 
+        // Books converted to the euro keep reporting earlier periods in BGN
+        let (period_start, _) = Self::period_date_range(request)?;
+        let default_currency_code =
+            EuroChangeoverService::base_currency_code(&self.db, company.id, period_start).await?;
+
         Ok(SafTHeader {
             audit_file_version: "007".to_string(), // Version 1.0.1
             audit_file_country: "BG".to_string(),
@@ -144,7 +205,7 @@ impl SafTServiceV2 {
                 bank_account: vec![], // TODO: Add bank accounts from company settings
             },
             ownership: None, // TODO: Add ownership information if available
-            default_currency_code: "BGN".to_string(),
+            default_currency_code,
             selection_criteria: SelectionCriteria {
                 tax_reporting_jurisdiction: "NRA".to_string(),
                 company_entity: None,
@@ -167,17 +228,79 @@ impl SafTServiceV2 {
         &self,
         request: &SafTExportRequest,
     ) -> Result<MasterFilesMonthly, Box<dyn std::error::Error + Send + Sync>> {
+        let (start_date, end_date) = Self::period_date_range(request)?;
+
         // Get accounts
         let accounts = AccountEntity::find()
             .filter(crate::entities::account::Column::CompanyId.eq(request.company_id))
-            .filter(crate::entities::account::Column::IsActive.eq(true))
+            .order_by_asc(crate::entities::account::Column::Code)
+            .all(&self.db)
+            .await?;
+        let account_codes: HashMap<i32, String> = accounts
+            .iter()
+            .map(|account| (account.id, account.code.clone()))
+            .collect();
+
+        // Posted turnover up to the end of the period, same selection as the GL
+        let posted_lines = EntryLineEntity::find()
+            .find_also_related(JournalEntryEntity)
+            .filter(crate::entities::journal_entry::Column::CompanyId.eq(request.company_id))
+            .filter(crate::entities::journal_entry::Column::IsPosted.eq(true))
+            .filter(crate::entities::journal_entry::Column::DocumentDate.lte(end_date))
             .all(&self.db)
             .await?;
 
+        let mut account_balances: HashMap<i32, PeriodBalance> = HashMap::new();
+        let mut customer_balances: HashMap<i32, (String, PeriodBalance)> = HashMap::new();
+        let mut supplier_balances: HashMap<i32, (String, PeriodBalance)> = HashMap::new();
+
+        for (line, entry) in posted_lines {
+            let Some(entry) = entry else { continue };
+            let in_period = entry.document_date >= start_date;
+            let apply = |balance: &mut PeriodBalance| {
+                if in_period {
+                    balance.debit += line.debit_amount;
+                    balance.credit += line.credit_amount;
+                } else {
+                    balance.opening += line.debit_amount - line.credit_amount;
+                }
+            };
+
+            apply(account_balances.entry(line.account_id).or_default());
+
+            let (Some(code), Some(counterpart_id)) =
+                (account_codes.get(&line.account_id), line.counterpart_id)
+            else {
+                continue;
+            };
+            let party_balances = if code.starts_with("411") {
+                &mut customer_balances
+            } else if code.starts_with("401") {
+                &mut supplier_balances
+            } else {
+                continue;
+            };
+            let (_, balance) = party_balances
+                .entry(counterpart_id)
+                .or_insert_with(|| (code.clone(), PeriodBalance::default()));
+            apply(balance);
+        }
+
         let mut saft_accounts = Vec::new();
         for account in accounts {
+            let balance = account_balances
+                .get(&account.id)
+                .copied()
+                .unwrap_or_default();
+            // Inactive accounts are still reported while they carry a balance
+            if !account.is_active && balance.is_empty() {
+                continue;
+            }
+
             // Parse account code as number
             let account_id = account.code.parse::<i32>().unwrap_or(0);
+            let (opening_debit, opening_credit) = Self::split_balance(balance.opening);
+            let (closing_debit, closing_credit) = Self::split_balance(balance.closing());
 
             saft_accounts.push(SafTAccount {
                 account_id,
@@ -187,10 +310,10 @@ impl SafTServiceV2 {
                 grouping_code: None,
                 account_type: "Bifunctional".to_string(), // Most common for Bulgarian accounting
                 account_creation_date: Some(account.created_at.date_naive()),
-                opening_debit_balance: Some(Decimal::ZERO), // TODO: Calculate from previous period
-                opening_credit_balance: Some(Decimal::ZERO),
-                closing_debit_balance: Some(Decimal::ZERO), // TODO: Calculate for current period
-                closing_credit_balance: Some(Decimal::ZERO),
+                opening_debit_balance: Some(opening_debit),
+                opening_credit_balance: Some(opening_credit),
+                closing_debit_balance: Some(closing_debit),
+                closing_credit_balance: Some(closing_credit),
             });
         }
 
@@ -204,23 +327,7 @@ impl SafTServiceV2 {
         let mut suppliers = Vec::new();
 
         for counterpart in counterparts {
-            let address = Address {
-                street_name: counterpart
-                    .address
-                    .clone()
-                    .or_else(|| Some("неизвестен адрес".to_string())),
-                number: None,
-                additional_address_detail: None,
-                building: None,
-                city: counterpart
-                    .city
-                    .clone()
-                    .unwrap_or("неизвестен град".to_string()),
-                postal_code: None,
-                region: None,
-                country: counterpart.country.clone().unwrap_or("BG".to_string()),
-                address_type: "StreetAddress".to_string(),
-            };
+            let address = Self::counterpart_address(&counterpart);
 
             let contact = Contact {
                 contact_person: counterpart
@@ -249,40 +356,59 @@ impl SafTServiceV2 {
                 tax_verification_date: None,
             });
 
-            match counterpart.counterpart_type {
-                crate::entities::counterpart::CounterpartType::Customer => {
-                    customers.push(SafTCustomer {
-                        customer_id: counterpart.id.to_string(),
-                        customer_name: counterpart.name.clone(),
-                        taxpayer_customer_id: None,
-                        addresses: vec![CustomerAddress { address }],
-                        contact: Some(contact),
-                        tax_registration: tax_reg,
-                        bank_account: vec![],
-                        self_billing_indicator: "0".to_string(),
-                        account_id: None,
-                        opening_balance: None,
-                        closing_balance: None,
-                        party_info: None,
-                    });
-                }
-                crate::entities::counterpart::CounterpartType::Supplier => {
-                    suppliers.push(SafTSupplier {
-                        supplier_id: counterpart.id.to_string(),
-                        supplier_name: counterpart.name.clone(),
-                        taxpayer_supplier_id: None,
-                        addresses: vec![SupplierAddress { address }],
-                        contact: Some(contact),
-                        tax_registration: tax_reg,
-                        bank_account: vec![],
-                        self_billing_indicator: "0".to_string(),
-                        account_id: None,
-                        opening_balance: None,
-                        closing_balance: None,
-                        party_info: None,
-                    });
-                }
-                _ => {}
+            // A counterpart is reported on each side where it is flagged or has a balance
+            let customer_balance = customer_balances.get(&counterpart.id);
+            if counterpart.is_customer
+                || customer_balance.is_some()
+                || matches!(
+                    counterpart.counterpart_type,
+                    crate::entities::counterpart::CounterpartType::Customer
+                )
+            {
+                let (account_id, opening_balance, closing_balance) =
+                    Self::party_balance(customer_balance);
+                customers.push(SafTCustomer {
+                    customer_id: counterpart.id.to_string(),
+                    customer_name: counterpart.name.clone(),
+                    taxpayer_customer_id: None,
+                    addresses: vec![CustomerAddress {
+                        address: address.clone(),
+                    }],
+                    contact: Some(contact.clone()),
+                    tax_registration: tax_reg.clone(),
+                    bank_account: vec![],
+                    self_billing_indicator: "0".to_string(),
+                    account_id,
+                    opening_balance,
+                    closing_balance,
+                    party_info: None,
+                });
+            }
+
+            let supplier_balance = supplier_balances.get(&counterpart.id);
+            if counterpart.is_supplier
+                || supplier_balance.is_some()
+                || matches!(
+                    counterpart.counterpart_type,
+                    crate::entities::counterpart::CounterpartType::Supplier
+                )
+            {
+                let (account_id, opening_balance, closing_balance) =
+                    Self::party_balance(supplier_balance);
+                suppliers.push(SafTSupplier {
+                    supplier_id: counterpart.id.to_string(),
+                    supplier_name: counterpart.name.clone(),
+                    taxpayer_supplier_id: None,
+                    addresses: vec![SupplierAddress { address }],
+                    contact: Some(contact),
+                    tax_registration: tax_reg,
+                    bank_account: vec![],
+                    self_billing_indicator: "0".to_string(),
+                    account_id,
+                    opening_balance,
+                    closing_balance,
+                    party_info: None,
+                });
             }
         }
 
@@ -372,113 +498,39 @@ impl SafTServiceV2 {
         &self,
         request: &SafTExportRequest,
     ) -> Result<GeneralLedgerEntries, Box<dyn std::error::Error + Send + Sync>> {
-        // Calculate date range from period
-        let start_date =
-            NaiveDate::from_ymd_opt(request.period_start_year, request.period_start as u32, 1)
-                .unwrap();
-        let end_date = if request.period_end == 12 {
-            NaiveDate::from_ymd_opt(request.period_end_year, 12, 31).unwrap()
-        } else {
-            NaiveDate::from_ymd_opt(request.period_end_year, (request.period_end + 1) as u32, 1)
-                .unwrap()
-                .pred_opt()
-                .unwrap()
-        };
+        let (start_date, end_date) = Self::period_date_range(request)?;
 
-        // Get journal entries
+        // Get posted journal entries
         let journal_entries = JournalEntryEntity::find()
             .filter(crate::entities::journal_entry::Column::CompanyId.eq(request.company_id))
+            .filter(crate::entities::journal_entry::Column::IsPosted.eq(true))
             .filter(
                 crate::entities::journal_entry::Column::DocumentDate.between(start_date, end_date),
             )
+            .order_by_asc(crate::entities::journal_entry::Column::DocumentDate)
+            .order_by_asc(crate::entities::journal_entry::Column::Id)
             .find_with_related(EntryLineEntity)
             .all(&self.db)
             .await?;
 
-        let mut transactions = Vec::new();
+        let accounts: HashMap<i32, crate::entities::account::Model> = AccountEntity::find()
+            .filter(crate::entities::account::Column::CompanyId.eq(request.company_id))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect();
+
+        let mut transactions = Vec::with_capacity(journal_entries.len());
         let mut total_debit = Decimal::ZERO;
         let mut total_credit = Decimal::ZERO;
 
         for (entry, lines) in journal_entries {
-            let mut debit_lines = Vec::new();
-            let mut credit_lines = Vec::new();
-
-            for line in lines {
-                // Get account code
-                let account = AccountEntity::find_by_id(line.account_id)
-                    .one(&self.db)
-                    .await?
-                    .ok_or("Account not found")?;
-
-                let account_id = account.code.parse::<i32>().unwrap_or(0);
-
+            for line in &lines {
                 total_debit += line.debit_amount;
                 total_credit += line.credit_amount;
-
-                let line_data = SafTTransactionLine {
-                    record_id: format!("L{}", line.id),
-                    account_id,
-                    taxpayer_account_id: Some(account.code),
-                    analysis: None,
-                    value_date: Some(entry.document_date),
-                    source_document_id: entry.document_number.clone(),
-                    customer_id: None,
-                    supplier_id: None,
-                    description: line.description.clone().unwrap_or_default(),
-                    debit_amount: if line.debit_amount > Decimal::ZERO {
-                        Some(AmountStructure {
-                            amount: line.debit_amount,
-                            currency_code: None,
-                            currency_amount: None,
-                            exchange_rate: None,
-                        })
-                    } else {
-                        None
-                    },
-                    credit_amount: if line.credit_amount > Decimal::ZERO {
-                        Some(AmountStructure {
-                            amount: line.credit_amount,
-                            currency_code: None,
-                            currency_amount: None,
-                            exchange_rate: None,
-                        })
-                    } else {
-                        None
-                    },
-                    tax_information: None,
-                    reference_number: None,
-                    cid: None,
-                    quantity: line.quantity,
-                    cross_reference: None,
-                    system_entry_time: None,
-                };
-
-                if line.debit_amount > Decimal::ZERO {
-                    debit_lines.push(line_data);
-                } else if line.credit_amount > Decimal::ZERO {
-                    credit_lines.push(line_data);
-                }
             }
-
-            transactions.push(SafTTransaction {
-                transaction_id: entry.id.to_string(),
-                period: entry.document_date.month() as i32,
-                period_year: entry.document_date.year(),
-                transaction_date: entry.document_date,
-                source_id: Some("1".to_string()),
-                description: entry.description.clone(),
-                doc_archival_number: None,
-                transaction_type: Some("Normal".to_string()),
-                system_entry_date: entry.created_at.date_naive(),
-                gl_posting_date: entry.accounting_date,
-                customer_id: None,
-                supplier_id: None,
-                system_id: None,
-                lines: Lines {
-                    debit_line: debit_lines,
-                    credit_line: credit_lines,
-                },
-            });
+            transactions.push(Self::build_transaction(&entry, lines, &accounts)?);
         }
 
         let journal = vec![SafTJournal {
@@ -496,6 +548,97 @@ impl SafTServiceV2 {
         })
     }
 
+    /// GL transaction of a posted journal entry; lines are split by side
+    fn build_transaction(
+        entry: &crate::entities::journal_entry::Model,
+        lines: Vec<crate::entities::entry_line::Model>,
+        accounts: &HashMap<i32, crate::entities::account::Model>,
+    ) -> Result<SafTTransaction, Box<dyn std::error::Error + Send + Sync>> {
+        let mut debit_lines = Vec::new();
+        let mut credit_lines = Vec::new();
+        let mut transaction_customer_id = None;
+        let mut transaction_supplier_id = None;
+
+        for line in lines {
+            let account = accounts
+                .get(&line.account_id)
+                .ok_or_else(|| format!("Account {} not found", line.account_id))?;
+
+            let account_id = account.code.parse::<i32>().unwrap_or(0);
+            let (customer_id, supplier_id) = Self::party_ids(&account.code, line.counterpart_id);
+            if transaction_customer_id.is_none() {
+                transaction_customer_id = customer_id.clone();
+            }
+            if transaction_supplier_id.is_none() {
+                transaction_supplier_id = supplier_id.clone();
+            }
+
+            let line_data = SafTTransactionLine {
+                record_id: format!("L{}", line.id),
+                account_id,
+                taxpayer_account_id: Some(account.code.clone()),
+                analysis: None,
+                value_date: Some(entry.document_date),
+                source_document_id: entry.document_number.clone(),
+                customer_id,
+                supplier_id,
+                description: line.description.clone().unwrap_or_default(),
+                debit_amount: if line.debit_amount > Decimal::ZERO {
+                    Some(AmountStructure {
+                        amount: line.debit_amount,
+                        currency_code: None,
+                        currency_amount: None,
+                        exchange_rate: None,
+                    })
+                } else {
+                    None
+                },
+                credit_amount: if line.credit_amount > Decimal::ZERO {
+                    Some(AmountStructure {
+                        amount: line.credit_amount,
+                        currency_code: None,
+                        currency_amount: None,
+                        exchange_rate: None,
+                    })
+                } else {
+                    None
+                },
+                tax_information: None,
+                reference_number: None,
+                cid: None,
+                quantity: line.quantity,
+                cross_reference: None,
+                system_entry_time: None,
+            };
+
+            if line.debit_amount > Decimal::ZERO {
+                debit_lines.push(line_data);
+            } else if line.credit_amount > Decimal::ZERO {
+                credit_lines.push(line_data);
+            }
+        }
+
+        Ok(SafTTransaction {
+            transaction_id: entry.id.to_string(),
+            period: entry.document_date.month() as i32,
+            period_year: entry.document_date.year(),
+            transaction_date: entry.document_date,
+            source_id: Some("1".to_string()),
+            description: entry.description.clone(),
+            doc_archival_number: None,
+            transaction_type: Some("Normal".to_string()),
+            system_entry_date: entry.created_at.date_naive(),
+            gl_posting_date: entry.accounting_date,
+            customer_id: transaction_customer_id,
+            supplier_id: transaction_supplier_id,
+            system_id: None,
+            lines: Lines {
+                debit_line: debit_lines,
+                credit_line: credit_lines,
+            },
+        })
+    }
+
     async fn build_corresponding_accounts_report(
         &self,
         _request: &SafTExportRequest,
@@ -506,909 +649,4085 @@ impl SafTServiceV2 {
 
     async fn build_source_documents_monthly(
         &self,
-        _request: &SafTExportRequest,
+        request: &SafTExportRequest,
+    ) -> Result<SourceDocumentsMonthly, Box<dyn std::error::Error + Send + Sync>> {
+        let (start_date, end_date) = Self::period_date_range(request)?;
+        let base_currency =
+            EuroChangeoverService::base_currency_code(&self.db, request.company_id, start_date)
+                .await?;
+
+        // Same selection as GeneralLedgerEntries so that the control totals reconcile
+        let journal_entries = JournalEntryEntity::find()
+            .filter(crate::entities::journal_entry::Column::CompanyId.eq(request.company_id))
+            .filter(crate::entities::journal_entry::Column::IsPosted.eq(true))
+            .filter(
+                crate::entities::journal_entry::Column::DocumentDate.between(start_date, end_date),
+            )
+            .order_by_asc(crate::entities::journal_entry::Column::DocumentDate)
+            .order_by_asc(crate::entities::journal_entry::Column::Id)
+            .find_with_related(EntryLineEntity)
+            .all(&self.db)
+            .await?;
+
+        let accounts: HashMap<i32, crate::entities::account::Model> = AccountEntity::find()
+            .filter(crate::entities::account::Column::CompanyId.eq(request.company_id))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect();
+
+        let counterparts: HashMap<i32, crate::entities::counterpart::Model> =
+            CounterpartEntity::find()
+                .filter(crate::entities::counterpart::Column::CompanyId.eq(request.company_id))
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|counterpart| (counterpart.id, counterpart))
+                .collect();
+
+        Self::collect_source_documents(journal_entries, &accounts, &counterparts, &base_currency)
+    }
+
+    /// Sales and purchase invoices and payments of the posted journal entries
+    fn collect_source_documents(
+        journal_entries: Vec<(
+            crate::entities::journal_entry::Model,
+            Vec<crate::entities::entry_line::Model>,
+        )>,
+        accounts: &HashMap<i32, crate::entities::account::Model>,
+        counterparts: &HashMap<i32, crate::entities::counterpart::Model>,
+        base_currency: &str,
     ) -> Result<SourceDocumentsMonthly, Box<dyn std::error::Error + Send + Sync>> {
-        // TODO: Implement based on invoices and other documents
+        let mut sales_invoices = SalesInvoices {
+            number_of_entries: 0,
+            total_debit: Decimal::ZERO,
+            total_credit: Decimal::ZERO,
+            invoice: vec![],
+        };
+        let mut purchase_invoices = PurchaseInvoices {
+            number_of_entries: 0,
+            total_debit: Decimal::ZERO,
+            total_credit: Decimal::ZERO,
+            invoice: vec![],
+        };
+        let mut payments = Payments {
+            number_of_entries: 0,
+            total_debit: Decimal::ZERO,
+            total_credit: Decimal::ZERO,
+            payment: vec![],
+        };
+
+        for (entry, mut lines) in journal_entries {
+            lines.sort_by_key(|line| (line.line_order, line.id));
+
+            match Self::source_document_kind(&entry, &lines, accounts) {
+                Some(SourceDocumentKind::SalesInvoice) => {
+                    let counterpart = Self::invoice_counterpart(&entry, &lines, counterparts)?;
+                    let (invoice_lines, totals) =
+                        Self::build_invoice_lines(&entry, &lines, accounts, true);
+                    Self::add_line_totals(
+                        &invoice_lines,
+                        &mut sales_invoices.total_debit,
+                        &mut sales_invoices.total_credit,
+                    );
+                    let (currency_code, exchange_rate) =
+                        Self::document_currency(&lines, base_currency);
+
+                    sales_invoices.invoice.push(SalesInvoice {
+                        invoice_no: Self::document_number(&entry),
+                        customer_info: CustomerInfo {
+                            customer_id: counterpart.id.to_string(),
+                            billing_address: Some(Self::counterpart_address(counterpart)),
+                        },
+                        invoice_date: entry.document_date,
+                        invoice_type: entry
+                            .vat_document_type
+                            .clone()
+                            .unwrap_or_else(|| "01".to_string()),
+                        self_billing_indicator: "0".to_string(),
+                        transaction_id: Some(entry.id.to_string()),
+                        currency_code,
+                        exchange_rate,
+                        lines: invoice_lines,
+                        document_totals: totals,
+                    });
+                }
+                Some(SourceDocumentKind::PurchaseInvoice) => {
+                    let counterpart = Self::invoice_counterpart(&entry, &lines, counterparts)?;
+                    let (invoice_lines, totals) =
+                        Self::build_invoice_lines(&entry, &lines, accounts, false);
+                    Self::add_line_totals(
+                        &invoice_lines,
+                        &mut purchase_invoices.total_debit,
+                        &mut purchase_invoices.total_credit,
+                    );
+                    let (currency_code, exchange_rate) =
+                        Self::document_currency(&lines, base_currency);
+
+                    purchase_invoices.invoice.push(PurchaseInvoice {
+                        invoice_no: Self::document_number(&entry),
+                        supplier_info: SupplierInfo {
+                            supplier_id: counterpart.id.to_string(),
+                            billing_address: Some(Self::counterpart_address(counterpart)),
+                        },
+                        invoice_date: entry.document_date,
+                        invoice_type: entry
+                            .vat_document_type
+                            .clone()
+                            .unwrap_or_else(|| "01".to_string()),
+                        self_billing_indicator: "0".to_string(),
+                        transaction_id: Some(entry.id.to_string()),
+                        currency_code,
+                        exchange_rate,
+                        lines: invoice_lines,
+                        document_totals: totals,
+                    });
+                }
+                Some(SourceDocumentKind::Payment) => {
+                    let payment = Self::build_payment(&entry, &lines, accounts);
+                    for line in &payment.lines {
+                        if line.debit_credit_indicator == "D" {
+                            payments.total_debit += line.payment_line_amount.amount;
+                        } else {
+                            payments.total_credit += line.payment_line_amount.amount;
+                        }
+                    }
+                    payments.payment.push(payment);
+                }
+                None => {}
+            }
+        }
+
+        sales_invoices.number_of_entries = sales_invoices.invoice.len() as i32;
+        purchase_invoices.number_of_entries = purchase_invoices.invoice.len() as i32;
+        payments.number_of_entries = payments.payment.len() as i32;
+
         Ok(SourceDocumentsMonthly {
-            sales_invoices: None,
-            purchase_invoices: None,
-            payments: None,
+            sales_invoices: Some(sales_invoices),
+            purchase_invoices: Some(purchase_invoices),
+            payments: Some(payments),
             movement_of_goods: None,
             asset_transactions: None,
         })
     }
 
-    async fn build_master_files_annual(
-        &self,
-        _request: &SafTExportRequest,
-    ) -> Result<MasterFilesAnnual, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(MasterFilesAnnual {
-            owners: None,
-            assets: Assets {},
+    /// Customer or supplier of an invoice; SAF-T needs a real CustomerID/SupplierID
+    fn invoice_counterpart<'a>(
+        entry: &crate::entities::journal_entry::Model,
+        lines: &[crate::entities::entry_line::Model],
+        counterparts: &'a HashMap<i32, crate::entities::counterpart::Model>,
+    ) -> Result<&'a crate::entities::counterpart::Model, Box<dyn std::error::Error + Send + Sync>>
+    {
+        let number = Self::document_number(entry);
+        let counterpart_id = lines
+            .iter()
+            .find_map(|line| line.counterpart_id)
+            .ok_or_else(|| {
+                format!(
+                    "Invoice {} (journal entry {}) has no counterpart",
+                    number, entry.id
+                )
+            })?;
+        counterparts.get(&counterpart_id).ok_or_else(|| {
+            format!(
+                "Counterpart {} of invoice {} (journal entry {}) is not found in this company",
+                counterpart_id, number, entry.id
+            )
+            .into()
         })
     }
 
-    async fn build_source_documents_annual(
-        &self,
-        _request: &SafTExportRequest,
-    ) -> Result<SourceDocumentsAnnual, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(SourceDocumentsAnnual {})
+    /// Classify a posted journal entry as a SAF-T source document.
+    /// VAT operation codes take precedence, then the VAT document type; entries
+    /// without VAT data that settle 40x/41x against cash/bank (50x) are payments.
+    fn source_document_kind(
+        entry: &crate::entities::journal_entry::Model,
+        lines: &[crate::entities::entry_line::Model],
+        accounts: &HashMap<i32, crate::entities::account::Model>,
+    ) -> Option<SourceDocumentKind> {
+        let has_value = |value: &Option<String>| value.as_deref().is_some_and(|v| !v.is_empty());
+
+        if has_value(&entry.vat_purchase_operation) {
+            return Some(SourceDocumentKind::PurchaseInvoice);
+        }
+        if has_value(&entry.vat_sales_operation) {
+            return Some(SourceDocumentKind::SalesInvoice);
+        }
+        match entry.vat_document_type.as_deref() {
+            Some("01") => return Some(SourceDocumentKind::SalesInvoice),
+            Some("03") => return Some(SourceDocumentKind::PurchaseInvoice),
+            Some(_) => return None,
+            None => {}
+        }
+
+        let account_code = |line: &crate::entities::entry_line::Model| {
+            accounts
+                .get(&line.account_id)
+                .map(|a| a.code.as_str())
+                .unwrap_or("")
+        };
+
+        let touches_cash = lines
+            .iter()
+            .any(|line| Self::is_cash_account(account_code(line)));
+        let settles_party = lines.iter().any(|line| {
+            let code = account_code(line);
+            !Self::is_cash_account(code)
+                && (line.counterpart_id.is_some()
+                    || code.starts_with("40")
+                    || code.starts_with("41"))
+        });
+
+        if touches_cash && settles_party {
+            Some(SourceDocumentKind::Payment)
+        } else {
+            None
+        }
     }
 
-    async fn build_master_files_on_demand(
-        &self,
-        _request: &SafTExportRequest,
-    ) -> Result<MasterFilesOnDemand, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(MasterFilesOnDemand {
-            tax_table: None,
-            movement_type_table: MovementTypeTable {
-                movement_type_entry: vec![],
+    /// Build invoice lines from the revenue (sales) or expense (purchase) side of
+    /// the entry. Credit notes are booked on the opposite side, so fall back to it
+    /// when the expected side has no non-VAT lines.
+    fn build_invoice_lines(
+        entry: &crate::entities::journal_entry::Model,
+        lines: &[crate::entities::entry_line::Model],
+        accounts: &HashMap<i32, crate::entities::account::Model>,
+        is_sales: bool,
+    ) -> (Vec<InvoiceLine>, DocumentTotals) {
+        let account_code = |line: &crate::entities::entry_line::Model| {
+            accounts
+                .get(&line.account_id)
+                .map(|a| a.code.clone())
+                .unwrap_or_default()
+        };
+        let amount_on_side = |line: &crate::entities::entry_line::Model, credit_side: bool| {
+            if credit_side {
+                line.credit_amount
+            } else {
+                line.debit_amount
+            }
+        };
+
+        let mut credit_side = is_sales;
+        let has_lines_on_side = |credit_side: bool| {
+            lines.iter().any(|line| {
+                !Self::is_vat_account(&account_code(line))
+                    && amount_on_side(line, credit_side) > Decimal::ZERO
+            })
+        };
+        if !has_lines_on_side(credit_side) && has_lines_on_side(!credit_side) {
+            credit_side = !credit_side;
+        }
+
+        let document_lines: Vec<&crate::entities::entry_line::Model> = lines
+            .iter()
+            .filter(|line| {
+                !Self::is_vat_account(&account_code(line))
+                    && amount_on_side(line, credit_side) > Decimal::ZERO
+            })
+            .collect();
+
+        let net_total: Decimal = document_lines
+            .iter()
+            .map(|line| amount_on_side(line, credit_side))
+            .sum();
+        let tax_payable: Decimal = lines
+            .iter()
+            .filter(|line| Self::is_vat_account(&account_code(line)))
+            .map(|line| amount_on_side(line, credit_side))
+            .sum();
+
+        let tax_percentage = Self::tax_percentage(net_total, tax_payable);
+        let tax_code = format!("VAT{}", tax_percentage);
+        let tax_point_date = entry.vat_date.unwrap_or(entry.document_date);
+        let indicator = if credit_side { "C" } else { "D" };
+
+        // Spread the document VAT over the lines; the last line takes the rounding remainder
+        let mut allocated_tax = Decimal::ZERO;
+        let line_count = document_lines.len();
+        let mut invoice_lines = Vec::with_capacity(line_count);
+
+        for (idx, line) in document_lines.into_iter().enumerate() {
+            let amount = amount_on_side(line, credit_side);
+            let line_tax = if idx + 1 == line_count {
+                tax_payable - allocated_tax
+            } else if net_total.is_zero() {
+                Decimal::ZERO
+            } else {
+                (tax_payable * amount / net_total).round_dp(2)
+            };
+            allocated_tax += line_tax;
+
+            let code = account_code(line);
+            let unit_price = line
+                .quantity
+                .filter(|quantity| !quantity.is_zero())
+                .map(|quantity| (amount / quantity).round_dp(4));
+
+            invoice_lines.push(InvoiceLine {
+                line_number: line.line_order.to_string(),
+                account_id: Some(code.parse::<i32>().unwrap_or(0)),
+                product_code: None,
+                product_description: None,
+                quantity: line.quantity,
+                unit_of_measure: line.unit_of_measure_code.clone(),
+                unit_price,
+                tax_base: Some(amount),
+                tax_point_date: Some(tax_point_date),
+                description: line
+                    .description
+                    .clone()
+                    .or_else(|| Some(entry.description.clone())),
+                debit_credit_indicator: indicator.to_string(),
+                line_amount: AmountStructure {
+                    amount,
+                    currency_code: None,
+                    currency_amount: None,
+                    exchange_rate: None,
+                },
+                tax: Some(Tax {
+                    tax_type: "100010".to_string(),
+                    tax_country_region: "BG".to_string(),
+                    tax_code: tax_code.clone(),
+                    tax_percentage: Some(Decimal::from(tax_percentage)),
+                    tax_amount: Some(AmountStructure {
+                        amount: line_tax,
+                        currency_code: None,
+                        currency_amount: None,
+                        exchange_rate: None,
+                    }),
+                }),
+            });
+        }
+
+        (
+            invoice_lines,
+            DocumentTotals {
+                tax_payable,
+                net_total,
+                gross_total: net_total + tax_payable,
             },
-            uom_table: UOMTable { uom_entry: vec![] },
-            products: Products { product: vec![] },
-            physical_stock: PhysicalStock {},
-            owners: None,
-        })
+        )
     }
 
-    async fn build_source_documents_on_demand(
-        &self,
-        _request: &SafTExportRequest,
-    ) -> Result<SourceDocumentsOnDemand, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(SourceDocumentsOnDemand {
-            sales_invoices: None,
-            purchase_invoices: None,
-            movement_of_goods: None,
-        })
-    }
+    /// Payment lines are the non-cash legs of the entry (40x/41x settlements)
+    fn build_payment(
+        entry: &crate::entities::journal_entry::Model,
+        lines: &[crate::entities::entry_line::Model],
+        accounts: &HashMap<i32, crate::entities::account::Model>,
+    ) -> Payment {
+        let account_code = |line: &crate::entities::entry_line::Model| {
+            accounts
+                .get(&line.account_id)
+                .map(|a| a.code.clone())
+                .unwrap_or_default()
+        };
 
-    fn generate_xml(
-        &self,
-        saft: &BulgarianSafT,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+        let payment_method = if lines
+            .iter()
+            .any(|line| account_code(line).starts_with("501"))
+        {
+            "01"
+        } else {
+            "02"
+        };
 
-        // Write XML declaration
-        writer.write_event(Event::Decl(quick_xml::events::BytesDecl::new(
-            "1.0",
-            Some("utf-8"),
-            None,
-        )))?;
+        let mut payment_lines = Vec::new();
+        let mut net_total = Decimal::ZERO;
 
-        // Start AuditFile element with namespace
-        let mut audit_file_elem = BytesStart::new("nsSAFT:AuditFile");
-        audit_file_elem.push_attribute((
-            "xmlns:doc",
-            "urn:schemas-OECD:schema-extensions:documentation xml:lang=en",
-        ));
-        audit_file_elem.push_attribute(("xmlns:nsSAFT", "mf:nra:dgti:dxxxx:declaration:v1"));
-        audit_file_elem.push_attribute(("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"));
-        writer.write_event(Event::Start(audit_file_elem))?;
+        for line in lines {
+            let code = account_code(line);
+            if Self::is_cash_account(&code) {
+                continue;
+            }
 
-        // Write Header
-        self.write_header(&mut writer, &saft.header)?;
+            let (indicator, amount) = if line.debit_amount > Decimal::ZERO {
+                ("D", line.debit_amount)
+            } else if line.credit_amount > Decimal::ZERO {
+                ("C", line.credit_amount)
+            } else {
+                continue;
+            };
+            net_total += amount;
+
+            let (customer_id, supplier_id) = Self::party_ids(&code, line.counterpart_id);
+
+            payment_lines.push(PaymentLine {
+                line_number: line.line_order.to_string(),
+                source_document_id: entry.document_number.clone(),
+                account_id: code.parse::<i32>().unwrap_or(0),
+                customer_id,
+                supplier_id,
+                description: line
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| entry.description.clone()),
+                debit_credit_indicator: indicator.to_string(),
+                payment_line_amount: AmountStructure {
+                    amount,
+                    currency_code: None,
+                    currency_amount: None,
+                    exchange_rate: None,
+                },
+            });
+        }
 
-        // Write content based on file type
-        match saft.file_type {
-            SafTFileType::Monthly => {
-                if let Some(ref master_files) = saft.master_files_monthly {
-                    self.write_master_files_monthly(&mut writer, master_files)?;
-                }
-                if let Some(ref corr_report) = saft.corresponding_accounts_report {
-                    self.write_corresponding_accounts_report(&mut writer, corr_report)?;
-                }
-                if let Some(ref gl_entries) = saft.general_ledger_entries {
-                    self.write_general_ledger_entries(&mut writer, gl_entries)?;
-                }
-                if let Some(ref source_docs) = saft.source_documents_monthly {
-                    self.write_source_documents_monthly(&mut writer, source_docs)?;
-                }
-            }
-            SafTFileType::Annual => {
-                if let Some(ref master_files) = saft.master_files_annual {
-                    self.write_master_files_annual(&mut writer, master_files)?;
-                }
-                if let Some(ref source_docs) = saft.source_documents_annual {
-                    self.write_source_documents_annual(&mut writer, source_docs)?;
-                }
-            }
-            SafTFileType::OnDemand => {
-                if let Some(ref master_files) = saft.master_files_on_demand {
-                    self.write_master_files_on_demand(&mut writer, master_files)?;
-                }
-                if let Some(ref source_docs) = saft.source_documents_on_demand {
-                    self.write_source_documents_on_demand(&mut writer, source_docs)?;
-                }
-            }
+        Payment {
+            payment_ref_no: Self::document_number(entry),
+            period: entry.document_date.month() as i32,
+            period_year: entry.document_date.year(),
+            transaction_id: entry.id.to_string(),
+            transaction_date: entry.document_date,
+            payment_method: payment_method.to_string(),
+            description: entry.description.clone(),
+            system_id: Some(entry.entry_number.clone()),
+            source_id: Some(entry.created_by.to_string()),
+            lines: payment_lines,
+            document_totals: DocumentTotals {
+                tax_payable: Decimal::ZERO,
+                net_total,
+                gross_total: net_total,
+            },
         }
+    }
 
-        // End AuditFile
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:AuditFile")))?;
+    fn add_line_totals(
+        lines: &[InvoiceLine],
+        total_debit: &mut Decimal,
+        total_credit: &mut Decimal,
+    ) {
+        for line in lines {
+            if line.debit_credit_indicator == "D" {
+                *total_debit += line.line_amount.amount;
+            } else {
+                *total_credit += line.line_amount.amount;
+            }
+        }
+    }
+
+    /// Foreign currency of the document, if any line was booked in one
+    fn document_currency(
+        lines: &[crate::entities::entry_line::Model],
+        base_currency: &str,
+    ) -> (Option<String>, Option<Decimal>) {
+        lines
+            .iter()
+            .find(|line| {
+                line.currency_code
+                    .as_deref()
+                    .is_some_and(|code| !code.is_empty() && code != base_currency)
+            })
+            .map(|line| (line.currency_code.clone(), line.exchange_rate))
+            .unwrap_or((None, None))
+    }
+
+    fn document_number(entry: &crate::entities::journal_entry::Model) -> String {
+        entry
+            .document_number
+            .clone()
+            .filter(|number| !number.is_empty())
+            .unwrap_or_else(|| entry.entry_number.clone())
+    }
+
+    /// Nearest statutory VAT rate (20%, 9% or 0%) for the document
+    fn tax_percentage(net_total: Decimal, tax_payable: Decimal) -> i32 {
+        if net_total.is_zero() || tax_payable.is_zero() {
+            return 0;
+        }
+        let rate = tax_payable / net_total * Decimal::from(100);
+        if rate >= Decimal::from(15) {
+            20
+        } else if rate >= Decimal::from(5) {
+            9
+        } else {
+            0
+        }
+    }
+
+    fn is_vat_account(code: &str) -> bool {
+        code.starts_with("453")
+    }
+
+    fn is_cash_account(code: &str) -> bool {
+        code.starts_with("50")
+    }
+
+    /// Customer/Supplier ID for a line, based on the 41x/40x account group
+    fn party_ids(
+        account_code: &str,
+        counterpart_id: Option<i32>,
+    ) -> (Option<String>, Option<String>) {
+        match counterpart_id {
+            Some(id) if account_code.starts_with("41") => (Some(id.to_string()), None),
+            Some(id) if account_code.starts_with("40") => (None, Some(id.to_string())),
+            _ => (None, None),
+        }
+    }
+
+    fn counterpart_address(counterpart: &crate::entities::counterpart::Model) -> Address {
+        Address {
+            street_name: counterpart
+                .address
+                .clone()
+                .or_else(|| Some("неизвестен адрес".to_string())),
+            number: None,
+            additional_address_detail: None,
+            building: None,
+            city: counterpart
+                .city
+                .clone()
+                .unwrap_or("неизвестен град".to_string()),
+            postal_code: None,
+            region: None,
+            country: counterpart.country.clone().unwrap_or("BG".to_string()),
+            address_type: "StreetAddress".to_string(),
+        }
+    }
+
+    /// First and last day of the requested period
+    fn period_date_range(
+        request: &SafTExportRequest,
+    ) -> Result<(NaiveDate, NaiveDate), Box<dyn std::error::Error + Send + Sync>> {
+        let start_date =
+            NaiveDate::from_ymd_opt(request.period_start_year, request.period_start as u32, 1)
+                .ok_or("Invalid period start")?;
+        let end_date = if request.period_end == 12 {
+            NaiveDate::from_ymd_opt(request.period_end_year, 12, 31)
+        } else {
+            NaiveDate::from_ymd_opt(request.period_end_year, (request.period_end + 1) as u32, 1)
+                .and_then(|date| date.pred_opt())
+        }
+        .ok_or("Invalid period end")?;
+
+        Ok((start_date, end_date))
+    }
+
+    /// Split a debit-positive balance into its debit and credit presentation
+    fn split_balance(balance: Decimal) -> (Decimal, Decimal) {
+        if balance >= Decimal::ZERO {
+            (balance, Decimal::ZERO)
+        } else {
+            (Decimal::ZERO, -balance)
+        }
+    }
+
+    /// Account, opening and closing balance reported for a customer or supplier
+    fn party_balance(
+        balance: Option<&(String, PeriodBalance)>,
+    ) -> (
+        Option<i32>,
+        Option<OpeningCloseBalance>,
+        Option<OpeningCloseBalance>,
+    ) {
+        let Some((account_code, balance)) = balance else {
+            return (None, None, None);
+        };
+        let to_balance = |amount: Decimal| {
+            let (debit, credit) = Self::split_balance(amount);
+            OpeningCloseBalance {
+                debit: Some(debit),
+                credit: Some(credit),
+            }
+        };
+
+        (
+            account_code.parse::<i32>().ok(),
+            Some(to_balance(balance.opening)),
+            Some(to_balance(balance.closing())),
+        )
+    }
+
+    async fn build_master_files_annual(
+        &self,
+        request: &SafTExportRequest,
+    ) -> Result<MasterFilesAnnual, Box<dyn std::error::Error + Send + Sync>> {
+        let (start_date, end_date) = Self::period_date_range(request)?;
+        let assets = self
+            .load_assets_for_period(request.company_id, start_date, end_date)
+            .await?;
+        let journals = self.load_depreciation_by_asset(request.company_id).await?;
+        let events = self.load_events_by_asset(request.company_id).await?;
+
+        let mut saft_assets = Vec::with_capacity(assets.len());
+        for (asset, category) in assets {
+            let asset_journals = journals.get(&asset.id).map(Vec::as_slice).unwrap_or(&[]);
+            let asset_events = events.get(&asset.id).map(Vec::as_slice).unwrap_or(&[]);
+
+            saft_assets.push(SafTAsset {
+                asset_id: asset.inventory_number.clone(),
+                account_id: category
+                    .as_ref()
+                    .map(|c| c.asset_account_code.clone())
+                    .unwrap_or_default(),
+                description: asset.description.clone().unwrap_or(asset.name.clone()),
+                supplier_id: None,
+                date_of_acquisition: asset.acquisition_date,
+                start_up_date: asset.put_into_service_date,
+                valuations: vec![
+                    Self::asset_valuation(
+                        &asset,
+                        category.as_ref(),
+                        asset_journals,
+                        asset_events,
+                        start_date,
+                        end_date,
+                        false,
+                    ),
+                    Self::asset_valuation(
+                        &asset,
+                        category.as_ref(),
+                        asset_journals,
+                        asset_events,
+                        start_date,
+                        end_date,
+                        true,
+                    ),
+                ],
+            });
+        }
+
+        Ok(MasterFilesAnnual {
+            owners: None,
+            assets: Assets { asset: saft_assets },
+        })
+    }
+
+    async fn build_source_documents_annual(
+        &self,
+        request: &SafTExportRequest,
+    ) -> Result<SourceDocumentsAnnual, Box<dyn std::error::Error + Send + Sync>> {
+        let (start_date, end_date) = Self::period_date_range(request)?;
+        let assets = self
+            .load_assets_for_period(request.company_id, start_date, end_date)
+            .await?;
+        let journals = self.load_depreciation_by_asset(request.company_id).await?;
+        let events = self.load_events_by_asset(request.company_id).await?;
+
+        let mut transactions = Vec::new();
+        for (asset, _) in &assets {
+            transactions.extend(Self::asset_transactions(
+                asset,
+                journals.get(&asset.id).map(Vec::as_slice).unwrap_or(&[]),
+                events.get(&asset.id).map(Vec::as_slice).unwrap_or(&[]),
+                start_date,
+                end_date,
+            ));
+        }
+
+        transactions.sort_by(|a, b| {
+            a.asset_transaction_date
+                .cmp(&b.asset_transaction_date)
+                .then_with(|| a.asset_id.cmp(&b.asset_id))
+        });
+
+        Ok(SourceDocumentsAnnual {
+            asset_transactions: Some(AssetTransactions {
+                number_of_asset_transactions: transactions.len() as i32,
+                asset_transaction: transactions,
+            }),
+        })
+    }
+
+    /// Acquisition, depreciation and lifecycle events of an asset within the period
+    fn asset_transactions(
+        asset: &crate::entities::fixed_asset::Model,
+        journals: &[crate::entities::depreciation_journal::Model],
+        events: &[crate::entities::fixed_asset_event::Model],
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Vec<AssetTransaction> {
+        use crate::entities::fixed_asset_event::FixedAssetEventType;
+
+        let in_period = |date: NaiveDate| date >= start_date && date <= end_date;
+        let mut transactions = Vec::new();
+
+        if in_period(asset.acquisition_date) {
+            transactions.push(AssetTransaction {
+                asset_transaction_id: format!("ACQ-{}", asset.id),
+                asset_id: asset.inventory_number.clone(),
+                asset_transaction_type: "Acquisition".to_string(),
+                description: Some(asset.name.clone()),
+                asset_transaction_date: asset.acquisition_date,
+                supplier_id: None,
+                customer_id: None,
+                transaction_id: None,
+                valuations: [("Accounting", false), ("Tax", true)]
+                    .into_iter()
+                    .map(|(valuation_type, is_tax)| {
+                        let cost = Self::asset_cost_at(asset, events, None, is_tax);
+                        AssetTransactionValuation {
+                            asset_valuation_type: valuation_type.to_string(),
+                            acquisition_and_production_costs_on_transaction: cost,
+                            book_value_on_transaction: cost,
+                            asset_transaction_amount: cost,
+                        }
+                    })
+                    .collect(),
+            });
+        }
+
+        for journal in journals.iter().filter(|j| in_period(j.period)) {
+            transactions.push(AssetTransaction {
+                asset_transaction_id: format!("DEP-{}", journal.id),
+                asset_id: asset.inventory_number.clone(),
+                asset_transaction_type: "Depreciation".to_string(),
+                description: Some(format!(
+                    "Амортизация {} за {}",
+                    asset.name,
+                    journal.get_period_display()
+                )),
+                asset_transaction_date: journal.period,
+                supplier_id: None,
+                customer_id: None,
+                transaction_id: journal.journal_entry_id.map(|id| id.to_string()),
+                valuations: vec![
+                    AssetTransactionValuation {
+                        asset_valuation_type: "Accounting".to_string(),
+                        acquisition_and_production_costs_on_transaction: Self::asset_cost_at(
+                            asset,
+                            events,
+                            Some(journal.period),
+                            false,
+                        ),
+                        book_value_on_transaction: journal.accounting_book_value_after,
+                        asset_transaction_amount: journal.accounting_depreciation_amount,
+                    },
+                    AssetTransactionValuation {
+                        asset_valuation_type: "Tax".to_string(),
+                        acquisition_and_production_costs_on_transaction: Self::asset_cost_at(
+                            asset,
+                            events,
+                            Some(journal.period),
+                            true,
+                        ),
+                        book_value_on_transaction: journal.tax_book_value_after,
+                        asset_transaction_amount: journal.tax_depreciation_amount,
+                    },
+                ],
+            });
+        }
+
+        for event in events.iter().filter(|e| in_period(e.event_date)) {
+            let transaction_type = match event.event_type {
+                FixedAssetEventType::Sale => "Sale",
+                FixedAssetEventType::Disposal | FixedAssetEventType::WriteOff => "Disposal",
+                FixedAssetEventType::Revaluation => "Revaluation",
+                FixedAssetEventType::Improvement => "Improvement",
+                FixedAssetEventType::Impairment => "Impairment",
+            };
+            let valuation = |valuation_type: &str, is_tax: bool| {
+                let (cost_before, _, book_before, book_after) = Self::event_values(event, is_tax);
+                let amount = match event.event_type {
+                    FixedAssetEventType::Sale => event.amount,
+                    FixedAssetEventType::Revaluation => book_after - book_before,
+                    _ => (book_after - book_before).abs(),
+                };
+                AssetTransactionValuation {
+                    asset_valuation_type: valuation_type.to_string(),
+                    acquisition_and_production_costs_on_transaction: cost_before,
+                    book_value_on_transaction: book_after,
+                    asset_transaction_amount: amount,
+                }
+            };
+
+            transactions.push(AssetTransaction {
+                asset_transaction_id: format!("EVT-{}", event.id),
+                asset_id: asset.inventory_number.clone(),
+                asset_transaction_type: transaction_type.to_string(),
+                description: Some(event.description.clone().unwrap_or_else(|| {
+                    format!("{} {}", event.event_type.display_name(), asset.name)
+                })),
+                asset_transaction_date: event.event_date,
+                supplier_id: None,
+                customer_id: None,
+                transaction_id: event.journal_entry_id.map(|id| id.to_string()),
+                valuations: vec![valuation("Accounting", false), valuation("Tax", true)],
+            });
+        }
+
+        // Assets retired before lifecycle events were recorded carry only a disposal date
+        let has_disposal_event = events.iter().any(|e| e.event_type.is_disposal());
+        if let Some(disposal_date) = asset
+            .disposal_date
+            .filter(|date| in_period(*date) && !has_disposal_event)
+        {
+            let transaction_type = if asset.status == "sold" {
+                "Sale"
+            } else {
+                "Disposal"
+            };
+            let amount = asset.disposal_amount.unwrap_or(Decimal::ZERO);
+
+            transactions.push(AssetTransaction {
+                asset_transaction_id: format!("DSP-{}", asset.id),
+                asset_id: asset.inventory_number.clone(),
+                asset_transaction_type: transaction_type.to_string(),
+                description: Some(asset.name.clone()),
+                asset_transaction_date: disposal_date,
+                supplier_id: None,
+                customer_id: None,
+                transaction_id: None,
+                valuations: vec![
+                    AssetTransactionValuation {
+                        asset_valuation_type: "Accounting".to_string(),
+                        acquisition_and_production_costs_on_transaction: asset.acquisition_cost,
+                        book_value_on_transaction: asset.accounting_book_value,
+                        asset_transaction_amount: amount,
+                    },
+                    AssetTransactionValuation {
+                        asset_valuation_type: "Tax".to_string(),
+                        acquisition_and_production_costs_on_transaction: asset
+                            .tax_depreciable_value,
+                        book_value_on_transaction: asset.tax_book_value,
+                        asset_transaction_amount: amount,
+                    },
+                ],
+            });
+        }
+
+        transactions
+    }
+
+    /// Assets held at any point during the period, with their categories
+    async fn load_assets_for_period(
+        &self,
+        company_id: i32,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<
+        Vec<(
+            crate::entities::fixed_asset::Model,
+            Option<crate::entities::fixed_asset_category::Model>,
+        )>,
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        use crate::entities::fixed_asset;
+
+        let assets = fixed_asset::Entity::find()
+            .filter(fixed_asset::Column::CompanyId.eq(company_id))
+            .filter(fixed_asset::Column::AcquisitionDate.lte(end_date))
+            .filter(
+                Condition::any()
+                    .add(fixed_asset::Column::DisposalDate.is_null())
+                    .add(fixed_asset::Column::DisposalDate.gte(start_date)),
+            )
+            .order_by_asc(fixed_asset::Column::InventoryNumber)
+            .find_also_related(crate::entities::fixed_asset_category::Entity)
+            .all(&self.db)
+            .await?;
+
+        Ok(assets)
+    }
+
+    async fn load_depreciation_by_asset(
+        &self,
+        company_id: i32,
+    ) -> Result<
+        HashMap<i32, Vec<crate::entities::depreciation_journal::Model>>,
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        use crate::entities::depreciation_journal;
+
+        let journals = depreciation_journal::Entity::find()
+            .filter(depreciation_journal::Column::CompanyId.eq(company_id))
+            .order_by_asc(depreciation_journal::Column::Period)
+            .all(&self.db)
+            .await?;
+
+        let mut by_asset: HashMap<i32, Vec<depreciation_journal::Model>> = HashMap::new();
+        for journal in journals {
+            by_asset
+                .entry(journal.fixed_asset_id)
+                .or_default()
+                .push(journal);
+        }
+
+        Ok(by_asset)
+    }
+
+    async fn load_events_by_asset(
+        &self,
+        company_id: i32,
+    ) -> Result<
+        HashMap<i32, Vec<crate::entities::fixed_asset_event::Model>>,
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        use crate::entities::fixed_asset_event;
+
+        let events = fixed_asset_event::Entity::find()
+            .filter(fixed_asset_event::Column::CompanyId.eq(company_id))
+            .order_by_asc(fixed_asset_event::Column::EventDate)
+            .order_by_asc(fixed_asset_event::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let mut by_asset: HashMap<i32, Vec<fixed_asset_event::Model>> = HashMap::new();
+        for event in events {
+            by_asset
+                .entry(event.fixed_asset_id)
+                .or_default()
+                .push(event);
+        }
+
+        Ok(by_asset)
+    }
+
+    /// Cost and book value before and after an event, in accounting or tax terms
+    fn event_values(
+        event: &crate::entities::fixed_asset_event::Model,
+        is_tax: bool,
+    ) -> (Decimal, Decimal, Decimal, Decimal) {
+        if is_tax {
+            (
+                event.tax_depreciable_value_before,
+                event.tax_depreciable_value_after,
+                event.tax_book_value_before,
+                event.tax_book_value_after,
+            )
+        } else {
+            (
+                event.acquisition_cost_before,
+                event.acquisition_cost_after,
+                event.accounting_book_value_before,
+                event.accounting_book_value_after,
+            )
+        }
+    }
+
+    /// Accounting cost or tax depreciable value as of a date, or at acquisition when
+    /// no date is given, by rolling the current value back over the later events
+    fn asset_cost_at(
+        asset: &crate::entities::fixed_asset::Model,
+        events: &[crate::entities::fixed_asset_event::Model],
+        date: Option<NaiveDate>,
+        is_tax: bool,
+    ) -> Decimal {
+        let current = if is_tax {
+            asset.tax_depreciable_value
+        } else {
+            asset.acquisition_cost
+        };
+        let later_changes: Decimal = events
+            .iter()
+            .filter(|event| date.is_none_or(|date| event.event_date > date))
+            .map(|event| {
+                let (before, after, _, _) = Self::event_values(event, is_tax);
+                after - before
+            })
+            .sum();
+
+        current - later_changes
+    }
+
+    /// Accounting or tax valuation of an asset for the period. Costs and book values
+    /// at period end are the current figures rolled back over the depreciation and
+    /// lifecycle events booked after the period, so opening balances carried in on
+    /// import are preserved. Improvements count as additions, sales and disposals as
+    /// disposals, and revaluations and impairments as appreciation of the book value.
+    fn asset_valuation(
+        asset: &crate::entities::fixed_asset::Model,
+        category: Option<&crate::entities::fixed_asset_category::Model>,
+        journals: &[crate::entities::depreciation_journal::Model],
+        events: &[crate::entities::fixed_asset_event::Model],
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        is_tax: bool,
+    ) -> AssetValuation {
+        use crate::entities::fixed_asset_event::FixedAssetEventType;
+
+        let depreciation_amount = |journal: &crate::entities::depreciation_journal::Model| {
+            if is_tax {
+                journal.tax_depreciation_amount
+            } else {
+                journal.accounting_depreciation_amount
+            }
+        };
+
+        let acquired_before = asset.acquisition_date < start_date;
+        let acquired_in_period = !acquired_before && asset.acquisition_date <= end_date;
+        let in_period = |date: NaiveDate| date >= start_date && date <= end_date;
+
+        let depreciation_for_period: Decimal = journals
+            .iter()
+            .filter(|j| in_period(j.period))
+            .map(depreciation_amount)
+            .sum();
+        let depreciation_after_period: Decimal = journals
+            .iter()
+            .filter(|j| j.period > end_date)
+            .map(depreciation_amount)
+            .sum();
+
+        let mut book_changes_after = Decimal::ZERO;
+        let mut cost_changes_in_period = Decimal::ZERO;
+        let mut book_changes_in_period = Decimal::ZERO;
+        let mut improvements = Decimal::ZERO;
+        let mut disposed_cost = Decimal::ZERO;
+        let mut appreciation = Decimal::ZERO;
+        for event in events {
+            let (cost_before, cost_after, book_before, book_after) =
+                Self::event_values(event, is_tax);
+            if event.event_date > end_date {
+                book_changes_after += book_after - book_before;
+            } else if event.event_date >= start_date {
+                cost_changes_in_period += cost_after - cost_before;
+                book_changes_in_period += book_after - book_before;
+                match event.event_type {
+                    FixedAssetEventType::Improvement => improvements += cost_after - cost_before,
+                    FixedAssetEventType::Revaluation | FixedAssetEventType::Impairment => {
+                        appreciation += book_after - book_before
+                    }
+                    _ => disposed_cost += cost_before - cost_after,
+                }
+            }
+        }
+
+        let current_book = if is_tax {
+            asset.tax_book_value
+        } else {
+            asset.accounting_book_value
+        };
+        let mut costs_end = Self::asset_cost_at(asset, events, Some(end_date), is_tax);
+        let mut book_value_end = current_book - book_changes_after + depreciation_after_period;
+
+        let (costs_begin, book_value_begin, addition) = if acquired_before {
+            (
+                costs_end - cost_changes_in_period,
+                book_value_end + depreciation_for_period - book_changes_in_period,
+                improvements,
+            )
+        } else if acquired_in_period {
+            (
+                Decimal::ZERO,
+                Decimal::ZERO,
+                Self::asset_cost_at(asset, events, None, is_tax) + improvements,
+            )
+        } else {
+            (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO)
+        };
+
+        // Retired without a disposal event: the whole asset leaves at its last values
+        let has_disposal_event = events.iter().any(|e| e.event_type.is_disposal());
+        if !has_disposal_event && asset.disposal_date.is_some_and(in_period) {
+            disposed_cost += costs_end;
+            costs_end = Decimal::ZERO;
+            book_value_end = Decimal::ZERO;
+        }
+        let accumulated_end = costs_end - book_value_end;
+
+        let (valuation_type, valuation_class, useful_life, method, percentage) = if is_tax {
+            (
+                "Tax",
+                category.map(|c| c.tax_category.to_string()),
+                asset.tax_useful_life,
+                "straight_line".to_string(),
+                asset.tax_depreciation_rate,
+            )
+        } else {
+            (
+                "Accounting",
+                category.map(|c| c.code.clone()),
+                Some(asset.accounting_useful_life),
+                asset.accounting_depreciation_method.clone(),
+                asset.accounting_depreciation_rate,
+            )
+        };
+
+        AssetValuation {
+            asset_valuation_type: (*valuation_type).to_string(),
+            valuation_class,
+            acquisition_and_production_costs_begin: costs_begin,
+            acquisition_and_production_costs_end: costs_end,
+            asset_life_year: useful_life
+                .map(|months| (Decimal::from(months) / Decimal::from(12)).round_dp(2)),
+            asset_addition: addition,
+            transfers: Decimal::ZERO,
+            asset_disposal: disposed_cost,
+            book_value_begin,
+            depreciation_method: method,
+            depreciation_percentage: percentage,
+            depreciation_for_period,
+            appreciation_for_period: appreciation,
+            accumulated_depreciation: accumulated_end,
+            book_value_end,
+        }
+    }
+
+    async fn build_master_files_on_demand(
+        &self,
+        request: &SafTExportRequest,
+    ) -> Result<MasterFilesOnDemand, Box<dyn std::error::Error + Send + Sync>> {
+        use crate::entities::{inventory_balance, inventory_movement};
+
+        let (start_date, end_date) = Self::period_date_range(request)?;
+        let stock_accounts = self.load_stock_accounts(request.company_id).await?;
+        let account_ids: Vec<i32> = stock_accounts.iter().map(|a| a.id).collect();
+
+        let movements = inventory_movement::Entity::find()
+            .filter(inventory_movement::Column::CompanyId.eq(request.company_id))
+            .filter(inventory_movement::Column::AccountId.is_in(account_ids.clone()))
+            .filter(inventory_movement::Column::MovementDate.lte(end_date))
+            .order_by_asc(inventory_movement::Column::MovementDate)
+            .order_by_asc(inventory_movement::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let balances: HashMap<i32, inventory_balance::Model> = inventory_balance::Entity::find()
+            .filter(inventory_balance::Column::CompanyId.eq(request.company_id))
+            .filter(inventory_balance::Column::AccountId.is_in(account_ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|balance| (balance.account_id, balance))
+            .collect();
+
+        Ok(Self::stock_master_files(
+            &stock_accounts,
+            &movements,
+            &balances,
+            start_date,
+        ))
+    }
+
+    /// Products, units and physical stock of the quantity-tracking accounts, with the
+    /// opening balance before `start_date` and the closing balance after the last
+    /// of the given movements
+    fn stock_master_files(
+        stock_accounts: &[crate::entities::account::Model],
+        movements: &[crate::entities::inventory_movement::Model],
+        balances: &HashMap<i32, crate::entities::inventory_balance::Model>,
+        start_date: NaiveDate,
+    ) -> MasterFilesOnDemand {
+        use crate::entities::inventory_movement;
+
+        let mut products = Vec::with_capacity(stock_accounts.len());
+        let mut stock_entries = Vec::with_capacity(stock_accounts.len());
+        let mut units: Vec<String> = Vec::new();
+
+        for account in stock_accounts {
+            let unit = account
+                .default_unit
+                .clone()
+                .unwrap_or_else(|| "бр".to_string());
+            if !units.contains(&unit) {
+                units.push(unit.clone());
+            }
+
+            let account_movements: Vec<&inventory_movement::Model> = movements
+                .iter()
+                .filter(|m| m.account_id == account.id)
+                .collect();
+
+            // Balances after the last movement before/within the period; accounts without
+            // movements carry their stored (imported) balance through the period
+            let (opening_quantity, opening_value) = account_movements
+                .iter()
+                .rev()
+                .find(|m| m.movement_date < start_date)
+                .map(|m| (m.balance_after_quantity, m.balance_after_amount))
+                .or_else(|| {
+                    account_movements
+                        .is_empty()
+                        .then(|| balances.get(&account.id))
+                        .flatten()
+                        .map(|b| (b.current_quantity, b.current_amount))
+                })
+                .unwrap_or((Decimal::ZERO, Decimal::ZERO));
+            let (closing_quantity, closing_value) = account_movements
+                .last()
+                .map(|m| (m.balance_after_quantity, m.balance_after_amount))
+                .unwrap_or((opening_quantity, opening_value));
+
+            for movement in &account_movements {
+                if let Some(ref movement_unit) = movement.unit_of_measure {
+                    if !units.contains(movement_unit) {
+                        units.push(movement_unit.clone());
+                    }
+                }
+            }
+
+            let unit_price = if closing_quantity.is_zero() {
+                Decimal::ZERO
+            } else {
+                (closing_value / closing_quantity).round_dp(4)
+            };
+
+            products.push(SafTProduct {
+                product_type: "P".to_string(),
+                product_code: account.code.clone(),
+                product_group: Some(account.account_class.to_string()),
+                description: account.name.clone(),
+                product_number_code: None,
+                products_category: None,
+                bar_code: None,
+                products_other_category: None,
+            });
+
+            stock_entries.push(PhysicalStockEntry {
+                warehouse_id: "1".to_string(),
+                product_code: account.code.clone(),
+                product_type: "P".to_string(),
+                stock_account_no: account.code.clone(),
+                uom_physical_stock: unit,
+                uom_to_uom_base_conversion_factor: Decimal::ONE,
+                unit_price,
+                opening_stock_quantity: opening_quantity,
+                opening_stock_value: opening_value,
+                closing_stock_quantity: closing_quantity,
+                closing_stock_value: closing_value,
+            });
+        }
+
+        MasterFilesOnDemand {
+            tax_table: None,
+            movement_type_table: MovementTypeTable {
+                movement_type_entry: vec![
+                    SafTMovementType {
+                        movement_type: Self::movement_type_code(true).to_string(),
+                        description: "Постъпление".to_string(),
+                    },
+                    SafTMovementType {
+                        movement_type: Self::movement_type_code(false).to_string(),
+                        description: "Изписване".to_string(),
+                    },
+                ],
+            },
+            uom_table: UOMTable {
+                uom_entry: units
+                    .into_iter()
+                    .map(|unit| SafTUomEntry {
+                        uom_description: Self::uom_description(&unit),
+                        unit_of_measure: unit,
+                        uom_to_uom_base: Decimal::ONE,
+                    })
+                    .collect(),
+            },
+            products: Products { product: products },
+            physical_stock: PhysicalStock {
+                physical_stock_entry: stock_entries,
+            },
+            owners: None,
+        }
+    }
+
+    async fn build_source_documents_on_demand(
+        &self,
+        request: &SafTExportRequest,
+    ) -> Result<SourceDocumentsOnDemand, Box<dyn std::error::Error + Send + Sync>> {
+        use crate::entities::{inventory_movement, journal_entry};
+
+        let (start_date, end_date) = Self::period_date_range(request)?;
+        let stock_accounts: HashMap<i32, crate::entities::account::Model> = self
+            .load_stock_accounts(request.company_id)
+            .await?
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect();
+
+        let movements = inventory_movement::Entity::find()
+            .filter(inventory_movement::Column::CompanyId.eq(request.company_id))
+            .filter(
+                inventory_movement::Column::AccountId
+                    .is_in(stock_accounts.keys().copied().collect::<Vec<_>>()),
+            )
+            .filter(inventory_movement::Column::MovementDate.between(start_date, end_date))
+            .order_by_asc(inventory_movement::Column::MovementDate)
+            .order_by_asc(inventory_movement::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let entry_ids: Vec<i32> = movements.iter().map(|m| m.journal_entry_id).collect();
+        let document_numbers: HashMap<i32, String> = journal_entry::Entity::find()
+            .filter(journal_entry::Column::Id.is_in(entry_ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.id, Self::document_number(&entry)))
+            .collect();
+
+        Ok(SourceDocumentsOnDemand {
+            sales_invoices: None,
+            purchase_invoices: None,
+            movement_of_goods: Some(Self::collect_movement_of_goods(
+                &stock_accounts,
+                &movements,
+                &document_numbers,
+            )),
+        })
+    }
+
+    /// Stock movements grouped per journal entry and direction
+    fn collect_movement_of_goods(
+        stock_accounts: &HashMap<i32, crate::entities::account::Model>,
+        movements: &[crate::entities::inventory_movement::Model],
+        document_numbers: &HashMap<i32, String>,
+    ) -> MovementOfGoods {
+        let mut stock_movements: Vec<StockMovement> = Vec::new();
+        let mut total_received = Decimal::ZERO;
+        let mut total_issued = Decimal::ZERO;
+        let mut line_count = 0;
+
+        for movement in movements {
+            let Some(account) = stock_accounts.get(&movement.account_id) else {
+                continue;
+            };
+
+            if movement.is_receipt() {
+                total_received += movement.quantity;
+            } else {
+                total_issued += movement.quantity;
+            }
+            line_count += 1;
+
+            let movement_type = Self::movement_type_code(movement.is_receipt());
+            let reference = format!("{}-{}", movement.journal_entry_id, movement_type);
+
+            // Receipts and issues of one journal entry are grouped into one movement each
+            let position = stock_movements
+                .iter()
+                .position(|m| m.movement_reference == reference);
+            let stock_movement = match position {
+                Some(idx) => &mut stock_movements[idx],
+                None => {
+                    stock_movements.push(StockMovement {
+                        movement_reference: reference,
+                        movement_date: movement.movement_date,
+                        movement_type: movement_type.to_string(),
+                        document_reference: document_numbers
+                            .get(&movement.journal_entry_id)
+                            .cloned(),
+                        lines: vec![],
+                    });
+                    stock_movements
+                        .last_mut()
+                        .expect("movement was just pushed")
+                }
+            };
+
+            stock_movement.lines.push(StockMovementLine {
+                line_number: (stock_movement.lines.len() + 1).to_string(),
+                account_id: account.code.clone(),
+                transaction_id: Some(movement.journal_entry_id.to_string()),
+                product_code: account.code.clone(),
+                quantity: movement.quantity,
+                unit_of_measure: movement
+                    .unit_of_measure
+                    .clone()
+                    .or_else(|| account.default_unit.clone())
+                    .unwrap_or_else(|| "бр".to_string()),
+                uom_to_uom_base_conversion_factor: Decimal::ONE,
+                book_value: movement.total_amount,
+                description: movement.description.clone(),
+            });
+        }
+
+        MovementOfGoods {
+            number_of_movement_lines: line_count,
+            total_quantity_received: total_received,
+            total_quantity_issued: total_issued,
+            stock_movement: stock_movements,
+        }
+    }
+
+    /// Quantity-tracking (material/goods) accounts act as SAF-T products
+    async fn load_stock_accounts(
+        &self,
+        company_id: i32,
+    ) -> Result<Vec<crate::entities::account::Model>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        let accounts = AccountEntity::find()
+            .filter(crate::entities::account::Column::CompanyId.eq(company_id))
+            .filter(crate::entities::account::Column::SupportsQuantities.eq(true))
+            .order_by_asc(crate::entities::account::Column::Code)
+            .all(&self.db)
+            .await?;
+
+        Ok(accounts)
+    }
+
+    fn movement_type_code(is_receipt: bool) -> &'static str {
+        if is_receipt {
+            "10"
+        } else {
+            "20"
+        }
+    }
+
+    fn uom_description(unit: &str) -> String {
+        match unit {
+            "бр" => "Брой",
+            "кг" => "Килограм",
+            "л" => "Литър",
+            "м" => "Метър",
+            "м2" => "Квадратен метър",
+            "м3" => "Кубичен метър",
+            "т" => "Тон",
+            other => other,
+        }
+        .to_string()
+    }
+
+    /// Cross-check the figures of a monthly file: the GL balances, every account
+    /// rolls forward from opening to closing, and customers/suppliers tie to 411/401
+    fn reconcile(saft: &BulgarianSafT) -> Vec<SafTValidationIssue> {
+        let mut issues = Vec::new();
+        let (Some(master_files), Some(entries)) =
+            (&saft.master_files_monthly, &saft.general_ledger_entries)
+        else {
+            return issues;
+        };
+
+        const GL_PATH: &str = "/AuditFile/GeneralLedgerEntries";
+        if entries.total_debit != entries.total_credit {
+            issues.push(SafTValidationIssue::reconciliation(
+                GL_PATH,
+                format!(
+                    "Total debit {} does not equal total credit {}",
+                    entries.total_debit, entries.total_credit
+                ),
+            ));
+        }
+
+        let line_amount =
+            |amount: &Option<AmountStructure>| amount.as_ref().map_or(Decimal::ZERO, |a| a.amount);
+        let mut turnover: HashMap<&str, (Decimal, Decimal)> = HashMap::new();
+
+        for (journal_index, journal) in entries.journal.iter().enumerate() {
+            for (transaction_index, transaction) in journal.transaction.iter().enumerate() {
+                let transaction_path = format!(
+                    "{}/Journal[{}]/Transaction[{}]",
+                    GL_PATH,
+                    journal_index + 1,
+                    transaction_index + 1
+                );
+                let mut debit = Decimal::ZERO;
+                let mut credit = Decimal::ZERO;
+
+                for line in transaction
+                    .lines
+                    .debit_line
+                    .iter()
+                    .chain(transaction.lines.credit_line.iter())
+                {
+                    let line_debit = line_amount(&line.debit_amount);
+                    let line_credit = line_amount(&line.credit_amount);
+                    debit += line_debit;
+                    credit += line_credit;
+
+                    if let Some(ref code) = line.taxpayer_account_id {
+                        let account_turnover = turnover.entry(code.as_str()).or_default();
+                        account_turnover.0 += line_debit;
+                        account_turnover.1 += line_credit;
+                    }
+                }
+
+                if debit != credit {
+                    issues.push(SafTValidationIssue::reconciliation(
+                        transaction_path,
+                        format!(
+                            "Transaction {} is not balanced: debit {} / credit {}",
+                            transaction.transaction_id, debit, credit
+                        ),
+                    ));
+                }
+            }
+        }
+
+        const ACCOUNTS_PATH: &str = "/AuditFile/MasterFilesMonthly/GeneralLedgerAccounts";
+        let balance = |debit: Option<Decimal>, credit: Option<Decimal>| {
+            debit.unwrap_or_default() - credit.unwrap_or_default()
+        };
+        let mut receivables = (Decimal::ZERO, Decimal::ZERO);
+        let mut payables = (Decimal::ZERO, Decimal::ZERO);
+
+        for (index, account) in master_files
+            .general_ledger_accounts
+            .account
+            .iter()
+            .enumerate()
+        {
+            let code = account.taxpayer_account_id.as_str();
+            let opening = balance(
+                account.opening_debit_balance,
+                account.opening_credit_balance,
+            );
+            let closing = balance(
+                account.closing_debit_balance,
+                account.closing_credit_balance,
+            );
+            let (debit, credit) = turnover.remove(code).unwrap_or_default();
+
+            if opening + debit - credit != closing {
+                issues.push(SafTValidationIssue::reconciliation(
+                    format!("{}/Account[{}]", ACCOUNTS_PATH, index + 1),
+                    format!(
+                        "Account {}: opening {} + debit {} - credit {} does not equal closing {}",
+                        code, opening, debit, credit, closing
+                    ),
+                ));
+            }
+
+            if code.starts_with("411") {
+                receivables.0 += opening;
+                receivables.1 += closing;
+            } else if code.starts_with("401") {
+                payables.0 += opening;
+                payables.1 += closing;
+            }
+        }
+
+        let mut unlisted: Vec<&str> = turnover.into_keys().collect();
+        unlisted.sort_unstable();
+        for code in unlisted {
+            issues.push(SafTValidationIssue::reconciliation(
+                ACCOUNTS_PATH,
+                format!("Account {} has GL entries but is not listed", code),
+            ));
+        }
+
+        let party_totals =
+            |balances: Vec<(&Option<OpeningCloseBalance>, &Option<OpeningCloseBalance>)>| {
+                balances.into_iter().fold(
+                    (Decimal::ZERO, Decimal::ZERO),
+                    |totals, (opening, closing)| {
+                        let amount = |b: &Option<OpeningCloseBalance>| {
+                            b.as_ref()
+                                .map_or(Decimal::ZERO, |b| balance(b.debit, b.credit))
+                        };
+                        (totals.0 + amount(opening), totals.1 + amount(closing))
+                    },
+                )
+            };
+        let customers = party_totals(
+            master_files
+                .customers
+                .customer
+                .iter()
+                .map(|c| (&c.opening_balance, &c.closing_balance))
+                .collect(),
+        );
+        let suppliers = party_totals(
+            master_files
+                .suppliers
+                .supplier
+                .iter()
+                .map(|s| (&s.opening_balance, &s.closing_balance))
+                .collect(),
+        );
+
+        for (path, label, account, parties, ledger) in [
+            (
+                "/AuditFile/MasterFilesMonthly/Customers",
+                "Customer",
+                "411",
+                customers,
+                receivables,
+            ),
+            (
+                "/AuditFile/MasterFilesMonthly/Suppliers",
+                "Supplier",
+                "401",
+                suppliers,
+                payables,
+            ),
+        ] {
+            if parties.0 != ledger.0 {
+                issues.push(SafTValidationIssue::reconciliation(
+                    path,
+                    format!(
+                        "{} opening balances total {} but account {} opens at {}",
+                        label, parties.0, account, ledger.0
+                    ),
+                ));
+            }
+            if parties.1 != ledger.1 {
+                issues.push(SafTValidationIssue::reconciliation(
+                    path,
+                    format!(
+                        "{} closing balances total {} but account {} closes at {}",
+                        label, parties.1, account, ledger.1
+                    ),
+                ));
+            }
+        }
+
+        issues
+    }
+
+    fn generate_xml(
+        &self,
+        saft: &BulgarianSafT,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+
+        // Write XML declaration
+        writer.write_event(Event::Decl(quick_xml::events::BytesDecl::new(
+            "1.0",
+            Some("utf-8"),
+            None,
+        )))?;
+
+        // Start AuditFile element with namespace
+        let mut audit_file_elem = BytesStart::new("nsSAFT:AuditFile");
+        audit_file_elem.push_attribute((
+            "xmlns:doc",
+            "urn:schemas-OECD:schema-extensions:documentation xml:lang=en",
+        ));
+        audit_file_elem.push_attribute(("xmlns:nsSAFT", "mf:nra:dgti:dxxxx:declaration:v1"));
+        audit_file_elem.push_attribute(("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"));
+        writer.write_event(Event::Start(audit_file_elem))?;
+
+        // Write Header
+        self.write_header(&mut writer, &saft.header)?;
+
+        // Write content based on file type
+        match saft.file_type {
+            SafTFileType::Monthly => {
+                if let Some(ref master_files) = saft.master_files_monthly {
+                    self.write_master_files_monthly(&mut writer, master_files)?;
+                }
+                if let Some(ref corr_report) = saft.corresponding_accounts_report {
+                    self.write_corresponding_accounts_report(&mut writer, corr_report)?;
+                }
+                if let Some(ref gl_entries) = saft.general_ledger_entries {
+                    self.write_general_ledger_entries(&mut writer, gl_entries)?;
+                }
+                if let Some(ref source_docs) = saft.source_documents_monthly {
+                    self.write_source_documents_monthly(&mut writer, source_docs)?;
+                }
+            }
+            SafTFileType::Annual => {
+                if let Some(ref master_files) = saft.master_files_annual {
+                    self.write_master_files_annual(&mut writer, master_files)?;
+                }
+                if let Some(ref source_docs) = saft.source_documents_annual {
+                    self.write_source_documents_annual(&mut writer, source_docs)?;
+                }
+            }
+            SafTFileType::OnDemand => {
+                if let Some(ref master_files) = saft.master_files_on_demand {
+                    self.write_master_files_on_demand(&mut writer, master_files)?;
+                }
+                if let Some(ref source_docs) = saft.source_documents_on_demand {
+                    self.write_source_documents_on_demand(&mut writer, source_docs)?;
+                }
+            }
+        }
+
+        // End AuditFile
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:AuditFile")))?;
+
+        // Get the result
+        let result = writer.into_inner().into_inner();
+        Ok(String::from_utf8(result)?)
+    }
+
+    fn write_header(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        header: &SafTHeader,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Header")))?;
+
+        self.write_element(
+            writer,
+            "nsSAFT:AuditFileVersion",
+            &header.audit_file_version,
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:AuditFileCountry",
+            &header.audit_file_country,
+        )?;
+        if let Some(ref region) = header.audit_file_region {
+            self.write_element(writer, "nsSAFT:AuditFileRegion", region)?;
+        }
+        self.write_element(
+            writer,
+            "nsSAFT:AuditFileDateCreated",
+            &header.audit_file_date_created.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:SoftwareCompanyName",
+            &header.software_company_name,
+        )?;
+        self.write_element(writer, "nsSAFT:SoftwareID", &header.software_id)?;
+        self.write_element(writer, "nsSAFT:SoftwareVersion", &header.software_version)?;
+
+        // Write Company
+        self.write_company(writer, &header.company)?;
+
+        // Write Ownership if present
+        if let Some(ref ownership) = header.ownership {
+            self.write_ownership(writer, ownership)?;
+        }
+
+        self.write_element(
+            writer,
+            "nsSAFT:DefaultCurrencyCode",
+            &header.default_currency_code,
+        )?;
+
+        // Write Selection Criteria
+        self.write_selection_criteria(writer, &header.selection_criteria)?;
+
+        self.write_element(writer, "nsSAFT:HeaderComment", &header.header_comment)?;
+        self.write_element(
+            writer,
+            "nsSAFT:TaxAccountingBasis",
+            &header.tax_accounting_basis,
+        )?;
+
+        if let Some(ref entity) = header.tax_entity {
+            self.write_element(writer, "nsSAFT:TaxEntity", entity)?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Header")))?;
+        Ok(())
+    }
+
+    fn write_company(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        company: &CompanyInfo,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Company")))?;
+
+        self.write_element(
+            writer,
+            "nsSAFT:RegistrationNumber",
+            &company.registration_number,
+        )?;
+        self.write_element(writer, "nsSAFT:Name", &company.name)?;
+
+        // Write Address
+        self.write_address(writer, &company.address)?;
+
+        // Write Contact
+        self.write_contact(writer, &company.contact)?;
+
+        // Write Tax Registrations
+        for tax_reg in &company.tax_registration {
+            self.write_tax_registration(writer, tax_reg)?;
+        }
+
+        // Write Bank Accounts
+        for bank_acc in &company.bank_account {
+            self.write_bank_account(writer, bank_acc)?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Company")))?;
+        Ok(())
+    }
+
+    fn write_address(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        address: &Address,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Address")))?;
+
+        if let Some(ref street) = address.street_name {
+            self.write_element(writer, "nsSAFT:StreetName", street)?;
+        }
+        if let Some(ref number) = address.number {
+            self.write_element(writer, "nsSAFT:Number", number)?;
+        }
+        if let Some(ref detail) = address.additional_address_detail {
+            self.write_element(writer, "nsSAFT:AdditionalAddressDetail", detail)?;
+        }
+        if let Some(ref building) = address.building {
+            self.write_element(writer, "nsSAFT:Building", building)?;
+        }
+        self.write_element(writer, "nsSAFT:City", &address.city)?;
+        if let Some(ref postal) = address.postal_code {
+            self.write_element(writer, "nsSAFT:PostalCode", postal)?;
+        }
+        if let Some(ref region) = address.region {
+            self.write_element(writer, "nsSAFT:Region", region)?;
+        }
+        self.write_element(writer, "nsSAFT:Country", &address.country)?;
+        self.write_element(writer, "nsSAFT:AddressType", &address.address_type)?;
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Address")))?;
+        Ok(())
+    }
+
+    fn write_contact(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        contact: &Contact,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Contact")))?;
+
+        if let Some(ref person) = contact.contact_person {
+            self.write_contact_person(writer, person)?;
+        }
+        if let Some(ref phone) = contact.telephone {
+            self.write_element(writer, "nsSAFT:Telephone", phone)?;
+        }
+        if let Some(ref fax) = contact.fax {
+            self.write_element(writer, "nsSAFT:Fax", fax)?;
+        }
+        if let Some(ref email) = contact.email {
+            self.write_element(writer, "nsSAFT:Email", email)?;
+        }
+        if let Some(ref website) = contact.website {
+            self.write_element(writer, "nsSAFT:Website", website)?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Contact")))?;
+        Ok(())
+    }
+
+    fn write_contact_person(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        person: &ContactPerson,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:ContactPerson")))?;
+
+        if let Some(ref title) = person.title {
+            self.write_element(writer, "nsSAFT:Title", title)?;
+        }
+        if let Some(ref first_name) = person.first_name {
+            self.write_element(writer, "nsSAFT:FirstName", first_name)?;
+        }
+        if let Some(ref initials) = person.initials {
+            self.write_element(writer, "nsSAFT:Initials", initials)?;
+        }
+        if let Some(ref prefix) = person.last_name_prefix {
+            self.write_element(writer, "nsSAFT:LastNamePrefix", prefix)?;
+        }
+        if let Some(ref last_name) = person.last_name {
+            self.write_element(writer, "nsSAFT:LastName", last_name)?;
+        }
+        if let Some(ref birth_name) = person.birth_name {
+            self.write_element(writer, "nsSAFT:BirthName", birth_name)?;
+        }
+        if let Some(ref salutation) = person.salutation {
+            self.write_element(writer, "nsSAFT:Salutation", salutation)?;
+        }
+        for other_title in &person.other_titles {
+            self.write_element(writer, "nsSAFT:OtherTitles", other_title)?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:ContactPerson")))?;
+        Ok(())
+    }
+
+    fn write_tax_registration(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        tax_reg: &TaxRegistration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxRegistration")))?;
+
+        self.write_element(
+            writer,
+            "nsSAFT:TaxRegistrationNumber",
+            &tax_reg.tax_registration_number,
+        )?;
+        self.write_element(writer, "nsSAFT:TaxType", &tax_reg.tax_type)?;
+        self.write_element(writer, "nsSAFT:TaxNumber", &tax_reg.tax_number)?;
+        if let Some(ref date) = tax_reg.tax_verification_date {
+            self.write_element(writer, "nsSAFT:TaxVerificationDate", &date.to_string())?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxRegistration")))?;
+        Ok(())
+    }
+
+    fn write_bank_account(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        bank_acc: &BankAccount,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:BankAccount")))?;
+
+        self.write_element(writer, "nsSAFT:IBANNumber", &bank_acc.iban_number)?;
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:BankAccount")))?;
+        Ok(())
+    }
+
+    fn write_ownership(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        ownership: &Ownership,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Ownership")))?;
+
+        self.write_element(writer, "nsSAFT:IsPartOfGroup", &ownership.is_part_of_group)?;
+        // Add other ownership fields as needed
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Ownership")))?;
+        Ok(())
+    }
+
+    fn write_selection_criteria(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        criteria: &SelectionCriteria,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:SelectionCriteria")))?;
+
+        self.write_element(
+            writer,
+            "nsSAFT:TaxReportingJurisdiction",
+            &criteria.tax_reporting_jurisdiction,
+        )?;
+        if let Some(ref entity) = criteria.company_entity {
+            self.write_element(writer, "nsSAFT:CompanyEntity", entity)?;
+        } else {
+            self.write_element(writer, "nsSAFT:CompanyEntity", "")?;
+        }
+        self.write_element(
+            writer,
+            "nsSAFT:PeriodStart",
+            &criteria.period_start.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:PeriodStartYear",
+            &criteria.period_start_year.to_string(),
+        )?;
+        self.write_element(writer, "nsSAFT:PeriodEnd", &criteria.period_end.to_string())?;
+        self.write_element(
+            writer,
+            "nsSAFT:PeriodEndYear",
+            &criteria.period_end_year.to_string(),
+        )?;
+        if let Some(ref doc_type) = criteria.document_type {
+            self.write_element(writer, "nsSAFT:DocumentType", doc_type)?;
+        } else {
+            self.write_element(writer, "nsSAFT:DocumentType", "")?;
+        }
+        if let Some(ref other) = criteria.other_criteria {
+            self.write_element(writer, "nsSAFT:OtherCriteria", other)?;
+        } else {
+            self.write_element(writer, "nsSAFT:OtherCriteria", "Няма")?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SelectionCriteria")))?;
+        Ok(())
+    }
+
+    // Helper methods for writing other sections
+    fn write_master_files_monthly(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        master_files: &MasterFilesMonthly,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MasterFilesMonthly")))?;
+
+        // Write GeneralLedgerAccounts
+        self.write_general_ledger_accounts(writer, &master_files.general_ledger_accounts)?;
+
+        // Write Customers
+        self.write_customers(writer, &master_files.customers)?;
+
+        // Write Suppliers
+        self.write_suppliers(writer, &master_files.suppliers)?;
+
+        // Write TaxTable
+        self.write_tax_table(writer, &master_files.tax_table)?;
+
+        // Write UOMTable
+        self.write_uom_table(writer, &master_files.uom_table)?;
+
+        // Write Products
+        self.write_products(writer, &master_files.products)?;
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MasterFilesMonthly")))?;
+        Ok(())
+    }
+
+    fn write_general_ledger_accounts(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        accounts: &GeneralLedgerAccounts,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new(
+            "nsSAFT:GeneralLedgerAccounts",
+        )))?;
+
+        for account in &accounts.account {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Account")))?;
+
+            self.write_element(writer, "nsSAFT:AccountID", &account.account_id.to_string())?;
+            self.write_element(
+                writer,
+                "nsSAFT:AccountDescription",
+                &account.account_description,
+            )?;
+            self.write_element(
+                writer,
+                "nsSAFT:TaxpayerAccountID",
+                &account.taxpayer_account_id,
+            )?;
+            if let Some(ref category) = account.grouping_category {
+                self.write_element(writer, "nsSAFT:GroupingCategory", category)?;
+            } else {
+                self.write_element(writer, "nsSAFT:GroupingCategory", "")?;
+            }
+            if let Some(ref code) = account.grouping_code {
+                self.write_element(writer, "nsSAFT:GroupingCode", code)?;
+            } else {
+                self.write_element(writer, "nsSAFT:GroupingCode", "")?;
+            }
+            self.write_element(writer, "nsSAFT:AccountType", &account.account_type)?;
+            if let Some(ref date) = account.account_creation_date {
+                self.write_element(writer, "nsSAFT:AccountCreationDate", &date.to_string())?;
+            }
+            if let Some(ref balance) = account.opening_debit_balance {
+                self.write_element(writer, "nsSAFT:OpeningDebitBalance", &balance.to_string())?;
+            }
+            if let Some(ref balance) = account.opening_credit_balance {
+                self.write_element(writer, "nsSAFT:OpeningCreditBalance", &balance.to_string())?;
+            }
+            if let Some(ref balance) = account.closing_debit_balance {
+                self.write_element(writer, "nsSAFT:ClosingDebitBalance", &balance.to_string())?;
+            }
+            if let Some(ref balance) = account.closing_credit_balance {
+                self.write_element(writer, "nsSAFT:ClosingCreditBalance", &balance.to_string())?;
+            }
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Account")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:GeneralLedgerAccounts")))?;
+        Ok(())
+    }
+
+    fn write_customers(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        customers: &Customers,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Customers")))?;
+
+        for customer in &customers.customer {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Customer")))?;
+
+            self.write_element(writer, "nsSAFT:CustomerID", &customer.customer_id)?;
+            self.write_element(writer, "nsSAFT:CustomerName", &customer.customer_name)?;
+
+            // Write addresses
+            for addr_wrapper in &customer.addresses {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Addresses")))?;
+                self.write_address(writer, &addr_wrapper.address)?;
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Addresses")))?;
+            }
+
+            // Write contact
+            if let Some(ref contact) = customer.contact {
+                self.write_contact(writer, contact)?;
+            }
+
+            // Write tax registration
+            if let Some(ref tax_reg) = customer.tax_registration {
+                self.write_tax_registration(writer, tax_reg)?;
+            }
+
+            self.write_element(
+                writer,
+                "nsSAFT:SelfBillingIndicator",
+                &customer.self_billing_indicator,
+            )?;
+            if let Some(account_id) = customer.account_id {
+                self.write_element(writer, "nsSAFT:AccountID", &account_id.to_string())?;
+            }
+            self.write_party_balances(
+                writer,
+                &customer.opening_balance,
+                &customer.closing_balance,
+            )?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Customer")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Customers")))?;
+        Ok(())
+    }
+
+    fn write_suppliers(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        suppliers: &Suppliers,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Suppliers")))?;
+
+        for supplier in &suppliers.supplier {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Supplier")))?;
+
+            self.write_element(writer, "nsSAFT:SupplierID", &supplier.supplier_id)?;
+            self.write_element(writer, "nsSAFT:SupplierName", &supplier.supplier_name)?;
 
-        // Get the result
-        let result = writer.into_inner().into_inner();
-        Ok(String::from_utf8(result)?)
+            // Write addresses
+            for addr_wrapper in &supplier.addresses {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Addresses")))?;
+                self.write_address(writer, &addr_wrapper.address)?;
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Addresses")))?;
+            }
+
+            // Write contact
+            if let Some(ref contact) = supplier.contact {
+                self.write_contact(writer, contact)?;
+            }
+
+            // Write tax registration
+            if let Some(ref tax_reg) = supplier.tax_registration {
+                self.write_tax_registration(writer, tax_reg)?;
+            }
+
+            self.write_element(
+                writer,
+                "nsSAFT:SelfBillingIndicator",
+                &supplier.self_billing_indicator,
+            )?;
+            if let Some(account_id) = supplier.account_id {
+                self.write_element(writer, "nsSAFT:AccountID", &account_id.to_string())?;
+            }
+            self.write_party_balances(
+                writer,
+                &supplier.opening_balance,
+                &supplier.closing_balance,
+            )?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Supplier")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Suppliers")))?;
+        Ok(())
     }
 
-    fn write_header(
+    fn write_party_balances(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        header: &SafTHeader,
+        opening_balance: &Option<OpeningCloseBalance>,
+        closing_balance: &Option<OpeningCloseBalance>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Header")))?;
+        for (balance, debit_tag, credit_tag) in [
+            (
+                opening_balance,
+                "nsSAFT:OpeningDebitBalance",
+                "nsSAFT:OpeningCreditBalance",
+            ),
+            (
+                closing_balance,
+                "nsSAFT:ClosingDebitBalance",
+                "nsSAFT:ClosingCreditBalance",
+            ),
+        ] {
+            let Some(balance) = balance else { continue };
+            if let Some(ref debit) = balance.debit {
+                self.write_element(writer, debit_tag, &debit.to_string())?;
+            }
+            if let Some(ref credit) = balance.credit {
+                self.write_element(writer, credit_tag, &credit.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_tax_table(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        tax_table: &TaxTable,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxTable")))?;
+
+        for tax_code in &tax_table.tax_code_details {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxCodeDetails")))?;
+
+            self.write_element(writer, "nsSAFT:TaxCode", &tax_code.tax_code)?;
+            self.write_element(
+                writer,
+                "nsSAFT:EffectiveDate",
+                &tax_code.effective_date.to_string(),
+            )?;
+            if let Some(ref date) = tax_code.expiration_date {
+                self.write_element(writer, "nsSAFT:ExpirationDate", &date.to_string())?;
+            }
+            self.write_element(writer, "nsSAFT:Description", &tax_code.description)?;
+            if let Some(ref percentage) = tax_code.tax_percentage {
+                self.write_element(writer, "nsSAFT:TaxPercentage", &percentage.to_string())?;
+            }
+            self.write_element(writer, "nsSAFT:Country", &tax_code.country)?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxCodeDetails")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxTable")))?;
+        Ok(())
+    }
+
+    fn write_uom_table(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        uom_table: &UOMTable,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:UOMTable")))?;
+
+        for uom in &uom_table.uom_entry {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:UOMEntry")))?;
+
+            self.write_element(writer, "nsSAFT:UnitOfMeasure", &uom.unit_of_measure)?;
+            self.write_element(writer, "nsSAFT:UOMDescription", &uom.uom_description)?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:UOMEntry")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:UOMTable")))?;
+        Ok(())
+    }
+
+    fn write_products(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        products: &Products,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Products")))?;
+
+        for product in &products.product {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Product")))?;
+
+            self.write_element(writer, "nsSAFT:ProductType", &product.product_type)?;
+            self.write_element(writer, "nsSAFT:ProductCode", &product.product_code)?;
+            self.write_element(writer, "nsSAFT:Description", &product.description)?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Product")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Products")))?;
+        Ok(())
+    }
+
+    fn write_general_ledger_entries(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        gl_entries: &GeneralLedgerEntries,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:GeneralLedgerEntries")))?;
 
         self.write_element(
             writer,
-            "nsSAFT:AuditFileVersion",
-            &header.audit_file_version,
-        )?;
-        self.write_element(
-            writer,
-            "nsSAFT:AuditFileCountry",
-            &header.audit_file_country,
+            "nsSAFT:NumberOfEntries",
+            &gl_entries.number_of_entries.to_string(),
         )?;
-        if let Some(ref region) = header.audit_file_region {
-            self.write_element(writer, "nsSAFT:AuditFileRegion", region)?;
-        }
         self.write_element(
             writer,
-            "nsSAFT:AuditFileDateCreated",
-            &header.audit_file_date_created.to_string(),
+            "nsSAFT:TotalDebit",
+            &gl_entries.total_debit.to_string(),
         )?;
         self.write_element(
             writer,
-            "nsSAFT:SoftwareCompanyName",
-            &header.software_company_name,
+            "nsSAFT:TotalCredit",
+            &gl_entries.total_credit.to_string(),
         )?;
-        self.write_element(writer, "nsSAFT:SoftwareID", &header.software_id)?;
-        self.write_element(writer, "nsSAFT:SoftwareVersion", &header.software_version)?;
 
-        // Write Company
-        self.write_company(writer, &header.company)?;
+        for journal in &gl_entries.journal {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Journal")))?;
 
-        // Write Ownership if present
-        if let Some(ref ownership) = header.ownership {
-            self.write_ownership(writer, ownership)?;
-        }
+            self.write_element(writer, "nsSAFT:JournalID", &journal.journal_id)?;
+            self.write_element(writer, "nsSAFT:Description", &journal.description)?;
+            self.write_element(writer, "nsSAFT:Type", &journal.journal_type)?;
 
-        self.write_element(
-            writer,
-            "nsSAFT:DefaultCurrencyCode",
-            &header.default_currency_code,
-        )?;
+            for transaction in &journal.transaction {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Transaction")))?;
 
-        // Write Selection Criteria
-        self.write_selection_criteria(writer, &header.selection_criteria)?;
+                self.write_element(writer, "nsSAFT:TransactionID", &transaction.transaction_id)?;
+                self.write_element(writer, "nsSAFT:Period", &transaction.period.to_string())?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:PeriodYear",
+                    &transaction.period_year.to_string(),
+                )?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:TransactionDate",
+                    &transaction.transaction_date.to_string(),
+                )?;
+                if let Some(ref source_id) = transaction.source_id {
+                    self.write_element(writer, "nsSAFT:SourceID", source_id)?;
+                }
+                self.write_element(writer, "nsSAFT:Description", &transaction.description)?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:SystemEntryDate",
+                    &transaction.system_entry_date.to_string(),
+                )?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:GLPostingDate",
+                    &transaction.gl_posting_date.to_string(),
+                )?;
 
-        self.write_element(writer, "nsSAFT:HeaderComment", &header.header_comment)?;
-        self.write_element(
-            writer,
-            "nsSAFT:TaxAccountingBasis",
-            &header.tax_accounting_basis,
-        )?;
+                // Write Lines
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Lines")))?;
 
-        if let Some(ref entity) = header.tax_entity {
-            self.write_element(writer, "nsSAFT:TaxEntity", entity)?;
+                // Write Debit Lines
+                for line in &transaction.lines.debit_line {
+                    writer.write_event(Event::Start(BytesStart::new("nsSAFT:DebitLine")))?;
+                    self.write_transaction_line(writer, line)?;
+                    writer.write_event(Event::End(BytesEnd::new("nsSAFT:DebitLine")))?;
+                }
+
+                // Write Credit Lines
+                for line in &transaction.lines.credit_line {
+                    writer.write_event(Event::Start(BytesStart::new("nsSAFT:CreditLine")))?;
+                    self.write_transaction_line(writer, line)?;
+                    writer.write_event(Event::End(BytesEnd::new("nsSAFT:CreditLine")))?;
+                }
+
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Lines")))?;
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Transaction")))?;
+            }
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Journal")))?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Header")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:GeneralLedgerEntries")))?;
         Ok(())
     }
 
-    fn write_company(
+    fn write_transaction_line(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        company: &CompanyInfo,
+        line: &SafTTransactionLine,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Company")))?;
+        self.write_element(writer, "nsSAFT:RecordID", &line.record_id)?;
+        self.write_element(writer, "nsSAFT:AccountID", &line.account_id.to_string())?;
 
-        self.write_element(
-            writer,
-            "nsSAFT:RegistrationNumber",
-            &company.registration_number,
-        )?;
-        self.write_element(writer, "nsSAFT:Name", &company.name)?;
+        if let Some(ref taxpayer_id) = line.taxpayer_account_id {
+            self.write_element(writer, "nsSAFT:TaxpayerAccountID", taxpayer_id)?;
+        }
 
-        // Write Address
-        self.write_address(writer, &company.address)?;
+        if let Some(ref date) = line.value_date {
+            self.write_element(writer, "nsSAFT:ValueDate", &date.to_string())?;
+        }
+
+        if let Some(ref doc_id) = line.source_document_id {
+            self.write_element(writer, "nsSAFT:SourceDocumentID", doc_id)?;
+        }
+
+        if let Some(ref customer_id) = line.customer_id {
+            self.write_element(writer, "nsSAFT:CustomerID", customer_id)?;
+        }
+
+        if let Some(ref supplier_id) = line.supplier_id {
+            self.write_element(writer, "nsSAFT:SupplierID", supplier_id)?;
+        }
 
-        // Write Contact
-        self.write_contact(writer, &company.contact)?;
+        self.write_element(writer, "nsSAFT:Description", &line.description)?;
 
-        // Write Tax Registrations
-        for tax_reg in &company.tax_registration {
-            self.write_tax_registration(writer, tax_reg)?;
+        if let Some(ref amount) = line.debit_amount {
+            self.write_amount_structure(writer, "nsSAFT:DebitAmount", amount)?;
         }
 
-        // Write Bank Accounts
-        for bank_acc in &company.bank_account {
-            self.write_bank_account(writer, bank_acc)?;
+        if let Some(ref amount) = line.credit_amount {
+            self.write_amount_structure(writer, "nsSAFT:CreditAmount", amount)?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Company")))?;
         Ok(())
     }
 
-    fn write_address(
+    fn write_amount_structure(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        address: &Address,
+        tag: &str,
+        amount: &AmountStructure,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Address")))?;
+        writer.write_event(Event::Start(BytesStart::new(tag)))?;
 
-        if let Some(ref street) = address.street_name {
-            self.write_element(writer, "nsSAFT:StreetName", street)?;
-        }
-        if let Some(ref number) = address.number {
-            self.write_element(writer, "nsSAFT:Number", number)?;
-        }
-        if let Some(ref detail) = address.additional_address_detail {
-            self.write_element(writer, "nsSAFT:AdditionalAddressDetail", detail)?;
-        }
-        if let Some(ref building) = address.building {
-            self.write_element(writer, "nsSAFT:Building", building)?;
+        self.write_element(writer, "nsSAFT:Amount", &amount.amount.to_string())?;
+
+        if let Some(ref currency) = amount.currency_code {
+            self.write_element(writer, "nsSAFT:CurrencyCode", currency)?;
         }
-        self.write_element(writer, "nsSAFT:City", &address.city)?;
-        if let Some(ref postal) = address.postal_code {
-            self.write_element(writer, "nsSAFT:PostalCode", postal)?;
+
+        if let Some(ref curr_amount) = amount.currency_amount {
+            self.write_element(writer, "nsSAFT:CurrencyAmount", &curr_amount.to_string())?;
         }
-        if let Some(ref region) = address.region {
-            self.write_element(writer, "nsSAFT:Region", region)?;
+
+        if let Some(ref rate) = amount.exchange_rate {
+            self.write_element(writer, "nsSAFT:ExchangeRate", &rate.to_string())?;
         }
-        self.write_element(writer, "nsSAFT:Country", &address.country)?;
-        self.write_element(writer, "nsSAFT:AddressType", &address.address_type)?;
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Address")))?;
+        writer.write_event(Event::End(BytesEnd::new(tag)))?;
         Ok(())
     }
 
-    fn write_contact(
+    fn write_corresponding_accounts_report(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        contact: &Contact,
+        _report: &CorrespondingAccountsReport,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Contact")))?;
-
-        if let Some(ref person) = contact.contact_person {
-            self.write_contact_person(writer, person)?;
-        }
-        if let Some(ref phone) = contact.telephone {
-            self.write_element(writer, "nsSAFT:Telephone", phone)?;
-        }
-        if let Some(ref fax) = contact.fax {
-            self.write_element(writer, "nsSAFT:Fax", fax)?;
-        }
-        if let Some(ref email) = contact.email {
-            self.write_element(writer, "nsSAFT:Email", email)?;
-        }
-        if let Some(ref website) = contact.website {
-            self.write_element(writer, "nsSAFT:Website", website)?;
-        }
-
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Contact")))?;
+        // Implementation for Corresponding Accounts Report
+        writer.write_event(Event::Start(BytesStart::new(
+            "nsSAFT:CorrespondingAccountsReport",
+        )))?;
+        // TODO: Write report entries
+        writer.write_event(Event::End(BytesEnd::new(
+            "nsSAFT:CorrespondingAccountsReport",
+        )))?;
         Ok(())
     }
 
-    fn write_contact_person(
+    fn write_source_documents_monthly(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        person: &ContactPerson,
+        docs: &SourceDocumentsMonthly,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:ContactPerson")))?;
+        writer.write_event(Event::Start(BytesStart::new(
+            "nsSAFT:SourceDocumentsMonthly",
+        )))?;
 
-        if let Some(ref title) = person.title {
-            self.write_element(writer, "nsSAFT:Title", title)?;
-        }
-        if let Some(ref first_name) = person.first_name {
-            self.write_element(writer, "nsSAFT:FirstName", first_name)?;
-        }
-        if let Some(ref initials) = person.initials {
-            self.write_element(writer, "nsSAFT:Initials", initials)?;
-        }
-        if let Some(ref prefix) = person.last_name_prefix {
-            self.write_element(writer, "nsSAFT:LastNamePrefix", prefix)?;
-        }
-        if let Some(ref last_name) = person.last_name {
-            self.write_element(writer, "nsSAFT:LastName", last_name)?;
-        }
-        if let Some(ref birth_name) = person.birth_name {
-            self.write_element(writer, "nsSAFT:BirthName", birth_name)?;
+        if let Some(ref sales_invoices) = docs.sales_invoices {
+            self.write_sales_invoices(writer, sales_invoices)?;
         }
-        if let Some(ref salutation) = person.salutation {
-            self.write_element(writer, "nsSAFT:Salutation", salutation)?;
+
+        if let Some(ref purchase_invoices) = docs.purchase_invoices {
+            self.write_purchase_invoices(writer, purchase_invoices)?;
         }
-        for other_title in &person.other_titles {
-            self.write_element(writer, "nsSAFT:OtherTitles", other_title)?;
+
+        if let Some(ref payments) = docs.payments {
+            self.write_payments(writer, payments)?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:ContactPerson")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SourceDocumentsMonthly")))?;
         Ok(())
     }
 
-    fn write_tax_registration(
+    fn write_sales_invoices(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        tax_reg: &TaxRegistration,
+        sales_invoices: &SalesInvoices,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxRegistration")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:SalesInvoices")))?;
 
         self.write_element(
             writer,
-            "nsSAFT:TaxRegistrationNumber",
-            &tax_reg.tax_registration_number,
+            "nsSAFT:NumberOfEntries",
+            &sales_invoices.number_of_entries.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:TotalDebit",
+            &sales_invoices.total_debit.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:TotalCredit",
+            &sales_invoices.total_credit.to_string(),
         )?;
-        self.write_element(writer, "nsSAFT:TaxType", &tax_reg.tax_type)?;
-        self.write_element(writer, "nsSAFT:TaxNumber", &tax_reg.tax_number)?;
-        if let Some(ref date) = tax_reg.tax_verification_date {
-            self.write_element(writer, "nsSAFT:TaxVerificationDate", &date.to_string())?;
-        }
-
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxRegistration")))?;
-        Ok(())
-    }
 
-    fn write_bank_account(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        bank_acc: &BankAccount,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:BankAccount")))?;
+        for invoice in &sales_invoices.invoice {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Invoice")))?;
 
-        self.write_element(writer, "nsSAFT:IBANNumber", &bank_acc.iban_number)?;
+            self.write_element(writer, "nsSAFT:InvoiceNo", &invoice.invoice_no)?;
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:BankAccount")))?;
-        Ok(())
-    }
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:CustomerInfo")))?;
+            self.write_element(
+                writer,
+                "nsSAFT:CustomerID",
+                &invoice.customer_info.customer_id,
+            )?;
+            if let Some(ref address) = invoice.customer_info.billing_address {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:BillingAddress")))?;
+                self.write_address(writer, address)?;
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:BillingAddress")))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:CustomerInfo")))?;
 
-    fn write_ownership(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        ownership: &Ownership,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Ownership")))?;
+            self.write_invoice_body(
+                writer,
+                &invoice.invoice_date,
+                &invoice.invoice_type,
+                &invoice.self_billing_indicator,
+                &invoice.transaction_id,
+                &invoice.currency_code,
+                &invoice.exchange_rate,
+                &invoice.lines,
+                &invoice.document_totals,
+            )?;
 
-        self.write_element(writer, "nsSAFT:IsPartOfGroup", &ownership.is_part_of_group)?;
-        // Add other ownership fields as needed
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Invoice")))?;
+        }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Ownership")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SalesInvoices")))?;
         Ok(())
     }
 
-    fn write_selection_criteria(
+    fn write_purchase_invoices(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        criteria: &SelectionCriteria,
+        purchase_invoices: &PurchaseInvoices,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:SelectionCriteria")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:PurchaseInvoices")))?;
 
         self.write_element(
             writer,
-            "nsSAFT:TaxReportingJurisdiction",
-            &criteria.tax_reporting_jurisdiction,
+            "nsSAFT:NumberOfEntries",
+            &purchase_invoices.number_of_entries.to_string(),
         )?;
-        if let Some(ref entity) = criteria.company_entity {
-            self.write_element(writer, "nsSAFT:CompanyEntity", entity)?;
-        } else {
-            self.write_element(writer, "nsSAFT:CompanyEntity", "")?;
-        }
         self.write_element(
             writer,
-            "nsSAFT:PeriodStart",
-            &criteria.period_start.to_string(),
+            "nsSAFT:TotalDebit",
+            &purchase_invoices.total_debit.to_string(),
         )?;
         self.write_element(
             writer,
-            "nsSAFT:PeriodStartYear",
-            &criteria.period_start_year.to_string(),
+            "nsSAFT:TotalCredit",
+            &purchase_invoices.total_credit.to_string(),
         )?;
-        self.write_element(writer, "nsSAFT:PeriodEnd", &criteria.period_end.to_string())?;
+
+        for invoice in &purchase_invoices.invoice {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Invoice")))?;
+
+            self.write_element(writer, "nsSAFT:InvoiceNo", &invoice.invoice_no)?;
+
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:SupplierInfo")))?;
+            self.write_element(
+                writer,
+                "nsSAFT:SupplierID",
+                &invoice.supplier_info.supplier_id,
+            )?;
+            if let Some(ref address) = invoice.supplier_info.billing_address {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:BillingAddress")))?;
+                self.write_address(writer, address)?;
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:BillingAddress")))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:SupplierInfo")))?;
+
+            self.write_invoice_body(
+                writer,
+                &invoice.invoice_date,
+                &invoice.invoice_type,
+                &invoice.self_billing_indicator,
+                &invoice.transaction_id,
+                &invoice.currency_code,
+                &invoice.exchange_rate,
+                &invoice.lines,
+                &invoice.document_totals,
+            )?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Invoice")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:PurchaseInvoices")))?;
+        Ok(())
+    }
+
+    /// Elements shared by sales and purchase invoices after the party info
+    #[allow(clippy::too_many_arguments)]
+    fn write_invoice_body(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        invoice_date: &NaiveDate,
+        invoice_type: &str,
+        self_billing_indicator: &str,
+        transaction_id: &Option<String>,
+        currency_code: &Option<String>,
+        exchange_rate: &Option<Decimal>,
+        lines: &[InvoiceLine],
+        document_totals: &DocumentTotals,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.write_element(writer, "nsSAFT:InvoiceDate", &invoice_date.to_string())?;
+        self.write_element(writer, "nsSAFT:InvoiceType", invoice_type)?;
         self.write_element(
             writer,
-            "nsSAFT:PeriodEndYear",
-            &criteria.period_end_year.to_string(),
+            "nsSAFT:SelfBillingIndicator",
+            self_billing_indicator,
         )?;
-        if let Some(ref doc_type) = criteria.document_type {
-            self.write_element(writer, "nsSAFT:DocumentType", doc_type)?;
-        } else {
-            self.write_element(writer, "nsSAFT:DocumentType", "")?;
+
+        if let Some(ref id) = transaction_id {
+            self.write_element(writer, "nsSAFT:TransactionID", id)?;
         }
-        if let Some(ref other) = criteria.other_criteria {
-            self.write_element(writer, "nsSAFT:OtherCriteria", other)?;
-        } else {
-            self.write_element(writer, "nsSAFT:OtherCriteria", "Няма")?;
+
+        if let Some(ref currency) = currency_code {
+            self.write_element(writer, "nsSAFT:CurrencyCode", currency)?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SelectionCriteria")))?;
+        if let Some(ref rate) = exchange_rate {
+            self.write_element(writer, "nsSAFT:ExchangeRate", &rate.to_string())?;
+        }
+
+        for line in lines {
+            self.write_invoice_line(writer, line)?;
+        }
+
+        self.write_document_totals(writer, "nsSAFT:InvoiceDocumentTotals", document_totals)?;
         Ok(())
     }
 
-    // Helper methods for writing other sections
-    fn write_master_files_monthly(
+    fn write_invoice_line(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        master_files: &MasterFilesMonthly,
+        line: &InvoiceLine,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MasterFilesMonthly")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:InvoiceLine")))?;
 
-        // Write GeneralLedgerAccounts
-        self.write_general_ledger_accounts(writer, &master_files.general_ledger_accounts)?;
+        self.write_element(writer, "nsSAFT:LineNumber", &line.line_number)?;
 
-        // Write Customers
-        self.write_customers(writer, &master_files.customers)?;
+        if let Some(ref account_id) = line.account_id {
+            self.write_element(writer, "nsSAFT:AccountID", &account_id.to_string())?;
+        }
 
-        // Write Suppliers
-        self.write_suppliers(writer, &master_files.suppliers)?;
+        if let Some(ref code) = line.product_code {
+            self.write_element(writer, "nsSAFT:ProductCode", code)?;
+        }
 
-        // Write TaxTable
-        self.write_tax_table(writer, &master_files.tax_table)?;
+        if let Some(ref description) = line.product_description {
+            self.write_element(writer, "nsSAFT:ProductDescription", description)?;
+        }
 
-        // Write UOMTable
-        self.write_uom_table(writer, &master_files.uom_table)?;
+        if let Some(ref quantity) = line.quantity {
+            self.write_element(writer, "nsSAFT:Quantity", &quantity.to_string())?;
+        }
 
-        // Write Products
-        self.write_products(writer, &master_files.products)?;
+        if let Some(ref uom) = line.unit_of_measure {
+            self.write_element(writer, "nsSAFT:InvoiceUOM", uom)?;
+        }
+
+        if let Some(ref price) = line.unit_price {
+            self.write_element(writer, "nsSAFT:UnitPrice", &price.to_string())?;
+        }
+
+        if let Some(ref date) = line.tax_point_date {
+            self.write_element(writer, "nsSAFT:TaxPointDate", &date.to_string())?;
+        }
+
+        if let Some(ref description) = line.description {
+            self.write_element(writer, "nsSAFT:Description", description)?;
+        }
+
+        self.write_amount_structure(writer, "nsSAFT:InvoiceLineAmount", &line.line_amount)?;
+        self.write_element(
+            writer,
+            "nsSAFT:DebitCreditIndicator",
+            &line.debit_credit_indicator,
+        )?;
+
+        if let Some(ref tax) = line.tax {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxInformation")))?;
+            self.write_element(writer, "nsSAFT:TaxType", &tax.tax_type)?;
+            self.write_element(writer, "nsSAFT:TaxCode", &tax.tax_code)?;
+            if let Some(ref percentage) = tax.tax_percentage {
+                self.write_element(writer, "nsSAFT:TaxPercentage", &percentage.to_string())?;
+            }
+            if let Some(ref base) = line.tax_base {
+                self.write_element(writer, "nsSAFT:TaxBase", &base.to_string())?;
+            }
+            if let Some(ref amount) = tax.tax_amount {
+                self.write_amount_structure(writer, "nsSAFT:TaxAmount", amount)?;
+            }
+            self.write_element(writer, "nsSAFT:Country", &tax.tax_country_region)?;
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxInformation")))?;
+        }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MasterFilesMonthly")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:InvoiceLine")))?;
         Ok(())
     }
 
-    fn write_general_ledger_accounts(
+    fn write_document_totals(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        accounts: &GeneralLedgerAccounts,
+        tag: &str,
+        totals: &DocumentTotals,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new(
-            "nsSAFT:GeneralLedgerAccounts",
-        )))?;
+        writer.write_event(Event::Start(BytesStart::new(tag)))?;
+        self.write_element(writer, "nsSAFT:TaxPayable", &totals.tax_payable.to_string())?;
+        self.write_element(writer, "nsSAFT:NetTotal", &totals.net_total.to_string())?;
+        self.write_element(writer, "nsSAFT:GrossTotal", &totals.gross_total.to_string())?;
+        writer.write_event(Event::End(BytesEnd::new(tag)))?;
+        Ok(())
+    }
 
-        for account in &accounts.account {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Account")))?;
+    fn write_payments(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        payments: &Payments,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Payments")))?;
 
-            self.write_element(writer, "nsSAFT:AccountID", &account.account_id.to_string())?;
+        self.write_element(
+            writer,
+            "nsSAFT:NumberOfEntries",
+            &payments.number_of_entries.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:TotalDebit",
+            &payments.total_debit.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:TotalCredit",
+            &payments.total_credit.to_string(),
+        )?;
+
+        for payment in &payments.payment {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Payment")))?;
+
+            self.write_element(writer, "nsSAFT:PaymentRefNo", &payment.payment_ref_no)?;
+            self.write_element(writer, "nsSAFT:Period", &payment.period.to_string())?;
             self.write_element(
                 writer,
-                "nsSAFT:AccountDescription",
-                &account.account_description,
+                "nsSAFT:PeriodYear",
+                &payment.period_year.to_string(),
             )?;
+            self.write_element(writer, "nsSAFT:TransactionID", &payment.transaction_id)?;
             self.write_element(
                 writer,
-                "nsSAFT:TaxpayerAccountID",
-                &account.taxpayer_account_id,
+                "nsSAFT:TransactionDate",
+                &payment.transaction_date.to_string(),
             )?;
-            if let Some(ref category) = account.grouping_category {
-                self.write_element(writer, "nsSAFT:GroupingCategory", category)?;
-            } else {
-                self.write_element(writer, "nsSAFT:GroupingCategory", "")?;
-            }
-            if let Some(ref code) = account.grouping_code {
-                self.write_element(writer, "nsSAFT:GroupingCode", code)?;
-            } else {
-                self.write_element(writer, "nsSAFT:GroupingCode", "")?;
-            }
-            self.write_element(writer, "nsSAFT:AccountType", &account.account_type)?;
-            if let Some(ref date) = account.account_creation_date {
-                self.write_element(writer, "nsSAFT:AccountCreationDate", &date.to_string())?;
+            self.write_element(writer, "nsSAFT:PaymentMethod", &payment.payment_method)?;
+            self.write_element(writer, "nsSAFT:Description", &payment.description)?;
+            if let Some(ref system_id) = payment.system_id {
+                self.write_element(writer, "nsSAFT:SystemID", system_id)?;
             }
-            if let Some(ref balance) = account.opening_debit_balance {
-                self.write_element(writer, "nsSAFT:OpeningDebitBalance", &balance.to_string())?;
-            }
-            if let Some(ref balance) = account.opening_credit_balance {
-                self.write_element(writer, "nsSAFT:OpeningCreditBalance", &balance.to_string())?;
-            }
-            if let Some(ref balance) = account.closing_debit_balance {
-                self.write_element(writer, "nsSAFT:ClosingDebitBalance", &balance.to_string())?;
+            if let Some(ref source_id) = payment.source_id {
+                self.write_element(writer, "nsSAFT:SourceID", source_id)?;
             }
-            if let Some(ref balance) = account.closing_credit_balance {
-                self.write_element(writer, "nsSAFT:ClosingCreditBalance", &balance.to_string())?;
+
+            for line in &payment.lines {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:PaymentLine")))?;
+
+                self.write_element(writer, "nsSAFT:LineNumber", &line.line_number)?;
+                if let Some(ref doc_id) = line.source_document_id {
+                    self.write_element(writer, "nsSAFT:SourceDocumentID", doc_id)?;
+                }
+                self.write_element(writer, "nsSAFT:AccountID", &line.account_id.to_string())?;
+                if let Some(ref customer_id) = line.customer_id {
+                    self.write_element(writer, "nsSAFT:CustomerID", customer_id)?;
+                }
+                if let Some(ref supplier_id) = line.supplier_id {
+                    self.write_element(writer, "nsSAFT:SupplierID", supplier_id)?;
+                }
+                self.write_element(writer, "nsSAFT:Description", &line.description)?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:DebitCreditIndicator",
+                    &line.debit_credit_indicator,
+                )?;
+                self.write_amount_structure(
+                    writer,
+                    "nsSAFT:PaymentLineAmount",
+                    &line.payment_line_amount,
+                )?;
+
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:PaymentLine")))?;
             }
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Account")))?;
+            self.write_document_totals(writer, "nsSAFT:DocumentTotals", &payment.document_totals)?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Payment")))?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:GeneralLedgerAccounts")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Payments")))?;
         Ok(())
     }
 
-    fn write_customers(
+    fn write_master_files_annual(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        customers: &Customers,
+        master_files: &MasterFilesAnnual,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Customers")))?;
-
-        for customer in &customers.customer {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Customer")))?;
-
-            self.write_element(writer, "nsSAFT:CustomerID", &customer.customer_id)?;
-            self.write_element(writer, "nsSAFT:CustomerName", &customer.customer_name)?;
-
-            // Write addresses
-            for addr_wrapper in &customer.addresses {
-                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Addresses")))?;
-                self.write_address(writer, &addr_wrapper.address)?;
-                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Addresses")))?;
-            }
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MasterFilesAnnual")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Assets")))?;
 
-            // Write contact
-            if let Some(ref contact) = customer.contact {
-                self.write_contact(writer, contact)?;
-            }
+        for asset in &master_files.assets.asset {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Asset")))?;
 
-            // Write tax registration
-            if let Some(ref tax_reg) = customer.tax_registration {
-                self.write_tax_registration(writer, tax_reg)?;
+            self.write_element(writer, "nsSAFT:AssetID", &asset.asset_id)?;
+            self.write_element(writer, "nsSAFT:AccountID", &asset.account_id)?;
+            self.write_element(writer, "nsSAFT:Description", &asset.description)?;
+            if let Some(ref supplier_id) = asset.supplier_id {
+                self.write_element(writer, "nsSAFT:SupplierID", supplier_id)?;
             }
-
             self.write_element(
                 writer,
-                "nsSAFT:SelfBillingIndicator",
-                &customer.self_billing_indicator,
+                "nsSAFT:DateOfAcquisition",
+                &asset.date_of_acquisition.to_string(),
             )?;
+            if let Some(ref date) = asset.start_up_date {
+                self.write_element(writer, "nsSAFT:StartUpDate", &date.to_string())?;
+            }
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Customer")))?;
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Valuations")))?;
+            for valuation in &asset.valuations {
+                self.write_asset_valuation(writer, valuation)?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Valuations")))?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Asset")))?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Customers")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Assets")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MasterFilesAnnual")))?;
         Ok(())
     }
 
-    fn write_suppliers(
+    fn write_asset_valuation(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        suppliers: &Suppliers,
+        valuation: &AssetValuation,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Suppliers")))?;
-
-        for supplier in &suppliers.supplier {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Supplier")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Valuation")))?;
 
-            self.write_element(writer, "nsSAFT:SupplierID", &supplier.supplier_id)?;
-            self.write_element(writer, "nsSAFT:SupplierName", &supplier.supplier_name)?;
-
-            // Write addresses
-            for addr_wrapper in &supplier.addresses {
-                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Addresses")))?;
-                self.write_address(writer, &addr_wrapper.address)?;
-                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Addresses")))?;
-            }
-
-            // Write contact
-            if let Some(ref contact) = supplier.contact {
-                self.write_contact(writer, contact)?;
-            }
+        self.write_element(
+            writer,
+            "nsSAFT:AssetValuationType",
+            &valuation.asset_valuation_type,
+        )?;
+        if let Some(ref class) = valuation.valuation_class {
+            self.write_element(writer, "nsSAFT:ValuationClass", class)?;
+        }
+        self.write_element(
+            writer,
+            "nsSAFT:AcquisitionAndProductionCostsBegin",
+            &valuation.acquisition_and_production_costs_begin.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:AcquisitionAndProductionCostsEnd",
+            &valuation.acquisition_and_production_costs_end.to_string(),
+        )?;
+        if let Some(ref life) = valuation.asset_life_year {
+            self.write_element(writer, "nsSAFT:AssetLifeYear", &life.to_string())?;
+        }
+        self.write_element(
+            writer,
+            "nsSAFT:AssetAddition",
+            &valuation.asset_addition.to_string(),
+        )?;
+        self.write_element(writer, "nsSAFT:Transfers", &valuation.transfers.to_string())?;
+        self.write_element(
+            writer,
+            "nsSAFT:AssetDisposal",
+            &valuation.asset_disposal.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:BookValueBegin",
+            &valuation.book_value_begin.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:DepreciationMethod",
+            &valuation.depreciation_method,
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:DepreciationPercentage",
+            &valuation.depreciation_percentage.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:DepreciationForPeriod",
+            &valuation.depreciation_for_period.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:AppreciationForPeriod",
+            &valuation.appreciation_for_period.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:AccumulatedDepreciation",
+            &valuation.accumulated_depreciation.to_string(),
+        )?;
+        self.write_element(
+            writer,
+            "nsSAFT:BookValueEnd",
+            &valuation.book_value_end.to_string(),
+        )?;
 
-            // Write tax registration
-            if let Some(ref tax_reg) = supplier.tax_registration {
-                self.write_tax_registration(writer, tax_reg)?;
-            }
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Valuation")))?;
+        Ok(())
+    }
 
-            self.write_element(
-                writer,
-                "nsSAFT:SelfBillingIndicator",
-                &supplier.self_billing_indicator,
-            )?;
+    fn write_source_documents_annual(
+        &self,
+        writer: &mut Writer<Cursor<Vec<u8>>>,
+        docs: &SourceDocumentsAnnual,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        writer.write_event(Event::Start(BytesStart::new(
+            "nsSAFT:SourceDocumentsAnnual",
+        )))?;
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Supplier")))?;
+        if let Some(ref asset_transactions) = docs.asset_transactions {
+            self.write_asset_transactions(writer, asset_transactions)?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Suppliers")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SourceDocumentsAnnual")))?;
         Ok(())
     }
 
-    fn write_tax_table(
+    fn write_asset_transactions(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        tax_table: &TaxTable,
+        asset_transactions: &AssetTransactions,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxTable")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:AssetTransactions")))?;
 
-        for tax_code in &tax_table.tax_code_details {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:TaxCodeDetails")))?;
+        self.write_element(
+            writer,
+            "nsSAFT:NumberOfAssetTransactions",
+            &asset_transactions.number_of_asset_transactions.to_string(),
+        )?;
+
+        for transaction in &asset_transactions.asset_transaction {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:AssetTransaction")))?;
 
-            self.write_element(writer, "nsSAFT:TaxCode", &tax_code.tax_code)?;
             self.write_element(
                 writer,
-                "nsSAFT:EffectiveDate",
-                &tax_code.effective_date.to_string(),
+                "nsSAFT:AssetTransactionID",
+                &transaction.asset_transaction_id,
             )?;
-            if let Some(ref date) = tax_code.expiration_date {
-                self.write_element(writer, "nsSAFT:ExpirationDate", &date.to_string())?;
+            self.write_element(writer, "nsSAFT:AssetID", &transaction.asset_id)?;
+            self.write_element(
+                writer,
+                "nsSAFT:AssetTransactionType",
+                &transaction.asset_transaction_type,
+            )?;
+            if let Some(ref description) = transaction.description {
+                self.write_element(writer, "nsSAFT:Description", description)?;
             }
-            self.write_element(writer, "nsSAFT:Description", &tax_code.description)?;
-            if let Some(ref percentage) = tax_code.tax_percentage {
-                self.write_element(writer, "nsSAFT:TaxPercentage", &percentage.to_string())?;
+            self.write_element(
+                writer,
+                "nsSAFT:AssetTransactionDate",
+                &transaction.asset_transaction_date.to_string(),
+            )?;
+            if let Some(ref supplier_id) = transaction.supplier_id {
+                self.write_element(writer, "nsSAFT:SupplierID", supplier_id)?;
+            }
+            if let Some(ref customer_id) = transaction.customer_id {
+                self.write_element(writer, "nsSAFT:CustomerID", customer_id)?;
+            }
+            if let Some(ref transaction_id) = transaction.transaction_id {
+                self.write_element(writer, "nsSAFT:TransactionID", transaction_id)?;
             }
-            self.write_element(writer, "nsSAFT:Country", &tax_code.country)?;
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxCodeDetails")))?;
+            writer.write_event(Event::Start(BytesStart::new(
+                "nsSAFT:AssetTransactionValuations",
+            )))?;
+            for valuation in &transaction.valuations {
+                writer.write_event(Event::Start(BytesStart::new(
+                    "nsSAFT:AssetTransactionValuation",
+                )))?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:AssetValuationType",
+                    &valuation.asset_valuation_type,
+                )?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:AcquisitionAndProductionCostsOnTransaction",
+                    &valuation
+                        .acquisition_and_production_costs_on_transaction
+                        .to_string(),
+                )?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:BookValueOnTransaction",
+                    &valuation.book_value_on_transaction.to_string(),
+                )?;
+                self.write_element(
+                    writer,
+                    "nsSAFT:AssetTransactionAmount",
+                    &valuation.asset_transaction_amount.to_string(),
+                )?;
+                writer.write_event(Event::End(BytesEnd::new(
+                    "nsSAFT:AssetTransactionValuation",
+                )))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new(
+                "nsSAFT:AssetTransactionValuations",
+            )))?;
+
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:AssetTransaction")))?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:TaxTable")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:AssetTransactions")))?;
         Ok(())
     }
 
-    fn write_uom_table(
+    fn write_master_files_on_demand(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        uom_table: &UOMTable,
+        master_files: &MasterFilesOnDemand,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:UOMTable")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MasterFilesOnDemand")))?;
 
-        for uom in &uom_table.uom_entry {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:UOMEntry")))?;
+        if let Some(ref tax_table) = master_files.tax_table {
+            self.write_tax_table(writer, tax_table)?;
+        }
 
-            self.write_element(writer, "nsSAFT:UnitOfMeasure", &uom.unit_of_measure)?;
-            self.write_element(writer, "nsSAFT:UOMDescription", &uom.uom_description)?;
+        self.write_uom_table(writer, &master_files.uom_table)?;
+
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MovementTypeTable")))?;
+        for entry in &master_files.movement_type_table.movement_type_entry {
+            writer.write_event(Event::Start(BytesStart::new(
+                "nsSAFT:MovementTypeTableEntry",
+            )))?;
+            self.write_element(writer, "nsSAFT:MovementType", &entry.movement_type)?;
+            self.write_element(writer, "nsSAFT:Description", &entry.description)?;
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:MovementTypeTableEntry")))?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MovementTypeTable")))?;
+
+        self.write_products(writer, &master_files.products)?;
+
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:PhysicalStock")))?;
+        for entry in &master_files.physical_stock.physical_stock_entry {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:PhysicalStockEntry")))?;
+
+            self.write_element(writer, "nsSAFT:WarehouseID", &entry.warehouse_id)?;
+            self.write_element(writer, "nsSAFT:ProductCode", &entry.product_code)?;
+            self.write_element(writer, "nsSAFT:ProductType", &entry.product_type)?;
+            self.write_element(writer, "nsSAFT:StockAccountNo", &entry.stock_account_no)?;
+            self.write_element(writer, "nsSAFT:UOMPhysicalStock", &entry.uom_physical_stock)?;
+            self.write_element(
+                writer,
+                "nsSAFT:UOMToUOMBaseConversionFactor",
+                &entry.uom_to_uom_base_conversion_factor.to_string(),
+            )?;
+            self.write_element(writer, "nsSAFT:UnitPrice", &entry.unit_price.to_string())?;
+            self.write_element(
+                writer,
+                "nsSAFT:OpeningStockQuantity",
+                &entry.opening_stock_quantity.to_string(),
+            )?;
+            self.write_element(
+                writer,
+                "nsSAFT:OpeningStockValue",
+                &entry.opening_stock_value.to_string(),
+            )?;
+            self.write_element(
+                writer,
+                "nsSAFT:ClosingStockQuantity",
+                &entry.closing_stock_quantity.to_string(),
+            )?;
+            self.write_element(
+                writer,
+                "nsSAFT:ClosingStockValue",
+                &entry.closing_stock_value.to_string(),
+            )?;
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:UOMEntry")))?;
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:PhysicalStockEntry")))?;
         }
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:PhysicalStock")))?;
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:UOMTable")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MasterFilesOnDemand")))?;
         Ok(())
     }
 
-    fn write_products(
+    fn write_source_documents_on_demand(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        products: &Products,
+        docs: &SourceDocumentsOnDemand,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:Products")))?;
+        writer.write_event(Event::Start(BytesStart::new(
+            "nsSAFT:SourceDocumentsOnDemand",
+        )))?;
 
-        for product in &products.product {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Product")))?;
+        if let Some(ref sales_invoices) = docs.sales_invoices {
+            self.write_sales_invoices(writer, sales_invoices)?;
+        }
 
-            self.write_element(writer, "nsSAFT:ProductType", &product.product_type)?;
-            self.write_element(writer, "nsSAFT:ProductCode", &product.product_code)?;
-            self.write_element(writer, "nsSAFT:Description", &product.description)?;
+        if let Some(ref purchase_invoices) = docs.purchase_invoices {
+            self.write_purchase_invoices(writer, purchase_invoices)?;
+        }
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Product")))?;
+        if let Some(ref movement_of_goods) = docs.movement_of_goods {
+            self.write_movement_of_goods(writer, movement_of_goods)?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:Products")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SourceDocumentsOnDemand")))?;
         Ok(())
     }
 
-    fn write_general_ledger_entries(
+    fn write_movement_of_goods(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        gl_entries: &GeneralLedgerEntries,
+        movement_of_goods: &MovementOfGoods,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:GeneralLedgerEntries")))?;
+        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MovementOfGoods")))?;
 
         self.write_element(
             writer,
-            "nsSAFT:NumberOfEntries",
-            &gl_entries.number_of_entries.to_string(),
+            "nsSAFT:NumberOfMovementLines",
+            &movement_of_goods.number_of_movement_lines.to_string(),
         )?;
         self.write_element(
             writer,
-            "nsSAFT:TotalDebit",
-            &gl_entries.total_debit.to_string(),
+            "nsSAFT:TotalQuantityReceived",
+            &movement_of_goods.total_quantity_received.to_string(),
         )?;
         self.write_element(
             writer,
-            "nsSAFT:TotalCredit",
-            &gl_entries.total_credit.to_string(),
+            "nsSAFT:TotalQuantityIssued",
+            &movement_of_goods.total_quantity_issued.to_string(),
         )?;
 
-        for journal in &gl_entries.journal {
-            writer.write_event(Event::Start(BytesStart::new("nsSAFT:Journal")))?;
+        for movement in &movement_of_goods.stock_movement {
+            writer.write_event(Event::Start(BytesStart::new("nsSAFT:StockMovement")))?;
 
-            self.write_element(writer, "nsSAFT:JournalID", &journal.journal_id)?;
-            self.write_element(writer, "nsSAFT:Description", &journal.description)?;
-            self.write_element(writer, "nsSAFT:Type", &journal.journal_type)?;
+            self.write_element(
+                writer,
+                "nsSAFT:MovementReference",
+                &movement.movement_reference,
+            )?;
+            self.write_element(
+                writer,
+                "nsSAFT:MovementDate",
+                &movement.movement_date.to_string(),
+            )?;
+            self.write_element(writer, "nsSAFT:MovementType", &movement.movement_type)?;
+            if let Some(ref document) = movement.document_reference {
+                self.write_element(writer, "nsSAFT:DocumentReference", document)?;
+            }
 
-            for transaction in &journal.transaction {
-                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Transaction")))?;
+            for line in &movement.lines {
+                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Line")))?;
 
-                self.write_element(writer, "nsSAFT:TransactionID", &transaction.transaction_id)?;
-                self.write_element(writer, "nsSAFT:Period", &transaction.period.to_string())?;
-                self.write_element(
-                    writer,
-                    "nsSAFT:PeriodYear",
-                    &transaction.period_year.to_string(),
-                )?;
-                self.write_element(
-                    writer,
-                    "nsSAFT:TransactionDate",
-                    &transaction.transaction_date.to_string(),
-                )?;
-                if let Some(ref source_id) = transaction.source_id {
-                    self.write_element(writer, "nsSAFT:SourceID", source_id)?;
+                self.write_element(writer, "nsSAFT:LineNumber", &line.line_number)?;
+                self.write_element(writer, "nsSAFT:AccountID", &line.account_id)?;
+                if let Some(ref transaction_id) = line.transaction_id {
+                    self.write_element(writer, "nsSAFT:TransactionID", transaction_id)?;
                 }
-                self.write_element(writer, "nsSAFT:Description", &transaction.description)?;
-                self.write_element(
-                    writer,
-                    "nsSAFT:SystemEntryDate",
-                    &transaction.system_entry_date.to_string(),
-                )?;
+                self.write_element(writer, "nsSAFT:ProductCode", &line.product_code)?;
+                self.write_element(writer, "nsSAFT:Quantity", &line.quantity.to_string())?;
+                self.write_element(writer, "nsSAFT:UnitOfMeasure", &line.unit_of_measure)?;
                 self.write_element(
                     writer,
-                    "nsSAFT:GLPostingDate",
-                    &transaction.gl_posting_date.to_string(),
+                    "nsSAFT:UOMToUOMBaseConversionFactor",
+                    &line.uom_to_uom_base_conversion_factor.to_string(),
                 )?;
-
-                // Write Lines
-                writer.write_event(Event::Start(BytesStart::new("nsSAFT:Lines")))?;
-
-                // Write Debit Lines
-                for line in &transaction.lines.debit_line {
-                    writer.write_event(Event::Start(BytesStart::new("nsSAFT:DebitLine")))?;
-                    self.write_transaction_line(writer, line)?;
-                    writer.write_event(Event::End(BytesEnd::new("nsSAFT:DebitLine")))?;
-                }
-
-                // Write Credit Lines
-                for line in &transaction.lines.credit_line {
-                    writer.write_event(Event::Start(BytesStart::new("nsSAFT:CreditLine")))?;
-                    self.write_transaction_line(writer, line)?;
-                    writer.write_event(Event::End(BytesEnd::new("nsSAFT:CreditLine")))?;
+                self.write_element(writer, "nsSAFT:BookValue", &line.book_value.to_string())?;
+                if let Some(ref description) = line.description {
+                    self.write_element(writer, "nsSAFT:Description", description)?;
                 }
 
-                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Lines")))?;
-                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Transaction")))?;
+                writer.write_event(Event::End(BytesEnd::new("nsSAFT:Line")))?;
             }
 
-            writer.write_event(Event::End(BytesEnd::new("nsSAFT:Journal")))?;
+            writer.write_event(Event::End(BytesEnd::new("nsSAFT:StockMovement")))?;
         }
 
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:GeneralLedgerEntries")))?;
+        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MovementOfGoods")))?;
         Ok(())
     }
 
-    fn write_transaction_line(
+    fn write_element(
         &self,
         writer: &mut Writer<Cursor<Vec<u8>>>,
-        line: &SafTTransactionLine,
+        tag: &str,
+        value: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.write_element(writer, "nsSAFT:RecordID", &line.record_id)?;
-        self.write_element(writer, "nsSAFT:AccountID", &line.account_id.to_string())?;
+        writer.write_event(Event::Start(BytesStart::new(tag)))?;
+        writer.write_event(Event::Text(BytesText::new(value)))?;
+        writer.write_event(Event::End(BytesEnd::new(tag)))?;
+        Ok(())
+    }
+}
 
-        if let Some(ref taxpayer_id) = line.taxpayer_account_id {
-            self.write_element(writer, "nsSAFT:TaxpayerAccountID", taxpayer_id)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::euro_changeover::LEGACY_BASE_CURRENCY;
+
+    fn address() -> Address {
+        Address {
+            street_name: Some("ул. Витоша 1".to_string()),
+            number: None,
+            additional_address_detail: None,
+            building: None,
+            city: "София".to_string(),
+            postal_code: Some("1000".to_string()),
+            region: None,
+            country: "BG".to_string(),
+            address_type: "StreetAddress".to_string(),
         }
+    }
 
-        if let Some(ref date) = line.value_date {
-            self.write_element(writer, "nsSAFT:ValueDate", &date.to_string())?;
+    fn account(code: &str, opening: Decimal, closing: Decimal) -> SafTAccount {
+        let (opening_debit, opening_credit) = SafTServiceV2::split_balance(opening);
+        let (closing_debit, closing_credit) = SafTServiceV2::split_balance(closing);
+        SafTAccount {
+            account_id: code.parse().unwrap(),
+            account_description: format!("Сметка {}", code),
+            taxpayer_account_id: code.to_string(),
+            grouping_category: None,
+            grouping_code: None,
+            account_type: "Bifunctional".to_string(),
+            account_creation_date: NaiveDate::from_ymd_opt(2024, 1, 1),
+            opening_debit_balance: Some(opening_debit),
+            opening_credit_balance: Some(opening_credit),
+            closing_debit_balance: Some(closing_debit),
+            closing_credit_balance: Some(closing_credit),
         }
+    }
 
-        if let Some(ref doc_id) = line.source_document_id {
-            self.write_element(writer, "nsSAFT:SourceDocumentID", doc_id)?;
+    fn line(code: &str, debit: Decimal, credit: Decimal) -> SafTTransactionLine {
+        let amount = |value: Decimal| {
+            (value > Decimal::ZERO).then_some(AmountStructure {
+                amount: value,
+                currency_code: None,
+                currency_amount: None,
+                exchange_rate: None,
+            })
+        };
+        SafTTransactionLine {
+            record_id: format!("L{}", code),
+            account_id: code.parse().unwrap(),
+            taxpayer_account_id: Some(code.to_string()),
+            analysis: None,
+            value_date: NaiveDate::from_ymd_opt(2025, 3, 10),
+            source_document_id: Some("0000000123".to_string()),
+            customer_id: code.starts_with("411").then(|| "7".to_string()),
+            supplier_id: None,
+            description: "Продажба".to_string(),
+            debit_amount: amount(debit),
+            credit_amount: amount(credit),
+            tax_information: None,
+            reference_number: None,
+            cid: None,
+            quantity: None,
+            cross_reference: None,
+            system_entry_time: None,
         }
+    }
 
-        if let Some(ref customer_id) = line.customer_id {
-            self.write_element(writer, "nsSAFT:CustomerID", customer_id)?;
+    /// Monthly file with 411 opening at 100 and one 50.00 sale in the period
+    fn monthly_saft() -> BulgarianSafT {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let customer_balance = (
+            "411".to_string(),
+            PeriodBalance {
+                opening: Decimal::from(100),
+                debit: Decimal::from(50),
+                credit: Decimal::ZERO,
+            },
+        );
+        let (account_id, opening_balance, closing_balance) =
+            SafTServiceV2::party_balance(Some(&customer_balance));
+
+        BulgarianSafT {
+            header: SafTHeader {
+                audit_file_version: "007".to_string(),
+                audit_file_country: "BG".to_string(),
+                audit_file_region: Some("BG-22".to_string()),
+                audit_file_date_created: date,
+                software_company_name: "RS Accounting BG".to_string(),
+                software_id: "RS-AC-BG".to_string(),
+                software_version: "001".to_string(),
+                company: CompanyInfo {
+                    registration_number: "123456789".to_string(),
+                    name: "Тест ООД".to_string(),
+                    address: address(),
+                    contact: Contact {
+                        contact_person: None,
+                        telephone: None,
+                        fax: None,
+                        email: None,
+                        website: None,
+                    },
+                    tax_registration: vec![],
+                    bank_account: vec![],
+                },
+                ownership: None,
+                default_currency_code: LEGACY_BASE_CURRENCY.to_string(),
+                selection_criteria: SelectionCriteria {
+                    tax_reporting_jurisdiction: "NRA".to_string(),
+                    company_entity: None,
+                    period_start: 3,
+                    period_start_year: 2025,
+                    period_end: 3,
+                    period_end_year: 2025,
+                    document_type: None,
+                    other_criteria: None,
+                },
+                header_comment: "M".to_string(),
+                segment_index: None,
+                total_segments_in_sequence: None,
+                tax_accounting_basis: "A".to_string(),
+                tax_entity: None,
+            },
+            file_type: SafTFileType::Monthly,
+            master_files_annual: None,
+            source_documents_annual: None,
+            master_files_monthly: Some(MasterFilesMonthly {
+                general_ledger_accounts: GeneralLedgerAccounts {
+                    account: vec![
+                        account("411", Decimal::from(100), Decimal::from(150)),
+                        account("702", Decimal::ZERO, Decimal::from(-50)),
+                    ],
+                },
+                taxonomies: None,
+                customers: Customers {
+                    customer: vec![SafTCustomer {
+                        customer_id: "7".to_string(),
+                        customer_name: "Клиент ЕООД".to_string(),
+                        taxpayer_customer_id: None,
+                        addresses: vec![CustomerAddress { address: address() }],
+                        contact: None,
+                        tax_registration: None,
+                        bank_account: vec![],
+                        self_billing_indicator: "0".to_string(),
+                        account_id,
+                        opening_balance,
+                        closing_balance,
+                        party_info: None,
+                    }],
+                },
+                suppliers: Suppliers { supplier: vec![] },
+                tax_table: TaxTable {
+                    tax_code_details: vec![],
+                },
+                uom_table: UOMTable { uom_entry: vec![] },
+                analysis_type_table: None,
+                products: Products { product: vec![] },
+                owners: None,
+            }),
+            corresponding_accounts_report: None,
+            general_ledger_entries: Some(GeneralLedgerEntries {
+                number_of_entries: 1,
+                total_debit: Decimal::from(50),
+                total_credit: Decimal::from(50),
+                journal: vec![SafTJournal {
+                    journal_id: "1".to_string(),
+                    description: "Главна книга".to_string(),
+                    journal_type: "GL".to_string(),
+                    transaction: vec![SafTTransaction {
+                        transaction_id: "1".to_string(),
+                        period: 3,
+                        period_year: 2025,
+                        transaction_date: date,
+                        source_id: Some("1".to_string()),
+                        description: "Продажба".to_string(),
+                        doc_archival_number: None,
+                        transaction_type: Some("Normal".to_string()),
+                        system_entry_date: date,
+                        gl_posting_date: date,
+                        customer_id: Some("7".to_string()),
+                        supplier_id: None,
+                        system_id: None,
+                        lines: Lines {
+                            debit_line: vec![line("411", Decimal::from(50), Decimal::ZERO)],
+                            credit_line: vec![line("702", Decimal::ZERO, Decimal::from(50))],
+                        },
+                    }],
+                }],
+            }),
+            source_documents_monthly: None,
+            master_files_on_demand: None,
+            source_documents_on_demand: None,
         }
+    }
 
-        if let Some(ref supplier_id) = line.supplier_id {
-            self.write_element(writer, "nsSAFT:SupplierID", supplier_id)?;
+    fn journal_entry(
+        id: i32,
+        document_type: Option<&str>,
+        description: &str,
+    ) -> crate::entities::journal_entry::Model {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        crate::entities::journal_entry::Model {
+            id,
+            entry_number: format!("0000000{}", id),
+            document_date: date,
+            vat_date: Some(date),
+            accounting_date: date,
+            document_number: Some(format!("000000012{}", id)),
+            description: description.to_string(),
+            total_amount: Decimal::from(120),
+            total_vat_amount: Decimal::from(20),
+            is_posted: true,
+            posted_by: Some(1),
+            posted_at: None,
+            created_by: 1,
+            company_id: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            vat_document_type: document_type.map(str::to_string),
+            vat_purchase_operation: None,
+            vat_sales_operation: None,
+            vat_additional_operation: None,
+            vat_additional_data: None,
+            series_id: None,
+            sequence_number: None,
         }
+    }
 
-        self.write_element(writer, "nsSAFT:Description", &line.description)?;
-
-        if let Some(ref amount) = line.debit_amount {
-            self.write_amount_structure(writer, "nsSAFT:DebitAmount", amount)?;
+    fn entry_line(
+        id: i32,
+        account_id: i32,
+        debit: i64,
+        credit: i64,
+        counterpart_id: Option<i32>,
+    ) -> crate::entities::entry_line::Model {
+        crate::entities::entry_line::Model {
+            id,
+            journal_entry_id: 1,
+            account_id,
+            debit_amount: Decimal::from(debit),
+            credit_amount: Decimal::from(credit),
+            counterpart_id,
+            currency_code: None,
+            currency_amount: None,
+            exchange_rate: None,
+            base_amount: Decimal::from(debit + credit),
+            vat_amount: Decimal::ZERO,
+            vat_rate_id: None,
+            quantity: None,
+            unit_of_measure_code: None,
+            description: None,
+            line_order: id,
+            created_at: Utc::now(),
         }
+    }
 
-        if let Some(ref amount) = line.credit_amount {
-            self.write_amount_structure(writer, "nsSAFT:CreditAmount", amount)?;
-        }
+    /// 411, 503, 702 and 4532 by their codes as ids
+    fn chart() -> HashMap<i32, crate::entities::account::Model> {
+        ["411", "503", "702", "4532"]
+            .into_iter()
+            .map(|code| {
+                let id = code.parse().unwrap();
+                let account = crate::entities::account::Model {
+                    id,
+                    code: code.to_string(),
+                    name: format!("Сметка {}", code),
+                    account_type: crate::entities::account::AccountType::Asset,
+                    account_class: id / 100,
+                    parent_id: None,
+                    level: 1,
+                    is_vat_applicable: false,
+                    vat_direction: crate::entities::account::VatDirection::None,
+                    is_active: true,
+                    is_analytical: false,
+                    company_id: 1,
+                    supports_quantities: false,
+                    default_unit: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                };
+                (id, account)
+            })
+            .collect()
+    }
 
-        Ok(())
+    fn customers() -> HashMap<i32, crate::entities::counterpart::Model> {
+        let customer = crate::entities::counterpart::Model {
+            id: 7,
+            name: "Клиент ЕООД".to_string(),
+            eik: Some("203040506".to_string()),
+            vat_number: Some("BG203040506".to_string()),
+            street: None,
+            address: Some("ул. Раковски 5".to_string()),
+            city: Some("Пловдив".to_string()),
+            postal_code: None,
+            country: Some("BG".to_string()),
+            phone: None,
+            email: None,
+            contact_person: None,
+            counterpart_type: crate::entities::counterpart::CounterpartType::Customer,
+            is_customer: true,
+            is_supplier: false,
+            is_vat_registered: true,
+            is_active: true,
+            company_id: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        HashMap::from([(7, customer)])
     }
 
-    fn write_amount_structure(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        tag: &str,
-        amount: &AmountStructure,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new(tag)))?;
+    /// Sale of 100 + 20 VAT to customer 7 and its payment to the bank
+    fn sale_and_payment() -> Vec<(
+        crate::entities::journal_entry::Model,
+        Vec<crate::entities::entry_line::Model>,
+    )> {
+        vec![
+            (
+                journal_entry(1, Some("01"), "Продажба"),
+                vec![
+                    entry_line(1, 411, 120, 0, Some(7)),
+                    entry_line(2, 702, 0, 100, None),
+                    entry_line(3, 4532, 0, 20, None),
+                ],
+            ),
+            (
+                journal_entry(2, None, "Плащане от клиент"),
+                vec![
+                    entry_line(4, 503, 120, 0, None),
+                    entry_line(5, 411, 0, 120, Some(7)),
+                ],
+            ),
+        ]
+    }
 
-        self.write_element(writer, "nsSAFT:Amount", &amount.amount.to_string())?;
+    #[test]
+    fn general_ledger_transaction_splits_lines_by_side() {
+        let (entry, lines) = sale_and_payment().remove(0);
+
+        let transaction = SafTServiceV2::build_transaction(&entry, lines, &chart()).unwrap();
+
+        assert_eq!(transaction.transaction_id, "1");
+        assert_eq!(transaction.customer_id.as_deref(), Some("7"));
+        assert_eq!(transaction.lines.debit_line.len(), 1);
+        assert_eq!(transaction.lines.debit_line[0].account_id, 411);
+        assert_eq!(
+            transaction.lines.debit_line[0]
+                .source_document_id
+                .as_deref(),
+            Some("0000000121")
+        );
+        let credits: Vec<(i32, Decimal)> = transaction
+            .lines
+            .credit_line
+            .iter()
+            .map(|line| (line.account_id, line.credit_amount.as_ref().unwrap().amount))
+            .collect();
+        assert_eq!(
+            credits,
+            vec![(702, Decimal::from(100)), (4532, Decimal::from(20))]
+        );
+    }
 
-        if let Some(ref currency) = amount.currency_code {
-            self.write_element(writer, "nsSAFT:CurrencyCode", currency)?;
-        }
+    #[test]
+    fn source_documents_hold_invoices_and_payments() {
+        let documents = SafTServiceV2::collect_source_documents(
+            sale_and_payment(),
+            &chart(),
+            &customers(),
+            LEGACY_BASE_CURRENCY,
+        )
+        .unwrap();
+
+        let sales = documents.sales_invoices.unwrap();
+        assert_eq!(sales.number_of_entries, 1);
+        assert_eq!(sales.total_credit, Decimal::from(100));
+        let invoice = &sales.invoice[0];
+        assert_eq!(invoice.invoice_no, "0000000121");
+        assert_eq!(invoice.customer_info.customer_id, "7");
+        assert_eq!(invoice.document_totals.tax_payable, Decimal::from(20));
+        assert_eq!(invoice.document_totals.gross_total, Decimal::from(120));
+        let tax = invoice.lines[0].tax.as_ref().unwrap();
+        assert_eq!(tax.tax_code, "VAT20");
+
+        let payments = documents.payments.unwrap();
+        assert_eq!(payments.number_of_entries, 1);
+        assert_eq!(payments.total_credit, Decimal::from(120));
+        let line = &payments.payment[0].lines[0];
+        assert_eq!(line.account_id, 411);
+        assert_eq!(line.customer_id.as_deref(), Some("7"));
+        assert_eq!(payments.payment[0].payment_method, "02");
+
+        assert_eq!(documents.purchase_invoices.unwrap().number_of_entries, 0);
+    }
 
-        if let Some(ref curr_amount) = amount.currency_amount {
-            self.write_element(writer, "nsSAFT:CurrencyAmount", &curr_amount.to_string())?;
-        }
+    #[test]
+    fn invoice_without_a_known_counterpart_is_an_error() {
+        let mut entries = sale_and_payment();
+        entries[0].1[0].counterpart_id = None;
+        let err = SafTServiceV2::collect_source_documents(
+            entries.clone(),
+            &chart(),
+            &customers(),
+            LEGACY_BASE_CURRENCY,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invoice 0000000121 (journal entry 1) has no counterpart"
+        );
+
+        entries[0].1[0].counterpart_id = Some(99);
+        let err = SafTServiceV2::collect_source_documents(
+            entries,
+            &chart(),
+            &customers(),
+            LEGACY_BASE_CURRENCY,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Counterpart 99 of invoice 0000000121"));
+    }
 
-        if let Some(ref rate) = amount.exchange_rate {
-            self.write_element(writer, "nsSAFT:ExchangeRate", &rate.to_string())?;
-        }
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
 
-        writer.write_event(Event::End(BytesEnd::new(tag)))?;
-        Ok(())
+    /// Machine bought in 2024, improved in June and impaired in September 2025 and
+    /// depreciated up to January 2026 by 200 in the books and 250 for tax purposes
+    fn improved_machine() -> (
+        crate::entities::fixed_asset::Model,
+        Vec<crate::entities::depreciation_journal::Model>,
+        Vec<crate::entities::fixed_asset_event::Model>,
+    ) {
+        use crate::entities::fixed_asset_event::FixedAssetEventType;
+
+        let machine = crate::entities::fixed_asset::Model {
+            id: 1,
+            inventory_number: "DMA-001".to_string(),
+            name: "Машина".to_string(),
+            description: None,
+            category_id: 2,
+            company_id: 1,
+            acquisition_cost: dec("12700"),
+            acquisition_date: NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+            put_into_service_date: NaiveDate::from_ymd_opt(2024, 1, 10),
+            accounting_useful_life: 60,
+            accounting_depreciation_rate: dec("20"),
+            accounting_depreciation_method: "straight_line".to_string(),
+            depreciation_start_convention: "next_month".to_string(),
+            production_capacity: None,
+            accounting_salvage_value: Decimal::ZERO,
+            accounting_accumulated_depreciation: dec("5600"),
+            tax_useful_life: None,
+            tax_depreciation_rate: dec("30"),
+            tax_accumulated_depreciation: dec("6250"),
+            tax_depreciable_value: dec("11200"),
+            is_new_first_time_investment: false,
+            accounting_book_value: dec("7100"),
+            tax_book_value: dec("4950"),
+            accounting_monthly_depreciation: None,
+            revaluation_reserve: Decimal::ZERO,
+            revaluation_loss: dec("500"),
+            opening_balance_date: None,
+            status: "active".to_string(),
+            disposal_date: None,
+            disposal_amount: None,
+            location: None,
+            responsible_person: None,
+            serial_number: None,
+            manufacturer: None,
+            model: None,
+            notes: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let months = (1..=12)
+            .map(|month| NaiveDate::from_ymd_opt(2025, month, 1).unwrap())
+            .chain(NaiveDate::from_ymd_opt(2026, 1, 1));
+        let journals = months
+            .enumerate()
+            .map(
+                |(index, period)| crate::entities::depreciation_journal::Model {
+                    id: index as i32 + 1,
+                    fixed_asset_id: 1,
+                    period,
+                    company_id: 1,
+                    accounting_depreciation_amount: dec("200"),
+                    accounting_book_value_before: Decimal::ZERO,
+                    accounting_book_value_after: Decimal::ZERO,
+                    tax_depreciation_amount: dec("250"),
+                    tax_book_value_before: Decimal::ZERO,
+                    tax_book_value_after: Decimal::ZERO,
+                    journal_entry_id: None,
+                    is_posted: true,
+                    posted_at: None,
+                    posted_by: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                },
+            )
+            .collect();
+
+        let event =
+            |id: i32,
+             event_type: FixedAssetEventType,
+             event_date: NaiveDate,
+             [cost_before, cost_after, book_before, book_after]: [&str; 4],
+             [tax_before, tax_after, tax_book_before, tax_book_after]: [&str; 4]| {
+                crate::entities::fixed_asset_event::Model {
+                    id,
+                    company_id: 1,
+                    fixed_asset_id: 1,
+                    event_type,
+                    event_date,
+                    portion: Decimal::ONE,
+                    amount: dec(cost_after) - dec(cost_before),
+                    acquisition_cost_before: dec(cost_before),
+                    acquisition_cost_after: dec(cost_after),
+                    accounting_book_value_before: dec(book_before),
+                    accounting_book_value_after: dec(book_after),
+                    tax_book_value_before: dec(tax_book_before),
+                    tax_book_value_after: dec(tax_book_after),
+                    tax_depreciable_value_before: dec(tax_before),
+                    tax_depreciable_value_after: dec(tax_after),
+                    gain_loss: Decimal::ZERO,
+                    remaining_life_months: None,
+                    journal_entry_id: Some(100 + id),
+                    description: None,
+                    created_by: None,
+                    created_at: Utc::now(),
+                }
+            };
+        let events = vec![
+            event(
+                1,
+                FixedAssetEventType::Improvement,
+                NaiveDate::from_ymd_opt(2025, 6, 15).unwrap(),
+                ["12000", "13200", "8000", "9200"],
+                ["10000", "11200", "5750", "6950"],
+            ),
+            event(
+                2,
+                FixedAssetEventType::Impairment,
+                NaiveDate::from_ymd_opt(2025, 9, 30).unwrap(),
+                ["13200", "12700", "8400", "7900"],
+                ["11200", "11200", "5950", "5950"],
+            ),
+        ];
+
+        (machine, journals, events)
     }
 
-    fn write_corresponding_accounts_report(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        _report: &CorrespondingAccountsReport,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Implementation for Corresponding Accounts Report
-        writer.write_event(Event::Start(BytesStart::new(
-            "nsSAFT:CorrespondingAccountsReport",
-        )))?;
-        // TODO: Write report entries
-        writer.write_event(Event::End(BytesEnd::new(
-            "nsSAFT:CorrespondingAccountsReport",
-        )))?;
-        Ok(())
+    #[test]
+    fn annual_asset_valuations_roll_back_later_depreciation_and_events() {
+        let (machine, journals, events) = improved_machine();
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+
+        let accounting =
+            SafTServiceV2::asset_valuation(&machine, None, &journals, &events, start, end, false);
+        assert_eq!(
+            accounting.acquisition_and_production_costs_begin,
+            dec("12000")
+        );
+        assert_eq!(
+            accounting.acquisition_and_production_costs_end,
+            dec("12700")
+        );
+        assert_eq!(accounting.asset_addition, dec("1200"));
+        assert_eq!(accounting.asset_disposal, Decimal::ZERO);
+        assert_eq!(accounting.book_value_begin, dec("9000"));
+        assert_eq!(accounting.depreciation_for_period, dec("2400"));
+        assert_eq!(accounting.appreciation_for_period, dec("-500"));
+        assert_eq!(accounting.accumulated_depreciation, dec("5400"));
+        assert_eq!(accounting.book_value_end, dec("7300"));
+
+        let tax =
+            SafTServiceV2::asset_valuation(&machine, None, &journals, &events, start, end, true);
+        assert_eq!(tax.acquisition_and_production_costs_begin, dec("10000"));
+        assert_eq!(tax.acquisition_and_production_costs_end, dec("11200"));
+        assert_eq!(tax.asset_addition, dec("1200"));
+        assert_eq!(tax.book_value_begin, dec("7000"));
+        assert_eq!(tax.depreciation_for_period, dec("3000"));
+        assert_eq!(tax.appreciation_for_period, Decimal::ZERO);
+        assert_eq!(tax.accumulated_depreciation, dec("6000"));
+        assert_eq!(tax.book_value_end, dec("5200"));
     }
 
-    fn write_source_documents_monthly(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        _docs: &SourceDocumentsMonthly,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new(
-            "nsSAFT:SourceDocumentsMonthly",
-        )))?;
-        // TODO: Write source documents
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SourceDocumentsMonthly")))?;
-        Ok(())
+    #[test]
+    fn annual_asset_transactions_include_lifecycle_events() {
+        let (machine, journals, events) = improved_machine();
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+
+        let transactions =
+            SafTServiceV2::asset_transactions(&machine, &journals, &events, start, end);
+
+        let kinds: Vec<&str> = transactions
+            .iter()
+            .map(|t| t.asset_transaction_type.as_str())
+            .filter(|kind| *kind != "Depreciation")
+            .collect();
+        assert_eq!(kinds, vec!["Improvement", "Impairment"]);
+        assert_eq!(transactions.len(), 14);
+
+        let improvement = transactions
+            .iter()
+            .find(|t| t.asset_transaction_id == "EVT-1")
+            .unwrap();
+        assert_eq!(improvement.transaction_id.as_deref(), Some("101"));
+        let amounts: Vec<(Decimal, Decimal, Decimal)> = improvement
+            .valuations
+            .iter()
+            .map(|v| {
+                (
+                    v.acquisition_and_production_costs_on_transaction,
+                    v.book_value_on_transaction,
+                    v.asset_transaction_amount,
+                )
+            })
+            .collect();
+        assert_eq!(
+            amounts,
+            vec![
+                (dec("12000"), dec("9200"), dec("1200")),
+                (dec("10000"), dec("6950"), dec("1200")),
+            ]
+        );
+
+        let impairment = transactions
+            .iter()
+            .find(|t| t.asset_transaction_id == "EVT-2")
+            .unwrap();
+        assert_eq!(
+            impairment.valuations[0].asset_transaction_amount,
+            dec("500")
+        );
+        assert_eq!(
+            impairment.valuations[1].asset_transaction_amount,
+            Decimal::ZERO
+        );
+
+        let tax_cost = |id: &str| {
+            transactions
+                .iter()
+                .find(|t| t.asset_transaction_id == id)
+                .unwrap()
+                .valuations[1]
+                .acquisition_and_production_costs_on_transaction
+        };
+        assert_eq!(tax_cost("DEP-2"), dec("10000"));
+        assert_eq!(tax_cost("DEP-12"), dec("11200"));
     }
 
-    fn write_master_files_annual(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        _master_files: &MasterFilesAnnual,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MasterFilesAnnual")))?;
-        // TODO: Write annual master files
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MasterFilesAnnual")))?;
-        Ok(())
+    fn stock_account(id: i32, code: &str, unit: &str) -> crate::entities::account::Model {
+        crate::entities::account::Model {
+            id,
+            code: code.to_string(),
+            name: format!("Материални запаси {}", code),
+            account_type: crate::entities::account::AccountType::Asset,
+            account_class: 3,
+            parent_id: None,
+            level: 1,
+            is_vat_applicable: false,
+            vat_direction: crate::entities::account::VatDirection::None,
+            is_active: true,
+            is_analytical: false,
+            company_id: 1,
+            supports_quantities: true,
+            default_unit: Some(unit.to_string()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
     }
 
-    fn write_source_documents_annual(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        _docs: &SourceDocumentsAnnual,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new(
-            "nsSAFT:SourceDocumentsAnnual",
-        )))?;
-        // TODO: Write annual source documents
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SourceDocumentsAnnual")))?;
-        Ok(())
+    fn stock_movement(
+        id: i32,
+        journal_entry_id: i32,
+        movement_date: NaiveDate,
+        receipt: bool,
+        [quantity, unit_price, total]: [&str; 3],
+        [balance_quantity, balance_amount]: [&str; 2],
+    ) -> crate::entities::inventory_movement::Model {
+        crate::entities::inventory_movement::Model {
+            id,
+            company_id: 1,
+            account_id: 302,
+            entry_line_id: id,
+            journal_entry_id,
+            movement_date,
+            movement_type: if receipt { "DEBIT" } else { "CREDIT" }.to_string(),
+            quantity: dec(quantity),
+            unit_price: dec(unit_price),
+            total_amount: dec(total),
+            unit_of_measure: Some("кг".to_string()),
+            description: None,
+            balance_after_quantity: dec(balance_quantity),
+            balance_after_amount: dec(balance_amount),
+            average_cost_at_time: dec(unit_price),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
     }
 
-    fn write_master_files_on_demand(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        _master_files: &MasterFilesOnDemand,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new("nsSAFT:MasterFilesOnDemand")))?;
-        // TODO: Write on-demand master files
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:MasterFilesOnDemand")))?;
-        Ok(())
+    /// Materials on 302 received in December and moved twice in January 2025;
+    /// goods on 304 carry only an imported balance
+    fn stock_fixtures() -> (
+        Vec<crate::entities::account::Model>,
+        Vec<crate::entities::inventory_movement::Model>,
+        HashMap<i32, crate::entities::inventory_balance::Model>,
+    ) {
+        let day = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let accounts = vec![
+            stock_account(302, "302", "кг"),
+            stock_account(304, "304", "бр"),
+        ];
+        let movements = vec![
+            stock_movement(
+                1,
+                10,
+                day(2024, 12, 10),
+                true,
+                ["100", "2", "200"],
+                ["100", "200"],
+            ),
+            stock_movement(
+                2,
+                11,
+                day(2025, 1, 5),
+                false,
+                ["30", "2", "60"],
+                ["70", "140"],
+            ),
+            stock_movement(
+                3,
+                12,
+                day(2025, 1, 20),
+                true,
+                ["50", "2.6", "130"],
+                ["120", "270"],
+            ),
+        ];
+        let goods = crate::entities::inventory_balance::Model {
+            id: 1,
+            company_id: 1,
+            account_id: 304,
+            current_quantity: dec("10"),
+            current_amount: dec("500"),
+            current_average_cost: dec("50"),
+            last_movement_date: None,
+            last_movement_id: None,
+            updated_at: Utc::now(),
+        };
+
+        (accounts, movements, HashMap::from([(304, goods)]))
     }
 
-    fn write_source_documents_on_demand(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        _docs: &SourceDocumentsOnDemand,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new(
-            "nsSAFT:SourceDocumentsOnDemand",
-        )))?;
-        // TODO: Write on-demand source documents
-        writer.write_event(Event::End(BytesEnd::new("nsSAFT:SourceDocumentsOnDemand")))?;
-        Ok(())
+    #[test]
+    fn physical_stock_gives_opening_and_closing_per_product() {
+        let (accounts, movements, balances) = stock_fixtures();
+
+        let master_files = SafTServiceV2::stock_master_files(
+            &accounts,
+            &movements,
+            &balances,
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+        );
+
+        let stock: Vec<(&str, Decimal, Decimal, Decimal, Decimal, Decimal)> = master_files
+            .physical_stock
+            .physical_stock_entry
+            .iter()
+            .map(|entry| {
+                (
+                    entry.product_code.as_str(),
+                    entry.opening_stock_quantity,
+                    entry.opening_stock_value,
+                    entry.closing_stock_quantity,
+                    entry.closing_stock_value,
+                    entry.unit_price,
+                )
+            })
+            .collect();
+        assert_eq!(
+            stock,
+            vec![
+                (
+                    "302",
+                    dec("100"),
+                    dec("200"),
+                    dec("120"),
+                    dec("270"),
+                    dec("2.25")
+                ),
+                (
+                    "304",
+                    dec("10"),
+                    dec("500"),
+                    dec("10"),
+                    dec("500"),
+                    dec("50")
+                ),
+            ]
+        );
+
+        let products: Vec<&str> = master_files
+            .products
+            .product
+            .iter()
+            .map(|p| p.product_code.as_str())
+            .collect();
+        assert_eq!(products, vec!["302", "304"]);
+        let units: Vec<&str> = master_files
+            .uom_table
+            .uom_entry
+            .iter()
+            .map(|u| u.unit_of_measure.as_str())
+            .collect();
+        assert_eq!(units, vec!["кг", "бр"]);
+        assert_eq!(
+            master_files.movement_type_table.movement_type_entry.len(),
+            2
+        );
     }
 
-    fn write_element(
-        &self,
-        writer: &mut Writer<Cursor<Vec<u8>>>,
-        tag: &str,
-        value: &str,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        writer.write_event(Event::Start(BytesStart::new(tag)))?;
-        writer.write_event(Event::Text(BytesText::new(value)))?;
-        writer.write_event(Event::End(BytesEnd::new(tag)))?;
-        Ok(())
+    #[test]
+    fn movement_of_goods_groups_receipts_and_issues_per_entry() {
+        let (accounts, movements, _) = stock_fixtures();
+        let accounts: HashMap<i32, crate::entities::account::Model> =
+            accounts.into_iter().map(|a| (a.id, a)).collect();
+        let document_numbers = HashMap::from([(11, "0000000011".to_string())]);
+
+        let goods =
+            SafTServiceV2::collect_movement_of_goods(&accounts, &movements[1..], &document_numbers);
+
+        assert_eq!(goods.number_of_movement_lines, 2);
+        assert_eq!(goods.total_quantity_received, dec("50"));
+        assert_eq!(goods.total_quantity_issued, dec("30"));
+
+        let issue = &goods.stock_movement[0];
+        assert_eq!(issue.movement_reference, "11-20");
+        assert_eq!(issue.movement_type, "20");
+        assert_eq!(issue.document_reference.as_deref(), Some("0000000011"));
+        assert_eq!(issue.lines[0].product_code, "302");
+        assert_eq!(issue.lines[0].quantity, dec("30"));
+        assert_eq!(issue.lines[0].book_value, dec("60"));
+        assert_eq!(issue.lines[0].unit_of_measure, "кг");
+
+        let receipt = &goods.stock_movement[1];
+        assert_eq!(receipt.movement_reference, "12-10");
+        assert_eq!(receipt.document_reference, None);
+        assert_eq!(receipt.lines[0].book_value, dec("130"));
+    }
+
+    #[test]
+    #[cfg(feature = "xsd-validation")]
+    fn generated_monthly_file_passes_validation() {
+        let service = SafTServiceV2::new(DatabaseConnection::Disconnected);
+        let saft = monthly_saft();
+        let xml = service.generate_xml(&saft).unwrap();
+
+        let issues = saft_validator::validate_xml(&xml).unwrap();
+        assert!(issues.is_empty(), "schema issues: {:?}", issues);
+    }
+
+    #[test]
+    fn balanced_monthly_file_reconciles() {
+        assert!(SafTServiceV2::reconcile(&monthly_saft()).is_empty());
+    }
+
+    #[test]
+    fn reconcile_reports_out_of_balance_figures() {
+        let mut saft = monthly_saft();
+        let master_files = saft.master_files_monthly.as_mut().unwrap();
+        master_files.general_ledger_accounts.account[0].closing_debit_balance =
+            Some(Decimal::from(140));
+        master_files.customers.customer[0].closing_balance = None;
+
+        let xpaths: Vec<String> = SafTServiceV2::reconcile(&saft)
+            .into_iter()
+            .map(|issue| issue.xpath)
+            .collect();
+        assert_eq!(
+            xpaths,
+            vec![
+                "/AuditFile/MasterFilesMonthly/GeneralLedgerAccounts/Account[1]",
+                "/AuditFile/MasterFilesMonthly/Customers",
+            ]
+        );
     }
 }
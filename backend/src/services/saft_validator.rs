@@ -0,0 +1,352 @@
+//! Schema validation for generated SAF-T files.
+//!
+//! Documents are validated with libxml2 against the SAF-T BG v1.0.1 schema
+//! bundled in `schemas/saft/saft_bg_v1_0_1.xsd`, or against the XSD that
+//! `SAFT_XSD_PATH` points at. Schema errors are reported with the XPath of the
+//! offending node.
+//!
+//! libxml2 is linked through the `xsd-validation` feature, on by default. A
+//! backend built without it cannot check the schema and says so.
+
+#[cfg(feature = "xsd-validation")]
+const BUNDLED_SCHEMA: &str = include_str!("../../schemas/saft/saft_bg_v1_0_1.xsd");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafTIssueKind {
+    /// The document does not conform to the schema
+    Schema,
+    /// The document is well-formed but the figures do not agree
+    Reconciliation,
+}
+
+impl SafTIssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SafTIssueKind::Schema => "SCHEMA",
+            SafTIssueKind::Reconciliation => "RECONCILIATION",
+        }
+    }
+}
+
+/// A single problem found in a SAF-T document, located by XPath
+#[derive(Debug, Clone)]
+pub struct SafTValidationIssue {
+    pub kind: SafTIssueKind,
+    pub xpath: String,
+    pub message: String,
+}
+
+impl SafTValidationIssue {
+    pub fn schema(xpath: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind: SafTIssueKind::Schema,
+            xpath: xpath.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn reconciliation(xpath: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind: SafTIssueKind::Reconciliation,
+            xpath: xpath.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Outcome of validating a generated SAF-T file
+#[derive(Debug, Clone)]
+pub struct SafTValidationReport {
+    pub issues: Vec<SafTValidationIssue>,
+    pub file_size_bytes: usize,
+    pub number_of_transactions: i32,
+}
+
+/// Validate a SAF-T XML document against the schema
+#[cfg(feature = "xsd-validation")]
+pub fn validate_xml(xml: &str) -> Result<Vec<SafTValidationIssue>, String> {
+    let schema = match std::env::var("SAFT_XSD_PATH") {
+        Ok(path) => libxml2::XsdSchema::from_file(std::path::Path::new(&path))?,
+        Err(_) => libxml2::XsdSchema::from_str(BUNDLED_SCHEMA)?,
+    };
+    schema.validate(xml)
+}
+
+/// Validate a SAF-T XML document against the schema
+#[cfg(not(feature = "xsd-validation"))]
+pub fn validate_xml(_xml: &str) -> Result<Vec<SafTValidationIssue>, String> {
+    Err("SAF-T schema validation needs a backend built with the xsd-validation feature".to_string())
+}
+
+/// Schema validation with libxml2
+#[cfg(feature = "xsd-validation")]
+mod libxml2 {
+    use super::SafTValidationIssue;
+    use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+    /// Minimal bindings to the libxml2 schema validation API
+    #[allow(non_camel_case_types)]
+    mod ffi {
+        use std::ffi::{c_char, c_int, c_void};
+
+        pub type xmlStructuredErrorFunc =
+            extern "C" fn(user_data: *mut c_void, error: *const xmlError);
+
+        /// `struct _xmlError` from libxml2's xmlerror.h
+        #[repr(C)]
+        pub struct xmlError {
+            pub domain: c_int,
+            pub code: c_int,
+            pub message: *const c_char,
+            pub level: c_int,
+            pub file: *const c_char,
+            pub line: c_int,
+            pub str1: *const c_char,
+            pub str2: *const c_char,
+            pub str3: *const c_char,
+            pub int1: c_int,
+            pub int2: c_int,
+            pub ctxt: *mut c_void,
+            pub node: *mut c_void,
+        }
+
+        pub const XML_PARSE_NOERROR: c_int = 1 << 5;
+        pub const XML_PARSE_NOWARNING: c_int = 1 << 6;
+        pub const XML_PARSE_NONET: c_int = 1 << 11;
+
+        extern "C" {
+            pub static xmlFree: extern "C" fn(ptr: *mut c_void);
+
+            pub fn xmlInitParser();
+            pub fn xmlReadMemory(
+                buffer: *const c_char,
+                size: c_int,
+                url: *const c_char,
+                encoding: *const c_char,
+                options: c_int,
+            ) -> *mut c_void;
+            pub fn xmlFreeDoc(doc: *mut c_void);
+            pub fn xmlGetNodePath(node: *const c_void) -> *mut c_char;
+
+            pub fn xmlSchemaNewParserCtxt(url: *const c_char) -> *mut c_void;
+            pub fn xmlSchemaNewMemParserCtxt(buffer: *const c_char, size: c_int) -> *mut c_void;
+            pub fn xmlSchemaSetParserStructuredErrors(
+                ctxt: *mut c_void,
+                serror: xmlStructuredErrorFunc,
+                ctx: *mut c_void,
+            );
+            pub fn xmlSchemaParse(ctxt: *mut c_void) -> *mut c_void;
+            pub fn xmlSchemaFreeParserCtxt(ctxt: *mut c_void);
+            pub fn xmlSchemaFree(schema: *mut c_void);
+
+            pub fn xmlSchemaNewValidCtxt(schema: *mut c_void) -> *mut c_void;
+            pub fn xmlSchemaSetValidStructuredErrors(
+                ctxt: *mut c_void,
+                serror: xmlStructuredErrorFunc,
+                ctx: *mut c_void,
+            );
+            pub fn xmlSchemaValidateDoc(ctxt: *mut c_void, doc: *mut c_void) -> c_int;
+            pub fn xmlSchemaFreeValidCtxt(ctxt: *mut c_void);
+        }
+    }
+
+    /// Message and location of a libxml2 error
+    struct CollectedError {
+        message: String,
+        xpath: Option<String>,
+        line: i32,
+    }
+
+    extern "C" fn collect_error(user_data: *mut c_void, error: *const ffi::xmlError) {
+        // SAFETY: libxml2 hands back the `Vec` registered with the context and a valid
+        // error for the duration of the callback
+        let (errors, error) = unsafe { (&mut *(user_data as *mut Vec<CollectedError>), &*error) };
+
+        let message = if error.message.is_null() {
+            "Unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(error.message) }
+                .to_string_lossy()
+                .trim()
+                .to_string()
+        };
+        let xpath = (!error.node.is_null())
+            .then(|| unsafe { ffi::xmlGetNodePath(error.node) })
+            .filter(|path| !path.is_null())
+            .map(|path| unsafe {
+                let xpath = CStr::from_ptr(path).to_string_lossy().to_string();
+                (ffi::xmlFree)(path as *mut c_void);
+                xpath
+            });
+
+        errors.push(CollectedError {
+            message,
+            xpath,
+            line: error.line,
+        });
+    }
+
+    /// A compiled XSD schema
+    pub(super) struct XsdSchema {
+        schema: *mut c_void,
+    }
+
+    impl XsdSchema {
+        pub(super) fn from_file(path: &std::path::Path) -> Result<Self, String> {
+            let url = CString::new(path.to_string_lossy().as_bytes())
+                .map_err(|_| "Invalid schema path".to_string())?;
+            unsafe {
+                ffi::xmlInitParser();
+                Self::compile(ffi::xmlSchemaNewParserCtxt(url.as_ptr()))
+            }
+        }
+
+        pub(super) fn from_str(xsd: &str) -> Result<Self, String> {
+            unsafe {
+                ffi::xmlInitParser();
+                Self::compile(ffi::xmlSchemaNewMemParserCtxt(
+                    xsd.as_ptr() as *const c_char,
+                    xsd.len() as c_int,
+                ))
+            }
+        }
+
+        /// Compile the schema of a parser context and free the context
+        unsafe fn compile(ctxt: *mut c_void) -> Result<Self, String> {
+            if ctxt.is_null() {
+                return Err("Cannot create the schema parser".to_string());
+            }
+
+            let mut errors: Vec<CollectedError> = Vec::new();
+            ffi::xmlSchemaSetParserStructuredErrors(
+                ctxt,
+                collect_error,
+                &mut errors as *mut Vec<CollectedError> as *mut c_void,
+            );
+            let schema = ffi::xmlSchemaParse(ctxt);
+            ffi::xmlSchemaFreeParserCtxt(ctxt);
+
+            if schema.is_null() {
+                let messages: Vec<String> = errors.into_iter().map(|e| e.message).collect();
+                return Err(format!("Invalid SAF-T schema: {}", messages.join("; ")));
+            }
+            Ok(Self { schema })
+        }
+
+        pub(super) fn validate(&self, xml: &str) -> Result<Vec<SafTValidationIssue>, String> {
+            let mut errors: Vec<CollectedError> = Vec::new();
+
+            unsafe {
+                let doc = ffi::xmlReadMemory(
+                    xml.as_ptr() as *const c_char,
+                    xml.len() as c_int,
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    ffi::XML_PARSE_NONET | ffi::XML_PARSE_NOERROR | ffi::XML_PARSE_NOWARNING,
+                );
+                if doc.is_null() {
+                    return Ok(vec![SafTValidationIssue::schema(
+                        "/",
+                        "The document is not well-formed XML",
+                    )]);
+                }
+
+                let ctxt = ffi::xmlSchemaNewValidCtxt(self.schema);
+                if ctxt.is_null() {
+                    ffi::xmlFreeDoc(doc);
+                    return Err("Cannot create the schema validation context".to_string());
+                }
+                ffi::xmlSchemaSetValidStructuredErrors(
+                    ctxt,
+                    collect_error,
+                    &mut errors as *mut Vec<CollectedError> as *mut c_void,
+                );
+                let result = ffi::xmlSchemaValidateDoc(ctxt, doc);
+                ffi::xmlSchemaFreeValidCtxt(ctxt);
+                ffi::xmlFreeDoc(doc);
+
+                if result < 0 {
+                    return Err("Schema validation failed with an internal error".to_string());
+                }
+            }
+
+            Ok(errors
+                .into_iter()
+                .map(|error| {
+                    let xpath = error.xpath.unwrap_or_else(|| "/".to_string());
+                    SafTValidationIssue::schema(
+                        xpath,
+                        format!("line {}: {}", error.line, error.message),
+                    )
+                })
+                .collect())
+        }
+    }
+
+    impl Drop for XsdSchema {
+        fn drop(&mut self) {
+            unsafe { ffi::xmlSchemaFree(self.schema) }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "xsd-validation"))]
+mod tests {
+    use super::libxml2::XsdSchema;
+    use super::*;
+
+    const TEST_SCHEMA: &str = r#"<?xml version="1.0"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:simpleType name="Indicator">
+    <xs:restriction base="xs:string">
+      <xs:enumeration value="D"/>
+      <xs:enumeration value="C"/>
+    </xs:restriction>
+  </xs:simpleType>
+  <xs:element name="Root">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="Total" type="xs:decimal"/>
+        <xs:element name="Line" minOccurs="0" maxOccurs="unbounded">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="Amount" type="xs:decimal"/>
+              <xs:element name="Indicator" type="Indicator"/>
+            </xs:sequence>
+          </xs:complexType>
+        </xs:element>
+      </xs:sequence>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>"#;
+
+    #[test]
+    fn bundled_schema_loads() {
+        XsdSchema::from_str(BUNDLED_SCHEMA).expect("bundled schema must compile");
+    }
+
+    #[test]
+    fn reports_issues_with_xpath() {
+        let schema = XsdSchema::from_str(TEST_SCHEMA).unwrap();
+
+        let valid = "<Root><Total>10.00</Total>\
+                     <Line><Amount>10.00</Amount><Indicator>D</Indicator></Line>\
+                     </Root>";
+        assert!(schema.validate(valid).unwrap().is_empty());
+
+        let invalid = "<Root><Total>abc</Total>\
+                       <Line><Amount>1</Amount><Indicator>D</Indicator></Line>\
+                       <Line><Indicator>X</Indicator></Line>\
+                       <Extra/></Root>";
+        let issues = schema.validate(invalid).unwrap();
+        let xpaths: Vec<&str> = issues.iter().map(|i| i.xpath.as_str()).collect();
+        assert_eq!(
+            xpaths,
+            vec!["/Root/Total", "/Root/Line[2]/Indicator", "/Root/Extra"]
+        );
+        assert!(issues.iter().all(|i| i.kind == SafTIssueKind::Schema));
+
+        let broken = schema.validate("<Root><Total>").unwrap();
+        assert_eq!(broken[0].xpath, "/");
+    }
+}
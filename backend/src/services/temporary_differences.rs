@@ -0,0 +1,410 @@
+//! Temporary Differences Service
+//!
+//! Annual comparison of the accounting and tax (ЗКПО) book values of fixed
+//! assets. For every asset the report gives the depreciation charged in the
+//! year in both books, the values written off on disposal, the temporary
+//! difference at the start and at the end of the year and the deferred tax on
+//! it. The totals per tax category are the amounts carried to the schedule of
+//! depreciable assets of the annual corporate tax return: accounting
+//! depreciation is added back and tax depreciation is deducted.
+//!
+//! Differences are accounting minus tax book value. A positive difference is
+//! taxable and gives a deferred tax liability, a negative one is deductible
+//! and gives a deferred tax asset.
+
+use anyhow::{anyhow, Result};
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::entities::{depreciation_journal, fixed_asset, fixed_asset_category, fixed_asset_event};
+
+/// Corporate income tax rate under ЗКПО, in percent
+pub const CORPORATE_TAX_RATE: Decimal = Decimal::from_parts(10, 0, 0, false, 0);
+
+pub struct TemporaryDifferencesService;
+
+/// Amounts of one asset or one tax category for the year
+#[derive(Debug, Clone, Default, PartialEq, SimpleObject)]
+pub struct TemporaryDifferenceAmounts {
+    pub accounting_book_value_start: Decimal,
+    pub tax_book_value_start: Decimal,
+    pub accounting_depreciation: Decimal,
+    pub tax_depreciation: Decimal,
+    /// Book values written off by sales, disposals and write-offs
+    pub accounting_disposed_value: Decimal,
+    pub tax_disposed_value: Decimal,
+    pub accounting_book_value_end: Decimal,
+    pub tax_book_value_end: Decimal,
+    pub temporary_difference_start: Decimal,
+    pub temporary_difference_end: Decimal,
+    pub deferred_tax_asset_start: Decimal,
+    pub deferred_tax_liability_start: Decimal,
+    pub deferred_tax_asset_end: Decimal,
+    pub deferred_tax_liability_end: Decimal,
+    /// Deferred tax expense of the year; negative is income
+    pub deferred_tax_change: Decimal,
+}
+
+impl TemporaryDifferenceAmounts {
+    fn add(&mut self, other: &TemporaryDifferenceAmounts) {
+        self.accounting_book_value_start += other.accounting_book_value_start;
+        self.tax_book_value_start += other.tax_book_value_start;
+        self.accounting_depreciation += other.accounting_depreciation;
+        self.tax_depreciation += other.tax_depreciation;
+        self.accounting_disposed_value += other.accounting_disposed_value;
+        self.tax_disposed_value += other.tax_disposed_value;
+        self.accounting_book_value_end += other.accounting_book_value_end;
+        self.tax_book_value_end += other.tax_book_value_end;
+        self.temporary_difference_start += other.temporary_difference_start;
+        self.temporary_difference_end += other.temporary_difference_end;
+        self.deferred_tax_asset_start += other.deferred_tax_asset_start;
+        self.deferred_tax_liability_start += other.deferred_tax_liability_start;
+        self.deferred_tax_asset_end += other.deferred_tax_asset_end;
+        self.deferred_tax_liability_end += other.deferred_tax_liability_end;
+        self.deferred_tax_change += other.deferred_tax_change;
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AssetTemporaryDifference {
+    pub fixed_asset_id: i32,
+    pub inventory_number: String,
+    pub name: String,
+    pub tax_category: i32,
+    #[graphql(flatten)]
+    pub amounts: TemporaryDifferenceAmounts,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct TaxCategoryTemporaryDifference {
+    pub tax_category: i32,
+    pub category_name: String,
+    pub assets_count: i32,
+    #[graphql(flatten)]
+    pub amounts: TemporaryDifferenceAmounts,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct TemporaryDifferencesReport {
+    pub year: i32,
+    /// Tax rate in percent
+    pub tax_rate: Decimal,
+    pub assets: Vec<AssetTemporaryDifference>,
+    pub categories: Vec<TaxCategoryTemporaryDifference>,
+    pub totals: TemporaryDifferenceAmounts,
+}
+
+/// Book values of an asset before and after a depreciation month or an event
+struct ValueChange {
+    month: NaiveDate,
+    created_at: DateTime<Utc>,
+    before: (Decimal, Decimal),
+    after: (Decimal, Decimal),
+    depreciation: (Decimal, Decimal),
+    disposal: bool,
+}
+
+impl TemporaryDifferencesService {
+    pub async fn report(
+        db: &DatabaseConnection,
+        company_id: i32,
+        year: i32,
+        tax_rate: Option<Decimal>,
+    ) -> Result<TemporaryDifferencesReport> {
+        let year_end = NaiveDate::from_ymd_opt(year, 12, 31)
+            .ok_or_else(|| anyhow!("Invalid year {}", year))?;
+
+        let assets = fixed_asset::Entity::find()
+            .filter(fixed_asset::Column::CompanyId.eq(company_id))
+            .filter(fixed_asset::Column::AcquisitionDate.lte(year_end))
+            .order_by_asc(fixed_asset::Column::InventoryNumber)
+            .all(db)
+            .await?;
+        let categories: HashMap<i32, fixed_asset_category::Model> =
+            fixed_asset_category::Entity::find()
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|category| (category.id, category))
+                .collect();
+        let journal = depreciation_journal::Entity::find()
+            .filter(depreciation_journal::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?;
+        let events = fixed_asset_event::Entity::find()
+            .filter(fixed_asset_event::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?;
+
+        let assets: Vec<(fixed_asset::Model, &fixed_asset_category::Model)> = assets
+            .into_iter()
+            .map(|asset| {
+                let category = categories
+                    .get(&asset.category_id)
+                    .ok_or_else(|| anyhow!("Asset category {} not found", asset.category_id))?;
+                Ok((asset, category))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self::build(
+            year,
+            tax_rate.unwrap_or(CORPORATE_TAX_RATE),
+            &assets,
+            &journal,
+            &events,
+        ))
+    }
+
+    pub fn build(
+        year: i32,
+        tax_rate: Decimal,
+        assets: &[(fixed_asset::Model, &fixed_asset_category::Model)],
+        journal: &[depreciation_journal::Model],
+        events: &[fixed_asset_event::Model],
+    ) -> TemporaryDifferencesReport {
+        let mut rows = Vec::new();
+        let mut categories: BTreeMap<i32, TaxCategoryTemporaryDifference> = BTreeMap::new();
+        let mut totals = TemporaryDifferenceAmounts::default();
+
+        for (asset, category) in assets {
+            let Some(amounts) = Self::asset_amounts(year, tax_rate, asset, journal, events) else {
+                continue;
+            };
+
+            let group = categories.entry(category.tax_category).or_insert_with(|| {
+                TaxCategoryTemporaryDifference {
+                    tax_category: category.tax_category,
+                    category_name: category.get_tax_category_display().to_string(),
+                    assets_count: 0,
+                    amounts: TemporaryDifferenceAmounts::default(),
+                }
+            });
+            group.assets_count += 1;
+            group.amounts.add(&amounts);
+            totals.add(&amounts);
+
+            rows.push(AssetTemporaryDifference {
+                fixed_asset_id: asset.id,
+                inventory_number: asset.inventory_number.clone(),
+                name: asset.name.clone(),
+                tax_category: category.tax_category,
+                amounts,
+            });
+        }
+
+        TemporaryDifferencesReport {
+            year,
+            tax_rate,
+            assets: rows,
+            categories: categories.into_values().collect(),
+            totals,
+        }
+    }
+
+    /// Amounts of one asset, or `None` when it had no values and no movements in the year
+    fn asset_amounts(
+        year: i32,
+        tax_rate: Decimal,
+        asset: &fixed_asset::Model,
+        journal: &[depreciation_journal::Model],
+        events: &[fixed_asset_event::Model],
+    ) -> Option<TemporaryDifferenceAmounts> {
+        let year_start = NaiveDate::from_ymd_opt(year, 1, 1)?;
+        let next_year = NaiveDate::from_ymd_opt(year + 1, 1, 1)?;
+
+        let mut changes: Vec<ValueChange> = journal
+            .iter()
+            .filter(|row| row.fixed_asset_id == asset.id)
+            .map(|row| ValueChange {
+                month: row.period,
+                created_at: row.created_at,
+                before: (row.accounting_book_value_before, row.tax_book_value_before),
+                after: (row.accounting_book_value_after, row.tax_book_value_after),
+                depreciation: (
+                    row.accounting_depreciation_amount,
+                    row.tax_depreciation_amount,
+                ),
+                disposal: false,
+            })
+            .chain(
+                events
+                    .iter()
+                    .filter(|event| event.fixed_asset_id == asset.id)
+                    .map(|event| ValueChange {
+                        month: event.event_date.with_day(1).unwrap_or(event.event_date),
+                        created_at: event.created_at,
+                        before: (
+                            event.accounting_book_value_before,
+                            event.tax_book_value_before,
+                        ),
+                        after: (
+                            event.accounting_book_value_after,
+                            event.tax_book_value_after,
+                        ),
+                        depreciation: (Decimal::ZERO, Decimal::ZERO),
+                        disposal: event.event_type.is_disposal(),
+                    }),
+            )
+            .collect();
+        changes.sort_by_key(|change| (change.month, change.created_at));
+
+        let current = (asset.accounting_book_value, asset.tax_book_value);
+        let value_at = |date: NaiveDate| {
+            if asset.acquisition_date >= date {
+                return (Decimal::ZERO, Decimal::ZERO);
+            }
+            match changes.iter().rev().find(|change| change.month < date) {
+                Some(change) => change.after,
+                None => changes.first().map_or(current, |change| change.before),
+            }
+        };
+
+        let (accounting_start, tax_start) = value_at(year_start);
+        let (accounting_end, tax_end) = value_at(next_year);
+        let mut amounts = TemporaryDifferenceAmounts {
+            accounting_book_value_start: accounting_start,
+            tax_book_value_start: tax_start,
+            accounting_book_value_end: accounting_end,
+            tax_book_value_end: tax_end,
+            ..Default::default()
+        };
+        for change in changes
+            .iter()
+            .filter(|change| change.month >= year_start && change.month < next_year)
+        {
+            amounts.accounting_depreciation += change.depreciation.0;
+            amounts.tax_depreciation += change.depreciation.1;
+            if change.disposal {
+                amounts.accounting_disposed_value += change.before.0 - change.after.0;
+                amounts.tax_disposed_value += change.before.1 - change.after.1;
+            }
+        }
+
+        if amounts == TemporaryDifferenceAmounts::default() {
+            return None;
+        }
+
+        let deferred_tax =
+            |difference: Decimal| (difference * tax_rate / Decimal::from(100)).round_dp(2);
+        amounts.temporary_difference_start = accounting_start - tax_start;
+        amounts.temporary_difference_end = accounting_end - tax_end;
+        let start = deferred_tax(amounts.temporary_difference_start);
+        let end = deferred_tax(amounts.temporary_difference_end);
+        amounts.deferred_tax_asset_start = (-start).max(Decimal::ZERO);
+        amounts.deferred_tax_liability_start = start.max(Decimal::ZERO);
+        amounts.deferred_tax_asset_end = (-end).max(Decimal::ZERO);
+        amounts.deferred_tax_liability_end = end.max(Decimal::ZERO);
+        amounts.deferred_tax_change = end - start;
+
+        Some(amounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::FixedAssetEventType;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn asset(id: i32, acquired: NaiveDate) -> fixed_asset::Model {
+        fixed_asset::Model::fixture(id, dec("12000"), acquired)
+    }
+
+    /// Monthly rows of 200 accounting and 300 tax depreciation
+    fn journal(asset_id: i32, from: NaiveDate, months: u32) -> Vec<depreciation_journal::Model> {
+        (0..months)
+            .map(|month| {
+                let done = Decimal::from(month);
+                depreciation_journal::Model {
+                    id: month as i32 + 1,
+                    fixed_asset_id: asset_id,
+                    period: from + chrono::Months::new(month),
+                    company_id: 1,
+                    accounting_depreciation_amount: dec("200"),
+                    accounting_book_value_before: dec("12000") - done * dec("200"),
+                    accounting_book_value_after: dec("11800") - done * dec("200"),
+                    tax_depreciation_amount: dec("300"),
+                    tax_book_value_before: dec("12000") - done * dec("300"),
+                    tax_book_value_after: dec("11700") - done * dec("300"),
+                    journal_entry_id: None,
+                    is_posted: true,
+                    posted_at: None,
+                    posted_by: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn report_gives_differences_and_deferred_tax_per_asset_and_category() {
+        let machinery = fixed_asset_category::Model::machinery();
+        let kept = asset(1, date(2024, 6, 10));
+        let sold = asset(2, date(2024, 6, 10));
+        let mut rows = journal(1, date(2024, 7, 1), 18);
+        rows.extend(journal(2, date(2024, 7, 1), 12));
+        let sale = fixed_asset_event::Model {
+            id: 1,
+            company_id: 1,
+            fixed_asset_id: 2,
+            event_type: FixedAssetEventType::Sale,
+            event_date: date(2025, 6, 30),
+            portion: Decimal::ONE,
+            amount: dec("10000"),
+            acquisition_cost_before: dec("12000"),
+            acquisition_cost_after: Decimal::ZERO,
+            accounting_book_value_before: dec("9600"),
+            accounting_book_value_after: Decimal::ZERO,
+            tax_book_value_before: dec("8400"),
+            tax_book_value_after: Decimal::ZERO,
+            tax_depreciable_value_before: dec("12000"),
+            tax_depreciable_value_after: Decimal::ZERO,
+            gain_loss: dec("400"),
+            remaining_life_months: None,
+            journal_entry_id: None,
+            description: None,
+            created_by: None,
+            created_at: Utc::now(),
+        };
+
+        let report = TemporaryDifferencesService::build(
+            2025,
+            CORPORATE_TAX_RATE,
+            &[(kept, &machinery), (sold, &machinery)],
+            &rows,
+            &[sale],
+        );
+
+        let kept = &report.assets[0].amounts;
+        assert_eq!(kept.accounting_book_value_start, dec("10800"));
+        assert_eq!(kept.tax_book_value_start, dec("10200"));
+        assert_eq!(kept.accounting_depreciation, dec("2400"));
+        assert_eq!(kept.tax_depreciation, dec("3600"));
+        assert_eq!(kept.temporary_difference_end, dec("1800"));
+        assert_eq!(kept.deferred_tax_liability_end, dec("180"));
+        assert_eq!(kept.deferred_tax_change, dec("120"));
+
+        let sold = &report.assets[1].amounts;
+        assert_eq!(sold.accounting_depreciation, dec("1200"));
+        assert_eq!(sold.accounting_disposed_value, dec("9600"));
+        assert_eq!(sold.tax_disposed_value, dec("8400"));
+        assert_eq!(sold.temporary_difference_end, Decimal::ZERO);
+        assert_eq!(sold.deferred_tax_change, dec("-60"));
+
+        assert_eq!(report.categories.len(), 1);
+        assert_eq!(report.categories[0].assets_count, 2);
+        assert_eq!(report.categories[0].amounts.tax_depreciation, dec("5400"));
+        assert_eq!(report.totals.deferred_tax_change, dec("60"));
+    }
+}
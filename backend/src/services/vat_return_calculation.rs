@@ -0,0 +1,598 @@
+//! VAT Return Calculation Service
+//!
+//! Fills the DEKLAR fields of a VAT return from the posted sales and purchase
+//! journals of its period. Every journal entry is assigned to declaration
+//! fields by its НАП operation code (про11, пок10, ...); the tax base is the
+//! entry total without VAT. Each field comes with the entries that make it
+//! up and the difference to the value currently stored on the return, so
+//! manual edits stand out before they are overwritten.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::entities::vat_return::{self, VatReturnStatus};
+use crate::services::accounting_period::AccountingPeriodService;
+
+pub struct VatReturnCalculationService;
+
+/// Posted journal entry with a VAT operation code
+#[derive(Debug, Clone, Default)]
+pub struct VatJournalDocument {
+    pub journal_entry_id: i32,
+    pub entry_number: String,
+    pub document_number: Option<String>,
+    pub document_date: NaiveDate,
+    /// НАП document type (01 invoice, 02 debit note, 03 credit note, ...)
+    pub document_type: Option<String>,
+    pub description: String,
+    pub counterpart_name: Option<String>,
+    pub counterpart_vat_number: Option<String>,
+    pub sales_operation: Option<String>,
+    pub purchase_operation: Option<String>,
+    pub base_amount: Decimal,
+    pub vat_amount: Decimal,
+}
+
+/// Journal entry contributing to a declaration field
+#[derive(SimpleObject, Serialize, Debug, Clone, PartialEq)]
+pub struct VatReturnFieldEntry {
+    pub journal_entry_id: i32,
+    pub entry_number: String,
+    pub document_number: Option<String>,
+    pub document_date: NaiveDate,
+    pub operation: String,
+    pub amount: Decimal,
+}
+
+/// One DEKLAR field: computed value, stored value and its source entries
+#[derive(SimpleObject, Serialize, Debug, Clone)]
+pub struct VatReturnFieldBreakdown {
+    /// Field number in the declaration, e.g. `01-11`
+    pub field: String,
+    /// Name of the field on the VAT return
+    pub column: String,
+    pub computed: Decimal,
+    pub stored: Decimal,
+    /// Stored minus computed; non-zero when the value was edited by hand
+    pub difference: Decimal,
+    pub entries: Vec<VatReturnFieldEntry>,
+}
+
+#[derive(SimpleObject, Serialize, Debug, Clone)]
+pub struct VatReturnCalculation {
+    pub vat_return: vat_return::Model,
+    pub fields: Vec<VatReturnFieldBreakdown>,
+    pub has_differences: bool,
+    pub sales_document_count: i32,
+    pub purchase_document_count: i32,
+    /// Entries whose operation code has no field on the return
+    pub unmapped_entries: Vec<VatReturnFieldEntry>,
+}
+
+/// Declaration fields filled from the journals, in declaration order
+const FIELDS: [(&str, &str); 25] = [
+    ("01-01", "total_sales_taxable"),
+    ("01-20", "total_sales_vat"),
+    ("01-11", "sales_base_20"),
+    ("01-21", "sales_vat_20"),
+    ("01-12", "sales_base_vop"),
+    ("01-22", "sales_vat_vop"),
+    ("01-23", "sales_vat_personal_use"),
+    ("01-13", "sales_base_9"),
+    ("01-24", "sales_vat_9"),
+    ("01-14", "sales_base_0_art3"),
+    ("01-15", "sales_base_0_vod"),
+    ("01-16", "sales_base_0_export"),
+    ("01-17", "sales_base_art21"),
+    ("01-18", "sales_base_art69"),
+    ("01-19", "sales_base_exempt"),
+    ("01-30", "purchase_base_no_credit"),
+    ("01-31", "purchase_base_full_credit"),
+    ("01-41", "purchase_vat_full_credit"),
+    ("01-32", "purchase_base_partial_credit"),
+    ("01-42", "purchase_vat_partial_credit"),
+    ("01-43", "purchase_vat_annual_adjustment"),
+    ("01-33", "credit_coefficient"),
+    ("01-40", "total_deductible_vat"),
+    ("50", "vat_to_pay"),
+    ("60", "vat_to_refund"),
+];
+
+/// Base and VAT fields an operation code is booked to
+pub(crate) fn operation_fields(
+    operation: &str,
+) -> Option<(Option<&'static str>, Option<&'static str>)> {
+    let fields = match operation {
+        "про11" => (Some("sales_base_20"), Some("sales_vat_20")),
+        // ВОП and tax charged by the recipient (art. 82)
+        "про12" | "про13" => (Some("sales_base_vop"), Some("sales_vat_vop")),
+        "про16" => (None, Some("sales_vat_personal_use")),
+        "про17" => (Some("sales_base_9"), Some("sales_vat_9")),
+        "про14" => (Some("sales_base_0_art3"), None),
+        "про20" => (Some("sales_base_0_vod"), None),
+        "про19" | "про21" => (Some("sales_base_0_export"), None),
+        "про22" => (Some("sales_base_art21"), None),
+        "про23-1" | "про23-2" => (Some("sales_base_art69"), None),
+        "про24-1" | "про24-2" | "про24-3" => (Some("sales_base_exempt"), None),
+        "пок09" => (Some("purchase_base_no_credit"), None),
+        "пок10" => (
+            Some("purchase_base_full_credit"),
+            Some("purchase_vat_full_credit"),
+        ),
+        "пок12" => (
+            Some("purchase_base_partial_credit"),
+            Some("purchase_vat_partial_credit"),
+        ),
+        "пок14" => (None, Some("purchase_vat_annual_adjustment")),
+        _ => return None,
+    };
+    Some(fields)
+}
+
+/// Current value of a declaration field on the return
+fn stored_value(model: &vat_return::Model, column: &str) -> Decimal {
+    match column {
+        "total_sales_taxable" => model.total_sales_taxable,
+        "total_sales_vat" => model.total_sales_vat,
+        "sales_base_20" => model.sales_base_20,
+        "sales_vat_20" => model.sales_vat_20,
+        "sales_base_vop" => model.sales_base_vop,
+        "sales_vat_vop" => model.sales_vat_vop,
+        "sales_vat_personal_use" => model.sales_vat_personal_use,
+        "sales_base_9" => model.sales_base_9,
+        "sales_vat_9" => model.sales_vat_9,
+        "sales_base_0_art3" => model.sales_base_0_art3,
+        "sales_base_0_vod" => model.sales_base_0_vod,
+        "sales_base_0_export" => model.sales_base_0_export,
+        "sales_base_art21" => model.sales_base_art21,
+        "sales_base_art69" => model.sales_base_art69,
+        "sales_base_exempt" => model.sales_base_exempt,
+        "purchase_base_no_credit" => model.purchase_base_no_credit,
+        "purchase_base_full_credit" => model.purchase_base_full_credit,
+        "purchase_vat_full_credit" => model.purchase_vat_full_credit,
+        "purchase_base_partial_credit" => model.purchase_base_partial_credit,
+        "purchase_vat_partial_credit" => model.purchase_vat_partial_credit,
+        "purchase_vat_annual_adjustment" => model.purchase_vat_annual_adjustment,
+        "credit_coefficient" => model.credit_coefficient,
+        "total_deductible_vat" => model.total_deductible_vat,
+        "vat_to_pay" => model.vat_to_pay,
+        "vat_to_refund" => model.vat_to_refund,
+        _ => Decimal::ZERO,
+    }
+}
+
+impl VatReturnCalculationService {
+    /// Computed fields of a return compared with its stored values
+    pub async fn preview<C: ConnectionTrait>(
+        db: &C,
+        vat_return_id: i32,
+    ) -> Result<VatReturnCalculation> {
+        let vat_return = vat_return::Entity::find_by_id(vat_return_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("VAT return not found"))?;
+        let documents = Self::load_documents(
+            db,
+            vat_return.company_id,
+            vat_return.period_from,
+            vat_return.period_to,
+        )
+        .await?;
+
+        Ok(Self::calculate(vat_return, &documents))
+    }
+
+    /// Overwrite the fields of a draft return with the computed values. The
+    /// returned breakdown compares them with the values before the update.
+    pub async fn recalculate<C: ConnectionTrait>(
+        db: &C,
+        vat_return_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<VatReturnCalculation> {
+        let calculation = Self::preview(db, vat_return_id).await?;
+        let existing = &calculation.vat_return;
+
+        if existing.status != VatReturnStatus::Draft {
+            bail!("Only draft VAT returns can be recalculated");
+        }
+        AccountingPeriodService::ensure_open(
+            db,
+            existing.company_id,
+            existing.period_from,
+            user_id,
+        )
+        .await?;
+
+        let value = |column: &str| {
+            calculation
+                .fields
+                .iter()
+                .find(|field| field.column == column)
+                .map(|field| field.computed)
+                .unwrap_or_default()
+        };
+
+        let mut model: vat_return::ActiveModel = existing.clone().into();
+        model.total_sales_taxable = Set(value("total_sales_taxable"));
+        model.total_sales_vat = Set(value("total_sales_vat"));
+        model.sales_base_20 = Set(value("sales_base_20"));
+        model.sales_vat_20 = Set(value("sales_vat_20"));
+        model.sales_base_vop = Set(value("sales_base_vop"));
+        model.sales_vat_vop = Set(value("sales_vat_vop"));
+        model.sales_vat_personal_use = Set(value("sales_vat_personal_use"));
+        model.sales_base_9 = Set(value("sales_base_9"));
+        model.sales_vat_9 = Set(value("sales_vat_9"));
+        model.sales_base_0_art3 = Set(value("sales_base_0_art3"));
+        model.sales_base_0_vod = Set(value("sales_base_0_vod"));
+        model.sales_base_0_export = Set(value("sales_base_0_export"));
+        model.sales_base_art21 = Set(value("sales_base_art21"));
+        model.sales_base_art69 = Set(value("sales_base_art69"));
+        model.sales_base_exempt = Set(value("sales_base_exempt"));
+        model.purchase_base_no_credit = Set(value("purchase_base_no_credit"));
+        model.purchase_base_full_credit = Set(value("purchase_base_full_credit"));
+        model.purchase_vat_full_credit = Set(value("purchase_vat_full_credit"));
+        model.purchase_base_partial_credit = Set(value("purchase_base_partial_credit"));
+        model.purchase_vat_partial_credit = Set(value("purchase_vat_partial_credit"));
+        model.purchase_vat_annual_adjustment = Set(value("purchase_vat_annual_adjustment"));
+        model.credit_coefficient = Set(value("credit_coefficient"));
+        model.total_deductible_vat = Set(value("total_deductible_vat"));
+        model.vat_to_pay = Set(value("vat_to_pay"));
+        model.vat_to_refund = Set(value("vat_to_refund"));
+        // Legacy summary fields
+        model.base_amount_20 = Set(value("sales_base_20"));
+        model.vat_amount_20 = Set(value("sales_vat_20"));
+        model.base_amount_9 = Set(value("sales_base_9"));
+        model.vat_amount_9 = Set(value("sales_vat_9"));
+        model.base_amount_0 = Set(value("sales_base_0_art3")
+            + value("sales_base_0_vod")
+            + value("sales_base_0_export"));
+        model.exempt_amount = Set(value("sales_base_exempt"));
+        model.output_vat_amount = Set(value("total_sales_vat"));
+        model.input_vat_amount = Set(value("total_deductible_vat"));
+        model.sales_document_count = Set(calculation.sales_document_count);
+        model.purchase_document_count = Set(calculation.purchase_document_count);
+        model.updated_at = Set(Utc::now());
+        let vat_return = model.update(db).await?;
+
+        Ok(VatReturnCalculation {
+            vat_return,
+            ..calculation
+        })
+    }
+
+    /// Assign the documents to declaration fields and derive the totals
+    pub fn calculate(
+        vat_return: vat_return::Model,
+        documents: &[VatJournalDocument],
+    ) -> VatReturnCalculation {
+        let mut computed: BTreeMap<&'static str, Decimal> = BTreeMap::new();
+        let mut sources: BTreeMap<&'static str, Vec<VatReturnFieldEntry>> = BTreeMap::new();
+        let mut unmapped_entries = Vec::new();
+        let mut sales_document_count = 0;
+        let mut purchase_document_count = 0;
+
+        for document in documents {
+            let operations = [
+                document.sales_operation.as_deref(),
+                document.purchase_operation.as_deref(),
+            ];
+            for (is_sale, operation) in [true, false].into_iter().zip(operations) {
+                let Some(operation) = operation.filter(|op| !op.is_empty()) else {
+                    continue;
+                };
+                if is_sale {
+                    sales_document_count += 1;
+                } else {
+                    purchase_document_count += 1;
+                }
+
+                let source = |amount: Decimal| VatReturnFieldEntry {
+                    journal_entry_id: document.journal_entry_id,
+                    entry_number: document.entry_number.clone(),
+                    document_number: document.document_number.clone(),
+                    document_date: document.document_date,
+                    operation: operation.to_string(),
+                    amount,
+                };
+
+                let Some((base_field, vat_field)) = operation_fields(operation) else {
+                    unmapped_entries.push(source(document.base_amount));
+                    continue;
+                };
+                for (field, amount) in [
+                    (base_field, document.base_amount),
+                    (vat_field, document.vat_amount),
+                ] {
+                    if let Some(field) = field {
+                        *computed.entry(field).or_default() += amount;
+                        sources.entry(field).or_default().push(source(amount));
+                    }
+                }
+            }
+        }
+
+        let get = |computed: &BTreeMap<&'static str, Decimal>, column: &str| {
+            computed.get(column).copied().unwrap_or_default()
+        };
+
+        // 01-01 and 01-20: all taxable supplies and all VAT charged
+        let total_sales_taxable = [
+            "sales_base_20",
+            "sales_base_vop",
+            "sales_base_9",
+            "sales_base_0_art3",
+            "sales_base_0_vod",
+            "sales_base_0_export",
+            "sales_base_art21",
+            "sales_base_art69",
+        ]
+        .iter()
+        .map(|column| get(&computed, column))
+        .sum::<Decimal>();
+        let total_sales_vat = [
+            "sales_vat_20",
+            "sales_vat_vop",
+            "sales_vat_personal_use",
+            "sales_vat_9",
+        ]
+        .iter()
+        .map(|column| get(&computed, column))
+        .sum::<Decimal>();
+
+        // Partial credit uses the coefficient on the return; without one the
+        // share of taxable supplies in the period is used
+        let credit_coefficient = if vat_return.credit_coefficient > Decimal::ZERO {
+            vat_return.credit_coefficient
+        } else {
+            let exempt = get(&computed, "sales_base_exempt");
+            if total_sales_taxable + exempt > Decimal::ZERO {
+                (total_sales_taxable / (total_sales_taxable + exempt)).round_dp(2)
+            } else {
+                Decimal::ONE
+            }
+        };
+        let total_deductible_vat = get(&computed, "purchase_vat_full_credit")
+            + (get(&computed, "purchase_vat_partial_credit") * credit_coefficient).round_dp(2)
+            + get(&computed, "purchase_vat_annual_adjustment");
+
+        computed.insert("total_sales_taxable", total_sales_taxable);
+        computed.insert("total_sales_vat", total_sales_vat);
+        computed.insert("credit_coefficient", credit_coefficient);
+        computed.insert("total_deductible_vat", total_deductible_vat);
+        computed.insert(
+            "vat_to_pay",
+            (total_sales_vat - total_deductible_vat).max(Decimal::ZERO),
+        );
+        computed.insert(
+            "vat_to_refund",
+            (total_deductible_vat - total_sales_vat).max(Decimal::ZERO),
+        );
+
+        let fields: Vec<VatReturnFieldBreakdown> = FIELDS
+            .iter()
+            .map(|(field, column)| {
+                let computed = get(&computed, column);
+                let stored = stored_value(&vat_return, column);
+                VatReturnFieldBreakdown {
+                    field: (*field).to_owned(),
+                    column: (*column).to_owned(),
+                    computed,
+                    stored,
+                    difference: stored - computed,
+                    entries: sources.remove(column).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        VatReturnCalculation {
+            has_differences: fields.iter().any(|field| !field.difference.is_zero()),
+            vat_return,
+            fields,
+            sales_document_count,
+            purchase_document_count,
+            unmapped_entries,
+        }
+    }
+
+    /// Posted entries with a VAT operation whose VAT date falls in the period.
+    /// The return, the POKUPKI/PRODAGBI journals and VIES.TXT are all built
+    /// from these documents so that the filed files tie to each other.
+    pub async fn load_documents<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        period_from: NaiveDate,
+        period_to: NaiveDate,
+    ) -> Result<Vec<VatJournalDocument>> {
+        let rows = db
+            .query_all(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"
+            SELECT
+                je.id,
+                je.entry_number,
+                je.document_number,
+                je.document_date,
+                je.vat_document_type,
+                je.description,
+                cp.name as counterpart_name,
+                cp.vat_number as counterpart_vat_number,
+                je.vat_sales_operation,
+                je.vat_purchase_operation,
+                (je.total_amount - je.total_vat_amount) as base_amount,
+                je.total_vat_amount as vat_amount
+            FROM journal_entries je
+            LEFT JOIN LATERAL (
+                SELECT cp.name, cp.vat_number
+                FROM entry_lines el
+                JOIN counterparts cp ON el.counterpart_id = cp.id
+                WHERE el.journal_entry_id = je.id
+                ORDER BY (COALESCE(cp.vat_number, '') = ''), el.line_order
+                LIMIT 1
+            ) cp ON true
+            WHERE je.company_id = $1
+            AND COALESCE(je.vat_date, je.document_date) BETWEEN $2 AND $3
+            AND je.is_posted = true
+            AND (je.vat_sales_operation IS NOT NULL OR je.vat_purchase_operation IS NOT NULL)
+            ORDER BY je.document_date, je.document_number, je.id
+            "#,
+                vec![company_id.into(), period_from.into(), period_to.into()],
+            ))
+            .await?;
+
+        let mut documents = Vec::with_capacity(rows.len());
+        for row in rows {
+            documents.push(VatJournalDocument {
+                journal_entry_id: row.try_get("", "id")?,
+                entry_number: row.try_get("", "entry_number")?,
+                document_number: row.try_get("", "document_number")?,
+                document_date: row.try_get("", "document_date")?,
+                document_type: row.try_get("", "vat_document_type")?,
+                description: row.try_get("", "description")?,
+                counterpart_name: row.try_get("", "counterpart_name")?,
+                counterpart_vat_number: row.try_get("", "counterpart_vat_number")?,
+                sales_operation: row.try_get("", "vat_sales_operation")?,
+                purchase_operation: row.try_get("", "vat_purchase_operation")?,
+                base_amount: row.try_get("", "base_amount")?,
+                vat_amount: row.try_get("", "vat_amount")?,
+            });
+        }
+        Ok(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn document(
+        id: i32,
+        sales: Option<&str>,
+        purchase: Option<&str>,
+        base: Decimal,
+        vat: Decimal,
+    ) -> VatJournalDocument {
+        VatJournalDocument {
+            journal_entry_id: id,
+            entry_number: format!("JE-2025-{:06}", id),
+            document_number: Some(id.to_string()),
+            document_date: NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(),
+            sales_operation: sales.map(str::to_string),
+            purchase_operation: purchase.map(str::to_string),
+            base_amount: base,
+            vat_amount: vat,
+            ..Default::default()
+        }
+    }
+
+    fn empty_return() -> vat_return::Model {
+        let period_from = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        vat_return::Model {
+            id: 1,
+            period_year: 2025,
+            period_month: 3,
+            period_from,
+            period_to: NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+            output_vat_amount: Decimal::ZERO,
+            input_vat_amount: Decimal::ZERO,
+            vat_to_pay: Decimal::ZERO,
+            vat_to_refund: Decimal::ZERO,
+            base_amount_20: Decimal::ZERO,
+            vat_amount_20: Decimal::ZERO,
+            base_amount_9: Decimal::ZERO,
+            vat_amount_9: Decimal::ZERO,
+            base_amount_0: Decimal::ZERO,
+            exempt_amount: Decimal::ZERO,
+            total_sales_taxable: Decimal::ZERO,
+            total_sales_vat: Decimal::ZERO,
+            sales_base_20: Decimal::ZERO,
+            sales_vat_20: Decimal::ZERO,
+            sales_base_vop: Decimal::ZERO,
+            sales_vat_vop: Decimal::ZERO,
+            sales_vat_personal_use: Decimal::ZERO,
+            sales_base_9: Decimal::ZERO,
+            sales_vat_9: Decimal::ZERO,
+            sales_base_0_art3: Decimal::ZERO,
+            sales_base_0_vod: Decimal::ZERO,
+            sales_base_0_export: Decimal::ZERO,
+            sales_base_art21: Decimal::ZERO,
+            sales_base_art69: Decimal::ZERO,
+            sales_base_exempt: Decimal::ZERO,
+            purchase_base_no_credit: Decimal::ZERO,
+            purchase_base_full_credit: Decimal::ZERO,
+            purchase_vat_full_credit: Decimal::ZERO,
+            purchase_base_partial_credit: Decimal::ZERO,
+            purchase_vat_partial_credit: Decimal::ZERO,
+            purchase_vat_annual_adjustment: Decimal::ZERO,
+            credit_coefficient: Decimal::ZERO,
+            total_deductible_vat: Decimal::ZERO,
+            sales_document_count: 0,
+            purchase_document_count: 0,
+            submitted_by_person: None,
+            status: VatReturnStatus::Draft,
+            submitted_at: None,
+            submitted_by: None,
+            due_date: NaiveDate::from_ymd_opt(2025, 4, 14).unwrap(),
+            notes: None,
+            company_id: 1,
+            created_by: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+            corrects_id: None,
+            correction_reason: None,
+            deklar_content: None,
+            pokupki_content: None,
+            prodagbi_content: None,
+            vies_content: None,
+        }
+    }
+
+    #[test]
+    fn fields_are_filled_from_operation_codes() {
+        let mut vat_return = empty_return();
+        vat_return.sales_vat_20 = dec!(150);
+        vat_return.credit_coefficient = dec!(0.5);
+
+        let calculation = VatReturnCalculationService::calculate(
+            vat_return,
+            &[
+                document(1, Some("про11"), None, dec!(1000), dec!(200)),
+                document(2, Some("про20"), None, dec!(500), dec!(0)),
+                document(3, None, Some("пок10"), dec!(300), dec!(60)),
+                document(4, None, Some("пок12"), dec!(100), dec!(20)),
+                document(5, Some("про25"), None, dec!(80), dec!(0)),
+            ],
+        );
+
+        let field = |column: &str| {
+            calculation
+                .fields
+                .iter()
+                .find(|field| field.column == column)
+                .unwrap()
+        };
+        assert_eq!(field("sales_base_20").computed, dec!(1000));
+        assert_eq!(field("sales_base_0_vod").computed, dec!(500));
+        assert_eq!(field("total_sales_taxable").computed, dec!(1500));
+        assert_eq!(field("total_deductible_vat").computed, dec!(70));
+        assert_eq!(field("vat_to_pay").computed, dec!(130));
+        assert_eq!(
+            field("purchase_vat_full_credit").entries[0].journal_entry_id,
+            3
+        );
+
+        // The hand-typed 150 differs from the journals
+        assert_eq!(field("sales_vat_20").difference, dec!(-50));
+        assert!(calculation.has_differences);
+
+        assert_eq!(calculation.sales_document_count, 3);
+        assert_eq!(calculation.purchase_document_count, 2);
+        assert_eq!(calculation.unmapped_entries.len(), 1);
+    }
+}
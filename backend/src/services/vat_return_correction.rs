@@ -0,0 +1,326 @@
+//! VAT Return Correction Service
+//!
+//! Corrective declarations (поправителна декларация) for filed VAT periods.
+//! A correction is a new version of the period's return that points at the
+//! version it corrects. While it is in draft, company admins may book late
+//! documents into the hard-closed period; it is then recalculated from the
+//! journals and submitted like any other return.
+//!
+//! Every submission keeps the DEKLAR/POKUPKI/PRODAGBI/VIES files as filed, so
+//! a correction can be compared with what NAP actually received. Submitting a
+//! correction marks the earlier filed versions of the period as superseded.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::entities::vat_return::{self, CreateCorrectiveVatReturnInput, VatReturnStatus};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::nap_export::{NapExportService, NapFileTexts};
+use crate::services::vat_return_calculation::VatReturnCalculationService;
+
+pub struct VatReturnCorrectionService;
+
+/// Lines of one NAP file that differ between two versions of a return
+#[derive(SimpleObject, Serialize, Debug, Clone, PartialEq)]
+pub struct VatFileDiff {
+    pub file_name: String,
+    /// Lines of the corrective file missing from the filed one
+    pub added_lines: Vec<String>,
+    /// Lines of the filed file missing from the corrective one
+    pub removed_lines: Vec<String>,
+    pub unchanged_line_count: i32,
+}
+
+impl VatReturnCorrectionService {
+    /// All versions of a period's return, oldest first
+    pub async fn versions<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        year: i32,
+        month: i32,
+    ) -> Result<Vec<vat_return::Model>> {
+        Ok(vat_return::Entity::find()
+            .filter(vat_return::Column::CompanyId.eq(company_id))
+            .filter(vat_return::Column::PeriodYear.eq(year))
+            .filter(vat_return::Column::PeriodMonth.eq(month))
+            .order_by_asc(vat_return::Column::Version)
+            .all(db)
+            .await?)
+    }
+
+    /// Open a draft corrective return for a filed period and fill it from
+    /// the journals
+    pub async fn create_correction<C: ConnectionTrait>(
+        db: &C,
+        input: &CreateCorrectiveVatReturnInput,
+        user_id: i32,
+    ) -> Result<vat_return::Model> {
+        let reason = input.reason.trim();
+        if reason.is_empty() {
+            bail!("A corrective VAT return needs a reason");
+        }
+
+        let original = vat_return::Entity::find_by_id(input.vat_return_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("VAT return not found"))?;
+        if !matches!(
+            original.status,
+            VatReturnStatus::Submitted | VatReturnStatus::Approved
+        ) {
+            bail!("Only submitted VAT returns can be corrected");
+        }
+
+        let versions = Self::versions(
+            db,
+            original.company_id,
+            original.period_year,
+            original.period_month,
+        )
+        .await?;
+        if versions
+            .iter()
+            .any(|version| version.status == VatReturnStatus::Draft)
+        {
+            bail!(
+                "A corrective VAT return for {} is already in draft",
+                original.get_period_description()
+            );
+        }
+        let latest = versions
+            .iter()
+            .map(|version| version.version)
+            .max()
+            .unwrap_or(original.version);
+        if original.version != latest {
+            bail!("Only the latest filed version of a VAT return can be corrected");
+        }
+
+        let now = Utc::now();
+        let correction = vat_return::ActiveModel {
+            period_year: Set(original.period_year),
+            period_month: Set(original.period_month),
+            period_from: Set(original.period_from),
+            period_to: Set(original.period_to),
+            due_date: Set(original.due_date),
+            company_id: Set(original.company_id),
+            status: Set(VatReturnStatus::Draft),
+            version: Set(latest + 1),
+            corrects_id: Set(Some(original.id)),
+            correction_reason: Set(Some(reason.to_string())),
+            credit_coefficient: Set(original.credit_coefficient),
+            purchase_vat_annual_adjustment: Set(original.purchase_vat_annual_adjustment),
+            created_by: Set(user_id),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        let correction = vat_return::Entity::insert(correction)
+            .exec_with_returning(db)
+            .await?;
+
+        Ok(
+            VatReturnCalculationService::recalculate(db, correction.id, Some(user_id))
+                .await?
+                .vat_return,
+        )
+    }
+
+    /// Mark a return as submitted, keep its NAP files as filed, supersede the
+    /// versions it replaces and hard-close its period, all in one transaction
+    pub async fn record_submission<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        existing: vat_return::Model,
+        user_id: i32,
+    ) -> Result<vat_return::Model> {
+        let texts = NapExportService::generate_texts(
+            db,
+            existing.company_id,
+            existing.period_year,
+            existing.period_month,
+        )
+        .await?;
+
+        let txn = db.begin().await?;
+
+        let (company_id, year, month, version) = (
+            existing.company_id,
+            existing.period_year,
+            existing.period_month,
+            existing.version,
+        );
+        let mut model: vat_return::ActiveModel = existing.into();
+        model.status = Set(VatReturnStatus::Submitted);
+        model.submitted_at = Set(Some(Utc::now()));
+        model.submitted_by = Set(Some(user_id));
+        model.deklar_content = Set(Some(texts.deklar));
+        model.pokupki_content = Set(Some(texts.pokupki));
+        model.prodagbi_content = Set(Some(texts.prodagbi));
+        model.vies_content = Set(texts.vies);
+        model.updated_at = Set(Utc::now());
+        let submitted = model.update(&txn).await?;
+
+        vat_return::Entity::update_many()
+            .col_expr(
+                vat_return::Column::Status,
+                Expr::value(VatReturnStatus::Superseded),
+            )
+            .col_expr(vat_return::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(vat_return::Column::CompanyId.eq(company_id))
+            .filter(vat_return::Column::PeriodYear.eq(year))
+            .filter(vat_return::Column::PeriodMonth.eq(month))
+            .filter(vat_return::Column::Version.lt(version))
+            .filter(
+                vat_return::Column::Status
+                    .is_in([VatReturnStatus::Submitted, VatReturnStatus::Approved]),
+            )
+            .exec(&txn)
+            .await?;
+
+        // A filed period must not change afterwards
+        AccountingPeriodService::hard_close(
+            &txn,
+            company_id,
+            submitted.period_from,
+            Some(user_id),
+            Some("VAT return submitted".to_string()),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(submitted)
+    }
+
+    /// NAP files of a return: as filed once submitted, otherwise generated
+    /// from the current journals
+    pub async fn files(db: &DatabaseConnection, vat_return_id: i32) -> Result<NapFileTexts> {
+        let vat_return = vat_return::Entity::find_by_id(vat_return_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("VAT return not found"))?;
+
+        match Self::filed_files(&vat_return) {
+            Some(files) => Ok(files),
+            None => {
+                NapExportService::generate_texts(
+                    db,
+                    vat_return.company_id,
+                    vat_return.period_year,
+                    vat_return.period_month,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Compare the files of a corrective return with those filed for the
+    /// version it corrects
+    pub async fn diff(db: &DatabaseConnection, vat_return_id: i32) -> Result<Vec<VatFileDiff>> {
+        let correction = vat_return::Entity::find_by_id(vat_return_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("VAT return not found"))?;
+        let corrects_id = correction
+            .corrects_id
+            .ok_or_else(|| anyhow!("VAT return does not correct an earlier one"))?;
+        let original = vat_return::Entity::find_by_id(corrects_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| anyhow!("Corrected VAT return not found"))?;
+        let filed = Self::filed_files(&original)
+            .ok_or_else(|| anyhow!("The files of the corrected VAT return were not kept"))?;
+
+        let current = Self::files(db, correction.id).await?;
+
+        Ok(vec![
+            Self::diff_lines("DEKLAR.TXT", &filed.deklar, &current.deklar),
+            Self::diff_lines("POKUPKI.TXT", &filed.pokupki, &current.pokupki),
+            Self::diff_lines("PRODAGBI.TXT", &filed.prodagbi, &current.prodagbi),
+            Self::diff_lines(
+                "VIES.TXT",
+                filed.vies.as_deref().unwrap_or_default(),
+                current.vies.as_deref().unwrap_or_default(),
+            ),
+        ])
+    }
+
+    fn filed_files(vat_return: &vat_return::Model) -> Option<NapFileTexts> {
+        Some(NapFileTexts {
+            deklar: vat_return.deklar_content.clone()?,
+            pokupki: vat_return.pokupki_content.clone()?,
+            prodagbi: vat_return.prodagbi_content.clone()?,
+            vies: vat_return.vies_content.clone(),
+        })
+    }
+
+    /// Lines added and removed between two versions of a file. NAP records
+    /// carry their own keys, so lines are matched regardless of position.
+    fn diff_lines(file_name: &str, filed: &str, current: &str) -> VatFileDiff {
+        let mut remaining: HashMap<&str, usize> = HashMap::new();
+        for line in filed.lines() {
+            *remaining.entry(line).or_default() += 1;
+        }
+
+        let mut added_lines = Vec::new();
+        let mut unchanged_line_count = 0;
+        for line in current.lines() {
+            match remaining.get_mut(line) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    unchanged_line_count += 1;
+                }
+                _ => added_lines.push(line.to_string()),
+            }
+        }
+
+        let mut removed_lines = Vec::new();
+        for line in filed.lines() {
+            if let Some(count) = remaining.get_mut(line) {
+                if *count > 0 {
+                    *count -= 1;
+                    removed_lines.push(line.to_string());
+                }
+            }
+        }
+
+        VatFileDiff {
+            file_name: file_name.to_string(),
+            added_lines,
+            removed_lines,
+            unchanged_line_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lists_added_and_removed_records() {
+        let filed = "0001 INV-1 100.00\r\n0002 INV-2 50.00\r\n";
+        let current = "0001 INV-1 100.00\r\n0002 INV-2 55.00\r\n0003 INV-3 20.00\r\n";
+
+        let diff = VatReturnCorrectionService::diff_lines("PRODAGBI.TXT", filed, current);
+
+        assert_eq!(diff.file_name, "PRODAGBI.TXT");
+        assert_eq!(diff.unchanged_line_count, 1);
+        assert_eq!(
+            diff.added_lines,
+            vec![
+                "0002 INV-2 55.00".to_string(),
+                "0003 INV-3 20.00".to_string()
+            ]
+        );
+        assert_eq!(diff.removed_lines, vec!["0002 INV-2 50.00".to_string()]);
+
+        let same = VatReturnCorrectionService::diff_lines("DEKLAR.TXT", filed, filed);
+        assert!(same.added_lines.is_empty() && same.removed_lines.is_empty());
+    }
+}
@@ -0,0 +1,603 @@
+//! Year-End Closing Service
+//!
+//! Rolls a company's books into the next fiscal year with three posted entries:
+//!
+//! 1. result entry (31 December): closes every class 6 and class 7 balance,
+//!    per account and counterpart, into the profit/loss account (123)
+//! 2. balance closing entry (31 December): closes every remaining balance,
+//!    including the result now sitting on the profit/loss account
+//! 3. opening entry (1 January of the next year): reopens the balances closed
+//!    by the second entry
+//!
+//! Entries 2 and 3 cancel out across the year boundary. Period reports
+//! compute opening balances from all earlier postings and leave both entries
+//! out (see `rollover_entry_ids`), so December and January turnovers are not
+//! inflated by the roll-over; the next year still starts with an explicit
+//! opening entry. A closing can be reversed as long as the next year is not
+//! closed and the affected periods are open; its entries are then cancelled
+//! by storno entries.
+
+use anyhow::{anyhow, bail, Result};
+use async_graphql::SimpleObject;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use std::collections::BTreeMap;
+
+use crate::entities::year_end_closing::YearEndClosingInput;
+use crate::entities::{account, entry_line, journal_entry, year_end_closing, JournalSeriesKind};
+use crate::services::accounting_period::AccountingPeriodService;
+use crate::services::euro_changeover::EuroChangeoverService;
+use crate::services::journal_numbering::JournalNumberingService;
+use crate::services::journal_storno::JournalStornoService;
+
+/// Profit/loss account of the Bulgarian chart of accounts
+pub const PROFIT_LOSS_ACCOUNT_CODE: &str = "123";
+
+pub struct YearEndClosingService;
+
+/// Posted balance of one account/counterpart pair at year end
+#[derive(Debug, Clone)]
+pub struct YearEndBalance {
+    pub account_id: i32,
+    pub account_code: String,
+    pub account_class: i32,
+    pub counterpart_id: Option<i32>,
+    /// Debit-positive balance
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct YearEndClosingLine {
+    pub account_id: i32,
+    pub account_code: String,
+    pub counterpart_id: Option<i32>,
+    pub debit_amount: Decimal,
+    pub credit_amount: Decimal,
+}
+
+impl YearEndClosingLine {
+    fn new(
+        account_id: i32,
+        account_code: &str,
+        counterpart_id: Option<i32>,
+        amount: Decimal,
+    ) -> Self {
+        Self {
+            account_id,
+            account_code: account_code.to_string(),
+            counterpart_id,
+            debit_amount: amount.max(Decimal::ZERO),
+            credit_amount: (-amount).max(Decimal::ZERO),
+        }
+    }
+
+    /// Debit-positive amount of the line
+    pub fn amount(&self) -> Decimal {
+        self.debit_amount - self.credit_amount
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct YearEndClosingPlan {
+    pub fiscal_year: i32,
+    pub profit_loss_account_id: i32,
+    /// Profit positive, loss negative
+    pub net_result: Decimal,
+    pub result_lines: Vec<YearEndClosingLine>,
+    pub balance_closing_lines: Vec<YearEndClosingLine>,
+    pub opening_lines: Vec<YearEndClosingLine>,
+}
+
+impl YearEndClosingService {
+    fn is_result_class(account_class: i32) -> bool {
+        account_class == 6 || account_class == 7
+    }
+
+    /// Build the three closing entries from the year-end balances
+    pub fn plan(
+        fiscal_year: i32,
+        profit_loss_account_id: i32,
+        profit_loss_account_code: &str,
+        balances: impl IntoIterator<Item = YearEndBalance>,
+    ) -> YearEndClosingPlan {
+        let mut result_lines = Vec::new();
+        let mut remaining: BTreeMap<(String, i32, Option<i32>), Decimal> = BTreeMap::new();
+        let mut result_total = Decimal::ZERO;
+
+        for balance in balances {
+            if balance.balance.is_zero() {
+                continue;
+            }
+            if Self::is_result_class(balance.account_class) {
+                result_total += balance.balance;
+                result_lines.push(YearEndClosingLine::new(
+                    balance.account_id,
+                    &balance.account_code,
+                    balance.counterpart_id,
+                    -balance.balance,
+                ));
+            } else {
+                *remaining
+                    .entry((
+                        balance.account_code,
+                        balance.account_id,
+                        balance.counterpart_id,
+                    ))
+                    .or_default() += balance.balance;
+            }
+        }
+
+        // Revenue is credit, so a credit total of classes 6 and 7 is a profit
+        if !result_total.is_zero() {
+            result_lines.push(YearEndClosingLine::new(
+                profit_loss_account_id,
+                profit_loss_account_code,
+                None,
+                result_total,
+            ));
+            *remaining
+                .entry((
+                    profit_loss_account_code.to_string(),
+                    profit_loss_account_id,
+                    None,
+                ))
+                .or_default() += result_total;
+        }
+
+        let (balance_closing_lines, opening_lines) = remaining
+            .into_iter()
+            .filter(|(_, balance)| !balance.is_zero())
+            .map(|((code, account_id, counterpart_id), balance)| {
+                (
+                    YearEndClosingLine::new(account_id, &code, counterpart_id, -balance),
+                    YearEndClosingLine::new(account_id, &code, counterpart_id, balance),
+                )
+            })
+            .unzip();
+
+        YearEndClosingPlan {
+            fiscal_year,
+            profit_loss_account_id,
+            net_result: -result_total,
+            result_lines,
+            balance_closing_lines,
+            opening_lines,
+        }
+    }
+
+    /// Calculate the closing without booking anything
+    pub async fn preview<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        fiscal_year: i32,
+        profit_loss_account_id: Option<i32>,
+    ) -> Result<YearEndClosingPlan> {
+        let (year_end, _) = Self::year_boundaries(fiscal_year)?;
+        let profit_loss_account =
+            Self::profit_loss_account(db, company_id, profit_loss_account_id).await?;
+        let balances = Self::load_balances(db, company_id, year_end).await?;
+
+        Ok(Self::plan(
+            fiscal_year,
+            profit_loss_account.id,
+            &profit_loss_account.code,
+            balances,
+        ))
+    }
+
+    pub async fn find<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        fiscal_year: i32,
+    ) -> Result<Option<year_end_closing::Model>> {
+        Ok(year_end_closing::Entity::find()
+            .filter(year_end_closing::Column::CompanyId.eq(company_id))
+            .filter(year_end_closing::Column::FiscalYear.eq(fiscal_year))
+            .one(db)
+            .await?)
+    }
+
+    /// Balance closing and opening entries of all closed years of a company.
+    /// They only carry balances across the year boundary, so period reports
+    /// leave them out.
+    pub async fn rollover_entry_ids<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+    ) -> Result<Vec<i32>> {
+        Ok(year_end_closing::Entity::find()
+            .filter(year_end_closing::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .flat_map(|closing| [closing.balance_closing_entry_id, closing.opening_entry_id])
+            .flatten()
+            .collect())
+    }
+
+    /// Book the closing and opening entries for a fiscal year
+    pub async fn execute<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        input: &YearEndClosingInput,
+        created_by: i32,
+    ) -> Result<year_end_closing::Model> {
+        let txn = db.begin().await?;
+        let company_id = input.company_id;
+        let (year_end, next_year_start) = Self::year_boundaries(input.fiscal_year)?;
+
+        if Self::find(&txn, company_id, input.fiscal_year)
+            .await?
+            .is_some()
+        {
+            bail!("Fiscal year {} is already closed", input.fiscal_year);
+        }
+
+        let year_start = NaiveDate::from_ymd_opt(input.fiscal_year, 1, 1)
+            .ok_or_else(|| anyhow!("Invalid fiscal year {}", input.fiscal_year))?;
+        let drafts = journal_entry::Entity::find()
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .filter(journal_entry::Column::IsPosted.eq(false))
+            .filter(journal_entry::Column::AccountingDate.gte(year_start))
+            .filter(journal_entry::Column::AccountingDate.lte(year_end))
+            .count(&txn)
+            .await?;
+        if drafts > 0 {
+            bail!(
+                "{} unposted journal entries belong to {}; post or delete them first",
+                drafts,
+                input.fiscal_year
+            );
+        }
+
+        AccountingPeriodService::ensure_open(&txn, company_id, year_end, Some(created_by)).await?;
+        AccountingPeriodService::ensure_open(&txn, company_id, next_year_start, Some(created_by))
+            .await?;
+
+        let profit_loss_account =
+            Self::profit_loss_account(&txn, company_id, input.profit_loss_account_id).await?;
+        let plan = Self::plan(
+            input.fiscal_year,
+            profit_loss_account.id,
+            &profit_loss_account.code,
+            Self::load_balances(&txn, company_id, year_end).await?,
+        );
+
+        let result_entry_id = Self::book_entry(
+            &txn,
+            company_id,
+            created_by,
+            year_end,
+            format!("Приключване на приходи и разходи за {}", input.fiscal_year),
+            &plan.result_lines,
+        )
+        .await?;
+        let balance_closing_entry_id = Self::book_entry(
+            &txn,
+            company_id,
+            created_by,
+            year_end,
+            format!("Приключване на салдата към 31.12.{}", input.fiscal_year),
+            &plan.balance_closing_lines,
+        )
+        .await?;
+        let opening_entry_id = Self::book_entry(
+            &txn,
+            company_id,
+            created_by,
+            next_year_start,
+            format!("Начални салда за {}", input.fiscal_year + 1),
+            &plan.opening_lines,
+        )
+        .await?;
+
+        let closing = year_end_closing::ActiveModel {
+            company_id: Set(company_id),
+            fiscal_year: Set(input.fiscal_year),
+            profit_loss_account_id: Set(profit_loss_account.id),
+            net_result: Set(plan.net_result),
+            result_entry_id: Set(result_entry_id),
+            balance_closing_entry_id: Set(balance_closing_entry_id),
+            opening_entry_id: Set(opening_entry_id),
+            created_by: Set(Some(created_by)),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let closing = year_end_closing::Entity::insert(closing)
+            .exec_with_returning(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(closing)
+    }
+
+    /// Cancel the closing and opening entries of a fiscal year with storno
+    /// entries and drop the closing, so the year can be closed again
+    pub async fn reverse<C: ConnectionTrait + TransactionTrait>(
+        db: &C,
+        company_id: i32,
+        fiscal_year: i32,
+        user_id: i32,
+    ) -> Result<()> {
+        let txn = db.begin().await?;
+        let (year_end, next_year_start) = Self::year_boundaries(fiscal_year)?;
+
+        let closing = Self::find(&txn, company_id, fiscal_year)
+            .await?
+            .ok_or_else(|| anyhow!("Fiscal year {} is not closed", fiscal_year))?;
+
+        if Self::find(&txn, company_id, fiscal_year + 1)
+            .await?
+            .is_some()
+        {
+            bail!(
+                "Fiscal year {} is closed; reverse its closing first",
+                fiscal_year + 1
+            );
+        }
+
+        AccountingPeriodService::ensure_open(&txn, company_id, year_end, Some(user_id)).await?;
+        AccountingPeriodService::ensure_open(&txn, company_id, next_year_start, Some(user_id))
+            .await?;
+
+        // Last entry first, so each storno follows the entry it cancels
+        let entry_ids = [
+            closing.opening_entry_id,
+            closing.balance_closing_entry_id,
+            closing.result_entry_id,
+        ];
+        for entry_id in entry_ids.into_iter().flatten() {
+            JournalStornoService::storno(&txn, entry_id, user_id).await?;
+        }
+
+        year_end_closing::Entity::delete_by_id(closing.id)
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    fn year_boundaries(fiscal_year: i32) -> Result<(NaiveDate, NaiveDate)> {
+        let year_end = NaiveDate::from_ymd_opt(fiscal_year, 12, 31)
+            .ok_or_else(|| anyhow!("Invalid fiscal year {}", fiscal_year))?;
+        let next_year_start = NaiveDate::from_ymd_opt(fiscal_year + 1, 1, 1)
+            .ok_or_else(|| anyhow!("Invalid fiscal year {}", fiscal_year))?;
+        Ok((year_end, next_year_start))
+    }
+
+    async fn profit_loss_account<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        account_id: Option<i32>,
+    ) -> Result<account::Model> {
+        let query = account::Entity::find().filter(account::Column::CompanyId.eq(company_id));
+        let account = match account_id {
+            Some(account_id) => query.filter(account::Column::Id.eq(account_id)),
+            None => query.filter(account::Column::Code.eq(PROFIT_LOSS_ACCOUNT_CODE)),
+        }
+        .one(db)
+        .await?;
+
+        let account =
+            account.ok_or_else(|| anyhow!("Profit/loss account not found in this company"))?;
+        if Self::is_result_class(account.account_class) {
+            bail!(
+                "Account {} is a revenue/expense account and cannot hold the result",
+                account.code
+            );
+        }
+        Ok(account)
+    }
+
+    async fn book_entry(
+        txn: &DatabaseTransaction,
+        company_id: i32,
+        created_by: i32,
+        date: NaiveDate,
+        description: String,
+        lines: &[YearEndClosingLine],
+    ) -> Result<Option<i32>> {
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let currency_code =
+            EuroChangeoverService::base_currency_code(txn, company_id, date).await?;
+        let total_amount: Decimal = lines.iter().map(|line| line.debit_amount).sum();
+        let number =
+            JournalNumberingService::allocate(txn, company_id, JournalSeriesKind::Closing, date)
+                .await?;
+
+        let entry = journal_entry::ActiveModel {
+            entry_number: Set(number.entry_number),
+            document_date: Set(date),
+            vat_date: Set(None),
+            accounting_date: Set(date),
+            document_number: Set(None),
+            description: Set(description.clone()),
+            total_amount: Set(total_amount),
+            total_vat_amount: Set(Decimal::ZERO),
+            is_posted: Set(true),
+            posted_by: Set(Some(created_by)),
+            posted_at: Set(Some(Utc::now())),
+            created_by: Set(created_by),
+            company_id: Set(company_id),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            vat_document_type: Set(None),
+            vat_purchase_operation: Set(None),
+            vat_sales_operation: Set(None),
+            vat_additional_operation: Set(None),
+            vat_additional_data: Set(None),
+            series_id: Set(Some(number.series_id)),
+            sequence_number: Set(Some(number.sequence_number)),
+            ..Default::default()
+        };
+        let entry = journal_entry::Entity::insert(entry)
+            .exec_with_returning(txn)
+            .await?;
+
+        let line_models = lines.iter().enumerate().map(|(idx, line)| {
+            let amount = line.amount().abs();
+            entry_line::ActiveModel {
+                journal_entry_id: Set(entry.id),
+                account_id: Set(line.account_id),
+                debit_amount: Set(line.debit_amount),
+                credit_amount: Set(line.credit_amount),
+                counterpart_id: Set(line.counterpart_id),
+                currency_code: Set(Some(currency_code.clone())),
+                currency_amount: Set(Some(amount)),
+                exchange_rate: Set(Some(Decimal::ONE)),
+                base_amount: Set(amount),
+                vat_amount: Set(Decimal::ZERO),
+                vat_rate_id: Set(None),
+                quantity: Set(None),
+                unit_of_measure_code: Set(None),
+                description: Set(Some(description.clone())),
+                line_order: Set(idx as i32 + 1),
+                created_at: Set(Utc::now()),
+                ..Default::default()
+            }
+        });
+        entry_line::Entity::insert_many(line_models)
+            .exec(txn)
+            .await?;
+
+        Ok(Some(entry.id))
+    }
+
+    /// Posted balances per account and counterpart up to the year end
+    async fn load_balances<C: ConnectionTrait>(
+        db: &C,
+        company_id: i32,
+        year_end: NaiveDate,
+    ) -> Result<Vec<YearEndBalance>> {
+        let lines = entry_line::Entity::find()
+            .find_also_related(journal_entry::Entity)
+            .filter(journal_entry::Column::CompanyId.eq(company_id))
+            .filter(journal_entry::Column::IsPosted.eq(true))
+            .filter(journal_entry::Column::AccountingDate.lte(year_end))
+            .all(db)
+            .await?;
+
+        let mut balances: BTreeMap<(i32, Option<i32>), Decimal> = BTreeMap::new();
+        for (line, _) in lines {
+            *balances
+                .entry((line.account_id, line.counterpart_id))
+                .or_default() += line.debit_amount - line.credit_amount;
+        }
+
+        let accounts: BTreeMap<i32, account::Model> = account::Entity::find()
+            .filter(account::Column::CompanyId.eq(company_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect();
+
+        let mut result: Vec<_> = balances
+            .into_iter()
+            .map(|((account_id, counterpart_id), balance)| {
+                let account = accounts.get(&account_id);
+                YearEndBalance {
+                    account_id,
+                    account_code: account.map(|a| a.code.clone()).unwrap_or_default(),
+                    account_class: account.map(|a| a.account_class).unwrap_or_default(),
+                    counterpart_id,
+                    balance,
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            a.account_code
+                .cmp(&b.account_code)
+                .then(a.counterpart_id.cmp(&b.counterpart_id))
+        });
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn balance(
+        account_id: i32,
+        code: &str,
+        counterpart_id: Option<i32>,
+        amount: &str,
+    ) -> YearEndBalance {
+        YearEndBalance {
+            account_id,
+            account_code: code.to_string(),
+            account_class: code[..1].parse().unwrap(),
+            counterpart_id,
+            balance: dec(amount),
+        }
+    }
+
+    fn sample_plan() -> YearEndClosingPlan {
+        YearEndClosingService::plan(
+            2025,
+            10,
+            "123",
+            vec![
+                balance(1, "501", None, "1500.00"),
+                balance(2, "411", Some(7), "1200.00"),
+                balance(3, "401", Some(8), "-900.00"),
+                balance(4, "602", Some(8), "900.00"),
+                balance(5, "703", Some(7), "-2700.00"),
+                balance(6, "101", None, "-0.00"),
+            ],
+        )
+    }
+
+    #[test]
+    fn result_entry_closes_revenue_and_expense_into_profit_loss() {
+        let plan = sample_plan();
+
+        assert_eq!(plan.net_result, dec("1800.00"));
+        assert_eq!(plan.result_lines.len(), 3);
+        let total: Decimal = plan.result_lines.iter().map(|line| line.amount()).sum();
+        assert!(total.is_zero());
+
+        let result_line = plan.result_lines.last().unwrap();
+        assert_eq!(result_line.account_id, 10);
+        assert_eq!(result_line.credit_amount, dec("1800.00"));
+    }
+
+    #[test]
+    fn opening_entry_reopens_balances_including_the_result() {
+        let plan = sample_plan();
+
+        assert_eq!(plan.opening_lines.len(), 4);
+        let total: Decimal = plan.opening_lines.iter().map(|line| line.amount()).sum();
+        assert!(total.is_zero());
+        assert!(
+            plan.opening_lines
+                .iter()
+                .all(|line| !line.account_code.starts_with('6')
+                    && !line.account_code.starts_with('7'))
+        );
+
+        let result = plan
+            .opening_lines
+            .iter()
+            .find(|line| line.account_code == "123")
+            .unwrap();
+        assert_eq!(result.credit_amount, dec("1800.00"));
+
+        for (closing, opening) in plan.balance_closing_lines.iter().zip(&plan.opening_lines) {
+            assert_eq!(closing.account_id, opening.account_id);
+            assert_eq!(closing.counterpart_id, opening.counterpart_id);
+            assert_eq!(closing.amount(), -opening.amount());
+        }
+    }
+}
@@ -88,6 +88,15 @@ const IMPORT_FORMAT_LABELS = {
   POSTBANK_XML: 'Postbank XML',
   OBB_XML: 'OBB XML',
   CCB_CSV: 'ЦКБ CSV',
+  DSK_CSV: 'ДСК CSV',
+  FIBANK_XML: 'Fibank XML',
+  OBB_CSV: 'ОББ CSV',
+  PROCREDIT_MT940: 'ProCredit MT940',
+  ALLIANZ_MT940: 'Алианц MT940',
+  MT942: 'MT942',
+  CAMT052: 'CAMT.052',
+  CAMT054: 'CAMT.054',
+  CUSTOM_CSV: 'CSV по шаблон',
 };
 
 const IMPORTS_PAGE_SIZE = 25;
@@ -26,8 +26,8 @@ export default function DepreciationCalculation({ onRefreshStats }) {
   `;
 
   const POST_MUTATION = `
-    mutation PostDepreciation($input: PostDepreciationInput!, $userId: Int!) {
-      postDepreciation(input: $input, userId: $userId) {
+    mutation PostDepreciation($input: PostDepreciationInput!) {
+      postDepreciation(input: $input) {
         success
         journalEntryId
         totalAmount
@@ -98,7 +98,6 @@ export default function DepreciationCalculation({ onRefreshStats }) {
       setLoading(true);
       
       const companyId = parseInt(localStorage.getItem('currentCompanyId')) || 1;
-      const userId = 1; // TODO: Get from auth context
       
       const input = {
         companyId,
@@ -107,7 +106,7 @@ export default function DepreciationCalculation({ onRefreshStats }) {
         reference: `АМ-${year}-${String(month).padStart(2, '0')}`
       };
       
-      const response = await graphqlRequest(POST_MUTATION, { input, userId });
+      const response = await graphqlRequest(POST_MUTATION, { input });
       setPostingResult(response.postDepreciation);
 
       if (response.postDepreciation.success) {
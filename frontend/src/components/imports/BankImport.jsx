@@ -43,6 +43,9 @@ const IMPORT_BANK_STATEMENT = `
       totalDebit
       totalCredit
       journalEntryIds
+      skippedDuplicates
+      autoPosted
+      overlappingImportIds
     }
   }
 `;
@@ -90,6 +93,60 @@ const importFormatConfig = {
     icon: '🏦',
     description: 'CSV извлечения от ЦКБ в Windows-1251 формат.',
   },
+  DSK_CSV: {
+    label: 'ДСК CSV',
+    extensions: ['.csv'],
+    icon: '🏦',
+    description: 'CSV извлечения от ДСК Директ в UTF-8 или Windows-1251.',
+  },
+  FIBANK_XML: {
+    label: 'Fibank XML',
+    extensions: ['.xml'],
+    icon: '🏦',
+    description: 'XML извлечения от e-fibank.',
+  },
+  OBB_CSV: {
+    label: 'ОББ CSV',
+    extensions: ['.csv'],
+    icon: '🏦',
+    description: 'CSV извлечения от ОББ Бизнес Онлайн в UTF-8 или Windows-1251.',
+  },
+  PROCREDIT_MT940: {
+    label: 'ProCredit MT940',
+    extensions: ['.mt940', '.sta', '.txt'],
+    icon: '🏦',
+    description: 'SWIFT MT940 извлечения от ПроКредит Банк.',
+  },
+  ALLIANZ_MT940: {
+    label: 'Алианц MT940',
+    extensions: ['.mt940', '.sta', '.txt'],
+    icon: '🏦',
+    description: 'SWIFT MT940 извлечения от Алианц Банк България.',
+  },
+  MT942: {
+    label: 'MT942',
+    extensions: ['.mt942', '.sta', '.txt'],
+    icon: '⏱️',
+    description: 'SWIFT MT942 дневни извлечения от всяка банка.',
+  },
+  CAMT052: {
+    label: 'CAMT.052 XML',
+    extensions: ['.xml'],
+    icon: '⏱️',
+    description: 'ISO 20022 CAMT.052 дневни отчети; чакащите движения се пропускат.',
+  },
+  CAMT054: {
+    label: 'CAMT.054 XML',
+    extensions: ['.xml'],
+    icon: '🔔',
+    description: 'ISO 20022 CAMT.054 известия за дебит и кредит.',
+  },
+  CUSTOM_CSV: {
+    label: 'CSV по шаблон',
+    extensions: ['.csv', '.txt'],
+    icon: '🧩',
+    description: 'CSV от всяка банка, прочетен с шаблона за колони от настройките на профила.',
+  },
 };
 
 const baseFeatures = [
@@ -106,6 +163,15 @@ const formatSpecificFeatures = {
   POSTBANK_XML: ['Импорт на XML, предоставени от Postbank API', 'Разпознаване на контрагенти и бордеро номер'],
   OBB_XML: ['Импорт на XML от ОББ', 'Разпознаване на картови плащания, бордеро и референции'],
   CCB_CSV: ['Импорт на CSV извлечения от ЦКБ', 'Автоматично разпознаване по полетата Приход/Разход'],
+  DSK_CSV: ['Разпознаване на колоните по заглавния ред', 'IBAN на контрагента в описанието'],
+  FIBANK_XML: ['Импорт на XML движения от e-fibank', 'Разпознаване на Д/К и референции'],
+  OBB_CSV: ['Разпознаване на колоните по заглавния ред', 'Посока на движението по колона Д/К'],
+  PROCREDIT_MT940: ['Структурирано поле :86: с подполета ?NN', 'Контрагент и IBAN в описанието'],
+  ALLIANZ_MT940: ['Структурирано поле :86: с подполета /REMI/, /NAME/, /IBAN/', 'Сторно движения RC/RD'],
+  MT942: ['Дневни движения по тагове :61: и :86:', 'Дубликатите с крайното извлечение се пропускат'],
+  CAMT052: ['Импорт само на осчетоводените (BOOK) движения'],
+  CAMT054: ['Импорт на известия за отделни движения'],
+  CUSTOM_CSV: ['Разделител, кодировка, формат на датата и колони от шаблона', 'Преглед на първите редове преди импорт'],
 };
 
 const defaultFormatConfig = {
@@ -578,6 +644,21 @@ export default function BankImport() {
                         <div>Журнални записи: {file.summary.transactions}</div>
                         <div>Сума Дт: {file.summary.totalDebit}</div>
                         <div>Сума Кт: {file.summary.totalCredit}</div>
+                        {file.summary.skippedDuplicates > 0 && (
+                          <div className="text-amber-700">
+                            Пропуснати като вече импортирани: {file.summary.skippedDuplicates}
+                          </div>
+                        )}
+                        {file.summary.autoPosted > 0 && (
+                          <div>
+                            Осчетоводени по банкови правила: {file.summary.autoPosted}
+                          </div>
+                        )}
+                        {file.summary.overlappingImportIds?.length > 0 && (
+                          <div className="text-amber-700">
+                            Периодът се застъпва с импорти: {file.summary.overlappingImportIds.join(', ')}
+                          </div>
+                        )}
                         {file.summary.journalEntryIds?.length > 0 && (
                           <div className="mt-1 text-xs text-green-700">
                             ID на журнални записи: {file.summary.journalEntryIds.join(', ')}
@@ -74,6 +74,15 @@ const importFormatOptions = [
   { value: 'POSTBANK_XML', label: 'Postbank XML' },
   { value: 'OBB_XML', label: 'OBB XML' },
   { value: 'CCB_CSV', label: 'ЦКБ CSV' },
+  { value: 'DSK_CSV', label: 'ДСК CSV' },
+  { value: 'FIBANK_XML', label: 'Fibank XML' },
+  { value: 'OBB_CSV', label: 'ОББ CSV' },
+  { value: 'PROCREDIT_MT940', label: 'ProCredit MT940 (SWIFT/TXT)' },
+  { value: 'ALLIANZ_MT940', label: 'Алианц MT940 (SWIFT/TXT)' },
+  { value: 'MT942', label: 'MT942 (дневно извлечение)' },
+  { value: 'CAMT052', label: 'CAMT.052 XML (дневен отчет)' },
+  { value: 'CAMT054', label: 'CAMT.054 XML (известия)' },
+  { value: 'CUSTOM_CSV', label: 'CSV по шаблон (csvMapping в настройките)' },
 ];
 
 const initialFormState = {
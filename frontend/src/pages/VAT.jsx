@@ -76,6 +76,7 @@ const GENERATE_NAP_FILES_MUTATION = `
       deklarContent
       pokupkiContent
       prodagbiContent
+      viesContent
     }
   }
 `;
@@ -406,6 +407,7 @@ export default function VAT() {
         { key: 'deklarContent', name: 'DEKLAR.TXT' },
         { key: 'pokupkiContent', name: 'POKUPKI.TXT' },
         { key: 'prodagbiContent', name: 'PRODAGBI.TXT' },
+        { key: 'viesContent', name: 'VIES.TXT' },
       ];
 
       let addedFiles = 0;
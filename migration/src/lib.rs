@@ -39,6 +39,21 @@ mod m20250101_000001_add_nap_fields_to_vat_returns;
 mod m20251015_000001_create_inventory_management;
 mod m20251017_000001_create_ai_accounting_settings;
 mod m20251017_000002_create_ai_bank_accounting_settings;
+mod m20251101_000001_create_euro_changeovers;
+mod m20251101_000002_create_accounting_periods;
+mod m20251101_000003_create_year_end_closings;
+mod m20251101_000004_create_audit_logs;
+mod m20251101_000005_create_journal_entry_series;
+mod m20251101_000006_add_vat_return_corrections;
+mod m20251101_000007_create_bank_transaction_fingerprints;
+mod m20251101_000008_create_fx_revaluations;
+mod m20251101_000009_create_bank_reconciliation_matches;
+mod m20251101_000010_add_bank_rule_postings;
+mod m20251101_000011_create_payment_batches;
+mod m20251101_000012_create_fixed_asset_events;
+mod m20251101_000013_add_depreciation_conventions;
+mod m20251101_000014_add_fixed_asset_opening_balance;
+mod m20251101_000015_add_fixed_asset_event_tax_value;
 
 pub struct Migrator;
 
@@ -85,6 +100,21 @@ impl MigratorTrait for Migrator {
             Box::new(m20251015_000001_create_inventory_management::Migration),
             Box::new(m20251017_000001_create_ai_accounting_settings::Migration),
             Box::new(m20251017_000002_create_ai_bank_accounting_settings::Migration),
+            Box::new(m20251101_000001_create_euro_changeovers::Migration),
+            Box::new(m20251101_000002_create_accounting_periods::Migration),
+            Box::new(m20251101_000003_create_year_end_closings::Migration),
+            Box::new(m20251101_000004_create_audit_logs::Migration),
+            Box::new(m20251101_000005_create_journal_entry_series::Migration),
+            Box::new(m20251101_000006_add_vat_return_corrections::Migration),
+            Box::new(m20251101_000007_create_bank_transaction_fingerprints::Migration),
+            Box::new(m20251101_000008_create_fx_revaluations::Migration),
+            Box::new(m20251101_000009_create_bank_reconciliation_matches::Migration),
+            Box::new(m20251101_000010_add_bank_rule_postings::Migration),
+            Box::new(m20251101_000011_create_payment_batches::Migration),
+            Box::new(m20251101_000012_create_fixed_asset_events::Migration),
+            Box::new(m20251101_000013_add_depreciation_conventions::Migration),
+            Box::new(m20251101_000014_add_fixed_asset_opening_balance::Migration),
+            Box::new(m20251101_000015_add_fixed_asset_event_tax_value::Migration),
             // Box::new(m20240101_000002_create_posts_table::Migration), // Not needed
         ]
     }
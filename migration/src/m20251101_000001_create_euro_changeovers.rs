@@ -0,0 +1,156 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EuroChangeovers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EuroChangeovers::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EuroChangeovers::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    // Last day kept in the previous currency
+                    .col(
+                        ColumnDef::new(EuroChangeovers::CutoffDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EuroChangeovers::PreviousCurrencyCode)
+                            .string_len(3)
+                            .not_null()
+                            .default("BGN"),
+                    )
+                    .col(
+                        ColumnDef::new(EuroChangeovers::ExchangeRate)
+                            .decimal_len(10, 5)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EuroChangeovers::RoundingAccountId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EuroChangeovers::RoundingDifference)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(EuroChangeovers::JournalEntryId)
+                            .integer()
+                            .null(), // No entry when the books had no balances
+                    )
+                    .col(ColumnDef::new(EuroChangeovers::CreatedBy).integer().null())
+                    .col(
+                        ColumnDef::new(EuroChangeovers::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_euro_changeovers_company")
+                            .from(EuroChangeovers::Table, EuroChangeovers::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_euro_changeovers_rounding_account")
+                            .from(EuroChangeovers::Table, EuroChangeovers::RoundingAccountId)
+                            .to(Accounts::Table, Accounts::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_euro_changeovers_journal_entry")
+                            .from(EuroChangeovers::Table, EuroChangeovers::JournalEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A company changes over only once
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_euro_changeovers_company")
+                    .table(EuroChangeovers::Table)
+                    .col(EuroChangeovers::CompanyId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Make sure the euro is available as a base currency
+        let sql = r#"
+            INSERT INTO currencies (code, name, name_bg, symbol, decimal_places, is_active, is_base_currency, bnb_code)
+            SELECT 'EUR', 'Euro', 'Евро', '€', 2, true, false, 'EUR'
+            WHERE NOT EXISTS (SELECT 1 FROM currencies WHERE code = 'EUR')
+        "#;
+        manager.get_connection().execute_unprepared(sql).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EuroChangeovers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EuroChangeovers {
+    #[sea_orm(iden = "euro_changeovers")]
+    Table,
+    Id,
+    CompanyId,
+    CutoffDate,
+    PreviousCurrencyCode,
+    ExchangeRate,
+    RoundingAccountId,
+    RoundingDifference,
+    JournalEntryId,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Accounts {
+    #[sea_orm(iden = "accounts")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
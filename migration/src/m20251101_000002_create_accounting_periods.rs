@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountingPeriods::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountingPeriods::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountingPeriods::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AccountingPeriods::Year).integer().not_null())
+                    .col(
+                        ColumnDef::new(AccountingPeriods::Month)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountingPeriods::Status)
+                            .string_len(12)
+                            .not_null()
+                            .default("OPEN"), // OPEN, SOFT_CLOSED, HARD_CLOSED
+                    )
+                    .col(ColumnDef::new(AccountingPeriods::ClosedBy).integer().null())
+                    .col(
+                        ColumnDef::new(AccountingPeriods::ClosedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(AccountingPeriods::Notes).text().null())
+                    .col(
+                        ColumnDef::new(AccountingPeriods::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(AccountingPeriods::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_accounting_periods_company")
+                            .from(AccountingPeriods::Table, AccountingPeriods::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Months without a row are open
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_accounting_periods_company_month")
+                    .table(AccountingPeriods::Table)
+                    .col(AccountingPeriods::CompanyId)
+                    .col(AccountingPeriods::Year)
+                    .col(AccountingPeriods::Month)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AccountingPeriods::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AccountingPeriods {
+    #[sea_orm(iden = "accounting_periods")]
+    Table,
+    Id,
+    CompanyId,
+    Year,
+    Month,
+    Status,
+    ClosedBy,
+    ClosedAt,
+    Notes,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
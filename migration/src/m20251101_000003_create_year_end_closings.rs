@@ -0,0 +1,164 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(YearEndClosings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(YearEndClosings::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(YearEndClosings::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(YearEndClosings::FiscalYear)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(YearEndClosings::ProfitLossAccountId)
+                            .integer()
+                            .not_null(),
+                    )
+                    // Profit positive, loss negative
+                    .col(
+                        ColumnDef::new(YearEndClosings::NetResult)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(YearEndClosings::ResultEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(YearEndClosings::BalanceClosingEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(YearEndClosings::OpeningEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(YearEndClosings::CreatedBy).integer().null())
+                    .col(
+                        ColumnDef::new(YearEndClosings::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_year_end_closings_company")
+                            .from(YearEndClosings::Table, YearEndClosings::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_year_end_closings_profit_loss_account")
+                            .from(YearEndClosings::Table, YearEndClosings::ProfitLossAccountId)
+                            .to(Accounts::Table, Accounts::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_year_end_closings_result_entry")
+                            .from(YearEndClosings::Table, YearEndClosings::ResultEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_year_end_closings_balance_closing_entry")
+                            .from(
+                                YearEndClosings::Table,
+                                YearEndClosings::BalanceClosingEntryId,
+                            )
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_year_end_closings_opening_entry")
+                            .from(YearEndClosings::Table, YearEndClosings::OpeningEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_year_end_closings_company_year")
+                    .table(YearEndClosings::Table)
+                    .col(YearEndClosings::CompanyId)
+                    .col(YearEndClosings::FiscalYear)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(YearEndClosings::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum YearEndClosings {
+    #[sea_orm(iden = "year_end_closings")]
+    Table,
+    Id,
+    CompanyId,
+    FiscalYear,
+    ProfitLossAccountId,
+    NetResult,
+    ResultEntryId,
+    BalanceClosingEntryId,
+    OpeningEntryId,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Accounts {
+    #[sea_orm(iden = "accounts")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
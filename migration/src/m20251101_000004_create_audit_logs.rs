@@ -0,0 +1,109 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign keys: the trail must survive deletion of the records it describes
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLogs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLogs::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLogs::CompanyId).integer().null())
+                    .col(ColumnDef::new(AuditLogs::UserId).integer().null())
+                    .col(ColumnDef::new(AuditLogs::Username).string_len(100).null())
+                    .col(ColumnDef::new(AuditLogs::Action).string_len(10).not_null())
+                    .col(
+                        ColumnDef::new(AuditLogs::EntityType)
+                            .string_len(50)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AuditLogs::EntityId).integer().null())
+                    .col(ColumnDef::new(AuditLogs::BeforeData).json_binary().null())
+                    .col(ColumnDef::new(AuditLogs::AfterData).json_binary().null())
+                    .col(
+                        ColumnDef::new(AuditLogs::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_logs_company_created")
+                    .table(AuditLogs::Table)
+                    .col(AuditLogs::CompanyId)
+                    .col(AuditLogs::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_logs_entity")
+                    .table(AuditLogs::Table)
+                    .col(AuditLogs::EntityType)
+                    .col(AuditLogs::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Append-only: reject any UPDATE or DELETE of audit records
+        let sql = r#"
+            CREATE OR REPLACE FUNCTION audit_logs_append_only() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'audit_logs is append-only';
+            END;
+            $$ LANGUAGE plpgsql;
+
+            CREATE TRIGGER trg_audit_logs_append_only
+                BEFORE UPDATE OR DELETE ON audit_logs
+                FOR EACH ROW EXECUTE FUNCTION audit_logs_append_only();
+        "#;
+        manager.get_connection().execute_unprepared(sql).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLogs::Table).to_owned())
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS audit_logs_append_only()")
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLogs {
+    #[sea_orm(iden = "audit_logs")]
+    Table,
+    Id,
+    CompanyId,
+    UserId,
+    Username,
+    Action,
+    EntityType,
+    EntityId,
+    BeforeData,
+    AfterData,
+    CreatedAt,
+}
@@ -0,0 +1,190 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JournalEntrySeries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::Code)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::Year)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::Prefix)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::Padding)
+                            .integer()
+                            .not_null()
+                            .default(6),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::StartNumber)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::NextNumber)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(JournalEntrySeries::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_journal_entry_series_company")
+                            .from(JournalEntrySeries::Table, JournalEntrySeries::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_journal_entry_series_company_code_year")
+                    .table(JournalEntrySeries::Table)
+                    .col(JournalEntrySeries::CompanyId)
+                    .col(JournalEntrySeries::Code)
+                    .col(JournalEntrySeries::Year)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JournalEntries::Table)
+                    .add_column(ColumnDef::new(JournalEntries::SeriesId).integer().null())
+                    .add_column(
+                        ColumnDef::new(JournalEntries::SequenceNumber)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("fk_journal_entries_series")
+                    .from(JournalEntries::Table, JournalEntries::SeriesId)
+                    .to(JournalEntrySeries::Table, JournalEntrySeries::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_journal_entries_series_sequence")
+                    .table(JournalEntries::Table)
+                    .col(JournalEntries::SeriesId)
+                    .col(JournalEntries::SequenceNumber)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("fk_journal_entries_series")
+                    .table(JournalEntries::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JournalEntries::Table)
+                    .drop_column(JournalEntries::SeriesId)
+                    .drop_column(JournalEntries::SequenceNumber)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(JournalEntrySeries::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JournalEntrySeries {
+    #[sea_orm(iden = "journal_entry_series")]
+    Table,
+    Id,
+    CompanyId,
+    Code,
+    Year,
+    Prefix,
+    Padding,
+    StartNumber,
+    NextNumber,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    SeriesId,
+    SequenceNumber,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
@@ -0,0 +1,137 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(VatReturns::Table)
+                    .add_column(
+                        ColumnDef::new(VatReturns::Version)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .add_column(ColumnDef::new(VatReturns::CorrectsId).integer().null())
+                    .add_column(ColumnDef::new(VatReturns::CorrectionReason).text().null())
+                    .add_column(ColumnDef::new(VatReturns::DeklarContent).text().null())
+                    .add_column(ColumnDef::new(VatReturns::PokupkiContent).text().null())
+                    .add_column(ColumnDef::new(VatReturns::ProdagbiContent).text().null())
+                    .add_column(ColumnDef::new(VatReturns::ViesContent).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("fk_vat_returns_corrects")
+                    .from(VatReturns::Table, VatReturns::CorrectsId)
+                    .to(VatReturns::Table, VatReturns::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One return per period becomes one return per period and version
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_vat_returns_company_period")
+                    .table(VatReturns::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_vat_returns_company_period_version")
+                    .table(VatReturns::Table)
+                    .col(VatReturns::CompanyId)
+                    .col(VatReturns::PeriodYear)
+                    .col(VatReturns::PeriodMonth)
+                    .col(VatReturns::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_vat_returns_company_period_version")
+                    .table(VatReturns::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("fk_vat_returns_corrects")
+                    .table(VatReturns::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only the first version of each period fits the old unique index
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM vat_returns WHERE version > 1")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(VatReturns::Table)
+                    .drop_column(VatReturns::Version)
+                    .drop_column(VatReturns::CorrectsId)
+                    .drop_column(VatReturns::CorrectionReason)
+                    .drop_column(VatReturns::DeklarContent)
+                    .drop_column(VatReturns::PokupkiContent)
+                    .drop_column(VatReturns::ProdagbiContent)
+                    .drop_column(VatReturns::ViesContent)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_vat_returns_company_period")
+                    .table(VatReturns::Table)
+                    .col(VatReturns::CompanyId)
+                    .col(VatReturns::PeriodYear)
+                    .col(VatReturns::PeriodMonth)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum VatReturns {
+    #[sea_orm(iden = "vat_returns")]
+    Table,
+    Id,
+    CompanyId,
+    PeriodYear,
+    PeriodMonth,
+    Version,
+    CorrectsId,
+    CorrectionReason,
+    DeklarContent,
+    PokupkiContent,
+    ProdagbiContent,
+    ViesContent,
+}
@@ -0,0 +1,188 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BankTransactionFingerprints::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::BankProfileId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::BankImportId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::JournalEntryId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::Fingerprint)
+                            .string_len(32)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::BookingDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::Amount)
+                            .decimal_len(18, 2)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BankTransactionFingerprints::Reference).string())
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::CounterpartyIban)
+                            .string_len(34),
+                    )
+                    .col(
+                        ColumnDef::new(BankTransactionFingerprints::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_transaction_fingerprints_profile")
+                            .from(
+                                BankTransactionFingerprints::Table,
+                                BankTransactionFingerprints::BankProfileId,
+                            )
+                            .to(BankProfiles::Table, BankProfiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_transaction_fingerprints_import")
+                            .from(
+                                BankTransactionFingerprints::Table,
+                                BankTransactionFingerprints::BankImportId,
+                            )
+                            .to(BankImports::Table, BankImports::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Deleting the booked entry frees the transaction for a new import
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_transaction_fingerprints_entry")
+                            .from(
+                                BankTransactionFingerprints::Table,
+                                BankTransactionFingerprints::JournalEntryId,
+                            )
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_bank_transaction_fingerprints_profile_fingerprint")
+                    .table(BankTransactionFingerprints::Table)
+                    .col(BankTransactionFingerprints::BankProfileId)
+                    .col(BankTransactionFingerprints::Fingerprint)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankImports::Table)
+                    .add_column(ColumnDef::new(BankImports::StatementFrom).date().null())
+                    .add_column(ColumnDef::new(BankImports::StatementTo).date().null())
+                    .add_column(
+                        ColumnDef::new(BankImports::SkippedDuplicates)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankImports::Table)
+                    .drop_column(BankImports::StatementFrom)
+                    .drop_column(BankImports::StatementTo)
+                    .drop_column(BankImports::SkippedDuplicates)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(BankTransactionFingerprints::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BankTransactionFingerprints {
+    #[sea_orm(iden = "bank_transaction_fingerprints")]
+    Table,
+    Id,
+    BankProfileId,
+    BankImportId,
+    JournalEntryId,
+    Fingerprint,
+    BookingDate,
+    Amount,
+    Reference,
+    CounterpartyIban,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum BankImports {
+    #[sea_orm(iden = "bank_imports")]
+    Table,
+    Id,
+    StatementFrom,
+    StatementTo,
+    SkippedDuplicates,
+}
+
+#[derive(DeriveIden)]
+enum BankProfiles {
+    #[sea_orm(iden = "bank_profiles")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
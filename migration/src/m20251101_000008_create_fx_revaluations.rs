@@ -0,0 +1,157 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FxRevaluations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FxRevaluations::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FxRevaluations::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FxRevaluations::Year).integer().not_null())
+                    .col(ColumnDef::new(FxRevaluations::Month).integer().not_null())
+                    .col(
+                        ColumnDef::new(FxRevaluations::RevaluationDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FxRevaluations::GainAccountId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FxRevaluations::LossAccountId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FxRevaluations::TotalGain)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(FxRevaluations::TotalLoss)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(FxRevaluations::JournalEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(FxRevaluations::CreatedBy).integer().null())
+                    .col(
+                        ColumnDef::new(FxRevaluations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fx_revaluations_company")
+                            .from(FxRevaluations::Table, FxRevaluations::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fx_revaluations_gain_account")
+                            .from(FxRevaluations::Table, FxRevaluations::GainAccountId)
+                            .to(Accounts::Table, Accounts::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fx_revaluations_loss_account")
+                            .from(FxRevaluations::Table, FxRevaluations::LossAccountId)
+                            .to(Accounts::Table, Accounts::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fx_revaluations_journal_entry")
+                            .from(FxRevaluations::Table, FxRevaluations::JournalEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_fx_revaluations_company_period")
+                    .table(FxRevaluations::Table)
+                    .col(FxRevaluations::CompanyId)
+                    .col(FxRevaluations::Year)
+                    .col(FxRevaluations::Month)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FxRevaluations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FxRevaluations {
+    #[sea_orm(iden = "fx_revaluations")]
+    Table,
+    Id,
+    CompanyId,
+    Year,
+    Month,
+    RevaluationDate,
+    GainAccountId,
+    LossAccountId,
+    TotalGain,
+    TotalLoss,
+    JournalEntryId,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Accounts {
+    #[sea_orm(iden = "accounts")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
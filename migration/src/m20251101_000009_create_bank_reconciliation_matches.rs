@@ -0,0 +1,196 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BankReconciliationMatches::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::BankEntryId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::EntryLineId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::InvoiceEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::CounterpartId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::AccountId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::Amount)
+                            .decimal_len(18, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::CreatedBy)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(BankReconciliationMatches::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_reconciliation_matches_company")
+                            .from(
+                                BankReconciliationMatches::Table,
+                                BankReconciliationMatches::CompanyId,
+                            )
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_reconciliation_matches_bank_entry")
+                            .from(
+                                BankReconciliationMatches::Table,
+                                BankReconciliationMatches::BankEntryId,
+                            )
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_reconciliation_matches_entry_line")
+                            .from(
+                                BankReconciliationMatches::Table,
+                                BankReconciliationMatches::EntryLineId,
+                            )
+                            .to(EntryLines::Table, EntryLines::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Deleting the invoice leaves the payment as an advance
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_reconciliation_matches_invoice_entry")
+                            .from(
+                                BankReconciliationMatches::Table,
+                                BankReconciliationMatches::InvoiceEntryId,
+                            )
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bank_reconciliation_matches_counterpart")
+                            .from(
+                                BankReconciliationMatches::Table,
+                                BankReconciliationMatches::CounterpartId,
+                            )
+                            .to(Counterparts::Table, Counterparts::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_bank_reconciliation_matches_bank_entry")
+                    .table(BankReconciliationMatches::Table)
+                    .col(BankReconciliationMatches::BankEntryId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_bank_reconciliation_matches_invoice_entry")
+                    .table(BankReconciliationMatches::Table)
+                    .col(BankReconciliationMatches::InvoiceEntryId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(BankReconciliationMatches::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BankReconciliationMatches {
+    #[sea_orm(iden = "bank_reconciliation_matches")]
+    Table,
+    Id,
+    CompanyId,
+    BankEntryId,
+    EntryLineId,
+    InvoiceEntryId,
+    CounterpartId,
+    AccountId,
+    Amount,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum EntryLines {
+    #[sea_orm(iden = "entry_lines")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Counterparts {
+    #[sea_orm(iden = "counterparts")]
+    Table,
+    Id,
+}
@@ -0,0 +1,181 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankTransactionFingerprints::Table)
+                    .add_column(
+                        ColumnDef::new(BankTransactionFingerprints::AiBankAccountingSettingId)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("fk_bank_transaction_fingerprints_rule")
+                    .from(
+                        BankTransactionFingerprints::Table,
+                        BankTransactionFingerprints::AiBankAccountingSettingId,
+                    )
+                    .to(
+                        AiBankAccountingSettings::Table,
+                        AiBankAccountingSettings::Id,
+                    )
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankImports::Table)
+                    .add_column(
+                        ColumnDef::new(BankImports::AutoPosted)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AiBankAccountingSettings::Table)
+                    .add_column(
+                        ColumnDef::new(AiBankAccountingSettings::VatRateId)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("fk_ai_bank_accounting_settings_vat_rate")
+                    .from(
+                        AiBankAccountingSettings::Table,
+                        AiBankAccountingSettings::VatRateId,
+                    )
+                    .to(VatRates::Table, VatRates::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AiBankAccountingSettings::Table)
+                    .add_column(
+                        ColumnDef::new(AiBankAccountingSettings::VatOperation)
+                            .string_len(20)
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AiBankAccountingSettings::Table)
+                    .drop_column(AiBankAccountingSettings::VatOperation)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("fk_ai_bank_accounting_settings_vat_rate")
+                    .table(AiBankAccountingSettings::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AiBankAccountingSettings::Table)
+                    .drop_column(AiBankAccountingSettings::VatRateId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankImports::Table)
+                    .drop_column(BankImports::AutoPosted)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("fk_bank_transaction_fingerprints_rule")
+                    .table(BankTransactionFingerprints::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankTransactionFingerprints::Table)
+                    .drop_column(BankTransactionFingerprints::AiBankAccountingSettingId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BankTransactionFingerprints {
+    #[sea_orm(iden = "bank_transaction_fingerprints")]
+    Table,
+    AiBankAccountingSettingId,
+}
+
+#[derive(DeriveIden)]
+enum BankImports {
+    #[sea_orm(iden = "bank_imports")]
+    Table,
+    AutoPosted,
+}
+
+#[derive(DeriveIden)]
+enum AiBankAccountingSettings {
+    #[sea_orm(iden = "ai_bank_accounting_settings")]
+    Table,
+    Id,
+    VatRateId,
+    VatOperation,
+}
+
+#[derive(DeriveIden)]
+enum VatRates {
+    #[sea_orm(iden = "vat_rates")]
+    Table,
+    Id,
+}
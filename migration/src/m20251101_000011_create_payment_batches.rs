@@ -0,0 +1,343 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankProfiles::Table)
+                    .add_column(ColumnDef::new(BankProfiles::Bic).string_len(11).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentBatches::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PaymentBatches::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::BankProfileId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::Kind)
+                            .string_len(10)
+                            .not_null()
+                            .default("SUPPLIER"),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::Status)
+                            .string_len(10)
+                            .not_null()
+                            .default("DRAFT"),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::MessageId)
+                            .string_len(35)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::ExecutionDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::CurrencyCode)
+                            .string_len(3)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::TotalAmount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::ExportedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(PaymentBatches::CreatedBy).integer().null())
+                    .col(
+                        ColumnDef::new(PaymentBatches::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentBatches::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_batches_company")
+                            .from(PaymentBatches::Table, PaymentBatches::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_batches_bank_profile")
+                            .from(PaymentBatches::Table, PaymentBatches::BankProfileId)
+                            .to(BankProfiles::Table, BankProfiles::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_batches_company_status")
+                    .table(PaymentBatches::Table)
+                    .col(PaymentBatches::CompanyId)
+                    .col(PaymentBatches::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentOrders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PaymentOrders::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PaymentOrders::BatchId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PaymentOrders::LineNumber)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::CounterpartId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::InvoiceEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::CreditorName)
+                            .string_len(70)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::CreditorIban)
+                            .string_len(34)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::CreditorBic)
+                            .string_len(11)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::Amount)
+                            .decimal_len(18, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::RemittanceInfo)
+                            .string_len(140)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::EndToEndId)
+                            .string_len(35)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::BudgetPaymentType)
+                            .string_len(6)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::BudgetDocumentType)
+                            .string_len(2)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::BudgetDocumentNumber)
+                            .string_len(17)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::BudgetDocumentDate)
+                            .date()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentOrders::BudgetPeriodFrom)
+                            .date()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(PaymentOrders::BudgetPeriodTo).date().null())
+                    .col(ColumnDef::new(PaymentOrders::BankEntryId).integer().null())
+                    .col(
+                        ColumnDef::new(PaymentOrders::MatchedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_orders_batch")
+                            .from(PaymentOrders::Table, PaymentOrders::BatchId)
+                            .to(PaymentBatches::Table, PaymentBatches::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_orders_counterpart")
+                            .from(PaymentOrders::Table, PaymentOrders::CounterpartId)
+                            .to(Counterparts::Table, Counterparts::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_orders_invoice_entry")
+                            .from(PaymentOrders::Table, PaymentOrders::InvoiceEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_orders_bank_entry")
+                            .from(PaymentOrders::Table, PaymentOrders::BankEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_orders_batch")
+                    .table(PaymentOrders::Table)
+                    .col(PaymentOrders::BatchId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentOrders::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PaymentBatches::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BankProfiles::Table)
+                    .drop_column(BankProfiles::Bic)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PaymentBatches {
+    #[sea_orm(iden = "payment_batches")]
+    Table,
+    Id,
+    CompanyId,
+    BankProfileId,
+    Kind,
+    Status,
+    MessageId,
+    ExecutionDate,
+    CurrencyCode,
+    TotalAmount,
+    ExportedAt,
+    CreatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PaymentOrders {
+    #[sea_orm(iden = "payment_orders")]
+    Table,
+    Id,
+    BatchId,
+    LineNumber,
+    CounterpartId,
+    InvoiceEntryId,
+    CreditorName,
+    CreditorIban,
+    CreditorBic,
+    Amount,
+    RemittanceInfo,
+    EndToEndId,
+    BudgetPaymentType,
+    BudgetDocumentType,
+    BudgetDocumentNumber,
+    BudgetDocumentDate,
+    BudgetPeriodFrom,
+    BudgetPeriodTo,
+    BankEntryId,
+    MatchedAt,
+}
+
+#[derive(DeriveIden)]
+enum BankProfiles {
+    #[sea_orm(iden = "bank_profiles")]
+    Table,
+    Id,
+    Bic,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Counterparts {
+    #[sea_orm(iden = "counterparts")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
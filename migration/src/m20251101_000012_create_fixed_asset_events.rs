@@ -0,0 +1,246 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssets::Table)
+                    .add_column(
+                        ColumnDef::new(FixedAssets::TaxDepreciableValue)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(FixedAssets::AccountingMonthlyDepreciation)
+                            .decimal_len(15, 2)
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(FixedAssets::RevaluationReserve)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(FixedAssets::RevaluationLoss)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("UPDATE fixed_assets SET tax_depreciable_value = acquisition_cost")
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FixedAssetEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::FixedAssetId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::EventType)
+                            .string_len(15)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::EventDate)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::Portion)
+                            .decimal_len(7, 6)
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::Amount)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::AcquisitionCostBefore)
+                            .decimal_len(15, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::AcquisitionCostAfter)
+                            .decimal_len(15, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::AccountingBookValueBefore)
+                            .decimal_len(15, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::AccountingBookValueAfter)
+                            .decimal_len(15, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::TaxBookValueBefore)
+                            .decimal_len(15, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::TaxBookValueAfter)
+                            .decimal_len(15, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::GainLoss)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::RemainingLifeMonths)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::JournalEntryId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(FixedAssetEvents::Description).text().null())
+                    .col(ColumnDef::new(FixedAssetEvents::CreatedBy).integer().null())
+                    .col(
+                        ColumnDef::new(FixedAssetEvents::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fixed_asset_events_company")
+                            .from(FixedAssetEvents::Table, FixedAssetEvents::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fixed_asset_events_asset")
+                            .from(FixedAssetEvents::Table, FixedAssetEvents::FixedAssetId)
+                            .to(FixedAssets::Table, FixedAssets::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fixed_asset_events_journal_entry")
+                            .from(FixedAssetEvents::Table, FixedAssetEvents::JournalEntryId)
+                            .to(JournalEntries::Table, JournalEntries::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_fixed_asset_events_asset_date")
+                    .table(FixedAssetEvents::Table)
+                    .col(FixedAssetEvents::FixedAssetId)
+                    .col(FixedAssetEvents::EventDate)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FixedAssetEvents::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssets::Table)
+                    .drop_column(FixedAssets::TaxDepreciableValue)
+                    .drop_column(FixedAssets::AccountingMonthlyDepreciation)
+                    .drop_column(FixedAssets::RevaluationReserve)
+                    .drop_column(FixedAssets::RevaluationLoss)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FixedAssetEvents {
+    #[sea_orm(iden = "fixed_asset_events")]
+    Table,
+    Id,
+    CompanyId,
+    FixedAssetId,
+    EventType,
+    EventDate,
+    Portion,
+    Amount,
+    AcquisitionCostBefore,
+    AcquisitionCostAfter,
+    AccountingBookValueBefore,
+    AccountingBookValueAfter,
+    TaxBookValueBefore,
+    TaxBookValueAfter,
+    GainLoss,
+    RemainingLifeMonths,
+    JournalEntryId,
+    Description,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum FixedAssets {
+    #[sea_orm(iden = "fixed_assets")]
+    Table,
+    Id,
+    TaxDepreciableValue,
+    AccountingMonthlyDepreciation,
+    RevaluationReserve,
+    RevaluationLoss,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum JournalEntries {
+    #[sea_orm(iden = "journal_entries")]
+    Table,
+    Id,
+}
@@ -0,0 +1,142 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssets::Table)
+                    .add_column(
+                        ColumnDef::new(FixedAssets::DepreciationStartConvention)
+                            .string_len(15)
+                            .not_null()
+                            .default("next_month"),
+                    )
+                    .add_column(
+                        ColumnDef::new(FixedAssets::ProductionCapacity)
+                            .decimal_len(18, 4)
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FixedAssetUsages::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FixedAssetUsages::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetUsages::CompanyId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetUsages::FixedAssetId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FixedAssetUsages::Period).date().not_null())
+                    .col(
+                        ColumnDef::new(FixedAssetUsages::Units)
+                            .decimal_len(18, 4)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetUsages::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(FixedAssetUsages::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fixed_asset_usages_company")
+                            .from(FixedAssetUsages::Table, FixedAssetUsages::CompanyId)
+                            .to(Companies::Table, Companies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_fixed_asset_usages_asset")
+                            .from(FixedAssetUsages::Table, FixedAssetUsages::FixedAssetId)
+                            .to(FixedAssets::Table, FixedAssets::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_fixed_asset_usages_asset_period")
+                    .table(FixedAssetUsages::Table)
+                    .col(FixedAssetUsages::FixedAssetId)
+                    .col(FixedAssetUsages::Period)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FixedAssetUsages::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssets::Table)
+                    .drop_column(FixedAssets::DepreciationStartConvention)
+                    .drop_column(FixedAssets::ProductionCapacity)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FixedAssetUsages {
+    #[sea_orm(iden = "fixed_asset_usages")]
+    Table,
+    Id,
+    CompanyId,
+    FixedAssetId,
+    Period,
+    Units,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum FixedAssets {
+    #[sea_orm(iden = "fixed_assets")]
+    Table,
+    Id,
+    DepreciationStartConvention,
+    ProductionCapacity,
+}
+
+#[derive(DeriveIden)]
+enum Companies {
+    #[sea_orm(iden = "companies")]
+    Table,
+    Id,
+}
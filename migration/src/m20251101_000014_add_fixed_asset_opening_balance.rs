@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssets::Table)
+                    .add_column(
+                        ColumnDef::new(FixedAssets::OpeningBalanceDate)
+                            .date()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssets::Table)
+                    .drop_column(FixedAssets::OpeningBalanceDate)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FixedAssets {
+    #[sea_orm(iden = "fixed_assets")]
+    Table,
+    OpeningBalanceDate,
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssetEvents::Table)
+                    .add_column(
+                        ColumnDef::new(FixedAssetEvents::TaxDepreciableValueBefore)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(FixedAssetEvents::TaxDepreciableValueAfter)
+                            .decimal_len(15, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Revaluations and impairments leave the tax value untouched, the other
+        // events move it together with the acquisition cost
+        let backfill_sql = "UPDATE fixed_asset_events SET \
+            tax_depreciable_value_before = acquisition_cost_before, \
+            tax_depreciable_value_after = CASE \
+                WHEN event_type IN ('REVALUATION', 'IMPAIRMENT') THEN acquisition_cost_before \
+                ELSE acquisition_cost_after END";
+        manager
+            .get_connection()
+            .execute_unprepared(backfill_sql)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FixedAssetEvents::Table)
+                    .drop_column(FixedAssetEvents::TaxDepreciableValueBefore)
+                    .drop_column(FixedAssetEvents::TaxDepreciableValueAfter)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FixedAssetEvents {
+    #[sea_orm(iden = "fixed_asset_events")]
+    Table,
+    TaxDepreciableValueBefore,
+    TaxDepreciableValueAfter,
+}